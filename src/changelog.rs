@@ -0,0 +1,104 @@
+use semver::Version;
+
+/// The changelog, bundled so that the changes in a new version can be
+/// shown after upgrading.
+const CHANGELOG: &str = include_str!("../CHANGELOG.md");
+
+/// Returns the entries of the changelog for versions newer than
+/// `previous`, up to and including `current`.
+pub fn changes_since(previous: &Version, current: &Version) -> Vec<String> {
+    return changes_in(CHANGELOG, previous, current);
+}
+
+/// Picks out the sections of a changelog for versions newer than
+/// `previous`, up to and including `current`. Sections start with a
+/// heading like "## v2.0.2 (2024-11-09)". Each heading, list item, or
+/// paragraph becomes one entry, with wrapped lines joined back up.
+fn changes_in(text: &str, previous: &Version, current: &Version) -> Vec<String> {
+    let mut entries: Vec<String> = Vec::new();
+    let mut included = false;
+    let mut continues = false;
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if let Some(heading) = trimmed.strip_prefix("## ") {
+            included = heading
+                .split_whitespace()
+                .next()
+                .map(|v| v.strip_prefix('v').unwrap_or(v))
+                .and_then(|v| Version::parse(v).ok())
+                .is_some_and(|v| v > *previous && v <= *current);
+            if included {
+                entries.push(heading.to_string());
+            }
+            continues = false;
+            continue;
+        }
+        if !included {
+            continue;
+        }
+
+        if trimmed.is_empty() {
+            continues = false;
+        } else if trimmed.starts_with('#')
+            || trimmed.starts_with("- ")
+            || trimmed.starts_with("* ")
+            || !continues
+        {
+            entries.push(trimmed.trim_start_matches('#').trim().to_string());
+            continues = !trimmed.starts_with('#');
+        } else if let Some(last) = entries.last_mut() {
+            last.push(' ');
+            last.push_str(trimmed);
+        }
+    }
+    return entries;
+}
+
+
+// TESTS -----------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sections_between_versions() {
+        let text = "# Changelog\n\
+            ## v2.1.0 (2025-01-01)\n\
+            ### New Features\n\
+            - One feature\n\
+            - A feature that is described\n  over two lines\n\
+            \n\
+            ## v2.0.2 (2024-11-09)\n\
+            Bug fix release\n\
+            \n\
+            ## v2.0.1 (2022-03-03)\n\
+            - Old fix\n";
+        let entries = changes_in(
+            text,
+            &Version::parse("2.0.1").unwrap(),
+            &Version::parse("2.1.0").unwrap(),
+        );
+        assert_eq!(entries, vec![
+            "v2.1.0 (2025-01-01)",
+            "New Features",
+            "- One feature",
+            "- A feature that is described over two lines",
+            "v2.0.2 (2024-11-09)",
+            "Bug fix release",
+        ]);
+    }
+
+    #[test]
+    fn bundled_changelog() {
+        let entries = changes_since(
+            &Version::parse("2.0.1").unwrap(),
+            &Version::parse("2.0.2").unwrap(),
+        );
+        assert_eq!(entries[0], "v2.0.2 (2024-11-09)");
+        assert!(changes_since(
+            &Version::parse("2.0.2").unwrap(),
+            &Version::parse("2.0.2").unwrap()
+        )
+        .is_empty());
+    }
+}
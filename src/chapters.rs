@@ -0,0 +1,329 @@
+use std::cmp::min;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+
+use crate::http;
+use crate::threadpool::Threadpool;
+use crate::types::{Chapter, Message};
+
+/// Largest chapters file that will be downloaded, in bytes.
+const MAX_CHAPTERS_SIZE: u64 = 1024 * 1024;
+
+/// Largest ID3 tag that will be read from an episode file, in bytes.
+/// Tags can hold several large images, so this is generous.
+const MAX_TAG_SIZE: u64 = 32 * 1024 * 1024;
+
+/// Enum used for communicating back to the main controller once the
+/// chapters for an episode have been fetched. Holds the podcast and
+/// episode ids.
+#[derive(Debug)]
+pub enum ChapterMsg {
+    Found(i64, i64, Vec<Chapter>),
+    Error(i64, i64),
+}
+
+/// Chapters file format used by the `podcast:chapters` tag; see
+/// https://github.com/Podcastindex-org/podcast-namespace/blob/main/chapters/jsonChapters.md
+#[derive(Debug, Deserialize)]
+struct JsonChapters {
+    chapters: Vec<JsonChapter>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonChapter {
+    start_time: f64,
+    title: Option<String>,
+    toc: Option<bool>,
+}
+
+/// Gets the chapters for an episode on a separate thread, and lets the
+/// main controller know when it is finished. Chapters are taken from
+/// the chapters file listed in the feed if there is one, and otherwise
+/// from the ID3 tag of the downloaded file.
+#[allow(clippy::too_many_arguments)]
+pub fn fetch_chapters(
+    pod_id: i64,
+    ep_id: i64,
+    url: Option<String>,
+    path: Option<PathBuf>,
+    max_retries: usize,
+    threadpool: &Threadpool,
+    tx_to_main: Sender<Message>,
+) {
+    threadpool.execute(move || {
+        let mut result = Err(anyhow!("No chapters"));
+        if let Some(url) = url {
+            result = download_chapters(&url, max_retries);
+        }
+        if result.is_err() {
+            if let Some(path) = path {
+                result = read_id3_chapters(&path);
+            }
+        }
+        let msg = match result {
+            Ok(chapters) => ChapterMsg::Found(pod_id, ep_id, chapters),
+            Err(_) => ChapterMsg::Error(pod_id, ep_id),
+        };
+        tx_to_main
+            .send(Message::Chapters(msg))
+            .expect("Thread messaging error");
+    });
+}
+
+/// Downloads and parses a JSON chapters file.
+fn download_chapters(url: &str, max_retries: usize) -> Result<Vec<Chapter>> {
+    let response = http::get(url, max_retries)
+        .map_err(|err| anyhow!("Could not fetch chapters: {err:#}"))?;
+
+    let mut text = String::new();
+    response
+        .take(MAX_CHAPTERS_SIZE)
+        .read_to_string(&mut text)?;
+    return parse_json_chapters(&text);
+}
+
+/// Parses the contents of a JSON chapters file. Chapters marked with
+/// `"toc": false` are meant to be hidden from the list of chapters, so
+/// they are left out.
+fn parse_json_chapters(text: &str) -> Result<Vec<Chapter>> {
+    let json: JsonChapters =
+        serde_json::from_str(text).with_context(|| "Could not parse chapters file")?;
+    let chapters = json
+        .chapters
+        .into_iter()
+        .filter(|ch| ch.toc != Some(false))
+        .map(|ch| (ch.start_time, ch.title))
+        .collect();
+    return Ok(number_chapters(chapters));
+}
+
+/// Reads the chapters (CHAP frames) from the ID3v2 tag at the start of
+/// an audio file.
+fn read_id3_chapters(path: &Path) -> Result<Vec<Chapter>> {
+    let mut file = File::open(path)?;
+    let mut header = [0u8; 10];
+    file.read_exact(&mut header)?;
+    if &header[0..3] != b"ID3" {
+        return Err(anyhow!("File has no ID3 tag"));
+    }
+    let size = syncsafe_int(&header[6..10]) as u64;
+    let mut tag = Vec::new();
+    file.take(min(size, MAX_TAG_SIZE)).read_to_end(&mut tag)?;
+    return parse_id3_chapters(header[3], header[5], &tag);
+}
+
+/// Parses the chapters from the body of an ID3v2 tag (i.e., everything
+/// after the 10-byte header). `version` and `flags` come from the tag
+/// header. Only ID3v2.3 and ID3v2.4 support chapters.
+fn parse_id3_chapters(version: u8, flags: u8, tag: &[u8]) -> Result<Vec<Chapter>> {
+    if version != 3 && version != 4 {
+        return Err(anyhow!("Unsupported ID3 version"));
+    }
+
+    // skip the extended header, if there is one; its size is stored
+    // differently in each version
+    let mut start = 0;
+    if flags & 0x40 != 0 && tag.len() >= 4 {
+        start = match version {
+            3 => 4 + u32::from_be_bytes([tag[0], tag[1], tag[2], tag[3]]) as usize,
+            _ => syncsafe_int(&tag[0..4]) as usize,
+        };
+    }
+    let frames = id3_frames(version, tag.get(start..).unwrap_or_default());
+
+    let mut chapters = Vec::new();
+    for (id, body) in frames.into_iter() {
+        if id != b"CHAP" {
+            continue;
+        }
+        // the element ID is a null-terminated string, followed by the
+        // start time, end time, start offset, and end offset, and then
+        // any sub-frames (the title is in a TIT2 sub-frame)
+        let id_end = match body.iter().position(|b| *b == 0) {
+            Some(pos) => pos + 1,
+            None => continue,
+        };
+        let times = match body.get(id_end..id_end + 16) {
+            Some(times) => times,
+            None => continue,
+        };
+        let start_ms = u32::from_be_bytes([times[0], times[1], times[2], times[3]]);
+        let title = id3_frames(version, &body[id_end + 16..])
+            .into_iter()
+            .find(|(id, _)| *id == b"TIT2")
+            .map(|(_, text)| decode_id3_text(text));
+        chapters.push((start_ms as f64 / 1000.0, title));
+    }
+    if chapters.is_empty() {
+        return Err(anyhow!("ID3 tag has no chapters"));
+    }
+    chapters.sort_by(|a, b| a.0.total_cmp(&b.0));
+    return Ok(number_chapters(chapters));
+}
+
+/// Splits a list of ID3v2 frames into (frame ID, frame body) pairs.
+fn id3_frames(version: u8, data: &[u8]) -> Vec<(&[u8], &[u8])> {
+    let mut frames = Vec::new();
+    let mut pos = 0;
+    while pos + 10 <= data.len() {
+        let id = &data[pos..pos + 4];
+        // the rest of the tag is padding
+        if id[0] == 0 {
+            break;
+        }
+        let size = match version {
+            3 => u32::from_be_bytes([data[pos + 4], data[pos + 5], data[pos + 6], data[pos + 7]]),
+            _ => syncsafe_int(&data[pos + 4..pos + 8]),
+        } as usize;
+        let body = match data.get(pos + 10..pos + 10 + size) {
+            Some(body) => body,
+            None => break,
+        };
+        frames.push((id, body));
+        pos += 10 + size;
+    }
+    return frames;
+}
+
+/// Decodes the body of an ID3v2 text frame, where the first byte gives
+/// the text encoding.
+fn decode_id3_text(data: &[u8]) -> String {
+    let (encoding, text) = match data.split_first() {
+        Some((encoding, text)) => (*encoding, text),
+        None => return String::new(),
+    };
+    let decoded = match encoding {
+        // ISO-8859-1, where each byte is the same as the Unicode code
+        // point
+        0 => text.iter().map(|b| *b as char).collect(),
+        // UTF-16 with a byte order mark, or UTF-16BE without one
+        1 | 2 => {
+            let mut little_endian = false;
+            let mut text = text;
+            if encoding == 1 && text.len() >= 2 {
+                little_endian = text[0] == 0xff && text[1] == 0xfe;
+                text = &text[2..];
+            }
+            let units: Vec<u16> = text
+                .chunks_exact(2)
+                .map(|pair| match little_endian {
+                    true => u16::from_le_bytes([pair[0], pair[1]]),
+                    false => u16::from_be_bytes([pair[0], pair[1]]),
+                })
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => String::from_utf8_lossy(text).to_string(),
+    };
+    return decoded.trim_end_matches('\0').to_string();
+}
+
+/// Reads a "syncsafe" integer, as used in ID3v2 tags, where only the
+/// lower seven bits of each byte are used.
+fn syncsafe_int(bytes: &[u8]) -> u32 {
+    return bytes
+        .iter()
+        .take(4)
+        .fold(0, |acc, b| (acc << 7) | (*b & 0x7f) as u32);
+}
+
+/// Converts a list of start times and (optional) titles to Chapters,
+/// numbering any chapters that have no title.
+fn number_chapters(chapters: Vec<(f64, Option<String>)>) -> Vec<Chapter> {
+    return chapters
+        .into_iter()
+        .enumerate()
+        .map(|(i, (start, title))| Chapter {
+            id: i as i64,
+            start: start,
+            title: match title {
+                Some(t) if !t.trim().is_empty() => t.trim().to_string(),
+                _ => format!("Chapter {}", i + 1),
+            },
+        })
+        .collect();
+}
+
+
+// TESTS -----------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds an ID3v2.3 frame with the given ID and body.
+    fn frame(id: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut frame = id.to_vec();
+        frame.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        frame.extend_from_slice(&[0, 0]);
+        frame.extend_from_slice(body);
+        return frame;
+    }
+
+    /// Builds an ID3v2.3 CHAP frame with a TIT2 sub-frame.
+    fn chap_frame(element_id: &str, start_ms: u32, title: &[u8]) -> Vec<u8> {
+        let mut body = element_id.as_bytes().to_vec();
+        body.push(0);
+        body.extend_from_slice(&start_ms.to_be_bytes());
+        body.extend_from_slice(&(start_ms + 1000).to_be_bytes());
+        body.extend_from_slice(&[0xff; 8]);
+        body.extend(frame(b"TIT2", title));
+        return frame(b"CHAP", &body);
+    }
+
+    #[test]
+    fn json_chapters() {
+        let text = r#"{
+            "version": "1.2.0",
+            "chapters": [
+                { "startTime": 0, "title": "Intro" },
+                { "startTime": 30.5, "title": "Ad break", "toc": false },
+                { "startTime": 62.25 }
+            ]
+        }"#;
+        let chapters = parse_json_chapters(text).unwrap();
+        assert_eq!(chapters.len(), 2);
+        assert_eq!(chapters[0].title, "Intro");
+        assert_eq!(chapters[1].start, 62.25);
+        assert_eq!(chapters[1].title, "Chapter 2");
+    }
+
+    #[test]
+    fn invalid_json_chapters() {
+        assert!(parse_json_chapters("<html></html>").is_err());
+    }
+
+    #[test]
+    fn id3_chapters() {
+        let mut tag = frame(b"TIT2", b"\x03Episode title");
+        // UTF-16 with a little-endian byte order mark
+        tag.extend(chap_frame("ch1", 90_000, b"\x01\xff\xfeS\0e\0c\0o\0n\0d\0"));
+        tag.extend(chap_frame("ch0", 0, b"\x00Caf\xe9"));
+        tag.extend_from_slice(&[0; 20]);
+
+        let chapters = parse_id3_chapters(3, 0, &tag).unwrap();
+        assert_eq!(chapters.len(), 2);
+        assert_eq!(chapters[0].start, 0.0);
+        assert_eq!(chapters[0].title, "Café");
+        assert_eq!(chapters[1].start, 90.0);
+        assert_eq!(chapters[1].title, "Second");
+    }
+
+    #[test]
+    fn id3_no_chapters() {
+        let tag = frame(b"TIT2", b"\x03Episode title");
+        assert!(parse_id3_chapters(3, 0, &tag).is_err());
+        assert!(parse_id3_chapters(2, 0, &tag).is_err());
+    }
+
+    #[test]
+    fn syncsafe() {
+        assert_eq!(syncsafe_int(&[0x00, 0x00, 0x02, 0x01]), 257);
+        assert_eq!(syncsafe_int(&[0x7f, 0x7f, 0x7f, 0x7f]), 0x0fff_ffff);
+    }
+}
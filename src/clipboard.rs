@@ -0,0 +1,100 @@
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::command;
+use crate::encoding::base64_encode;
+
+/// Copies `text` to the system clipboard. If `command` is set (e.g.,
+/// "wl-copy" or "xclip -selection clipboard"), the text is given to it
+/// on stdin; otherwise, the terminal is asked to set the clipboard with
+/// an OSC 52 escape sequence, which also works over SSH.
+pub fn copy(text: &str, command: Option<&str>) -> Result<()> {
+    return match command {
+        Some(command) => copy_with_command(text, command),
+        None => {
+            let mut stdout = io::stdout();
+            stdout.write_all(osc52_sequence(text).as_bytes())?;
+            stdout.flush()?;
+            Ok(())
+        }
+    };
+}
+
+/// Runs the clipboard command, writing `text` to its stdin.
+fn copy_with_command(text: &str, command: &str) -> Result<()> {
+    let mut parts = command.split_whitespace();
+    let base_cmd = parts.next().ok_or_else(|| anyhow!("Invalid command."))?;
+    let mut child = Command::new(shellexpand::tilde(base_cmd).as_ref())
+        .args(parts)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Could not run {base_cmd}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(anyhow!("{base_cmd} exited with an error"));
+    }
+    return Ok(());
+}
+
+/// Opens a link with `command` (e.g., "xdg-open" or "open"), which is
+/// given the link in place of "%s", or as its last argument. The command is left to run in
+/// the background, in case it is a browser that stays open. Only web
+/// links are opened, as links come from feeds, and an opener would
+/// also run local files or other programs' links.
+pub fn open(url: &str, command: &str) -> Result<()> {
+    let parsed = reqwest::Url::parse(url).map_err(|_| anyhow!("Not a valid link: {url}"))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(anyhow!("Only web links can be opened, not {}: links.", parsed.scheme()));
+    }
+    let (program, args) = command::parse(command, url, true)?;
+    let mut child = Command::new(&program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Could not run {program}"))?;
+    std::thread::spawn(move || child.wait());
+    return Ok(());
+}
+
+/// Returns the OSC 52 escape sequence that puts `text` on the clipboard.
+fn osc52_sequence(text: &str) -> String {
+    return format!("\x1b]52;c;{}\x07", base64_encode(text.as_bytes()));
+}
+
+
+// TESTS -----------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn osc52() {
+        assert_eq!(osc52_sequence("Man"), "\x1b]52;c;TWFu\x07");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn clipboard_command() {
+        assert!(copy_with_command("text", "cat").is_ok());
+        assert!(copy_with_command("text", "false").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn open_web_links_only() {
+        assert!(open("https://example.com/show?a=1", "true").is_ok());
+        assert!(open("HTTP://example.com/", "true").is_ok());
+        for link in ["file:///etc/passwd", "javascript:alert(1)", "--help", "/usr/bin/env"] {
+            assert!(open(link, "true").is_err(), "{link} was opened");
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use anyhow::{anyhow, Result};
+
+/// Splits a command set in config.toml (e.g., "mpv --no-video %s") into
+/// the program to run, with "~" expanded, and its arguments. Every "%s"
+/// in the arguments is replaced by `target`, including inside a longer
+/// argument (e.g., "--file=%s"). If there is no "%s" and `append` is
+/// set, `target` is added as the last argument.
+///
+/// Commands are split on white space rather than run through a shell,
+/// so `target` is always passed as it is, whatever it contains.
+pub fn parse(command: &str, target: &str, append: bool) -> Result<(String, Vec<String>)> {
+    let mut parts = command.split_whitespace();
+    let program = parts.next().ok_or_else(|| anyhow!("Invalid command."))?;
+    let mut found = false;
+    let mut args: Vec<String> = parts
+        .map(|part| {
+            if part.contains("%s") {
+                found = true;
+                part.replace("%s", target)
+            } else {
+                part.to_string()
+            }
+        })
+        .collect();
+    if !found && append {
+        args.push(target.to_string());
+    }
+    return Ok((shellexpand::tilde(program).to_string(), args));
+}
+
+
+// TESTS -----------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn target_inside_arguments() {
+        let (program, args) = parse("mpv --file=%s  %s", "a b.mp3", true).unwrap();
+        assert_eq!(program, "mpv");
+        assert_eq!(args, vec!["--file=a b.mp3", "a b.mp3"]);
+    }
+
+    #[test]
+    fn target_appended() {
+        let (_, args) = parse("vlc --play-and-exit", "https://example.com/%s", true).unwrap();
+        assert_eq!(args, vec!["--play-and-exit", "https://example.com/%s"]);
+        let (_, args) = parse("notify-send done", "episode.mp3", false).unwrap();
+        assert_eq!(args, vec!["done"]);
+    }
+
+    #[test]
+    fn empty_command() {
+        assert!(parse("  ", "episode.mp3", true).is_err());
+    }
+}
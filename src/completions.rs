@@ -0,0 +1,130 @@
+use std::io::Write;
+
+use anyhow::{anyhow, Result};
+use clap::Command;
+use clap_complete::Shell;
+
+/// Shells that completions can be printed for.
+pub const SHELLS: [&str; 3] = ["bash", "zsh", "fish"];
+
+/// Subcommands that take podcast titles as arguments.
+const TAKE_PODCASTS: [&str; 3] = ["sync", "check", "list"];
+
+/// Bash function that completes podcast titles for the subcommands that
+/// take them, and leaves everything else to the generated completions.
+const BASH_PODCASTS: &str = r#"
+_shellcaster_podcasts() {
+    local cur="${COMP_WORDS[COMP_CWORD]}"
+    local word
+    for word in "${COMP_WORDS[@]:1:COMP_CWORD-1}"; do
+        case "${word}" in
+            SUBCOMMANDS)
+                if [[ "${cur}" != -* ]]; then
+                    local IFS=$'\n'
+                    COMPREPLY=($(compgen -W "$(shellcaster completions --podcasts 2>/dev/null)" -- "${cur}"))
+                    if [[ ${#COMPREPLY[@]} -gt 0 ]]; then
+                        COMPREPLY=($(printf '%q\n' "${COMPREPLY[@]}"))
+                    fi
+                    return 0
+                fi
+                ;;
+        esac
+    done
+    _shellcaster "$@"
+}
+
+complete -F _shellcaster_podcasts -o bashdefault -o default shellcaster
+"#;
+
+/// Zsh function that completes podcast titles.
+const ZSH_PODCASTS: &str = r#"(( $+functions[_shellcaster_podcasts] )) ||
+_shellcaster_podcasts() {
+    local -a podcasts
+    podcasts=("${(@f)$(shellcaster completions --podcasts 2>/dev/null)}")
+    compadd -a podcasts
+}
+
+"#;
+
+/// Fish completion of podcast titles.
+const FISH_PODCASTS: &str = r#"complete -c shellcaster -n "__fish_seen_subcommand_from SUBCOMMANDS" -f -a "(shellcaster completions --podcasts 2>/dev/null)"
+"#;
+
+/// Writes the completion script for the given shell. The scripts that
+/// clap generates complete subcommands and flags; podcast titles are
+/// completed by asking shellcaster for them (see `--podcasts`).
+pub fn generate(cmd: &mut Command, shell: &str, out: &mut dyn Write) -> Result<()> {
+    let kind = match shell {
+        "bash" => Shell::Bash,
+        "zsh" => Shell::Zsh,
+        "fish" => Shell::Fish,
+        _ => return Err(anyhow!("Completions are not available for {shell}.")),
+    };
+    let mut generated = Vec::new();
+    clap_complete::generate(kind, cmd, "shellcaster", &mut generated);
+    let mut script = String::from_utf8(generated)?;
+
+    match kind {
+        Shell::Bash => {
+            // the generated function is registered by the wrapper instead
+            let register = "complete -F _shellcaster -o bashdefault -o default shellcaster\n";
+            script = script.replace(register, "");
+            script.push_str(&BASH_PODCASTS.replace("SUBCOMMANDS", &TAKE_PODCASTS.join("|")));
+        }
+        Shell::Zsh => {
+            // podcast arguments are described as, e.g.,
+            // '*::podcast -- Only syncs...:' with nothing to complete
+            // them with after the last colon
+            let mut lines: Vec<String> = script.lines().map(String::from).collect();
+            for line in lines.iter_mut() {
+                if line.starts_with("'*::podcast -- ") && line.ends_with(":' \\") {
+                    let end = line.len() - "' \\".len();
+                    line.insert_str(end, "_shellcaster_podcasts");
+                }
+            }
+            script = lines.join("\n") + "\n";
+            let last = "_shellcaster \"$@\"";
+            script = script.replace(last, &format!("{ZSH_PODCASTS}{last}"));
+        }
+        _ => script.push_str(&FISH_PODCASTS.replace("SUBCOMMANDS", &TAKE_PODCASTS.join(" "))),
+    }
+    out.write_all(script.as_bytes())?;
+    return Ok(());
+}
+
+
+// TESTS -----------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn script(shell: &str) -> String {
+        let mut out = Vec::new();
+        generate(&mut crate::command_line(), shell, &mut out).unwrap();
+        return String::from_utf8(out).unwrap();
+    }
+
+    #[test]
+    fn bash_completes_podcasts() {
+        let bash = script("bash");
+        assert!(bash.contains("sync|check|list)"));
+        assert!(bash.contains("shellcaster completions --podcasts"));
+        assert_eq!(bash.matches("complete -F").count(), 1);
+        assert!(bash.contains("complete -F _shellcaster_podcasts"));
+    }
+
+    #[test]
+    fn zsh_completes_podcasts() {
+        let zsh = script("zsh");
+        assert_eq!(zsh.matches(":_shellcaster_podcasts' \\").count(), 3);
+        assert!(zsh.trim_end().ends_with("_shellcaster \"$@\""));
+        assert!(zsh.contains("compadd -a podcasts"));
+    }
+
+    #[test]
+    fn fish_completes_podcasts() {
+        let fish = script("fish");
+        assert!(fish.contains("__fish_seen_subcommand_from sync check list\" -f -a"));
+        assert!(fish.contains("-a \"sync\""));
+    }
+}
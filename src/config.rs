@@ -1,16 +1,34 @@
 use anyhow::{anyhow, Context, Result};
+use log::LevelFilter;
 use serde::Deserialize;
+use std::collections::BTreeMap;
 use std::fs::File;
-use std::io::Read;
+use std::io::{ErrorKind, Read};
+use std::net::IpAddr;
 use std::path::{Path, PathBuf};
 
+use crate::cover_art::CoverArt;
+use crate::http::{BindTo, IpFamily, NetworkConfig};
 use crate::keymap::Keybindings;
+use crate::logging;
+use crate::sanitizer::{SanitizeProfile, Sanitizer};
+use crate::scrobble;
+use crate::secrets;
+use crate::smart_filters::SmartFilter;
+use crate::title_rules::TitleRules;
+use crate::webhooks::Webhook;
+use crate::storage::WebDavConfig;
+use crate::types::{to_tag, SortMode, SortTarget, Sorts, TagGroup};
 use crate::ui::colors::AppColors;
 
 // Specifies how long, in milliseconds, to display messages at the
 // bottom of the screen in the UI.
 pub const MESSAGE_TIME: u64 = 5000;
 
+// Maximum width, in columns, of the cover art shown in the details
+// panel
+pub const COVER_ART_MAX_COLS: u16 = 30;
+
 // How many columns we need, minimum, before we display the
 // (unplayed/total) after the podcast title
 pub const PODCAST_UNPLAYED_TOTALS_LENGTH: usize = 25;
@@ -27,10 +45,25 @@ pub const EPISODE_PUBDATE_LENGTH: usize = 60;
 // display the details panel
 pub const DETAILS_PANEL_LENGTH: u16 = 135;
 
+// How often, in milliseconds, to check the watch folder for new files
+// to import.
+pub const WATCH_DIR_INTERVAL: u64 = 2000;
+
+// Minimum time, in seconds, between checks for a newer version of
+// shellcaster.
+pub const UPDATE_CHECK_INTERVAL: i64 = 86_400;
+
 // How many lines will be scrolled by the big scroll,
 // in relation to the rows eg: 4 = 1/4 of the screen
 pub const BIG_SCROLL_AMOUNT: u16 = 4;
 
+// Number of notifications kept for the notification history window.
+pub const NOTIF_HISTORY_LENGTH: usize = 100;
+
+// Template used for the now-playing file if `now_playing_format` is
+// not set.
+pub const NOW_PLAYING_FORMAT: &str = "{podcast} - {episode} ({elapsed})";
+
 
 /// Identifies the user's selection for what to do with new episodes
 /// when syncing.
@@ -42,34 +75,215 @@ pub enum DownloadNewEpisodes {
     Never,
 }
 
+/// Identifies which episodes of a newly added podcast start off
+/// unplayed; the rest are marked as played.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NewPodcastEpisodes {
+    Ask,
+    LatestUnplayed,
+    AllPlayed,
+    AllUnplayed,
+}
+
+/// Identifies what the "select" key (Enter, by default) does in one
+/// of the menus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnterAction {
+    Play,
+    Open,
+    MarkPlayed,
+}
+
+/// Holds the markers shown before the titles of episodes in the episode
+/// menu, according to their state. Empty markers are not shown; played
+/// episodes are dimmed instead, so they have no marker by default. The
+/// headers of groups in the podcast menu start with `expanded` or
+/// `collapsed`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Glyphs {
+    pub new: String,
+    pub queued: String,
+    pub downloaded: String,
+    pub updated: String,
+    pub played: String,
+    pub expanded: String,
+    pub collapsed: String,
+}
+
+impl Glyphs {
+    /// Returns Unicode symbols, which look tidier but are not in every
+    /// font.
+    pub fn unicode() -> Self {
+        return Self {
+            new: "●".to_string(),
+            queued: "≡".to_string(),
+            downloaded: "↓".to_string(),
+            updated: "↻".to_string(),
+            played: String::new(),
+            expanded: "▾".to_string(),
+            collapsed: "▸".to_string(),
+        };
+    }
+
+    /// Returns plain ASCII markers, which every terminal can show. These
+    /// are used by default.
+    pub fn ascii() -> Self {
+        return Self {
+            new: "[N]".to_string(),
+            queued: "[Q]".to_string(),
+            downloaded: "[D]".to_string(),
+            updated: "[U]".to_string(),
+            played: String::new(),
+            expanded: "-".to_string(),
+            collapsed: "+".to_string(),
+        };
+    }
+}
+
 /// Holds information about user configuration of program.
 #[derive(Debug, Clone)]
 pub struct Config {
     pub download_path: PathBuf,
+    pub webdav: Option<WebDavConfig>,
+    pub trash_days: Option<u64>,
+    pub watch_dir: Option<PathBuf>,
     pub play_command: String,
+    pub play_in_terminal: bool,
+    pub feed_url_hook: Option<String>,
+    pub ca_file: Option<PathBuf>,
+    pub network: NetworkConfig,
+    pub post_download_command: Option<String>,
+    pub post_play_command: Option<String>,
+    pub status_file: Option<PathBuf>,
+    pub now_playing_file: Option<PathBuf>,
+    pub now_playing_format: String,
+    pub mpv_socket: Option<PathBuf>,
+    pub continuous_playback: bool,
+    pub queue_gap: u64,
+    pub remove_from_queue: bool,
+    pub listenbrainz_token: Option<String>,
+    pub listenbrainz_url: String,
+    pub clipboard_command: Option<String>,
+    pub open_command: Option<String>,
+    pub log_file: PathBuf,
+    pub log_level: LevelFilter,
+    pub remote_socket: Option<PathBuf>,
+    pub remote_host: Option<String>,
     pub download_new_episodes: DownloadNewEpisodes,
+    pub new_episode_max_age: Option<i64>,
+    pub new_podcast_episodes: NewPodcastEpisodes,
+    pub sorts: Sorts,
+    pub smart_filters: Vec<SmartFilter>,
+    pub groups: Vec<TagGroup>,
+    pub title_rules: TitleRules,
+    pub webhooks: Vec<Webhook>,
+    pub enter_podcast: EnterAction,
+    pub enter_episode: EnterAction,
     pub simultaneous_downloads: usize,
+    pub simultaneous_syncs: usize,
     pub max_retries: usize,
+    pub preferred_enclosure: Option<String>,
+    pub dead_feed_syncs: Option<i64>,
+    pub db_maintenance_interval: Option<i64>,
+    pub check_for_updates: bool,
+    pub sync_digest: bool,
+    pub sanitizer: Sanitizer,
+    pub cover_art: CoverArt,
+    pub image_cache: Option<PathBuf>,
+    pub wrap_details: bool,
+    pub details_wrap_width: Option<usize>,
+    pub tree_layout: bool,
     pub keybindings: Keybindings,
     pub colors: AppColors,
+    pub themes_dir: PathBuf,
+    pub theme: Option<String>,
+    pub glyphs: Glyphs,
+    pub color_overrides: Option<AppColorsFromToml>,
 }
 
 /// A temporary struct used to deserialize data from the TOML configuration
 /// file. Will be converted into Config struct.
 #[derive(Debug, Deserialize)]
-struct ConfigFromToml {
+pub struct ConfigFromToml {
     download_path: Option<String>,
+    webdav_url: Option<String>,
+    webdav_user: Option<String>,
+    webdav_password: Option<String>,
+    trash_days: Option<u64>,
+    watch_dir: Option<String>,
     play_command: Option<String>,
+    play_in_terminal: Option<bool>,
+    feed_url_hook: Option<String>,
+    ca_file: Option<String>,
+    prefer_ip_version: Option<toml::Value>,
+    bind_address: Option<String>,
+    post_download_command: Option<String>,
+    post_play_command: Option<String>,
+    status_file: Option<String>,
+    now_playing_file: Option<String>,
+    now_playing_format: Option<String>,
+    mpv_socket: Option<String>,
+    continuous_playback: Option<bool>,
+    queue_gap: Option<u64>,
+    remove_from_queue: Option<bool>,
+    listenbrainz_token: Option<String>,
+    listenbrainz_url: Option<String>,
+    clipboard_command: Option<String>,
+    open_command: Option<String>,
+    log_file: Option<String>,
+    log_level: Option<String>,
+    remote_socket: Option<String>,
+    remote_host: Option<String>,
     download_new_episodes: Option<String>,
+    new_episode_max_age: Option<i64>,
+    new_podcast_episodes: Option<String>,
+    sort_podcasts: Option<String>,
+    sort_episodes: Option<String>,
+    enter_podcast: Option<String>,
+    enter_episode: Option<String>,
     simultaneous_downloads: Option<usize>,
+    simultaneous_syncs: Option<usize>,
     max_retries: Option<usize>,
+    preferred_enclosure: Option<String>,
+    dead_feed_syncs: Option<i64>,
+    db_maintenance_interval: Option<i64>,
+    check_for_updates: Option<bool>,
+    sync_digest: Option<bool>,
+    filename_profile: Option<String>,
+    max_filename_length: Option<usize>,
+    cover_art: Option<String>,
+    image_cache: Option<String>,
+    wrap_details: Option<bool>,
+    details_wrap_width: Option<usize>,
+    layout: Option<String>,
     keybindings: Option<KeybindingsFromToml>,
+    macros: Option<BTreeMap<String, Vec<String>>>,
+    filters: Option<BTreeMap<String, String>>,
+    groups: Option<BTreeMap<String, Vec<String>>>,
+    title_rules: Option<BTreeMap<String, String>>,
+    webhooks: Option<Vec<WebhookFromToml>>,
+    theme: Option<String>,
+    markers: Option<String>,
+    new_marker: Option<String>,
+    queued_marker: Option<String>,
+    downloaded_marker: Option<String>,
+    updated_marker: Option<String>,
+    played_marker: Option<String>,
     colors: Option<AppColorsFromToml>,
 }
 
-/// A temporary struct used to deserialize keybinding data from the TOML
+/// A temporary struct used to deserialize a webhook from the TOML
 /// configuration file.
 #[derive(Debug, Deserialize)]
+struct WebhookFromToml {
+    url: String,
+    body: Option<String>,
+    events: Option<Vec<String>>,
+}
+
+/// A temporary struct used to deserialize keybinding data from the TOML
+/// configuration file.
+#[derive(Debug, Default, Deserialize)]
 pub struct KeybindingsFromToml {
     pub left: Option<Vec<String>>,
     pub right: Option<Vec<String>>,
@@ -81,28 +295,86 @@ pub struct KeybindingsFromToml {
     pub go_bot: Option<Vec<String>>,
     pub page_up: Option<Vec<String>>,
     pub page_down: Option<Vec<String>>,
+    pub toggle_group: Option<Vec<String>>,
+    pub toggle_all_groups: Option<Vec<String>>,
+    pub toggle_layout: Option<Vec<String>>,
+    pub tab_library: Option<Vec<String>>,
+    pub tab_new: Option<Vec<String>>,
+    pub tab_queue: Option<Vec<String>>,
+    pub tab_downloads: Option<Vec<String>>,
+    pub tab_search: Option<Vec<String>>,
+    pub next_tab: Option<Vec<String>>,
     pub add_feed: Option<Vec<String>>,
+    pub import: Option<Vec<String>>,
     pub sync: Option<Vec<String>>,
     pub sync_all: Option<Vec<String>>,
+    pub check_all: Option<Vec<String>>,
+    pub select: Option<Vec<String>>,
     pub play: Option<Vec<String>>,
     pub mark_played: Option<Vec<String>>,
     pub mark_all_played: Option<Vec<String>>,
+    pub mark_older_played: Option<Vec<String>>,
+    pub mark_library_older_played: Option<Vec<String>>,
+    pub toggle_star: Option<Vec<String>>,
+    pub enqueue: Option<Vec<String>>,
+    pub enqueue_next: Option<Vec<String>>,
+    pub move_up: Option<Vec<String>>,
+    pub move_down: Option<Vec<String>>,
+    pub move_to_top: Option<Vec<String>>,
+    pub move_to_bottom: Option<Vec<String>>,
+    pub shuffle_queue: Option<Vec<String>>,
+    pub interleave_queue: Option<Vec<String>>,
+    pub fill_queue: Option<Vec<String>>,
     pub download: Option<Vec<String>>,
     pub download_all: Option<Vec<String>>,
+    pub choose_enclosure: Option<Vec<String>>,
+    pub download_library: Option<Vec<String>>,
+    pub cancel: Option<Vec<String>>,
     pub delete: Option<Vec<String>>,
     pub delete_all: Option<Vec<String>>,
+    pub unmark_downloaded: Option<Vec<String>>,
     pub remove: Option<Vec<String>>,
     pub remove_all: Option<Vec<String>>,
+    pub toggle_select: Option<Vec<String>>,
+    pub toggle_notify: Option<Vec<String>>,
+    pub podcast_settings: Option<Vec<String>>,
+    pub change_url: Option<Vec<String>>,
+    pub support: Option<Vec<String>>,
+    pub cycle_theme: Option<Vec<String>>,
+    pub theme_preview: Option<Vec<String>>,
+    pub reload_config: Option<Vec<String>>,
+    pub setup: Option<Vec<String>>,
+    pub chapters: Option<Vec<String>>,
+    pub transcript: Option<Vec<String>>,
+    pub search: Option<Vec<String>>,
+    pub copy_mode: Option<Vec<String>>,
+    pub yank: Option<Vec<String>>,
+    pub yank_all: Option<Vec<String>>,
+    pub toggle_wrap: Option<Vec<String>>,
     pub filter_played: Option<Vec<String>>,
     pub filter_downloaded: Option<Vec<String>>,
+    pub cycle_sort: Option<Vec<String>>,
+    pub cycle_filter: Option<Vec<String>>,
+    pub filter_menu: Option<Vec<String>>,
+    pub find: Option<Vec<String>>,
+    pub find_in_menu: Option<Vec<String>>,
+    pub in_progress: Option<Vec<String>>,
+    pub inbox: Option<Vec<String>>,
+    pub triage_mode: Option<Vec<String>>,
+    pub queue: Option<Vec<String>>,
+    pub stats: Option<Vec<String>>,
+    pub show_log: Option<Vec<String>>,
+    pub notifications: Option<Vec<String>>,
+    pub suspend: Option<Vec<String>>,
+    pub shell: Option<Vec<String>>,
     pub help: Option<Vec<String>>,
     pub quit: Option<Vec<String>>,
 }
 
 /// A temporary struct used to deserialize colors data from the TOML
-/// configuration file. See crate::ui::colors module for the AppColors
-/// struct which handles the final color scheme.
-#[derive(Debug, Deserialize)]
+/// configuration file or a theme file. See crate::ui::colors module for
+/// the AppColors struct which handles the final color scheme.
+#[derive(Debug, Clone, Deserialize)]
 pub struct AppColorsFromToml {
     pub normal_foreground: Option<String>,
     pub normal_background: Option<String>,
@@ -114,6 +386,20 @@ pub struct AppColorsFromToml {
     pub highlighted_background: Option<String>,
     pub error_foreground: Option<String>,
     pub error_background: Option<String>,
+    pub border_foreground: Option<String>,
+    pub border_background: Option<String>,
+    pub title_foreground: Option<String>,
+    pub title_background: Option<String>,
+    pub notification_foreground: Option<String>,
+    pub notification_background: Option<String>,
+    pub played_foreground: Option<String>,
+    pub played_background: Option<String>,
+    pub downloaded_foreground: Option<String>,
+    pub downloaded_background: Option<String>,
+    pub popup_border_foreground: Option<String>,
+    pub popup_border_background: Option<String>,
+    pub popup_title_foreground: Option<String>,
+    pub popup_title_background: Option<String>,
 }
 
 
@@ -132,6 +418,13 @@ impl Config {
                 toml::from_str(&config_string)
                     .with_context(|| "Could not parse config.toml. Please check file syntax.")?
             }
+            // a config file that is there but cannot be opened should
+            // not be replaced by the defaults without saying so
+            Err(err) if err.kind() != ErrorKind::NotFound => {
+                return Err(err).with_context(|| {
+                    "Could not open config.toml. Please ensure file is readable."
+                });
+            }
             Err(_) => {
                 // if we can't find the file, set everything to empty
                 // so we it will use the defaults for everything
@@ -146,20 +439,78 @@ impl Config {
                     go_bot: None,
                     page_up: None,
                     page_down: None,
+                    toggle_group: None,
+                    toggle_all_groups: None,
+                    toggle_layout: None,
+                    tab_library: None,
+                    tab_new: None,
+                    tab_queue: None,
+                    tab_downloads: None,
+                    tab_search: None,
+                    next_tab: None,
                     add_feed: None,
+                    import: None,
                     sync: None,
                     sync_all: None,
+                    check_all: None,
+                    select: None,
                     play: None,
                     mark_played: None,
                     mark_all_played: None,
+                    mark_older_played: None,
+                    mark_library_older_played: None,
+                    toggle_star: None,
+                    enqueue: None,
+                    enqueue_next: None,
+                    move_up: None,
+                    move_down: None,
+                    move_to_top: None,
+                    move_to_bottom: None,
+                    shuffle_queue: None,
+                    interleave_queue: None,
+                    fill_queue: None,
                     download: None,
                     download_all: None,
+                    choose_enclosure: None,
+                    download_library: None,
+                    cancel: None,
                     delete: None,
                     delete_all: None,
+                    unmark_downloaded: None,
                     remove: None,
                     remove_all: None,
+                    toggle_select: None,
+                    toggle_notify: None,
+                    podcast_settings: None,
+                    change_url: None,
+                    support: None,
+                    cycle_theme: None,
+                    theme_preview: None,
+                    reload_config: None,
+                    setup: None,
+                    chapters: None,
+                    transcript: None,
+                    search: None,
+                    copy_mode: None,
+                    yank: None,
+                    yank_all: None,
+                    toggle_wrap: None,
                     filter_played: None,
                     filter_downloaded: None,
+                    cycle_sort: None,
+                    cycle_filter: None,
+                    filter_menu: None,
+                    find: None,
+                    find_in_menu: None,
+                    in_progress: None,
+                    inbox: None,
+                    triage_mode: None,
+                    queue: None,
+                    stats: None,
+                    show_log: None,
+                    notifications: None,
+                    suspend: None,
+                    shell: None,
                     help: None,
                     quit: None,
                 };
@@ -175,53 +526,216 @@ impl Config {
                     highlighted_background: None,
                     error_foreground: None,
                     error_background: None,
+                    border_foreground: None,
+                    border_background: None,
+                    title_foreground: None,
+                    title_background: None,
+                    notification_foreground: None,
+                    notification_background: None,
+                    played_foreground: None,
+                    played_background: None,
+                    downloaded_foreground: None,
+                    downloaded_background: None,
+                    popup_border_foreground: None,
+                    popup_border_background: None,
+                    popup_title_foreground: None,
+                    popup_title_background: None,
                 };
                 ConfigFromToml {
                     download_path: None,
+                    webdav_url: None,
+                    webdav_user: None,
+                    webdav_password: None,
+                    trash_days: None,
+                    watch_dir: None,
                     play_command: None,
+                    play_in_terminal: None,
+                    feed_url_hook: None,
+                    ca_file: None,
+                    prefer_ip_version: None,
+                    bind_address: None,
+                    post_download_command: None,
+                    post_play_command: None,
+                    status_file: None,
+                    now_playing_file: None,
+                    now_playing_format: None,
+                    mpv_socket: None,
+                    continuous_playback: None,
+                    queue_gap: None,
+                    remove_from_queue: None,
+                    listenbrainz_token: None,
+                    listenbrainz_url: None,
+                    clipboard_command: None,
+                    open_command: None,
+                    log_file: None,
+                    log_level: None,
+                    remote_socket: None,
+                    remote_host: None,
                     download_new_episodes: None,
+                    new_episode_max_age: None,
+                    new_podcast_episodes: None,
+                    sort_podcasts: None,
+                    sort_episodes: None,
+                    enter_podcast: None,
+                    enter_episode: None,
                     simultaneous_downloads: None,
+                    simultaneous_syncs: None,
                     max_retries: None,
+                    preferred_enclosure: None,
+                    dead_feed_syncs: None,
+                    db_maintenance_interval: None,
+                    check_for_updates: None,
+                    sync_digest: None,
+                    filename_profile: None,
+                    max_filename_length: None,
+                    cover_art: None,
+                    image_cache: None,
+                    wrap_details: None,
+                    details_wrap_width: None,
+                    layout: None,
                     keybindings: Some(keybindings),
+                    macros: None,
+                    filters: None,
+                    groups: None,
+                    title_rules: None,
+                    webhooks: None,
+                    theme: None,
+                    markers: None,
+                    new_marker: None,
+                    queued_marker: None,
+                    downloaded_marker: None,
+                    updated_marker: None,
+                    played_marker: None,
                     colors: Some(colors),
                 }
             }
         };
 
-        return config_with_defaults(config_toml);
+        // theme files are stored in a "themes" folder next to
+        // config.toml
+        let mut themes_dir = path.to_path_buf();
+        themes_dir.pop();
+        themes_dir.push("themes");
+
+        return config_with_defaults(config_toml, &themes_dir);
+    }
+
+    /// Returns a Config struct with the default value of every setting,
+    /// as if the config file at `path` did not exist. This is used when
+    /// the config file has errors (see `config_check::check()`).
+    pub fn defaults(path: &Path) -> Result<Config> {
+        let mut themes_dir = path.to_path_buf();
+        themes_dir.pop();
+        themes_dir.push("themes");
+        return config_with_defaults(toml::from_str("")?, &themes_dir);
     }
 }
 
 /// Takes the deserialized TOML configuration, and creates a Config struct
 /// that specifies user settings where indicated, and defaults for any
 /// settings that were not specified by the user.
-fn config_with_defaults(config_toml: ConfigFromToml) -> Result<Config> {
-    // specify keybindings
-    let keymap = match config_toml.keybindings {
-        Some(kb) => Keybindings::from_config(kb),
-        None => Keybindings::default(),
-    };
+fn config_with_defaults(config_toml: ConfigFromToml, themes_dir: &Path) -> Result<Config> {
+    // specify keybindings and macros
+    let keymap = Keybindings::from_config(
+        config_toml.keybindings.unwrap_or_default(),
+        config_toml.macros.unwrap_or_default(),
+    )?;
 
-    // specify app colors
-    let colors = match config_toml.colors {
-        Some(clrs) => {
-            let mut colors = AppColors::default();
-            colors.add_from_config(clrs);
-            colors
-        }
-        None => AppColors::default(),
-    };
+    // specify app colors: colors from the selected theme (if any) are
+    // applied first, and then any colors set in config.toml
+    let colors = AppColors::from_theme(
+        themes_dir,
+        config_toml.theme.as_deref(),
+        config_toml.colors.clone(),
+    )?;
 
     // paths are set by user, or they resolve to OS-specific path as
     // provided by dirs crate
     let download_path =
-        parse_create_dir(config_toml.download_path.as_deref(), dirs::data_local_dir())?;
+        parse_create_dir(config_toml.download_path.as_deref(), data_dir())?;
+
+    // downloads go to a WebDAV share instead, if one is set
+    let webdav = config_toml
+        .webdav_url
+        .filter(|url| !url.trim().is_empty())
+        .map(|url| WebDavConfig {
+            url: url,
+            user: config_toml.webdav_user.filter(|user| !user.is_empty()),
+            // a password in the system keyring comes first
+            password: secrets::get("webdav_password").or(config_toml.webdav_password),
+        });
+
+    // 0 turns the trash off, the same as leaving it unset
+    let trash_days = config_toml.trash_days.filter(|days| *days > 0);
+
+    // the watch folder is only used if the user sets it
+    let watch_dir = match config_toml.watch_dir.as_deref() {
+        Some(path) => Some(parse_create_dir(Some(path), None)?),
+        None => None,
+    };
 
     let play_command = match config_toml.play_command.as_deref() {
         Some(cmd) => cmd.to_string(),
         None => "vlc %s".to_string(),
     };
 
+    let feed_url_hook = config_toml
+        .feed_url_hook
+        .filter(|cmd| !cmd.trim().is_empty());
+    let post_download_command = config_toml
+        .post_download_command
+        .filter(|cmd| !cmd.trim().is_empty());
+    let post_play_command = config_toml
+        .post_play_command
+        .filter(|cmd| !cmd.trim().is_empty());
+
+    // which IP version to try first, and where connections come from
+    let network = NetworkConfig {
+        prefer: parse_ip_version(config_toml.prefer_ip_version.as_ref()),
+        bind: parse_bind_address(config_toml.bind_address.as_deref())?,
+    };
+
+    let status_file = expand_path(config_toml.status_file.as_deref())?;
+    let now_playing_file = expand_path(config_toml.now_playing_file.as_deref())?;
+    let now_playing_format = config_toml
+        .now_playing_format
+        .unwrap_or_else(|| NOW_PLAYING_FORMAT.to_string());
+    let mpv_socket = expand_path(config_toml.mpv_socket.as_deref())?;
+    let clipboard_command = config_toml
+        .clipboard_command
+        .filter(|cmd| !cmd.trim().is_empty());
+    let open_command = config_toml
+        .open_command
+        .filter(|cmd| !cmd.trim().is_empty());
+
+    // an empty path turns off the remote control socket; by default,
+    // it goes in the runtime directory, or else next to config.toml
+    let remote_socket = match config_toml.remote_socket.as_deref() {
+        Some(socket) if socket.trim().is_empty() => None,
+        Some(socket) => expand_path(Some(socket))?,
+        None => dirs::runtime_dir()
+            .or_else(|| themes_dir.parent().map(|dir| dir.to_path_buf()))
+            .map(|dir| dir.join("shellcaster.sock")),
+    };
+
+    // by default, the log is kept in the data folder, with the database
+    let log_file = match expand_path(config_toml.log_file.as_deref())? {
+        Some(path) => path,
+        None => match data_dir() {
+            Some(dir) => dir.join("shellcaster").join("shellcaster.log"),
+            None => themes_dir.with_file_name("shellcaster.log"),
+        },
+    };
+    let log_level = config_toml
+        .log_level
+        .as_deref()
+        .and_then(logging::parse_level)
+        .unwrap_or(LevelFilter::Warn);
+
+    let remote_host = config_toml
+        .remote_host
+        .filter(|host| !host.trim().is_empty());
+
     let download_new_episodes = match config_toml.download_new_episodes.as_deref() {
         Some("always") => DownloadNewEpisodes::Always,
         Some("ask-selected") => DownloadNewEpisodes::AskSelected,
@@ -230,30 +744,322 @@ fn config_with_defaults(config_toml: ConfigFromToml) -> Result<Config> {
         Some(_) | None => DownloadNewEpisodes::AskUnselected,
     };
 
+    // zero (or a negative number) means there is no age limit
+    let new_episode_max_age = config_toml.new_episode_max_age.filter(|days| *days > 0);
+
+    let new_podcast_episodes = match config_toml.new_podcast_episodes.as_deref() {
+        Some("ask") => NewPodcastEpisodes::Ask,
+        Some("latest-unplayed") => NewPodcastEpisodes::LatestUnplayed,
+        Some("all-played") => NewPodcastEpisodes::AllPlayed,
+        Some("all-unplayed") => NewPodcastEpisodes::AllUnplayed,
+        Some(_) | None => NewPodcastEpisodes::Ask,
+    };
+
+    let sorts = Sorts {
+        podcasts: SortMode::from_config(config_toml.sort_podcasts.as_deref(), SortTarget::Podcasts),
+        episodes: SortMode::from_config(config_toml.sort_episodes.as_deref(), SortTarget::Episodes),
+    };
+
+    let smart_filters = config_toml
+        .filters
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(name, filter)| SmartFilter::new(name, &filter))
+        .collect::<Result<Vec<SmartFilter>>>()?;
+
+    // tags are written the same way as in saved filters
+    let groups = config_toml
+        .groups
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(name, tags)| TagGroup {
+            name: name,
+            tags: tags.iter().map(|tag| to_tag(tag)).collect(),
+        })
+        .collect();
+
+    let title_rules = TitleRules::new(
+        config_toml
+            .title_rules
+            .iter()
+            .flatten()
+            .map(|(pattern, replacement)| (pattern.as_str(), replacement.as_str())),
+    )?;
+
+    let webhooks = config_toml
+        .webhooks
+        .unwrap_or_default()
+        .into_iter()
+        .map(|hook| Webhook::new(hook.url, hook.body, hook.events))
+        .collect::<Result<Vec<Webhook>>>()?;
+
+    let enter_podcast = parse_enter_action(config_toml.enter_podcast.as_deref());
+    let enter_episode = parse_enter_action(config_toml.enter_episode.as_deref());
+
     let simultaneous_downloads = match config_toml.simultaneous_downloads {
         Some(num) if num > 0 => num,
         Some(_) => 3,
         None => 3,
     };
 
+    let simultaneous_syncs = match config_toml.simultaneous_syncs {
+        Some(num) if num > 0 => num,
+        Some(_) => 20,
+        None => 20,
+    };
+
     let max_retries = match config_toml.max_retries {
         Some(num) if num > 0 => num,
         Some(_) => 3,
         None => 3,
     };
 
+    let preferred_enclosure = config_toml
+        .preferred_enclosure
+        .filter(|pref| !pref.trim().is_empty());
+
+    // zero (or a negative number) means never asking about dead feeds
+    let dead_feed_syncs = match config_toml.dead_feed_syncs {
+        Some(num) if num > 0 => Some(num),
+        Some(_) => None,
+        None => Some(5),
+    };
+
+    // zero (or a negative number) turns off automatic maintenance
+    let db_maintenance_interval = match config_toml.db_maintenance_interval {
+        Some(days) if days > 0 => Some(days),
+        Some(_) | None => None,
+    };
+
+    // guessing at the profile could leave files with names that the
+    // user's devices can't read, so a typo is not ignored
+    let filename_profile = match config_toml.filename_profile.as_deref() {
+        Some("conservative") => SanitizeProfile::Conservative,
+        Some("unicode") => SanitizeProfile::Unicode,
+        Some("windows") | None => SanitizeProfile::Windows,
+        Some(profile) => {
+            return Err(anyhow!(
+                "filename_profile in config.toml must be \"conservative\", \"unicode\" or \
+                 \"windows\", not \"{profile}\"."
+            ));
+        }
+    };
+
+    // most filesystems limit file names to 255 bytes
+    let max_filename_length = match config_toml.max_filename_length {
+        Some(num) if num > 0 && num <= 255 => num,
+        Some(_) => 255,
+        None => 255,
+    };
+
+    // individual markers replace those of the preset; an empty marker
+    // is not shown at all
+    let mut glyphs = match config_toml.markers.as_deref() {
+        Some("unicode") => Glyphs::unicode(),
+        Some(_) | None => Glyphs::ascii(),
+    };
+    let markers = [
+        (&mut glyphs.new, config_toml.new_marker),
+        (&mut glyphs.queued, config_toml.queued_marker),
+        (&mut glyphs.downloaded, config_toml.downloaded_marker),
+        (&mut glyphs.updated, config_toml.updated_marker),
+        (&mut glyphs.played, config_toml.played_marker),
+    ];
+    for (glyph, marker) in markers {
+        if let Some(marker) = marker {
+            *glyph = marker.trim().to_string();
+        }
+    }
+
+    let cover_art = match config_toml.cover_art.as_deref() {
+        Some("auto") => CoverArt::Auto,
+        Some("kitty") => CoverArt::Kitty,
+        Some("sixel") => CoverArt::Sixel,
+        Some("off") => CoverArt::Off,
+        Some(_) | None => CoverArt::Off,
+    };
+
+    // images are only cached if cover art is turned on
+    let image_cache = match cover_art {
+        CoverArt::Off => None,
+        _ => Some(parse_create_dir(
+            config_toml.image_cache.as_deref(),
+            cache_dir(),
+        )?),
+    };
+
     return Ok(Config {
         download_path: download_path,
+        webdav: webdav,
+        trash_days: trash_days,
+        watch_dir: watch_dir,
         play_command: play_command,
+        play_in_terminal: config_toml.play_in_terminal.unwrap_or(false),
+        feed_url_hook: feed_url_hook,
+        ca_file: expand_path(config_toml.ca_file.as_deref())?,
+        network: network,
+        post_download_command: post_download_command,
+        post_play_command: post_play_command,
+        status_file: status_file,
+        now_playing_file: now_playing_file,
+        now_playing_format: now_playing_format,
+        mpv_socket: mpv_socket,
+        continuous_playback: config_toml.continuous_playback.unwrap_or(true),
+        queue_gap: config_toml.queue_gap.unwrap_or(0),
+        remove_from_queue: config_toml.remove_from_queue.unwrap_or(true),
+        listenbrainz_token: secrets::get("listenbrainz_token")
+            .or(config_toml.listenbrainz_token)
+            .map(|token| token.trim().to_string())
+            .filter(|token| !token.is_empty()),
+        listenbrainz_url: config_toml
+            .listenbrainz_url
+            .filter(|url| !url.trim().is_empty())
+            .unwrap_or_else(|| scrobble::DEFAULT_URL.to_string()),
+        clipboard_command: clipboard_command,
+        open_command: open_command,
+        log_file: log_file,
+        log_level: log_level,
+        remote_socket: remote_socket,
+        remote_host: remote_host,
         download_new_episodes: download_new_episodes,
+        new_episode_max_age: new_episode_max_age,
+        new_podcast_episodes: new_podcast_episodes,
+        sorts: sorts,
+        smart_filters: smart_filters,
+        groups: groups,
+        title_rules: title_rules,
+        webhooks: webhooks,
+        enter_podcast: enter_podcast,
+        enter_episode: enter_episode,
         simultaneous_downloads: simultaneous_downloads,
+        simultaneous_syncs: simultaneous_syncs,
         max_retries: max_retries,
+        preferred_enclosure: preferred_enclosure,
+        dead_feed_syncs: dead_feed_syncs,
+        db_maintenance_interval: db_maintenance_interval,
+        check_for_updates: config_toml.check_for_updates.unwrap_or(true),
+        sync_digest: config_toml.sync_digest.unwrap_or(true),
+        sanitizer: Sanitizer::new(filename_profile, max_filename_length),
+        cover_art: cover_art,
+        image_cache: image_cache,
+        wrap_details: config_toml.wrap_details.unwrap_or(true),
+        details_wrap_width: config_toml.details_wrap_width.filter(|width| *width > 0),
+        tree_layout: config_toml.layout.as_deref() == Some("tree"),
         keybindings: keymap,
         colors: colors,
+        themes_dir: themes_dir.to_path_buf(),
+        theme: config_toml.theme,
+        glyphs: glyphs,
+        color_overrides: config_toml.colors,
     });
 }
 
 
+/// Helper function that expands "~" and environment variables in an
+/// (optionally specified) path from the config file.
+pub fn expand_path(path: Option<&str>) -> Result<Option<PathBuf>> {
+    return match path {
+        Some(path) => match shellexpand::full(path) {
+            Ok(realpath) => Ok(Some(PathBuf::from(realpath.as_ref()))),
+            Err(err) => Err(anyhow!(
+                "Could not parse environment variable {} in config.toml. Reason: {}",
+                err.var_name,
+                err.cause
+            )),
+        },
+        None => Ok(None),
+    };
+}
+
+/// Returns the directory where config.toml is kept by default, e.g.,
+/// $XDG_CONFIG_HOME or ~/.config on Linux.
+pub fn config_dir() -> Option<PathBuf> {
+    return xdg_dir("XDG_CONFIG_HOME", dirs::config_dir());
+}
+
+/// Returns the directory where the database and downloaded episodes are
+/// kept by default, e.g., $XDG_DATA_HOME or ~/.local/share on Linux.
+pub fn data_dir() -> Option<PathBuf> {
+    return xdg_dir("XDG_DATA_HOME", dirs::data_local_dir());
+}
+
+/// Returns the directory where cover art is cached by default, e.g.,
+/// $XDG_CACHE_HOME or ~/.cache on Linux.
+pub fn cache_dir() -> Option<PathBuf> {
+    return xdg_dir("XDG_CACHE_HOME", dirs::cache_dir());
+}
+
+/// Whether the XDG environment variables are followed on this OS. On
+/// macOS and Windows they are ignored, as they were before, so that
+/// setting them for other programs does not move shellcaster's files.
+const FOLLOWS_XDG: bool = cfg!(all(unix, not(target_os = "macos")));
+
+/// Helper function that uses the directory in an XDG environment
+/// variable if it is set (see `FOLLOWS_XDG`), or else the OS default.
+fn xdg_dir(var: &str, default: Option<PathBuf>) -> Option<PathBuf> {
+    return pick_dir(std::env::var_os(var), default, FOLLOWS_XDG);
+}
+
+/// Helper function for `xdg_dir()`, which picks between the value of
+/// an XDG variable and the OS default. As the XDG spec says, relative
+/// paths are ignored.
+fn pick_dir(
+    value: Option<std::ffi::OsString>,
+    default: Option<PathBuf>,
+    follows_xdg: bool,
+) -> Option<PathBuf> {
+    return match value.map(PathBuf::from) {
+        Some(dir) if follows_xdg && dir.is_absolute() => Some(dir),
+        _ => default,
+    };
+}
+
+/// Helper function that reads the user's `prefer_ip_version`, which
+/// may be written as a number or a string. Anything other than 4 or 6
+/// is ignored (and reported by `config_check`).
+fn parse_ip_version(version: Option<&toml::Value>) -> Option<IpFamily> {
+    let version = match version? {
+        toml::Value::Integer(version) => version.to_string(),
+        toml::Value::String(version) => version.trim().to_string(),
+        _ => return None,
+    };
+    return match version.as_str() {
+        "4" => Some(IpFamily::V4),
+        "6" => Some(IpFamily::V6),
+        _ => None,
+    };
+}
+
+/// Helper function that reads the user's `bind_address`, which is either
+/// an IP address or, on Linux, the name of a network interface.
+fn parse_bind_address(bind: Option<&str>) -> Result<Option<BindTo>> {
+    let bind = match bind.map(str::trim) {
+        Some(bind) if !bind.is_empty() => bind,
+        _ => return Ok(None),
+    };
+    if let Ok(addr) = bind.parse::<IpAddr>() {
+        return Ok(Some(BindTo::Address(addr)));
+    }
+    if cfg!(any(target_os = "android", target_os = "fuchsia", target_os = "linux")) {
+        return Ok(Some(BindTo::Interface(bind.to_string())));
+    }
+    return Err(anyhow!(
+        "bind_address in config.toml must be an IP address on this system, not {bind}."
+    ));
+}
+
+/// Helper function that converts the user's setting for what the
+/// "select" key does in a menu. Defaults to playing the selected
+/// episode.
+fn parse_enter_action(action: Option<&str>) -> EnterAction {
+    return match action {
+        Some("open") => EnterAction::Open,
+        Some("mark-played") => EnterAction::MarkPlayed,
+        Some("play") => EnterAction::Play,
+        Some(_) | None => EnterAction::Play,
+    };
+}
+
 /// Helper function that takes an (optionally specified) user directory
 /// and an (OS-dependent) default directory, expands any environment
 /// variables, ~ alias, etc. Returns a PathBuf. Panics if environment
@@ -292,3 +1098,33 @@ fn parse_create_dir(user_dir: Option<&str>, default: Option<PathBuf>) -> Result<
 
     return Ok(final_path);
 }
+
+
+// TESTS -----------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn xdg_dirs() {
+        let default = Some(PathBuf::from("/home/me/.local/share"));
+        let set = Some(std::ffi::OsString::from("/data"));
+        assert_eq!(pick_dir(set.clone(), default.clone(), true), Some(PathBuf::from("/data")));
+        assert_eq!(pick_dir(set, default.clone(), false), default);
+        let relative = Some(std::ffi::OsString::from("data"));
+        assert_eq!(pick_dir(relative, default.clone(), true), default);
+        assert_eq!(pick_dir(None, default.clone(), true), default);
+        assert_eq!(pick_dir(None, None, true), None);
+    }
+
+    #[test]
+    fn ip_versions() {
+        let version = |value: toml::Value| parse_ip_version(Some(&value));
+        assert_eq!(version(toml::Value::Integer(4)), Some(IpFamily::V4));
+        assert_eq!(version(toml::Value::String(" 6".to_string())), Some(IpFamily::V6));
+        assert_eq!(version(toml::Value::Integer(5)), None);
+        assert_eq!(version(toml::Value::String("ipv6".to_string())), None);
+        assert_eq!(parse_ip_version(None), None);
+    }
+}
@@ -0,0 +1,442 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::ErrorKind;
+use std::path::Path;
+
+use serde::de::{DeserializeOwned, Visitor};
+use serde::forward_to_deserialize_any;
+
+use crate::config::{AppColorsFromToml, Config, ConfigFromToml, KeybindingsFromToml};
+use crate::keymap::normalize_key;
+use crate::types::{SortMode, SortTarget};
+use crate::ui::colors::AppColors;
+
+/// Settings that take one of a list of values. Any other value is
+/// ignored, and the default is used instead. (An unknown
+/// filename_profile stops the config from loading instead, which is
+/// reported as an error.)
+const CHOICES: [(&str, &[&str]); 9] = [
+    ("download_new_episodes", &["always", "ask-selected", "ask-unselected", "never"]),
+    ("new_podcast_episodes", &["ask", "latest-unplayed", "all-played", "all-unplayed"]),
+    ("enter_podcast", &["open", "mark-played", "play"]),
+    ("enter_episode", &["open", "mark-played", "play"]),
+    ("log_level", &["off", "error", "warn", "info", "debug"]),
+    ("markers", &["ascii", "unicode"]),
+    ("cover_art", &["auto", "kitty", "sixel", "off"]),
+    ("layout", &["panes", "tree"]),
+    ("prefer_ip_version", &["4", "6"]),
+];
+
+/// Something wrong with config.toml. Errors (`fatal`) stop the config
+/// from being loaded at all; anything else is ignored, with the default
+/// used in its place. `line` is the number (starting from 1) and text
+/// of the line in config.toml that the problem is on, if it is known.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    pub message: String,
+    pub fatal: bool,
+    pub line: Option<(usize, String)>,
+}
+
+impl Problem {
+    fn error(message: String, line: Option<(usize, String)>) -> Self {
+        return Self {
+            message: message,
+            fatal: true,
+            line: line,
+        };
+    }
+
+    fn warning(message: String, line: Option<(usize, String)>) -> Self {
+        return Self {
+            message: message,
+            fatal: false,
+            line: line,
+        };
+    }
+
+    /// Returns lines describing the problem for a report, followed by
+    /// the line of config.toml it is on, if it is known.
+    pub fn describe(&self) -> Vec<String> {
+        let kind = if self.fatal { "error" } else { "warning" };
+        return match &self.line {
+            Some((num, text)) => vec![
+                format!("{kind} (line {num}): {}", self.message),
+                format!("    {num} | {}", text.trim_end()),
+            ],
+            None => vec![format!("{kind}: {}", self.message)],
+        };
+    }
+}
+
+/// Checks the config file at `path` for anything that would stop it
+/// from loading, or that would be ignored: syntax errors, keys bound to
+/// more than one action, unknown settings, invalid colors and choices,
+/// and folders that shellcaster cannot write to. A missing config file
+/// is not a problem, as the defaults are used.
+pub fn check(path: &Path) -> Vec<Problem> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == ErrorKind::NotFound => String::new(),
+        Err(err) => {
+            let message = format!("Could not read {}: {err}", path.display());
+            return vec![Problem::error(message, None)];
+        }
+    };
+    let doc: toml::Value = match toml::from_str(&text) {
+        Ok(doc) => doc,
+        Err(err) => return vec![toml_error(&err, &text)],
+    };
+
+    let mut problems = unknown_settings(&doc, &text);
+    problems.extend(bad_values(&doc, &text));
+    problems.extend(key_conflicts(&doc, &text));
+    match Config::new(path) {
+        Ok(config) => problems.extend(unwritable_paths(&config, path, &text)),
+        Err(err) => {
+            if let Some(err) = err.downcast_ref::<toml::de::Error>() {
+                problems.push(toml_error(err, &text));
+            } else {
+                problems.push(Problem::error(format!("{err:#}"), None));
+            }
+        }
+    }
+    problems.sort_by_key(|problem| problem.line.as_ref().map(|(num, _)| *num));
+    return problems;
+}
+
+/// Turns an error from the TOML parser into a problem on the line it
+/// points to.
+fn toml_error(err: &toml::de::Error, text: &str) -> Problem {
+    let message = format!("{err}");
+    // the position is shown with the line instead
+    let message = match message.rsplit_once(" at line ") {
+        Some((message, _)) => message.to_string(),
+        None => message,
+    };
+    let line = err
+        .line_col()
+        .and_then(|(num, _)| Some((num + 1, text.lines().nth(num)?.to_string())));
+    return Problem::error(message, line);
+}
+
+/// Finds settings that shellcaster does not know about, e.g., because
+/// of a typo, in the main part of config.toml and in the "keybindings"
+/// and "colors" sections.
+fn unknown_settings(doc: &toml::Value, text: &str) -> Vec<Problem> {
+    let sections: [(Option<&str>, &[&str]); 3] = [
+        (None, field_names::<ConfigFromToml>()),
+        (Some("keybindings"), field_names::<KeybindingsFromToml>()),
+        (Some("colors"), field_names::<AppColorsFromToml>()),
+    ];
+    let mut problems = Vec::new();
+    for (section, known) in sections {
+        let table = match section {
+            Some(section) => doc.get(section).and_then(|table| table.as_table()),
+            None => doc.as_table(),
+        };
+        for key in table.into_iter().flat_map(|table| table.keys()) {
+            if !known.contains(&key.as_str()) {
+                let what = match section {
+                    Some("keybindings") => "action",
+                    Some(_) => "color",
+                    None => "setting",
+                };
+                problems.push(Problem::warning(
+                    format!("Unknown {what} \"{key}\" is ignored."),
+                    find_line(text, section, key),
+                ));
+            }
+        }
+    }
+    return problems;
+}
+
+/// Finds colors and choices that are not valid, which are ignored.
+fn bad_values(doc: &toml::Value, text: &str) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let known = field_names::<AppColorsFromToml>();
+    let colors = doc.get("colors").and_then(|colors| colors.as_table());
+    for (key, value) in colors.into_iter().flatten() {
+        // unknown colors are reported by unknown_settings()
+        if !known.contains(&key.as_str()) {
+            continue;
+        }
+        if let Some(color) = value.as_str() {
+            if AppColors::color_from_str(color).is_err() {
+                problems.push(Problem::warning(
+                    format!("\"{color}\" is not a color, so {key} is not changed."),
+                    find_line(text, Some("colors"), key),
+                ));
+            }
+        }
+    }
+
+    let sort_modes = |target| -> Vec<&str> {
+        return SortMode::modes(target)
+            .iter()
+            .map(|mode| mode.config_name())
+            .collect();
+    };
+    let sorts = [
+        ("sort_podcasts", sort_modes(SortTarget::Podcasts)),
+        ("sort_episodes", sort_modes(SortTarget::Episodes)),
+    ];
+    let choices = CHOICES
+        .iter()
+        .map(|(key, values)| (*key, values.to_vec()))
+        .chain(sorts);
+    for (key, values) in choices {
+        let value = match doc.get(key) {
+            Some(toml::Value::String(value)) => value.clone(),
+            Some(toml::Value::Integer(value)) => value.to_string(),
+            Some(value) => value.to_string(),
+            None => continue,
+        };
+        if !values.contains(&value.as_str()) {
+            problems.push(Problem::warning(
+                format!(
+                    "\"{value}\" is not a choice for {key} ({}); the default is used.",
+                    values.join(", ")
+                ),
+                find_line(text, None, key),
+            ));
+        }
+    }
+    return problems;
+}
+
+/// Finds keys that config.toml binds to more than one action or macro.
+/// The actions involved keep their default keys (see
+/// `Keybindings::from_config()`).
+fn key_conflicts(doc: &toml::Value, text: &str) -> Vec<Problem> {
+    let mut bound: BTreeMap<String, String> = BTreeMap::new();
+    let mut problems = Vec::new();
+    let keybindings = doc.get("keybindings").and_then(|keys| keys.as_table());
+    for (action, keys) in keybindings.into_iter().flatten() {
+        let keys = keys.as_array().into_iter().flatten().filter_map(|key| key.as_str());
+        for key in keys {
+            let key = normalize_key(key);
+            match bound.get(&key) {
+                Some(other) if other != action => problems.push(Problem::warning(
+                    format!(
+                        "\"{key}\" is bound to both {other} and {action}; \
+                         both keep their default keys."
+                    ),
+                    find_line(text, Some("keybindings"), action),
+                )),
+                _ => {
+                    bound.insert(key, action.clone());
+                }
+            }
+        }
+    }
+    let macros = doc.get("macros").and_then(|macros| macros.as_table());
+    for key in macros.into_iter().flat_map(|macros| macros.keys()) {
+        if let Some(other) = bound.get(&normalize_key(key)) {
+            problems.push(Problem::warning(
+                format!(
+                    "\"{key}\" is bound to both {other} and a macro; \
+                     {other} keeps its default keys."
+                ),
+                find_line(text, Some("macros"), key),
+            ));
+        }
+    }
+    return problems;
+}
+
+/// Finds folders that shellcaster needs to write to but cannot: the
+/// folder holding config.toml (where the database is kept), the
+/// download folder, and the folders for the log, status files, and
+/// cover art. The watch folder only needs to be readable.
+fn unwritable_paths(config: &Config, path: &Path, text: &str) -> Vec<Problem> {
+    let mut folders = Vec::new();
+    if let Some(dir) = path.parent() {
+        folders.push((None, dir.to_path_buf()));
+    }
+    if config.webdav.is_none() {
+        folders.push((Some("download_path"), config.download_path.clone()));
+    }
+    let files = [
+        ("log_file", Some(&config.log_file)),
+        ("status_file", config.status_file.as_ref()),
+        ("now_playing_file", config.now_playing_file.as_ref()),
+    ];
+    for (key, file) in files {
+        if let Some(dir) = file.and_then(|file| file.parent()) {
+            folders.push((Some(key), dir.to_path_buf()));
+        }
+    }
+    if let Some(dir) = &config.image_cache {
+        folders.push((Some("image_cache"), dir.clone()));
+    }
+
+    let mut problems = Vec::new();
+    for (key, dir) in folders {
+        if !writable(&dir) {
+            let line = key.and_then(|key| find_line(text, None, key));
+            problems.push(Problem::warning(
+                format!("Cannot write to the folder {}.", dir.display()),
+                line,
+            ));
+        }
+    }
+    if let Some(dir) = &config.watch_dir {
+        if let Err(err) = fs::read_dir(dir) {
+            problems.push(Problem::warning(
+                format!("Cannot read the watch folder {}: {err}", dir.display()),
+                find_line(text, None, "watch_dir"),
+            ));
+        }
+    }
+    return problems;
+}
+
+/// Checks whether files can be created in a folder, by creating one and
+/// removing it again.
+fn writable(dir: &Path) -> bool {
+    let probe = dir.join(".shellcaster-check");
+    let created = File::create(&probe).is_ok();
+    let _ = fs::remove_file(&probe);
+    return created;
+}
+
+/// Returns the number and text of the line where `key` is set in
+/// config.toml, in the given section (or outside of any section).
+fn find_line(text: &str, section: Option<&str>, key: &str) -> Option<(usize, String)> {
+    let mut current = None;
+    for (num, line) in text.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            current = Some(trimmed.trim_matches(|c| c == '[' || c == ']').trim());
+        } else if current == section {
+            if let Some((name, _)) = trimmed.split_once('=') {
+                if name.trim().trim_matches(|c| c == '"' || c == '\'') == key {
+                    return Some((num + 1, line.to_string()));
+                }
+            }
+        }
+    }
+    return None;
+}
+
+/// Returns the names of the settings in one of the structs that
+/// config.toml is read into, by asking serde which fields it expects.
+fn field_names<T: DeserializeOwned>() -> &'static [&'static str] {
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(FieldNames(&mut fields));
+    return fields;
+}
+
+/// Deserializer that only records the names of the fields of the
+/// struct it is asked for, and then gives up.
+struct FieldNames<'a>(&'a mut &'static [&'static str]);
+
+impl<'de, 'a> serde::Deserializer<'de> for FieldNames<'a> {
+    type Error = serde::de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        return Err(serde::de::Error::custom("expected a struct"));
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        *self.0 = fields;
+        return Err(serde::de::Error::custom("only the field names are needed"));
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes
+        byte_buf option unit unit_struct newtype_struct seq tuple tuple_struct map
+        enum identifier ignored_any
+    }
+}
+
+
+// TESTS -----------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "sort_podcast = \"title\"
+cover_art = \"huge\"
+
+[keybindings]
+play = [ \"p\" ]
+
+[colors]
+normal_foreground = \"purpel\"
+";
+
+    #[test]
+    fn lines_in_sections() {
+        let line = find_line(CONFIG, None, "cover_art");
+        assert_eq!(line, Some((2, "cover_art = \"huge\"".to_string())));
+        let line = find_line(CONFIG, Some("keybindings"), "play");
+        assert_eq!(line, Some((5, "play = [ \"p\" ]".to_string())));
+        assert_eq!(find_line(CONFIG, None, "play"), None);
+        assert!(field_names::<KeybindingsFromToml>().contains(&"play"));
+        assert!(field_names::<ConfigFromToml>().contains(&"download_path"));
+    }
+
+    #[test]
+    fn problems_with_lines() {
+        let dir = std::env::temp_dir().join(format!("shellcaster-check-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        let download_path = format!("download_path = \"{}\"\n", dir.display());
+        fs::write(&path, download_path.clone() + CONFIG).unwrap();
+
+        let problems = check(&path);
+        let lines: Vec<usize> =
+            problems.iter().filter_map(|p| p.line.as_ref().map(|l| l.0)).collect();
+        assert_eq!(lines, vec![2, 3, 9]);
+        assert!(problems.iter().all(|problem| !problem.fatal));
+        assert!(problems[0].message.contains("Unknown setting \"sort_podcast\""));
+
+        // a key bound twice is reported, but the defaults are used
+        let conflict = "[keybindings]\nplay = [ \"p\" ]\nquit = [ \"P\", \"p\" ]\n";
+        fs::write(&path, download_path.clone() + conflict).unwrap();
+        let problems = check(&path);
+        assert_eq!(problems.len(), 1);
+        assert!(!problems[0].fatal);
+        assert!(problems[0].message.contains("both keep their default keys"));
+        assert_eq!(problems[0].line.as_ref().map(|l| l.0), Some(4));
+
+        // an IP version that is not 4 or 6 is ignored
+        for version in ["5", "\"ipv6\"", "4.0"] {
+            fs::write(&path, format!("{download_path}prefer_ip_version = {version}\n")).unwrap();
+            let problems = check(&path);
+            assert_eq!(problems.len(), 1, "{version}");
+            assert!(!problems[0].fatal);
+            assert!(problems[0].message.contains("prefer_ip_version"));
+        }
+        fs::write(&path, format!("{download_path}prefer_ip_version = \"6\"\n")).unwrap();
+        assert!(check(&path).is_empty());
+
+        // so are markers other than the two presets
+        fs::write(&path, format!("{download_path}markers = \"emoji\"\n")).unwrap();
+        let problems = check(&path);
+        assert_eq!(problems.len(), 1);
+        assert!(!problems[0].fatal);
+        assert!(problems[0].message.contains("\"emoji\" is not a choice for markers"));
+        fs::write(&path, format!("{download_path}markers = \"unicode\"\n")).unwrap();
+        assert!(check(&path).is_empty());
+
+        // but a file name profile has to be one that exists
+        fs::write(&path, format!("{download_path}filename_profile = \"fat32\"\n")).unwrap();
+        let problems = check(&path);
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].fatal);
+        assert!(problems[0].message.contains("\"conservative\", \"unicode\" or \"windows\""));
+
+        fs::write(&path, "queue_gap = [\n").unwrap();
+        assert!(check(&path)[0].fatal);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,356 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Context, Result};
+use lazy_static::lazy_static;
+
+use crate::keymap::{Keybindings, UserAction};
+use crate::ui::colors::AppColors;
+
+/// Number of backups of config.toml that are kept; older ones are
+/// deleted when a new one is made.
+const MAX_BACKUPS: usize = 5;
+
+/// The sample config.toml, with comments describing every setting.
+const SAMPLE_CONFIG: &str = include_str!("../config.toml");
+
+lazy_static! {
+    /// Config files that have already been backed up since shellcaster
+    /// started; each one is only backed up before it is first changed.
+    static ref BACKED_UP: Mutex<HashSet<PathBuf>> = Mutex::new(HashSet::new());
+}
+
+/// Returns the sample config.toml, with its keybindings and colors set
+/// to the defaults built into shellcaster, i.e., the ones used when a
+/// setting is left out. Actions that the sample does not list are
+/// added after the last of its keybindings. Colors that follow another
+/// color by default are left commented out.
+pub fn default_config() -> String {
+    let keymap = Keybindings::default();
+    let colors = AppColors::default().settings();
+    let mut actions = UserAction::all();
+    let mut section = None;
+    let mut last_keybinding = None;
+    let mut lines: Vec<String> = Vec::new();
+    for line in SAMPLE_CONFIG.lines() {
+        if line.starts_with('[') {
+            section = Some(line.trim_matches(|c| c == '[' || c == ']'));
+            lines.push(line.to_string());
+            continue;
+        }
+        let commented = line.strip_prefix('#');
+        let key = line_key(commented.unwrap_or(line));
+        match (section, key) {
+            (Some("keybindings"), Some(name)) if commented.is_none() => {
+                if let Some(action) = UserAction::from_config_name(name) {
+                    actions.retain(|a| *a != action);
+                    lines.push(keymap.config_line(action));
+                    last_keybinding = Some(lines.len());
+                    continue;
+                }
+            }
+            (Some("colors"), Some(name)) => {
+                if let Some((_, value)) = colors.iter().find(|(color, _)| color == name) {
+                    let new_line = match value {
+                        Some(color) => format!("{name} = {:?}", AppColors::color_to_str(*color)),
+                        None => format!("#{}", commented.unwrap_or(line)),
+                    };
+                    lines.push(new_line);
+                    continue;
+                }
+            }
+            _ => (),
+        }
+        lines.push(line.to_string());
+    }
+
+    if let Some(idx) = last_keybinding {
+        let missing = actions.into_iter().map(|action| keymap.config_line(action));
+        lines.splice(idx..idx, missing);
+    }
+    let mut text = lines.join("\n");
+    text.push('\n');
+    return text;
+}
+
+/// Writes the default config file (see `default_config()`) to `path`,
+/// with `settings` changed as in `save_settings()`, creating the folders
+/// it goes in. An existing file is never replaced.
+pub fn write_default_config(path: &Path, settings: &[(&str, Option<String>)]) -> Result<()> {
+    if path.exists() {
+        return Err(anyhow!("{} already exists.", path.to_string_lossy()));
+    }
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Could not create directory: {}", dir.to_string_lossy()))?;
+    }
+    let mut text = default_config();
+    for (key, value) in settings.iter() {
+        text = set_key(&text, key, value.as_deref())?;
+    }
+    return fs::write(path, text)
+        .with_context(|| format!("Could not write file: {}", path.to_string_lossy()));
+}
+
+/// Changes top-level settings in the config file at `path`, e.g.,
+/// `("theme", Some("\"gruvbox\""))`. Values are written as given, so
+/// strings need to be quoted. A value of None comments the setting out.
+/// Comments and the order of the file are kept as they are. The first
+/// time the file is changed while shellcaster is running, it is backed
+/// up, with a timestamp added to its name. The new file replaces the
+/// old one in one step, so it is never half-written, and nothing is
+/// written if the settings are unchanged. Returns the path to the
+/// backup, if one was made.
+pub fn save_settings(path: &Path, settings: &[(&str, Option<String>)]) -> Result<Option<PathBuf>> {
+    let old_text = match fs::read_to_string(path) {
+        Ok(text) => Some(text),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+        Err(err) => {
+            return Err(err)
+                .with_context(|| format!("Could not read file: {}", path.to_string_lossy()))
+        }
+    };
+
+    let mut text = old_text.clone().unwrap_or_default();
+    for (key, value) in settings.iter() {
+        text = set_key(&text, key, value.as_deref())?;
+    }
+    if old_text.as_ref() == Some(&text) {
+        return Ok(None);
+    }
+
+    let backup = match old_text {
+        Some(_) if BACKED_UP.lock().expect("Mutex error").insert(path.to_path_buf()) => {
+            Some(backup_file(path)?)
+        }
+        _ => None,
+    };
+    let tmp_path = path.with_extension("toml.tmp");
+    fs::write(&tmp_path, text)
+        .with_context(|| format!("Could not write file: {}", tmp_path.to_string_lossy()))?;
+    fs::rename(&tmp_path, path)
+        .with_context(|| format!("Could not write file: {}", path.to_string_lossy()))?;
+    return Ok(backup);
+}
+
+/// Copies the config file to a timestamped backup next to it, and
+/// deletes the oldest backups beyond `MAX_BACKUPS`. A number is added
+/// to the name if there is already a backup with the same timestamp,
+/// so none are overwritten.
+fn backup_file(path: &Path) -> Result<PathBuf> {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "config.toml".to_string());
+    let prefix = format!("{file_name}.bak-");
+    let stamp = chrono::Utc::now().format("%Y%m%d-%H%M%S-%3f");
+    let mut backup = path.with_file_name(format!("{prefix}{stamp}"));
+    let mut count = 0;
+    while backup.exists() {
+        count += 1;
+        backup = path.with_file_name(format!("{prefix}{stamp}-{count}"));
+    }
+    fs::copy(path, &backup)
+        .with_context(|| format!("Could not back up file: {}", path.to_string_lossy()))?;
+
+    // timestamps sort in the same order as the names
+    if let Some(dir) = path.parent().and_then(|dir| fs::read_dir(dir).ok()) {
+        let mut backups: Vec<PathBuf> = dir
+            .flatten()
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+            .map(|entry| entry.path())
+            .collect();
+        backups.sort();
+        let excess = backups.len().saturating_sub(MAX_BACKUPS);
+        for old in backups.into_iter().take(excess) {
+            let _ = fs::remove_file(old);
+        }
+    }
+    return Ok(backup);
+}
+
+/// Sets one top-level key in the text of a TOML file. An existing
+/// setting for the key is replaced, including every line of a value
+/// that is spread over several lines; otherwise the new line goes right
+/// after a commented-out example of the key (as in the default
+/// config.toml), or else before the first table. Returns an error if
+/// the text is not valid TOML, as it can't be changed safely then.
+fn set_key(text: &str, key: &str, value: Option<&str>) -> Result<String> {
+    let mut lines: Vec<String> = text.lines().map(|line| line.to_string()).collect();
+    let (settings, top_level) = top_level_settings(text)?;
+
+    let active = settings
+        .iter()
+        .find(|(name, _, _)| name == key)
+        .map(|(_, idx, len)| (*idx, *len));
+    match (active, value) {
+        (Some((idx, len)), Some(value)) => {
+            lines.splice(idx..idx + len, [format!("{key} = {value}")]);
+        }
+        (Some((idx, len)), None) => {
+            for line in lines[idx..idx + len].iter_mut() {
+                line.insert(0, '#');
+            }
+        }
+        (None, Some(value)) => {
+            let example = lines[..top_level].iter().rposition(|line| {
+                return line
+                    .trim_start()
+                    .strip_prefix('#')
+                    .and_then(line_key)
+                    .is_some_and(|k| k == key);
+            });
+            let new_line = format!("{key} = {value}");
+            match example {
+                Some(idx) => lines.insert(idx + 1, new_line),
+                None if top_level < lines.len() => {
+                    lines.insert(top_level, String::new());
+                    lines.insert(top_level, new_line);
+                }
+                None => lines.push(new_line),
+            }
+        }
+        (None, None) => (),
+    }
+
+    let mut new_text = lines.join("\n");
+    new_text.push('\n');
+    return Ok(new_text);
+}
+
+/// Finds the top-level settings in the text of a TOML file, as their
+/// key, the index of the line they start on and the number of lines
+/// they take up, along with the index of the line the first table
+/// starts on. The text is parsed with toml_edit, which keeps values as
+/// they were written, so values spread over several lines (e.g.,
+/// arrays or multi-line strings) are found as a whole.
+#[allow(clippy::type_complexity)]
+fn top_level_settings(text: &str) -> Result<(Vec<(String, usize, usize)>, usize)> {
+    let doc = text
+        .parse::<toml_edit::Document>()
+        .context("Could not parse config file")?;
+    let lines: Vec<&str> = text.lines().collect();
+    let mut settings = Vec::new();
+    let mut idx = 0;
+    while idx < lines.len() && !lines[idx].trim_start().starts_with('[') {
+        let value = line_key(lines[idx])
+            .and_then(|key| doc.get(key).and_then(|item| item.as_value()).map(|v| (key, v)));
+        match value {
+            Some((key, value)) => {
+                let len = value.to_string().matches('\n').count() + 1;
+                settings.push((key.to_string(), idx, len));
+                idx += len;
+            }
+            None => idx += 1,
+        }
+    }
+    return Ok((settings, idx));
+}
+
+/// Returns the key set on a line like `key = value`, if any.
+fn line_key(line: &str) -> Option<&str> {
+    let (key, _) = line.split_once('=')?;
+    let key = key.trim();
+    if key.is_empty() || key.contains(|c: char| c.is_whitespace() || c == '#') {
+        return None;
+    }
+    return Some(key);
+}
+
+
+// TESTS -----------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "# The theme to use.\n#theme = \"gruvbox\"\n\nmax_retries = 3 # retries\n\n[keybindings]\ntheme = [ \"t\" ]\n";
+
+    #[test]
+    fn replace_existing() {
+        let text = set_key(CONFIG, "max_retries", Some("5")).unwrap();
+        assert!(text.contains("\nmax_retries = 5\n"));
+        assert!(text.starts_with("# The theme to use.\n"));
+    }
+
+    #[test]
+    fn after_example() {
+        let text = set_key(CONFIG, "theme", Some("\"nord\"")).unwrap();
+        assert!(text.contains("#theme = \"gruvbox\"\ntheme = \"nord\"\n"));
+        // keys in tables are left alone
+        assert!(text.ends_with("[keybindings]\ntheme = [ \"t\" ]\n"));
+    }
+
+    #[test]
+    fn new_and_removed_keys() {
+        let text = set_key(CONFIG, "play_command", Some("\"mpv\"")).unwrap();
+        assert!(text.contains("play_command = \"mpv\"\n\n[keybindings]"));
+
+        let text = set_key(&text, "play_command", None).unwrap();
+        assert!(text.contains("#play_command = \"mpv\"\n"));
+        let parsed: toml::Value = toml::from_str(&text).unwrap();
+        assert!(parsed.get("play_command").is_none());
+    }
+
+    #[test]
+    fn multi_line_values() {
+        let config = "feeds = [\n  \"a\",\n  [1, 2],\n]\nmax_retries = 3\n\n[colors]\nnormal = 1\n";
+        let text = set_key(config, "feeds", Some("[]")).unwrap();
+        assert_eq!(text, "feeds = []\nmax_retries = 3\n\n[colors]\nnormal = 1\n");
+
+        let text = set_key(config, "feeds", None).unwrap();
+        let parsed: toml::Value = toml::from_str(&text).unwrap();
+        assert!(parsed.get("feeds").is_none());
+        assert_eq!(parsed.get("max_retries"), Some(&toml::Value::Integer(3)));
+
+        assert!(set_key("feeds = [", "feeds", None).is_err());
+    }
+
+    #[test]
+    fn save_with_backup() {
+        let dir = std::env::temp_dir().join(format!("shellcaster-config-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        fs::write(&path, CONFIG).unwrap();
+
+        let backup = save_settings(&path, &[("theme", Some("\"nord\"".to_string()))])
+            .unwrap()
+            .unwrap();
+        assert_eq!(fs::read_to_string(&backup).unwrap(), CONFIG);
+        let saved = fs::read_to_string(&path).unwrap();
+        assert!(saved.contains("theme = \"nord\""));
+
+        // later changes don't replace the backup with another one
+        let again = save_settings(&path, &[("theme", Some("\"dark\"".to_string()))]).unwrap();
+        assert_eq!(again, None);
+        assert_eq!(fs::read_to_string(&backup).unwrap(), CONFIG);
+        assert!(fs::read_to_string(&path).unwrap().contains("theme = \"dark\""));
+
+        // backups made within the same second don't overwrite each other
+        let first = backup_file(&path).unwrap();
+        let second = backup_file(&path).unwrap();
+        assert_ne!(first, second);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn default_config_matches_defaults() {
+        use crate::config::{AppColorsFromToml, KeybindingsFromToml};
+        use std::collections::BTreeMap;
+
+        #[derive(serde::Deserialize)]
+        struct Sections {
+            keybindings: KeybindingsFromToml,
+            colors: AppColorsFromToml,
+        }
+
+        let sections: Sections = toml::from_str(&default_config()).unwrap();
+        let keymap = Keybindings::from_config(sections.keybindings, BTreeMap::new()).unwrap();
+        assert_eq!(keymap.to_toml(), Keybindings::default().to_toml());
+
+        let mut colors = AppColors::default();
+        colors.add_from_config(sections.colors);
+        assert_eq!(colors.settings(), AppColors::default().settings());
+    }
+}
@@ -0,0 +1,342 @@
+use std::cmp::min;
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+
+use anyhow::{anyhow, Context, Result};
+use image::imageops::FilterType;
+use image::DynamicImage;
+
+use crate::encoding::base64_encode;
+use crate::http;
+use crate::threadpool::Threadpool;
+use crate::types::Message;
+
+/// Largest image file that will be downloaded, in bytes.
+const MAX_IMAGE_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Approximate size of a terminal cell in pixels (width, height). There
+/// is no portable way to ask the terminal for this, so images are
+/// scaled assuming cells of this size.
+const CELL_SIZE: (u32, u32) = (10, 20);
+
+/// Maximum number of base64 bytes sent in one kitty graphics command.
+const KITTY_CHUNK_SIZE: usize = 4096;
+
+/// Identifies the user's selection for whether to show cover art in the
+/// details panel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoverArt {
+    Off,
+    Auto,
+    Kitty,
+    Sixel,
+}
+
+/// Graphics protocols that can be used to draw images in the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageProtocol {
+    Kitty,
+    Sixel,
+}
+
+impl CoverArt {
+    /// Returns the graphics protocol to use, if any. With "auto", this
+    /// checks whether the terminal is known to support one of them.
+    pub fn protocol(&self) -> Option<ImageProtocol> {
+        return match self {
+            CoverArt::Off => None,
+            CoverArt::Kitty => Some(ImageProtocol::Kitty),
+            CoverArt::Sixel => Some(ImageProtocol::Sixel),
+            CoverArt::Auto => detect_protocol(|var| std::env::var(var).ok()),
+        };
+    }
+}
+
+/// Enum used for communicating back to the main controller once an
+/// image has been fetched (the String is the URL of the image), or
+/// rendered at the given size (None if it could not be decoded).
+#[derive(Debug)]
+pub enum ImageMsg {
+    Cached(String),
+    Error(String),
+    Rendered(PathBuf, u16, u16, Option<String>),
+}
+
+/// Guesses from environment variables whether the terminal supports the
+/// kitty graphics protocol or sixel images. Terminals can't be queried
+/// for this without reading their response from stdin, which would get
+/// in the way of the UI's own input handling.
+fn detect_protocol<F>(get_var: F) -> Option<ImageProtocol>
+where F: Fn(&str) -> Option<String> {
+    let term = get_var("TERM").unwrap_or_default();
+    let term_program = get_var("TERM_PROGRAM").unwrap_or_default();
+
+    if get_var("KITTY_WINDOW_ID").is_some()
+        || term == "xterm-kitty"
+        || term == "xterm-ghostty"
+        || term_program == "ghostty"
+    {
+        return Some(ImageProtocol::Kitty);
+    }
+    if term.starts_with("foot")
+        || term.starts_with("mlterm")
+        || term.contains("sixel")
+        || term_program == "WezTerm"
+        || term_program == "iTerm.app"
+    {
+        return Some(ImageProtocol::Sixel);
+    }
+    return None;
+}
+
+/// Returns the path where the image at `url` is cached. The file name
+/// is a hash of the URL, so the same image is only downloaded once.
+pub fn cache_path(cache_dir: &Path, url: &str) -> PathBuf {
+    // FNV-1a, which (unlike the std hasher) is stable across Rust
+    // versions, so cached files stay valid after upgrading
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in url.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    let mut path = cache_dir.to_path_buf();
+    path.push(format!("{hash:016x}"));
+    return path;
+}
+
+/// Downloads an image into the image cache on a separate thread, and
+/// lets the main controller know when it is finished.
+pub fn fetch_image(
+    url: String,
+    cache_dir: &Path,
+    max_retries: usize,
+    threadpool: &Threadpool,
+    tx_to_main: Sender<Message>,
+) {
+    let path = cache_path(cache_dir, &url);
+    threadpool.execute(move || {
+        let msg = match download_image(&url, &path, max_retries) {
+            Ok(_) => ImageMsg::Cached(url),
+            Err(_) => ImageMsg::Error(url),
+        };
+        tx_to_main
+            .send(Message::Image(msg))
+            .expect("Thread messaging error");
+    });
+}
+
+/// Renders a cached image on a separate thread, as decoding and scaling
+/// it is too slow to do while drawing the screen, and lets the main
+/// controller know when it is finished.
+pub fn render_image(
+    path: PathBuf,
+    protocol: ImageProtocol,
+    cols: u16,
+    rows: u16,
+    threadpool: &Threadpool,
+    tx_to_main: Sender<Message>,
+) {
+    threadpool.execute(move || {
+        let art = render(&path, protocol, cols, rows).ok();
+        tx_to_main
+            .send(Message::Image(ImageMsg::Rendered(path, cols, rows, art)))
+            .expect("Thread messaging error");
+    });
+}
+
+/// Downloads an image and saves it to `path`, after checking that it
+/// is an image format that can be displayed.
+fn download_image(url: &str, path: &Path, max_retries: usize) -> Result<()> {
+    let response = http::get(url, max_retries)
+        .map_err(|err| anyhow!("Could not fetch image: {err:#}"))?;
+
+    let mut bytes = Vec::new();
+    response
+        .take(MAX_IMAGE_SIZE + 1)
+        .read_to_end(&mut bytes)?;
+    if bytes.len() as u64 > MAX_IMAGE_SIZE {
+        return Err(anyhow!("Image is too large"));
+    }
+    image::load_from_memory(&bytes).with_context(|| "Not a supported image format")?;
+
+    // write to a temporary file first, so a partly-written file is
+    // never mistaken for a cached image
+    let tmp_path = path.with_extension("part");
+    fs::write(&tmp_path, &bytes)?;
+    fs::rename(&tmp_path, path)?;
+    return Ok(());
+}
+
+/// Loads a cached image and converts it to the escape sequence that
+/// draws it in the terminal, scaled to fit within `cols` x `rows`
+/// cells. The image is drawn at the current cursor position.
+fn render(path: &Path, protocol: ImageProtocol, cols: u16, rows: u16) -> Result<String> {
+    let img = image::io::Reader::open(path)?
+        .with_guessed_format()?
+        .decode()?;
+    let img = img.resize(
+        cols as u32 * CELL_SIZE.0,
+        rows as u32 * CELL_SIZE.1,
+        FilterType::Triangle,
+    );
+    return Ok(match protocol {
+        ImageProtocol::Kitty => kitty_sequence(&img),
+        ImageProtocol::Sixel => sixel_sequence(&img),
+    });
+}
+
+/// Returns the escape sequence that removes all images drawn with the
+/// kitty graphics protocol. Sixel images are simply overwritten by any
+/// text drawn over them, so they need no equivalent.
+pub fn clear_sequence(protocol: ImageProtocol) -> &'static str {
+    return match protocol {
+        ImageProtocol::Kitty => "\x1b_Ga=d,q=2\x1b\\",
+        ImageProtocol::Sixel => "",
+    };
+}
+
+/// Encodes an image using the kitty graphics protocol, sending the raw
+/// RGBA data in chunks. The image is placed over a number of cells
+/// matching its size, without moving the cursor, and the terminal is
+/// asked not to reply (replies would show up as keyboard input).
+fn kitty_sequence(img: &DynamicImage) -> String {
+    let rgba = img.to_rgba8();
+    let (width, height) = rgba.dimensions();
+    let cols = width.div_ceil(CELL_SIZE.0);
+    let rows = height.div_ceil(CELL_SIZE.1);
+    let payload = base64_encode(rgba.as_raw());
+
+    let mut out = String::new();
+    let chunks: Vec<&[u8]> = payload.as_bytes().chunks(KITTY_CHUNK_SIZE).collect();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = if i < chunks.len() - 1 { 1 } else { 0 };
+        let chunk = String::from_utf8_lossy(chunk);
+        if i == 0 {
+            out.push_str(&format!(
+                "\x1b_Ga=T,f=32,s={width},v={height},c={cols},r={rows},C=1,q=2,m={more};{chunk}\x1b\\"
+            ));
+        } else {
+            out.push_str(&format!("\x1b_Gm={more};{chunk}\x1b\\"));
+        }
+    }
+    return out;
+}
+
+/// Encodes an image as sixel data. Colors are reduced to a 6x6x6 color
+/// cube, which every sixel terminal supports and is good enough for
+/// small cover images.
+fn sixel_sequence(img: &DynamicImage) -> String {
+    let rgb = img.to_rgb8();
+    let (width, height) = rgb.dimensions();
+    let level = |c: u8| (c as usize * 5 + 127) / 255;
+    let pixels: Vec<usize> = rgb
+        .pixels()
+        .map(|p| level(p[0]) * 36 + level(p[1]) * 6 + level(p[2]))
+        .collect();
+
+    let mut out = format!("\x1bPq\"1;1;{width};{height}");
+    for i in 0..216 {
+        out.push_str(&format!(
+            "#{i};2;{};{};{}",
+            i / 36 * 20,
+            i / 6 % 6 * 20,
+            i % 6 * 20
+        ));
+    }
+
+    let width = width as usize;
+    let height = height as usize;
+    for band in (0..height).step_by(6) {
+        let band_height = min(6, height - band);
+        let colors: BTreeSet<usize> = pixels[band * width..(band + band_height) * width]
+            .iter()
+            .cloned()
+            .collect();
+        for color in colors {
+            let sixels: Vec<u8> = (0..width)
+                .map(|x| {
+                    let mut bits = 0;
+                    for dy in 0..band_height {
+                        if pixels[(band + dy) * width + x] == color {
+                            bits |= 1 << dy;
+                        }
+                    }
+                    bits + 63
+                })
+                .collect();
+            out.push_str(&format!("#{color}{}$", run_length_encode(&sixels)));
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\");
+    return out;
+}
+
+/// Compresses repeated sixel characters using the "!<count><char>"
+/// syntax.
+fn run_length_encode(sixels: &[u8]) -> String {
+    let mut out = String::new();
+    let mut i = 0;
+    while i < sixels.len() {
+        let mut run = 1;
+        while i + run < sixels.len() && sixels[i + run] == sixels[i] {
+            run += 1;
+        }
+        let ch = sixels[i] as char;
+        if run > 3 {
+            out.push_str(&format!("!{run}{ch}"));
+        } else {
+            for _ in 0..run {
+                out.push(ch);
+            }
+        }
+        i += run;
+    }
+    return out;
+}
+
+
+// TESTS -----------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_kitty() {
+        let protocol = detect_protocol(|var| match var {
+            "TERM" => Some("xterm-kitty".to_string()),
+            _ => None,
+        });
+        assert_eq!(protocol, Some(ImageProtocol::Kitty));
+    }
+
+    #[test]
+    fn detect_none() {
+        let protocol = detect_protocol(|var| match var {
+            "TERM" => Some("xterm-256color".to_string()),
+            _ => None,
+        });
+        assert_eq!(protocol, None);
+    }
+
+    #[test]
+    fn sixel_run_length() {
+        assert_eq!(run_length_encode(b"~~~~~??@"), "!5~??@");
+    }
+
+    #[test]
+    fn sixel_single_color() {
+        let img = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(
+            4,
+            6,
+            image::Rgb([255, 0, 0]),
+        ));
+        let sixel = sixel_sequence(&img);
+        assert!(sixel.starts_with("\x1bPq\"1;1;4;6"));
+        // red is color 180 in the 6x6x6 cube; all six rows filled
+        assert!(sixel.ends_with("#180!4~$-\x1b\\"));
+    }
+}
@@ -8,6 +8,9 @@ use regex::Regex;
 use rusqlite::{params, Connection};
 use semver::Version;
 
+use crate::feeds;
+use crate::scrobble::Listen;
+use crate::secrets;
 use crate::types::*;
 
 lazy_static! {
@@ -17,9 +20,67 @@ lazy_static! {
 }
 
 
+/// Results of syncing a podcast. `rereleased` lists the downloaded
+/// episodes whose feed now points to a different file, which should be
+/// downloaded again.
 pub struct SyncResult {
     pub added: Vec<NewEpisode>,
     pub updated: Vec<i64>,
+    pub rereleased: Vec<i64>,
+}
+
+/// What syncing a podcast would change, found without changing
+/// anything: the titles of new episodes, and the old and new titles of
+/// episodes that were renamed. `updated` counts the other episodes that
+/// would be updated (e.g., with a new description or audio file).
+#[derive(Debug, Default)]
+pub struct SyncPreview {
+    pub added: Vec<String>,
+    pub retitled: Vec<(String, String)>,
+    pub updated: usize,
+}
+
+impl SyncPreview {
+    /// Returns lines describing the changes for a report: a summary
+    /// starting with the podcast's title, followed by each new and
+    /// renamed episode.
+    pub fn report(&self, pod_title: &str) -> Vec<String> {
+        let count = |n: usize, what: &str| {
+            let plural = if n == 1 { "" } else { "s" };
+            return format!("{n} {what}{plural}");
+        };
+        let mut parts = Vec::new();
+        if !self.added.is_empty() {
+            parts.push(count(self.added.len(), "new episode"));
+        }
+        if !self.retitled.is_empty() {
+            parts.push(count(self.retitled.len(), "new title"));
+        }
+        if self.updated > 0 {
+            parts.push(count(self.updated, "other update"));
+        }
+        if parts.is_empty() {
+            return vec![format!("{pod_title}: no changes")];
+        }
+
+        let mut lines = vec![format!("{pod_title}: {}", parts.join(", "))];
+        for title in self.added.iter() {
+            lines.push(format!("  + {title}"));
+        }
+        for (old, new) in self.retitled.iter() {
+            lines.push(format!("  ~ {old} -> {new}"));
+        }
+        return lines;
+    }
+}
+
+/// Results of database maintenance. `problems` lists anything the
+/// integrity check found; if there are any, the database is left as
+/// is rather than being rebuilt.
+pub struct MaintenanceReport {
+    pub problems: Vec<String>,
+    pub size_before: u64,
+    pub size_after: u64,
 }
 
 /// Struct holding a sqlite database connection, with methods to interact
@@ -28,6 +89,7 @@ pub struct SyncResult {
 pub struct Database {
     path: PathBuf,
     conn: Option<Connection>,
+    keyring_id: String,
 }
 
 impl Database {
@@ -39,9 +101,10 @@ impl Database {
             .with_context(|| "Unable to create subdirectory for database.")?;
         db_path.push("data.db");
         let conn = Connection::open(&db_path)?;
-        let db_conn = Database {
+        let mut db_conn = Database {
             path: db_path,
             conn: Some(conn),
+            keyring_id: String::new(),
         };
         db_conn.create()?;
 
@@ -81,11 +144,112 @@ impl Database {
                 }
                 Err(_) => db_conn.update_version(curr_ver, false)?,
             }
+
+            // columns added since the last version bump; these are
+            // checked directly, as the version number alone cannot tell
+            // us whether they exist yet
+            add_column_if_missing(conn, "podcasts", "notify", "INTEGER NOT NULL DEFAULT 1")?;
+            add_column_if_missing(conn, "podcasts", "image_url", "TEXT")?;
+            add_column_if_missing(conn, "podcasts", "include_pattern", "TEXT")?;
+            add_column_if_missing(conn, "podcasts", "exclude_pattern", "TEXT")?;
+            add_column_if_missing(conn, "podcasts", "download_dir", "TEXT")?;
+            add_column_if_missing(conn, "podcasts", "play_command", "TEXT")?;
+            add_column_if_missing(conn, "podcasts", "auto_download", "INTEGER")?;
+            add_column_if_missing(conn, "podcasts", "keep_downloads", "INTEGER")?;
+            add_column_if_missing(conn, "podcasts", "preferred_enclosure", "TEXT")?;
+            add_column_if_missing(conn, "podcasts", "auth_user", "TEXT")?;
+            add_column_if_missing(conn, "podcasts", "auth_password", "TEXT")?;
+            add_column_if_missing(conn, "podcasts", "auth_headers", "TEXT")?;
+            add_column_if_missing(
+                conn,
+                "podcasts",
+                "auth_in_keyring",
+                "INTEGER NOT NULL DEFAULT 0",
+            )?;
+            add_column_if_missing(
+                conn,
+                "podcasts",
+                "accept_invalid_certs",
+                "INTEGER NOT NULL DEFAULT 0",
+            )?;
+            add_column_if_missing(conn, "podcasts", "etag", "TEXT")?;
+            add_column_if_missing(conn, "podcasts", "last_modified", "TEXT")?;
+            add_column_if_missing(conn, "podcasts", "sync_failures", "INTEGER NOT NULL DEFAULT 0")?;
+            add_column_if_missing(conn, "podcasts", "archived", "INTEGER NOT NULL DEFAULT 0")?;
+            add_column_if_missing(conn, "podcasts", "custom_title", "TEXT")?;
+            add_column_if_missing(conn, "podcasts", "group_name", "TEXT")?;
+            add_column_if_missing(conn, "podcasts", "title_pattern", "TEXT")?;
+            add_column_if_missing(conn, "podcasts", "title_replacement", "TEXT")?;
+            add_column_if_missing(conn, "podcasts", "show_pattern", "TEXT")?;
+            add_column_if_missing(conn, "podcasts", "hide_pattern", "TEXT")?;
+            add_column_if_missing(conn, "podcasts", "categories", "TEXT")?;
+            add_column_if_missing(conn, "podcasts", "language", "TEXT")?;
+            add_column_if_missing(conn, "podcasts", "website", "TEXT")?;
+            add_column_if_missing(conn, "podcasts", "funding_url", "TEXT")?;
+            add_column_if_missing(conn, "podcasts", "funding_text", "TEXT")?;
+            add_column_if_missing(conn, "podcasts", "ttl", "INTEGER")?;
+            add_column_if_missing(conn, "podcasts", "skip_hours", "TEXT")?;
+            add_column_if_missing(conn, "podcasts", "skip_days", "TEXT")?;
+            add_column_if_missing(conn, "episodes", "image_url", "TEXT")?;
+            add_column_if_missing(conn, "episodes", "chapters_url", "TEXT")?;
+            add_column_if_missing(conn, "episodes", "transcript_url", "TEXT")?;
+            add_column_if_missing(conn, "episodes", "transcript_type", "TEXT")?;
+            add_column_if_missing(conn, "episodes", "enclosures", "TEXT")?;
+            add_column_if_missing(conn, "episodes", "enclosure_error", "TEXT")?;
+            add_column_if_missing(conn, "episodes", "added", "INTEGER")?;
+            add_column_if_missing(conn, "episodes", "updated", "INTEGER NOT NULL DEFAULT 0")?;
+            add_column_if_missing(conn, "episodes", "starred", "INTEGER NOT NULL DEFAULT 0")?;
+            add_column_if_missing(conn, "episodes", "position", "INTEGER")?;
+            add_column_if_missing(conn, "episodes", "last_played", "INTEGER")?;
+            add_column_if_missing(conn, "episodes", "new", "INTEGER NOT NULL DEFAULT 0")?;
+            add_column_if_missing(conn, "version", "last_maintenance", "INTEGER")?;
+            add_column_if_missing(conn, "version", "last_update_check", "INTEGER")?;
+            add_column_if_missing(conn, "version", "update_notified", "TEXT")?;
+            add_column_if_missing(conn, "version", "last_run", "TEXT")?;
+            add_column_if_missing(conn, "version", "filters", "TEXT")?;
+            add_column_if_missing(conn, "version", "keyring_id", "TEXT")?;
         }
+        db_conn.keyring_id = db_conn.load_keyring_id()?;
 
         return Ok(db_conn);
     }
 
+    /// Reads the id that the names of this database's feed passwords
+    /// in the keyring start with, making one up the first time. Any
+    /// passwords kept under the names used before there was an id are
+    /// moved to the new names then.
+    fn load_keyring_id(&self) -> Result<String> {
+        let conn = self.conn.as_ref().expect("Error connecting to database.");
+        let id: Option<String> =
+            conn.query_row("SELECT keyring_id FROM version WHERE id = 1;", params![], |row| {
+                row.get(0)
+            })?;
+        if let Some(id) = id {
+            return Ok(id);
+        }
+
+        let id = secrets::new_database_id();
+        conn.execute("UPDATE version SET keyring_id = ? WHERE id = 1;", params![id])?;
+        let mut stmt = conn.prepare("SELECT id FROM podcasts WHERE auth_in_keyring = 1;")?;
+        let in_keyring: Vec<i64> =
+            stmt.query_map(params![], |row| row.get(0))?.flatten().collect();
+        for pod_id in in_keyring {
+            let old_name = secrets::old_feed_password(pod_id);
+            if let Some(password) = secrets::get(&old_name) {
+                if secrets::set(&secrets::feed_password(&id, pod_id), &password).is_ok() {
+                    let _ = secrets::delete(&old_name);
+                }
+            }
+        }
+        return Ok(id);
+    }
+
+    /// Returns the name of the keyring entry for the password of a
+    /// private feed.
+    pub fn feed_password_name(&self, podcast_id: i64) -> String {
+        return secrets::feed_password(&self.keyring_id, podcast_id);
+    }
+
     /// Creates the necessary database tables, if they do not already
     /// exist. Panics if database cannot be accessed, or if tables cannot
     /// be created.
@@ -101,7 +265,22 @@ impl Database {
                 description TEXT,
                 author TEXT,
                 explicit INTEGER,
-                last_checked INTEGER
+                last_checked INTEGER,
+                notify INTEGER NOT NULL DEFAULT 1,
+                image_url TEXT,
+                include_pattern TEXT,
+                exclude_pattern TEXT,
+                download_dir TEXT,
+                play_command TEXT,
+                auto_download INTEGER,
+                keep_downloads INTEGER,
+                sync_failures INTEGER NOT NULL DEFAULT 0,
+                archived INTEGER NOT NULL DEFAULT 0,
+                custom_title TEXT,
+                title_pattern TEXT,
+                title_replacement TEXT,
+                show_pattern TEXT,
+                hide_pattern TEXT
             );",
             params![],
         )
@@ -120,6 +299,16 @@ impl Database {
                 duration INTEGER,
                 played INTEGER,
                 hidden INTEGER,
+                image_url TEXT,
+                chapters_url TEXT,
+                transcript_url TEXT,
+                transcript_type TEXT,
+                added INTEGER,
+                updated INTEGER NOT NULL DEFAULT 0,
+                starred INTEGER NOT NULL DEFAULT 0,
+                position INTEGER,
+                last_played INTEGER,
+                new INTEGER NOT NULL DEFAULT 0,
                 FOREIGN KEY(podcast_id) REFERENCES podcasts(id) ON DELETE CASCADE
             );",
             params![],
@@ -138,6 +327,43 @@ impl Database {
         )
         .with_context(|| "Could not create files database table")?;
 
+        // create chapters table
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS chapters (
+                id INTEGER PRIMARY KEY NOT NULL,
+                episode_id INTEGER NOT NULL,
+                start REAL NOT NULL,
+                title TEXT NOT NULL,
+                FOREIGN KEY (episode_id) REFERENCES episodes(id) ON DELETE CASCADE
+            );",
+            params![],
+        )
+        .with_context(|| "Could not create chapters database table")?;
+
+        // create table of listens waiting to be submitted to
+        // ListenBrainz
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS scrobbles (
+                id INTEGER PRIMARY KEY NOT NULL,
+                listened_at INTEGER NOT NULL,
+                podcast TEXT NOT NULL,
+                episode TEXT NOT NULL
+            );",
+            params![],
+        )
+        .with_context(|| "Could not create scrobbles database table")?;
+
+        // create table holding the play queue, in order
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS queue (
+                episode_id INTEGER PRIMARY KEY NOT NULL,
+                position INTEGER NOT NULL,
+                FOREIGN KEY (episode_id) REFERENCES episodes(id) ON DELETE CASCADE
+            );",
+            params![],
+        )
+        .with_context(|| "Could not create queue database table")?;
+
         conn.execute(
             "CREATE TABLE IF NOT EXISTS version (
                 id INTEGER PRIMARY KEY NOT NULL,
@@ -180,8 +406,10 @@ impl Database {
         {
             let mut stmt = tx.prepare_cached(
                 "INSERT INTO podcasts (title, url, description, author,
-                explicit, last_checked)
-                VALUES (?, ?, ?, ?, ?, ?);",
+                explicit, last_checked, image_url, categories, language,
+                website, funding_url, funding_text, etag, last_modified, ttl,
+                skip_hours, skip_days)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?);",
             )?;
             stmt.execute(params![
                 podcast.title,
@@ -189,7 +417,18 @@ impl Database {
                 podcast.description,
                 podcast.author,
                 podcast.explicit,
-                podcast.last_checked.timestamp()
+                podcast.last_checked.timestamp(),
+                podcast.image_url,
+                join_lines(&podcast.categories),
+                podcast.language,
+                podcast.website,
+                podcast.funding_url,
+                podcast.funding_text,
+                podcast.validators.etag,
+                podcast.validators.last_modified,
+                podcast.schedule.ttl,
+                join_lines(&podcast.schedule.skip_hours),
+                join_lines(&podcast.schedule.skip_days),
             ])?;
         }
 
@@ -200,12 +439,13 @@ impl Database {
         }
         let mut ep_ids = Vec::new();
         for ep in podcast.episodes.iter().rev() {
-            let id = self.insert_episode(&tx, pod_id, ep)?;
+            let id = self.insert_episode(&tx, pod_id, ep, false)?;
             let new_ep = NewEpisode {
                 id: id,
                 pod_id: pod_id,
                 title: ep.title.clone(),
                 pod_title: podcast.title.clone(),
+                pubdate: ep.pubdate,
                 selected: false,
             };
             ep_ids.push(new_ep);
@@ -215,22 +455,28 @@ impl Database {
         return Ok(SyncResult {
             added: ep_ids,
             updated: Vec::new(),
+            rereleased: Vec::new(),
         });
     }
 
-    /// Inserts a podcast episode into the database.
+    /// Inserts a podcast episode into the database. Episodes found when
+    /// syncing a podcast are flagged as `new`, so they show up in the
+    /// inbox.
     pub fn insert_episode(
         &self,
         conn: &Connection,
         podcast_id: i64,
         episode: &EpisodeNoId,
+        new: bool,
     ) -> Result<i64> {
         let pubdate = episode.pubdate.map(|dt| dt.timestamp());
 
         let mut stmt = conn.prepare_cached(
             "INSERT INTO episodes (podcast_id, title, url, guid,
-                description, pubdate, duration, played, hidden)
-                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?);",
+                description, pubdate, duration, played, hidden, image_url,
+                chapters_url, transcript_url, transcript_type, enclosures,
+                enclosure_error, added, new)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?);",
         )?;
         stmt.execute(params![
             podcast_id,
@@ -242,6 +488,14 @@ impl Database {
             episode.duration,
             false,
             false,
+            episode.image_url,
+            episode.chapters_url,
+            episode.transcript_url,
+            episode.transcript_type,
+            enclosures_to_json(&episode.enclosures),
+            episode.enclosure_error,
+            Utc::now().timestamp(),
+            new,
         ])?;
         return Ok(conn.last_insert_rowid());
     }
@@ -258,6 +512,26 @@ impl Database {
         return Ok(());
     }
 
+    /// Replaces the stored chapters for an episode.
+    pub fn set_chapters(&self, episode_id: i64, chapters: &[Chapter]) -> Result<()> {
+        let mut conn = Connection::open(&self.path).expect("Error connecting to database.");
+        let tx = conn.transaction()?;
+        {
+            let mut stmt = tx.prepare_cached("DELETE FROM chapters WHERE episode_id = ?;")?;
+            stmt.execute(params![episode_id])?;
+
+            let mut stmt = tx.prepare_cached(
+                "INSERT INTO chapters (episode_id, start, title)
+                    VALUES (?, ?, ?);",
+            )?;
+            for chapter in chapters.iter() {
+                stmt.execute(params![episode_id, chapter.start, chapter.title])?;
+            }
+        }
+        tx.commit()?;
+        return Ok(());
+    }
+
     /// Removes a file listing for an episode from the database when the
     /// user has chosen to delete the file.
     pub fn remove_file(&self, episode_id: i64) -> Result<()> {
@@ -283,6 +557,13 @@ impl Database {
     /// Removes a podcast, all episodes, and files from the database.
     pub fn remove_podcast(&self, podcast_id: i64) -> Result<()> {
         let conn = self.conn.as_ref().expect("Error connecting to database.");
+        // a password in the keyring would otherwise be left behind
+        let mut stmt =
+            conn.prepare_cached("SELECT auth_in_keyring FROM podcasts WHERE id = ?;")?;
+        let in_keyring: bool = stmt.query_row(params![podcast_id], |row| row.get(0))?;
+        if in_keyring {
+            let _ = secrets::delete(&self.feed_password_name(podcast_id));
+        }
         // Note: Because of the foreign key constraints on `episodes`
         // and `files` tables, all associated episodes for this podcast
         // will also be deleted, and all associated file entries for
@@ -300,7 +581,10 @@ impl Database {
             let conn = self.conn.as_ref().expect("Error connecting to database.");
             let mut stmt = conn.prepare_cached(
                 "UPDATE podcasts SET title = ?, url = ?, description = ?,
-            author = ?, explicit = ?, last_checked = ?
+            author = ?, explicit = ?, last_checked = ?, image_url = ?,
+            categories = ?, language = ?, website = ?, funding_url = ?, funding_text = ?,
+            etag = ?, last_modified = ?, ttl = ?, skip_hours = ?, skip_days = ?,
+            sync_failures = 0
             WHERE id = ?;",
             )?;
             stmt.execute(params![
@@ -310,6 +594,17 @@ impl Database {
                 podcast.author,
                 podcast.explicit,
                 podcast.last_checked.timestamp(),
+                podcast.image_url,
+                join_lines(&podcast.categories),
+                podcast.language,
+                podcast.website,
+                podcast.funding_url,
+                podcast.funding_text,
+                podcast.validators.etag,
+                podcast.validators.last_modified,
+                podcast.schedule.ttl,
+                join_lines(&podcast.schedule.skip_hours),
+                join_lines(&podcast.schedule.skip_days),
                 pod_id,
             ])?;
         }
@@ -318,6 +613,30 @@ impl Database {
         return Ok(result);
     }
 
+    /// Records that a podcast was just synced, for a feed that had not
+    /// changed since the last sync.
+    pub fn set_last_checked(&self, pod_id: i64) -> Result<()> {
+        let conn = self.conn.as_ref().expect("Error connecting to database.");
+        let mut stmt = conn.prepare_cached(
+            "UPDATE podcasts SET last_checked = ?, sync_failures = 0 WHERE id = ?;",
+        )?;
+        stmt.execute(params![Utc::now().timestamp(), pod_id])?;
+        return Ok(());
+    }
+
+    /// Records that syncing a podcast failed, returning how many syncs
+    /// in a row have now failed.
+    pub fn record_sync_failure(&self, pod_id: i64) -> Result<i64> {
+        let conn = self.conn.as_ref().expect("Error connecting to database.");
+        conn.execute(
+            "UPDATE podcasts SET sync_failures = sync_failures + 1 WHERE id = ?;",
+            params![pod_id],
+        )?;
+        let mut stmt = conn.prepare_cached("SELECT sync_failures FROM podcasts WHERE id = ?;")?;
+        let failures = stmt.query_row(params![pod_id], |row| row.get(0))?;
+        return Ok(failures);
+    }
+
     /// Updates metadata about episodes that already exist in database,
     /// or inserts new episodes.
     ///
@@ -326,6 +645,10 @@ impl Database {
     /// episode that has changed either of these fields will show up as
     /// a "new" episode. The old version will still remain in the
     /// database.
+    ///
+    /// A downloaded episode whose audio file has changed (see
+    /// `is_rerelease()`) is flagged as updated, as the feed has most
+    /// likely re-released it with a fixed audio file.
     fn update_episodes(
         &self,
         podcast_id: i64,
@@ -333,55 +656,24 @@ impl Database {
         episodes: Vec<EpisodeNoId>,
     ) -> Result<SyncResult> {
         let old_episodes = self.get_episodes(podcast_id, true)?;
-        let mut old_ep_map = AHashMap::new();
-        for ep in old_episodes.iter() {
-            if !ep.guid.is_empty() {
-                old_ep_map.insert(ep.guid.clone(), ep.clone());
-            }
-        }
+        let old_ep_map = map_by_guid(&old_episodes);
 
         let mut conn = Connection::open(&self.path).expect("Error connecting to database.");
         let tx = conn.transaction()?;
 
         let mut insert_ep = Vec::new();
         let mut update_ep = Vec::new();
+        let mut rereleased_ep = Vec::new();
         for new_ep in episodes.iter().rev() {
             let new_pd = new_ep.pubdate.map(|dt| dt.timestamp());
 
             let mut existing_id = None;
             let mut update = false;
-
-            // primary matching mechanism: check guid to see if it
-            // already exists in database
-            if !new_ep.guid.is_empty() {
-                if let Some(old_ep) = old_ep_map.get(&new_ep.guid) {
-                    existing_id = Some(old_ep.id);
-                    update = self.check_for_updates(old_ep, new_ep);
-                }
-            }
-
-            // fallback matching: for each existing episode, check the
-            // title, url, and pubdate -- if two of the three match, we
-            // count it as an existing episode; otherwise, we add it as
-            // a new episode
-            if existing_id.is_none() {
-                for old_ep in old_episodes.iter().rev() {
-                    let mut matching = 0;
-                    matching += (new_ep.title == old_ep.title) as i32;
-                    matching += (new_ep.url == old_ep.url) as i32;
-
-                    if let Some(pd) = new_pd {
-                        if let Some(old_pd) = old_ep.pubdate {
-                            matching += (pd == old_pd.timestamp()) as i32;
-                        }
-                    }
-
-                    if matching >= 2 {
-                        existing_id = Some(old_ep.id);
-                        update = self.check_for_updates(old_ep, new_ep);
-                        break;
-                    }
-                }
+            let mut rereleased = false;
+            if let Some(old_ep) = find_existing(&old_episodes, &old_ep_map, new_ep) {
+                existing_id = Some(old_ep.id);
+                update = self.check_for_updates(old_ep, new_ep);
+                rereleased = is_rerelease(old_ep, new_ep);
             }
 
             match existing_id {
@@ -390,7 +682,11 @@ impl Database {
                         let mut stmt = tx.prepare_cached(
                             "UPDATE episodes SET title = ?, url = ?,
                                 guid = ?, description = ?, pubdate = ?,
-                                duration = ? WHERE id = ?;",
+                                duration = ?, image_url = ?, chapters_url = ?,
+                                transcript_url = ?, transcript_type = ?,
+                                enclosures = ?, enclosure_error = ?,
+                                updated = MAX(updated, ?)
+                                WHERE id = ?;",
                         )?;
                         stmt.execute(params![
                             new_ep.title,
@@ -399,18 +695,29 @@ impl Database {
                             new_ep.description,
                             new_pd,
                             new_ep.duration,
+                            new_ep.image_url,
+                            new_ep.chapters_url,
+                            new_ep.transcript_url,
+                            new_ep.transcript_type,
+                            enclosures_to_json(&new_ep.enclosures),
+                            new_ep.enclosure_error,
+                            rereleased,
                             id,
                         ])?;
                         update_ep.push(id);
+                        if rereleased {
+                            rereleased_ep.push(id);
+                        }
                     }
                 }
                 None => {
-                    let id = self.insert_episode(&tx, podcast_id, new_ep)?;
+                    let id = self.insert_episode(&tx, podcast_id, new_ep, true)?;
                     let new_ep = NewEpisode {
                         id: id,
                         pod_id: podcast_id,
                         title: new_ep.title.clone(),
                         pod_title: podcast_title.clone(),
+                        pubdate: new_ep.pubdate,
                         selected: false,
                     };
                     insert_ep.push(new_ep);
@@ -421,9 +728,30 @@ impl Database {
         return Ok(SyncResult {
             added: insert_ep,
             updated: update_ep,
+            rereleased: rereleased_ep,
         });
     }
 
+    /// Works out what syncing a podcast with the given feed data would
+    /// change, without writing anything to the database.
+    pub fn preview_sync(&self, podcast_id: i64, podcast: &PodcastNoId) -> Result<SyncPreview> {
+        let old_episodes = self.get_episodes(podcast_id, true)?;
+        let old_ep_map = map_by_guid(&old_episodes);
+
+        let mut preview = SyncPreview::default();
+        for new_ep in podcast.episodes.iter().rev() {
+            match find_existing(&old_episodes, &old_ep_map, new_ep) {
+                Some(old_ep) if old_ep.title != new_ep.title => {
+                    preview.retitled.push((old_ep.title.clone(), new_ep.title.clone()));
+                }
+                Some(old_ep) if self.check_for_updates(old_ep, new_ep) => preview.updated += 1,
+                Some(_) => (),
+                None => preview.added.push(new_ep.title.clone()),
+            }
+        }
+        return Ok(preview);
+    }
+
     /// Checks two matching episodes to see whether there are details
     /// that need to be updated (e.g., same episode, but the title has
     /// been changed).
@@ -440,6 +768,12 @@ impl Database {
             && new_ep.guid == old_ep.guid
             && new_ep.description == old_ep.description
             && new_ep.duration == old_ep.duration
+            && new_ep.image_url == old_ep.image_url
+            && new_ep.chapters_url == old_ep.chapters_url
+            && new_ep.transcript_url == old_ep.transcript_url
+            && new_ep.transcript_type == old_ep.transcript_type
+            && new_ep.enclosures == old_ep.enclosures
+            && new_ep.enclosure_error == old_ep.enclosure_error
             && pd_match)
         {
             return true;
@@ -447,6 +781,16 @@ impl Database {
         return false;
     }
 
+    /// Sets or clears the flag marking a downloaded episode as having
+    /// been re-released in the feed.
+    pub fn set_updated_status(&self, episode_id: i64, updated: bool) -> Result<()> {
+        let conn = self.conn.as_ref().expect("Error connecting to database.");
+
+        let mut stmt = conn.prepare_cached("UPDATE episodes SET updated = ? WHERE id = ?;")?;
+        stmt.execute(params![updated, episode_id])?;
+        return Ok(());
+    }
+
     /// Updates an episode to mark it as played or unplayed.
     pub fn set_played_status(&self, episode_id: i64, played: bool) -> Result<()> {
         let conn = self.conn.as_ref().expect("Error connecting to database.");
@@ -456,6 +800,126 @@ impl Database {
         return Ok(());
     }
 
+    /// Updates an episode to mark it as starred or not.
+    pub fn set_starred(&self, episode_id: i64, starred: bool) -> Result<()> {
+        let conn = self.conn.as_ref().expect("Error connecting to database.");
+
+        let mut stmt = conn.prepare_cached("UPDATE episodes SET starred = ? WHERE id = ?;")?;
+        stmt.execute(params![starred, episode_id])?;
+        return Ok(());
+    }
+
+    /// Saves how far into an episode playback has got, in seconds, or
+    /// clears it once the episode has been played to the end.
+    pub fn set_position(&self, episode_id: i64, position: Option<i64>) -> Result<()> {
+        let conn = self.conn.as_ref().expect("Error connecting to database.");
+
+        let mut stmt = conn.prepare_cached("UPDATE episodes SET position = ? WHERE id = ?;")?;
+        stmt.execute(params![position, episode_id])?;
+        return Ok(());
+    }
+
+    /// Flags an episode as new (i.e., in the inbox) or not.
+    pub fn set_new(&self, episode_id: i64, new: bool) -> Result<()> {
+        let conn = self.conn.as_ref().expect("Error connecting to database.");
+
+        let mut stmt = conn.prepare_cached("UPDATE episodes SET new = ? WHERE id = ?;")?;
+        stmt.execute(params![new, episode_id])?;
+        return Ok(());
+    }
+
+    /// Adds a listen to the spool of those waiting to be submitted to
+    /// ListenBrainz.
+    pub fn spool_listen(&self, listened_at: i64, podcast: &str, episode: &str) -> Result<()> {
+        let conn = self.conn.as_ref().expect("Error connecting to database.");
+
+        let mut stmt = conn.prepare_cached(
+            "INSERT INTO scrobbles (listened_at, podcast, episode) VALUES (?, ?, ?);",
+        )?;
+        stmt.execute(params![listened_at, podcast, episode])?;
+        return Ok(());
+    }
+
+    /// Returns the episodes in the play queue, in order, as pairs of
+    /// podcast and episode IDs. Hidden episodes are left out.
+    pub fn get_queue(&self) -> Result<Vec<(i64, i64)>> {
+        let conn = self.conn.as_ref().expect("Error connecting to database.");
+
+        let mut stmt = conn.prepare_cached(
+            "SELECT episodes.podcast_id, queue.episode_id FROM queue
+                INNER JOIN episodes ON episodes.id = queue.episode_id
+                WHERE episodes.hidden = 0 OR episodes.hidden IS NULL
+                ORDER BY queue.position ASC;",
+        )?;
+        let queue = stmt
+            .query_map(params![], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .flatten()
+            .collect();
+        return Ok(queue);
+    }
+
+    /// Replaces the play queue with the given episodes, in order.
+    pub fn set_queue(&self, queue: &[(i64, i64)]) -> Result<()> {
+        let mut conn = Connection::open(&self.path).expect("Error connecting to database.");
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM queue;", params![])?;
+        {
+            let mut stmt =
+                tx.prepare_cached("INSERT INTO queue (episode_id, position) VALUES (?, ?);")?;
+            for (position, (_, ep_id)) in queue.iter().enumerate() {
+                stmt.execute(params![ep_id, position as i64])?;
+            }
+        }
+        tx.commit()?;
+        return Ok(());
+    }
+
+    /// Returns the oldest listens waiting to be submitted, up to `limit`
+    /// of them.
+    pub fn spooled_listens(&self, limit: usize) -> Result<Vec<Listen>> {
+        let conn = self.conn.as_ref().expect("Error connecting to database.");
+
+        let mut stmt = conn.prepare_cached(
+            "SELECT * FROM scrobbles ORDER BY listened_at ASC, id ASC LIMIT ?;",
+        )?;
+        let listens = stmt
+            .query_map(params![limit as i64], |row| {
+                Ok(Listen {
+                    id: row.get("id")?,
+                    listened_at: row.get("listened_at")?,
+                    podcast: row.get("podcast")?,
+                    episode: row.get("episode")?,
+                })
+            })?
+            .flatten()
+            .collect();
+        return Ok(listens);
+    }
+
+    /// Takes listens out of the spool once they have been submitted.
+    pub fn unspool_listens(&self, ids: &[i64]) -> Result<()> {
+        let mut conn = Connection::open(&self.path).expect("Error connecting to database.");
+        let tx = conn.transaction()?;
+        {
+            let mut stmt = tx.prepare_cached("DELETE FROM scrobbles WHERE id = ?;")?;
+            for id in ids.iter() {
+                stmt.execute(params![id])?;
+            }
+        }
+        tx.commit()?;
+        return Ok(());
+    }
+
+    /// Records when an episode was last played, as a Unix timestamp.
+    pub fn set_last_played(&self, episode_id: i64, timestamp: i64) -> Result<()> {
+        let conn = self.conn.as_ref().expect("Error connecting to database.");
+
+        let mut stmt =
+            conn.prepare_cached("UPDATE episodes SET last_played = ? WHERE id = ?;")?;
+        stmt.execute(params![timestamp, episode_id])?;
+        return Ok(());
+    }
+
     /// Updates an episode to "remove" it by hiding it. "Removed"
     /// episodes need to stay in the database so that they don't get
     /// re-added when the podcast is synced again.
@@ -467,6 +931,188 @@ impl Database {
         return Ok(());
     }
 
+    /// Updates a podcast to turn new-episode notifications on or off.
+    pub fn set_notify(&self, podcast_id: i64, notify: bool) -> Result<()> {
+        let conn = self.conn.as_ref().expect("Error connecting to database.");
+
+        let mut stmt = conn.prepare_cached("UPDATE podcasts SET notify = ? WHERE id = ?;")?;
+        stmt.execute(params![notify, podcast_id])?;
+        return Ok(());
+    }
+
+    /// Updates a podcast to archive it (i.e., stop syncing it along with
+    /// the others), or to bring it back.
+    pub fn set_archived(&self, podcast_id: i64, archived: bool) -> Result<()> {
+        let conn = self.conn.as_ref().expect("Error connecting to database.");
+
+        let mut stmt = conn.prepare_cached("UPDATE podcasts SET archived = ? WHERE id = ?;")?;
+        stmt.execute(params![archived, podcast_id])?;
+        return Ok(());
+    }
+
+    /// Updates a podcast to show it under a title of the user's choosing
+    /// instead of the one in its feed, or to go back to the feed's title
+    /// if `title` is None.
+    pub fn set_custom_title(&self, podcast_id: i64, title: Option<&str>) -> Result<()> {
+        let conn = self.conn.as_ref().expect("Error connecting to database.");
+
+        let mut stmt = conn.prepare_cached("UPDATE podcasts SET custom_title = ? WHERE id = ?;")?;
+        stmt.execute(params![title, podcast_id])?;
+        return Ok(());
+    }
+
+    /// Puts a podcast in a group of the user's choosing in the podcast
+    /// menu, or takes it out of its group if `group` is None.
+    pub fn set_group(&self, podcast_id: i64, group: Option<&str>) -> Result<()> {
+        let conn = self.conn.as_ref().expect("Error connecting to database.");
+
+        let mut stmt = conn.prepare_cached("UPDATE podcasts SET group_name = ? WHERE id = ?;")?;
+        stmt.execute(params![group, podcast_id])?;
+        return Ok(());
+    }
+
+    /// Updates the patterns deciding which new episodes of a podcast are
+    /// downloaded automatically.
+    pub fn set_download_rules(
+        &self,
+        podcast_id: i64,
+        include: Option<&str>,
+        exclude: Option<&str>,
+    ) -> Result<()> {
+        let conn = self.conn.as_ref().expect("Error connecting to database.");
+
+        let mut stmt = conn.prepare_cached(
+            "UPDATE podcasts SET include_pattern = ?, exclude_pattern = ?
+            WHERE id = ?;",
+        )?;
+        stmt.execute(params![include, exclude, podcast_id])?;
+        return Ok(());
+    }
+
+    /// Updates the patterns deciding which episodes of a podcast are
+    /// shown.
+    pub fn set_skip_rules(
+        &self,
+        podcast_id: i64,
+        show: Option<&str>,
+        hide: Option<&str>,
+    ) -> Result<()> {
+        let conn = self.conn.as_ref().expect("Error connecting to database.");
+
+        let mut stmt = conn.prepare_cached(
+            "UPDATE podcasts SET show_pattern = ?, hide_pattern = ?
+            WHERE id = ?;",
+        )?;
+        stmt.execute(params![show, hide, podcast_id])?;
+        return Ok(());
+    }
+
+    /// Updates the rule that cleans up the episode titles of a podcast.
+    pub fn set_title_rule(
+        &self,
+        podcast_id: i64,
+        pattern: Option<&str>,
+        replacement: Option<&str>,
+    ) -> Result<()> {
+        let conn = self.conn.as_ref().expect("Error connecting to database.");
+
+        let mut stmt = conn.prepare_cached(
+            "UPDATE podcasts SET title_pattern = ?, title_replacement = ?
+            WHERE id = ?;",
+        )?;
+        stmt.execute(params![pattern, replacement, podcast_id])?;
+        return Ok(());
+    }
+
+    /// Updates the settings of a podcast that override the global
+    /// configuration.
+    pub fn set_overrides(&self, podcast_id: i64, overrides: &PodcastOverrides) -> Result<()> {
+        let conn = self.conn.as_ref().expect("Error connecting to database.");
+
+        let mut stmt = conn.prepare_cached(
+            "UPDATE podcasts SET download_dir = ?, play_command = ?,
+            auto_download = ?, keep_downloads = ?, preferred_enclosure = ?
+            WHERE id = ?;",
+        )?;
+        stmt.execute(params![
+            overrides.download_dir,
+            overrides.play_command,
+            overrides.auto_download,
+            overrides.keep_downloads,
+            overrides.preferred_enclosure,
+            podcast_id,
+        ])?;
+        return Ok(());
+    }
+
+    /// Updates the credentials and extra headers used for a private
+    /// podcast feed. Headers are stored one per line. The password is
+    /// kept in the system keyring if it can be, and in the database
+    /// otherwise. A password that is in the keyring but was never read
+    /// (see `FeedAuth::keyring_entry`) stays where it is; once there is
+    /// no password, it is deleted from the keyring.
+    pub fn set_auth(&self, podcast_id: i64, auth: &FeedAuth) -> Result<()> {
+        let conn = self.conn.as_ref().expect("Error connecting to database.");
+
+        let keyring_name = self.feed_password_name(podcast_id);
+        let in_keyring = match &auth.password {
+            Some(password) => secrets::set(&keyring_name, password).is_ok(),
+            None => auth.keyring_entry.is_some(),
+        };
+        if !in_keyring {
+            let _ = secrets::delete(&keyring_name);
+        }
+        let password = if in_keyring { None } else { auth.password.as_deref() };
+
+        let mut stmt = conn.prepare_cached(
+            "UPDATE podcasts SET auth_user = ?, auth_password = ?, auth_headers = ?,
+            auth_in_keyring = ?, accept_invalid_certs = ? WHERE id = ?;",
+        )?;
+        stmt.execute(params![
+            auth.user,
+            password,
+            join_lines(&auth.headers),
+            in_keyring,
+            auth.accept_invalid_certs,
+            podcast_id,
+        ])?;
+        return Ok(());
+    }
+
+    /// Updates the shellcaster-specific settings of the podcast with
+    /// the given feed URL, e.g., after importing it from another
+    /// machine. Logins and certificate checks are never set this way;
+    /// they can only be changed in the podcast's own settings.
+    pub fn set_settings_by_url(&self, url: &str, settings: &PodcastSettings) -> Result<()> {
+        let conn = self.conn.as_ref().expect("Error connecting to database.");
+
+        let mut stmt = conn.prepare_cached(
+            "UPDATE podcasts SET notify = ?, custom_title = ?, include_pattern = ?,
+            exclude_pattern = ?, title_pattern = ?, title_replacement = ?,
+            show_pattern = ?, hide_pattern = ?, group_name = ?,
+            download_dir = ?, play_command = ?, auto_download = ?, keep_downloads = ?,
+            preferred_enclosure = ? WHERE url = ?;",
+        )?;
+        stmt.execute(params![
+            settings.notify,
+            settings.custom_title,
+            settings.include_pattern,
+            settings.exclude_pattern,
+            settings.title_pattern,
+            settings.title_replacement,
+            settings.show_pattern,
+            settings.hide_pattern,
+            settings.group,
+            settings.overrides.download_dir,
+            settings.overrides.play_command,
+            settings.overrides.auto_download,
+            settings.overrides.keep_downloads,
+            settings.overrides.preferred_enclosure,
+            url,
+        ])?;
+        return Ok(());
+    }
+
     /// Generates list of all podcasts in database.
     /// TODO: This should probably use a JOIN statement instead.
     pub fn get_podcasts(&self) -> Result<Vec<Podcast>> {
@@ -480,20 +1126,72 @@ impl Database {
             }?;
 
             // create a sort title that is lowercased and removes
-            // articles from the beginning
+            // articles from the beginning; podcasts the user has renamed
+            // are sorted by their new title
             let title: String = row.get("title")?;
-            let title_lower = title.to_lowercase();
+            let custom_title: Option<String> = row.get("custom_title")?;
+            let title_lower = custom_title.as_ref().unwrap_or(&title).to_lowercase();
             let sort_title = RE_ARTICLES.replace(&title_lower, "").to_string();
 
+            // passwords in the keyring are only read when they are
+            // needed, as each one can take a while
+            let keyring_entry = if row.get("auth_in_keyring")? {
+                Some(self.feed_password_name(pod_id))
+            } else {
+                None
+            };
+
             Ok(Podcast {
                 id: pod_id,
                 title: title,
+                custom_title: custom_title,
                 sort_title: sort_title,
                 url: row.get("url")?,
                 description: row.get("description")?,
                 author: row.get("author")?,
                 explicit: row.get("explicit")?,
                 last_checked: convert_date(row.get("last_checked")).unwrap(),
+                image_url: row.get("image_url")?,
+                categories: split_lines(row.get("categories")?),
+                language: row.get("language")?,
+                website: row.get("website")?,
+                funding_url: row.get("funding_url")?,
+                funding_text: row.get("funding_text")?,
+                notify: row.get("notify")?,
+                archived: row.get("archived")?,
+                include_pattern: row.get("include_pattern")?,
+                exclude_pattern: row.get("exclude_pattern")?,
+                title_pattern: row.get("title_pattern")?,
+                title_replacement: row.get("title_replacement")?,
+                show_pattern: row.get("show_pattern")?,
+                hide_pattern: row.get("hide_pattern")?,
+                group: row.get("group_name")?,
+                overrides: PodcastOverrides {
+                    download_dir: row.get("download_dir")?,
+                    play_command: row.get("play_command")?,
+                    auto_download: row.get("auto_download")?,
+                    keep_downloads: row.get("keep_downloads")?,
+                    preferred_enclosure: row.get("preferred_enclosure")?,
+                },
+                auth: FeedAuth {
+                    user: row.get("auth_user")?,
+                    password: row.get("auth_password")?,
+                    headers: split_lines(row.get("auth_headers")?),
+                    accept_invalid_certs: row.get("accept_invalid_certs")?,
+                    keyring_entry: keyring_entry,
+                },
+                validators: FeedValidators {
+                    etag: row.get("etag")?,
+                    last_modified: row.get("last_modified")?,
+                },
+                schedule: FeedSchedule {
+                    ttl: row.get("ttl")?,
+                    skip_hours: split_lines(row.get("skip_hours")?)
+                        .iter()
+                        .filter_map(|h| h.parse().ok())
+                        .collect(),
+                    skip_days: split_lines(row.get("skip_days")?),
+                },
                 episodes: LockVec::new(episodes),
             })
         })?;
@@ -541,17 +1239,286 @@ impl Database {
                 description: row.get("description")?,
                 pubdate: convert_date(row.get("pubdate")),
                 duration: row.get("duration")?,
+                image_url: row.get("image_url")?,
+                chapters_url: row.get("chapters_url")?,
+                chapters: Vec::new(),
+                transcript_url: row.get("transcript_url")?,
+                transcript_type: row.get("transcript_type")?,
+                enclosures: row
+                    .get::<_, Option<String>>("enclosures")?
+                    .and_then(|json| serde_json::from_str(&json).ok())
+                    .unwrap_or_default(),
+                enclosure_error: row.get("enclosure_error")?,
                 path: path,
                 played: row.get("played")?,
+                updated: row.get("updated")?,
+                starred: row.get("starred")?,
+                clean_title: None,
+                position: row.get("position")?,
+                last_played: row.get("last_played")?,
+                new: row.get("new")?,
             })
         })?;
-        let episodes = episode_iter.flatten().collect();
+        let mut episodes: Vec<Episode> = episode_iter.flatten().collect();
+
+        let mut chapters = self.get_chapters(pod_id)?;
+        for ep in episodes.iter_mut() {
+            if let Some(ep_chapters) = chapters.remove(&ep.id) {
+                ep.chapters = ep_chapters;
+            }
+        }
         return Ok(episodes);
     }
 
+    /// Gets the stored chapters for all episodes of a given podcast,
+    /// keyed by episode id.
+    fn get_chapters(&self, pod_id: i64) -> Result<AHashMap<i64, Vec<Chapter>>> {
+        let conn = self.conn.as_ref().expect("Error connecting to database.");
+        let mut stmt = conn.prepare_cached(
+            "SELECT chapters.episode_id, chapters.start, chapters.title
+                    FROM chapters
+                    INNER JOIN episodes ON episodes.id = chapters.episode_id
+                    WHERE episodes.podcast_id = ?
+                    ORDER BY chapters.episode_id, chapters.start;",
+        )?;
+        let chapter_iter = stmt.query_map(params![pod_id], |row| {
+            Ok((
+                row.get::<&str, i64>("episode_id")?,
+                row.get::<&str, f64>("start")?,
+                row.get::<&str, String>("title")?,
+            ))
+        })?;
+
+        let mut chapters: AHashMap<i64, Vec<Chapter>> = AHashMap::new();
+        for (ep_id, start, title) in chapter_iter.flatten() {
+            let ep_chapters = chapters.entry(ep_id).or_default();
+            ep_chapters.push(Chapter {
+                id: ep_chapters.len() as i64,
+                start: start,
+                title: title,
+            });
+        }
+        return Ok(chapters);
+    }
+
+    /// Adds up statistics about the whole library. Episodes added
+    /// before the database recorded when episodes were added are not
+    /// counted as added in the last week.
+    pub fn get_stats(&self) -> Result<LibraryStats> {
+        let conn = self.conn.as_ref().expect("Error connecting to database.");
+        let week_ago = (Utc::now() - chrono::Duration::days(7)).timestamp();
+
+        let podcasts = conn.query_row("SELECT COUNT(*) FROM podcasts;", params![], |row| {
+            row.get::<usize, i64>(0)
+        })?;
+        let mut stats = conn.query_row(
+            "SELECT COUNT(*),
+                COALESCE(SUM(played = 0 OR played IS NULL), 0),
+                COALESCE(SUM(CASE WHEN played = 1 THEN duration END), 0),
+                COALESCE(SUM(added >= ?), 0)
+            FROM episodes
+            WHERE hidden = 0 OR hidden IS NULL;",
+            params![week_ago],
+            |row| {
+                Ok(LibraryStats {
+                    podcasts: podcasts,
+                    episodes: row.get(0)?,
+                    unplayed: row.get(1)?,
+                    listened: row.get(2)?,
+                    added_last_week: row.get(3)?,
+                    ..Default::default()
+                })
+            },
+        )?;
+
+        let mut stmt = conn.prepare_cached(
+            "SELECT podcasts.title, files.path
+                FROM files
+                INNER JOIN episodes ON episodes.id = files.episode_id
+                INNER JOIN podcasts ON podcasts.id = episodes.podcast_id;",
+        )?;
+        let file_iter = stmt.query_map(params![], |row| {
+            Ok((
+                row.get::<&str, String>("title")?,
+                row.get::<&str, String>("path")?,
+            ))
+        })?;
+        let mut usage: AHashMap<String, u64> = AHashMap::new();
+        for (title, path) in file_iter.flatten() {
+            stats.downloaded += 1;
+            let size = std::fs::metadata(path).map(|meta| meta.len()).unwrap_or(0);
+            *usage.entry(title).or_default() += size;
+        }
+        stats.disk_usage = usage.into_iter().collect();
+        stats
+            .disk_usage
+            .sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        return Ok(stats);
+    }
+
+    /// Checks the database for corruption, updates the statistics used
+    /// to plan queries, and rebuilds the database file to reclaim unused
+    /// space. `progress` is called with a description of each step
+    /// before it starts.
+    pub fn maintain<F>(&self, mut progress: F) -> Result<MaintenanceReport>
+    where F: FnMut(&str) {
+        let conn = self.conn.as_ref().expect("Error connecting to database.");
+        let size_before = self.file_size();
+
+        progress("Checking database integrity");
+        let mut stmt = conn.prepare("PRAGMA integrity_check;")?;
+        let problems = stmt
+            .query_map(params![], |row| row.get::<usize, String>(0))?
+            .flatten()
+            .filter(|line| line != "ok")
+            .collect::<Vec<String>>();
+        if !problems.is_empty() {
+            return Ok(MaintenanceReport {
+                problems: problems,
+                size_before: size_before,
+                size_after: size_before,
+            });
+        }
+
+        progress("Analyzing database");
+        conn.execute("ANALYZE;", params![])
+            .with_context(|| "Could not analyze database")?;
+
+        progress("Compacting database");
+        conn.execute("VACUUM;", params![])
+            .with_context(|| "Could not compact database")?;
+
+        conn.execute(
+            "UPDATE version SET last_maintenance = ? WHERE id = 1;",
+            params![Utc::now().timestamp()],
+        )?;
+        return Ok(MaintenanceReport {
+            problems: problems,
+            size_before: size_before,
+            size_after: self.file_size(),
+        });
+    }
+
+    /// Returns when maintenance last finished successfully, if ever.
+    pub fn last_maintenance(&self) -> Option<DateTime<Utc>> {
+        let conn = self.conn.as_ref().expect("Error connecting to database.");
+        let timestamp = conn.query_row(
+            "SELECT last_maintenance FROM version WHERE id = 1;",
+            params![],
+            |row| row.get("last_maintenance"),
+        );
+        return convert_date(timestamp);
+    }
+
+    /// Returns when the app last checked for a newer version, if ever.
+    pub fn last_update_check(&self) -> Option<DateTime<Utc>> {
+        let conn = self.conn.as_ref().expect("Error connecting to database.");
+        let timestamp = conn.query_row(
+            "SELECT last_update_check FROM version WHERE id = 1;",
+            params![],
+            |row| row.get("last_update_check"),
+        );
+        return convert_date(timestamp);
+    }
+
+    /// Records that the app has just checked for a newer version.
+    pub fn set_update_checked(&self) -> Result<()> {
+        let conn = self.conn.as_ref().expect("Error connecting to database.");
+        conn.execute(
+            "UPDATE version SET last_update_check = ? WHERE id = 1;",
+            params![Utc::now().timestamp()],
+        )?;
+        return Ok(());
+    }
+
+    /// Returns the newer version the user was last told about, if any.
+    pub fn update_notified(&self) -> Option<String> {
+        let conn = self.conn.as_ref().expect("Error connecting to database.");
+        return conn
+            .query_row(
+                "SELECT update_notified FROM version WHERE id = 1;",
+                params![],
+                |row| row.get("update_notified"),
+            )
+            .ok()
+            .flatten();
+    }
+
+    /// Records that the user has been told about a newer version, so
+    /// they are only told once.
+    pub fn set_update_notified(&self, version: &str) -> Result<()> {
+        let conn = self.conn.as_ref().expect("Error connecting to database.");
+        conn.execute(
+            "UPDATE version SET update_notified = ? WHERE id = 1;",
+            params![version],
+        )?;
+        return Ok(());
+    }
+
+    /// Returns the version of shellcaster whose interface was last
+    /// opened, if known.
+    pub fn last_run_version(&self) -> Option<String> {
+        let conn = self.conn.as_ref().expect("Error connecting to database.");
+        return conn
+            .query_row(
+                "SELECT last_run FROM version WHERE id = 1;",
+                params![],
+                |row| row.get("last_run"),
+            )
+            .ok()
+            .flatten();
+    }
+
+    /// Records the version of shellcaster whose interface is open.
+    pub fn set_last_run_version(&self, version: &str) -> Result<()> {
+        let conn = self.conn.as_ref().expect("Error connecting to database.");
+        conn.execute(
+            "UPDATE version SET last_run = ? WHERE id = 1;",
+            params![version],
+        )?;
+        return Ok(());
+    }
+
+    /// Returns the filters that were active when the interface was last
+    /// closed, as written by `set_filters()`.
+    pub fn filters(&self) -> Option<String> {
+        let conn = self.conn.as_ref().expect("Error connecting to database.");
+        return conn
+            .query_row("SELECT filters FROM version WHERE id = 1;", params![], |row| {
+                row.get("filters")
+            })
+            .ok()
+            .flatten();
+    }
+
+    /// Records the active filters, so they can be restored on the next
+    /// start.
+    pub fn set_filters(&self, filters: &str) -> Result<()> {
+        let conn = self.conn.as_ref().expect("Error connecting to database.");
+        conn.execute(
+            "UPDATE version SET filters = ? WHERE id = 1;",
+            params![filters],
+        )?;
+        return Ok(());
+    }
+
+    /// Returns the size of the database file in bytes.
+    fn file_size(&self) -> u64 {
+        return std::fs::metadata(&self.path)
+            .map(|meta| meta.len())
+            .unwrap_or(0);
+    }
+
     /// Deletes all rows in all tables
     pub fn clear_db(&self) -> Result<()> {
         let conn = self.conn.as_ref().expect("Error connecting to database.");
+        let mut stmt = conn.prepare("SELECT id FROM podcasts WHERE auth_in_keyring = 1;")?;
+        let in_keyring: Vec<i64> =
+            stmt.query_map(params![], |row| row.get(0))?.flatten().collect();
+        for pod_id in in_keyring {
+            let _ = secrets::delete(&self.feed_password_name(pod_id));
+        }
+        conn.execute("DELETE FROM chapters;", params![])?;
         conn.execute("DELETE FROM files;", params![])?;
         conn.execute("DELETE FROM episodes;", params![])?;
         conn.execute("DELETE FROM podcasts;", params![])?;
@@ -559,6 +1526,144 @@ impl Database {
     }
 }
 
+/// Helper function that indexes episodes by their GUIDs, leaving out any
+/// that have none.
+fn map_by_guid(episodes: &[Episode]) -> AHashMap<&str, &Episode> {
+    let mut map = AHashMap::new();
+    for ep in episodes.iter() {
+        if !ep.guid.is_empty() {
+            map.insert(ep.guid.as_str(), ep);
+        }
+    }
+    return map;
+}
+
+/// Helper function that finds the existing episode a newly synced
+/// episode matches, if any.
+fn find_existing<'a>(
+    old_episodes: &'a [Episode],
+    old_ep_map: &AHashMap<&str, &'a Episode>,
+    new_ep: &EpisodeNoId,
+) -> Option<&'a Episode> {
+    // primary matching mechanism: check guid to see if it
+    // already exists in database
+    if !new_ep.guid.is_empty() {
+        if let Some(old_ep) = old_ep_map.get(new_ep.guid.as_str()) {
+            return Some(*old_ep);
+        }
+    }
+
+    // fallback matching: for each existing episode, check the
+    // title, url, and pubdate -- if two of the three match, we
+    // count it as an existing episode; otherwise, we add it as
+    // a new episode
+    let new_pd = new_ep.pubdate.map(|dt| dt.timestamp());
+    for old_ep in old_episodes.iter().rev() {
+        let mut matching = 0;
+        matching += (new_ep.title == old_ep.title) as i32;
+        matching += (new_ep.url == old_ep.url) as i32;
+
+        if let Some(pd) = new_pd {
+            if let Some(old_pd) = old_ep.pubdate {
+                matching += (pd == old_pd.timestamp()) as i32;
+            }
+        }
+
+        if matching >= 2 {
+            return Some(old_ep);
+        }
+    }
+    return None;
+}
+
+/// Indicates whether the audio file of a downloaded episode has been
+/// replaced in its feed. Many feeds sign their links or add tracking
+/// details that change from one sync to the next, and links are cleaned
+/// up when feeds are read (see `feeds::clean_url()`), so links are only
+/// compared by their host and path. When the feed gives the size of
+/// both files, that is compared instead.
+fn is_rerelease(old_ep: &Episode, new_ep: &EpisodeNoId) -> bool {
+    if old_ep.path.is_none() {
+        return false;
+    }
+    let length = |url: &str, enclosures: &[Enclosure]| {
+        return enclosures
+            .iter()
+            .find(|enclosure| enclosure.url == url)
+            .and_then(|enclosure| enclosure.length)
+            .filter(|length| *length > 0);
+    };
+    let old_length = length(&old_ep.url, &old_ep.enclosures);
+    let new_length = length(&new_ep.url, &new_ep.enclosures);
+    if let (Some(old_length), Some(new_length)) = (old_length, new_length) {
+        return old_length != new_length;
+    }
+    return file_key(&old_ep.url) != file_key(&new_ep.url);
+}
+
+/// Reduces the link to an episode's audio file to its host and path,
+/// after cleaning it up in the same way as links read from feeds.
+fn file_key(url: &str) -> String {
+    let parsed = feeds::clean_url(url, None)
+        .ok()
+        .and_then(|url| reqwest::Url::parse(&url).ok());
+    return match parsed {
+        Some(parsed) => format!("{}{}", parsed.host_str().unwrap_or_default(), parsed.path()),
+        None => url.to_string(),
+    };
+}
+
+/// Joins a list of values (HTTP headers, feed categories) into one line
+/// each for storing in the database, or None if there are none.
+fn join_lines<T: ToString>(lines: &[T]) -> Option<String> {
+    if lines.is_empty() {
+        return None;
+    }
+    let lines: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+    return Some(lines.join("\n"));
+}
+
+/// Converts the versions of an episode to JSON for storing in the
+/// database, or None if there are none.
+fn enclosures_to_json(enclosures: &[Enclosure]) -> Option<String> {
+    if enclosures.is_empty() {
+        return None;
+    }
+    return serde_json::to_string(enclosures).ok();
+}
+
+/// Helper function that splits a column stored by `join_lines()` back
+/// into its lines.
+fn split_lines(joined: Option<String>) -> Vec<String> {
+    return joined
+        .map(|s| s.lines().map(|l| l.to_string()).collect())
+        .unwrap_or_default();
+}
+
+/// Helper function that adds a column to a table, if the table does not
+/// already have a column with that name. `definition` holds the type
+/// and constraints of the column, e.g., "INTEGER NOT NULL DEFAULT 0".
+fn add_column_if_missing(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({table});"))?;
+    let columns = stmt
+        .query_map(params![], |row| row.get::<&str, String>("name"))?
+        .flatten()
+        .collect::<Vec<String>>();
+    if !columns.iter().any(|c| c == column) {
+        conn.execute(
+            &format!("ALTER TABLE {table} ADD COLUMN {column} {definition};"),
+            params![],
+        )
+        .with_context(|| format!("Could not add column {column} to {table} database table"))?;
+    }
+    return Ok(());
+}
+
 /// Helper function converting an (optional) Unix timestamp to a
 /// DateTime<Utc> object
 fn convert_date(result: Result<i64, rusqlite::Error>) -> Option<DateTime<Utc>> {
@@ -569,3 +1674,330 @@ fn convert_date(result: Result<i64, rusqlite::Error>) -> Option<DateTime<Utc>> {
         Err(_) => None,
     };
 }
+
+
+// TESTS -----------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enclosure(url: &str, length: Option<i64>) -> Enclosure {
+        return Enclosure {
+            url: url.to_string(),
+            mime: None,
+            bitrate: None,
+            length: length,
+            title: None,
+        };
+    }
+
+    fn old_episode(url: &str, length: Option<i64>) -> Episode {
+        return Episode {
+            id: 1,
+            pod_id: 1,
+            title: "Episode".to_string(),
+            url: url.to_string(),
+            guid: "guid".to_string(),
+            description: String::new(),
+            pubdate: None,
+            duration: None,
+            image_url: None,
+            chapters_url: None,
+            chapters: Vec::new(),
+            transcript_url: None,
+            transcript_type: None,
+            enclosures: vec![enclosure(url, length)],
+            enclosure_error: None,
+            path: Some(PathBuf::from("episode.mp3")),
+            played: false,
+            updated: false,
+            starred: false,
+            clean_title: None,
+            position: None,
+            last_played: None,
+            new: false,
+        };
+    }
+
+    fn new_episode(url: &str, length: Option<i64>) -> EpisodeNoId {
+        return EpisodeNoId {
+            title: "Episode".to_string(),
+            url: url.to_string(),
+            guid: "guid".to_string(),
+            description: String::new(),
+            pubdate: None,
+            duration: None,
+            image_url: None,
+            chapters_url: None,
+            transcript_url: None,
+            transcript_type: None,
+            enclosures: vec![enclosure(url, length)],
+            enclosure_error: None,
+        };
+    }
+
+    /// Opens a new database in a folder of its own under the temp
+    /// folder.
+    fn temp_db(name: &str) -> (PathBuf, Database) {
+        let dir = std::env::temp_dir().join(format!("shellcaster-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let db = Database::connect(&dir).unwrap();
+        return (dir, db);
+    }
+
+    fn podcast(url: &str) -> PodcastNoId {
+        return PodcastNoId {
+            title: "Podcast".to_string(),
+            url: url.to_string(),
+            description: None,
+            author: None,
+            explicit: None,
+            last_checked: Utc::now(),
+            image_url: None,
+            categories: Vec::new(),
+            language: None,
+            website: None,
+            funding_url: None,
+            funding_text: None,
+            validators: FeedValidators::default(),
+            schedule: FeedSchedule::default(),
+            episodes: Vec::new(),
+        };
+    }
+
+    #[test]
+    fn imported_settings_keep_checks() {
+        let (dir, db) = temp_db("import-settings");
+        let url = "https://example.com/a.xml";
+        db.insert_podcast(podcast(url)).unwrap();
+        let settings = PodcastSettings {
+            custom_title: Some("A".to_string()),
+            auth: FeedAuth {
+                user: Some("me".to_string()),
+                password: Some("secret".to_string()),
+                headers: Vec::new(),
+                accept_invalid_certs: true,
+                keyring_entry: None,
+            },
+            ..PodcastSettings::default()
+        };
+        db.set_settings_by_url(url, &settings).unwrap();
+
+        let pod = db.get_podcasts().unwrap().remove(0);
+        assert_eq!(pod.custom_title, Some("A".to_string()));
+        assert_eq!(pod.auth, FeedAuth::default());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn podcast_overrides() {
+        let (dir, db) = temp_db("overrides");
+        db.insert_podcast(podcast("https://example.com/a.xml")).unwrap();
+        let pod_id = db.get_podcasts().unwrap()[0].id;
+        assert_eq!(db.get_podcasts().unwrap()[0].overrides, PodcastOverrides::default());
+
+        let overrides = PodcastOverrides {
+            download_dir: Some("~/Audiobooks".to_string()),
+            play_command: Some("vlc %s".to_string()),
+            auto_download: Some(2),
+            keep_downloads: None,
+            ..PodcastOverrides::default()
+        };
+        db.set_overrides(pod_id, &overrides).unwrap();
+        assert_eq!(db.get_podcasts().unwrap()[0].overrides, overrides);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn sync_preview_changes_nothing() {
+        let (dir, db) = temp_db("preview");
+        let url = "https://example.com/a.xml";
+        let mut pod = podcast(url);
+        pod.episodes.push(new_episode("https://example.com/1.mp3", None));
+        db.insert_podcast(pod).unwrap();
+        let pod_id = db.get_podcasts().unwrap()[0].id;
+
+        let mut renamed = new_episode("https://example.com/1.mp3", None);
+        renamed.title = "Renamed".to_string();
+        let mut added = new_episode("https://example.com/2.mp3", None);
+        added.title = "New".to_string();
+        added.guid = "guid2".to_string();
+        let mut pod = podcast(url);
+        pod.episodes = vec![added, renamed];
+
+        let preview = db.preview_sync(pod_id, &pod).unwrap();
+        assert_eq!(preview.report("Podcast"), vec![
+            "Podcast: 1 new episode, 1 new title",
+            "  + New",
+            "  ~ Episode -> Renamed",
+        ]);
+        let episodes = db.get_episodes(pod_id, true).unwrap();
+        assert_eq!(episodes.len(), 1);
+        assert_eq!(episodes[0].title, "Episode");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn feed_schedule_saved() {
+        let (dir, db) = temp_db("schedule");
+        let url = "https://example.com/a.xml";
+        let mut pod = podcast(url);
+        pod.schedule = FeedSchedule {
+            ttl: Some(60),
+            skip_hours: vec![0, 23],
+            skip_days: vec!["Sunday".to_string()],
+        };
+        db.insert_podcast(pod).unwrap();
+        let saved = db.get_podcasts().unwrap().remove(0);
+        assert_eq!(saved.schedule.ttl, Some(60));
+        assert_eq!(saved.schedule.skip_hours, vec![0, 23]);
+        assert_eq!(saved.schedule.skip_days, vec!["Sunday"]);
+
+        // a feed that drops its hints is checked as usual again
+        db.update_podcast(saved.id, podcast(url)).unwrap();
+        assert_eq!(db.get_podcasts().unwrap()[0].schedule, FeedSchedule::default());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn sync_failures_in_a_row() {
+        let (dir, db) = temp_db("sync-failures");
+        db.insert_podcast(podcast("https://example.com/a.xml")).unwrap();
+        let pod_id = db.get_podcasts().unwrap()[0].id;
+        assert_eq!(db.record_sync_failure(pod_id).unwrap(), 1);
+        assert_eq!(db.record_sync_failure(pod_id).unwrap(), 2);
+
+        // a sync that works starts the count again
+        db.set_last_checked(pod_id).unwrap();
+        assert_eq!(db.record_sync_failure(pod_id).unwrap(), 1);
+
+        db.set_archived(pod_id, true).unwrap();
+        assert!(db.get_podcasts().unwrap()[0].archived);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn renamed_podcast() {
+        let (dir, db) = temp_db("custom-title");
+        db.insert_podcast(podcast("https://example.com/a.xml")).unwrap();
+        let pod_id = db.get_podcasts().unwrap()[0].id;
+
+        // the podcast is sorted by its new title
+        db.set_custom_title(pod_id, Some("The Zebra Show")).unwrap();
+        let pod = db.get_podcasts().unwrap().remove(0);
+        assert_eq!(pod.display_title(), "The Zebra Show");
+        assert_eq!(pod.title, "Podcast");
+        assert_eq!(pod.sort_title, "zebra show");
+
+        db.set_custom_title(pod_id, None).unwrap();
+        let pod = db.get_podcasts().unwrap().remove(0);
+        assert_eq!(pod.display_title(), "Podcast");
+        assert_eq!(pod.sort_title, "podcast");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn library_stats() {
+        let (dir, db) = temp_db("stats");
+        let mut pod = podcast("https://example.com/a.xml");
+        let mut first = new_episode("https://example.com/1.mp3", None);
+        first.duration = Some(600);
+        let mut second = new_episode("https://example.com/2.mp3", None);
+        second.guid = "guid2".to_string();
+        second.duration = Some(300);
+        pod.episodes = vec![first, second];
+        db.insert_podcast(pod).unwrap();
+        let pod_id = db.get_podcasts().unwrap()[0].id;
+        let episodes = db.get_episodes(pod_id, false).unwrap();
+
+        // only played episodes count towards the time listened
+        db.set_played_status(episodes[0].id, true).unwrap();
+        let file = dir.join("episode.mp3");
+        std::fs::write(&file, [0; 1000]).unwrap();
+        db.insert_file(episodes[0].id, &file).unwrap();
+
+        let stats = db.get_stats().unwrap();
+        assert_eq!(stats.podcasts, 1);
+        assert_eq!(stats.episodes, 2);
+        assert_eq!(stats.unplayed, 1);
+        assert_eq!(stats.downloaded, 1);
+        assert_eq!(stats.listened, episodes[0].duration.unwrap());
+        assert_eq!(stats.added_last_week, 2);
+        assert_eq!(stats.disk_usage, vec![("Podcast".to_string(), 1000)]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn keyring_names() {
+        let (dir, db) = temp_db("keyring-a");
+        let (other_dir, other) = temp_db("keyring-b");
+        assert_ne!(db.feed_password_name(1), other.feed_password_name(1));
+        assert_eq!(db.feed_password_name(1), db.feed_password_name(1));
+        // the id is kept in the database, so it stays the same
+        let name = db.feed_password_name(1);
+        drop(db);
+        assert_eq!(Database::connect(&dir).unwrap().feed_password_name(1), name);
+        std::fs::remove_dir_all(&dir).unwrap();
+        std::fs::remove_dir_all(&other_dir).unwrap();
+    }
+
+    #[test]
+    fn keyring_passwords_read_later() {
+        let (dir, db) = temp_db("keyring-lazy");
+        let url = "https://example.com/a.xml";
+        db.insert_podcast(podcast(url)).unwrap();
+        let pod_id = db.get_podcasts().unwrap()[0].id;
+        let name = db.feed_password_name(pod_id);
+
+        // changing other settings leaves a password in the keyring
+        // where it is
+        let auth = FeedAuth {
+            user: Some("me".to_string()),
+            headers: vec!["X-Token: abc".to_string()],
+            keyring_entry: Some(name.clone()),
+            ..FeedAuth::default()
+        };
+        db.set_auth(pod_id, &auth).unwrap();
+        let pod = db.get_podcasts().unwrap().remove(0);
+        assert_eq!(pod.auth.password, None);
+        assert_eq!(pod.auth.keyring_entry, Some(name));
+        assert_eq!(pod.auth.headers, auth.headers);
+
+        // removing the login takes it out of the keyring
+        db.set_auth(pod_id, &FeedAuth::default()).unwrap();
+        let pod = db.get_podcasts().unwrap().remove(0);
+        assert_eq!(pod.auth, FeedAuth::default());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rerelease_new_file() {
+        let old = old_episode("https://example.com/ep1.mp3", None);
+        assert!(is_rerelease(&old, &new_episode("https://example.com/ep1-fixed.mp3", None)));
+
+        let old = old_episode("https://example.com/ep1.mp3?sig=a", Some(1000));
+        let new = new_episode("https://example.com/ep1.mp3?sig=b", Some(2000));
+        assert!(is_rerelease(&old, &new));
+    }
+
+    #[test]
+    fn rerelease_same_file() {
+        // signed links change on every sync
+        let old = old_episode("https://example.com/ep1.mp3?sig=a&expires=1", None);
+        let new = new_episode("https://example.com/ep1.mp3?sig=b&expires=2", None);
+        assert!(!is_rerelease(&old, &new));
+
+        // links that were cleaned up after the episode was downloaded
+        let old = old_episode("http://example.com/my ep.mp3?a=1&amp;b=2", None);
+        assert!(!is_rerelease(&old, &new_episode("https://example.com/my%20ep.mp3?a=1&b=2", None)));
+
+        // the same file, served from somewhere else
+        let old = old_episode("https://cdn1.example.com/ep1.mp3", Some(1000));
+        assert!(!is_rerelease(&old, &new_episode("https://cdn2.example.com/ep1.mp3", Some(1000))));
+
+        // episodes that were not downloaded are never re-released
+        let mut old = old_episode("https://example.com/ep1.mp3", None);
+        old.path = None;
+        assert!(!is_rerelease(&old, &new_episode("https://example.com/ep2.mp3", None)));
+    }
+}
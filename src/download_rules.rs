@@ -0,0 +1,89 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+
+/// Per-podcast rules that pick out episodes based on their titles, used
+/// to decide which new episodes are downloaded automatically and which
+/// episodes are shown at all. An episode is allowed if it matches every
+/// include pattern and none of the exclude patterns.
+#[derive(Debug, Clone, Default)]
+pub struct DownloadRules {
+    include: Vec<Regex>,
+    exclude: Vec<Regex>,
+}
+
+impl DownloadRules {
+    /// Compiles the include and exclude patterns. Empty patterns are
+    /// treated as not being set.
+    pub fn new(include: Option<&str>, exclude: Option<&str>) -> Result<Self> {
+        return Self::default().with(include, exclude);
+    }
+
+    /// Adds another pair of include and exclude patterns, which an
+    /// episode must also pass.
+    pub fn with(mut self, include: Option<&str>, exclude: Option<&str>) -> Result<Self> {
+        self.include.extend(compile(include)?);
+        self.exclude.extend(compile(exclude)?);
+        return Ok(self);
+    }
+
+    /// Indicates whether an episode with the given title passes the
+    /// rules.
+    pub fn allows(&self, title: &str) -> bool {
+        return self.include.iter().all(|include| include.is_match(title))
+            && !self.exclude.iter().any(|exclude| exclude.is_match(title));
+    }
+}
+
+/// Checks that a pattern entered by the user is a valid regex.
+pub fn validate(pattern: &str) -> Result<()> {
+    return compile(Some(pattern)).map(|_| ());
+}
+
+/// Helper function that compiles an optional pattern into a regex.
+fn compile(pattern: Option<&str>) -> Result<Option<Regex>> {
+    return match pattern {
+        Some(pattern) if !pattern.trim().is_empty() => Regex::new(pattern)
+            .map(Some)
+            .with_context(|| format!("Invalid pattern: {pattern}")),
+        _ => Ok(None),
+    };
+}
+
+
+// TESTS -----------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_rules() {
+        let rules = DownloadRules::new(None, Some("")).unwrap();
+        assert!(rules.allows("Anything at all"));
+    }
+
+    #[test]
+    fn include_and_exclude() {
+        let rules = DownloadRules::new(Some(r"Part \d+"), Some("(?i)bonus")).unwrap();
+        assert!(rules.allows("The Story, Part 2"));
+        assert!(!rules.allows("The Story, Part Two"));
+        assert!(!rules.allows("BONUS: The Story, Part 3"));
+    }
+
+    #[test]
+    fn combined_rules() {
+        let rules = DownloadRules::new(Some("Story"), None)
+            .unwrap()
+            .with(None, Some("(?i)trailer|rebroadcast"))
+            .unwrap();
+        assert!(rules.allows("The Story, Part 2"));
+        assert!(!rules.allows("The Story: Rebroadcast"));
+        assert!(!rules.allows("Trailer"));
+    }
+
+    #[test]
+    fn invalid_pattern() {
+        assert!(validate("Part (").is_err());
+        assert!(DownloadRules::new(Some("[a-"), None).is_err());
+        assert!(validate(r"^Episode \d+$").is_ok());
+    }
+}
@@ -1,13 +1,36 @@
-use std::fs::File;
+use std::io::{self, Read};
 use std::path::{Path, PathBuf};
 use std::sync::mpsc::Sender;
+use std::sync::Arc;
 use std::time::Duration;
 
 use chrono::{DateTime, Utc};
-use sanitize_filename::{sanitize_with_options, Options};
+use lazy_static::lazy_static;
+use reqwest::header::CONTENT_TYPE;
+use tokio::sync::mpsc;
 
-use crate::threadpool::Threadpool;
-use crate::types::Message;
+use crate::db::Database;
+use crate::feeds;
+use crate::http;
+use crate::sanitizer::Sanitizer;
+use crate::smart_filters::FilterExpr;
+use crate::storage::{Storage, StoreError};
+use crate::threadpool::{CancelToken, TaskPool};
+use crate::types::{format_size, Episode, FeedAuth, Filters, Message, Podcast};
+
+lazy_static! {
+    /// HTTP client shared by all downloads, so that connections and TLS
+    /// setup can be reused.
+    static ref CLIENT: reqwest::Client = http::client(Duration::from_secs(10), Duration::from_secs(120), false);
+
+    /// HTTP client for episodes of feeds whose TLS certificates are not
+    /// checked (see `FeedAuth::accept_invalid_certs`).
+    static ref INSECURE_CLIENT: reqwest::Client = http::client(Duration::from_secs(10), Duration::from_secs(120), true);
+}
+
+/// Bitrate assumed when estimating the size of an episode from its
+/// length: 128 kbps, which is typical for podcasts.
+const ESTIMATED_BYTES_PER_SEC: i64 = 16_000;
 
 /// Enum used for communicating back to the main controller upon
 /// successful or unsuccessful downloading of a file. i32 value
@@ -29,98 +52,460 @@ pub struct EpData {
     pub url: String,
     pub pubdate: Option<DateTime<Utc>>,
     pub file_path: Option<PathBuf>,
+    pub auth: FeedAuth,
 }
 
 /// This is the function the main controller uses to indicate new
-/// files to download. It uses the threadpool to start jobs
+/// files to download. It uses the task pool to start jobs
 /// for every episode to be downloaded. New jobs can be requested
-/// by the user while there are still ongoing jobs.
+/// by the user while there are still ongoing jobs. Once `cancel` is
+/// set, downloads that have not started are skipped, those in progress
+/// stop and remove what they had written so far, and nothing is sent
+/// back to the main controller.
+#[allow(clippy::too_many_arguments)]
 pub fn download_list(
     episodes: Vec<EpData>,
     dest: &Path,
     max_retries: usize,
-    threadpool: &Threadpool,
+    sanitizer: Sanitizer,
+    storage: Arc<dyn Storage>,
+    pool: &TaskPool,
+    cancel: CancelToken,
     tx_to_main: Sender<Message>,
 ) {
     // parse episode details and push to queue
     for ep in episodes.into_iter() {
         let tx = tx_to_main.clone();
         let dest2 = dest.to_path_buf();
-        threadpool.execute(move || {
-            let result = download_file(ep, dest2, max_retries);
-            tx.send(Message::Dl(result))
-                .expect("Thread messaging error");
+        let storage = storage.clone();
+        let cancel = cancel.clone();
+        pool.spawn(async move {
+            if cancel.is_cancelled() {
+                return;
+            }
+            let result = download_file(ep, dest2, max_retries, sanitizer, storage, &cancel).await;
+            if !cancel.is_cancelled() {
+                tx.send(Message::Dl(result))
+                    .expect("Thread messaging error");
+            }
         });
     }
 }
 
+/// Picks the episodes to download across the whole library: those that
+/// are unplayed and not downloaded yet (or were re-released), and that
+/// pass `filters`, the smart filter `smart` (if one is in use), and the
+/// skip rules of their podcast. Archived podcasts are left out.
+pub fn library_episodes<'a, I>(
+    podcasts: I,
+    filters: &Filters,
+    smart: Option<&FilterExpr>,
+) -> Vec<Episode>
+where I: IntoIterator<Item = &'a Podcast> {
+    let mut episodes = Vec::new();
+    for pod in podcasts.into_iter().filter(|pod| !pod.archived) {
+        let skip_rules = pod.skip_rules();
+        let tags = pod.tags();
+        episodes.extend(pod.episodes.filter_map(|ep| {
+            let wanted = !ep.played
+                && ep.enclosure_error.is_none()
+                && (ep.path.is_none() || ep.updated)
+                && skip_rules.allows(&ep.title)
+                && filters.matches(ep)
+                && smart.is_none_or(|expr| expr.matches(ep, &tags));
+            wanted.then(|| ep.clone())
+        }));
+    }
+    return episodes;
+}
+
+/// Describes a batch of episodes about to be downloaded, with a rough
+/// estimate of their size worked out from their lengths, e.g.,
+/// "12 episodes (about 850.3 MB)".
+pub fn describe_batch(episodes: &[Episode]) -> String {
+    let count = match episodes.len() {
+        1 => "1 episode".to_string(),
+        n => format!("{n} episodes"),
+    };
+    let seconds = episodes
+        .iter()
+        .filter_map(|ep| ep.duration)
+        .fold(0i64, |total, duration| total.saturating_add(duration.max(0)));
+    let size = format_size(seconds.saturating_mul(ESTIMATED_BYTES_PER_SEC) as u64);
+    let unknown = episodes.iter().filter(|ep| ep.duration.is_none()).count();
+    return match unknown {
+        0 => format!("{count} (about {size})"),
+        n if n == episodes.len() => format!("{count} (size unknown)"),
+        n => format!("{count} (about {size}, not counting {n} of unknown length)"),
+    };
+}
+
+/// Records a finished download in the database and in the episode in
+/// `podcast`. A re-released episode replaces the stale file it was
+/// downloaded to before, which is deleted.
+pub fn finish(
+    db: &Database,
+    storage: &dyn Storage,
+    podcast: &Podcast,
+    ep_id: i64,
+    file_path: &Path,
+) -> anyhow::Result<()> {
+    let episode = podcast.episodes.clone_episode(ep_id);
+    if let Some(old_path) = episode.as_ref().and_then(|ep| ep.path.as_deref()) {
+        if old_path != file_path {
+            let _ = storage.discard(old_path);
+        }
+        let _ = db.remove_file(ep_id);
+        let _ = db.set_updated_status(ep_id, false);
+    }
+    db.insert_file(ep_id, file_path)?;
+    if let Some(mut episode) = episode {
+        episode.path = Some(file_path.to_path_buf());
+        episode.updated = false;
+        podcast.episodes.replace(ep_id, episode);
+    }
+    return Ok(());
+}
+
+/// Deletes the oldest downloaded episodes of a podcast once there are
+/// more than it is set to keep, except for the episode `spare` (the one
+/// that is playing, if any). Returns how many were deleted.
+pub fn prune(db: &Database, storage: &dyn Storage, podcast: &Podcast, spare: Option<i64>) -> usize {
+    let keep = match podcast.overrides.keep_downloads {
+        Some(keep) => keep,
+        None => return 0,
+    };
+    let downloaded: Vec<i64> = podcast
+        .episodes
+        .ids_newest_first()
+        .into_iter()
+        .filter(|id| {
+            Some(*id) != spare
+                && podcast.episodes.map_single(*id, |ep| ep.path.is_some()) == Some(true)
+        })
+        .collect();
+    let mut removed = 0;
+    for ep_id in to_prune(downloaded, keep) {
+        let mut episode = match podcast.episodes.clone_episode(ep_id) {
+            Some(episode) => episode,
+            None => continue,
+        };
+        let path = match episode.path.take() {
+            Some(path) => path,
+            None => continue,
+        };
+        if storage.discard(&path).is_ok() && db.remove_file(episode.id).is_ok() {
+            podcast.episodes.replace(episode.id, episode);
+            removed += 1;
+        }
+    }
+    return removed;
+}
+
+/// Picks the downloads to delete so that only the newest `keep` of them
+/// are left, given the ids of the downloaded episodes, newest first. A
+/// limit of 0 or less is taken to mean there is no limit, so a typo
+/// can't delete every download at once.
+fn to_prune(downloaded: Vec<i64>, keep: i64) -> Vec<i64> {
+    if keep <= 0 {
+        return Vec::new();
+    }
+    return downloaded.into_iter().skip(keep as usize).collect();
+}
+
+/// Reader over the body of a download, a chunk at a time as the chunks
+/// arrive. A `None` chunk marks the end of the file; if the channel
+/// closes before that (e.g., the download was cancelled), reading
+/// fails, so that a partial file is not taken for a complete one.
+struct ChunkReader {
+    rx: mpsc::Receiver<Option<Vec<u8>>>,
+    chunk: Vec<u8>,
+    pos: usize,
+    done: bool,
+}
 
-/// Downloads a file to a local filepath, returning DownloadMsg variant
-/// indicating success or failure.
-fn download_file(mut ep_data: EpData, dest: PathBuf, mut max_retries: usize) -> DownloadMsg {
-    let agent_builder = ureq::builder()
-        .timeout_connect(Duration::from_secs(10))
-        .timeout_read(Duration::from_secs(120))
-        .redirects(10);
-
-    #[cfg(feature = "native_tls")]
-    let tls_connector = std::sync::Arc::new(native_tls::TlsConnector::new().unwrap());
-    #[cfg(feature = "native_tls")]
-    let agent_builder = agent_builder.tls_connector(tls_connector);
-    let agent = agent_builder.build();
-
-    let request: Result<ureq::Response, ()> = loop {
-        let response = agent.get(&ep_data.url).call();
-        match response {
-            Ok(resp) => break Ok(resp),
-            Err(_) => {
-                max_retries -= 1;
-                if max_retries == 0 {
-                    break Err(());
+impl Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos >= self.chunk.len() {
+            if self.done {
+                return Ok(0);
+            }
+            match self.rx.blocking_recv() {
+                Some(Some(chunk)) => {
+                    self.chunk = chunk;
+                    self.pos = 0;
                 }
+                Some(None) => self.done = true,
+                None => return Err(io::Error::other("Download stopped")),
             }
         }
-    };
+        let len = buf.len().min(self.chunk.len() - self.pos);
+        buf[..len].copy_from_slice(&self.chunk[self.pos..self.pos + len]);
+        self.pos += len;
+        return Ok(len);
+    }
+}
 
-    if request.is_err() {
-        return DownloadMsg::ResponseError(ep_data);
-    };
 
-    let response = request.unwrap();
+/// Downloads a file into the storage backend (usually a local
+/// filepath), returning DownloadMsg variant indicating success or
+/// failure.
+async fn download_file(
+    mut ep_data: EpData,
+    dest: PathBuf,
+    max_retries: usize,
+    sanitizer: Sanitizer,
+    storage: Arc<dyn Storage>,
+    cancel: &CancelToken,
+) -> DownloadMsg {
+    let client: &reqwest::Client = if ep_data.auth.accept_invalid_certs {
+        log::warn!("Not checking the TLS certificate of {}", ep_data.url);
+        &INSECURE_CLIENT
+    } else {
+        &CLIENT
+    };
+    let auth = feeds::load_password(&ep_data.auth).await;
+    let mut attempt = 0;
+    let mut response = loop {
+        let request = feeds::authorize(client.get(&ep_data.url), &auth);
+        let response = tokio::select! {
+            biased;
+            _ = cancel.cancelled() => return DownloadMsg::ResponseError(ep_data),
+            response = request.send() => response,
+        };
+        match response.and_then(|resp| resp.error_for_status()) {
+            Ok(resp) => break resp,
+            Err(err) => {
+                let err = anyhow::anyhow!(err);
+                log::debug!("Request for {} failed: {err:#}", ep_data.url);
+                attempt += 1;
+                if attempt >= max_retries {
+                    log::warn!("Could not download {}: {err:#}", ep_data.url);
+                    return DownloadMsg::ResponseError(ep_data);
+                }
+                tokio::select! {
+                    biased;
+                    _ = cancel.cancelled() => return DownloadMsg::ResponseError(ep_data),
+                    _ = tokio::time::sleep(http::retry_delay(attempt)) => (),
+                }
+            }
+        }
+    };
 
     // figure out the file type
-    let ext = match response.header("content-type") {
-        Some("audio/x-m4a") => "m4a",
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|h| h.to_str().ok());
+    let mime = content_type.map(|mime| mime.split(';').next().unwrap_or("").trim());
+    let ext = match mime {
+        Some("audio/x-m4a") | Some("audio/mp4") => "m4a",
         Some("audio/mpeg") => "mp3",
+        Some("audio/opus") => "opus",
+        Some("audio/ogg") => "ogg",
+        Some("audio/aac") => "aac",
         Some("video/quicktime") => "mov",
         Some("video/mp4") => "mp4",
         Some("video/x-m4v") => "m4v",
         _ => "mp3", // assume .mp3 unless we figure out otherwise
     };
 
-    let mut file_name = sanitize_with_options(&ep_data.title, Options {
-        truncate: true,
-        windows: true, // for simplicity, we'll just use Windows-friendly paths for everyone
-        replacement: "",
-    });
-
+    let mut file_name = ep_data.title.clone();
     if let Some(pubdate) = ep_data.pubdate {
         file_name = format!("{}_{}", file_name, pubdate.format("%Y%m%d_%H%M%S"));
     }
 
     let mut file_path = dest;
-    file_path.push(format!("{file_name}.{ext}"));
+    file_path.push(sanitizer.sanitize_with_ext(&file_name, ext));
 
-    let dst = File::create(&file_path);
-    if dst.is_err() {
-        return DownloadMsg::FileCreateError(ep_data);
+    // the storage backends write from a blocking reader, so they run on
+    // their own thread, and are passed the body as it arrives
+    let (tx, rx) = mpsc::channel(16);
+    let writer = {
+        let storage = storage.clone();
+        let file_path = file_path.clone();
+        tokio::task::spawn_blocking(move || {
+            let reader = ChunkReader {
+                rx: rx,
+                chunk: Vec::new(),
+                pos: 0,
+                done: false,
+            };
+            storage.store(&file_path, Box::new(reader))
+        })
     };
+    loop {
+        let chunk = tokio::select! {
+            biased;
+            _ = cancel.cancelled() => break,
+            chunk = response.chunk() => chunk,
+        };
+        let sent = match chunk {
+            Ok(Some(bytes)) => tx.send(Some(bytes.to_vec())).await,
+            Ok(None) => {
+                let _ = tx.send(None).await;
+                break;
+            }
+            Err(_) => break,
+        };
+        // the storage backend stopped reading, i.e., it failed
+        if sent.is_err() {
+            break;
+        }
+    }
+    drop(tx);
 
+    let result = writer.await.unwrap_or(Err(StoreError::Write));
+    if result.is_err() && cancel.is_cancelled() {
+        let file_path = file_path.clone();
+        let _ = tokio::task::spawn_blocking(move || storage.remove(&file_path)).await;
+    }
+    if result.is_err() && !cancel.is_cancelled() {
+        log::warn!(
+            "Could not save {} to {}",
+            ep_data.url,
+            file_path.to_string_lossy()
+        );
+    }
     ep_data.file_path = Some(file_path);
-
-    let mut reader = response.into_reader();
-    return match std::io::copy(&mut reader, &mut dst.unwrap()) {
+    return match result {
         Ok(_) => DownloadMsg::Complete(ep_data),
-        Err(_) => DownloadMsg::FileWriteError(ep_data),
+        Err(StoreError::Create) => DownloadMsg::FileCreateError(ep_data),
+        Err(StoreError::Write) => DownloadMsg::FileWriteError(ep_data),
     };
 }
+
+
+// TESTS -----------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk_reader() -> (mpsc::Sender<Option<Vec<u8>>>, ChunkReader) {
+        let (tx, rx) = mpsc::channel(16);
+        let reader = ChunkReader {
+            rx: rx,
+            chunk: Vec::new(),
+            pos: 0,
+            done: false,
+        };
+        return (tx, reader);
+    }
+
+    #[test]
+    fn chunks_read_in_order() {
+        let (tx, mut reader) = chunk_reader();
+        tx.blocking_send(Some(b"abc".to_vec())).unwrap();
+        tx.blocking_send(Some(Vec::new())).unwrap();
+        tx.blocking_send(Some(b"defg".to_vec())).unwrap();
+        tx.blocking_send(None).unwrap();
+
+        let mut buf = [0; 2];
+        assert_eq!(reader.read(&mut buf).unwrap(), 2);
+        assert_eq!(&buf, b"ab");
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"cdefg");
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn chunks_stopped_early() {
+        let (tx, mut reader) = chunk_reader();
+        tx.blocking_send(Some(b"abc".to_vec())).unwrap();
+        drop(tx);
+
+        // a download that stops before its end is an error, not a
+        // shorter file
+        let mut data = Vec::new();
+        assert!(reader.read_to_end(&mut data).is_err());
+        assert_eq!(data, b"abc");
+    }
+
+    #[test]
+    fn cancelled_downloads_report_nothing() {
+        let dest = std::env::temp_dir().join(format!("shellcaster-cancel-{}", std::process::id()));
+        let storage = crate::storage::from_config(&dest, None, None);
+        let sanitizer = Sanitizer::new(crate::sanitizer::SanitizeProfile::Conservative, 100);
+        let pool = TaskPool::new(2);
+        let (tx, rx) = std::sync::mpsc::channel();
+        let ep_data = EpData {
+            id: 1,
+            pod_id: 1,
+            title: "Episode".to_string(),
+            url: "http://127.0.0.1:9/episode.mp3".to_string(),
+            pubdate: None,
+            file_path: None,
+            auth: FeedAuth::default(),
+        };
+
+        // a download that fails is reported back
+        let eps = vec![ep_data.clone()];
+        let cancel = CancelToken::new();
+        download_list(eps, &dest, 1, sanitizer, storage.clone(), &pool, cancel, tx.clone());
+        let msg = rx.recv_timeout(Duration::from_secs(30)).unwrap();
+        assert!(matches!(msg, Message::Dl(DownloadMsg::ResponseError(_))));
+
+        // once cancelled, downloads are skipped without a word
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        download_list(vec![ep_data], &dest, 1, sanitizer, storage, &pool, cancel, tx);
+        assert!(rx.recv_timeout(Duration::from_millis(500)).is_err());
+    }
+
+    fn episode(duration: Option<i64>) -> Episode {
+        return Episode {
+            id: 1,
+            pod_id: 1,
+            title: "Episode".to_string(),
+            url: String::new(),
+            guid: String::new(),
+            description: String::new(),
+            pubdate: None,
+            duration: duration,
+            image_url: None,
+            chapters_url: None,
+            chapters: Vec::new(),
+            transcript_url: None,
+            transcript_type: None,
+            enclosures: Vec::new(),
+            enclosure_error: None,
+            path: None,
+            played: false,
+            updated: false,
+            starred: false,
+            clean_title: None,
+            position: None,
+            last_played: None,
+            new: false,
+        };
+    }
+
+    #[test]
+    fn batch_size_estimate() {
+        let episodes = vec![episode(Some(3600)), episode(None)];
+        assert_eq!(
+            describe_batch(&episodes),
+            "2 episodes (about 54.9 MB, not counting 1 of unknown length)"
+        );
+        assert_eq!(describe_batch(&episodes[1..]), "1 episode (size unknown)");
+    }
+
+    #[test]
+    fn batch_size_does_not_overflow() {
+        let episodes = vec![episode(Some(i64::MAX)), episode(Some(i64::MAX)), episode(Some(-5))];
+        assert!(describe_batch(&episodes).starts_with("3 episodes (about "));
+    }
+
+    #[test]
+    fn prune_keeps_newest() {
+        assert_eq!(to_prune(vec![5, 4, 3, 2, 1], 2), vec![3, 2, 1]);
+        assert_eq!(to_prune(vec![5, 4], 3), Vec::<i64>::new());
+    }
+
+    #[test]
+    fn prune_zero_is_off() {
+        assert_eq!(to_prune(vec![3, 2, 1], 0), Vec::<i64>::new());
+        assert_eq!(to_prune(vec![3, 2, 1], -1), Vec::<i64>::new());
+    }
+}
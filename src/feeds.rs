@@ -1,5 +1,5 @@
 use anyhow::{anyhow, Result};
-use std::io::Read;
+use std::process::{Command, Stdio};
 use std::sync::mpsc;
 use std::time::Duration;
 
@@ -7,26 +7,72 @@ use chrono::{DateTime, Utc};
 use lazy_static::lazy_static;
 use regex::{Match, Regex};
 use rfc822_sanitizer::parse_from_rfc2822_with_fallback;
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::{StatusCode, Url};
 use rss::{Channel, Item};
 
-use crate::threadpool::Threadpool;
+use crate::command;
+use crate::db::{Database, SyncPreview};
+use crate::http;
+use crate::storage;
+use crate::threadpool::{CancelToken, TaskPool};
+use crate::transcript::TranscriptFormat;
 use crate::types::*;
 
 lazy_static! {
     /// Regex for parsing an episode "duration", which could take the form
     /// of HH:MM:SS, MM:SS, or SS.
     static ref RE_DURATION: Regex = Regex::new(r"(\d+)(?::(\d+))?(?::(\d+))?").expect("Regex error");
+
+    /// HTTP client shared by all feed requests, so that connections and
+    /// TLS setup can be reused.
+    static ref CLIENT: reqwest::Client = http::client(Duration::from_secs(5), Duration::from_secs(20), false);
+
+    /// HTTP client for feeds whose TLS certificates are not checked (see
+    /// `FeedAuth::accept_invalid_certs`).
+    static ref INSECURE_CLIENT: reqwest::Client = http::client(Duration::from_secs(5), Duration::from_secs(20), true);
 }
 
+/// URI of the Podcasting 2.0 namespace, which defines tags such as
+/// `podcast:chapters`.
+const PODCAST_NAMESPACE: &str = "https://podcastindex.org/namespace/1.0";
+
 /// Enum for communicating back to the main thread after feed data has
-/// been retrieved.
+/// been retrieved. Feed data is boxed, as it is much larger than the
+/// other messages.
 #[derive(Debug)]
 pub enum FeedMsg {
-    NewData(PodcastNoId),
-    SyncData((i64, PodcastNoId)),
+    NewData(Box<PodcastNoId>),
+    SyncData((i64, Box<PodcastNoId>)),
+    NotModified(PodcastFeed),
+    AuthRequired(PodcastFeed),
     Error(PodcastFeed),
+    Checked(PodcastFeed, FeedCheck),
 }
 
+/// What was found when fetching a feed only to check it for changes
+/// (see `PodcastFeed::check_only`).
+#[derive(Debug)]
+pub enum FeedCheck {
+    Fetched(Box<PodcastNoId>),
+    NotModified,
+    AuthRequired,
+    Error(String),
+}
+
+/// Error returned when a feed asks for credentials (HTTP 401), so that
+/// the user can be asked for them instead of the feed just failing.
+#[derive(Debug)]
+struct AuthRequired;
+
+impl std::fmt::Display for AuthRequired {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        return write!(f, "Feed requires authentication");
+    }
+}
+
+impl std::error::Error for AuthRequired {}
+
 /// Struct holding data about a podcast feed (subset of info about a
 /// podcast) for the purpose of passing back and forth between threads.
 #[derive(Debug, Clone)]
@@ -34,6 +80,10 @@ pub struct PodcastFeed {
     pub id: Option<i64>,
     pub url: String,
     pub title: Option<String>,
+    pub settings: Option<Box<PodcastSettings>>,
+    pub auth: FeedAuth,
+    pub validators: FeedValidators,
+    pub check_only: bool,
 }
 
 impl PodcastFeed {
@@ -42,69 +92,233 @@ impl PodcastFeed {
             id: id,
             url: url,
             title: title,
+            settings: None,
+            auth: FeedAuth::default(),
+            validators: FeedValidators::default(),
+            check_only: false,
         };
     }
 }
 
-/// Spawns a new thread to check a feed and retrieve podcast data.
+/// Starts a job on the task pool to check a feed and retrieve podcast
+/// data. If `url_hook` is set, the feed URL is passed through that
+/// command first to get the URL that is actually requested. Once
+/// `cancel` is set, the request is dropped and nothing is sent back to
+/// the main controller.
 pub fn check_feed(
     feed: PodcastFeed,
     max_retries: usize,
-    threadpool: &Threadpool,
+    url_hook: Option<String>,
+    pool: &TaskPool,
+    cancel: CancelToken,
     tx_to_main: mpsc::Sender<Message>,
 ) {
-    threadpool.execute(move || match get_feed_data(feed.url.clone(), max_retries) {
-        Ok(pod) => match feed.id {
-            Some(id) => {
+    pool.spawn(async move {
+        let result = tokio::select! {
+            biased;
+            _ = cancel.cancelled() => return,
+            result = get_feed_data(&feed, max_retries, url_hook) => result,
+        };
+
+        // feeds that are only being checked report what was found
+        // instead, so that nothing gets saved
+        if feed.check_only {
+            let found = match result {
+                Ok(None) => FeedCheck::NotModified,
+                Ok(Some(pod)) => FeedCheck::Fetched(Box::new(pod)),
+                Err(err) if err.is::<AuthRequired>() => FeedCheck::AuthRequired,
+                Err(err) => FeedCheck::Error(format!("{err}")),
+            };
+            tx_to_main
+                .send(Message::Feed(FeedMsg::Checked(feed, found)))
+                .expect("Thread messaging error");
+            return;
+        }
+        match result {
+            Ok(None) => tx_to_main
+                .send(Message::Feed(FeedMsg::NotModified(feed)))
+                .expect("Thread messaging error"),
+            Ok(Some(pod)) => match feed.id {
+                Some(id) => {
+                    tx_to_main
+                        .send(Message::Feed(FeedMsg::SyncData((id, Box::new(pod)))))
+                        .expect("Thread messaging error");
+                }
+                None => tx_to_main
+                    .send(Message::Feed(FeedMsg::NewData(Box::new(pod))))
+                    .expect("Thread messaging error"),
+            },
+            Err(err) if err.is::<AuthRequired>() => tx_to_main
+                .send(Message::Feed(FeedMsg::AuthRequired(feed)))
+                .expect("Thread messaging error"),
+            Err(err) => {
+                log::warn!("Could not sync {}: {err:#}", feed.url);
                 tx_to_main
-                    .send(Message::Feed(FeedMsg::SyncData((id, pod))))
+                    .send(Message::Feed(FeedMsg::Error(feed)))
                     .expect("Thread messaging error");
             }
-            None => tx_to_main
-                .send(Message::Feed(FeedMsg::NewData(pod)))
-                .expect("Thread messaging error"),
-        },
-        Err(_err) => tx_to_main
-            .send(Message::Feed(FeedMsg::Error(feed)))
-            .expect("Thread messaging error"),
+        }
     });
 }
 
-/// Given a URL, this attempts to pull the data about a podcast and its
-/// episodes from an RSS feed.
-fn get_feed_data(url: String, mut max_retries: usize) -> Result<PodcastNoId> {
-    let agent_builder = ureq::builder()
-        .timeout_connect(Duration::from_secs(5))
-        .timeout_read(Duration::from_secs(20));
-    #[cfg(feature = "native_tls")]
-    let tls_connector = std::sync::Arc::new(native_tls::TlsConnector::new().unwrap());
-    #[cfg(feature = "native_tls")]
-    let agent_builder = agent_builder.tls_connector(tls_connector);
-    let agent = agent_builder.build();
-
-    let request: Result<ureq::Response> = loop {
-        let response = agent.get(&url).call();
-        match response {
-            Ok(resp) => break Ok(resp),
-            Err(_) => {
-                max_retries -= 1;
-                if max_retries == 0 {
-                    break Err(anyhow!("No response from feed"));
+/// Returns lines describing what syncing a feed that was only checked
+/// would change (see `SyncPreview::report()`), or why it can't be told.
+pub fn check_report(db: &Database, feed: &PodcastFeed, found: &FeedCheck) -> Vec<String> {
+    let title = feed.title.clone().unwrap_or_else(|| feed.url.clone());
+    return match (found, feed.id) {
+        (FeedCheck::Fetched(pod), Some(id)) => match db.preview_sync(id, pod) {
+            Ok(preview) => preview.report(&title),
+            Err(_) => vec![format!("{title}: could not be compared with the database")],
+        },
+        (FeedCheck::Fetched(_), None) => vec![format!("{title}: not in the database")],
+        (FeedCheck::NotModified, _) => SyncPreview::default().report(&title),
+        (FeedCheck::AuthRequired, _) => vec![format!("{title}: needs a login")],
+        (FeedCheck::Error(err), _) => vec![format!("{title}: could not be fetched ({err})")],
+    };
+}
+
+/// Returns the feed HTTP client to use with the given settings of a
+/// private feed.
+fn client_for(auth: &FeedAuth) -> &'static reqwest::Client {
+    if auth.accept_invalid_certs {
+        return &INSECURE_CLIENT;
+    }
+    return &CLIENT;
+}
+
+/// Reads the password of a private feed from the keyring, if it is
+/// kept there, without holding up the other jobs on the task pool.
+pub async fn load_password(auth: &FeedAuth) -> FeedAuth {
+    let auth = auth.clone();
+    if auth.password.is_some() || auth.keyring_entry.is_none() {
+        return auth;
+    }
+    let fallback = auth.clone();
+    return tokio::task::spawn_blocking(move || auth.with_password())
+        .await
+        .unwrap_or(fallback);
+}
+
+/// Adds the credentials and extra headers of a private feed to a
+/// request.
+pub fn authorize(
+    mut request: reqwest::RequestBuilder,
+    auth: &FeedAuth,
+) -> reqwest::RequestBuilder {
+    if let Some(user) = &auth.user {
+        request = request.header(
+            "Authorization",
+            storage::basic_auth(user, auth.password.as_deref()),
+        );
+    }
+    for (name, value) in auth.header_pairs() {
+        request = request.header(name, value);
+    }
+    return request;
+}
+
+/// Given a feed, this attempts to pull the data about a podcast and its
+/// episodes from an RSS feed, sending the feed's credentials, if any.
+/// When syncing an existing podcast, the validators from the last sync
+/// are sent along, and None is returned if the feed has not changed.
+async fn get_feed_data(
+    feed: &PodcastFeed,
+    max_retries: usize,
+    url_hook: Option<String>,
+) -> Result<Option<PodcastNoId>> {
+    let url = &feed.url;
+    if feed.auth.accept_invalid_certs {
+        log::warn!("Not checking the TLS certificate of {url}");
+    }
+    // the resolved URL is only used for this request; the podcast keeps
+    // the original URL so it can be resolved again on the next sync
+    let request_url = match url_hook {
+        Some(hook) => {
+            let url = url.clone();
+            tokio::task::spawn_blocking(move || resolve_url(&hook, &url)).await??
+        }
+        None => url.clone(),
+    };
+
+    let mut attempt = 0;
+    let auth = load_password(&feed.auth).await;
+    let response = loop {
+        let mut request = authorize(client_for(&auth).get(&request_url), &auth);
+        if feed.id.is_some() {
+            if let Some(etag) = &feed.validators.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &feed.validators.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+        match request.send().await {
+            Ok(resp) if resp.status() == StatusCode::UNAUTHORIZED => {
+                return Err(anyhow!(AuthRequired))
+            }
+            Ok(resp) if !resp.status().is_client_error() && !resp.status().is_server_error() => {
+                break resp
+            }
+            result => {
+                let reason = match result {
+                    Ok(resp) => format!("status {}", resp.status()),
+                    Err(err) => format!("{:#}", anyhow!(err)),
+                };
+                log::debug!("Request for {request_url} failed: {reason}");
+                attempt += 1;
+                if attempt >= max_retries {
+                    return Err(anyhow!("No response from feed ({reason})"));
                 }
+                tokio::time::sleep(http::retry_delay(attempt)).await;
             }
         }
     };
 
-    return match request {
-        Ok(resp) => {
-            let mut reader = resp.into_reader();
-            let mut resp_data = Vec::new();
-            reader.read_to_end(&mut resp_data)?;
+    if response.status() == StatusCode::NOT_MODIFIED {
+        return Ok(None);
+    }
+    let header = |name| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|h| h.to_str().ok())
+            .map(|h| h.to_string())
+    };
+    let validators = FeedValidators {
+        etag: header(ETAG),
+        last_modified: header(LAST_MODIFIED),
+    };
+    // relative links in the feed are relative to where it was finally
+    // fetched from, after any redirects
+    let base = response.url().clone();
+    let resp_data = response.bytes().await?;
+
+    let channel = Channel::read_from(&resp_data[..])?;
+    let mut pod = parse_feed_data(channel, url, Some(&base));
+    pod.validators = validators;
+    return Ok(Some(pod));
+}
 
-            let channel = Channel::read_from(&resp_data[..])?;
-            Ok(parse_feed_data(channel, &url))
-        }
-        Err(err) => Err(err),
+/// Runs the user's URL hook command to get the URL that should be
+/// requested for a feed, e.g., for private feeds that use signed URLs
+/// which expire. The feed URL is inserted wherever "%s" appears in the
+/// command (or appended to the end if there is no "%s"), and the first
+/// line the command prints is used as the new URL. If the command
+/// prints nothing, the original URL is used.
+fn resolve_url(hook: &str, url: &str) -> Result<String> {
+    let (program, args) = command::parse(hook, url, true)?;
+    let output = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow!("URL hook exited with an error"));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    return match stdout.lines().map(|l| l.trim()).find(|l| !l.is_empty()) {
+        Some(new_url) => Ok(new_url.to_string()),
+        None => Ok(url.to_string()),
     };
 }
 
@@ -113,17 +327,32 @@ fn get_feed_data(url: String, mut max_retries: usize) -> Result<PodcastNoId> {
 /// podcast and its episodes and returns a Podcast. There are existing
 /// specifications for podcast RSS feeds that a feed should adhere to, but
 /// this does try to make some attempt to account for the possibility that
-/// a feed might not be valid according to the spec.
-fn parse_feed_data(channel: Channel, url: &str) -> PodcastNoId {
+/// a feed might not be valid according to the spec. Relative enclosure
+/// URLs are resolved against `base`.
+fn parse_feed_data(channel: Channel, url: &str, base: Option<&Url>) -> PodcastNoId {
     let title = channel.title().to_string();
     let url = url.to_string();
     let description = Some(channel.description().to_string());
     let last_checked = Utc::now();
 
+    let language = channel.language().map(|l| l.trim().to_string()).filter(|l| !l.is_empty());
+    let website = Some(channel.link().trim().to_string()).filter(|l| !l.is_empty());
+
     let mut author = None;
     let mut explicit = None;
+    let mut categories = Vec::new();
+    let mut image_url = channel.image().map(|img| img.url().to_string());
     if let Some(itunes) = channel.itunes_ext() {
         author = itunes.author().map(|a| a.to_string());
+        for category in itunes.categories() {
+            categories.push(category.text().to_string());
+            if let Some(sub) = category.subcategory() {
+                categories.push(sub.text().to_string());
+            }
+        }
+        if let Some(img) = itunes.image() {
+            image_url = Some(img.to_string());
+        }
         explicit = match itunes.explicit() {
             None => None,
             Some(s) => {
@@ -137,11 +366,77 @@ fn parse_feed_data(channel: Channel, url: &str) -> PodcastNoId {
         };
     }
 
+    // the iTunes categories are more consistent between feeds, so the
+    // plain RSS ones are only used if there are none
+    if categories.is_empty() {
+        categories = channel.categories().iter().map(|c| c.name().to_string()).collect();
+    }
+    categories = categories
+        .into_iter()
+        .map(|c| c.trim().to_string())
+        .filter(|c| !c.is_empty())
+        .collect();
+    categories.dedup();
+
+    // feeds almost always use the "podcast" prefix for the Podcasting
+    // 2.0 namespace, but they are free to choose another one
+    let podcast_ns = channel
+        .namespaces()
+        .iter()
+        .find(|(_, uri)| uri.trim_end_matches('/') == PODCAST_NAMESPACE)
+        .map(|(prefix, _)| prefix.clone())
+        .unwrap_or_else(|| "podcast".to_string());
+
+    // a feed may list several ways to support the show; the first one
+    // is taken to be the main one
+    let funding = channel
+        .extensions()
+        .get(&podcast_ns)
+        .and_then(|ext| ext.get("funding"))
+        .and_then(|tags| {
+            tags.iter().find_map(|tag| {
+                let url = tag.attrs().get("url")?.trim();
+                let text = tag.value().map(|t| t.trim()).filter(|t| !t.is_empty());
+                (!url.is_empty()).then(|| (url.to_string(), text.map(|t| t.to_string())))
+            })
+        });
+    let (funding_url, funding_text) = match funding {
+        Some((url, text)) => (Some(url), text),
+        None => (None, None),
+    };
+
+    // hints about when the feed is worth checking; hour 24 is taken to
+    // mean midnight, and day names are stored as, e.g., "Saturday"
+    let ttl = channel.ttl().and_then(|t| t.trim().parse::<i64>().ok()).filter(|t| *t > 0);
+    let mut skip_hours: Vec<u32> = channel
+        .skip_hours()
+        .iter()
+        .filter_map(|h| h.trim().parse::<u32>().ok())
+        .filter(|h| *h <= 24)
+        .map(|h| h % 24)
+        .collect();
+    skip_hours.sort_unstable();
+    skip_hours.dedup();
+    let mut skip_days = Vec::new();
+    for day in channel.skip_days() {
+        if let Ok(weekday) = day.trim().parse::<chrono::Weekday>() {
+            let name = weekday_name(weekday);
+            if !skip_days.iter().any(|d| d == name) {
+                skip_days.push(name.to_string());
+            }
+        }
+    }
+    let schedule = FeedSchedule {
+        ttl: ttl,
+        skip_hours: skip_hours,
+        skip_days: skip_days,
+    };
+
     let mut episodes = Vec::new();
     let items = channel.into_items();
     if !items.is_empty() {
         for item in &items {
-            episodes.push(parse_episode_data(item));
+            episodes.push(parse_episode_data(item, &podcast_ns, base));
         }
     }
 
@@ -152,24 +447,53 @@ fn parse_feed_data(channel: Channel, url: &str) -> PodcastNoId {
         author: author,
         explicit: explicit,
         last_checked: last_checked,
+        image_url: image_url,
+        categories: categories,
+        language: language,
+        website: website,
+        funding_url: funding_url,
+        funding_text: funding_text,
+        validators: FeedValidators::default(),
+        schedule: schedule,
         episodes: episodes,
     };
 }
 
+/// Returns the full English name of a day of the week, as used in the
+/// `skipDays` element of RSS feeds.
+fn weekday_name(day: chrono::Weekday) -> &'static str {
+    return match day {
+        chrono::Weekday::Mon => "Monday",
+        chrono::Weekday::Tue => "Tuesday",
+        chrono::Weekday::Wed => "Wednesday",
+        chrono::Weekday::Thu => "Thursday",
+        chrono::Weekday::Fri => "Friday",
+        chrono::Weekday::Sat => "Saturday",
+        chrono::Weekday::Sun => "Sunday",
+    };
+}
+
 /// For an item (episode) in an RSS feed, this pulls data about the item
 /// and converts it to an Episode. There are existing specifications for
 /// podcast RSS feeds that a feed should adhere to, but this does try to
 /// make some attempt to account for the possibility that a feed might
-/// not be valid according to the spec.
-fn parse_episode_data(item: &Item) -> EpisodeNoId {
+/// not be valid according to the spec. `podcast_ns` is the prefix the
+/// feed uses for the Podcasting 2.0 namespace. An episode whose
+/// enclosure is missing or cannot be made sense of is still kept, with
+/// `enclosure_error` saying what is wrong with it.
+fn parse_episode_data(item: &Item, podcast_ns: &str, base: Option<&Url>) -> EpisodeNoId {
     let title = match item.title() {
         Some(s) => s.to_string(),
         None => "".to_string(),
     };
-    let url = match item.enclosure() {
-        Some(enc) => enc.url().to_string(),
-        None => "".to_string(),
+    let (url, enclosure_error) = match item.enclosure().map(|enc| enc.url()) {
+        Some(raw) if !raw.trim().is_empty() => match clean_url(raw, base) {
+            Ok(url) => (url, None),
+            Err(err) => (raw.trim().to_string(), Some(err)),
+        },
+        _ => (String::new(), Some("The feed does not link to a file".to_string())),
     };
+    let enclosures = parse_enclosures(item, podcast_ns, base);
     let guid = match item.guid() {
         Some(guid) => guid.value().to_string(),
         None => "".to_string(),
@@ -194,9 +518,42 @@ fn parse_episode_data(item: &Item) -> EpisodeNoId {
     };
 
     let mut duration = None;
+    let mut image_url = None;
     if let Some(itunes) = item.itunes_ext() {
         duration = duration_to_int(itunes.duration()).map(|dur| dur as i64);
+        image_url = itunes.image().map(|img| img.to_string());
+    }
+
+    let chapters_url = item
+        .extensions()
+        .get(podcast_ns)
+        .and_then(|ext| ext.get("chapters"))
+        .and_then(|tags| tags.first())
+        .and_then(|tag| tag.attrs().get("url"))
+        .map(|url| url.to_string());
+
+    // episodes may list several transcripts in different formats, so
+    // this picks the one in the most useful format that can be shown
+    let mut transcript = None;
+    if let Some(tags) = item
+        .extensions()
+        .get(podcast_ns)
+        .and_then(|ext| ext.get("transcript"))
+    {
+        transcript = tags
+            .iter()
+            .filter_map(|tag| {
+                let url = tag.attrs().get("url")?;
+                let mime = tag.attrs().get("type")?;
+                let format = TranscriptFormat::from_mime(mime)?;
+                Some((format, url.to_string(), mime.to_string()))
+            })
+            .min_by_key(|(format, _, _)| *format);
     }
+    let (transcript_url, transcript_type) = match transcript {
+        Some((_, url, mime)) => (Some(url), Some(mime)),
+        None => (None, None),
+    };
 
     return EpisodeNoId {
         title: title,
@@ -205,6 +562,12 @@ fn parse_episode_data(item: &Item) -> EpisodeNoId {
         description: description,
         pubdate: pubdate,
         duration: duration,
+        image_url: image_url,
+        chapters_url: chapters_url,
+        transcript_url: transcript_url,
+        transcript_type: transcript_type,
+        enclosures: enclosures,
+        enclosure_error: enclosure_error,
     };
 }
 
@@ -263,6 +626,99 @@ fn duration_to_int(duration: Option<&str>) -> Option<i32> {
     }
 }
 
+/// Lists the versions of an episode that a feed offers with the
+/// `alternateEnclosure` tag of the Podcasting 2.0 namespace, after its
+/// main enclosure. Each alternate is downloaded from the first of its
+/// sources that is a web address. Returns an empty list if the feed
+/// does not list any alternates.
+fn parse_enclosures(item: &Item, podcast_ns: &str, base: Option<&Url>) -> Vec<Enclosure> {
+    let number = |attr: Option<&String>| attr.and_then(|n| n.trim().parse::<f64>().ok());
+    let alternates: Vec<Enclosure> = item
+        .extensions()
+        .get(podcast_ns)
+        .and_then(|ext| ext.get("alternateEnclosure"))
+        .map(|tags| {
+            tags.iter()
+                .filter_map(|tag| {
+                    let url = tag
+                        .children()
+                        .get("source")?
+                        .iter()
+                        .filter_map(|source| source.attrs().get("uri"))
+                        .find_map(|uri| clean_url(uri, base).ok())?;
+                    Some(Enclosure {
+                        url: url,
+                        mime: tag.attrs().get("type").map(|t| t.to_string()),
+                        bitrate: number(tag.attrs().get("bitrate")).map(|b| b as i64),
+                        length: number(tag.attrs().get("length")).map(|l| l as i64),
+                        title: tag.attrs().get("title").map(|t| t.to_string()),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    if alternates.is_empty() {
+        return alternates;
+    }
+
+    let mut enclosures = Vec::new();
+    if let Some((enc, url)) = item
+        .enclosure()
+        .and_then(|enc| clean_url(enc.url(), base).ok().map(|url| (enc, url)))
+    {
+        enclosures.push(Enclosure {
+            url: url,
+            mime: Some(enc.mime_type().to_string()).filter(|m| !m.is_empty()),
+            bitrate: None,
+            length: enc.length().trim().parse().ok(),
+            title: None,
+        });
+    }
+    for alt in alternates {
+        if !enclosures.iter().any(|enc| enc.url == alt.url) {
+            enclosures.push(alt);
+        }
+    }
+    return enclosures;
+}
+
+/// Makes a usable web address out of an enclosure URL from a feed,
+/// working around common mistakes: stray whitespace or quotes, spaces
+/// that are not escaped, "&amp;" escaped twice, missing schemes, and
+/// URLs relative to the feed (resolved against `base`). URLs that are
+/// fine as they are are left untouched, so they still match the ones
+/// stored by earlier syncs. Returns why the URL is unusable otherwise.
+pub fn clean_url(raw: &str, base: Option<&Url>) -> Result<String, String> {
+    let trimmed = raw.trim();
+    let is_web = |url: &Url| matches!(url.scheme(), "http" | "https") && url.has_host();
+    let suspect = trimmed.contains(char::is_whitespace) || trimmed.contains("&amp;");
+    if !suspect && Url::parse(trimmed).is_ok_and(|url| is_web(&url)) {
+        return Ok(trimmed.to_string());
+    }
+
+    let mut text: String = trimmed
+        .trim_matches(|c| c == '"' || c == '\'' || c == '<' || c == '>')
+        .chars()
+        .filter(|c| !matches!(c, '\n' | '\r' | '\t'))
+        .collect::<String>()
+        .replace("&amp;", "&")
+        .replace(' ', "%20");
+    if text.starts_with("www.") {
+        text = format!("https://{text}");
+    }
+    let parsed = match (Url::parse(&text), base) {
+        (Ok(url), _) => Ok(url),
+        (Err(_), Some(base)) => base.join(&text),
+        (Err(_), None) if text.starts_with("//") => Url::parse(&format!("https:{text}")),
+        (Err(err), None) => Err(err),
+    };
+    return match parsed {
+        Ok(url) if is_web(&url) => Ok(url.to_string()),
+        Ok(url) => Err(format!("Unsupported link in the feed ({}:)", url.scheme())),
+        Err(_) => Err(format!("Invalid link in the feed: {trimmed}")),
+    };
+}
+
 /// Helper function converting a match from a regex capture group into an
 /// integer.
 fn regex_to_int(re_match: Match) -> Result<i32, std::num::ParseIntError> {
@@ -286,7 +742,7 @@ mod tests {
     fn no_description() {
         let path = "./tests/test_no_description.xml";
         let channel = Channel::read_from(open_file(path)).unwrap();
-        let data = parse_feed_data(channel, "dummy_url");
+        let data = parse_feed_data(channel, "dummy_url", None);
         assert_eq!(data.description, Some("".to_string()));
     }
 
@@ -294,7 +750,7 @@ mod tests {
     fn invalid_explicit() {
         let path = "./tests/test_inval_explicit.xml";
         let channel = Channel::read_from(open_file(path)).unwrap();
-        let data = parse_feed_data(channel, "dummy_url");
+        let data = parse_feed_data(channel, "dummy_url", None);
         assert_eq!(data.explicit, None);
     }
 
@@ -302,10 +758,133 @@ mod tests {
     fn no_episodes() {
         let path = "./tests/test_no_episodes.xml";
         let channel = Channel::read_from(open_file(path)).unwrap();
-        let data = parse_feed_data(channel, "dummy_url");
+        let data = parse_feed_data(channel, "dummy_url", None);
         assert_eq!(data.episodes.len(), 0);
     }
 
+    #[test]
+    fn podcast_namespace() {
+        let path = "./tests/test_podcast_namespace.xml";
+        let channel = Channel::read_from(open_file(path)).unwrap();
+        let data = parse_feed_data(channel, "dummy_url", None);
+        assert_eq!(
+            data.episodes[0].chapters_url,
+            Some("https://example.com/episode2/chapters.json".to_string())
+        );
+        assert_eq!(data.episodes[1].chapters_url, None);
+        assert_eq!(
+            data.episodes[0].transcript_url,
+            Some("https://example.com/episode2/transcript.vtt".to_string())
+        );
+        assert_eq!(data.episodes[0].transcript_type, Some("text/vtt".to_string()));
+        assert_eq!(data.episodes[1].transcript_url, None);
+    }
+
+    #[test]
+    fn alternate_enclosures() {
+        let path = "./tests/test_podcast_namespace.xml";
+        let channel = Channel::read_from(open_file(path)).unwrap();
+        let data = parse_feed_data(channel, "dummy_url", None);
+        let urls: Vec<&str> = data.episodes[0]
+            .enclosures
+            .iter()
+            .map(|enc| enc.url.as_str())
+            .collect();
+        assert_eq!(urls, vec![
+            "https://example.com/episode2.mp3",
+            "https://example.com/episode2.opus",
+            "https://example.com/episode2-hq.mp3",
+        ]);
+        let opus = &data.episodes[0].enclosures[1];
+        assert_eq!(opus.mime.as_deref(), Some("audio/opus"));
+        assert_eq!(opus.bitrate, Some(32000));
+        assert_eq!(opus.length, Some(5000));
+        assert_eq!(opus.title.as_deref(), Some("Opus"));
+        assert_eq!(data.episodes[0].enclosures[2].bitrate, Some(128000));
+        assert!(data.episodes[1].enclosures.is_empty());
+    }
+
+    #[test]
+    fn preferred_enclosure() {
+        let path = "./tests/test_podcast_namespace.xml";
+        let channel = Channel::read_from(open_file(path)).unwrap();
+        let data = parse_feed_data(channel, "dummy_url", None);
+        let enclosures = &data.episodes[0].enclosures;
+        let url = |pref| choose_enclosure(enclosures, pref).map(|enc| enc.url.as_str());
+        assert_eq!(url("OPUS"), Some("https://example.com/episode2.opus"));
+        assert_eq!(url("smallest"), Some("https://example.com/episode2.opus"));
+        assert_eq!(url("largest"), Some("https://example.com/episode2-hq.mp3"));
+        assert_eq!(url("flac"), None);
+        assert_eq!(url("flac, Opus"), Some("https://example.com/episode2.opus"));
+        assert_eq!(url("audio/mpeg"), Some("https://example.com/episode2.mp3"));
+    }
+
+    #[test]
+    fn malformed_enclosure_urls() {
+        let base = Url::parse("https://example.com/feeds/show.xml").unwrap();
+        let clean = |raw| clean_url(raw, Some(&base));
+        assert_eq!(clean("../audio/ep1.mp3").unwrap(), "https://example.com/audio/ep1.mp3");
+        assert_eq!(clean("/ep 2.mp3").unwrap(), "https://example.com/ep%202.mp3");
+        assert_eq!(
+            clean(" https://cdn.example.com/ep.mp3?a=1&amp;b=2 ").unwrap(),
+            "https://cdn.example.com/ep.mp3?a=1&b=2"
+        );
+        assert_eq!(clean("www.example.com/ep.mp3").unwrap(), "https://www.example.com/ep.mp3");
+        let protocol_relative = clean_url("//cdn.example.com/ep.mp3", None);
+        assert_eq!(protocol_relative.unwrap(), "https://cdn.example.com/ep.mp3");
+        assert!(clean_url("ep1.mp3", None).is_err());
+        assert!(clean("ftp://example.com/ep.mp3").is_err());
+    }
+
+    #[test]
+    fn podcast_metadata() {
+        let path = "./tests/test_podcast_namespace.xml";
+        let channel = Channel::read_from(open_file(path)).unwrap();
+        let data = parse_feed_data(channel, "dummy_url", None);
+        assert_eq!(data.author, Some("Example Author".to_string()));
+        assert_eq!(data.categories, vec!["Technology", "Tech News", "Education"]);
+        assert_eq!(data.language, Some("en-US".to_string()));
+        assert_eq!(data.website, Some("https://example.com/".to_string()));
+        assert_eq!(data.funding_url, Some("https://example.com/support".to_string()));
+        assert_eq!(data.funding_text, Some("Become a member".to_string()));
+    }
+
+    #[test]
+    fn feed_schedule() {
+        let path = "./tests/test_podcast_namespace.xml";
+        let channel = Channel::read_from(open_file(path)).unwrap();
+        let schedule = parse_feed_data(channel, "dummy_url", None).schedule;
+        assert_eq!(schedule.ttl, Some(60));
+        assert_eq!(schedule.skip_hours, vec![0, 1]);
+        assert_eq!(schedule.skip_days, vec!["Saturday", "Sunday"]);
+
+        // Friday, April 15, 2022
+        use chrono::TimeZone;
+        let at = |hour, min| Utc.ymd(2022, 4, 15).and_hms(hour, min, 0);
+        assert!(!schedule.allows_check(at(10, 0), at(10, 30)));
+        assert!(schedule.allows_check(at(10, 0), at(11, 0)));
+        assert!(!schedule.allows_check(at(0, 0) - chrono::Duration::days(1), at(0, 30)));
+        let saturday = at(12, 0) + chrono::Duration::days(1);
+        assert!(!schedule.allows_check(at(10, 0), saturday));
+    }
+
+    #[test]
+    fn url_hook_output() {
+        let url = resolve_url("echo %s", "https://example.com/signed?token=abc");
+        assert_eq!(url.unwrap(), "https://example.com/signed?token=abc");
+    }
+
+    #[test]
+    fn url_hook_no_output() {
+        let url = resolve_url("true", "https://example.com/feed.xml");
+        assert_eq!(url.unwrap(), "https://example.com/feed.xml");
+    }
+
+    #[test]
+    fn url_hook_failure() {
+        assert!(resolve_url("false", "https://example.com/feed.xml").is_err());
+    }
+
     #[test]
     fn nan_duration() {
         let duration = String::from("nan");
@@ -354,6 +933,67 @@ mod tests {
         assert_eq!(duration_to_int(Some(&duration)), Some(522));
     }
 
+    #[test]
+    fn auth_headers() {
+        let auth = FeedAuth {
+            user: Some("me".to_string()),
+            password: Some("pass".to_string()),
+            headers: FeedAuth::parse_headers("X-Token: abc:123 | Accept: */*").unwrap(),
+            accept_invalid_certs: false,
+            keyring_entry: None,
+        };
+        let request = authorize(CLIENT.get("https://example.com/feed.xml"), &auth)
+            .build()
+            .unwrap();
+        let headers = request.headers();
+        assert_eq!(headers["Authorization"], "Basic bWU6cGFzcw==");
+        assert_eq!(headers["X-Token"], "abc:123");
+        assert_eq!(headers["Accept"], "*/*");
+        assert_eq!(FeedAuth::parse_headers("no colon here"), None);
+    }
+
+    #[test]
+    fn conditional_get() {
+        use std::io::{BufRead, Write};
+        use std::net::TcpListener;
+
+        // answers two requests: the first with the feed and an ETag, and
+        // the second with 304 if the ETag was sent back
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/feed.xml", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let feed = std::fs::read_to_string("./tests/test_no_episodes.xml").unwrap();
+            for stream in listener.incoming().take(2) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut cached = false;
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    cached |= line.to_lowercase().starts_with("if-none-match: \"v1\"");
+                    line.clear();
+                }
+                let response = if cached {
+                    "HTTP/1.1 304 Not Modified\r\nContent-Length: 0\r\n\r\n".to_string()
+                } else {
+                    format!(
+                        "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: {}\r\n\r\n{feed}",
+                        feed.len()
+                    )
+                };
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let mut feed = PodcastFeed::new(Some(1), url, None);
+        let pod = runtime.block_on(get_feed_data(&feed, 1, None)).unwrap().unwrap();
+        assert_eq!(pod.validators.etag.as_deref(), Some("\"v1\""));
+
+        feed.validators = pod.validators;
+        assert!(runtime.block_on(get_feed_data(&feed, 1, None)).unwrap().is_none());
+        server.join().unwrap();
+    }
+
     #[test]
     fn duration_sss() {
         let duration = String::from("142");
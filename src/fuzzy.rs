@@ -0,0 +1,149 @@
+// Scores for fuzzy matching, in the style of skim and fzf: every
+// character of the pattern earns points, with bonuses for characters
+// at the start of a word or following the previous match, and a
+// penalty for the characters skipped between matches.
+const SCORE_MATCH: i64 = 16;
+const PENALTY_GAP_START: i64 = 3;
+const PENALTY_GAP_EXTENSION: i64 = 1;
+const BONUS_BOUNDARY: i64 = 8;
+const BONUS_CAMEL: i64 = 7;
+const BONUS_CONSECUTIVE: i64 = 4;
+const BONUS_FIRST_CHAR_MULTIPLIER: i64 = 2;
+
+/// Scores how well `text` matches `pattern`, where the characters of
+/// the pattern must appear in the text in order, but not necessarily
+/// next to each other. Returns None if the text does not match; higher
+/// scores are better matches. Matching ignores case, unless the pattern
+/// has an uppercase letter in it. An empty pattern matches everything
+/// with a score of 0.
+pub fn score(pattern: &str, text: &str) -> Option<i64> {
+    let case_sensitive = pattern.chars().any(|c| c.is_uppercase());
+    let fold = |c: char| match case_sensitive {
+        true => c,
+        false => c.to_lowercase().next().unwrap_or(c),
+    };
+    let pattern: Vec<char> = pattern.chars().filter(|c| !c.is_whitespace()).map(fold).collect();
+    if pattern.is_empty() {
+        return Some(0);
+    }
+    let text: Vec<char> = text.chars().collect();
+    let folded: Vec<char> = text.iter().map(|c| fold(*c)).collect();
+    if !is_subsequence(&pattern, &folded) {
+        return None;
+    }
+    let bonuses: Vec<i64> = (0..text.len())
+        .map(|j| bonus(if j == 0 { None } else { Some(text[j - 1]) }, text[j]))
+        .collect();
+
+    // best[j] is the best score for matching the pattern so far with
+    // its last character at position j of the text, if it can be
+    let mut best: Vec<Option<i64>> = folded
+        .iter()
+        .zip(bonuses.iter())
+        .map(|(c, b)| (*c == pattern[0]).then_some(SCORE_MATCH + b * BONUS_FIRST_CHAR_MULTIPLIER))
+        .collect();
+    for p in pattern.iter().skip(1) {
+        let mut next = vec![None; text.len()];
+        // the best score so far that skips at least one character
+        // before position j
+        let mut gapped: Option<i64> = None;
+        for j in 1..text.len() {
+            if j >= 2 {
+                let extended = gapped.map(|s| s - PENALTY_GAP_EXTENSION);
+                let started = best[j - 2].map(|s| s - PENALTY_GAP_START);
+                gapped = extended.max(started);
+            }
+            if folded[j] != *p {
+                continue;
+            }
+            let consecutive =
+                best[j - 1].map(|s| s + SCORE_MATCH + bonuses[j].max(BONUS_CONSECUTIVE));
+            let after_gap = gapped.map(|s| s + SCORE_MATCH + bonuses[j]);
+            next[j] = consecutive.max(after_gap);
+        }
+        best = next;
+    }
+    return best.into_iter().flatten().max();
+}
+
+/// Indicates whether all of `pattern` appears in `text`, in order.
+fn is_subsequence(pattern: &[char], text: &[char]) -> bool {
+    let mut chars = text.iter();
+    return pattern.iter().all(|p| chars.any(|c| c == p));
+}
+
+/// Returns the bonus for matching the character `c`, which follows
+/// `prev` (or starts the text): matches at the start of a word or a
+/// number, or on an uppercase letter in the middle of a word, are more
+/// likely to be what the user meant.
+fn bonus(prev: Option<char>, c: char) -> i64 {
+    return match prev {
+        None => BONUS_BOUNDARY,
+        Some(prev) if !prev.is_alphanumeric() && c.is_alphanumeric() => BONUS_BOUNDARY,
+        Some(prev) if prev.is_lowercase() && c.is_uppercase() => BONUS_CAMEL,
+        Some(prev) if !prev.is_numeric() && c.is_numeric() => BONUS_CAMEL,
+        _ => 0,
+    };
+}
+
+
+// TESTS -----------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_match() {
+        assert_eq!(score("xyz", "The Daily"), None);
+        assert_eq!(score("yliad", "The Daily"), None);
+    }
+
+    #[test]
+    fn empty_pattern() {
+        assert_eq!(score("", "The Daily"), Some(0));
+        assert_eq!(score("  ", "The Daily"), Some(0));
+    }
+
+    #[test]
+    fn smart_case() {
+        assert!(score("daily", "The Daily").is_some());
+        assert!(score("Daily", "The Daily").is_some());
+        assert_eq!(score("DAILY", "The Daily"), None);
+    }
+
+    #[test]
+    fn consecutive_beats_scattered() {
+        let consecutive = score("pod", "A podcast").unwrap();
+        let scattered = score("pod", "Places of Doom").unwrap();
+        assert!(consecutive > scattered);
+    }
+
+    #[test]
+    fn word_start_beats_middle() {
+        let start = score("cast", "Cast Away").unwrap();
+        let middle = score("cast", "Podcasting").unwrap();
+        assert!(start > middle);
+    }
+
+    #[test]
+    fn initials() {
+        let initials = score("tal", "This American Life").unwrap();
+        let inside = score("tal", "Digital Stories").unwrap();
+        assert!(initials > inside);
+    }
+
+    #[test]
+    fn shorter_gaps_win() {
+        let near = score("ab", "a-b").unwrap();
+        let far = score("ab", "a----b").unwrap();
+        assert!(near > far);
+    }
+
+    #[test]
+    fn best_alignment() {
+        // a greedy match would take the first "s" and miss the start of
+        // "stories"
+        let best = score("stories", "sad stories").unwrap();
+        assert_eq!(best, score("stories", "stories").unwrap());
+    }
+}
@@ -0,0 +1,153 @@
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::Sender;
+use std::thread;
+
+use anyhow::{anyhow, Result};
+
+use crate::command;
+use crate::types::Message;
+
+/// Events after which a user-defined hook command can be run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    Download,
+    Play,
+}
+
+impl HookEvent {
+    /// Returns the name of the config option holding the command for
+    /// this event.
+    pub fn config_name(&self) -> &'static str {
+        return match self {
+            HookEvent::Download => "post_download_command",
+            HookEvent::Play => "post_play_command",
+        };
+    }
+}
+
+/// Enum used for communicating back to the main controller when a hook
+/// command fails.
+#[derive(Debug)]
+pub enum HookMsg {
+    Failed(HookEvent, String),
+}
+
+/// Details about the episode that are given to a hook command as
+/// environment variables.
+#[derive(Debug, Clone)]
+pub struct HookData {
+    pub ep_title: String,
+    pub pod_title: String,
+    pub url: String,
+    pub file_path: Option<PathBuf>,
+}
+
+/// Runs a hook command on a separate thread, and lets the main
+/// controller know if it fails. The episode details are given to the
+/// command as the environment variables SHELLCASTER_EVENT,
+/// SHELLCASTER_EPISODE_TITLE, SHELLCASTER_PODCAST_TITLE, SHELLCASTER_URL,
+/// and SHELLCASTER_FILE_PATH (empty if the episode was streamed). Any
+/// "%s" in the command is replaced by the file path, or the URL if
+/// there is no file (see `command::parse()`).
+pub fn run_hook(event: HookEvent, command: String, data: HookData, tx_to_main: Sender<Message>) {
+    thread::spawn(move || {
+        if let Err(err) = execute(event, &command, &data) {
+            let _ = tx_to_main.send(Message::Hook(HookMsg::Failed(event, err.to_string())));
+        }
+    });
+}
+
+/// Runs the hook command and waits for it to finish, returning an error
+/// if it could not be started or exited with an error.
+pub fn execute(event: HookEvent, command: &str, data: &HookData) -> Result<()> {
+    let file_path = data
+        .file_path
+        .as_ref()
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let target = if file_path.is_empty() {
+        data.url.as_str()
+    } else {
+        file_path.as_str()
+    };
+
+    let (program, args) = command::parse(command, target, false)?;
+    let base_cmd = program.as_str();
+    let mut cmd = Command::new(base_cmd);
+    cmd.args(args)
+        .env("SHELLCASTER_EVENT", match event {
+            HookEvent::Download => "download",
+            HookEvent::Play => "play",
+        })
+        .env("SHELLCASTER_EPISODE_TITLE", &data.ep_title)
+        .env("SHELLCASTER_PODCAST_TITLE", &data.pod_title)
+        .env("SHELLCASTER_URL", &data.url)
+        .env("SHELLCASTER_FILE_PATH", &file_path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    let status = cmd
+        .status()
+        .map_err(|err| anyhow!("Could not run {base_cmd}: {err}"))?;
+    return match status.code() {
+        Some(0) => Ok(()),
+        Some(code) => Err(anyhow!("{base_cmd} exited with status {code}")),
+        None => Err(anyhow!("{base_cmd} was stopped by a signal")),
+    };
+}
+
+
+// TESTS -----------------------------------------------------------------
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn hook_data() -> HookData {
+        return HookData {
+            ep_title: "Episode one".to_string(),
+            pod_title: "A podcast".to_string(),
+            url: "https://example.com/ep1.mp3".to_string(),
+            file_path: None,
+        };
+    }
+
+    #[test]
+    fn hook_environment() {
+        let dir = std::env::temp_dir().join(format!("shellcaster-hook-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let script = dir.join("hook.sh");
+        let out = dir.join("out.txt");
+        fs::write(
+            &script,
+            format!(
+                "echo \"$SHELLCASTER_EVENT|$SHELLCASTER_EPISODE_TITLE|$SHELLCASTER_PODCAST_TITLE|$1\" > {}\n",
+                out.to_string_lossy()
+            ),
+        )
+        .unwrap();
+
+        let command = format!("sh {} %s", script.to_string_lossy());
+        execute(HookEvent::Play, &command, &hook_data()).unwrap();
+        assert_eq!(
+            fs::read_to_string(&out).unwrap(),
+            "play|Episode one|A podcast|https://example.com/ep1.mp3\n"
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn hook_failure() {
+        let err = execute(HookEvent::Download, "false", &hook_data()).unwrap_err();
+        assert_eq!(err.to_string(), "false exited with status 1");
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        run_hook(HookEvent::Download, "false".to_string(), hook_data(), tx);
+        match rx.recv().unwrap() {
+            Message::Hook(HookMsg::Failed(event, _)) => assert_eq!(event, HookEvent::Download),
+            _ => panic!("Expected a hook failure"),
+        }
+    }
+}
@@ -0,0 +1,427 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    /// Regex for the target of a link, e.g., `href="https://..."`
+    static ref RE_HREF: Regex = Regex::new(r#"(?i)\bhref\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))"#).expect("Regex error");
+
+    /// Regex for tags that show the text is laid out with HTML, rather
+    /// than with line breaks
+    static ref RE_LAYOUT_TAGS: Regex = Regex::new(r"(?i)<(br|p|div|li|h\d)[\s/>]").expect("Regex error");
+}
+
+/// Inline styling picked up from the markup.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TextStyle {
+    pub bold: bool,
+    pub italic: bool,
+}
+
+/// A run of text that has a single style.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub text: String,
+    pub style: TextStyle,
+}
+
+/// Text rendered from HTML: a list of lines, each made up of styled
+/// spans, and the targets of the links found in it. Links are marked in
+/// the text with their number, e.g., "the show notes [1]".
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Rendered {
+    pub lines: Vec<Vec<Span>>,
+    pub links: Vec<String>,
+}
+
+impl Rendered {
+    /// Returns the text without styling, followed by the list of links,
+    /// if there are any.
+    pub fn plain_text(&self) -> String {
+        let mut text: Vec<String> = self.lines.iter().map(|line| line_text(line)).collect();
+        if !self.links.is_empty() {
+            text.push(String::new());
+            text.push("Links:".to_string());
+            for (i, link) in self.links.iter().enumerate() {
+                text.push(format!("[{}] {link}", i + 1));
+            }
+        }
+        return text.join("\n");
+    }
+}
+
+/// Returns the text of a line, without styling.
+pub fn line_text(line: &[Span]) -> String {
+    return line.iter().map(|span| span.text.as_str()).collect();
+}
+
+/// Returns the part of a line between the byte offsets `start` and
+/// `end` of its text, keeping the styles. Spans are cut where needed.
+pub fn slice_spans(line: &[Span], start: usize, end: usize) -> Vec<Span> {
+    let mut spans = Vec::new();
+    let mut pos = 0;
+    for span in line.iter() {
+        let span_end = pos + span.text.len();
+        let from = start.max(pos);
+        let to = end.min(span_end);
+        if from < to {
+            spans.push(Span {
+                text: span.text[from - pos..to - pos].to_string(),
+                style: span.style,
+            });
+        }
+        pos = span_end;
+    }
+    return spans;
+}
+
+/// Turns HTML into lines of styled text. Paragraphs, headings and list
+/// items start new lines, list items get bullets or numbers, bold and
+/// italic text keep their style, links are numbered, and HTML entities
+/// (e.g., `&amp;`) are decoded. Text that is not laid out with HTML
+/// keeps its own line breaks. Runs of blank lines are cut down to one.
+pub fn render(html: &str) -> Rendered {
+    let mut renderer = Renderer {
+        collapse: RE_LAYOUT_TAGS.is_match(html),
+        ..Default::default()
+    };
+    let mut rest = html;
+    while !rest.is_empty() {
+        match rest.find('<') {
+            Some(0) => match parse_tag(rest) {
+                Some((tag, len)) => {
+                    renderer.tag(&tag);
+                    rest = &rest[len..];
+                }
+                None => {
+                    renderer.text("<");
+                    rest = &rest[1..];
+                }
+            },
+            Some(idx) => {
+                renderer.text(&rest[..idx]);
+                rest = &rest[idx..];
+            }
+            None => {
+                renderer.text(rest);
+                rest = "";
+            }
+        }
+    }
+    return renderer.finish();
+}
+
+/// Turns HTML into plain text, as with `render()`.
+pub fn to_text(html: &str) -> String {
+    return render(html).plain_text();
+}
+
+/// Decodes HTML entities, leaving the text as it is if they are not
+/// valid.
+fn decode(text: &str) -> String {
+    return escaper::decode_html(text).unwrap_or_else(|_| text.to_string());
+}
+
+
+/// An opening or closing HTML tag.
+#[derive(Debug)]
+struct Tag {
+    name: String,
+    closing: bool,
+    href: Option<String>,
+}
+
+/// Reads the tag at the start of `text`, returning it along with its
+/// length. Returns None if the text does not start with a tag, e.g.,
+/// for a "<" used as a less-than sign.
+fn parse_tag(text: &str) -> Option<(Tag, usize)> {
+    let end = text.find('>')?;
+    let inner = &text[1..end];
+    if inner.contains('<') {
+        return None;
+    }
+    let (closing, inner) = match inner.strip_prefix('/') {
+        Some(inner) => (true, inner),
+        None => (false, inner),
+    };
+    let name: String = inner
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric())
+        .collect();
+    // comments and doctypes are skipped like any other tag
+    if name.is_empty() && !inner.starts_with('!') {
+        return None;
+    }
+    let href = RE_HREF.captures(inner).and_then(|cap| {
+        let target = cap.get(1).or_else(|| cap.get(2)).or_else(|| cap.get(3))?;
+        return Some(decode(target.as_str()));
+    });
+    let tag = Tag {
+        name: name.to_ascii_lowercase(),
+        closing: closing,
+        href: href,
+    };
+    return Some((tag, end + 1));
+}
+
+
+/// Keeps track of the text rendered so far, and of the tags that are
+/// open.
+///
+/// * `collapse` indicates that the text is laid out with HTML, so
+///   line breaks and runs of spaces in the text are shown as a single
+///   space, as a browser would.
+/// * `lists` holds an entry for each open list: the number of the next
+///   item for numbered lists, or None for bulleted lists.
+/// * `open_links` holds an entry for each open link: its number, or
+///   None if it has no target.
+#[derive(Debug, Default)]
+struct Renderer {
+    lines: Vec<Vec<Span>>,
+    line: Vec<Span>,
+    links: Vec<String>,
+    collapse: bool,
+    bold: usize,
+    italic: usize,
+    preformatted: usize,
+    hidden: usize,
+    lists: Vec<Option<usize>>,
+    open_links: Vec<Option<usize>>,
+}
+
+impl Renderer {
+    /// Adds text found between tags.
+    fn text(&mut self, raw: &str) {
+        if self.hidden > 0 {
+            return;
+        }
+        let decoded = decode(raw).replace("\r\n", "\n").replace('\r', "\n");
+        if self.collapse && self.preformatted == 0 {
+            // spaces at the start of a line are left out, as are spaces
+            // that would follow another one
+            let words = decoded.split_whitespace().collect::<Vec<&str>>().join(" ");
+            let after_space = self.at_line_start()
+                || self.line.last().is_some_and(|span| span.text.ends_with(' '));
+            let mut text = String::new();
+            if decoded.starts_with(char::is_whitespace) && !after_space {
+                text.push(' ');
+            }
+            text.push_str(&words);
+            if decoded.ends_with(char::is_whitespace) && !words.is_empty() {
+                text.push(' ');
+            }
+            self.push(&text);
+        } else {
+            for (i, part) in decoded.split('\n').enumerate() {
+                if i > 0 {
+                    self.line_break();
+                }
+                self.push(part);
+            }
+        }
+    }
+
+    /// Handles a tag, changing the layout or style of what follows.
+    fn tag(&mut self, tag: &Tag) {
+        let open = !tag.closing;
+        match tag.name.as_str() {
+            "br" => self.line_break(),
+            "p" | "div" | "blockquote" | "table" | "hr" => self.paragraph_break(),
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.paragraph_break();
+                self.bold = toggle(self.bold, open);
+            }
+            "pre" => {
+                self.paragraph_break();
+                self.preformatted = toggle(self.preformatted, open);
+            }
+            "tr" if tag.closing => self.line_break(),
+            "ul" | "ol" => {
+                if open {
+                    self.lists.push((tag.name == "ol").then_some(1));
+                } else {
+                    self.lists.pop();
+                }
+                if self.lists.is_empty() {
+                    self.paragraph_break();
+                } else if !self.at_line_start() {
+                    self.line_break();
+                }
+            }
+            "li" if open => {
+                if !self.at_line_start() {
+                    self.line_break();
+                }
+                let indent = "  ".repeat(self.lists.len().saturating_sub(1));
+                let marker = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}. ", *number - 1)
+                    }
+                    _ => "• ".to_string(),
+                };
+                self.push(&format!("{indent}{marker}"));
+            }
+            "b" | "strong" => self.bold = toggle(self.bold, open),
+            "i" | "em" => self.italic = toggle(self.italic, open),
+            "a" if open => {
+                let number = tag.href.as_ref().filter(|href| !href.is_empty()).map(|href| {
+                    match self.links.iter().position(|link| link == href) {
+                        Some(idx) => idx + 1,
+                        None => {
+                            self.links.push(href.clone());
+                            self.links.len()
+                        }
+                    }
+                });
+                self.open_links.push(number);
+            }
+            "a" => {
+                if let Some(Some(number)) = self.open_links.pop() {
+                    self.push(&format!(" [{number}]"));
+                }
+            }
+            "script" | "style" => self.hidden = toggle(self.hidden, open),
+            _ => (),
+        }
+    }
+
+    /// Adds text to the current line, in the current style.
+    fn push(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        let style = TextStyle {
+            bold: self.bold > 0,
+            italic: self.italic > 0,
+        };
+        match self.line.last_mut() {
+            Some(last) if last.style == style => last.text.push_str(text),
+            _ => self.line.push(Span {
+                text: text.to_string(),
+                style: style,
+            }),
+        }
+    }
+
+    /// Indicates whether nothing but spaces has been added to the
+    /// current line.
+    fn at_line_start(&self) -> bool {
+        return self.line.iter().all(|span| span.text.trim().is_empty());
+    }
+
+    /// Starts a new line.
+    fn line_break(&mut self) {
+        self.lines.push(std::mem::take(&mut self.line));
+    }
+
+    /// Starts a new paragraph, leaving a blank line after the text so
+    /// far.
+    fn paragraph_break(&mut self) {
+        if !self.at_line_start() {
+            self.line_break();
+        }
+        self.line_break();
+    }
+
+    /// Tidies up the lines: trailing spaces are removed, as are blank
+    /// lines at the start and end, and runs of blank lines are cut
+    /// down to one.
+    fn finish(mut self) -> Rendered {
+        self.line_break();
+        let mut lines: Vec<Vec<Span>> = Vec::new();
+        for mut line in self.lines.into_iter() {
+            while let Some(last) = line.last_mut() {
+                let trimmed = last.text.trim_end().len();
+                if trimmed > 0 {
+                    last.text.truncate(trimmed);
+                    break;
+                }
+                line.pop();
+            }
+            let blank = line.is_empty();
+            let last_blank = lines.last().is_none_or(|last| last.is_empty());
+            if !(blank && last_blank) {
+                lines.push(line);
+            }
+        }
+        if lines.last().is_some_and(|line| line.is_empty()) {
+            lines.pop();
+        }
+        return Rendered {
+            lines: lines,
+            links: self.links,
+        };
+    }
+}
+
+/// Counts an opening or closing tag of a kind that can be nested.
+fn toggle(depth: usize, open: bool) -> usize {
+    return if open { depth + 1 } else { depth.saturating_sub(1) };
+}
+
+
+// TESTS -----------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(rendered: &Rendered) -> Vec<String> {
+        return rendered.lines.iter().map(|line| line_text(line)).collect();
+    }
+
+    #[test]
+    fn plain_text_keeps_lines() {
+        let rendered = render("First line.\r\nSecond &amp; last.\n\n\n\nAfter a gap. a < b");
+        assert_eq!(texts(&rendered), vec![
+            "First line.",
+            "Second & last.",
+            "",
+            "After a gap. a < b",
+        ]);
+        assert!(rendered.links.is_empty());
+    }
+
+    #[test]
+    fn paragraphs_and_lists() {
+        let html = "<p>Intro\n   text.</p>\n<ul>\n  <li>One</li>\n  <li>Two\n<ol><li>Nested</li><li>More</li></ol></li>\n</ul><p>Line<br/>break</p>";
+        assert_eq!(texts(&render(html)), vec![
+            "Intro text.",
+            "",
+            "• One",
+            "• Two",
+            "  1. Nested",
+            "  2. More",
+            "",
+            "Line",
+            "break",
+        ]);
+    }
+
+    #[test]
+    fn styles_and_links() {
+        let html = "<p>A <b>bold</b> and <em>slanted</em> <a href=\"https://example.com/notes?a=1&amp;b=2\">link</a>, <a href='https://example.com/notes?a=1&amp;b=2'>again</a>.</p><script>var x;</script>";
+        let rendered = render(html);
+        assert_eq!(texts(&rendered), vec!["A bold and slanted link [1], again [1]."]);
+        assert_eq!(rendered.links, vec!["https://example.com/notes?a=1&b=2"]);
+        let bold: Vec<&str> = rendered.lines[0]
+            .iter()
+            .filter(|span| span.style.bold)
+            .map(|span| span.text.as_str())
+            .collect();
+        assert_eq!(bold, vec!["bold"]);
+        assert_eq!(
+            rendered.plain_text(),
+            "A bold and slanted link [1], again [1].\n\nLinks:\n[1] https://example.com/notes?a=1&b=2"
+        );
+    }
+
+    #[test]
+    fn slices() {
+        let rendered = render("one <i>two three</i> four");
+        let line = &rendered.lines[0];
+        let slice = slice_spans(line, 2, 7);
+        assert_eq!(line_text(&slice), "e two");
+        assert!(!slice[0].style.italic && slice[1].style.italic);
+        assert_eq!(line_text(&slice_spans(line, 13, 100)), " four");
+    }
+}
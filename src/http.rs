@@ -0,0 +1,238 @@
+use anyhow::{anyhow, Context, Result};
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use lazy_static::lazy_static;
+
+lazy_static! {
+    /// HTTP client shared by the small requests made from plain threads
+    /// (chapters, cover art, transcripts, scrobbles, webhooks and update
+    /// checks).
+    pub static ref BLOCKING_CLIENT: reqwest::blocking::Client = blocking_client(Duration::from_secs(10), Duration::from_secs(30));
+}
+
+/// Identifies shellcaster to servers; some, like the GitHub API, refuse
+/// requests without one.
+const USER_AGENT: &str = concat!("shellcaster/", env!("CARGO_PKG_VERSION"));
+
+/// Longest wait before retrying a failed request.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// Extra certificate authorities trusted by the HTTP clients, from
+/// `ca_file` in config.toml. These have to be loaded before the first
+/// client is built.
+static CA_CERTS: OnceLock<Vec<reqwest::Certificate>> = OnceLock::new();
+
+/// Network settings used by the HTTP clients, from config.toml. Like
+/// `CA_CERTS`, these have to be set before the first client is built.
+static NETWORK: OnceLock<NetworkConfig> = OnceLock::new();
+
+/// Settings for the connections made to fetch feeds and download
+/// episodes: which IP version to try first when a server has both, and
+/// the local address or network interface to connect from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NetworkConfig {
+    pub prefer: Option<IpFamily>,
+    pub bind: Option<BindTo>,
+}
+
+/// IP version to try first (see `prefer_ip_version` in config.toml).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpFamily {
+    V4,
+    V6,
+}
+
+/// Where outgoing connections are made from (see `bind_address` in
+/// config.toml). Binding to a network interface is only possible on
+/// Linux.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BindTo {
+    Address(IpAddr),
+    Interface(String),
+}
+
+/// DNS resolver that puts the addresses of one IP version first, so
+/// they are the ones tried first. The others are still tried if those
+/// cannot be reached.
+struct PreferFamily(IpFamily);
+
+impl reqwest::dns::Resolve for PreferFamily {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let family = self.0;
+        let host = name.as_str().to_string();
+        return Box::pin(async move {
+            let mut addrs: Vec<SocketAddr> =
+                tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            sort_by_family(&mut addrs, family);
+            let addrs: reqwest::dns::Addrs = Box::new(addrs.into_iter());
+            return Ok(addrs);
+        });
+    }
+}
+
+/// Moves the addresses of the given IP version to the front, keeping
+/// the order the resolver gave them in otherwise.
+fn sort_by_family(addrs: &mut [SocketAddr], family: IpFamily) {
+    addrs.sort_by_key(|addr| addr.is_ipv4() != (family == IpFamily::V4));
+}
+
+/// Starts building an HTTP client that gives up if it cannot connect
+/// within `connect_timeout`, or if the server stops sending data for
+/// longer than `read_timeout`. Every client is built from this, so
+/// that they all trust the certificate authorities from `ca_file` and
+/// follow the network settings; if `accept_invalid_certs` is set,
+/// certificates are not checked at all.
+fn builder(
+    connect_timeout: Duration,
+    read_timeout: Duration,
+    accept_invalid_certs: bool,
+) -> reqwest::ClientBuilder {
+    let mut builder = reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        .connect_timeout(connect_timeout)
+        .read_timeout(read_timeout)
+        .danger_accept_invalid_certs(accept_invalid_certs);
+    for cert in CA_CERTS.get().into_iter().flatten() {
+        builder = builder.add_root_certificate(cert.clone());
+    }
+    if let Some(network) = NETWORK.get() {
+        if let Some(family) = network.prefer {
+            builder = builder.dns_resolver(Arc::new(PreferFamily(family)));
+        }
+        match &network.bind {
+            Some(BindTo::Address(addr)) => builder = builder.local_address(*addr),
+            #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+            Some(BindTo::Interface(name)) => builder = builder.interface(name),
+            _ => (),
+        }
+    }
+    #[cfg(feature = "native_tls")]
+    let builder = builder.use_native_tls();
+    return builder;
+}
+
+/// Builds an HTTP client for use on the task pool (see `builder()`).
+pub fn client(
+    connect_timeout: Duration,
+    read_timeout: Duration,
+    accept_invalid_certs: bool,
+) -> reqwest::Client {
+    return builder(connect_timeout, read_timeout, accept_invalid_certs)
+        .build()
+        .expect("Could not set up HTTP client");
+}
+
+/// Builds an HTTP client for use from plain threads (see `builder()`).
+/// Certificates are always checked. These clients run a runtime of
+/// their own, which cannot be dropped from the task pool, so they are
+/// kept in statics.
+pub fn blocking_client(
+    connect_timeout: Duration,
+    read_timeout: Duration,
+) -> reqwest::blocking::Client {
+    return reqwest::blocking::ClientBuilder::from(builder(connect_timeout, read_timeout, false))
+        // only the time between reads is limited, like the other clients
+        .timeout(None)
+        .build()
+        .expect("Could not set up HTTP client");
+}
+
+/// Returns how long to wait before retrying a request that has failed
+/// `attempt` times: one second after the first failure, doubling each
+/// time up to `MAX_RETRY_DELAY`.
+pub fn retry_delay(attempt: usize) -> Duration {
+    let exponent = attempt.saturating_sub(1).min(31) as u32;
+    return Duration::from_secs(2u64.pow(exponent)).min(MAX_RETRY_DELAY);
+}
+
+/// Fetches a URL with `BLOCKING_CLIENT`, trying up to `max_retries`
+/// times, with a growing wait in between (see `retry_delay()`).
+/// Responses with an error status count as failures.
+pub fn get(url: &str, max_retries: usize) -> Result<reqwest::blocking::Response> {
+    let mut attempt = 0;
+    loop {
+        match BLOCKING_CLIENT.get(url).send().and_then(|resp| resp.error_for_status()) {
+            Ok(resp) => return Ok(resp),
+            Err(err) => {
+                attempt += 1;
+                if attempt >= max_retries {
+                    return Err(anyhow!(err));
+                }
+                log::debug!("Request for {url} failed, retrying: {err}");
+                std::thread::sleep(retry_delay(attempt));
+            }
+        }
+    }
+}
+
+/// Reads the certificate authorities in a PEM file (see `ca_file` in
+/// config.toml), to be trusted by every HTTP client built after this.
+pub fn load_ca_file(path: &Path) -> Result<()> {
+    let pem = std::fs::read(path)
+        .with_context(|| format!("Could not read ca_file: {}", path.display()))?;
+    let certs = reqwest::Certificate::from_pem_bundle(&pem)
+        .with_context(|| format!("Could not read certificates from ca_file: {}", path.display()))?;
+    if certs.is_empty() {
+        return Err(anyhow!("No certificates found in ca_file: {}", path.display()));
+    }
+    // bad certificates only show up once a client is built with them
+    let mut builder = reqwest::Client::builder();
+    for cert in certs.iter() {
+        builder = builder.add_root_certificate(cert.clone());
+    }
+    builder
+        .build()
+        .with_context(|| format!("Could not use the certificates in ca_file: {}", path.display()))?;
+    let _ = CA_CERTS.set(certs);
+    return Ok(());
+}
+
+/// Sets the network settings used by every HTTP client built after
+/// this.
+pub fn set_network(network: NetworkConfig) {
+    let _ = NETWORK.set(network);
+}
+
+
+// TESTS -----------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preferred_ip_version_first() {
+        let v4: SocketAddr = "192.0.2.1:0".parse().unwrap();
+        let v4_other: SocketAddr = "192.0.2.2:0".parse().unwrap();
+        let v6: SocketAddr = "[2001:db8::1]:0".parse().unwrap();
+        let mut addrs = vec![v6, v4, v4_other];
+        sort_by_family(&mut addrs, IpFamily::V4);
+        assert_eq!(addrs, vec![v4, v4_other, v6]);
+        sort_by_family(&mut addrs, IpFamily::V6);
+        assert_eq!(addrs, vec![v6, v4, v4_other]);
+    }
+
+    #[test]
+    fn retry_delay_doubles() {
+        let delays: Vec<u64> = (1..=7).map(|attempt| retry_delay(attempt).as_secs()).collect();
+        assert_eq!(delays, vec![1, 2, 4, 8, 16, 30, 30]);
+        assert_eq!(retry_delay(usize::MAX), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn missing_ca_file() {
+        let path = std::env::temp_dir().join("shellcaster-no-such-ca.pem");
+        assert!(load_ca_file(&path).is_err());
+    }
+
+    #[test]
+    fn ca_file_without_certificates() {
+        let path = std::env::temp_dir().join(format!("shellcaster-ca-{}.pem", std::process::id()));
+        std::fs::write(&path, "not a certificate\n").unwrap();
+        let result = load_ca_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(result.is_err());
+    }
+}
@@ -1,5 +1,7 @@
 use ahash::AHashMap;
+use anyhow::{anyhow, Result};
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::{BTreeMap, HashSet};
 
 use crate::config::KeybindingsFromToml;
 
@@ -19,39 +21,215 @@ pub enum UserAction {
     GoTop,
     GoBot,
 
+    ToggleGroup,
+    ToggleAllGroups,
+    ToggleLayout,
+
+    TabLibrary,
+    TabNew,
+    TabQueue,
+    TabDownloads,
+    TabSearch,
+    NextTab,
+
     AddFeed,
+    Import,
     Sync,
     SyncAll,
+    CheckAll,
 
+    Select,
     Play,
     MarkPlayed,
     MarkAllPlayed,
+    MarkOlderPlayed,
+    MarkLibraryOlderPlayed,
+    ToggleStar,
+    Enqueue,
+    EnqueueNext,
+    MoveUp,
+    MoveDown,
+    MoveToTop,
+    MoveToBottom,
+    ShuffleQueue,
+    InterleaveQueue,
+    FillQueue,
 
     Download,
     DownloadAll,
+    ChooseEnclosure,
+    DownloadLibrary,
+    Cancel,
     Delete,
     DeleteAll,
     Remove,
     RemoveAll,
     UnmarkDownloaded,
 
+    ToggleSelect,
+    ToggleNotify,
+    PodcastSettings,
+    ChangeFeedUrl,
+    Support,
+    CycleTheme,
+    ThemePreview,
+    ReloadConfig,
+    Setup,
+    Chapters,
+    Transcript,
+    Search,
+    CopyMode,
+    Yank,
+    YankAll,
+    ToggleWrap,
+
     FilterPlayed,
     FilterDownloaded,
+    CycleSort,
+    CycleFilter,
+    FilterMenu,
+    Find,
+    FindInMenu,
+    InProgress,
+    Inbox,
+    TriageMode,
+    Queue,
+    Stats,
+    ShowLog,
+    Notifications,
+    Suspend,
+    Shell,
 
     Help,
     Quit,
 }
 
+impl UserAction {
+    /// Returns the name used for the action in the "keybindings"
+    /// section of config.toml.
+    pub fn config_name(&self) -> &'static str {
+        return match self {
+            UserAction::Left => "left",
+            UserAction::Right => "right",
+            UserAction::Up => "up",
+            UserAction::Down => "down",
+            UserAction::BigUp => "big_up",
+            UserAction::BigDown => "big_down",
+            UserAction::PageUp => "page_up",
+            UserAction::PageDown => "page_down",
+            UserAction::GoTop => "go_top",
+            UserAction::GoBot => "go_bot",
+            UserAction::ToggleGroup => "toggle_group",
+            UserAction::ToggleAllGroups => "toggle_all_groups",
+            UserAction::ToggleLayout => "toggle_layout",
+            UserAction::TabLibrary => "tab_library",
+            UserAction::TabNew => "tab_new",
+            UserAction::TabQueue => "tab_queue",
+            UserAction::TabDownloads => "tab_downloads",
+            UserAction::TabSearch => "tab_search",
+            UserAction::NextTab => "next_tab",
+            UserAction::AddFeed => "add_feed",
+            UserAction::Import => "import",
+            UserAction::Sync => "sync",
+            UserAction::SyncAll => "sync_all",
+            UserAction::CheckAll => "check_all",
+            UserAction::Select => "select",
+            UserAction::Play => "play",
+            UserAction::MarkPlayed => "mark_played",
+            UserAction::MarkAllPlayed => "mark_all_played",
+            UserAction::MarkOlderPlayed => "mark_older_played",
+            UserAction::MarkLibraryOlderPlayed => "mark_library_older_played",
+            UserAction::ToggleStar => "toggle_star",
+            UserAction::Enqueue => "enqueue",
+            UserAction::EnqueueNext => "enqueue_next",
+            UserAction::MoveUp => "move_up",
+            UserAction::MoveDown => "move_down",
+            UserAction::MoveToTop => "move_to_top",
+            UserAction::MoveToBottom => "move_to_bottom",
+            UserAction::ShuffleQueue => "shuffle_queue",
+            UserAction::InterleaveQueue => "interleave_queue",
+            UserAction::FillQueue => "fill_queue",
+            UserAction::Download => "download",
+            UserAction::DownloadAll => "download_all",
+            UserAction::ChooseEnclosure => "choose_enclosure",
+            UserAction::DownloadLibrary => "download_library",
+            UserAction::Cancel => "cancel",
+            UserAction::Delete => "delete",
+            UserAction::DeleteAll => "delete_all",
+            UserAction::Remove => "remove",
+            UserAction::RemoveAll => "remove_all",
+            UserAction::UnmarkDownloaded => "unmark_downloaded",
+            UserAction::ToggleSelect => "toggle_select",
+            UserAction::ToggleNotify => "toggle_notify",
+            UserAction::PodcastSettings => "podcast_settings",
+            UserAction::ChangeFeedUrl => "change_url",
+            UserAction::Support => "support",
+            UserAction::CycleTheme => "cycle_theme",
+            UserAction::ThemePreview => "theme_preview",
+            UserAction::ReloadConfig => "reload_config",
+            UserAction::Setup => "setup",
+            UserAction::Chapters => "chapters",
+            UserAction::Transcript => "transcript",
+            UserAction::Search => "search",
+            UserAction::CopyMode => "copy_mode",
+            UserAction::Yank => "yank",
+            UserAction::YankAll => "yank_all",
+            UserAction::ToggleWrap => "toggle_wrap",
+            UserAction::FilterPlayed => "filter_played",
+            UserAction::FilterDownloaded => "filter_downloaded",
+            UserAction::CycleSort => "cycle_sort",
+            UserAction::CycleFilter => "cycle_filter",
+            UserAction::FilterMenu => "filter_menu",
+            UserAction::Find => "find",
+            UserAction::FindInMenu => "find_in_menu",
+            UserAction::InProgress => "in_progress",
+            UserAction::Inbox => "inbox",
+            UserAction::TriageMode => "triage_mode",
+            UserAction::Queue => "queue",
+            UserAction::Stats => "stats",
+            UserAction::ShowLog => "show_log",
+            UserAction::Notifications => "notifications",
+            UserAction::Suspend => "suspend",
+            UserAction::Shell => "shell",
+            UserAction::Help => "help",
+            UserAction::Quit => "quit",
+        };
+    }
+
+    /// Returns the action with the given name from the "keybindings"
+    /// section of config.toml, if there is one.
+    pub fn from_config_name(name: &str) -> Option<Self> {
+        return Self::all()
+            .into_iter()
+            .find(|action| action.config_name() == name);
+    }
+
+    /// Returns every action, in the order of the default keybindings.
+    pub fn all() -> Vec<Self> {
+        return Keybindings::_defaults()
+            .into_iter()
+            .map(|(action, _)| action)
+            .collect();
+    }
+}
+
 /// Wrapper around a hash map that keeps track of all keybindings. Multiple
 /// keys may perform the same action, but each key may only perform one
-/// action.
+/// action. Keys may also be bound to macros, which perform a list of
+/// actions one after the other.
 #[derive(Debug, Clone)]
-pub struct Keybindings(AHashMap<String, UserAction>);
+pub struct Keybindings {
+    keys: AHashMap<String, UserAction>,
+    macros: AHashMap<String, Vec<UserAction>>,
+}
 
 impl Keybindings {
     /// Returns a new Keybindings struct.
     pub fn new() -> Self {
-        return Self(AHashMap::new());
+        return Self {
+            keys: AHashMap::new(),
+            macros: AHashMap::new(),
+        };
     }
 
     /// Returns a Keybindings struct with all default values set.
@@ -67,7 +245,17 @@ impl Keybindings {
     /// Given a struct deserialized from config.toml (for which any or
     /// all fields may be missing), create a Keybindings struct using
     /// user-defined keys where specified, and default values otherwise.
-    pub fn from_config(config: KeybindingsFromToml) -> Self {
+    /// Keys set in config.toml replace any default use of the same key.
+    /// `macros` maps keys to lists of action names, from the "macros"
+    /// section of config.toml. If config.toml binds one key to more than
+    /// one action, those actions keep their default keys, and an action
+    /// whose key is taken by a macro keeps its default keys as well (the
+    /// check-config report lists these conflicts). Returns an error if a
+    /// macro names an action that does not exist.
+    pub fn from_config(
+        config: KeybindingsFromToml,
+        macros: BTreeMap<String, Vec<String>>,
+    ) -> Result<Self> {
         let config_actions: Vec<(Option<Vec<String>>, UserAction)> = vec![
             (config.left, UserAction::Left),
             (config.right, UserAction::Right),
@@ -79,46 +267,169 @@ impl Keybindings {
             (config.page_down, UserAction::PageDown),
             (config.go_top, UserAction::GoTop),
             (config.go_bot, UserAction::GoBot),
+            (config.toggle_group, UserAction::ToggleGroup),
+            (config.toggle_all_groups, UserAction::ToggleAllGroups),
+            (config.toggle_layout, UserAction::ToggleLayout),
+            (config.tab_library, UserAction::TabLibrary),
+            (config.tab_new, UserAction::TabNew),
+            (config.tab_queue, UserAction::TabQueue),
+            (config.tab_downloads, UserAction::TabDownloads),
+            (config.tab_search, UserAction::TabSearch),
+            (config.next_tab, UserAction::NextTab),
             (config.add_feed, UserAction::AddFeed),
+            (config.import, UserAction::Import),
             (config.sync, UserAction::Sync),
             (config.sync_all, UserAction::SyncAll),
+            (config.check_all, UserAction::CheckAll),
+            (config.select, UserAction::Select),
             (config.play, UserAction::Play),
             (config.mark_played, UserAction::MarkPlayed),
             (config.mark_all_played, UserAction::MarkAllPlayed),
+            (config.mark_older_played, UserAction::MarkOlderPlayed),
+            (config.mark_library_older_played, UserAction::MarkLibraryOlderPlayed),
+            (config.toggle_star, UserAction::ToggleStar),
+            (config.enqueue, UserAction::Enqueue),
+            (config.enqueue_next, UserAction::EnqueueNext),
+            (config.move_up, UserAction::MoveUp),
+            (config.move_down, UserAction::MoveDown),
+            (config.move_to_top, UserAction::MoveToTop),
+            (config.move_to_bottom, UserAction::MoveToBottom),
+            (config.shuffle_queue, UserAction::ShuffleQueue),
+            (config.interleave_queue, UserAction::InterleaveQueue),
+            (config.fill_queue, UserAction::FillQueue),
             (config.download, UserAction::Download),
             (config.download_all, UserAction::DownloadAll),
+            (config.choose_enclosure, UserAction::ChooseEnclosure),
+            (config.download_library, UserAction::DownloadLibrary),
+            (config.cancel, UserAction::Cancel),
             (config.delete, UserAction::Delete),
             (config.delete_all, UserAction::DeleteAll),
+            (config.unmark_downloaded, UserAction::UnmarkDownloaded),
             (config.remove, UserAction::Remove),
             (config.remove_all, UserAction::RemoveAll),
+            (config.toggle_select, UserAction::ToggleSelect),
+            (config.toggle_notify, UserAction::ToggleNotify),
+            (config.podcast_settings, UserAction::PodcastSettings),
+            (config.change_url, UserAction::ChangeFeedUrl),
+            (config.support, UserAction::Support),
+            (config.cycle_theme, UserAction::CycleTheme),
+            (config.theme_preview, UserAction::ThemePreview),
+            (config.reload_config, UserAction::ReloadConfig),
+            (config.setup, UserAction::Setup),
+            (config.chapters, UserAction::Chapters),
+            (config.transcript, UserAction::Transcript),
+            (config.search, UserAction::Search),
+            (config.copy_mode, UserAction::CopyMode),
+            (config.yank, UserAction::Yank),
+            (config.yank_all, UserAction::YankAll),
+            (config.toggle_wrap, UserAction::ToggleWrap),
             (config.filter_played, UserAction::FilterPlayed),
             (config.filter_downloaded, UserAction::FilterDownloaded),
+            (config.cycle_sort, UserAction::CycleSort),
+            (config.cycle_filter, UserAction::CycleFilter),
+            (config.filter_menu, UserAction::FilterMenu),
+            (config.find, UserAction::Find),
+            (config.find_in_menu, UserAction::FindInMenu),
+            (config.in_progress, UserAction::InProgress),
+            (config.inbox, UserAction::Inbox),
+            (config.triage_mode, UserAction::TriageMode),
+            (config.queue, UserAction::Queue),
+            (config.stats, UserAction::Stats),
+            (config.show_log, UserAction::ShowLog),
+            (config.notifications, UserAction::Notifications),
+            (config.suspend, UserAction::Suspend),
+            (config.shell, UserAction::Shell),
             (config.help, UserAction::Help),
             (config.quit, UserAction::Quit),
         ];
 
+        let mut user_keys: AHashMap<String, UserAction> = AHashMap::new();
+        let mut conflicted: HashSet<UserAction> = HashSet::new();
+        for (config, action) in config_actions.iter() {
+            if let Some(config) = config {
+                for key in config.iter() {
+                    let key = normalize_key(key);
+                    match user_keys.get(&key) {
+                        Some(other) if other != action => {
+                            conflicted.insert(*other);
+                            conflicted.insert(*action);
+                        }
+                        _ => {
+                            user_keys.insert(key, *action);
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut user_macros = AHashMap::new();
+        for (key, names) in macros.into_iter() {
+            let key = normalize_key(&key);
+            if let Some(other) = user_keys.get(&key) {
+                conflicted.insert(*other);
+            }
+            let mut actions = Vec::new();
+            for name in names.iter() {
+                match UserAction::from_config_name(name) {
+                    Some(action) => actions.push(action),
+                    None => {
+                        return Err(anyhow!(
+                            "Macro for key \"{key}\" in config.toml has an unknown action: \"{name}\""
+                        ))
+                    }
+                }
+            }
+            user_macros.insert(key, actions);
+        }
+
         let mut keymap = Self::default();
         for (config, action) in config_actions.into_iter() {
-            if let Some(config) = config {
-                keymap.insert_from_vec(config, action);
+            match config {
+                Some(config) if !conflicted.contains(&action) => {
+                    keymap.insert_from_vec(config, action)
+                }
+                _ => (),
             }
         }
-        return keymap;
+        // as with keybindings, macros take over their keys from any
+        // default action
+        for (key, actions) in user_macros.into_iter() {
+            keymap.keys.remove(&key);
+            keymap.macros.insert(key, actions);
+        }
+        return Ok(keymap);
     }
 
     /// Takes an Input object from crossterm and returns the associated
     /// user action, if one exists.
     pub fn get_from_input(&self, input: KeyEvent) -> Option<&UserAction> {
         match input_to_str(input) {
-            Some(code) => self.0.get(&code),
+            Some(code) => self.keys.get(&code),
+            None => None,
+        }
+    }
+
+    /// Takes an Input object from crossterm and returns the list of
+    /// actions in the macro bound to it, if one exists.
+    pub fn get_macro_from_input(&self, input: KeyEvent) -> Option<&Vec<UserAction>> {
+        match input_to_str(input) {
+            Some(code) => self.macros.get(&code),
             None => None,
         }
     }
 
+    /// Returns all macros, sorted by key, along with the actions they
+    /// perform.
+    pub fn macros(&self) -> Vec<(&String, &Vec<UserAction>)> {
+        let mut macros: Vec<_> = self.macros.iter().collect();
+        macros.sort_by_key(|(key, _)| *key);
+        return macros;
+    }
+
     /// Inserts a new keybinding into the hash map. Will overwrite the
     /// value of a key if it already exists.
     pub fn insert(&mut self, code: String, action: UserAction) {
-        self.0.insert(code, action);
+        self.keys.insert(normalize_key(&code), action);
     }
 
     /// Inserts a set of new keybindings into the hash map, each one
@@ -134,7 +445,7 @@ impl Keybindings {
     /// action.
     pub fn keys_for_action(&self, action: UserAction) -> Vec<String> {
         return self
-            .0
+            .keys
             .iter()
             .filter_map(|(key, &val)| {
                 if val == action {
@@ -146,6 +457,43 @@ impl Keybindings {
             .collect();
     }
 
+    /// Writes out the full keymap, including default keys, in the format
+    /// of the "keybindings" section of config.toml, followed by the
+    /// "macros" section if any macros are set. Actions that have no
+    /// keys left (because all of their keys were given to other actions)
+    /// are listed with an empty array.
+    pub fn to_toml(&self) -> String {
+        let mut out = "[keybindings]\n".to_string();
+        for (action, _) in Self::_defaults().into_iter() {
+            out += &self.config_line(action);
+            out.push('\n');
+        }
+
+        if !self.macros.is_empty() {
+            out += "\n[macros]\n";
+            for (key, actions) in self.macros() {
+                let names: Vec<String> =
+                    actions.iter().map(|a| format!("{:?}", a.config_name())).collect();
+                out += &format!("{key:?} = [ {} ]\n", names.join(", "));
+            }
+        }
+        return out;
+    }
+
+    /// Writes the line of the "keybindings" section of config.toml that
+    /// binds the keys for `action`.
+    pub fn config_line(&self, action: UserAction) -> String {
+        let mut keys = self.keys_for_action(action);
+        keys.sort();
+        let keys: Vec<String> = keys.iter().map(|k| format!("{k:?}")).collect();
+        let list = if keys.is_empty() {
+            "[]".to_string()
+        } else {
+            format!("[ {} ]", keys.join(", "))
+        };
+        return format!("{} = {list}", action.config_name());
+    }
+
     fn _defaults() -> Vec<(UserAction, Vec<String>)> {
         return vec![
             (UserAction::Left, vec!["Left".to_string(), "h".to_string()]),
@@ -161,27 +509,134 @@ impl Keybindings {
             (UserAction::PageDown, vec!["PgDn".to_string()]),
             (UserAction::GoTop, vec!["g".to_string()]),
             (UserAction::GoBot, vec!["G".to_string()]),
+            (UserAction::ToggleGroup, vec!["z".to_string()]),
+            (UserAction::ToggleAllGroups, vec!["Z".to_string()]),
+            (UserAction::ToggleLayout, vec!["A-l".to_string()]),
+            (UserAction::TabLibrary, vec!["A-1".to_string()]),
+            (UserAction::TabNew, vec!["A-2".to_string()]),
+            (UserAction::TabQueue, vec!["A-3".to_string()]),
+            (UserAction::TabDownloads, vec!["A-4".to_string()]),
+            (UserAction::TabSearch, vec!["A-5".to_string()]),
+            (UserAction::NextTab, vec!["Tab".to_string()]),
             (UserAction::AddFeed, vec!["a".to_string()]),
+            (UserAction::Import, vec!["O".to_string()]),
             (UserAction::Sync, vec!["s".to_string()]),
             (UserAction::SyncAll, vec!["S".to_string()]),
-            (UserAction::Play, vec!["Enter".to_string(), "p".to_string()]),
+            (UserAction::CheckAll, vec!["A-s".to_string()]),
+            (UserAction::Select, vec!["Enter".to_string()]),
+            (UserAction::Play, vec!["p".to_string()]),
             (UserAction::MarkPlayed, vec!["m".to_string()]),
             (UserAction::MarkAllPlayed, vec!["M".to_string()]),
+            (UserAction::MarkOlderPlayed, vec!["A-m".to_string()]),
+            (UserAction::MarkLibraryOlderPlayed, vec!["A-M".to_string()]),
+            (UserAction::ToggleStar, vec!["*".to_string()]),
+            (UserAction::Enqueue, vec!["+".to_string()]),
+            (UserAction::EnqueueNext, vec!["=".to_string()]),
+            (UserAction::MoveUp, vec!["A-k".to_string()]),
+            (UserAction::MoveDown, vec!["A-j".to_string()]),
+            (UserAction::MoveToTop, vec!["A-g".to_string()]),
+            (UserAction::MoveToBottom, vec!["A-G".to_string()]),
+            (UserAction::ShuffleQueue, vec!["A-r".to_string()]),
+            (UserAction::InterleaveQueue, vec!["A-i".to_string()]),
+            (UserAction::FillQueue, vec!["A-f".to_string()]),
             (UserAction::Download, vec!["d".to_string()]),
             (UserAction::DownloadAll, vec!["D".to_string()]),
+            (UserAction::ChooseEnclosure, vec!["E".to_string()]),
+            (UserAction::DownloadLibrary, vec!["A-d".to_string()]),
+            (UserAction::Cancel, vec!["C-c".to_string()]),
             (UserAction::Delete, vec!["x".to_string()]),
             (UserAction::DeleteAll, vec!["X".to_string()]),
             (UserAction::UnmarkDownloaded, vec!["u".to_string()]),
             (UserAction::Remove, vec!["r".to_string()]),
             (UserAction::RemoveAll, vec!["R".to_string()]),
+            (UserAction::ToggleSelect, vec!["Space".to_string()]),
+            (UserAction::ToggleNotify, vec!["n".to_string()]),
+            (UserAction::PodcastSettings, vec!["o".to_string()]),
+            (UserAction::ChangeFeedUrl, vec!["e".to_string()]),
+            (UserAction::Support, vec!["$".to_string()]),
+            (UserAction::CycleTheme, vec!["t".to_string()]),
+            (UserAction::ThemePreview, vec!["P".to_string()]),
+            (UserAction::ReloadConfig, vec!["C".to_string()]),
+            (UserAction::Setup, vec!["A-o".to_string()]),
+            (UserAction::Chapters, vec!["c".to_string()]),
+            (UserAction::Transcript, vec!["T".to_string()]),
+            (UserAction::Search, vec!["/".to_string()]),
+            (UserAction::CopyMode, vec!["v".to_string()]),
+            (UserAction::Yank, vec!["y".to_string()]),
+            (UserAction::YankAll, vec!["Y".to_string()]),
+            (UserAction::ToggleWrap, vec!["W".to_string()]),
             (UserAction::FilterPlayed, vec!["1".to_string()]),
             (UserAction::FilterDownloaded, vec!["2".to_string()]),
+            (UserAction::CycleSort, vec!["3".to_string()]),
+            (UserAction::CycleFilter, vec!["4".to_string()]),
+            (UserAction::FilterMenu, vec!["5".to_string()]),
+            (UserAction::Find, vec!["f".to_string()]),
+            (UserAction::FindInMenu, vec!["F".to_string()]),
+            (UserAction::InProgress, vec!["w".to_string()]),
+            (UserAction::Inbox, vec!["i".to_string()]),
+            (UserAction::TriageMode, vec!["b".to_string()]),
+            (UserAction::Queue, vec!["Q".to_string()]),
+            (UserAction::Stats, vec!["I".to_string()]),
+            (UserAction::ShowLog, vec!["L".to_string()]),
+            (UserAction::Notifications, vec!["N".to_string()]),
+            (UserAction::Suspend, vec!["C-z".to_string()]),
+            (UserAction::Shell, vec!["!".to_string()]),
             (UserAction::Help, vec!["?".to_string()]),
             (UserAction::Quit, vec!["q".to_string()]),
         ];
     }
 }
 
+/// Helper function converting a key as written in config.toml to the
+/// form returned by `input_to_str()`, so that, e.g., "C-r" and "Ctrl+r"
+/// are treated as the same key. "C-", "A-" (or "M-"), and "S-" prefixes
+/// stand for the Ctrl, Alt, and Shift modifiers.
+pub fn normalize_key(key: &str) -> String {
+    let mut ctrl = "";
+    let mut alt = "";
+    let mut shift = false;
+    let mut rest = key;
+    loop {
+        // a prefix needs something after it, so that keys like "C"
+        // still refer to the letter itself
+        let next = [
+            ("C-", 0),
+            ("Ctrl+", 0),
+            ("A-", 1),
+            ("M-", 1),
+            ("Alt+", 1),
+            ("S-", 2),
+            ("Shift+", 2),
+        ]
+        .iter()
+        .find(|(prefix, _)| rest.len() > prefix.len() && rest.starts_with(prefix))
+        .copied();
+        match next {
+            Some((prefix, modifier)) => {
+                match modifier {
+                    0 => ctrl = "Ctrl+",
+                    1 => alt = "Alt+",
+                    _ => shift = true,
+                }
+                rest = &rest[prefix.len()..];
+            }
+            None => break,
+        }
+    }
+
+    // as in `input_to_str()`, Shift with a single character is written
+    // as the shifted character itself
+    let mut chars = rest.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        if shift {
+            return format!("{ctrl}{alt}{}", c.to_uppercase());
+        }
+        return format!("{ctrl}{alt}{c}");
+    }
+    let shift = if shift { "Shift+" } else { "" };
+    return format!("{ctrl}{alt}{shift}{rest}");
+}
+
 /// Helper function converting a crossterm KeyEvent object to a unique
 /// string representing that input.
 pub fn input_to_str(input: KeyEvent) -> Option<String> {
@@ -227,6 +682,8 @@ pub fn input_to_str(input: KeyEvent) -> Option<String> {
                 Some(format!("{ctrl}{alt}{shift}Enter"))
             } else if c == '\t' {
                 Some(format!("{ctrl}{alt}{shift}Tab"))
+            } else if c == ' ' {
+                Some(format!("{ctrl}{alt}{shift}Space"))
             } else {
                 // here we don't include "shift" because that will
                 // already be encoded in the character itself
@@ -236,3 +693,95 @@ pub fn input_to_str(input: KeyEvent) -> Option<String> {
         _ => None,
     };
 }
+
+
+// TESTS -----------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keymap_from_toml(text: &str) -> Result<Keybindings> {
+        let config: KeybindingsFromToml = toml::from_str(text).unwrap();
+        return Keybindings::from_config(config, BTreeMap::new());
+    }
+
+    fn keymap_with_macros(text: &str, macros: &str) -> Result<Keybindings> {
+        let config: KeybindingsFromToml = toml::from_str(text).unwrap();
+        let macros: BTreeMap<String, Vec<String>> = toml::from_str(macros).unwrap();
+        return Keybindings::from_config(config, macros);
+    }
+
+    #[test]
+    fn normalize_modifiers() {
+        assert_eq!(normalize_key("C-r"), "Ctrl+r");
+        assert_eq!(normalize_key("Ctrl+r"), "Ctrl+r");
+        assert_eq!(normalize_key("S-Del"), "Shift+Del");
+        assert_eq!(normalize_key("S-a"), "A");
+        assert_eq!(normalize_key("A-C-x"), "Ctrl+Alt+x");
+        assert_eq!(normalize_key("C"), "C");
+        assert_eq!(normalize_key("Enter"), "Enter");
+    }
+
+    #[test]
+    fn conflicting_keys() {
+        // both actions keep their default keys
+        let keymap = keymap_from_toml("download = [\"d\"]\ndelete = [\"x\", \"d\"]").unwrap();
+        let default = Keybindings::default();
+        for action in [UserAction::Download, UserAction::Delete] {
+            assert_eq!(keymap.keys_for_action(action), default.keys_for_action(action));
+        }
+
+        // different spellings of the same key also conflict
+        let keymap = keymap_from_toml("sync = [\"C-s\"]\nsync_all = [\"Ctrl+s\"]").unwrap();
+        assert_eq!(
+            keymap.keys_for_action(UserAction::SyncAll),
+            default.keys_for_action(UserAction::SyncAll)
+        );
+    }
+
+    #[test]
+    fn override_default_key() {
+        // "t" is the default for cycle_theme, which is left unbound
+        let keymap = keymap_from_toml("play = [\"t\"]").unwrap();
+        assert!(keymap.keys_for_action(UserAction::Play).contains(&"t".to_string()));
+        assert!(keymap.keys_for_action(UserAction::CycleTheme).is_empty());
+    }
+
+    #[test]
+    fn keymap_to_toml() {
+        let keymap = keymap_from_toml("play = [\"t\"]").unwrap();
+        let text = keymap.to_toml();
+        assert!(text.contains("\nleft = [ \"Left\", \"h\" ]\n"));
+        assert!(text.contains("\ncycle_theme = []\n"));
+
+        // the output can be read back in as a config file
+        let table: toml::Value = toml::from_str(&text).unwrap();
+        let config: KeybindingsFromToml = table["keybindings"].clone().try_into().unwrap();
+        let reloaded = Keybindings::from_config(config, BTreeMap::new()).unwrap();
+        assert_eq!(reloaded.to_toml(), text);
+    }
+
+    #[test]
+    fn macros() {
+        let keymap = keymap_with_macros("", "\"S-d\" = [\"mark_played\", \"delete\", \"down\"]")
+            .unwrap();
+        assert_eq!(keymap.macros(), vec![(
+            &"D".to_string(),
+            &vec![UserAction::MarkPlayed, UserAction::Delete, UserAction::Down]
+        )]);
+        // "D" is no longer bound to its default action
+        assert!(keymap.keys_for_action(UserAction::DownloadAll).is_empty());
+        assert!(keymap.to_toml().contains("\n[macros]\n\"D\" = [ \"mark_played\", \"delete\", \"down\" ]\n"));
+
+        let err = keymap_with_macros("", "x = [\"mark_played\", \"fly\"]").unwrap_err();
+        assert!(err.to_string().contains("unknown action: \"fly\""));
+
+        // the macro keeps its key, and the action its default keys
+        let keymap = keymap_with_macros("play = [\"x\"]", "x = [\"down\"]").unwrap();
+        assert_eq!(keymap.macros().len(), 1);
+        assert_eq!(
+            keymap.keys_for_action(UserAction::Play),
+            Keybindings::default().keys_for_action(UserAction::Play)
+        );
+    }
+}
@@ -0,0 +1,231 @@
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::feeds::PodcastFeed;
+use crate::opml;
+use crate::types::*;
+
+/// Version of the library file format. Files written by a newer version
+/// of shellcaster are refused rather than imported partially.
+const FORMAT_VERSION: u32 = 1;
+
+/// A full export of the podcast library, holding the settings of each
+/// podcast along with its feed. Unlike OPML, this keeps everything
+/// needed to move a library to another machine.
+#[derive(Debug, Serialize, Deserialize)]
+struct Library {
+    version: u32,
+    podcasts: Vec<LibraryPodcast>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LibraryPodcast {
+    title: Option<String>,
+    url: String,
+    #[serde(flatten)]
+    settings: LibrarySettings,
+}
+
+/// The settings of a podcast that are kept in a library file. These are
+/// listed one by one, rather than using all of `PodcastSettings`, as
+/// library files are shared and imported from the watch folder: logins
+/// and TLS settings are left out so they are never written out in plain
+/// text, and the download folder and play command are left out so that
+/// importing a file can't make shellcaster run a command or write files
+/// somewhere else.
+#[derive(Debug, Serialize, Deserialize)]
+struct LibrarySettings {
+    #[serde(default = "default_notify")]
+    notify: bool,
+    custom_title: Option<String>,
+    include_pattern: Option<String>,
+    exclude_pattern: Option<String>,
+    title_pattern: Option<String>,
+    title_replacement: Option<String>,
+    show_pattern: Option<String>,
+    hide_pattern: Option<String>,
+    group: Option<String>,
+    auto_download: Option<i64>,
+    keep_downloads: Option<i64>,
+    preferred_enclosure: Option<String>,
+}
+
+fn default_notify() -> bool {
+    return true;
+}
+
+impl From<PodcastSettings> for LibrarySettings {
+    fn from(settings: PodcastSettings) -> Self {
+        return LibrarySettings {
+            notify: settings.notify,
+            custom_title: settings.custom_title,
+            include_pattern: settings.include_pattern,
+            exclude_pattern: settings.exclude_pattern,
+            title_pattern: settings.title_pattern,
+            title_replacement: settings.title_replacement,
+            show_pattern: settings.show_pattern,
+            hide_pattern: settings.hide_pattern,
+            group: settings.group,
+            auto_download: settings.overrides.auto_download,
+            keep_downloads: settings.overrides.keep_downloads,
+            preferred_enclosure: settings.overrides.preferred_enclosure,
+        };
+    }
+}
+
+impl From<LibrarySettings> for PodcastSettings {
+    fn from(settings: LibrarySettings) -> Self {
+        return PodcastSettings {
+            notify: settings.notify,
+            custom_title: settings.custom_title,
+            include_pattern: settings.include_pattern,
+            exclude_pattern: settings.exclude_pattern,
+            title_pattern: settings.title_pattern,
+            title_replacement: settings.title_replacement,
+            show_pattern: settings.show_pattern,
+            hide_pattern: settings.hide_pattern,
+            group: settings.group,
+            overrides: PodcastOverrides {
+                auto_download: settings.auto_download,
+                keep_downloads: settings.keep_downloads,
+                preferred_enclosure: settings.preferred_enclosure,
+                ..PodcastOverrides::default()
+            },
+            auth: FeedAuth::default(),
+        };
+    }
+}
+
+/// Reads a list of podcast feeds from either a library file or an OPML
+/// file, depending on what the text looks like.
+pub fn import_any(text: String) -> Result<Vec<PodcastFeed>> {
+    if text.trim_start().starts_with('{') {
+        return import(&text);
+    }
+    return opml::import(text);
+}
+
+/// Reads a list of podcast feeds, with their settings, from a library
+/// file.
+pub fn import(text: &str) -> Result<Vec<PodcastFeed>> {
+    let library: Library =
+        serde_json::from_str(text).with_context(|| "Could not parse library file")?;
+    if library.version > FORMAT_VERSION {
+        return Err(anyhow!(
+            "Library file was written by a newer version of shellcaster"
+        ));
+    }
+    return Ok(library
+        .podcasts
+        .into_iter()
+        .map(|pod| {
+            let mut feed = PodcastFeed::new(None, pod.url, pod.title);
+            feed.settings = Some(Box::new(pod.settings.into()));
+            feed
+        })
+        .collect());
+}
+
+/// Converts the current set of podcasts, with their settings, to the
+/// library file format.
+pub fn export(podcasts: &[Podcast]) -> Result<String> {
+    let library = Library {
+        version: FORMAT_VERSION,
+        podcasts: podcasts
+            .iter()
+            .map(|pod| LibraryPodcast {
+                title: Some(pod.title.clone()),
+                url: pod.url.clone(),
+                settings: pod.settings().into(),
+            })
+            .collect(),
+    };
+    return serde_json::to_string_pretty(&library)
+        .with_context(|| "Could not create library file");
+}
+
+
+// TESTS -----------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn import_settings() {
+        let text = r#"{"version": 1, "podcasts": [
+            {"title": "A", "url": "https://example.com/a.xml", "notify": false,
+             "custom_title": "Short A", "include_pattern": "interview", "keep_downloads": 3},
+            {"url": "https://example.com/b.xml"}
+        ]}"#;
+        let feeds = import_any(text.to_string()).unwrap();
+        assert_eq!(feeds.len(), 2);
+
+        let settings = feeds[0].settings.as_ref().unwrap();
+        assert!(!settings.notify);
+        assert_eq!(settings.custom_title.as_deref(), Some("Short A"));
+        assert_eq!(settings.include_pattern.as_deref(), Some("interview"));
+        assert_eq!(settings.overrides.keep_downloads, Some(3));
+        assert_eq!(settings.overrides.download_dir, None);
+
+        // missing settings fall back to the defaults
+        let settings = feeds[1].settings.as_ref().unwrap();
+        assert!(settings.notify);
+        assert_eq!(settings.custom_title, None);
+        assert_eq!(feeds[1].title, None);
+    }
+
+    #[test]
+    fn import_leaves_out_unsafe_settings() {
+        let text = r#"{"version": 1, "podcasts": [
+            {"url": "https://example.com/a.xml", "play_command": "rm -rf ~ %s",
+             "download_dir": "/etc", "user": "me", "password": "secret",
+             "headers": ["Authorization: Bearer abc"], "accept_invalid_certs": true}
+        ]}"#;
+        let feeds = import(text).unwrap();
+        let settings = feeds[0].settings.as_ref().unwrap();
+        assert_eq!(settings.overrides.play_command, None);
+        assert_eq!(settings.overrides.download_dir, None);
+        assert_eq!(settings.auth, FeedAuth::default());
+    }
+
+    #[test]
+    fn export_leaves_out_logins() {
+        let settings = PodcastSettings {
+            custom_title: Some("Short A".to_string()),
+            overrides: PodcastOverrides {
+                play_command: Some("vlc %s".to_string()),
+                download_dir: Some("/home/me/a".to_string()),
+                keep_downloads: Some(3),
+                ..PodcastOverrides::default()
+            },
+            auth: FeedAuth {
+                user: Some("me".to_string()),
+                password: Some("secret".to_string()),
+                headers: vec!["Authorization: Bearer abc".to_string()],
+                accept_invalid_certs: true,
+                keyring_entry: None,
+            },
+            ..PodcastSettings::default()
+        };
+        let library = Library {
+            version: FORMAT_VERSION,
+            podcasts: vec![LibraryPodcast {
+                title: Some("A".to_string()),
+                url: "https://example.com/a.xml".to_string(),
+                settings: settings.into(),
+            }],
+        };
+        let text = serde_json::to_string(&library).unwrap();
+        for hidden in ["secret", "Bearer", "vlc", "/home/me", "accept_invalid_certs"] {
+            assert!(!text.contains(hidden), "{hidden} was exported");
+        }
+        assert!(text.contains("\"custom_title\":\"Short A\""));
+        assert!(text.contains("\"keep_downloads\":3"));
+    }
+
+    #[test]
+    fn newer_version() {
+        let text = r#"{"version": 99, "podcasts": []}"#;
+        assert!(import(text).is_err());
+    }
+}
@@ -0,0 +1,219 @@
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use log::{LevelFilter, Log, Metadata, Record};
+
+/// Size, in bytes, at which the log file is moved aside and a new one
+/// is started.
+pub const MAX_LOG_SIZE: u64 = 1_000_000;
+
+/// Most lines of the log shown in the log window.
+pub const LOG_LINES_SHOWN: usize = 500;
+
+/// Logger that appends messages from shellcaster (but not from the
+/// libraries it uses) to a file. Once the file grows past
+/// `MAX_LOG_SIZE`, it is renamed with ".1" added to the end, replacing
+/// any older one, and a new file is started.
+struct FileLogger {
+    path: PathBuf,
+    level: LevelFilter,
+    file: Mutex<Option<File>>,
+}
+
+impl FileLogger {
+    fn new(path: &Path, level: LevelFilter) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Could not create folder: {}", dir.to_string_lossy()))?;
+        }
+        let file = open(path)?;
+        return Ok(Self {
+            path: path.to_path_buf(),
+            level: level,
+            file: Mutex::new(Some(file)),
+        });
+    }
+
+    /// Moves the log file aside and opens a new one, if it has grown
+    /// too large.
+    fn rotate(&self, file: &mut Option<File>) {
+        let size = file.as_ref().and_then(|f| f.metadata().ok()).map(|m| m.len());
+        if size.is_some_and(|size| size >= MAX_LOG_SIZE) {
+            *file = None;
+            let _ = fs::rename(&self.path, backup_path(&self.path));
+            *file = open(&self.path).ok();
+        }
+    }
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        return metadata.level() <= self.level && metadata.target().starts_with("shellcaster");
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = format!(
+            "{} {:<5} {}\n",
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+            record.level(),
+            record.args()
+        );
+        if let Ok(mut file) = self.file.lock() {
+            if let Some(f) = file.as_mut() {
+                let _ = f.write_all(line.as_bytes());
+            }
+            self.rotate(&mut file);
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            if let Some(f) = file.as_mut() {
+                let _ = f.flush();
+            }
+        }
+    }
+}
+
+/// Starts logging to `path`, keeping messages at `level` or above.
+/// Nothing is logged if `level` is off.
+pub fn init(path: &Path, level: LevelFilter) -> Result<()> {
+    if level == LevelFilter::Off {
+        return Ok(());
+    }
+    let logger = FileLogger::new(path, level)?;
+    log::set_boxed_logger(Box::new(logger)).with_context(|| "Could not start logging")?;
+    log::set_max_level(level);
+    return Ok(());
+}
+
+/// Converts the `log_level` set in config.toml.
+pub fn parse_level(level: &str) -> Option<LevelFilter> {
+    return match level {
+        "off" => Some(LevelFilter::Off),
+        "error" => Some(LevelFilter::Error),
+        "warn" => Some(LevelFilter::Warn),
+        "info" => Some(LevelFilter::Info),
+        "debug" => Some(LevelFilter::Debug),
+        _ => None,
+    };
+}
+
+/// Reads up to `max` of the most recent lines of the log, newest first,
+/// continuing into the older file if the current one was started
+/// recently.
+pub fn read_recent(path: &Path, max: usize) -> Result<Vec<String>> {
+    let mut lines = Vec::new();
+    for file in [path.to_path_buf(), backup_path(path)] {
+        let contents = match fs::read_to_string(&file) {
+            Ok(contents) => contents,
+            Err(_) if file != path => break,
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("Could not read log: {}", path.to_string_lossy()))
+            }
+        };
+        lines.extend(
+            contents
+                .lines()
+                .rev()
+                .take(max - lines.len())
+                .map(str::to_string),
+        );
+        if lines.len() >= max {
+            break;
+        }
+    }
+    return Ok(lines);
+}
+
+fn open(path: &Path) -> Result<File> {
+    return OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Could not open log file: {}", path.to_string_lossy()));
+}
+
+/// Returns the path the log file is moved to when it grows too large.
+fn backup_path(path: &Path) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(".1");
+    return PathBuf::from(name);
+}
+
+
+// TESTS -----------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("shellcaster-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        return dir;
+    }
+
+    #[test]
+    fn levels_and_targets() {
+        let dir = log_dir("log-levels");
+        let path = dir.join("shellcaster.log");
+        let logger = FileLogger::new(&path, LevelFilter::Warn).unwrap();
+        logger.log(
+            &Record::builder()
+                .level(log::Level::Warn)
+                .target("shellcaster::feeds")
+                .args(format_args!("Could not sync feed"))
+                .build(),
+        );
+        logger.log(
+            &Record::builder()
+                .level(log::Level::Info)
+                .target("shellcaster::feeds")
+                .args(format_args!("Synced feed"))
+                .build(),
+        );
+        logger.log(
+            &Record::builder()
+                .level(log::Level::Error)
+                .target("rustls::conn")
+                .args(format_args!("Not ours"))
+                .build(),
+        );
+        let lines = read_recent(&path, 10).unwrap();
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].ends_with("WARN  Could not sync feed"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn rotation() {
+        let dir = log_dir("log-rotation");
+        let path = dir.join("shellcaster.log");
+        let logger = FileLogger::new(&path, LevelFilter::Info).unwrap();
+        let long = "x".repeat(MAX_LOG_SIZE as usize);
+        for text in ["first", long.as_str(), "third"] {
+            logger.log(
+                &Record::builder()
+                    .level(log::Level::Info)
+                    .target("shellcaster")
+                    .args(format_args!("{text}"))
+                    .build(),
+            );
+        }
+        assert!(backup_path(&path).exists());
+        let lines = read_recent(&path, 3).unwrap();
+        assert!(lines[0].ends_with("third"));
+        assert!(lines[1].ends_with(&long));
+        assert!(lines[2].ends_with("first"));
+        assert_eq!(read_recent(&path, 1).unwrap().len(), 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,30 +1,67 @@
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 use std::process;
 use std::sync::mpsc;
+use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, Context, Result};
 use clap::{Arg, Command};
 
+mod changelog;
+mod chapters;
+mod clipboard;
+mod command;
+mod completions;
 mod config;
+mod config_check;
+mod config_writer;
+mod cover_art;
 mod db;
+mod download_rules;
 mod downloads;
+mod encoding;
 mod feeds;
+mod fuzzy;
+mod hooks;
+mod html;
+mod http;
 mod keymap;
+mod library;
+mod logging;
 mod main_controller;
+#[cfg(feature = "mpris")]
+mod mpris;
 mod opml;
+mod output;
 mod play_file;
+mod queue;
+mod remote;
+mod sanitizer;
+mod scrobble;
+mod secrets;
+mod smart_filters;
+mod status;
+mod storage;
 mod threadpool;
+mod title_rules;
+mod transcript;
 mod types;
 mod ui;
+mod updates;
+mod watch;
+mod webhooks;
 
 use crate::config::Config;
 use crate::db::Database;
 use crate::feeds::{FeedMsg, PodcastFeed};
+use crate::downloads::{DownloadMsg, EpData};
+use crate::hooks::{HookData, HookEvent};
 use crate::main_controller::{MainController, MainMessage};
-use crate::threadpool::Threadpool;
+use crate::threadpool::{CancelToken, TaskPool};
 use crate::types::*;
+use crate::webhooks::Notice;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -55,11 +92,147 @@ const VERSION: &str = env!("CARGO_PKG_VERSION");
 /// *Export subcommand:*
 /// Connects to the sqlite database, and reads all podcasts into an OPML
 /// file, with the location specified from the command line arguments.
+///
+/// *Keymap subcommand:*
+/// Writes out the keybindings in effect, combining config.toml with the
+/// default keys, in the same format as config.toml.
+///
+/// *Maintain subcommand:*
+/// Checks the database for corruption, then updates its query
+/// statistics and compacts it. This also happens automatically every so
+/// often when the app starts.
+///
+/// *List subcommand:*
+/// Prints the podcasts in the database, or the episodes of the podcasts
+/// named on the command line.
+///
+/// *Remote subcommand:*
+/// Sends a command (e.g., to sync all podcasts or play an episode) to a
+/// running instance of shellcaster over its remote control socket.
+///
+/// *Secret subcommand:*
+/// Stores a password or token in the system keyring, where it is used
+/// in place of the one in config.toml, or moves the passwords of
+/// private feeds from the database into the keyring.
+///
+/// *Check-config subcommand:*
+/// Checks the config file for errors and for settings that would be
+/// ignored, and lists them along with the lines they are on.
+///
+/// *Default-config subcommand:*
+/// Writes out a config file with every setting commented out and all
+/// of the default keybindings and colors, to start a new config from
+/// or to compare an existing one with.
+///
+/// *Completions subcommand:*
+/// Prints a completion script for bash, zsh, or fish, which completes
+/// subcommands and flags, and the titles of podcasts where a command
+/// takes them.
 fn main() -> Result<()> {
     // SETUP -----------------------------------------------------------
 
     // set up the possible command line arguments and subcommands
-    let args = Command::new(clap::crate_name!())
+    let args = command_line().get_matches();
+
+    // figure out where config file is located -- either specified from
+    // command line args, set via $SHELLCASTER_CONFIG, or using default
+    // config location for OS
+    let config_path = get_config_path(args.value_of("config"))?
+        .unwrap_or_else(|| {
+            eprintln!("Could not identify your operating system's default directory to store configuration files. Please specify paths manually using config.toml and use `-c` or `--config` flag to specify where config.toml is located when launching the program.");
+            process::exit(1);
+        });
+
+    // problems with the config file are reported by check-config, so it
+    // runs before the config is loaded
+    match args.subcommand() {
+        Some(("check-config", _)) => return check_config(&config_path),
+        Some(("default-config", sub_args)) => return default_config(sub_args),
+        _ => (),
+    }
+    let config = match Config::new(&config_path) {
+        Ok(config) => config,
+        // the interface starts with the default settings instead, and
+        // shows what is wrong with the config file
+        Err(_) if args.subcommand().is_none() && !args.is_present("bench-startup") => {
+            Config::defaults(&config_path)?
+        }
+        Err(err) => return Err(err),
+    };
+
+    // extra certificate authorities and network settings have to be in
+    // place before any feeds are fetched
+    if let Some(ca_file) = &config.ca_file {
+        http::load_ca_file(ca_file)?;
+    }
+    http::set_network(config.network.clone());
+
+    // not being able to write the log shouldn't stop shellcaster from
+    // running
+    if let Err(err) = logging::init(&config.log_file, config.log_level) {
+        eprintln!("{err:#}");
+    }
+
+    let db_path = get_data_dir(&args, &config_path)?;
+
+
+    return match args.subcommand() {
+        // SYNC SUBCOMMAND ----------------------------------------------
+        Some(("sync", sub_args)) => sync_podcasts(&db_path, config, sub_args),
+
+        // CHECK SUBCOMMAND ---------------------------------------------
+        Some(("check", sub_args)) => check_podcasts(&db_path, config, sub_args),
+
+        // DOWNLOAD SUBCOMMAND ------------------------------------------
+        Some(("download", sub_args)) => download_library(&db_path, config, sub_args),
+
+        // IMPORT SUBCOMMAND --------------------------------------------
+        Some(("import", sub_args)) => import(&db_path, config, sub_args),
+
+        // EXPORT SUBCOMMAND --------------------------------------------
+        Some(("export", sub_args)) => export(&db_path, sub_args),
+
+        // KEYMAP SUBCOMMAND --------------------------------------------
+        Some(("keymap", sub_args)) => keymap(&config, sub_args),
+
+        // MAINTAIN SUBCOMMAND ------------------------------------------
+        Some(("maintain", sub_args)) => maintain(&db_path, sub_args),
+
+        // LIST SUBCOMMAND ----------------------------------------------
+        Some(("list", sub_args)) => list(&db_path, &config, sub_args),
+
+        // REMOTE SUBCOMMAND --------------------------------------------
+        Some(("remote", sub_args)) => remote(&config, sub_args),
+
+        // SECRET SUBCOMMAND --------------------------------------------
+        Some(("secret", sub_args)) => secret(&db_path, sub_args),
+
+        // COMPLETIONS SUBCOMMAND ---------------------------------------
+        Some(("completions", sub_args)) => print_completions(&db_path, sub_args),
+
+        // MAIN COMMAND -------------------------------------------------
+        _ if args.is_present("bench-startup") => bench_startup(&db_path, config),
+        _ => {
+            let mut main_ctrl = MainController::new(config, config_path, &db_path)?;
+
+            main_ctrl.loop_msgs(); // main loop
+
+            let _ = main_ctrl.tx_to_ui.send(MainMessage::UiTearDown);
+            // wait for UI thread to finish teardown; if the UI stopped
+            // early, this is where its error comes out
+            match main_ctrl.ui_thread.join() {
+                Ok(result) => result.context("The interface stopped unexpectedly"),
+                Err(_) => Err(anyhow!("The interface crashed")),
+            }
+        }
+    };
+}
+
+
+/// Builds the command line interface: the possible arguments and
+/// subcommands. This is also used to generate shell completions.
+fn command_line() -> Command<'static> {
+    return Command::new(clap::crate_name!())
         .version(clap::crate_version!())
         // .author(clap::crate_authors!(", "))
         .author("Jeff Hughes <jeff.hughes@gmail.com>")
@@ -72,20 +245,58 @@ fn main() -> Result<()> {
             .takes_value(true)
             .value_name("FILE")
             .help("Sets a custom config file location. Can also be set with environment variable."))
+        .arg(Arg::new("data-dir")
+            .long("data-dir")
+            .env("SHELLCASTER_DATA_DIR")
+            .global(true)
+            .takes_value(true)
+            .value_name("DIR")
+            .help("Sets the folder where the database is kept. Can also be set with environment variable."))
+        .arg(Arg::new("bench-startup")
+            .long("bench-startup")
+            .hide(true)
+            .help("Loads the library and draws the screen once, then prints how long each step took."))
         .subcommand(Command::new("sync")
             .about("Syncs all podcasts in database")
+            .arg(Arg::new("podcast")
+                .multiple_values(true)
+                .value_name("PODCAST")
+                .help("Only syncs the podcasts with these titles, including archived ones."))
+            .arg(Arg::new("scheduled")
+                .short('s')
+                .long("scheduled")
+                .help("Skips feeds that ask not to be checked yet (with ttl, skipHours or skipDays), e.g., for cron jobs."))
+            .arg(Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .help("Suppresses output messages to stdout."))
+            .arg(json_arg()))
+        .subcommand(Command::new("check")
+            .about("Fetches all podcast feeds and reports what syncing would change, without saving anything")
+            .arg(Arg::new("podcast")
+                .multiple_values(true)
+                .value_name("PODCAST")
+                .help("Only checks the podcasts with these titles, including archived ones."))
+            .arg(json_arg()))
+        .subcommand(Command::new("download")
+            .about("Downloads every unplayed episode that matches the current filter, in all podcasts")
+            .arg(Arg::new("yes")
+                .short('y')
+                .long("yes")
+                .help("Starts the downloads without asking first."))
             .arg(Arg::new("quiet")
                 .short('q')
                 .long("quiet")
-                .help("Suppresses output messages to stdout.")))
+                .help("Suppresses output messages to stdout."))
+            .arg(json_arg().requires("yes")))
         .subcommand(Command::new("import")
-            .about("Imports podcasts from an OPML file")
+            .about("Imports podcasts from an OPML file or a shellcaster library file")
             .arg(Arg::new("file")
                 .short('f')
                 .long("file")
                 .takes_value(true)
                 .value_name("FILE")
-                .help("Specifies the filepath to the OPML file to be imported. If this flag is not set, the command will read from stdin."))
+                .help("Specifies the filepath to the OPML or library file to be imported. If this flag is not set, the command will read from stdin. Library files (exported with --library) also restore the settings of each podcast."))
             .arg(Arg::new("replace")
                 .short('r')
                 .long("replace")
@@ -94,7 +305,8 @@ fn main() -> Result<()> {
             .arg(Arg::new("quiet")
                 .short('q')
                 .long("quiet")
-                .help("Suppresses output messages to stdout.")))
+                .help("Suppresses output messages to stdout."))
+            .arg(json_arg()))
         .subcommand(Command::new("export")
             .about("Exports podcasts to an OPML file")
             .arg(Arg::new("file")
@@ -102,121 +314,332 @@ fn main() -> Result<()> {
                 .long("file")
                 .takes_value(true)
                 .value_name("FILE")
-                .help("Specifies the filepath for where the OPML file will be exported. If this flag is not set, the command will print to stdout.")))
-        .get_matches();
+                .help("Specifies the filepath for where the OPML file will be exported. If this flag is not set, the command will print to stdout."))
+            .arg(Arg::new("library")
+                .short('l')
+                .long("library")
+                .takes_value(false)
+                .help("Exports a shellcaster library file (JSON) instead of OPML, which includes the settings of each podcast, for moving the full library to another machine.")))
+        .subcommand(Command::new("keymap")
+            .about("Lists all keybindings, including defaults, in config file format")
+            .arg(Arg::new("file")
+                .short('f')
+                .long("file")
+                .takes_value(true)
+                .value_name("FILE")
+                .help("Specifies the filepath for where the keybindings will be written. If this flag is not set, the command will print to stdout.")))
+        .subcommand(Command::new("maintain")
+            .about("Checks, analyzes, and compacts the database")
+            .arg(Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .help("Suppresses output messages to stdout."))
+            .arg(json_arg()))
+        .subcommand(Command::new("list")
+            .about("Lists the podcasts in the library, or the episodes of some of them")
+            .arg(Arg::new("podcast")
+                .multiple_values(true)
+                .value_name("PODCAST")
+                .help("Lists the episodes of the podcasts with these titles instead."))
+            .arg(json_arg()))
+        .subcommand(Command::new("remote")
+            .about("Sends a command to a running instance of shellcaster")
+            .arg(Arg::new("command")
+                .required(true)
+                .multiple_values(true)
+                .value_name("COMMAND")
+                .help("The command to send: sync-all, sync <podcast id>, play <episode id>, play-next, download <episode id>, mark-played <episode id>, mark-unplayed <episode id>, list [podcast id], or quit."))
+            .arg(Arg::new("host")
+                .short('H')
+                .long("host")
+                .takes_value(true)
+                .value_name("HOST")
+                .help("Sends the command over SSH to shellcaster running on HOST (e.g., me@homeserver), instead of to this machine. Overrides remote_host in config.toml.")))
+        .subcommand(Command::new("secret")
+            .about("Stores a password or token in the system keyring instead of config.toml")
+            .arg(Arg::new("name")
+                .required_unless_present("feeds")
+                .possible_values(secrets::CONFIG_SECRETS)
+                .value_name("NAME")
+                .help("The setting to store, which is read from stdin."))
+            .arg(Arg::new("delete")
+                .short('d')
+                .long("delete")
+                .help("Removes the setting from the keyring instead."))
+            .arg(Arg::new("feeds")
+                .long("feeds")
+                .conflicts_with("name")
+                .help("Moves the passwords of private feeds from the database into the keyring.")))
+        .subcommand(Command::new("check-config")
+            .about("Checks the config file for errors and settings that would be ignored"))
+        .subcommand(Command::new("default-config")
+            .about("Prints a config file with all of the default settings")
+            .arg(Arg::new("file")
+                .short('f')
+                .long("file")
+                .takes_value(true)
+                .value_name("FILE")
+                .help("Specifies the filepath for where the config file will be written. If this flag is not set, the command will print to stdout. An existing file is never replaced.")))
+        .subcommand(Command::new("completions")
+            .about("Prints shell completions for shellcaster's subcommands and flags")
+            .arg(Arg::new("shell")
+                .required_unless_present("podcasts")
+                .possible_values(completions::SHELLS)
+                .value_name("SHELL")
+                .help("The shell to print completions for."))
+            .arg(Arg::new("podcasts")
+                .long("podcasts")
+                .hide(true)
+                .help("Lists the titles of all podcasts, one per line, for completing them.")));
+}
 
-    // figure out where config file is located -- either specified from
-    // command line args, set via $SHELLCASTER_CONFIG, or using default
-    // config location for OS
-    let config_path = get_config_path(args.value_of("config"))
-        .unwrap_or_else(|| {
-            eprintln!("Could not identify your operating system's default directory to store configuration files. Please specify paths manually using config.toml and use `-c` or `--config` flag to specify where config.toml is located when launching the program.");
-            process::exit(1);
-        });
-    let config = Config::new(&config_path)?;
 
-    let mut db_path = config_path;
-    if !db_path.pop() {
-        return Err(anyhow!("Could not correctly parse the config file location. Please specify a valid path to the config file."));
-    }
+/// The `--json` flag of subcommands whose results can be read by
+/// scripts (see the `output` module).
+fn json_arg() -> Arg<'static> {
+    return Arg::new("json")
+        .long("json")
+        .help("Prints the results as JSON instead, for use in scripts.");
+}
 
 
-    return match args.subcommand() {
-        // SYNC SUBCOMMAND ----------------------------------------------
-        Some(("sync", sub_args)) => sync_podcasts(&db_path, config, sub_args),
+/// Gets the path to the config file if one is specified in the command-
+/// line arguments, or else returns the default config path for the
+/// user's operating system.
+/// Returns None if default OS config directory cannot be determined.
+fn get_config_path(config: Option<&str>) -> Result<Option<PathBuf>> {
+    return match config {
+        Some(path) => expand_arg(path).map(Some),
+        None => Ok(config::config_dir().map(|dir| dir.join("shellcaster").join("config.toml"))),
+    };
+}
 
-        // IMPORT SUBCOMMAND --------------------------------------------
-        Some(("import", sub_args)) => import(&db_path, config, sub_args),
 
-        // EXPORT SUBCOMMAND --------------------------------------------
-        Some(("export", sub_args)) => export(&db_path, sub_args),
+/// Gets the folder that holds the database: the one given with
+/// `--data-dir`, or else the OS data folder (e.g.,
+/// $XDG_DATA_HOME/shellcaster). A config file given with `--config`
+/// keeps its database next to it instead, so that separate configs have
+/// separate libraries. A database that older versions kept next to the
+/// default config file is moved to the data folder.
+fn get_data_dir(args: &clap::ArgMatches, config_path: &Path) -> Result<PathBuf> {
+    if let Some(dir) = args.value_of("data-dir") {
+        return expand_arg(dir);
+    }
+    let config_dir = match config_path.parent() {
+        Some(dir) => dir.to_path_buf(),
+        None => return Err(anyhow!("Could not correctly parse the config file location. Please specify a valid path to the config file.")),
+    };
+    if args.value_of("config").is_some() {
+        return Ok(config_dir);
+    }
+    let data_dir = match config::data_dir() {
+        Some(dir) => dir.join("shellcaster"),
+        None => return Ok(config_dir),
+    };
 
-        // MAIN COMMAND -------------------------------------------------
-        _ => {
-            let mut main_ctrl = MainController::new(config, &db_path)?;
+    return match move_database(&config_dir, &data_dir) {
+        Ok(()) => Ok(data_dir),
+        Err(err) => {
+            // e.g., the folders are on different file systems; the
+            // database is left where it is
+            log::warn!("Could not move the database to {}: {err}", data_dir.display());
+            Ok(config_dir)
+        }
+    };
+}
 
-            main_ctrl.loop_msgs(); // main loop
 
-            main_ctrl.tx_to_ui.send(MainMessage::UiTearDown).unwrap();
-            main_ctrl.ui_thread.join().unwrap(); // wait for UI thread to finish teardown
-            Ok(())
+/// Moves a database from `old_dir` to `new_dir`, unless there is
+/// already one there. Its journal goes with it, as SQLite needs it to
+/// undo a change that was cut short. If the database cannot be moved,
+/// the journal is put back.
+fn move_database(old_dir: &Path, new_dir: &Path) -> std::io::Result<()> {
+    let old_db = old_dir.join("data.db");
+    let new_db = new_dir.join("data.db");
+    if !old_db.exists() || new_db.exists() {
+        return Ok(());
+    }
+    std::fs::create_dir_all(new_dir)?;
+    let old_journal = old_dir.join("data.db-journal");
+    let new_journal = new_dir.join("data.db-journal");
+    let journal = old_journal.exists();
+    if journal {
+        std::fs::rename(&old_journal, &new_journal)?;
+    }
+    if let Err(err) = std::fs::rename(&old_db, &new_db) {
+        if journal {
+            let _ = std::fs::rename(&new_journal, &old_journal);
         }
+        return Err(err);
+    }
+    return Ok(());
+}
+
+
+/// Expands "~" and environment variables in a path given on the command
+/// line or in an environment variable.
+fn expand_arg(path: &str) -> Result<PathBuf> {
+    return match shellexpand::full(path) {
+        Ok(realpath) => Ok(PathBuf::from(realpath.as_ref())),
+        Err(err) => Err(anyhow!(
+            "Could not parse environment variable {} in {path}. Reason: {}",
+            err.var_name,
+            err.cause
+        )),
     };
 }
 
 
-/// Gets the path to the config file if one is specified in the command-
-/// line arguments, or else returns the default config path for the
-/// user's operating system.
-/// Returns None if default OS config directory cannot be determined.
-///
-/// Note: Right now we only have one possible command-line argument,
-/// specifying a config path. If the command-line API is
-/// extended in the future, this will have to be refactored.
-fn get_config_path(config: Option<&str>) -> Option<PathBuf> {
-    return match config {
-        Some(path) => Some(PathBuf::from(path)),
+/// Returns the podcasts named on the command line, matched by title
+/// regardless of case, or all podcasts that are not archived if none
+/// are named.
+fn chosen_podcasts(db_inst: &Database, args: &clap::ArgMatches) -> Result<Vec<Podcast>> {
+    let mut podcast_list = db_inst.get_podcasts()?;
+    let names: Vec<&str> = match args.values_of("podcast") {
+        Some(names) => names.collect(),
         None => {
-            let default_config = dirs::config_dir();
-            match default_config {
-                Some(mut path) => {
-                    path.push("shellcaster");
-                    path.push("config.toml");
-                    Some(path)
-                }
-                None => None,
-            }
+            // archived podcasts are left out
+            podcast_list.retain(|pod| !pod.archived);
+            return Ok(podcast_list);
         }
     };
+    let matches = |pod: &Podcast, name: &str| {
+        pod.display_title().to_lowercase() == name.to_lowercase()
+            || pod.title.to_lowercase() == name.to_lowercase()
+    };
+    for name in names.iter() {
+        if !podcast_list.iter().any(|pod| matches(pod, name)) {
+            return Err(anyhow!("No podcast is called \"{name}\"."));
+        }
+    }
+    podcast_list.retain(|pod| names.iter().any(|name| matches(pod, name)));
+    return Ok(podcast_list);
 }
 
 
-/// Synchronizes RSS feed data for all podcasts, without setting up a UI.
+/// Synchronizes RSS feed data for all podcasts, or the ones named on
+/// the command line, without setting up a UI. With `--json`, prints
+/// the podcasts that were synced, the new episodes and the feeds that
+/// failed once it is done.
 fn sync_podcasts(db_path: &Path, config: Config, args: &clap::ArgMatches) -> Result<()> {
+    let json = args.is_present("json");
+    let quiet = args.is_present("quiet") || json;
     let db_inst = Database::connect(db_path)?;
-    let podcast_list = db_inst.get_podcasts()?;
+    let mut podcast_list = chosen_podcasts(&db_inst, args)?;
+
+    // feeds that ask not to be checked yet are only left out when asked
+    // to, as a sync that is run by hand should check everything
+    let mut skipped = 0;
+    if args.is_present("scheduled") {
+        let now = chrono::Utc::now();
+        let before = podcast_list.len();
+        podcast_list.retain(|pod| pod.schedule.allows_check(pod.last_checked, now));
+        skipped = before - podcast_list.len();
+        if skipped > 0 && !quiet {
+            println!("Skipping {skipped} podcasts that ask not to be checked yet.");
+        }
+    }
 
     if podcast_list.is_empty() {
-        if !args.is_present("quiet") {
+        if json {
+            output::print(&serde_json::json!({
+                "synced": [],
+                "unchanged": [],
+                "skipped": skipped,
+                "new_episodes": [],
+                "failed": [],
+            }));
+        } else if !quiet {
             println!("No podcasts to sync.");
         }
         return Ok(());
     }
 
-    let threadpool = Threadpool::new(config.simultaneous_downloads);
+    let pool = TaskPool::new(config.simultaneous_syncs);
     let (tx_to_main, rx_to_main) = mpsc::channel();
 
     for pod in podcast_list.iter() {
-        let feed = PodcastFeed::new(Some(pod.id), pod.url.clone(), Some(pod.title.clone()));
-        feeds::check_feed(feed, config.max_retries, &threadpool, tx_to_main.clone());
+        let mut feed = PodcastFeed::new(Some(pod.id), pod.url.clone(), Some(pod.title.clone()));
+        feed.auth = pod.auth.clone();
+        feed.validators = pod.validators.clone();
+        feeds::check_feed(
+            feed,
+            config.max_retries,
+            config.feed_url_hook.clone(),
+            &pool,
+            CancelToken::new(),
+            tx_to_main.clone(),
+        );
     }
 
     let mut msg_counter: usize = 0;
     let mut failure = false;
+    let mut new_eps = Vec::new();
+    let mut failed = Vec::new();
+    // only filled in for --json
+    let mut synced = Vec::new();
+    let mut unchanged = Vec::new();
+    let mut all_new = Vec::new();
+    let mut errors = Vec::new();
     while let Some(message) = rx_to_main.iter().next() {
         match message {
             Message::Feed(FeedMsg::SyncData((pod_id, pod))) => {
                 let title = pod.title.clone();
-                let db_result = db_inst.update_podcast(pod_id, pod);
+                let db_result = db_inst.update_podcast(pod_id, *pod);
                 match db_result {
-                    Ok(_) => {
-                        if !args.is_present("quiet") {
+                    Ok(result) => {
+                        if !quiet {
                             println!("Synced {title}");
                         }
+                        if json {
+                            synced.push(serde_json::json!({ "id": pod_id, "podcast": title }));
+                            all_new.extend(result.added.iter().map(output::new_episode));
+                        }
+                        // podcasts with notifications turned off are
+                        // left out of the webhooks
+                        if podcast_list.iter().any(|pod| pod.id == pod_id && pod.notify) {
+                            new_eps.extend(result.added);
+                        }
                     }
                     Err(_err) => {
                         failure = true;
+                        log::error!("Could not save synced data for {title}");
                         eprintln!("Error synchronizing {title}");
+                        let error = "could not be saved";
+                        errors.push(output::podcast_error(Some(pod_id), &title, error));
+                        failed.push(title);
                     }
                 }
             }
 
             Message::Feed(FeedMsg::Error(feed)) => {
                 failure = true;
-                match feed.title {
+                let _ = feed.id.map(|id| db_inst.record_sync_failure(id));
+                match &feed.title {
                     Some(t) => eprintln!("Error retrieving RSS feed for {}.", t),
                     None => eprintln!("Error retrieving RSS feed."),
                 }
+                let title = feed.title.unwrap_or(feed.url);
+                log::error!("Error retrieving RSS feed for {title}.");
+                errors.push(output::podcast_error(feed.id, &title, "could not be fetched"));
+                failed.push(title);
+            }
+
+            Message::Feed(FeedMsg::NotModified(feed)) => {
+                let _ = feed.id.map(|id| db_inst.set_last_checked(id));
+                let title = feed.title.unwrap_or(feed.url);
+                if !quiet {
+                    println!("{title} is unchanged");
+                }
+                unchanged.push(serde_json::json!({ "id": feed.id, "podcast": title }));
+            }
+
+            Message::Feed(FeedMsg::AuthRequired(feed)) => {
+                failure = true;
+                let title = feed.title.unwrap_or(feed.url);
+                eprintln!("{title} needs a login; set one in the podcast settings.");
+                log::error!("{title} needs a login");
+                errors.push(output::podcast_error(feed.id, &title, "needs a login"));
             }
             _ => (),
         }
@@ -226,20 +649,323 @@ fn sync_podcasts(db_path: &Path, config: Config, args: &clap::ArgMatches) -> Res
             break;
         }
     }
+    log::info!("Sync complete: {} of {} podcasts failed", failed.len(), podcast_list.len());
+
+    // webhooks are sent before exiting, so that they are not cut off
+    let notices = [Notice::new_episodes(&new_eps), Notice::sync_failed(&failed)];
+    for notice in notices.iter().flatten() {
+        for webhook in config.webhooks.iter() {
+            if webhook.events.contains(&notice.event) {
+                if let Err(err) = webhooks::send(webhook, notice) {
+                    log::error!("{err}");
+                    eprintln!("{err}");
+                }
+            }
+        }
+    }
 
+    if json {
+        output::print(&serde_json::json!({
+            "synced": synced,
+            "unchanged": unchanged,
+            "skipped": skipped,
+            "new_episodes": all_new,
+            "failed": errors,
+        }));
+    }
     if failure {
         return Err(anyhow!("Process finished with errors."));
-    } else if !args.is_present("quiet") {
+    } else if !quiet {
         println!("Sync successful.");
     }
     return Ok(());
 }
 
 
-/// Imports a list of podcasts from OPML format, either reading from a
-/// file or from stdin. If the `replace` flag is set, this replaces all
-/// existing data in the database.
+/// Fetches the feeds of all podcasts that are not archived (or the ones
+/// named on the command line), and prints what syncing them would
+/// change, without writing to the database. With `--json`, prints a
+/// list with the changes to each podcast instead.
+fn check_podcasts(db_path: &Path, config: Config, args: &clap::ArgMatches) -> Result<()> {
+    let json = args.is_present("json");
+    let db_inst = Database::connect(db_path)?;
+    let podcast_list = chosen_podcasts(&db_inst, args)?;
+    if podcast_list.is_empty() {
+        if json {
+            output::print(&serde_json::json!([]));
+        } else {
+            println!("No podcasts to check.");
+        }
+        return Ok(());
+    }
+
+    let pool = TaskPool::new(config.simultaneous_syncs);
+    let (tx_to_main, rx_to_main) = mpsc::channel();
+    for pod in podcast_list.iter() {
+        let mut feed = PodcastFeed::new(Some(pod.id), pod.url.clone(), Some(pod.title.clone()));
+        feed.auth = pod.auth.clone();
+        feed.validators = pod.validators.clone();
+        feed.check_only = true;
+        feeds::check_feed(
+            feed,
+            config.max_retries,
+            config.feed_url_hook.clone(),
+            &pool,
+            CancelToken::new(),
+            tx_to_main.clone(),
+        );
+    }
+
+    // the report is printed in the same order as the podcasts, once
+    // all of them have been checked
+    let mut reports = HashMap::new();
+    let mut values = HashMap::new();
+    while reports.len() + values.len() < podcast_list.len() {
+        match rx_to_main.recv() {
+            Ok(Message::Feed(FeedMsg::Checked(feed, found))) if json => {
+                let value = output::check(&db_inst, &feed, &found);
+                values.insert(feed.id.unwrap_or_default(), value);
+            }
+            Ok(Message::Feed(FeedMsg::Checked(feed, found))) => {
+                let lines = feeds::check_report(&db_inst, &feed, &found);
+                reports.insert(feed.id.unwrap_or_default(), lines);
+            }
+            Ok(_) => (),
+            Err(_) => break,
+        }
+    }
+    if json {
+        let values: Vec<serde_json::Value> =
+            podcast_list.iter().filter_map(|pod| values.remove(&pod.id)).collect();
+        output::print(&serde_json::Value::Array(values));
+        return Ok(());
+    }
+    for pod in podcast_list.iter() {
+        for line in reports.remove(&pod.id).unwrap_or_default() {
+            println!("{line}");
+        }
+    }
+    return Ok(());
+}
+
+
+/// Downloads every unplayed episode that matches the filters last used
+/// in the app, across all podcasts that are not archived. The number of
+/// episodes and an estimate of their size are shown first, and the user
+/// is asked to go ahead, unless the `yes` flag is set. With `--json`,
+/// prints the episodes that were downloaded and the ones that failed
+/// once all downloads are done.
+fn download_library(db_path: &Path, config: Config, args: &clap::ArgMatches) -> Result<()> {
+    let db_inst = Database::connect(db_path)?;
+    let podcasts = db_inst.get_podcasts()?;
+    for podcast in podcasts.iter() {
+        config.title_rules.clean_podcast(podcast);
+    }
+    let filters = db_inst
+        .filters()
+        .map(|filters| Filters::restore(&filters, &config.smart_filters))
+        .unwrap_or_default();
+    let smart_filter = filters
+        .smart
+        .and_then(|idx| config.smart_filters.get(idx))
+        .map(|filter| &filter.expr);
+
+    let json = args.is_present("json");
+    let quiet = args.is_present("quiet") || json;
+    let episodes = downloads::library_episodes(podcasts.iter(), &filters, smart_filter);
+    if episodes.is_empty() {
+        if json {
+            output::print(&serde_json::json!({ "downloaded": [], "failed": [] }));
+        } else if !quiet {
+            println!("No episodes to download.");
+        }
+        return Ok(());
+    }
+    let description = downloads::describe_batch(&episodes);
+    if !args.is_present("yes") {
+        print!("Download {description}? (y/n) ");
+        std::io::stdout().flush()?;
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer)?;
+        if !answer.trim().eq_ignore_ascii_case("y") {
+            return Ok(());
+        }
+    } else if !quiet {
+        println!("Downloading {description}.");
+    }
+
+    let storage = storage::from_config(
+        &config.download_path,
+        config.webdav.as_ref(),
+        config.trash_days,
+    );
+    let pool = TaskPool::new(config.simultaneous_downloads);
+    let (tx_to_main, rx_to_main) = mpsc::channel();
+    let mut failure = false;
+    let mut started = 0;
+    // only filled in for --json
+    let mut downloaded = Vec::new();
+    let mut errors = Vec::new();
+    for pod in podcasts.iter() {
+        let preference = pod
+            .overrides
+            .preferred_enclosure
+            .as_deref()
+            .or(config.preferred_enclosure.as_deref());
+        let ep_data: Vec<EpData> = episodes
+            .iter()
+            .filter(|ep| ep.pod_id == pod.id)
+            .map(|ep| EpData {
+                id: ep.id,
+                pod_id: ep.pod_id,
+                title: ep.display_title().to_string(),
+                url: ep.download_url(preference),
+                pubdate: ep.pubdate,
+                file_path: None,
+                auth: pod.auth.clone(),
+            })
+            .collect();
+        if ep_data.is_empty() {
+            continue;
+        }
+        let dir_name = config.sanitizer.sanitize(&pod.title);
+        match storage.create_dir(&dir_name, pod.overrides.download_dir.as_deref()) {
+            Ok(path) => {
+                started += ep_data.len();
+                downloads::download_list(
+                    ep_data,
+                    &path,
+                    config.max_retries,
+                    config.sanitizer,
+                    storage.clone(),
+                    &pool,
+                    CancelToken::new(),
+                    tx_to_main.clone(),
+                );
+            }
+            Err(_) => {
+                failure = true;
+                eprintln!("Could not create dir: {}", pod.title);
+                let error = "could not create folder";
+                for ep in episodes.iter().filter(|ep| ep.pod_id == pod.id) {
+                    errors.push(output::download_error(ep.id, ep.display_title(), error));
+                }
+            }
+        }
+    }
+    drop(tx_to_main);
+
+    for message in rx_to_main.iter().take(started) {
+        match message {
+            Message::Dl(DownloadMsg::Complete(ep_data)) => {
+                let file_path = ep_data.file_path.clone().unwrap_or_default();
+                let podcast = match podcasts.iter().find(|pod| pod.id == ep_data.pod_id) {
+                    Some(podcast) => podcast,
+                    None => continue,
+                };
+                let res = downloads::finish(&db_inst, &*storage, podcast, ep_data.id, &file_path);
+                if res.is_err() {
+                    failure = true;
+                    eprintln!(
+                        "Could not add episode file to database: {}",
+                        file_path.to_string_lossy()
+                    );
+                    let error = "could not be saved";
+                    errors.push(output::download_error(ep_data.id, &ep_data.title, error));
+                    continue;
+                }
+                if !quiet {
+                    println!("Downloaded {}", ep_data.title);
+                }
+                if json {
+                    downloaded.push(serde_json::json!({
+                        "id": ep_data.id,
+                        "podcast_id": ep_data.pod_id,
+                        "title": ep_data.title,
+                        "file": file_path.to_string_lossy(),
+                    }));
+                }
+
+                // hooks and webhooks are run before moving on, so that
+                // they are not cut off when this exits
+                let pod_title = podcast.title.clone();
+                if let Some(command) = &config.post_download_command {
+                    let data = HookData {
+                        ep_title: ep_data.title.clone(),
+                        pod_title: pod_title.clone(),
+                        url: ep_data.url.clone(),
+                        file_path: Some(file_path),
+                    };
+                    if let Err(err) = hooks::execute(HookEvent::Download, command, &data) {
+                        eprintln!("Post-download command failed: {err}");
+                    }
+                }
+                let notice = Notice::download(&pod_title, &ep_data.title);
+                for webhook in config.webhooks.iter() {
+                    if webhook.events.contains(&notice.event) {
+                        if let Err(err) = webhooks::send(webhook, &notice) {
+                            eprintln!("{err}");
+                        }
+                    }
+                }
+
+                let removed = downloads::prune(&db_inst, &*storage, podcast, None);
+                if removed > 0 && !quiet {
+                    println!("Deleted {removed} old downloads of {}", podcast.display_title());
+                }
+            }
+            Message::Dl(DownloadMsg::ResponseError(ep_data)) => {
+                failure = true;
+                eprintln!("Error sending download request: {}", ep_data.url);
+                let error = "could not be requested";
+                errors.push(output::download_error(ep_data.id, &ep_data.title, error));
+            }
+            Message::Dl(DownloadMsg::FileCreateError(ep_data)) => {
+                failure = true;
+                eprintln!(
+                    "Error creating file: {}",
+                    ep_data.file_path.clone().unwrap_or_default().to_string_lossy()
+                );
+                let error = "could not create file";
+                errors.push(output::download_error(ep_data.id, &ep_data.title, error));
+            }
+            Message::Dl(DownloadMsg::FileWriteError(ep_data)) => {
+                failure = true;
+                eprintln!("Error downloading episode: {}", ep_data.url);
+                let error = "could not be downloaded";
+                errors.push(output::download_error(ep_data.id, &ep_data.title, error));
+            }
+            _ => (),
+        }
+    }
+
+    if json {
+        output::print(&serde_json::json!({ "downloaded": downloaded, "failed": errors }));
+    }
+    if failure {
+        return Err(anyhow!("Process finished with errors."));
+    } else if !quiet {
+        println!("Downloads complete.");
+    }
+    return Ok(());
+}
+
+
+/// Imports a list of podcasts from OPML format or a library file, either
+/// reading from a file or from stdin. If the `replace` flag is set, this replaces all
+/// existing data in the database. With `--json`, prints the podcasts
+/// that were added and the ones that failed once it is done.
 fn import(db_path: &Path, config: Config, args: &clap::ArgMatches) -> Result<()> {
+    let json = args.is_present("json");
+    let quiet = args.is_present("quiet") || json;
+    let nothing = || {
+        if json {
+            output::print(&serde_json::json!({ "added": [], "failed": [] }));
+        } else if !quiet {
+            println!("No podcasts to import.");
+        }
+    };
+
     // read from file or from stdin
     let xml = match args.value_of("file") {
         Some(filepath) => {
@@ -259,14 +985,12 @@ fn import(db_path: &Path, config: Config, args: &clap::ArgMatches) -> Result<()>
         }
     };
 
-    let mut podcast_list = opml::import(xml).with_context(|| {
-        "Could not properly parse OPML file -- file may be formatted improperly or corrupted."
+    let mut podcast_list = library::import_any(xml).with_context(|| {
+        "Could not properly parse import file -- file may be formatted improperly or corrupted."
     })?;
 
     if podcast_list.is_empty() {
-        if !args.is_present("quiet") {
-            println!("No podcasts to import.");
-        }
+        nothing();
         return Ok(());
     }
 
@@ -297,53 +1021,79 @@ fn import(db_path: &Path, config: Config, args: &clap::ArgMatches) -> Result<()>
     // check again, now that we may have removed feeds after looking at
     // the database
     if podcast_list.is_empty() {
-        if !args.is_present("quiet") {
-            println!("No podcasts to import.");
-        }
+        nothing();
         return Ok(());
     }
 
-    println!("Importing {} podcasts...", podcast_list.len());
+    if !json {
+        println!("Importing {} podcasts...", podcast_list.len());
+    }
 
-    let threadpool = Threadpool::new(config.simultaneous_downloads);
+    let pool = TaskPool::new(config.simultaneous_syncs);
     let (tx_to_main, rx_to_main) = mpsc::channel();
 
+    let settings: HashMap<String, PodcastSettings> = podcast_list
+        .iter()
+        .filter_map(|pod| Some((pod.url.clone(), *pod.settings.clone()?)))
+        .collect();
+
     for pod in podcast_list.iter() {
         feeds::check_feed(
             pod.clone(),
             config.max_retries,
-            &threadpool,
+            config.feed_url_hook.clone(),
+            &pool,
+            CancelToken::new(),
             tx_to_main.clone(),
         );
     }
 
     let mut msg_counter: usize = 0;
     let mut failure = false;
+    // only filled in for --json
+    let mut added = Vec::new();
+    let mut errors = Vec::new();
     while let Some(message) = rx_to_main.iter().next() {
         match message {
             Message::Feed(FeedMsg::NewData(pod)) => {
                 let title = pod.title.clone();
-                let db_result = db_inst.insert_podcast(pod);
+                let pod_settings = settings.get(&pod.url);
+                let url = pod.url.clone();
+                let db_result = db_inst.insert_podcast(*pod).and_then(|_| match pod_settings {
+                    Some(pod_settings) => db_inst.set_settings_by_url(&url, pod_settings),
+                    None => Ok(()),
+                });
                 match db_result {
                     Ok(_) => {
-                        if !args.is_present("quiet") {
+                        if !quiet {
                             println!("Added {title}");
                         }
+                        added.push(serde_json::json!({ "podcast": title, "url": url }));
                     }
                     Err(_err) => {
                         failure = true;
                         eprintln!("Error adding {title}");
+                        errors.push(output::podcast_error(None, &title, "could not be saved"));
                     }
                 }
             }
 
             Message::Feed(FeedMsg::Error(feed)) => {
                 failure = true;
-                if let Some(t) = feed.title {
+                if let Some(t) = &feed.title {
                     eprintln!("Error retrieving RSS feed: {t}");
                 } else {
                     eprintln!("Error retrieving RSS feed");
                 }
+                let title = feed.title.unwrap_or(feed.url);
+                errors.push(output::podcast_error(None, &title, "could not be fetched"));
+            }
+
+            Message::Feed(FeedMsg::AuthRequired(feed)) => {
+                failure = true;
+                let title = feed.title.unwrap_or(feed.url);
+                eprintln!("Error retrieving RSS feed: {title} needs a login");
+                errors.push(output::podcast_error(None, &title, "needs a login"));
             }
             _ => (),
         }
@@ -354,26 +1104,32 @@ fn import(db_path: &Path, config: Config, args: &clap::ArgMatches) -> Result<()>
         }
     }
 
+    if json {
+        output::print(&serde_json::json!({ "added": added, "failed": errors }));
+    }
     if failure {
         return Err(anyhow!("Process finished with errors."));
-    } else if !args.is_present("quiet") {
+    } else if !quiet {
         println!("Import successful.");
     }
     return Ok(());
 }
 
 
-/// Exports all podcasts to OPML format, either printing to stdout or
-/// exporting to a file.
+/// Exports all podcasts to OPML format (or a library file, if the
+/// `library` flag is set), either printing to stdout or exporting to a
+/// file.
 fn export(db_path: &Path, args: &clap::ArgMatches) -> Result<()> {
     let db_inst = Database::connect(db_path)?;
     let podcast_list = db_inst.get_podcasts()?;
-    let opml = opml::export(podcast_list);
-
-    let xml = opml
-        .to_string()
-        .map_err(|err| anyhow!(err))
-        .with_context(|| "Could not create OPML format")?;
+    let xml = if args.is_present("library") {
+        library::export(&podcast_list)?
+    } else {
+        opml::export(podcast_list)
+            .to_string()
+            .map_err(|err| anyhow!(err))
+            .with_context(|| "Could not create OPML format")?
+    };
 
     match args.value_of("file") {
         // export to file
@@ -381,10 +1137,358 @@ fn export(db_path: &Path, args: &clap::ArgMatches) -> Result<()> {
             let mut dst = File::create(file)
                 .with_context(|| format!("Could not create output file: {file}"))?;
             dst.write_all(xml.as_bytes())
-                .with_context(|| format!("Could not copy OPML data to output file: {file}"))?;
+                .with_context(|| format!("Could not copy data to output file: {file}"))?;
         }
         // print to stdout
         None => println!("{xml}"),
     }
     return Ok(());
 }
+
+
+/// Writes the keybindings currently in effect, either printing to
+/// stdout or writing to a file.
+fn keymap(config: &Config, args: &clap::ArgMatches) -> Result<()> {
+    let toml = config.keybindings.to_toml();
+    match args.value_of("file") {
+        Some(file) => {
+            let mut dst = File::create(file)
+                .with_context(|| format!("Could not create output file: {file}"))?;
+            dst.write_all(toml.as_bytes())
+                .with_context(|| format!("Could not write keybindings to output file: {file}"))?;
+        }
+        None => print!("{toml}"),
+    }
+    return Ok(());
+}
+
+
+/// Runs database maintenance, reporting on each step as it goes. With
+/// `--json`, prints the problems found and the size of the database
+/// once it is done.
+fn maintain(db_path: &Path, args: &clap::ArgMatches) -> Result<()> {
+    let json = args.is_present("json");
+    let quiet = args.is_present("quiet") || json;
+    let db_inst = Database::connect(db_path)?;
+    let report = db_inst.maintain(|step| {
+        if !quiet {
+            println!("{step}...");
+        }
+    })?;
+    if json {
+        output::print(&serde_json::json!({
+            "problems": report.problems,
+            "size_before": report.size_before,
+            "size_after": report.size_after,
+        }));
+    }
+
+    if !report.problems.is_empty() {
+        for problem in report.problems.iter() {
+            eprintln!("{problem}");
+        }
+        return Err(anyhow!(
+            "Database integrity check failed. Consider restoring a backup, or exporting your podcasts and importing them into a new database."
+        ));
+    }
+    if !quiet {
+        println!(
+            "Maintenance complete. Database size: {} KB (was {} KB).",
+            report.size_after / 1024,
+            report.size_before / 1024
+        );
+    }
+    return Ok(());
+}
+
+
+/// Times the steps of starting up with the current library -- connecting
+/// to the database, loading podcasts and episodes, building the menus,
+/// and drawing the first screen -- and prints a report. This is hidden
+/// from the help text; it is meant for users with very large libraries
+/// to attach numbers to reports of slow startups.
+fn bench_startup(db_path: &Path, config: Config) -> Result<()> {
+    let start = Instant::now();
+    let db_inst = Database::connect(db_path)?;
+    let connected = start.elapsed();
+    let podcasts = db_inst.get_podcasts()?;
+    let loaded = start.elapsed() - connected;
+
+    let num_podcasts = podcasts.len();
+    let num_episodes: usize = podcasts.iter().map(|pod| pod.episodes.len(false)).sum();
+    let db_size = std::fs::metadata(db_path.join("data.db"))
+        .map(|meta| meta.len())
+        .unwrap_or(0);
+    let (built, drawn) = ui::Ui::bench_startup(config, LockVec::new(podcasts))?;
+
+    let ms = |dur: Duration| dur.as_secs_f64() * 1000.0;
+    println!("shellcaster {VERSION} startup benchmark");
+    println!(
+        "Library: {num_podcasts} podcasts, {num_episodes} episodes, database {} KB",
+        db_size / 1024
+    );
+    println!();
+    for (step, dur) in [
+        ("Connect to database", connected),
+        ("Load library", loaded),
+        ("Build menus", built),
+        ("First draw", drawn),
+        ("Total", connected + loaded + built + drawn),
+    ] {
+        println!("{step:<20} {:>10.1} ms", ms(dur));
+    }
+    return Ok(());
+}
+
+
+/// Prints the ID and title of each podcast in the database, or of each
+/// episode of the podcasts named on the command line (with whether it
+/// has been played), separated by tabs. With `--json`, prints a list of
+/// them with more details instead.
+fn list(db_path: &Path, config: &Config, args: &clap::ArgMatches) -> Result<()> {
+    let json = args.is_present("json");
+    let db_inst = Database::connect(db_path)?;
+    let listing_episodes = args.is_present("podcast");
+    let podcasts = if listing_episodes {
+        chosen_podcasts(&db_inst, args)?
+    } else {
+        db_inst.get_podcasts()?
+    };
+    for podcast in podcasts.iter() {
+        config.title_rules.clean_podcast(podcast);
+    }
+
+    if !listing_episodes {
+        if json {
+            output::print(&podcasts.iter().map(output::podcast).collect());
+        } else {
+            for pod in podcasts.iter() {
+                println!("{}\t{}", pod.id, pod.display_title());
+            }
+        }
+        return Ok(());
+    }
+
+    let mut values = Vec::new();
+    for pod in podcasts.iter() {
+        if json {
+            values.extend(pod.episodes.map(output::episode, false));
+        } else {
+            let lines = pod.episodes.map(
+                |ep| {
+                    let played = if ep.played { "played" } else { "unplayed" };
+                    format!("{}\t{played}\t{}", ep.id, ep.display_title())
+                },
+                false,
+            );
+            for line in lines.iter() {
+                println!("{line}");
+            }
+        }
+    }
+    if json {
+        output::print(&serde_json::Value::Array(values));
+    }
+    return Ok(());
+}
+
+
+/// Sends a command to a running instance of shellcaster and prints its
+/// reply.
+fn remote(config: &Config, args: &clap::ArgMatches) -> Result<()> {
+    let command: Vec<&str> = args
+        .values_of("command")
+        .map(|values| values.collect())
+        .unwrap_or_default();
+    let host = args.value_of("host").or(config.remote_host.as_deref());
+    let reply = match host {
+        Some(host) => remote::send_ssh(host, &command.join(" "))?,
+        None => {
+            let socket = config
+                .remote_socket
+                .as_ref()
+                .ok_or_else(|| anyhow!("Remote control is turned off in config.toml."))?;
+            remote::send(socket, &command.join(" "))?
+        }
+    };
+    if !reply.is_empty() {
+        println!("{reply}");
+    }
+    return Ok(());
+}
+
+
+/// Checks the config file, printing each problem found with the line it
+/// is on. Returns an error if there are problems that stop the config
+/// from loading.
+fn check_config(config_path: &Path) -> Result<()> {
+    println!("Checking {}", config_path.display());
+    if !config_path.exists() {
+        println!("There is no config file there, so the default settings are used.");
+    }
+    let problems = config_check::check(config_path);
+    for problem in problems.iter() {
+        for line in problem.describe() {
+            println!("{line}");
+        }
+    }
+    let errors = problems.iter().filter(|problem| problem.fatal).count();
+    let warnings = problems.len() - errors;
+    let count = |n: usize, what: &str| {
+        let plural = if n == 1 { "" } else { "s" };
+        return format!("{n} {what}{plural}");
+    };
+    if errors > 0 {
+        return Err(anyhow!(
+            "Found {} and {}.",
+            count(errors, "error"),
+            count(warnings, "warning")
+        ));
+    }
+    if warnings > 0 {
+        println!("The config file can be loaded, with {}.", count(warnings, "warning"));
+    } else {
+        println!("No problems found.");
+    }
+    return Ok(());
+}
+
+
+/// Writes out the default config file. To keep an existing config from
+/// being lost, this refuses to write over a file that is already there.
+fn default_config(args: &clap::ArgMatches) -> Result<()> {
+    return match args.value_of("file") {
+        Some(file) if Path::new(file).exists() => Err(anyhow!(
+            "{file} already exists. To compare it with the defaults, run `shellcaster default-config` without --file and diff the output."
+        )),
+        Some(file) => config_writer::write_default_config(Path::new(file), &[]),
+        None => {
+            print!("{}", config_writer::default_config());
+            Ok(())
+        }
+    };
+}
+
+
+/// Prints the completion script for a shell. With `--podcasts`, lists
+/// the titles of all podcasts instead, which the completion scripts
+/// call to complete podcast names.
+fn print_completions(db_path: &Path, args: &clap::ArgMatches) -> Result<()> {
+    if args.is_present("podcasts") {
+        let db_inst = Database::connect(db_path)?;
+        for pod in db_inst.get_podcasts()?.iter() {
+            println!("{}", pod.display_title());
+        }
+        return Ok(());
+    }
+    let shell = args.value_of("shell").unwrap_or_default();
+    return completions::generate(&mut command_line(), shell, &mut std::io::stdout());
+}
+
+
+/// Stores a setting from config.toml in the system keyring, reading it
+/// from stdin, or removes it from the keyring. With `--feeds`, moves the
+/// passwords of private feeds from the database into the keyring
+/// instead.
+fn secret(db_path: &Path, args: &clap::ArgMatches) -> Result<()> {
+    if args.is_present("feeds") {
+        let db_inst = Database::connect(db_path)?;
+        let mut moved = 0;
+        for pod in db_inst.get_podcasts()?.iter() {
+            if let Some(password) = &pod.auth.password {
+                // setting it here first means a keyring that cannot be
+                // reached is an error, rather than leaving it as it was
+                secrets::set(&db_inst.feed_password_name(pod.id), password)?;
+                db_inst.set_auth(pod.id, &pod.auth)?;
+                moved += 1;
+            }
+        }
+        println!("Stored the passwords of {moved} private feeds in the system keyring.");
+        return Ok(());
+    }
+
+    let name = args.value_of("name").unwrap_or_default();
+    if args.is_present("delete") {
+        secrets::delete(name)?;
+        println!("Removed {name} from the system keyring.");
+        return Ok(());
+    }
+
+    eprint!("{name}: ");
+    let mut secret = String::new();
+    std::io::stdin()
+        .read_line(&mut secret)
+        .with_context(|| format!("Could not read {name}"))?;
+    let secret = secret.trim();
+    if secret.is_empty() {
+        return Err(anyhow!("No {name} given."));
+    }
+    secrets::set(name, secret)?;
+    println!(
+        "Stored {name} in the system keyring. It is used in place of {name} in config.toml, which can be removed."
+    );
+    return Ok(());
+}
+
+
+// TESTS -----------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("shellcaster-{name}-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        return dir;
+    }
+
+    #[test]
+    fn data_dir_from_args() {
+        let config = Path::new("/home/me/podcasts/config.toml");
+        let args = command_line().get_matches_from(["shellcaster", "--data-dir", "/data/pods"]);
+        assert_eq!(get_data_dir(&args, config).unwrap(), PathBuf::from("/data/pods"));
+
+        // a config file of its own keeps its database next to it
+        let args = command_line().get_matches_from(["shellcaster", "-c", config.to_str().unwrap()]);
+        assert_eq!(get_data_dir(&args, config).unwrap(), PathBuf::from("/home/me/podcasts"));
+    }
+
+    #[test]
+    fn database_moved_with_journal() {
+        let dir = temp_dir("move-db");
+        let (old_dir, new_dir) = (dir.join("config"), dir.join("data"));
+        fs::create_dir_all(&old_dir).unwrap();
+        fs::write(old_dir.join("data.db"), "db").unwrap();
+        fs::write(old_dir.join("data.db-journal"), "journal").unwrap();
+        fs::write(old_dir.join("config.toml"), "").unwrap();
+
+        move_database(&old_dir, &new_dir).unwrap();
+        assert_eq!(fs::read_to_string(new_dir.join("data.db")).unwrap(), "db");
+        assert_eq!(fs::read_to_string(new_dir.join("data.db-journal")).unwrap(), "journal");
+        assert!(!old_dir.join("data.db").exists());
+        assert!(!old_dir.join("data.db-journal").exists());
+        assert!(old_dir.join("config.toml").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn database_not_replaced() {
+        let dir = temp_dir("keep-db");
+        let (old_dir, new_dir) = (dir.join("config"), dir.join("data"));
+        fs::create_dir_all(&old_dir).unwrap();
+        fs::create_dir_all(&new_dir).unwrap();
+        fs::write(old_dir.join("data.db"), "old").unwrap();
+        fs::write(new_dir.join("data.db"), "new").unwrap();
+
+        move_database(&old_dir, &new_dir).unwrap();
+        assert_eq!(fs::read_to_string(new_dir.join("data.db")).unwrap(), "new");
+        assert_eq!(fs::read_to_string(old_dir.join("data.db")).unwrap(), "old");
+
+        // nothing to move is not an error
+        move_database(&new_dir.join("none"), &dir.join("other")).unwrap();
+        assert!(!dir.join("other").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,19 +1,43 @@
-use anyhow::Result;
-use std::collections::HashSet;
+use ahash::AHashMap;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use log::LevelFilter;
+use semver::Version;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::mpsc;
-
-use sanitize_filename::{sanitize_with_options, Options};
-
-use crate::config::{Config, DownloadNewEpisodes};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::changelog;
+use crate::chapters::{self, ChapterMsg};
+use crate::config::{Config, DownloadNewEpisodes, NewPodcastEpisodes};
+use crate::config_check;
+use crate::config_writer;
+use crate::cover_art::{self, ImageMsg};
 use crate::db::{Database, SyncResult};
+use crate::download_rules::DownloadRules;
 use crate::downloads::{self, DownloadMsg, EpData};
-use crate::feeds::{self, FeedMsg, PodcastFeed};
-use crate::play_file;
-use crate::threadpool::Threadpool;
+use crate::feeds::{self, FeedCheck, FeedMsg, PodcastFeed};
+use crate::hooks::{self, HookData, HookEvent, HookMsg};
+use crate::library;
+use crate::logging;
+#[cfg(feature = "mpris")]
+use crate::mpris::{Mpris, TrackInfo};
+use crate::play_file::{self, PlayerMsg};
+use crate::queue;
+use crate::remote::{self, RemoteCmd, RemoteMsg};
+use crate::scrobble::{self, ScrobbleMsg};
+use crate::status::{self, NowPlaying, NowPlayingWriter, Status};
+use crate::storage::{self, Storage};
+use crate::threadpool::{CancelToken, TaskPool, Threadpool};
+use crate::transcript::{self, TranscriptLine, TranscriptMsg};
+use crate::updates::{self, UpdateMsg};
 use crate::types::*;
 use crate::ui::{Ui, UiMsg};
+use crate::watch::{self, WatchMsg};
+use crate::webhooks::{self, Notice, WebhookMsg};
 
 /// Enum used for communicating with other threads.
 #[allow(clippy::enum_variant_names)]
@@ -24,6 +48,25 @@ pub enum MainMessage {
     UiSpawnPersistentNotif(String, bool),
     UiClearPersistentNotif,
     UiSpawnDownloadPopup(Vec<NewEpisode>, bool),
+    UiSpawnDigestPopup(Vec<NewEpisode>, Vec<(i64, String)>),
+    UiSpawnTranscript(String, Vec<TranscriptLine>),
+    UiSpawnWhatsNew(String, Vec<String>),
+    UiSpawnLog(String, Vec<String>),
+    UiSpawnConfigProblems(Vec<String>),
+    UiSpawnSetup(Vec<String>),
+    UiSpawnCheckReport(Vec<String>),
+    UiAskNewPodcast(i64),
+    UiAskFeedAuth(Box<PodcastFeed>),
+    UiAskDeadFeed(Box<PodcastFeed>, i64),
+    UiAskDownloadLibrary(Vec<(i64, i64)>, String),
+    UiSpawnStats(LibraryStats),
+    UiSpawnQueue(Vec<ListedEpisode>),
+    UiUpdateQueue(Vec<i64>),
+    UiReloadConfig(Box<Config>),
+    UiUpdateDetails,
+    UiUpdateArt(PathBuf, u16, u16, Option<String>),
+    UiUpdateFilters(Filters),
+    UiHandOverTerminal(mpsc::Sender<()>, mpsc::Receiver<Option<String>>),
     UiTearDown,
 }
 
@@ -31,14 +74,42 @@ pub enum MainMessage {
 /// state and mechanisms for communicatingg with the rest of the app.
 pub struct MainController {
     config: Config,
+    config_path: PathBuf,
     db: Database,
     threadpool: Threadpool,
+    feed_pool: TaskPool,
+    download_pool: TaskPool,
     podcasts: LockVec<Podcast>,
     filters: Filters,
+    sorts: Sorts,
+    storage: Arc<dyn Storage>,
     sync_counter: usize,
     sync_tracker: Vec<SyncResult>,
+    sync_all: bool,
+    sync_failed: Vec<(i64, String)>,
+    sync_cancel: CancelToken,
+    check_counter: usize,
+    check_report: Vec<(String, Vec<String>)>,
     download_tracker: HashSet<i64>,
-    pub ui_thread: std::thread::JoinHandle<()>,
+    download_cancel: CancelToken,
+    image_tracker: HashSet<String>,
+    chapter_tracker: HashSet<i64>,
+    playing_ep: Option<i64>,
+    terminal_busy: Arc<AtomicBool>,
+    waiting_play: Option<(i64, i64)>,
+    queue: Vec<(i64, i64)>,
+    scrobbling: bool,
+    now_playing: Option<NowPlaying>,
+    now_playing_writer: Option<NowPlayingWriter>,
+    now_playing_text: Option<String>,
+    last_status: Option<Status>,
+    #[cfg(feature = "mpris")]
+    mpris: Option<Mpris>,
+    remote_socket: Option<PathBuf>,
+    adding_feeds: HashSet<String>,
+    changing_urls: HashSet<i64>,
+    import_settings: HashMap<String, PodcastSettings>,
+    pub ui_thread: std::thread::JoinHandle<Result<()>>,
     pub tx_to_ui: mpsc::Sender<MainMessage>,
     pub tx_to_main: mpsc::Sender<Message>,
     pub rx_to_main: mpsc::Receiver<Message>,
@@ -48,7 +119,7 @@ impl MainController {
     /// Instantiates the main controller (used during app startup), which
     /// sets up the connection to the database, download manager, and UI
     /// thread, and reads the list of podcasts from the database.
-    pub fn new(config: Config, db_path: &Path) -> Result<MainController> {
+    pub fn new(config: Config, config_path: PathBuf, db_path: &Path) -> Result<MainController> {
         // create transmitters and receivers for passing messages between threads
         let (tx_to_ui, rx_from_main) = mpsc::channel();
         let (tx_to_main, rx_to_main) = mpsc::channel();
@@ -56,15 +127,41 @@ impl MainController {
         // get connection to the database
         let db_inst = Database::connect(db_path)?;
 
-        // set up threadpool
+        // keep long-lived databases healthy, if asked to; this happens
+        // before anything else uses the database, as compacting it can't
+        // run while other connections are busy
+        let maintenance = match config.db_maintenance_interval {
+            Some(days) => {
+                if maintenance_due(db_inst.last_maintenance(), Utc::now(), days) {
+                    Some(db_inst.maintain(|_| ()))
+                } else {
+                    None
+                }
+            }
+            None => None,
+        };
+
+        // set up threadpool for smaller jobs (cover art, chapters, and
+        // transcripts)
         let threadpool = Threadpool::new(config.simultaneous_downloads);
+        // feeds and downloads are fetched on a shared async runtime,
+        // each with their own limit
+        let feed_pool = TaskPool::new(config.simultaneous_syncs);
+        let download_pool = feed_pool.with_limit(config.simultaneous_downloads);
 
         // create vector of podcasts, where references are checked at
         // runtime; this is necessary because we want main.rs to hold the
         // "ground truth" list of podcasts, and it must be mutable, but
         // UI needs to check this list and update the screen when
         // necessary
-        let podcast_list = LockVec::new(db_inst.get_podcasts()?);
+        let podcast_list = db_inst.get_podcasts()?;
+        for podcast in podcast_list.iter() {
+            config.title_rules.clean_podcast(podcast);
+        }
+        let podcast_list = LockVec::new(podcast_list);
+
+        // the play queue is kept from the last time shellcaster ran
+        let queue = db_inst.get_queue().unwrap_or_default();
 
         // set up UI in new thread
         let tx_ui_to_main = mpsc::Sender::clone(&tx_to_main);
@@ -76,16 +173,118 @@ impl MainController {
         );
         // TODO: Can we do this without cloning the config?
 
+        // optionally watch a folder for OPML files or lists of URLs
+        if let Some(dir) = &config.watch_dir {
+            watch::spawn_watcher(dir.clone(), tx_to_main.clone());
+        }
+
+        // without a session D-Bus, media controls are not available
+        #[cfg(feature = "mpris")]
+        let mpris = Mpris::new(config.mpv_socket.clone()).ok();
+
+        // the notification has no room for the problems found, but
+        // they are kept in the log
+        match maintenance {
+            Some(Ok(report)) if !report.problems.is_empty() => {
+                for problem in report.problems.iter() {
+                    log::error!("Database integrity check: {problem}");
+                }
+                tx_to_ui
+                    .send(MainMessage::UiSpawnPersistentNotif(
+                        "Database integrity check failed. Run `shellcaster maintain` for details."
+                            .to_string(),
+                        true,
+                    ))
+                    .expect("Thread messaging error");
+            }
+            Some(Err(err)) => {
+                log::error!("Database maintenance failed: {err:#}");
+                tx_to_ui
+                    .send(MainMessage::UiSpawnNotif(
+                        format!("Database maintenance failed: {err}"),
+                        true,
+                        crate::config::MESSAGE_TIME,
+                    ))
+                    .expect("Thread messaging error");
+            }
+            _ => (),
+        }
+
+        // listen for commands from `shellcaster remote`; only one
+        // instance can use the socket at a time
+        let mut remote_socket = None;
+        if let Some(path) = &config.remote_socket {
+            match remote::spawn_listener(path, tx_to_main.clone()) {
+                Ok(()) => remote_socket = Some(path.clone()),
+                Err(err) => {
+                    log::error!("Remote control is off: {err}");
+                    tx_to_ui
+                        .send(MainMessage::UiSpawnNotif(
+                            format!("Remote control is off: {err}"),
+                            true,
+                            crate::config::MESSAGE_TIME,
+                        ))
+                        .expect("Thread messaging error");
+                }
+            }
+        }
+
+        let now_playing_writer = config.now_playing_file.clone().map(NowPlayingWriter::new);
+
+        let sorts = config.sorts;
+        let storage = storage::from_config(
+            &config.download_path,
+            config.webdav.as_ref(),
+            config.trash_days,
+        );
+        // downloads that have been in the trash long enough are cleared
+        // out at startup, as well as whenever another one is discarded
+        let _ = storage.purge_trash();
+
+        // the filters are restored as they were when the app was last
+        // closed, unless the saved filter they used is gone
+        let filters = db_inst
+            .filters()
+            .map(|filters| Filters::restore(&filters, &config.smart_filters))
+            .unwrap_or_default();
         return Ok(MainController {
             config: config,
+            config_path: config_path,
             db: db_inst,
             threadpool: threadpool,
+            feed_pool: feed_pool,
+            download_pool: download_pool,
             podcasts: podcast_list,
-            filters: Filters::default(),
+            filters: filters,
+            sorts: sorts,
+            storage: storage,
             ui_thread: ui_thread,
             sync_counter: 0,
             sync_tracker: Vec::new(),
+            sync_all: false,
+            sync_failed: Vec::new(),
+            check_counter: 0,
+            check_report: Vec::new(),
+            sync_cancel: CancelToken::new(),
             download_tracker: HashSet::new(),
+            download_cancel: CancelToken::new(),
+            image_tracker: HashSet::new(),
+            chapter_tracker: HashSet::new(),
+            playing_ep: None,
+            terminal_busy: Arc::new(AtomicBool::new(false)),
+            waiting_play: None,
+            queue: queue,
+            scrobbling: false,
+            now_playing: None,
+            now_playing_writer: now_playing_writer,
+            now_playing_text: None,
+            last_status: None,
+            #[cfg(feature = "mpris")]
+            mpris: mpris,
+            remote_socket: remote_socket,
+            adding_feeds: HashSet::new(),
+            changing_urls: HashSet::new(),
+            import_settings: HashMap::new(),
             tx_to_ui: tx_to_ui,
             tx_to_main: tx_to_main,
             rx_to_main: rx_to_main,
@@ -94,37 +293,70 @@ impl MainController {
 
     /// Initiates the main loop where the controller waits for messages coming in from the UI and other threads, and processes them.
     pub fn loop_msgs(&mut self) {
+        self.update_filters(self.filters, true);
+        self.queue_to_ui();
+        self.tx_to_ui
+            .send(MainMessage::UiUpdateFilters(self.filters))
+            .expect("Thread messaging error");
+        self.update_status();
+        self.check_for_updates();
+        self.show_whats_new();
+        self.show_config_problems(true);
+        self.show_setup();
+        self.submit_listens();
         while let Some(message) = self.rx_to_main.iter().next() {
             match message {
                 Message::Ui(UiMsg::Quit) => break,
 
                 Message::Ui(UiMsg::AddFeed(url)) => self.add_podcast(url),
 
-                Message::Feed(FeedMsg::NewData(pod)) => self.add_or_sync_data(pod, None),
+                Message::Feed(FeedMsg::NewData(pod)) => self.add_or_sync_data(*pod, None),
 
-                Message::Feed(FeedMsg::Error(feed)) => match feed.title {
-                    Some(t) => {
-                        self.notif_to_ui(format!("Error retrieving RSS feed for {t}."), true)
-                    }
-                    None => self.notif_to_ui("Error retrieving RSS feed.".to_string(), true),
-                },
+                Message::Feed(FeedMsg::Error(feed)) => self.feed_error(feed),
+
+                Message::Feed(FeedMsg::AuthRequired(feed)) => self.feed_auth_required(feed),
+
+                Message::Feed(FeedMsg::NotModified(feed)) => self.feed_not_modified(feed),
+
+                Message::Ui(UiMsg::RetryFeed(feed, auth)) => self.retry_feed(*feed, auth),
 
                 Message::Ui(UiMsg::Sync(pod_id)) => self.sync(Some(pod_id)),
 
-                Message::Feed(FeedMsg::SyncData((id, pod))) => self.add_or_sync_data(pod, Some(id)),
+                Message::Feed(FeedMsg::SyncData((id, pod))) => {
+                    self.add_or_sync_data(*pod, Some(id))
+                }
 
                 Message::Ui(UiMsg::SyncAll) => self.sync(None),
 
+                Message::Ui(UiMsg::CheckAll) => self.check_all(),
+
+                Message::Feed(FeedMsg::Checked(feed, found)) => self.feed_checked(feed, found),
+
                 Message::Ui(UiMsg::Play(pod_id, ep_id)) => self.play_file(pod_id, ep_id),
 
                 Message::Ui(UiMsg::MarkPlayed(pod_id, ep_id, played)) => {
-                    self.mark_played(pod_id, ep_id, played)
+                    self.mark_played(pod_id, ep_id, played);
+                    if played {
+                        self.set_position(pod_id, ep_id, None);
+                    }
+                }
+
+                Message::Ui(UiMsg::MarkPlayedMulti(vec, played)) => {
+                    self.mark_played_multi(vec, played)
+                }
+
+                Message::Ui(UiMsg::MarkNewPodcast(pod_id, choice)) => {
+                    self.mark_new_podcast(pod_id, choice)
                 }
 
                 Message::Ui(UiMsg::MarkAllPlayed(pod_id, played)) => {
                     self.mark_all_played(pod_id, played)
                 }
 
+                Message::Ui(UiMsg::MarkOlderPlayed(pod_id, cutoff)) => {
+                    self.mark_older_played(pod_id, cutoff)
+                }
+
                 Message::Ui(UiMsg::Download(pod_id, ep_id)) => self.download(pod_id, Some(ep_id)),
 
                 Message::Ui(UiMsg::DownloadMulti(vec)) => {
@@ -134,18 +366,31 @@ impl MainController {
                 }
 
                 Message::Ui(UiMsg::DownloadAll(pod_id)) => self.download(pod_id, None),
+                Message::Ui(UiMsg::DownloadEnclosure(pod_id, ep_id, index)) => {
+                    self.download_enclosure(pod_id, ep_id, index)
+                }
+
+                Message::Ui(UiMsg::DownloadLibrary) => self.ask_download_library(),
+
+                Message::Ui(UiMsg::Cancel) => self.cancel(),
 
                 // downloading can produce any one of these responses
                 Message::Dl(DownloadMsg::Complete(ep_data)) => self.download_complete(ep_data),
-                Message::Dl(DownloadMsg::ResponseError(_)) => {
-                    self.notif_to_ui("Error sending download request.".to_string(), true)
-                }
-                Message::Dl(DownloadMsg::FileCreateError(_)) => {
-                    self.notif_to_ui("Error creating file.".to_string(), true)
-                }
-                Message::Dl(DownloadMsg::FileWriteError(_)) => {
-                    self.notif_to_ui("Error downloading episode.".to_string(), true)
-                }
+                Message::Dl(DownloadMsg::ResponseError(ep_data)) => self.notif_to_ui(
+                    format!("Error sending download request: {}", ep_data.url),
+                    true,
+                ),
+                Message::Dl(DownloadMsg::FileCreateError(ep_data)) => self.notif_to_ui(
+                    format!(
+                        "Error creating file: {}",
+                        ep_data.file_path.unwrap_or_default().to_string_lossy()
+                    ),
+                    true,
+                ),
+                Message::Dl(DownloadMsg::FileWriteError(ep_data)) => self.notif_to_ui(
+                    format!("Error downloading episode: {}", ep_data.url),
+                    true,
+                ),
                 Message::Ui(UiMsg::UnmarkDownloaded(pod_id, ep_id)) => {
                     if let Err(_) = self.unmark_downloaded(pod_id, ep_id) {
                         self.notif_to_ui("Error unmarking episode as downloaded".to_string(), true);
@@ -155,8 +400,18 @@ impl MainController {
 
                 Message::Ui(UiMsg::Delete(pod_id, ep_id)) => self.delete_file(pod_id, ep_id),
 
+                Message::Ui(UiMsg::DeleteMulti(vec)) => self.delete_multi(vec),
+
                 Message::Ui(UiMsg::DeleteAll(pod_id)) => self.delete_files(pod_id),
 
+                Message::Ui(UiMsg::SetOverrides(pod_id, overrides)) => {
+                    self.set_overrides(pod_id, overrides)
+                }
+
+                Message::Ui(UiMsg::SetAuth(pod_id, auth)) => {
+                    self.set_auth(pod_id, auth);
+                }
+
                 Message::Ui(UiMsg::RemovePodcast(pod_id, delete_files)) => {
                     self.remove_podcast(pod_id, delete_files)
                 }
@@ -169,65 +424,515 @@ impl MainController {
                     self.remove_all_episodes(pod_id, delete_files)
                 }
 
+                Message::Ui(UiMsg::ToggleNotify(pod_id)) => self.toggle_notify(pod_id),
+
+                Message::Ui(UiMsg::SetDownloadRules(pod_id, include, exclude)) => {
+                    self.set_download_rules(pod_id, include, exclude)
+                }
+
+                Message::Ui(UiMsg::SetArchived(pod_id, archived)) => {
+                    self.set_archived(pod_id, archived)
+                }
+
+                Message::Ui(UiMsg::SetSkipRules(pod_id, show, hide)) => {
+                    self.set_skip_rules(pod_id, show, hide)
+                }
+
+                Message::Ui(UiMsg::SetTitleRule(pod_id, pattern, replacement)) => {
+                    self.set_title_rule(pod_id, pattern, replacement)
+                }
+
+                Message::Ui(UiMsg::SetCustomTitle(pod_id, title)) => {
+                    self.set_custom_title(pod_id, title)
+                }
+
+                Message::Ui(UiMsg::SetGroup(pod_id, group)) => self.set_group(pod_id, group),
+
+                Message::Ui(UiMsg::ChangeFeedUrl(pod_id, url)) => self.change_feed_url(pod_id, url),
+
+                Message::Ui(UiMsg::ReloadConfig) => self.reload_config(),
+
+                Message::Ui(UiMsg::SaveSettings(settings)) => self.save_settings(settings),
+
+                Message::Ui(UiMsg::FinishSetup(settings)) => self.finish_setup(settings),
+
+                Message::Ui(UiMsg::ImportFile(path)) => self.import_file(&path),
+
+                Message::Ui(UiMsg::SortChange(target)) => self.change_sort(target),
+
+                Message::Ui(UiMsg::ShowStats) => self.show_stats(),
+
+                Message::Ui(UiMsg::ShowLog) => self.show_log(),
+
+                Message::Ui(UiMsg::Triage(action, episodes)) => self.triage(action, episodes),
+
+                Message::Ui(UiMsg::ShowQueue) => self.show_queue(),
+
+                Message::Ui(UiMsg::Dequeue(pod_id, ep_id)) => {
+                    self.queue.retain(|&ep| ep != (pod_id, ep_id));
+                    self.queue_to_ui();
+                }
+
+                Message::Ui(UiMsg::SetQueue(episodes)) => self.set_queue(episodes),
+
+                Message::Ui(UiMsg::ShuffleQueue) => self.rearrange_queue(true),
+
+                Message::Ui(UiMsg::InterleaveQueue) => self.rearrange_queue(false),
+
+                Message::Ui(UiMsg::FillQueue(count)) => self.fill_queue(count),
+
                 Message::Ui(UiMsg::FilterChange(filter_type)) => {
-                    let new_filter;
-                    let message;
-                    match filter_type {
-                        // we need to handle these separately because the
-                        // order that makes the most sense to me is
-                        // different:
-                        // played goes from all -> neg -> pos;
-                        // downloaded goes from all -> pos -> neg;
-                        // this is purely based on the idea that people
-                        // are most likely to want to specifically find
-                        // unplayed episodes, or downloaded episodes
-                        FilterType::Played => {
-                            match self.filters.played {
-                                FilterStatus::All => {
-                                    new_filter = FilterStatus::NegativeCases;
-                                    message = "Unplayed only";
-                                }
-                                FilterStatus::NegativeCases => {
-                                    new_filter = FilterStatus::PositiveCases;
-                                    message = "Played only";
-                                }
-                                FilterStatus::PositiveCases => {
-                                    new_filter = FilterStatus::All;
-                                    message = "Played and unplayed";
-                                }
-                            }
-                            self.filters.played = new_filter;
-                        }
-                        FilterType::Downloaded => {
-                            match self.filters.downloaded {
-                                FilterStatus::All => {
-                                    new_filter = FilterStatus::PositiveCases;
-                                    message = "Downloaded only";
-                                }
-                                FilterStatus::PositiveCases => {
-                                    new_filter = FilterStatus::NegativeCases;
-                                    message = "Undownloaded only";
-                                }
-                                FilterStatus::NegativeCases => {
-                                    new_filter = FilterStatus::All;
-                                    message = "Downloaded and undownloaded";
-                                }
-                            }
-                            self.filters.downloaded = new_filter;
-                        }
-                    }
+                    self.filters
+                        .change(filter_type, self.config.smart_filters.len());
+                    let message = self
+                        .filters
+                        .describe(filter_type, &self.config.smart_filters);
                     self.notif_to_ui(format!("Filter: {message}"), false);
                     self.update_filters(self.filters, true);
+                    self.filters_changed();
+                }
+
+                Message::Ui(UiMsg::ToggleStar(pod_id, ep_id)) => self.toggle_star(pod_id, ep_id),
+
+                Message::Watch(WatchMsg::Import(file_name, feeds)) => {
+                    self.import_feeds(file_name, feeds)
+                }
+
+                Message::Watch(WatchMsg::Error(err)) => self.notif_to_ui(err, true),
+
+                Message::Ui(UiMsg::FetchImage(url)) => self.fetch_image(url),
+
+                Message::Image(ImageMsg::Cached(url)) => {
+                    self.image_tracker.remove(&url);
+                    self.tx_to_ui
+                        .send(MainMessage::UiUpdateDetails)
+                        .expect("Thread messaging error");
+                }
+
+                // images that can't be fetched stay in the tracker, so
+                // they are not requested again every time the details
+                // panel is redrawn
+                Message::Image(ImageMsg::Error(_url)) => (),
+
+                Message::Ui(UiMsg::RenderImage(path, protocol, cols, rows)) => {
+                    cover_art::render_image(
+                        path,
+                        protocol,
+                        cols,
+                        rows,
+                        &self.threadpool,
+                        self.tx_to_main.clone(),
+                    );
+                }
+
+                Message::Image(ImageMsg::Rendered(path, cols, rows, art)) => {
+                    self.tx_to_ui
+                        .send(MainMessage::UiUpdateArt(path, cols, rows, art))
+                        .expect("Thread messaging error");
+                }
+
+                Message::Ui(UiMsg::FetchChapters(pod_id, ep_id)) => {
+                    self.fetch_chapters(pod_id, ep_id)
+                }
+
+                Message::Chapters(ChapterMsg::Found(pod_id, ep_id, chapters)) => {
+                    self.save_chapters(pod_id, ep_id, chapters)
+                }
+
+                // as with images, episodes without chapters stay in the
+                // tracker so they are only checked once
+                Message::Chapters(ChapterMsg::Error(_pod_id, _ep_id)) => (),
+
+                Message::Ui(UiMsg::JumpToChapter(ep_id, start)) => {
+                    self.jump_to_chapter(ep_id, start)
+                }
+
+                Message::Ui(UiMsg::FetchTranscript(pod_id, ep_id)) => {
+                    self.fetch_transcript(pod_id, ep_id)
+                }
+
+                Message::Transcript(TranscriptMsg::Fetched(pod_id, ep_id, lines)) => {
+                    self.show_transcript(pod_id, ep_id, lines)
+                }
+
+                Message::Transcript(TranscriptMsg::Error(_pod_id, _ep_id)) => {
+                    self.notif_to_ui("Could not load transcript.".to_string(), true)
+                }
+
+                Message::Hook(HookMsg::Failed(event, err)) => self.notif_to_ui(
+                    format!("Error in {}: {err}", event.config_name()),
+                    true,
+                ),
+
+                Message::Webhook(WebhookMsg::Failed(err)) => self.notif_to_ui(err, true),
+
+                Message::Remote(RemoteMsg::Command(RemoteCmd::Quit, reply)) => {
+                    log::info!("Remote command: {:?}", RemoteCmd::Quit);
+                    let _ = reply.send(Ok(String::new()));
+                    break;
+                }
+
+                Message::Remote(RemoteMsg::Command(cmd, reply)) => {
+                    log::info!("Remote command: {cmd:?}");
+                    let result = self.remote_command(cmd);
+                    if let Err(err) = &result {
+                        log::warn!("Remote command failed: {err}");
+                    }
+                    let _ = reply.send(result);
+                }
+
+                Message::Update(UpdateMsg::Available(version)) => {
+                    self.update_available(version)
+                }
+
+                // a player that is still running may report on an
+                // episode after another one has started
+                Message::Player(PlayerMsg::Position(pod_id, ep_id, position)) => {
+                    if self.playing_ep == Some(ep_id) {
+                        if position.is_some() {
+                            self.set_position(pod_id, ep_id, position);
+                        }
+                        self.update_now_playing(position);
+                    }
+                }
+
+                Message::Player(PlayerMsg::Stopped(pod_id, ep_id, finished)) => {
+                    if self.playing_ep == Some(ep_id) {
+                        self.now_playing = None;
+                        self.update_now_playing(None);
+                    }
+                    if finished {
+                        self.episode_finished(pod_id, ep_id);
+                    }
+                }
+
+                // the user may have played something else while waiting
+                Message::Player(PlayerMsg::Next(after_id, pod_id, ep_id)) => {
+                    if self.playing_ep == Some(after_id) {
+                        self.play_file(pod_id, ep_id);
+                    }
+                }
+
+                Message::Player(PlayerMsg::Failed(failure)) => {
+                    self.notif_to_ui(format!("Error: {failure}"), true);
                 }
 
+                Message::Player(PlayerMsg::TerminalReturned) => {
+                    if let Some((pod_id, ep_id)) = self.waiting_play.take() {
+                        self.play_file(pod_id, ep_id);
+                    }
+                }
+
+                Message::Scrobble(msg) => self.listens_submitted(msg),
+
                 Message::Ui(UiMsg::Noop) => (),
             }
+            self.update_status();
+        }
+        self.save_sorts();
+
+        // remove the status file so status bars don't keep showing
+        // stale information once the app is closed
+        if let Some(path) = &self.config.status_file {
+            let _ = fs::remove_file(path);
+        }
+        if let Some(path) = &self.config.now_playing_file {
+            if !status::is_fifo(path) {
+                let _ = fs::remove_file(path);
+            }
+        }
+        if let Some(path) = &self.remote_socket {
+            remote::remove_socket(path);
+        }
+    }
+
+    /// Carries out a command sent with `shellcaster remote`, returning
+    /// the text to send back, or an error message.
+    pub fn remote_command(&mut self, cmd: RemoteCmd) -> Result<String, String> {
+        match cmd {
+            RemoteCmd::SyncAll => self.sync(None),
+            RemoteCmd::Sync(pod_id) => {
+                self.remote_podcast(pod_id)?;
+                self.sync(Some(pod_id));
+            }
+            RemoteCmd::Play(ep_id) => {
+                let pod_id = self.remote_episode(ep_id)?;
+                self.play_file(pod_id, ep_id);
+            }
+            RemoteCmd::PlayNext => {
+                let (pod_id, ep_id) = self.next_episode()?;
+                self.play_file(pod_id, ep_id);
+            }
+            RemoteCmd::QueueNext(ep_id) => {
+                let pod_id = self.remote_episode(ep_id)?;
+                queue::insert_next(&mut self.queue, vec![(pod_id, ep_id)], self.playing_ep);
+                self.queue_to_ui();
+            }
+            RemoteCmd::Download(ep_id) => {
+                let pod_id = self.remote_episode(ep_id)?;
+                self.download(pod_id, Some(ep_id));
+            }
+            RemoteCmd::MarkPlayed(ep_id, played) => {
+                let pod_id = self.remote_episode(ep_id)?;
+                self.mark_played(pod_id, ep_id, played);
+            }
+            RemoteCmd::List(None) => {
+                return Ok(self
+                    .podcasts
+                    .map(|pod| format!("{}\t{}", pod.id, pod.title), false)
+                    .join("\n"));
+            }
+            RemoteCmd::List(Some(pod_id)) => {
+                let podcast = self.remote_podcast(pod_id)?;
+                return Ok(podcast
+                    .episodes
+                    .map(
+                        |ep| {
+                            let played = if ep.played { "played" } else { "unplayed" };
+                            format!("{}\t{played}\t{}", ep.id, ep.title)
+                        },
+                        false,
+                    )
+                    .join("\n"));
+            }
+            RemoteCmd::Quit => (),
+        }
+        return Ok(String::new());
+    }
+
+    /// Looks up a podcast requested by `shellcaster remote`.
+    fn remote_podcast(&self, pod_id: i64) -> Result<Podcast, String> {
+        return self
+            .podcasts
+            .clone_podcast(pod_id)
+            .ok_or_else(|| format!("No podcast with id {pod_id}"));
+    }
+
+    /// Finds the podcast that an episode requested by `shellcaster
+    /// remote` belongs to.
+    fn remote_episode(&self, ep_id: i64) -> Result<i64, String> {
+        return self
+            .podcasts
+            .filter_map(|pod| pod.episodes.map_single(ep_id, |_| pod.id))
+            .into_iter()
+            .next()
+            .ok_or_else(|| format!("No episode with id {ep_id}"));
+    }
+
+    /// Finds the episode of the same podcast that was published right
+    /// after the one played most recently.
+    fn next_episode(&self) -> Result<(i64, i64), String> {
+        let ep_id = self
+            .playing_ep
+            .ok_or_else(|| "No episode has been played yet".to_string())?;
+        let pod_id = self.remote_episode(ep_id)?;
+        let podcast = self.remote_podcast(pod_id)?;
+
+        let order = podcast.episodes.ids_newest_first();
+        return match order.iter().position(|id| *id == ep_id) {
+            Some(index) if index > 0 => Ok((pod_id, order[index - 1])),
+            _ => Err("There is no newer episode to play".to_string()),
+        };
+    }
+
+    /// Switches to the next sort mode for the podcast or episode list.
+    /// The new mode is saved to the config file when shellcaster is
+    /// closed (see `save_sorts()`).
+    pub fn change_sort(&mut self, target: SortTarget) {
+        let (mode, list) = match target {
+            SortTarget::Podcasts => {
+                self.sorts.podcasts = self.sorts.podcasts.cycle(target);
+                (self.sorts.podcasts, "Podcasts")
+            }
+            SortTarget::Episodes => {
+                self.sorts.episodes = self.sorts.episodes.cycle(target);
+                (self.sorts.episodes, "Episodes")
+            }
+        };
+        self.update_filters(self.filters, true);
+        self.notif_to_ui(format!("{list} sorted by {}", mode.description()), false);
+    }
+
+    /// Saves the sort modes to the config file, if they were changed
+    /// since it was loaded, so they are kept after restarting. This
+    /// happens once, on the way out, rather than every time the user
+    /// cycles through the sort modes.
+    pub fn save_sorts(&self) {
+        let changed = [
+            ("sort_podcasts", self.sorts.podcasts, self.config.sorts.podcasts),
+            ("sort_episodes", self.sorts.episodes, self.config.sorts.episodes),
+        ];
+        let settings: Vec<(String, Option<String>)> = changed
+            .iter()
+            .filter(|(_, mode, saved)| mode != saved)
+            .map(|(key, mode, _)| {
+                let value = toml::Value::String(mode.config_name().to_string()).to_string();
+                (key.to_string(), Some(value))
+            })
+            .collect();
+        if !settings.is_empty() {
+            self.save_settings(settings);
+        }
+    }
+
+    /// Reads the config file again and passes the new configuration on
+    /// to the UI. Settings that are only used at startup (e.g., the
+    /// watch folder or the number of simultaneous downloads) still need
+    /// a restart.
+    pub fn reload_config(&mut self) {
+        match Config::new(&self.config_path) {
+            Ok(config) => {
+                // the saved filter in use is found again by name, as the
+                // filters may have been reordered
+                let smart = self
+                    .filters
+                    .smart_name(&self.config.smart_filters)
+                    .and_then(|name| config.smart_filters.iter().position(|f| f.name == name));
+                self.config = config.clone();
+                self.sorts = config.sorts;
+                self.storage = storage::from_config(
+                    &config.download_path,
+                    config.webdav.as_ref(),
+                    config.trash_days,
+                );
+                self.podcasts
+                    .map(|pod| config.title_rules.clean_podcast(pod), false);
+                if smart != self.filters.smart {
+                    self.filters.smart = smart;
+                    self.filters_changed();
+                }
+                self.update_filters(self.filters, true);
+                self.tx_to_ui
+                    .send(MainMessage::UiReloadConfig(Box::new(config)))
+                    .expect("Thread messaging error");
+            }
+            Err(err) => {
+                if !self.show_config_problems(false) {
+                    self.notif_to_ui(format!("Could not reload config: {err:#}"), true);
+                }
+            }
+        }
+    }
+
+    /// Checks the config file and shows any problems with it in a popup
+    /// window, e.g., settings that are ignored because they are not
+    /// valid. If there are errors, the config file was not loaded: at
+    /// `startup`, the default settings are used in its place; otherwise,
+    /// the settings that were loaded before are kept. Returns whether
+    /// any problems were found.
+    pub fn show_config_problems(&self, startup: bool) -> bool {
+        let problems = config_check::check(&self.config_path);
+        if problems.is_empty() {
+            return false;
+        }
+        let mut lines = Vec::new();
+        if problems.iter().any(|problem| problem.fatal) {
+            let settings = if startup {
+                "the default settings are used"
+            } else {
+                "the settings stay as they were"
+            };
+            lines.push(format!(
+                "The config file could not be loaded, so {settings} until the errors are fixed."
+            ));
+        }
+        lines.extend(problems.iter().flat_map(|problem| problem.describe()));
+        self.tx_to_ui
+            .send(MainMessage::UiSpawnConfigProblems(lines))
+            .expect("Thread messaging error");
+        return true;
+    }
+
+    /// Writes changed settings to the config file, keeping a backup of
+    /// the old one.
+    pub fn save_settings(&self, settings: Vec<(String, Option<String>)>) {
+        let settings: Vec<(&str, Option<String>)> = settings
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.clone()))
+            .collect();
+        if let Err(err) = config_writer::save_settings(&self.config_path, &settings) {
+            self.notif_to_ui(format!("Could not save config: {err:#}"), true);
+        }
+    }
+
+    /// Opens the setup window the first time shellcaster runs, i.e.,
+    /// when there is no config file and no podcasts yet.
+    pub fn show_setup(&self) {
+        if self.config_path.exists() || !self.podcasts.is_empty() {
+            return;
+        }
+        self.tx_to_ui
+            .send(MainMessage::UiSpawnSetup(play_file::find_players()))
+            .expect("Thread messaging error");
+    }
+
+    /// Writes the settings chosen in the setup window to the config
+    /// file, starting from the default config file (with all of its
+    /// comments) if there is none yet, and then loads them.
+    pub fn finish_setup(&mut self, settings: Vec<(String, Option<String>)>) {
+        if self.config_path.exists() {
+            if settings.is_empty() {
+                return;
+            }
+            self.save_settings(settings);
+        } else {
+            let settings: Vec<(&str, Option<String>)> = settings
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.clone()))
+                .collect();
+            if let Err(err) = config_writer::write_default_config(&self.config_path, &settings) {
+                self.notif_to_ui(format!("Could not save config: {err:#}"), true);
+                return;
+            }
+        }
+        self.reload_config();
+    }
+
+    /// Imports the podcasts in an OPML or library file, e.g., one chosen
+    /// in the setup window.
+    pub fn import_file(&mut self, path: &Path) {
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let feeds = fs::read_to_string(path)
+            .with_context(|| format!("Could not read file: {}", path.to_string_lossy()))
+            .and_then(library::import_any);
+        match feeds {
+            Ok(feeds) => self.import_feeds(file_name, feeds),
+            Err(err) => self.notif_to_ui(format!("{err:#}"), true),
+        }
+    }
+
+    /// Saves the active filters, so they are restored on the next start,
+    /// and passes them on to the UI to show.
+    fn filters_changed(&self) {
+        let _ = self.db.set_filters(&self.filters.save(&self.config.smart_filters));
+        self.tx_to_ui
+            .send(MainMessage::UiUpdateFilters(self.filters))
+            .expect("Thread messaging error");
+    }
+
+    /// Reloads the list of podcasts from the database, and cleans up the
+    /// titles of their episodes.
+    fn reload_podcasts(&self) {
+        let podcasts = self
+            .db
+            .get_podcasts()
+            .expect("Error retrieving info from database.");
+        for podcast in podcasts.iter() {
+            self.config.title_rules.clean_podcast(podcast);
         }
+        self.podcasts.replace_all(podcasts);
     }
 
     /// Sends the specified notification to the UI, which will display at
-    /// the bottom of the screen.
+    /// the bottom of the screen. Errors are also written to the log, so
+    /// they can be looked at later.
     pub fn notif_to_ui(&self, message: String, error: bool) {
+        if error {
+            log::error!("{message}");
+        }
         self.tx_to_ui
             .send(MainMessage::UiSpawnNotif(
                 message,
@@ -240,6 +945,9 @@ impl MainController {
     /// Sends a persistent notification to the UI, which will display at
     /// the bottom of the screen until cleared.
     pub fn persistent_notif_to_ui(&self, message: String, error: bool) {
+        if error {
+            log::error!("{message}");
+        }
         self.tx_to_ui
             .send(MainMessage::UiSpawnPersistentNotif(message, error))
             .expect("Thread messaging error");
@@ -270,185 +978,1449 @@ impl MainController {
         } else if dl_len > 0 {
             let notif = format!("Downloading {dl_len} episode{dl_plural}...");
             self.persistent_notif_to_ui(notif, false);
+        } else if self.check_counter > 0 {
+            let check_len = self.check_counter;
+            let check_plural = if check_len > 1 { "s" } else { "" };
+            let notif = format!("Checking {check_len} podcast{check_plural} for changes...");
+            self.persistent_notif_to_ui(notif, false);
         } else {
             self.clear_persistent_notif();
         }
     }
 
     /// Add a new podcast by fetching the RSS feed data.
-    pub fn add_podcast(&self, url: String) {
+    pub fn add_podcast(&mut self, url: String) {
+        self.adding_feeds.insert(url.clone());
         let feed = PodcastFeed::new(None, url, None);
         feeds::check_feed(
             feed,
             self.config.max_retries,
-            &self.threadpool,
+            self.config.feed_url_hook.clone(),
+            &self.feed_pool,
+            self.sync_cancel.clone(),
             self.tx_to_main.clone(),
         );
     }
 
-    /// Synchronize RSS feed data for one or more podcasts.
-    pub fn sync(&mut self, pod_id: Option<i64>) {
-        // We pull out the data we need here first, so we can
-        // stop borrowing the podcast list as quickly as possible.
-        // Slightly less efficient (two loops instead of
-        // one), but then it won't block other tasks that
-        // need to access the list.
-        let mut pod_data = Vec::new();
-        match pod_id {
-            // just grab one podcast
-            Some(id) => pod_data.push(
-                self.podcasts
-                    .map_single(id, |pod| {
-                        PodcastFeed::new(Some(pod.id), pod.url.clone(), Some(pod.title.clone()))
-                    })
-                    .unwrap(),
-            ),
-            // get all of 'em!
-            None => {
-                pod_data = self.podcasts.map(
-                    |pod| PodcastFeed::new(Some(pod.id), pod.url.clone(), Some(pod.title.clone())),
-                    false,
-                )
-            }
+    /// Add a list of new podcasts (e.g., from a file dropped in the watch
+    /// folder), skipping any that are already in the database. Settings
+    /// that come with the feeds are applied once each podcast is added.
+    pub fn import_feeds(&mut self, file_name: String, feeds: Vec<PodcastFeed>) {
+        let existing = self.podcasts.map(|pod| pod.url.clone(), false);
+        let new_feeds: Vec<PodcastFeed> = feeds
+            .into_iter()
+            .filter(|feed| !existing.contains(&feed.url))
+            .collect();
+
+        if new_feeds.is_empty() {
+            self.notif_to_ui(format!("No new podcasts to import from {file_name}."), false);
+            return;
         }
-        for feed in pod_data.into_iter() {
-            self.sync_counter += 1;
+        self.notif_to_ui(
+            format!("Importing {} podcasts from {file_name}...", new_feeds.len()),
+            false,
+        );
+        for mut feed in new_feeds.into_iter() {
+            if let Some(settings) = feed.settings.take() {
+                self.import_settings.insert(feed.url.clone(), *settings);
+            }
             feeds::check_feed(
                 feed,
                 self.config.max_retries,
-                &self.threadpool,
+                self.config.feed_url_hook.clone(),
+                &self.feed_pool,
+                self.sync_cancel.clone(),
                 self.tx_to_main.clone(),
-            )
+            );
         }
-        self.update_tracker_notif();
     }
 
-    /// Handles the application logic for adding a new podcast, or
-    /// synchronizing data from the RSS feed of an existing podcast.
-    /// `pod_id` will be None if a new podcast is being added (i.e.,
-    /// the database has not given it an id yet).
-    pub fn add_or_sync_data(&mut self, pod: PodcastNoId, pod_id: Option<i64>) {
-        let title = pod.title.clone();
-        let db_result;
-        let failure;
+    /// Handles a feed that could not be retrieved. If the feed belongs
+    /// to a podcast that is being synced, this also counts it as done.
+    pub fn feed_error(&mut self, feed: PodcastFeed) {
+        let title = feed.title.clone().unwrap_or_else(|| feed.url.clone());
+        if let Some(id) = feed.id {
+            self.sync_failed.push((id, title.clone()));
+        }
+        if feed.id.is_none() || !self.sync_all {
+            self.notif_to_ui(format!("Error retrieving RSS feed for {title}."), true);
+        }
+        match feed.id {
+            Some(id) if self.changing_urls.remove(&id) => {
+                self.sync_finished_one();
+                self.notif_to_ui(
+                    format!("Could not get a feed from the new URL for {title}; kept the old one."),
+                    true,
+                );
+            }
+            Some(id) => {
+                self.sync_finished_one();
+
+                // once a feed has failed enough syncs in a row, the
+                // user is asked what to do about it (unless it has been
+                // archived already)
+                let failures = self.db.record_sync_failure(id).unwrap_or(0);
+                let archived = self.podcasts.map_single(id, |pod| pod.archived);
+                if self.config.dead_feed_syncs.is_some_and(|limit| failures >= limit)
+                    && archived == Some(false)
+                {
+                    self.tx_to_ui
+                        .send(MainMessage::UiAskDeadFeed(Box::new(feed), failures))
+                        .expect("Thread messaging error");
+                }
+            }
+            None => {
+                self.import_settings.remove(&feed.url);
+            }
+        }
+    }
+
+    /// Handles a podcast whose feed has not changed since it was last
+    /// synced, which counts as done without anything to update.
+    pub fn feed_not_modified(&mut self, feed: PodcastFeed) {
+        if let Some(id) = feed.id {
+            let _ = self.db.set_last_checked(id);
+            self.sync_finished_one();
+        }
+    }
+
+    /// Handles a feed that asked for credentials, by asking the user for
+    /// them. If the feed belongs to a podcast that is being synced, this
+    /// also counts it as done; it is synced again once the user answers.
+    pub fn feed_auth_required(&mut self, feed: PodcastFeed) {
+        if feed.id.is_some() {
+            self.sync_finished_one();
+        }
+        self.tx_to_ui
+            .send(MainMessage::UiAskFeedAuth(Box::new(feed)))
+            .expect("Thread messaging error");
+    }
+
+    /// Tries a feed again with the credentials the user gave, saving
+    /// them with the podcast. If the user gave none, the podcast is not
+    /// added (or, if it already exists, not synced).
+    pub fn retry_feed(&mut self, mut feed: PodcastFeed, auth: Option<FeedAuth>) {
+        match (feed.id, auth) {
+            (Some(id), Some(auth)) => {
+                if self.set_auth(id, auth) {
+                    if self.changing_urls.contains(&id) {
+                        self.change_feed_url(id, feed.url);
+                    } else {
+                        self.sync(Some(id));
+                    }
+                }
+            }
+            (None, Some(auth)) => {
+                self.import_settings
+                    .entry(feed.url.clone())
+                    .or_default()
+                    .auth = auth.clone();
+                feed.auth = auth;
+                feeds::check_feed(
+                    feed,
+                    self.config.max_retries,
+                    self.config.feed_url_hook.clone(),
+                    &self.feed_pool,
+                    self.sync_cancel.clone(),
+                    self.tx_to_main.clone(),
+                );
+            }
+            (Some(id), None) => {
+                self.changing_urls.remove(&id);
+                let title = feed.title.unwrap_or(feed.url);
+                self.notif_to_ui(format!("Could not sync {title} without a login."), true);
+            }
+            (None, None) => {
+                self.adding_feeds.remove(&feed.url);
+                self.import_settings.remove(&feed.url);
+                self.notif_to_ui(format!("Could not add {} without a login.", feed.url), true);
+            }
+        }
+    }
+
+    /// Writes the status file, if one is configured and anything has
+    /// changed since it was last written.
+    pub fn update_status(&mut self) {
+        let path = match &self.config.status_file {
+            Some(path) => path.clone(),
+            None => return,
+        };
+        let status = Status {
+            now_playing: self.now_playing.clone(),
+            podcasts: self.podcasts.len(false),
+            unplayed: self
+                .podcasts
+                .map(|pod| pod.num_unplayed(), false)
+                .iter()
+                .sum(),
+            downloads: self.download_tracker.len(),
+            syncing: self.sync_counter,
+        };
+        if self.last_status.as_ref() == Some(&status) {
+            return;
+        }
+        if let Err(err) = status::write_status(&path, &status) {
+            self.notif_to_ui(format!("{err}"), true);
+        }
+        self.last_status = Some(status);
+    }
+
+    /// Writes what is playing to the now-playing file, if one is
+    /// configured, or clears it if nothing is. `elapsed` is how far into
+    /// the episode the player is, if it could tell; otherwise, the time
+    /// since playback started is used.
+    pub fn update_now_playing(&mut self, elapsed: Option<i64>) {
+        let writer = match &self.now_playing_writer {
+            Some(writer) => writer,
+            None => return,
+        };
+        let text = match &self.now_playing {
+            Some(now_playing) => {
+                let elapsed =
+                    elapsed.unwrap_or_else(|| Utc::now().timestamp() - now_playing.started);
+                now_playing.format(&self.config.now_playing_format, elapsed)
+            }
+            None => String::new(),
+        };
+        if self.now_playing_text.as_ref() == Some(&text) {
+            return;
+        }
+        writer.write(text.clone());
+        self.now_playing_text = Some(text);
+    }
+
+    /// Downloads cover art into the image cache, unless it is already
+    /// being fetched.
+    pub fn fetch_image(&mut self, url: String) {
+        if let Some(cache_dir) = &self.config.image_cache {
+            if self.image_tracker.insert(url.clone()) {
+                cover_art::fetch_image(
+                    url,
+                    cache_dir,
+                    self.config.max_retries,
+                    &self.threadpool,
+                    self.tx_to_main.clone(),
+                );
+            }
+        }
+    }
+
+    /// Synchronize RSS feed data for one or more podcasts.
+    pub fn sync(&mut self, pod_id: Option<i64>) {
+        // We pull out the data we need here first, so we can
+        // stop borrowing the podcast list as quickly as possible.
+        // Slightly less efficient (two loops instead of
+        // one), but then it won't block other tasks that
+        // need to access the list.
+        let mut pod_data = Vec::new();
+        match pod_id {
+            // just grab one podcast
+            Some(id) => pod_data.push(
+                self.podcasts
+                    .map_single(id, Self::podcast_feed)
+                    .unwrap(),
+            ),
+            // get all of 'em! (apart from archived podcasts)
+            None => {
+                self.sync_all = true;
+                pod_data = self
+                    .podcasts
+                    .filter_map(|pod| (!pod.archived).then(|| Self::podcast_feed(pod)))
+            }
+        }
+        for feed in pod_data.into_iter() {
+            self.sync_counter += 1;
+            feeds::check_feed(
+                feed,
+                self.config.max_retries,
+                self.config.feed_url_hook.clone(),
+                &self.feed_pool,
+                self.sync_cancel.clone(),
+                self.tx_to_main.clone(),
+            )
+        }
+        self.update_tracker_notif();
+    }
+
+    /// Fetches the feeds of all podcasts (apart from archived ones) to
+    /// report what syncing them would change, without saving anything.
+    pub fn check_all(&mut self) {
+        if self.check_counter > 0 {
+            self.notif_to_ui("Already checking for changes.".to_string(), false);
+            return;
+        }
+        let feeds = self.podcasts.filter_map(|pod| {
+            (!pod.archived).then(|| PodcastFeed {
+                check_only: true,
+                ..Self::podcast_feed(pod)
+            })
+        });
+        if feeds.is_empty() {
+            self.notif_to_ui("No podcasts to check.".to_string(), false);
+            return;
+        }
+        for feed in feeds.into_iter() {
+            self.check_counter += 1;
+            feeds::check_feed(
+                feed,
+                self.config.max_retries,
+                self.config.feed_url_hook.clone(),
+                &self.feed_pool,
+                self.sync_cancel.clone(),
+                self.tx_to_main.clone(),
+            )
+        }
+        self.update_tracker_notif();
+    }
+
+    /// Adds a feed that was checked to the report of changes, which is
+    /// shown once all of the feeds have been checked.
+    pub fn feed_checked(&mut self, feed: PodcastFeed, found: FeedCheck) {
+        let title = feed.title.clone().unwrap_or_default().to_lowercase();
+        let lines = feeds::check_report(&self.db, &feed, &found);
+        self.check_report.push((title, lines));
+        self.check_counter = self.check_counter.saturating_sub(1);
+        if self.check_counter == 0 {
+            let mut report = std::mem::take(&mut self.check_report);
+            report.sort_by(|a, b| a.0.cmp(&b.0));
+            let lines = report.into_iter().flat_map(|(_, lines)| lines).collect();
+            self.tx_to_ui
+                .send(MainMessage::UiSpawnCheckReport(lines))
+                .expect("Thread messaging error");
+        }
+        self.update_tracker_notif();
+    }
+
+    /// Syncs a podcast from a different feed URL (e.g., after the show
+    /// has moved). The new URL is only saved if the feed can be fetched
+    /// and parsed; the podcast keeps its episodes and played status.
+    pub fn change_feed_url(&mut self, pod_id: i64, url: String) {
+        let mut feed = match self.podcasts.map_single(pod_id, Self::podcast_feed) {
+            Some(feed) => feed,
+            None => return,
+        };
+        feed.url = url;
+        feed.validators = FeedValidators::default();
+        self.changing_urls.insert(pod_id);
+        self.sync_counter += 1;
+        feeds::check_feed(
+            feed,
+            self.config.max_retries,
+            self.config.feed_url_hook.clone(),
+            &self.feed_pool,
+            self.sync_cancel.clone(),
+            self.tx_to_main.clone(),
+        );
+        self.update_tracker_notif();
+    }
+
+    /// Stops any syncing and downloading in progress. Jobs that have
+    /// already started run until they next check in, but their results
+    /// are not sent back; new syncs and downloads can start right away.
+    pub fn cancel(&mut self) {
+        let syncing =
+            self.sync_counter > 0 || self.check_counter > 0 || !self.adding_feeds.is_empty();
+        let downloading = !self.download_tracker.is_empty();
+
+        // every job sent to the threadpool holds a copy of the token
+        // that was current at the time, so swapping in a fresh token
+        // leaves later jobs unaffected
+        if syncing {
+            std::mem::take(&mut self.sync_cancel).cancel();
+            self.sync_counter = 0;
+            self.sync_tracker = Vec::new();
+            self.sync_all = false;
+            self.sync_failed = Vec::new();
+            self.check_counter = 0;
+            self.check_report = Vec::new();
+            self.adding_feeds.clear();
+            self.changing_urls.clear();
+            self.import_settings.clear();
+        }
+        if downloading {
+            std::mem::take(&mut self.download_cancel).cancel();
+            self.download_tracker.clear();
+        }
+        self.update_tracker_notif();
+
+        let message = match (syncing, downloading) {
+            (true, true) => "Cancelled syncing and downloads.",
+            (true, false) => "Cancelled syncing.",
+            (false, true) => "Cancelled downloads.",
+            (false, false) => "Nothing to cancel.",
+        };
+        self.notif_to_ui(message.to_string(), false);
+    }
+
+    /// Returns the feed of an existing podcast, for syncing it.
+    fn podcast_feed(pod: &Podcast) -> PodcastFeed {
+        let mut feed = PodcastFeed::new(Some(pod.id), pod.url.clone(), Some(pod.title.clone()));
+        feed.auth = pod.auth.clone();
+        feed.validators = pod.validators.clone();
+        return feed;
+    }
+
+    /// Handles the application logic for adding a new podcast, or
+    /// synchronizing data from the RSS feed of an existing podcast.
+    /// `pod_id` will be None if a new podcast is being added (i.e.,
+    /// the database has not given it an id yet).
+    pub fn add_or_sync_data(&mut self, pod: PodcastNoId, pod_id: Option<i64>) {
+        let title = pod.title.clone();
+        let db_result;
+        let failure;
+        let mut asked = false;
 
         if let Some(id) = pod_id {
             db_result = self.db.update_podcast(id, pod);
             failure = format!("Error synchronizing {title}.");
         } else {
-            db_result = self.db.insert_podcast(pod);
+            asked = self.adding_feeds.remove(&pod.url);
+            let settings = self.import_settings.remove(&pod.url);
+            let url = pod.url.clone();
+            db_result = self.db.insert_podcast(pod).and_then(|result| {
+                if let Some(settings) = settings {
+                    self.db.set_settings_by_url(&url, &settings)?;
+                }
+                Ok(result)
+            });
             failure = "Error adding podcast to database.".to_string();
         }
-        match db_result {
-            Ok(result) => {
-                {
-                    self.podcasts.replace_all(
-                        self.db
-                            .get_podcasts()
-                            .expect("Error retrieving info from database."),
-                    );
-                }
-                self.update_filters(self.filters, true);
+        match db_result {
+            Ok(result) => {
+                self.reload_podcasts();
+                self.update_filters(self.filters, true);
+
+                if let Some(id) = pod_id {
+                    self.sync_tracker.push(result);
+                    self.sync_finished_one();
+                    if self.changing_urls.remove(&id) {
+                        self.notif_to_ui(format!("Changed the feed URL of {title}."), false);
+                    }
+                } else {
+                    self.notif_to_ui(
+                        format!("Successfully added {} episodes.", result.added.len()),
+                        false,
+                    );
+
+                    // podcasts added by the user (rather than imported)
+                    // can ask which episodes to start off with
+                    if let Some(new_id) = result.added.first().map(|ep| ep.pod_id) {
+                        match self.config.new_podcast_episodes {
+                            NewPodcastEpisodes::Ask if asked && result.added.len() > 1 => self
+                                .tx_to_ui
+                                .send(MainMessage::UiAskNewPodcast(new_id))
+                                .expect("Thread messaging error"),
+                            choice => self.mark_new_podcast(new_id, choice),
+                        }
+                    }
+                }
+            }
+            Err(_err) => {
+                if let Some(id) = pod_id {
+                    self.sync_failed.push((id, title.clone()));
+                }
+                if pod_id.is_none() || !self.sync_all {
+                    self.notif_to_ui(failure, true);
+                }
+                if let Some(id) = pod_id {
+                    self.sync_finished_one();
+                    // e.g., another podcast already has that URL
+                    if self.changing_urls.remove(&id) {
+                        self.notif_to_ui(format!("Could not change the feed URL of {title}."), true);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Records that one podcast has finished syncing (successfully or
+    /// not), and wraps up the sync process once all podcasts are done.
+    fn sync_finished_one(&mut self) {
+        self.sync_counter = self.sync_counter.saturating_sub(1);
+        self.update_tracker_notif();
+        if self.sync_counter > 0 {
+            return;
+        }
+
+        // the network may be back, so try any listens that could not
+        // be submitted before
+        self.submit_listens();
+
+        // count up total new episodes and updated episodes when sync
+        // process is finished
+        let mut added = 0;
+        let mut updated = 0;
+        let mut new_eps = Vec::new();
+        let mut rereleased = Vec::new();
+        for res in self.sync_tracker.iter() {
+            added += res.added.len();
+            updated += res.updated.len();
+            new_eps.extend(res.added.clone());
+            rereleased.extend(res.rereleased.iter().copied());
+        }
+        self.sync_tracker = Vec::new();
+
+        // podcasts with notifications turned off are left out of the
+        // new episode count, the digest, and the download popup
+        let muted = self.podcasts.filter_map(|pod| {
+            if pod.notify {
+                None
+            } else {
+                Some(pod.id)
+            }
+        });
+        let mut notify_eps: Vec<NewEpisode> = new_eps
+            .iter()
+            .filter(|ep| !muted.contains(&ep.pod_id))
+            .cloned()
+            .collect();
+        let muted_added = added - notify_eps.len();
+        let added = notify_eps.len();
+
+        // after syncing all podcasts, results are collected in a
+        // single digest rather than a string of notifications
+        let rereleased_msg = match rereleased.len() {
+            0 => String::new(),
+            1 => " 1 downloaded episode was re-released.".to_string(),
+            n => format!(" {n} downloaded episodes were re-released."),
+        };
+        let failed = std::mem::take(&mut self.sync_failed);
+        log::info!(
+            "Sync complete: added {}, updated {updated}, failed {}",
+            added + muted_added,
+            failed.len()
+        );
+        if let Some(notice) = Notice::new_episodes(&notify_eps) {
+            webhooks::fire(&self.config.webhooks, notice, self.tx_to_main.clone());
+        }
+        let failed_titles: Vec<String> = failed.iter().map(|(_, title)| title.clone()).collect();
+        if let Some(notice) = Notice::sync_failed(&failed_titles) {
+            webhooks::fire(&self.config.webhooks, notice, self.tx_to_main.clone());
+        }
+        let digest = self.config.sync_digest && self.sync_all;
+        if digest && (!notify_eps.is_empty() || !failed.is_empty()) {
+            self.tx_to_ui
+                .send(MainMessage::UiSpawnDigestPopup(notify_eps.clone(), failed))
+                .expect("Thread messaging error");
+            if !rereleased.is_empty() {
+                self.notif_to_ui(rereleased_msg.trim_start().to_string(), false);
+            }
+        } else {
+            // without the digest, feeds that failed while syncing all
+            // podcasts have not been mentioned yet
+            let failed_msg = match failed.len() {
+                _ if !self.sync_all => String::new(),
+                0 => String::new(),
+                1 => " 1 feed could not be synced.".to_string(),
+                n => format!(" {n} feeds could not be synced."),
+            };
+            let added_msg = match muted_added {
+                0 => format!("{added}"),
+                _ => format!("{added} (+{muted_added} muted)"),
+            };
+            self.notif_to_ui(
+                format!("Sync complete: Added {added_msg}, updated {updated} episodes.{rereleased_msg}{failed_msg}"),
+                !failed_msg.is_empty(),
+            );
+        }
+        self.sync_all = false;
+
+        // only episodes that pass their podcast's download rules, and
+        // that are not older than the configured age limit, are
+        // downloaded automatically, or offered for download
+        let rules: AHashMap<i64, DownloadRules> = self
+            .podcasts
+            .map(|pod| (pod.id, pod.download_rules()), false)
+            .into_iter()
+            .collect();
+        let max_age = self.config.new_episode_max_age;
+        let now = Utc::now();
+        let allowed = |ep: &NewEpisode| {
+            return recent_enough(ep.pubdate, now, max_age)
+                && rules.get(&ep.pod_id).is_none_or(|r| r.allows(&ep.title));
+        };
+        new_eps.retain(allowed);
+        notify_eps.retain(allowed);
+
+        // podcasts can limit how many of their newest episodes are
+        // downloaded automatically, or offered for download
+        let limits: AHashMap<i64, i64> = self
+            .podcasts
+            .filter_map(|pod| pod.overrides.auto_download.map(|num| (pod.id, num)))
+            .into_iter()
+            .collect();
+        if !limits.is_empty() {
+            let mut newest = new_eps.clone();
+            newest.sort_by_key(|ep| std::cmp::Reverse(ep.pubdate));
+            let mut counts: AHashMap<i64, i64> = AHashMap::new();
+            let within_limit: HashSet<i64> = newest
+                .iter()
+                .filter(|ep| {
+                    let count = counts.entry(ep.pod_id).or_insert(0);
+                    *count += 1;
+                    return limits.get(&ep.pod_id).is_none_or(|limit| *count <= *limit);
+                })
+                .map(|ep| ep.id)
+                .collect();
+            new_eps.retain(|ep| within_limit.contains(&ep.id));
+            notify_eps.retain(|ep| within_limit.contains(&ep.id));
+        }
+
+        // downloaded episodes that were re-released have already been
+        // chosen once, so they skip the rules and limits above
+        let rereleased = self.rereleased_episodes(&rereleased);
+        notify_eps.extend(
+            rereleased
+                .iter()
+                .filter(|ep| !muted.contains(&ep.pod_id))
+                .cloned(),
+        );
+        new_eps.extend(rereleased);
+
+        // deal with new episodes once syncing is complete, based on
+        // user preferences
+        match self.config.download_new_episodes {
+            DownloadNewEpisodes::Always => {
+                for ep in new_eps.into_iter() {
+                    self.download(ep.pod_id, Some(ep.id));
+                }
+            }
+            DownloadNewEpisodes::AskSelected if !notify_eps.is_empty() => {
+                self.tx_to_ui
+                    .send(MainMessage::UiSpawnDownloadPopup(notify_eps, true))
+                    .expect("Thread messaging error");
+            }
+            DownloadNewEpisodes::AskUnselected if !notify_eps.is_empty() => {
+                self.tx_to_ui
+                    .send(MainMessage::UiSpawnDownloadPopup(notify_eps, false))
+                    .expect("Thread messaging error");
+            }
+            _ => (),
+        }
+    }
+
+    /// Looks up episodes that were re-released in their feed, to be
+    /// offered for download again alongside new episodes.
+    fn rereleased_episodes(&self, ep_ids: &[i64]) -> Vec<NewEpisode> {
+        let mut episodes = Vec::new();
+        let borrowed_map = self.podcasts.borrow_map();
+        for podcast in borrowed_map.values() {
+            for ep_id in ep_ids.iter() {
+                let new_ep = podcast.episodes.map_single(*ep_id, |ep| NewEpisode {
+                    id: ep.id,
+                    pod_id: ep.pod_id,
+                    title: format!("{} (updated)", ep.title),
+                    pod_title: podcast.title.clone(),
+                    pubdate: ep.pubdate,
+                    selected: false,
+                });
+                if let Some(new_ep) = new_ep {
+                    episodes.push(new_ep);
+                }
+            }
+        }
+        return episodes;
+    }
+
+    /// Gets the chapters for an episode, from the chapters file listed
+    /// in the feed or the downloaded file, unless they have already been
+    /// requested.
+    pub fn fetch_chapters(&mut self, pod_id: i64, ep_id: i64) {
+        if !self.chapter_tracker.insert(ep_id) {
+            return;
+        }
+        if let Some(episode) = self.podcasts.clone_episode(pod_id, ep_id) {
+            chapters::fetch_chapters(
+                pod_id,
+                ep_id,
+                episode.chapters_url,
+                // chapters can only be read from files stored locally
+                episode.path.filter(|_| self.storage.is_local()),
+                self.config.max_retries,
+                &self.threadpool,
+                self.tx_to_main.clone(),
+            );
+        }
+    }
+
+    /// Stores the chapters found for an episode, and shows them in the
+    /// details panel.
+    pub fn save_chapters(&mut self, pod_id: i64, ep_id: i64, chapters: Vec<Chapter>) {
+        if self.db.set_chapters(ep_id, &chapters).is_err() {
+            self.notif_to_ui("Could not save chapters to database.".to_string(), true);
+            return;
+        }
+        if let Some(podcast) = self.podcasts.clone_podcast(pod_id) {
+            if let Some(mut episode) = podcast.episodes.clone_episode(ep_id) {
+                episode.chapters = chapters;
+                podcast.episodes.replace(ep_id, episode);
+            }
+        }
+        self.tx_to_ui
+            .send(MainMessage::UiUpdateDetails)
+            .expect("Thread messaging error");
+    }
+
+    /// Asks mpv to jump to a chapter of the episode that is playing.
+    pub fn jump_to_chapter(&mut self, ep_id: i64, start: f64) {
+        let socket = match &self.config.mpv_socket {
+            Some(socket) => socket,
+            None => {
+                self.notif_to_ui(
+                    "Set mpv_socket in the config file to jump to chapters.".to_string(),
+                    true,
+                );
+                return;
+            }
+        };
+        if self.playing_ep != Some(ep_id) {
+            self.notif_to_ui(
+                "Play this episode first to jump to its chapters.".to_string(),
+                true,
+            );
+            return;
+        }
+        let command = serde_json::json!(["seek", start, "absolute"]);
+        if let Err(err) = play_file::mpv_command(socket, command) {
+            self.notif_to_ui(format!("Error: {err}"), true);
+        }
+    }
+
+    /// Downloads the transcript listed in the feed for an episode.
+    /// Transcripts are not stored, so they are fetched again each time
+    /// they are opened.
+    pub fn fetch_transcript(&mut self, pod_id: i64, ep_id: i64) {
+        let episode = match self.podcasts.clone_episode(pod_id, ep_id) {
+            Some(episode) => episode,
+            None => return,
+        };
+        match episode.transcript_url {
+            Some(url) => {
+                self.notif_to_ui("Loading transcript...".to_string(), false);
+                transcript::fetch_transcript(
+                    pod_id,
+                    ep_id,
+                    url,
+                    episode.transcript_type,
+                    self.config.max_retries,
+                    &self.threadpool,
+                    self.tx_to_main.clone(),
+                );
+            }
+            None => self.notif_to_ui("This episode has no transcript.".to_string(), true),
+        }
+    }
+
+    /// Starts checking for a newer version of shellcaster in the
+    /// background, unless this is turned off or was done recently.
+    pub fn check_for_updates(&self) {
+        if !self.config.check_for_updates {
+            return;
+        }
+        let due = self.db.last_update_check().is_none_or(|last| {
+            Utc::now().timestamp() - last.timestamp() >= crate::config::UPDATE_CHECK_INTERVAL
+        });
+        if !due || self.db.set_update_checked().is_err() {
+            return;
+        }
+        if let Ok(current) = Version::parse(env!("CARGO_PKG_VERSION")) {
+            updates::spawn_check(current, self.tx_to_main.clone());
+        }
+    }
+
+    /// Shows what has changed if shellcaster was upgraded since it was
+    /// last run. Nothing is shown on the first run, or if the version
+    /// that was last run is not known.
+    pub fn show_whats_new(&self) {
+        let current = env!("CARGO_PKG_VERSION");
+        let last_run = self.db.last_run_version();
+        if last_run.as_deref() == Some(current) || self.db.set_last_run_version(current).is_err() {
+            return;
+        }
+        let (previous, current) = match (
+            last_run.map(|v| Version::parse(&v)),
+            Version::parse(current),
+        ) {
+            (Some(Ok(previous)), Ok(current)) if previous < current => (previous, current),
+            _ => return,
+        };
+        let entries = changelog::changes_since(&previous, &current);
+        if !entries.is_empty() {
+            self.tx_to_ui
+                .send(MainMessage::UiSpawnWhatsNew(current.to_string(), entries))
+                .expect("Thread messaging error");
+        }
+    }
+
+    /// Lets the user know that a newer version is available, once for
+    /// each new version.
+    pub fn update_available(&self, version: Version) {
+        let version = version.to_string();
+        if self.db.update_notified().as_deref() == Some(version.as_str()) {
+            return;
+        }
+        if self.db.set_update_notified(&version).is_ok() {
+            self.notif_to_ui(
+                format!(
+                    "Shellcaster {version} is available (you have {}).",
+                    env!("CARGO_PKG_VERSION")
+                ),
+                false,
+            );
+        }
+    }
+
+    /// Gathers statistics about the library and shows them in a popup
+    /// window.
+    pub fn show_stats(&self) {
+        match self.db.get_stats() {
+            Ok(stats) => self
+                .tx_to_ui
+                .send(MainMessage::UiSpawnStats(stats))
+                .expect("Thread messaging error"),
+            Err(_) => self.notif_to_ui("Could not gather statistics.".to_string(), true),
+        }
+    }
+
+    /// Shows the most recent lines of the log file.
+    pub fn show_log(&self) {
+        if self.config.log_level == LevelFilter::Off {
+            self.notif_to_ui("Logging is turned off.".to_string(), false);
+            return;
+        }
+        let path = &self.config.log_file;
+        match logging::read_recent(path, logging::LOG_LINES_SHOWN) {
+            Ok(lines) if lines.is_empty() => {
+                self.notif_to_ui("The log is empty.".to_string(), false)
+            }
+            Ok(lines) => self
+                .tx_to_ui
+                .send(MainMessage::UiSpawnLog(path.to_string_lossy().to_string(), lines))
+                .expect("Thread messaging error"),
+            Err(err) => self.notif_to_ui(format!("{err}"), true),
+        }
+    }
+
+    /// Opens a transcript that has been fetched in the transcript
+    /// viewer.
+    pub fn show_transcript(&mut self, pod_id: i64, ep_id: i64, lines: Vec<TranscriptLine>) {
+        if lines.is_empty() {
+            self.notif_to_ui("Transcript is empty.".to_string(), true);
+            return;
+        }
+        let title = match self.podcasts.clone_episode(pod_id, ep_id) {
+            Some(episode) => episode.title,
+            None => return,
+        };
+        self.tx_to_ui
+            .send(MainMessage::UiSpawnTranscript(title, lines))
+            .expect("Thread messaging error");
+    }
+
+    /// Attempts to execute the play command on the given podcast
+    /// episode. Episodes that have since been removed (e.g., while
+    /// waiting in the play queue) are not played.
+    pub fn play_file(&mut self, pod_id: i64, ep_id: i64) {
+        let mut episode = match self.podcasts.clone_episode(pod_id, ep_id) {
+            Some(episode) => episode,
+            None => {
+                self.notif_to_ui("Can't play an episode that has been removed.".to_string(), true);
+                return;
+            }
+        };
+        if let (None, Some(err)) = (&episode.path, &episode.enclosure_error) {
+            self.notif_to_ui(format!("Can't play {}: {err}.", episode.title), true);
+            return;
+        }
+        // the UI is given over to a player that runs in the terminal
+        // until it exits, so another one waits its turn (the last one
+        // asked for wins) rather than blocking until then
+        if self.config.play_in_terminal && self.terminal_busy.load(Ordering::SeqCst) {
+            self.waiting_play = Some((pod_id, ep_id));
+            return;
+        }
+        self.mark_played(pod_id, ep_id, true);
+        let now = chrono::Utc::now().timestamp();
+        episode.last_played = Some(now);
+        let _ = self.db.set_last_played(ep_id, now);
+        // playing an episode takes it out of the inbox
+        if episode.new && self.db.set_new(ep_id, false).is_ok() {
+            episode.new = false;
+        }
+        if let Some(episodes) = self.podcasts.map_single(pod_id, |pod| pod.episodes.clone()) {
+            episodes.replace(ep_id, episode.clone());
+        }
+        let pod_title = self
+            .podcasts
+            .map_single(pod_id, |pod| pod.title.clone())
+            .unwrap_or_default();
+        self.now_playing = Some(NowPlaying {
+            podcast: pod_title.clone(),
+            episode: episode.title.clone(),
+            started: chrono::Utc::now().timestamp(),
+            duration: episode.duration,
+        });
+        self.playing_ep = Some(ep_id);
+        #[cfg(feature = "mpris")]
+        self.update_mpris(pod_id, &episode);
+
+        // podcasts can have a play command of their own
+        let play_command = self
+            .podcasts
+            .map_single(pod_id, |pod| pod.overrides.play_command.clone())
+            .flatten()
+            .unwrap_or_else(|| self.config.play_command.clone());
+
+        // with an mpv socket, mpv can be told to jump to chapters later,
+        // and asked how far into the episode it is; an episode left
+        // partway through starts where it was left. Other players would
+        // not know the option, and may refuse to start.
+        let mpv_socket = self
+            .config
+            .mpv_socket
+            .clone()
+            .filter(|_| play_file::is_mpv(&play_command));
+        let mut start = None;
+        let extra_args = match &mpv_socket {
+            Some(socket) => {
+                let mut args = vec![format!("--input-ipc-server={}", socket.to_string_lossy())];
+                if episode.is_in_progress() {
+                    start = episode.position;
+                    args.push(format!("--start={}", start.unwrap_or(0)));
+                }
+                args
+            }
+            None => Vec::new(),
+        };
+
+        // a player that runs in the terminal has it to itself until it
+        // exits
+        let in_terminal = self.config.play_in_terminal;
+        let hand_over = if in_terminal {
+            self.hand_over_terminal()
+        } else {
+            None
+        };
+
+        let player = match &episode.path {
+            // if there is a downloaded file, try to play that
+            Some(path) => match self.storage.location(path) {
+                Some(p) => match play_file::execute(&play_command, &p, &extra_args, in_terminal) {
+                    Ok(player) => Some(player),
+                    Err(err) => {
+                        self.notif_to_ui(format!("Error: Could not play file. {err}"), true);
+                        None
+                    }
+                },
+                None => {
+                    self.notif_to_ui("Error: Filepath is not valid Unicode.".to_string(), true);
+                    None
+                }
+            },
+            // otherwise, try to stream the URL
+            None => match play_file::execute(
+                &play_command,
+                &episode.url,
+                &extra_args,
+                in_terminal,
+            ) {
+                Ok(player) => Some(player),
+                Err(err) => {
+                    self.notif_to_ui(format!("Error: Could not stream URL. {err}"), true);
+                    None
+                }
+            },
+        };
+
+        if let Some(player) = player {
+            self.update_now_playing(start);
+            // if the player could not be started, the UI has already
+            // taken the terminal back
+            if hand_over.is_some() {
+                self.terminal_busy.store(true, Ordering::SeqCst);
+            }
+            let terminal_busy = self.terminal_busy.clone();
+
+            // the post-play hook runs once the player exits
+            let hook = self.config.post_play_command.clone().map(|command| {
+                (command, HookData {
+                    ep_title: episode.title.clone(),
+                    pod_title: pod_title,
+                    url: episode.url.clone(),
+                    file_path: episode.path.clone(),
+                })
+            });
+            let tx_to_main = self.tx_to_main.clone();
+            play_file::watch_player(
+                player,
+                mpv_socket,
+                pod_id,
+                ep_id,
+                self.tx_to_main.clone(),
+                move |failure| {
+                    // the UI takes the terminal back, and shows the
+                    // player's output for a moment if it failed
+                    match hand_over {
+                        Some(done) => {
+                            let _ = done.send(failure);
+                            terminal_busy.store(false, Ordering::SeqCst);
+                            let _ = tx_to_main.send(Message::Player(PlayerMsg::TerminalReturned));
+                        }
+                        None => {
+                            if let Some(failure) = failure {
+                                let _ = tx_to_main
+                                    .send(Message::Player(PlayerMsg::Failed(failure)));
+                            }
+                        }
+                    }
+                    if let Some((command, data)) = hook {
+                        hooks::run_hook(HookEvent::Play, command, data, tx_to_main);
+                    }
+                },
+            );
+        }
+    }
+
+    /// Asks the UI to give the terminal to a player that runs in it, and
+    /// waits until it has. The UI takes the terminal back once something
+    /// is sent on the returned channel, or it is dropped. This is only
+    /// called while no other player has the terminal (see
+    /// `terminal_busy`), as the UI would not answer until it was back.
+    fn hand_over_terminal(&self) -> Option<mpsc::Sender<Option<String>>> {
+        let (tx_ready, rx_ready) = mpsc::channel();
+        let (tx_done, rx_done) = mpsc::channel();
+        self.tx_to_ui
+            .send(MainMessage::UiHandOverTerminal(tx_ready, rx_done))
+            .ok()?;
+        rx_ready.recv().ok()?;
+        return Some(tx_done);
+    }
+
+    /// Shows the episode that is playing in desktop media widgets. The
+    /// cached cover art is used if there is one, and the image URL
+    /// otherwise.
+    #[cfg(feature = "mpris")]
+    fn update_mpris(&self, pod_id: i64, episode: &Episode) {
+        let mpris = match &self.mpris {
+            Some(mpris) => mpris,
+            None => return,
+        };
+        let (pod_title, pod_image) = self
+            .podcasts
+            .map_single(pod_id, |pod| (pod.title.clone(), pod.image_url.clone()))
+            .unwrap_or_default();
+        let art_url = episode.image_url.clone().or(pod_image).map(|url| {
+            if let Some(cache_dir) = &self.config.image_cache {
+                let path = cover_art::cache_path(cache_dir, &url);
+                if path.exists() {
+                    return format!("file://{}", path.to_string_lossy());
+                }
+            }
+            url
+        });
+        let track = TrackInfo {
+            ep_id: episode.id,
+            title: episode.title.clone(),
+            podcast: pod_title,
+            art_url: art_url,
+            duration: episode.duration,
+        };
+        if mpris.set_track(track).is_err() {
+            self.notif_to_ui("Could not update media controls.".to_string(), true);
+        }
+    }
+
+    /// Given a podcast and episode, it marks the given episode as
+    /// played/unplayed, sending this info to the database and updating
+    /// in self.podcasts
+    pub fn mark_played(&self, pod_id: i64, ep_id: i64, played: bool) {
+        let podcast = self.podcasts.clone_podcast(pod_id).unwrap();
+
+        // TODO: Try to find a way to do this without having
+        // to clone the episode...
+        let mut episode = podcast.episodes.clone_episode(ep_id).unwrap();
+        episode.played = played;
+
+        let _ = self.db.set_played_status(episode.id, played);
+        podcast.episodes.replace(ep_id, episode);
+
+        self.podcasts.replace(pod_id, podcast);
+        self.update_filters(self.filters, true);
+    }
+
+    /// Deals with episodes from the inbox (or in triage mode): adds them
+    /// to the play queue, downloads them, marks them as played, hides
+    /// them, or just marks them as seen. Either way, they are taken out
+    /// of the inbox.
+    pub fn triage(&mut self, action: TriageAction, episodes: Vec<(i64, i64)>) {
+        for &(pod_id, ep_id) in episodes.iter() {
+            if let Some(mut episode) = self.podcasts.clone_episode(pod_id, ep_id) {
+                if episode.new && self.db.set_new(ep_id, false).is_ok() {
+                    episode.new = false;
+                    if let Some(episodes) =
+                        self.podcasts.map_single(pod_id, |pod| pod.episodes.clone())
+                    {
+                        episodes.replace(ep_id, episode);
+                    }
+                }
+            }
+        }
+
+        match action {
+            TriageAction::Queue => {
+                let mut added = 0;
+                for ep in episodes.into_iter() {
+                    if !self.queue.contains(&ep) {
+                        self.queue.push(ep);
+                        added += 1;
+                    }
+                }
+                self.queue_to_ui();
+                let message = match added {
+                    0 => "Already in the queue.".to_string(),
+                    1 => "Added 1 episode to the queue.".to_string(),
+                    n => format!("Added {n} episodes to the queue."),
+                };
+                self.notif_to_ui(message, false);
+            }
+            TriageAction::QueueNext => {
+                let placed = queue::insert_next(&mut self.queue, episodes, self.playing_ep);
+                self.queue_to_ui();
+                let message = match placed {
+                    0 => "Already playing.".to_string(),
+                    1 => "Playing 1 episode next.".to_string(),
+                    n => format!("Playing {n} episodes next."),
+                };
+                self.notif_to_ui(message, false);
+            }
+            TriageAction::Download => {
+                for (pod_id, ep_id) in episodes.into_iter() {
+                    self.download(pod_id, Some(ep_id));
+                }
+            }
+            TriageAction::Hide => {
+                for (pod_id, ep_id) in episodes.into_iter() {
+                    self.remove_episode(pod_id, ep_id, false);
+                }
+            }
+            TriageAction::Played => {
+                for &(pod_id, ep_id) in episodes.iter() {
+                    self.set_position(pod_id, ep_id, None);
+                }
+                // this updates the filters as well
+                self.mark_played_multi(episodes, true);
+                return;
+            }
+            TriageAction::Seen => (),
+        }
+        self.update_filters(self.filters, true);
+    }
+
+    /// Sends the episodes in the play queue to the UI, to be shown in a
+    /// popup window. Episodes that no longer exist are dropped from the
+    /// queue.
+    pub fn show_queue(&mut self) {
+        let podcasts = &self.podcasts;
+        self.queue.retain(|&(pod_id, ep_id)| podcasts.clone_episode(pod_id, ep_id).is_some());
+        self.queue_to_ui();
+        let episodes = self
+            .queue
+            .iter()
+            .filter_map(|&(pod_id, ep_id)| {
+                self.podcasts.map_single(pod_id, |pod| {
+                    pod.episodes
+                        .map_single(ep_id, |ep| ListedEpisode::new(pod, ep))
+                })?
+            })
+            .collect();
+        self.tx_to_ui
+            .send(MainMessage::UiSpawnQueue(episodes))
+            .expect("Thread messaging error");
+    }
+
+    /// Replaces the play queue with the episodes as reordered (or
+    /// removed) in the queue window. Episodes that have left the queue
+    /// in the meantime (e.g., because they finished playing) are not
+    /// put back.
+    pub fn set_queue(&mut self, episodes: Vec<(i64, i64)>) {
+        self.queue = episodes
+            .into_iter()
+            .filter(|ep| self.queue.contains(ep))
+            .collect();
+        self.queue_to_ui();
+    }
+
+    /// Shuffles the play queue, or (if `shuffle` is false) reorders it
+    /// so that podcasts take turns.
+    pub fn rearrange_queue(&mut self, shuffle: bool) {
+        if self.queue.len() < 2 {
+            self.notif_to_ui("Not enough episodes in the queue.".to_string(), false);
+            return;
+        }
+        let message = if shuffle {
+            queue::shuffle(&mut self.queue);
+            "Shuffled the queue."
+        } else {
+            self.queue = queue::interleave(std::mem::take(&mut self.queue), |ep| ep.0);
+            "Podcasts now take turns in the queue."
+        };
+        self.queue_to_ui();
+        self.notif_to_ui(message.to_string(), false);
+    }
+
+    /// Adds the `count` newest unplayed episodes to the end of the play
+    /// queue, from podcasts that are not archived. Episodes that can't
+    /// be played (e.g., because their feed's link is broken) are left
+    /// out.
+    pub fn fill_queue(&mut self, count: usize) {
+        let candidates = self
+            .podcasts
+            .filter_map(|pod| {
+                (!pod.archived).then(|| {
+                    pod.episodes.filter_map(|ep| {
+                        let playable = ep.path.is_some() || ep.enclosure_error.is_none();
+                        (!ep.played && playable).then_some((pod.id, ep.id, ep.pubdate))
+                    })
+                })
+            })
+            .concat();
+        let added = queue::fill(&mut self.queue, candidates, count);
+        self.queue_to_ui();
+        let message = match added {
+            0 => "No unplayed episodes to add to the queue.".to_string(),
+            1 => "Added 1 episode to the queue.".to_string(),
+            n => format!("Added {n} episodes to the queue."),
+        };
+        self.notif_to_ui(message, false);
+    }
+
+    /// Saves the play queue, so that it is kept when shellcaster is
+    /// restarted, and lets the UI know which episodes are in it, so they
+    /// can be marked in the episode menu.
+    fn queue_to_ui(&self) {
+        if let Err(err) = self.db.set_queue(&self.queue) {
+            log::error!("Could not save the play queue: {err}");
+        }
+        let episodes = self.queue.iter().map(|&(_, ep_id)| ep_id).collect();
+        self.tx_to_ui
+            .send(MainMessage::UiUpdateQueue(episodes))
+            .expect("Thread messaging error");
+    }
+
+    /// Takes the episodes for which `removed` returns true (given the
+    /// podcast and episode IDs) out of the play queue, e.g., because
+    /// they are no longer in the list.
+    fn drop_from_queue(&mut self, removed: impl Fn(i64, i64) -> bool) {
+        let queued = self.queue.len();
+        self.queue.retain(|&(pod_id, ep_id)| !removed(pod_id, ep_id));
+        if self.queue.len() != queued {
+            self.queue_to_ui();
+        }
+    }
+
+    /// Tidies up after an episode has been played to the end. If it was
+    /// in the play queue, it is taken out of the queue (if
+    /// `remove_from_queue` is set), and the next episode in the queue
+    /// is started, after waiting for `queue_gap` seconds. Episodes that
+    /// were removed after being queued are passed over.
+    pub fn episode_finished(&mut self, pod_id: i64, ep_id: i64) {
+        self.set_position(pod_id, ep_id, None);
+        if self
+            .podcasts
+            .clone_episode(pod_id, ep_id)
+            .is_some_and(|ep| !ep.played)
+        {
+            self.mark_played(pod_id, ep_id, true);
+        }
+        self.scrobble(pod_id, ep_id);
+
+        let index = match self.queue.iter().position(|&ep| ep == (pod_id, ep_id)) {
+            Some(index) => index,
+            None => return,
+        };
+        let next_index = if self.config.remove_from_queue {
+            self.queue.remove(index);
+            self.queue_to_ui();
+            index
+        } else {
+            index + 1
+        };
+        let podcasts = &self.podcasts;
+        let next = self
+            .queue
+            .iter()
+            .skip(next_index)
+            .find(|&&(pod_id, ep_id)| podcasts.clone_episode(pod_id, ep_id).is_some())
+            .copied();
+        // nothing more is started if another episode was played while
+        // this one was still going
+        if !self.config.continuous_playback || self.playing_ep != Some(ep_id) {
+            return;
+        }
+        let (next_pod_id, next_ep_id) = match next {
+            Some(next) => next,
+            None => {
+                self.notif_to_ui("Reached the end of the queue.".to_string(), false);
+                return;
+            }
+        };
 
-                if pod_id.is_some() {
-                    self.sync_tracker.push(result);
-                    self.sync_counter -= 1;
-                    self.update_tracker_notif();
-
-                    if self.sync_counter == 0 {
-                        // count up total new episodes and updated
-                        // episodes when sync process is finished
-                        let mut added = 0;
-                        let mut updated = 0;
-                        let mut new_eps = Vec::new();
-                        for res in self.sync_tracker.iter() {
-                            added += res.added.len();
-                            updated += res.updated.len();
-                            new_eps.extend(res.added.clone());
-                        }
-                        self.sync_tracker = Vec::new();
-                        self.notif_to_ui(
-                            format!("Sync complete: Added {added}, updated {updated} episodes."),
-                            false,
-                        );
+        let gap = self.config.queue_gap;
+        if gap == 0 {
+            self.play_file(next_pod_id, next_ep_id);
+            return;
+        }
+        if let Some(title) = self
+            .podcasts
+            .clone_episode(next_pod_id, next_ep_id)
+            .map(|ep| ep.display_title().to_string())
+        {
+            self.notif_to_ui(format!("Playing \"{title}\" in {gap} seconds."), false);
+        }
+        let tx_to_main = self.tx_to_main.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_secs(gap));
+            let _ = tx_to_main.send(Message::Player(PlayerMsg::Next(
+                ep_id,
+                next_pod_id,
+                next_ep_id,
+            )));
+        });
+    }
 
-                        // deal with new episodes once syncing is
-                        // complete, based on user preferences
-                        if !new_eps.is_empty() {
-                            match self.config.download_new_episodes {
-                                DownloadNewEpisodes::Always => {
-                                    for ep in new_eps.into_iter() {
-                                        self.download(ep.pod_id, Some(ep.id));
-                                    }
-                                }
-                                DownloadNewEpisodes::AskSelected => {
-                                    self.tx_to_ui
-                                        .send(MainMessage::UiSpawnDownloadPopup(new_eps, true))
-                                        .expect("Thread messaging error");
-                                }
-                                DownloadNewEpisodes::AskUnselected => {
-                                    self.tx_to_ui
-                                        .send(MainMessage::UiSpawnDownloadPopup(new_eps, false))
-                                        .expect("Thread messaging error");
-                                }
-                                _ => (),
-                            }
-                        }
-                    }
-                } else {
-                    self.notif_to_ui(
-                        format!("Successfully added {} episodes.", result.added.len()),
-                        false,
-                    );
-                }
+    /// Adds an episode that was played to the end to the spool of
+    /// listens for ListenBrainz, if a token is set, and tries to submit
+    /// the spool.
+    fn scrobble(&mut self, pod_id: i64, ep_id: i64) {
+        if self.config.listenbrainz_token.is_none() {
+            return;
+        }
+        let listen = self.podcasts.map_single(pod_id, |pod| {
+            pod.episodes.map_single(ep_id, |ep| {
+                (
+                    ep.last_played.unwrap_or_else(|| Utc::now().timestamp()),
+                    pod.title.clone(),
+                    ep.title.clone(),
+                )
+            })
+        });
+        if let Some(Some((listened_at, podcast, episode))) = listen {
+            if self.db.spool_listen(listened_at, &podcast, &episode).is_err() {
+                self.notif_to_ui("Could not save listen for ListenBrainz.".to_string(), true);
+                return;
             }
-            Err(_err) => self.notif_to_ui(failure, true),
         }
+        self.submit_listens();
     }
 
-    /// Attempts to execute the play command on the given podcast
-    /// episode.
-    pub fn play_file(&self, pod_id: i64, ep_id: i64) {
-        self.mark_played(pod_id, ep_id, true);
-        let episode = self.podcasts.clone_episode(pod_id, ep_id).unwrap();
+    /// Starts submitting the listens waiting in the spool to
+    /// ListenBrainz, unless a submission is already under way.
+    fn submit_listens(&mut self) {
+        let token = match &self.config.listenbrainz_token {
+            Some(token) => token.clone(),
+            None => return,
+        };
+        if self.scrobbling {
+            return;
+        }
+        let listens = match self.db.spooled_listens(scrobble::MAX_LISTENS) {
+            Ok(listens) if !listens.is_empty() => listens,
+            _ => return,
+        };
+        self.scrobbling = true;
+        scrobble::spawn_submit(
+            self.config.listenbrainz_url.clone(),
+            token,
+            listens,
+            self.tx_to_main.clone(),
+        );
+    }
 
-        match episode.path {
-            // if there is a local file, try to play that
-            Some(path) => match path.to_str() {
-                Some(p) => {
-                    if play_file::execute(&self.config.play_command, p).is_err() {
-                        self.notif_to_ui(
-                            "Error: Could not play file. Check configuration.".to_string(),
-                            true,
-                        );
-                    }
-                }
-                None => self.notif_to_ui("Error: Filepath is not valid Unicode.".to_string(), true),
-            },
-            // otherwise, try to stream the URL
-            None => {
-                if play_file::execute(&self.config.play_command, &episode.url).is_err() {
-                    self.notif_to_ui("Error: Could not stream URL.".to_string(), true);
+    /// Takes submitted listens out of the spool and carries on with any
+    /// that are left. Listens that could not be sent stay in the spool
+    /// until the next attempt, but those the server refused are dropped,
+    /// so that they do not hold up the ones after them.
+    fn listens_submitted(&mut self, msg: ScrobbleMsg) {
+        self.scrobbling = false;
+        match msg {
+            ScrobbleMsg::Submitted(ids) => {
+                log::info!("Submitted {} listens to ListenBrainz", ids.len());
+                if self.db.unspool_listens(&ids).is_ok() && ids.len() == scrobble::MAX_LISTENS {
+                    self.submit_listens();
                 }
             }
+            ScrobbleMsg::Offline => {
+                log::warn!("Could not reach ListenBrainz; listens are kept to send later")
+            }
+            ScrobbleMsg::Unauthorized(err) => self.notif_to_ui(err, true),
+            ScrobbleMsg::Rejected(ids, err) => {
+                log::warn!("Dropped {} listens refused by ListenBrainz: {err}", ids.len());
+                let _ = self.db.unspool_listens(&ids);
+                self.notif_to_ui(format!("{err} (listens dropped)"), true);
+            }
         }
     }
 
-    /// Given a podcast and episode, it marks the given episode as
-    /// played/unplayed, sending this info to the database and updating
-    /// in self.podcasts
-    pub fn mark_played(&self, pod_id: i64, ep_id: i64, played: bool) {
-        let podcast = self.podcasts.clone_podcast(pod_id).unwrap();
-
-        // TODO: Try to find a way to do this without having
-        // to clone the episode...
-        let mut episode = podcast.episodes.clone_episode(ep_id).unwrap();
-        episode.played = played;
+    /// Saves how far into an episode playback has got, or clears it
+    /// once the episode has been played to the end.
+    pub fn set_position(&self, pod_id: i64, ep_id: i64, position: Option<i64>) {
+        let mut episode = match self.podcasts.clone_episode(pod_id, ep_id) {
+            Some(episode) => episode,
+            None => return,
+        };
+        if episode.position == position {
+            return;
+        }
+        episode.position = position;
+        if self.db.set_position(ep_id, position).is_err() {
+            return;
+        }
+        if let Some(episodes) = self.podcasts.map_single(pod_id, |pod| pod.episodes.clone()) {
+            episodes.replace(ep_id, episode);
+        }
+    }
 
-        let _ = self.db.set_played_status(episode.id, played);
-        podcast.episodes.replace(ep_id, episode);
+    /// Stars or unstars an episode (the opposite of its current status).
+    pub fn toggle_star(&self, pod_id: i64, ep_id: i64) {
+        let mut episode = match self.podcasts.clone_episode(pod_id, ep_id) {
+            Some(episode) => episode,
+            None => return,
+        };
+        episode.starred = !episode.starred;
+        if self.db.set_starred(ep_id, episode.starred).is_err() {
+            self.notif_to_ui("Could not update episode in database".to_string(), true);
+            return;
+        }
+        if let Some(episodes) = self.podcasts.map_single(pod_id, |pod| pod.episodes.clone()) {
+            episodes.replace(ep_id, episode);
+        }
+        self.update_filters(self.filters, true);
+    }
 
-        self.podcasts.replace(pod_id, podcast);
+    /// Given a list of podcasts and episodes, it marks all of the given
+    /// episodes as played/unplayed, sending this info to the database
+    /// and updating in self.podcasts. Episodes of podcasts that have
+    /// since been removed are skipped.
+    pub fn mark_played_multi(&self, episodes: Vec<(i64, i64)>, played: bool) {
+        let mut by_podcast: HashMap<i64, Vec<i64>> = HashMap::new();
+        for (pod_id, ep_id) in episodes.into_iter() {
+            by_podcast.entry(pod_id).or_default().push(ep_id);
+        }
+        for (pod_id, ep_ids) in by_podcast.into_iter() {
+            let podcast = match self.podcasts.clone_podcast(pod_id) {
+                Some(podcast) => podcast,
+                None => continue,
+            };
+            for ep_id in ep_ids.into_iter() {
+                if let Some(mut episode) = podcast.episodes.clone_episode(ep_id) {
+                    if self.db.set_played_status(ep_id, played).is_ok() {
+                        episode.played = played;
+                        podcast.episodes.replace(ep_id, episode);
+                    }
+                }
+            }
+            self.podcasts.replace(pod_id, podcast);
+        }
         self.update_filters(self.filters, true);
     }
 
@@ -468,22 +2440,296 @@ impl MainController {
                 .get_episodes(podcast.id, false)
                 .expect("Error retrieving info from database."),
         );
+        self.config.title_rules.clean_podcast(&podcast);
+
+        self.podcasts.replace(pod_id, podcast);
+        self.update_filters(self.filters, true);
+    }
+
+    /// Marks every unplayed episode published before `cutoff` as
+    /// played, in the given podcast or, if there is none, in all
+    /// podcasts.
+    pub fn mark_older_played(&self, pod_id: Option<i64>, cutoff: DateTime<Utc>) {
+        let pod_ids = match pod_id {
+            Some(pod_id) => vec![pod_id],
+            None => self.podcasts.map(|pod| pod.id, false),
+        };
+        let mut episodes = Vec::new();
+        for pod_id in pod_ids.into_iter() {
+            if let Some(podcast) = self.podcasts.clone_podcast(pod_id) {
+                episodes.extend(podcast.episodes.filter_map(|ep| {
+                    let older = ep.pubdate.is_some_and(|date| date < cutoff);
+                    (!ep.played && older).then_some((pod_id, ep.id))
+                }));
+            }
+        }
+
+        let message = match episodes.len() {
+            0 => "No unplayed episodes are that old.".to_string(),
+            1 => "Marked 1 episode as played.".to_string(),
+            n => format!("Marked {n} episodes as played."),
+        };
+        if !episodes.is_empty() {
+            self.mark_played_multi(episodes, true);
+        }
+        self.notif_to_ui(message, false);
+    }
+
+    /// Marks the episodes of a newly added podcast as played, except
+    /// for the ones the user wants to start off with.
+    pub fn mark_new_podcast(&self, pod_id: i64, choice: NewPodcastEpisodes) {
+        match choice {
+            NewPodcastEpisodes::AllPlayed => self.mark_all_played(pod_id, true),
+            NewPodcastEpisodes::LatestUnplayed => {
+                self.mark_all_played(pod_id, true);
+                let latest = self
+                    .podcasts
+                    .map_single(pod_id, |pod| pod.episodes.ids_newest_first().first().copied())
+                    .flatten();
+                if let Some(ep_id) = latest {
+                    self.mark_played(pod_id, ep_id, false);
+                }
+            }
+            NewPodcastEpisodes::Ask | NewPodcastEpisodes::AllUnplayed => (),
+        }
+    }
+
+    /// Turns new-episode notifications on or off for a podcast.
+    pub fn toggle_notify(&self, pod_id: i64) {
+        let mut podcast = match self.podcasts.clone_podcast(pod_id) {
+            Some(podcast) => podcast,
+            None => return,
+        };
+        podcast.notify = !podcast.notify;
+        if self.db.set_notify(pod_id, podcast.notify).is_err() {
+            self.notif_to_ui("Could not update podcast in database".to_string(), true);
+            return;
+        }
+        let message = if podcast.notify {
+            format!("Notifications on for {}", podcast.display_title())
+        } else {
+            format!("Notifications off for {}", podcast.display_title())
+        };
+        self.podcasts.replace(pod_id, podcast);
+        self.notif_to_ui(message, false);
+    }
+
+    /// Changes the patterns deciding which new episodes of a podcast are
+    /// downloaded automatically.
+    pub fn set_download_rules(
+        &self,
+        pod_id: i64,
+        include: Option<String>,
+        exclude: Option<String>,
+    ) {
+        let mut podcast = match self.podcasts.clone_podcast(pod_id) {
+            Some(podcast) => podcast,
+            None => return,
+        };
+        if self
+            .db
+            .set_download_rules(pod_id, include.as_deref(), exclude.as_deref())
+            .is_err()
+        {
+            self.notif_to_ui("Could not update podcast in database".to_string(), true);
+            return;
+        }
+        podcast.include_pattern = include;
+        podcast.exclude_pattern = exclude;
+        let message = format!("Download rules saved for {}", podcast.display_title());
+        self.podcasts.replace(pod_id, podcast);
+        self.notif_to_ui(message, false);
+    }
+
+    /// Updates the patterns deciding which episodes of a podcast are
+    /// shown.
+    pub fn set_skip_rules(&self, pod_id: i64, show: Option<String>, hide: Option<String>) {
+        let mut podcast = match self.podcasts.clone_podcast(pod_id) {
+            Some(podcast) => podcast,
+            None => return,
+        };
+        if self
+            .db
+            .set_skip_rules(pod_id, show.as_deref(), hide.as_deref())
+            .is_err()
+        {
+            self.notif_to_ui("Could not update podcast in database".to_string(), true);
+            return;
+        }
+        podcast.show_pattern = show;
+        podcast.hide_pattern = hide;
+        let message = format!("Skip rules saved for {}", podcast.display_title());
+        self.podcasts.replace(pod_id, podcast);
+        self.update_filters(self.filters, true);
+        self.notif_to_ui(message, false);
+    }
+
+    /// Updates the rule that cleans up the episode titles of a podcast.
+    pub fn set_title_rule(&self, pod_id: i64, pattern: Option<String>, replacement: Option<String>) {
+        let mut podcast = match self.podcasts.clone_podcast(pod_id) {
+            Some(podcast) => podcast,
+            None => return,
+        };
+        if self
+            .db
+            .set_title_rule(pod_id, pattern.as_deref(), replacement.as_deref())
+            .is_err()
+        {
+            self.notif_to_ui("Could not update podcast in database".to_string(), true);
+            return;
+        }
+        podcast.title_pattern = pattern;
+        podcast.title_replacement = replacement;
+        self.config.title_rules.clean_podcast(&podcast);
+        let message = format!("Title cleanup saved for {}", podcast.display_title());
+        self.podcasts.replace(pod_id, podcast);
+        self.update_filters(self.filters, true);
+        self.notif_to_ui(message, false);
+    }
+
+    /// Updates the settings of a podcast that override the global
+    /// configuration.
+    pub fn set_overrides(&self, pod_id: i64, overrides: PodcastOverrides) {
+        let mut podcast = match self.podcasts.clone_podcast(pod_id) {
+            Some(podcast) => podcast,
+            None => return,
+        };
+        if self.db.set_overrides(pod_id, &overrides).is_err() {
+            self.notif_to_ui("Could not update podcast in database".to_string(), true);
+            return;
+        }
+        podcast.overrides = overrides;
+        let message = format!("Settings saved for {}", podcast.display_title());
+        self.podcasts.replace(pod_id, podcast);
+        self.notif_to_ui(message, false);
+        self.prune_downloads(pod_id);
+    }
+
+    /// Archives a podcast, so that it is no longer synced along with
+    /// the others, or brings it back.
+    pub fn set_archived(&self, pod_id: i64, archived: bool) {
+        let mut podcast = match self.podcasts.clone_podcast(pod_id) {
+            Some(podcast) => podcast,
+            None => return,
+        };
+        if self.db.set_archived(pod_id, archived).is_err() {
+            self.notif_to_ui("Could not update podcast in database".to_string(), true);
+            return;
+        }
+        podcast.archived = archived;
+        let message = if archived {
+            format!("Archived {}", podcast.display_title())
+        } else {
+            format!("Unarchived {}", podcast.display_title())
+        };
+        self.podcasts.replace(pod_id, podcast);
+        self.update_filters(self.filters, true);
+        self.notif_to_ui(message, false);
+    }
+
+    /// Gives a podcast a title of the user's choosing, or goes back to
+    /// the title from its feed. The podcast list is reloaded so that it
+    /// is sorted by the new title.
+    pub fn set_custom_title(&self, pod_id: i64, title: Option<String>) {
+        let feed_title = match self.podcasts.map_single(pod_id, |pod| pod.title.clone()) {
+            Some(title) => title,
+            None => return,
+        };
+        if self.db.set_custom_title(pod_id, title.as_deref()).is_err() {
+            self.notif_to_ui("Could not update podcast in database".to_string(), true);
+            return;
+        }
+        self.reload_podcasts();
+        self.update_filters(self.filters, true);
+        let message = match title {
+            Some(title) => format!("Renamed {feed_title} to {title}"),
+            None => format!("{feed_title} uses the title from its feed again"),
+        };
+        self.notif_to_ui(message, false);
+    }
 
+    /// Puts a podcast in a group in the podcast menu, or takes it out of
+    /// the one it was put in. The podcast list is sorted again so that
+    /// the podcast is shown with the rest of its group.
+    pub fn set_group(&self, pod_id: i64, group: Option<String>) {
+        let mut podcast = match self.podcasts.clone_podcast(pod_id) {
+            Some(podcast) => podcast,
+            None => return,
+        };
+        // a group that other podcasts are in already keeps its spelling
+        let mut known: Vec<String> = self
+            .podcasts
+            .map(|pod| (pod.id != pod_id).then(|| pod.group_name(&self.config.groups)), false)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .collect();
+        known.extend(self.config.groups.iter().map(|group| group.name.clone()));
+        let group = group.and_then(|name| normalize_group(&name, known.iter().map(String::as_str)));
+        if self.db.set_group(pod_id, group.as_deref()).is_err() {
+            self.notif_to_ui("Could not update podcast in database".to_string(), true);
+            return;
+        }
+        let message = match &group {
+            Some(group) => format!("Moved {} to {group}", podcast.display_title()),
+            None => format!("Took {} out of its group", podcast.display_title()),
+        };
+        podcast.group = group;
         self.podcasts.replace(pod_id, podcast);
         self.update_filters(self.filters, true);
+        self.notif_to_ui(message, false);
+    }
+
+    /// Updates the credentials and extra headers used for a private
+    /// podcast feed. Returns whether they were saved.
+    pub fn set_auth(&self, pod_id: i64, auth: FeedAuth) -> bool {
+        let mut podcast = match self.podcasts.clone_podcast(pod_id) {
+            Some(podcast) => podcast,
+            None => return false,
+        };
+        if self.db.set_auth(pod_id, &auth).is_err() {
+            self.notif_to_ui("Could not update podcast in database".to_string(), true);
+            return false;
+        }
+        let title = podcast.display_title().to_string();
+        let was_insecure = podcast.auth.accept_invalid_certs;
+        let (message, warning) = match (was_insecure, auth.accept_invalid_certs) {
+            (false, true) => (
+                format!("TLS certificates are no longer checked for {title}. Only do this on networks you trust."),
+                true,
+            ),
+            (true, false) => (format!("TLS certificates are checked again for {title}."), false),
+            _ => (format!("Login saved for {title}"), false),
+        };
+        podcast.auth = auth;
+        self.podcasts.replace(pod_id, podcast);
+        self.notif_to_ui(message, warning);
+        return true;
     }
 
     /// Given a podcast index (and not an episode index), this will send
     /// a vector of jobs to the threadpool to download all episodes in
     /// the podcast. If given an episode index as well, it will download
-    /// just that episode.
+    /// just that episode. Episodes offered in several versions are
+    /// downloaded in the one the podcast (or config.toml) prefers.
     pub fn download(&mut self, pod_id: i64, ep_id: Option<i64>) {
         let pod_title;
+        let download_dir;
+        let auth;
+        let preference;
+        let mut problem = None;
         let mut ep_data = Vec::new();
         {
             let borrowed_map = self.podcasts.borrow_map();
             let podcast = borrowed_map.get(&pod_id).unwrap();
             pod_title = podcast.title.clone();
+            download_dir = podcast.overrides.download_dir.clone();
+            auth = podcast.auth.clone();
+            preference = podcast
+                .overrides
+                .preferred_enclosure
+                .clone()
+                .or_else(|| self.config.preferred_enclosure.clone());
 
             // if we are selecting one specific episode, just grab that
             // one; otherwise, loop through them all
@@ -493,34 +2739,44 @@ impl MainController {
                     let data = podcast
                         .episodes
                         .map_single(ep_id, |ep| {
+                            if let Some(err) = &ep.enclosure_error {
+                                problem = Some(format!("Can't download {}: {err}.", ep.title));
+                            }
                             (
                                 EpData {
                                     id: ep.id,
                                     pod_id: ep.pod_id,
-                                    title: ep.title.clone(),
-                                    url: ep.url.clone(),
+                                    title: ep.display_title().to_string(),
+                                    url: ep.download_url(preference.as_deref()),
                                     pubdate: ep.pubdate,
                                     file_path: None,
+                                    auth: auth.clone(),
                                 },
-                                ep.path.is_none(),
+                                ep.path.is_none() || ep.updated,
                             )
                         })
                         .unwrap();
-                    if data.1 {
+                    if data.1 && problem.is_none() {
                         ep_data.push(data.0);
                     }
                 }
                 None => {
-                    // grab just the relevant data we need
+                    // grab just the relevant data we need, leaving out
+                    // episodes hidden by the podcast's skip rules
+                    let skip_rules = podcast.skip_rules();
                     ep_data = podcast.episodes.filter_map(|ep| {
-                        if ep.path.is_none() {
+                        if (ep.path.is_none() || ep.updated)
+                            && ep.enclosure_error.is_none()
+                            && skip_rules.allows(&ep.title)
+                        {
                             Some(EpData {
                                 id: ep.id,
                                 pod_id: ep.pod_id,
-                                title: ep.title.clone(),
-                                url: ep.url.clone(),
+                                title: ep.display_title().to_string(),
+                                url: ep.download_url(preference.as_deref()),
                                 pubdate: ep.pubdate,
                                 file_path: None,
+                                auth: auth.clone(),
                             })
                         } else {
                             None
@@ -530,18 +2786,55 @@ impl MainController {
             }
         }
 
+        if let Some(problem) = problem {
+            self.notif_to_ui(problem, true);
+        }
+        self.start_downloads(&pod_title, download_dir.as_deref(), ep_data);
+    }
+
+    /// Downloads the version of an episode at position `index` in the
+    /// list of versions its feed offers, as chosen by the user. If the
+    /// episode was already downloaded, the new file takes the place of
+    /// the old one.
+    pub fn download_enclosure(&mut self, pod_id: i64, ep_id: i64, index: usize) {
+        let podcast = match self.podcasts.clone_podcast(pod_id) {
+            Some(podcast) => podcast,
+            None => return,
+        };
+        let data = podcast.episodes.map_single(ep_id, |ep| {
+            ep.enclosures.get(index).map(|enc| EpData {
+                id: ep.id,
+                pod_id: ep.pod_id,
+                title: ep.display_title().to_string(),
+                url: enc.url.clone(),
+                pubdate: ep.pubdate,
+                file_path: None,
+                auth: podcast.auth.clone(),
+            })
+        });
+        if let Some(Some(data)) = data {
+            let download_dir = podcast.overrides.download_dir.clone();
+            self.start_downloads(&podcast.title, download_dir.as_deref(), vec![data]);
+        }
+    }
+
+    /// Sends a list of episodes of one podcast to the threadpool to be
+    /// downloaded, into the podcast's folder in `download_dir` (or the
+    /// default download path).
+    fn start_downloads(
+        &mut self,
+        pod_title: &str,
+        download_dir: Option<&str>,
+        mut ep_data: Vec<EpData>,
+    ) {
         // check against episodes currently being downloaded -- so we
         // don't needlessly download them again
         ep_data.retain(|ep| !self.download_tracker.contains(&ep.id));
 
         if !ep_data.is_empty() {
             // add directory for podcast, create if it does not exist
-            let dir_name = sanitize_with_options(&pod_title, Options {
-                truncate: true,
-                windows: true, // for simplicity, we'll just use Windows-friendly paths for everyone
-                replacement: "",
-            });
-            match self.create_podcast_dir(dir_name) {
+            let dir_name = self.config.sanitizer.sanitize(pod_title);
+            match self.create_podcast_dir(dir_name, download_dir) {
                 Ok(path) => {
                     for ep in ep_data.iter() {
                         self.download_tracker.insert(ep.id);
@@ -550,7 +2843,10 @@ impl MainController {
                         ep_data,
                         &path,
                         self.config.max_retries,
-                        &self.threadpool,
+                        self.config.sanitizer,
+                        self.storage.clone(),
+                        &self.download_pool,
+                        self.download_cancel.clone(),
                         self.tx_to_main.clone(),
                     );
                 }
@@ -563,7 +2859,16 @@ impl MainController {
     /// Handles logic for what to do when a download successfully completes.
     pub fn download_complete(&mut self, ep_data: EpData) {
         let file_path = ep_data.file_path.unwrap();
-        let res = self.db.insert_file(ep_data.id, &file_path);
+        log::info!("Downloaded {} to {}", ep_data.url, file_path.to_string_lossy());
+
+        self.download_tracker.remove(&ep_data.id);
+        self.update_tracker_notif();
+        // the podcast may have been removed while this was downloading
+        let podcast = match self.podcasts.clone_podcast(ep_data.pod_id) {
+            Some(podcast) => podcast,
+            None => return,
+        };
+        let res = downloads::finish(&self.db, &*self.storage, &podcast, ep_data.id, &file_path);
         if res.is_err() {
             self.notif_to_ui(
                 format!(
@@ -574,32 +2879,87 @@ impl MainController {
             );
             return;
         }
-        {
-            // TODO: Try to do this without cloning the podcast...
-            let podcast = self.podcasts.clone_podcast(ep_data.pod_id).unwrap();
-            let mut episode = podcast.episodes.clone_episode(ep_data.id).unwrap();
-            episode.path = Some(file_path);
-            podcast.episodes.replace(ep_data.id, episode);
+        if let Some(command) = &self.config.post_download_command {
+            hooks::run_hook(
+                HookEvent::Download,
+                command.clone(),
+                HookData {
+                    ep_title: ep_data.title.clone(),
+                    pod_title: podcast.title.clone(),
+                    url: ep_data.url.clone(),
+                    file_path: Some(file_path),
+                },
+                self.tx_to_main.clone(),
+            );
         }
+        webhooks::fire(
+            &self.config.webhooks,
+            Notice::download(&podcast.title, &ep_data.title),
+            self.tx_to_main.clone(),
+        );
 
-        self.download_tracker.remove(&ep_data.id);
-        self.update_tracker_notif();
+        // the downloaded file may have chapters of its own
+        self.chapter_tracker.remove(&ep_data.id);
         if self.download_tracker.is_empty() {
             self.notif_to_ui("Downloads complete.".to_string(), false);
         }
 
+        self.prune_downloads(ep_data.pod_id);
         self.update_filters(self.filters, true);
     }
 
-    /// Given a podcast title, creates a download directory for that
-    /// podcast if it does not already exist.
-    pub fn create_podcast_dir(&self, pod_title: String) -> Result<PathBuf, std::io::Error> {
-        let mut download_path = self.config.download_path.clone();
-        download_path.push(pod_title);
-        return match std::fs::create_dir_all(&download_path) {
-            Ok(_) => Ok(download_path),
-            Err(err) => Err(err),
+    /// Finds the episodes to download across the whole library (see
+    /// `downloads::library_episodes()`), leaving out any that are being
+    /// downloaded already, and asks the user whether to download them.
+    pub fn ask_download_library(&self) {
+        let smart_filter = self
+            .filters
+            .smart
+            .and_then(|idx| self.config.smart_filters.get(idx))
+            .map(|filter| &filter.expr);
+        let mut episodes = {
+            let (pod_map, pod_order, _unused) = self.podcasts.borrow();
+            drop(_unused);
+            downloads::library_episodes(
+                pod_order.iter().filter_map(|id| pod_map.get(id)),
+                &self.filters,
+                smart_filter,
+            )
         };
+        episodes.retain(|ep| !self.download_tracker.contains(&ep.id));
+        if episodes.is_empty() {
+            self.notif_to_ui("No episodes to download.".to_string(), false);
+            return;
+        }
+        let description = downloads::describe_batch(&episodes);
+        let episodes = episodes.iter().map(|ep| (ep.pod_id, ep.id)).collect();
+        self.tx_to_ui
+            .send(MainMessage::UiAskDownloadLibrary(episodes, description))
+            .expect("Thread messaging error");
+    }
+
+    /// Deletes the oldest downloaded episodes of a podcast once there
+    /// are more than it is set to keep. The episode that is playing is
+    /// never deleted.
+    pub fn prune_downloads(&self, pod_id: i64) {
+        let podcast = match self.podcasts.clone_podcast(pod_id) {
+            Some(podcast) => podcast,
+            None => return,
+        };
+        let removed = downloads::prune(&self.db, &*self.storage, &podcast, self.playing_ep);
+        if removed > 0 {
+            let message = format!("Deleted {removed} old downloads of {}", podcast.display_title());
+            self.podcasts.replace(pod_id, podcast);
+            self.update_filters(self.filters, true);
+            self.notif_to_ui(message, false);
+        }
+    }
+
+    /// Given a podcast title, creates a download directory for that
+    /// podcast if it does not already exist. If the podcast has a
+    /// download directory of its own, that is used instead.
+    pub fn create_podcast_dir(&self, pod_title: String, custom_dir: Option<&str>) -> Result<PathBuf> {
+        return self.storage.create_dir(&pod_title, custom_dir);
     }
     pub fn unmark_downloaded(&self, pod_id: i64, ep_id: i64) -> Result<()> {
         let podcast = self.podcasts.clone_podcast(pod_id).unwrap();
@@ -617,15 +2977,22 @@ impl MainController {
     
 
     /// Deletes a downloaded file for an episode from the user's local
-    /// system.
+    /// system. Episodes that have since been removed are skipped.
     pub fn delete_file(&self, pod_id: i64, ep_id: i64) {
-        let borrowed_map = self.podcasts.borrow_map();
-        let podcast = borrowed_map.get(&pod_id).unwrap();
+        // the podcast map can't stay locked, as updating the filters
+        // below locks it again
+        let podcast = match self.podcasts.clone_podcast(pod_id) {
+            Some(podcast) => podcast,
+            None => return,
+        };
 
-        let mut episode = podcast.episodes.clone_episode(ep_id).unwrap();
+        let mut episode = match podcast.episodes.clone_episode(ep_id) {
+            Some(episode) => episode,
+            None => return,
+        };
         if episode.path.is_some() {
             let title = episode.title.clone();
-            match fs::remove_file(episode.path.unwrap()) {
+            match self.storage.discard(&episode.path.unwrap()) {
                 Ok(_) => {
                     let res = self.db.remove_file(episode.id);
                     if res.is_err() {
@@ -641,11 +3008,22 @@ impl MainController {
                     self.update_filters(self.filters, true);
                     self.notif_to_ui(format!("Deleted \"{title}\""), false);
                 }
-                Err(_) => self.notif_to_ui(format!("Error deleting \"{title}\""), true),
+                Err(err) => {
+                    log::warn!("{err:#}");
+                    self.notif_to_ui(format!("Error deleting \"{title}\""), true);
+                }
             }
         }
     }
 
+    /// Deletes the downloaded files for a list of podcasts and episodes
+    /// (e.g., those marked in the episode menu).
+    pub fn delete_multi(&self, episodes: Vec<(i64, i64)>) {
+        for (pod_id, ep_id) in episodes.into_iter() {
+            self.delete_file(pod_id, ep_id);
+        }
+    }
+
     /// Deletes all downloaded files for a given podcast from the user's
     /// local system.
     pub fn delete_files(&self, pod_id: i64) {
@@ -659,13 +3037,16 @@ impl MainController {
             for (_, ep) in borrowed_ep_map.iter_mut() {
                 if ep.path.is_some() {
                     let mut episode = ep.clone();
-                    match fs::remove_file(episode.path.unwrap()) {
+                    match self.storage.discard(&episode.path.unwrap()) {
                         Ok(_) => {
                             eps_to_remove.push(episode.id);
                             episode.path = None;
                             *ep = episode;
                         }
-                        Err(_) => success = false,
+                        Err(err) => {
+                            log::warn!("{err:#}");
+                            success = false;
+                        }
                     }
                 }
             }
@@ -697,13 +3078,8 @@ impl MainController {
             self.notif_to_ui("Could not remove podcast from database".to_string(), true);
             return;
         }
-        {
-            self.podcasts.replace_all(
-                self.db
-                    .get_podcasts()
-                    .expect("Error retrieving info from database."),
-            );
-        }
+        self.reload_podcasts();
+        self.drop_from_queue(|id, _| id == pod_id);
         self.tx_to_ui
             .send(MainMessage::UiUpdateMenus)
             .expect("Thread messaging error");
@@ -711,7 +3087,7 @@ impl MainController {
 
     /// Removes an episode from the list, optionally deleting local files
     /// first
-    pub fn remove_episode(&self, pod_id: i64, ep_id: i64, delete_files: bool) {
+    pub fn remove_episode(&mut self, pod_id: i64, ep_id: i64, delete_files: bool) {
         if delete_files {
             self.delete_file(pod_id, ep_id);
         }
@@ -725,7 +3101,9 @@ impl MainController {
                     .get_episodes(pod_id, false)
                     .expect("Error retrieving info from database."),
             );
+            self.config.title_rules.clean_podcast(podcast);
         }
+        self.drop_from_queue(|id, episode_id| (id, episode_id) == (pod_id, ep_id));
         self.tx_to_ui
             .send(MainMessage::UiUpdateMenus)
             .expect("Thread messaging error");
@@ -733,7 +3111,7 @@ impl MainController {
 
     /// Removes all episodes for a podcast from the list, optionally
     /// deleting local files first
-    pub fn remove_all_episodes(&self, pod_id: i64, delete_files: bool) {
+    pub fn remove_all_episodes(&mut self, pod_id: i64, delete_files: bool) {
         if delete_files {
             self.delete_files(pod_id);
         }
@@ -747,6 +3125,7 @@ impl MainController {
         );
         podcast.episodes = LockVec::new(Vec::new());
         self.podcasts.replace(pod_id, podcast);
+        self.drop_from_queue(|id, _| id == pod_id);
 
         self.tx_to_ui
             .send(MainMessage::UiUpdateMenus)
@@ -756,23 +3135,24 @@ impl MainController {
     /// Updates the user-selected filters to show only played/unplayed
     /// or downloaded/not downloaded episodes.
     pub fn update_filters(&self, filters: Filters, update_menus: bool) {
+        let sorts = self.sorts;
+        let smart_filter = filters
+            .smart
+            .and_then(|idx| self.config.smart_filters.get(idx))
+            .map(|filter| &filter.expr);
         {
             let (pod_map, pod_order, mut pod_filtered_order) = self.podcasts.borrow();
             let mut new_filtered_pods = Vec::new();
             for pod_id in pod_order.iter() {
                 let pod = pod_map.get(pod_id).unwrap();
+                let skip_rules = pod.skip_rules();
+                let tags = pod.tags();
                 let new_filter = pod.episodes.filter_map(|ep| {
-                    let play_filter = match filters.played {
-                        FilterStatus::All => false,
-                        FilterStatus::PositiveCases => !ep.is_played(),
-                        FilterStatus::NegativeCases => ep.is_played(),
-                    };
-                    let download_filter = match filters.downloaded {
-                        FilterStatus::All => false,
-                        FilterStatus::PositiveCases => ep.path.is_none(),
-                        FilterStatus::NegativeCases => ep.path.is_some(),
-                    };
-                    if !(play_filter | download_filter) {
+                    if !skip_rules.allows(&ep.title) {
+                        return None;
+                    }
+                    let smart = smart_filter.is_none_or(|expr| expr.matches(ep, &tags));
+                    if filters.matches(ep) && smart {
                         return Some(ep.id);
                     } else {
                         return None;
@@ -781,11 +3161,18 @@ impl MainController {
                 if !new_filter.is_empty() {
                     new_filtered_pods.push(pod.id);
                 }
-                let mut filtered_order = pod.episodes.borrow_filtered_order();
-                *filtered_order = new_filter;
+                *pod.episodes.borrow_filtered_order() = new_filter;
+                pod.episodes
+                    .sort_filtered_by(|a, b| sorts.episodes.compare_episodes(a, b));
             }
             *pod_filtered_order = new_filtered_pods;
         }
+        // podcasts are kept together with the rest of their group
+        let groups = &self.config.groups;
+        self.podcasts.sort_filtered_by(|a, b| {
+            compare_groups(a.group_name(groups).as_deref(), b.group_name(groups).as_deref())
+                .then_with(|| sorts.podcasts.compare_podcasts(a, b))
+        });
         if update_menus {
             self.tx_to_ui
                 .send(MainMessage::UiUpdateMenus)
@@ -793,3 +3180,238 @@ impl MainController {
         }
     }
 }
+
+/// Works out whether automatic database maintenance should run, given
+/// when it last ran (if ever) and how many days it is set to wait.
+fn maintenance_due(last: Option<DateTime<Utc>>, now: DateTime<Utc>, days: i64) -> bool {
+    return match last {
+        Some(last) => {
+            let elapsed = now.timestamp().saturating_sub(last.timestamp());
+            elapsed >= days.saturating_mul(86_400)
+        }
+        None => true,
+    };
+}
+
+/// Works out whether a new episode is recent enough to be downloaded
+/// automatically, given the age limit in days, if there is one.
+/// Episodes without a publication date always are.
+fn recent_enough(pubdate: Option<DateTime<Utc>>, now: DateTime<Utc>, max_age: Option<i64>) -> bool {
+    return match (pubdate, max_age) {
+        (Some(pubdate), Some(days)) => {
+            let cutoff = now.timestamp().saturating_sub(days.saturating_mul(86_400));
+            pubdate.timestamp() >= cutoff
+        }
+        _ => true,
+    };
+}
+
+
+// TESTS -----------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    /// Sets up a main controller without a UI thread, around a new
+    /// database (in a folder of its own under the temp folder) holding
+    /// one podcast with episodes of the given titles. Messages meant for
+    /// the UI are sent to the receiver that is returned.
+    fn test_controller(
+        name: &str,
+        titles: &[&str],
+    ) -> (PathBuf, MainController, mpsc::Receiver<MainMessage>) {
+        let dir = std::env::temp_dir().join(format!("shellcaster-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let db = Database::connect(&dir).unwrap();
+        let episodes = titles
+            .iter()
+            .map(|title| EpisodeNoId {
+                title: title.to_string(),
+                url: format!("https://example.com/{title}.mp3"),
+                guid: title.to_string(),
+                description: String::new(),
+                pubdate: None,
+                duration: None,
+                image_url: None,
+                chapters_url: None,
+                transcript_url: None,
+                transcript_type: None,
+                enclosures: Vec::new(),
+                enclosure_error: None,
+            })
+            .collect();
+        db.insert_podcast(PodcastNoId {
+            title: "Podcast".to_string(),
+            url: "https://example.com/feed.xml".to_string(),
+            description: None,
+            author: None,
+            explicit: None,
+            last_checked: Utc::now(),
+            image_url: None,
+            categories: Vec::new(),
+            language: None,
+            website: None,
+            funding_url: None,
+            funding_text: None,
+            validators: FeedValidators::default(),
+            schedule: FeedSchedule::default(),
+            episodes: episodes,
+        })
+        .unwrap();
+
+        let mut config = Config::new(Path::new("/nonexistent/config.toml")).unwrap();
+        config.download_path = dir.join("downloads");
+        let (tx_to_ui, rx_from_main) = mpsc::channel();
+        let (tx_to_main, rx_to_main) = mpsc::channel();
+        let feed_pool = TaskPool::new(1);
+        let ctrl = MainController {
+            threadpool: Threadpool::new(1),
+            download_pool: feed_pool.with_limit(1),
+            feed_pool: feed_pool,
+            podcasts: LockVec::new(db.get_podcasts().unwrap()),
+            filters: Filters::default(),
+            sorts: config.sorts,
+            storage: storage::from_config(&config.download_path, None, None),
+            config: config,
+            config_path: dir.join("config.toml"),
+            db: db,
+            ui_thread: std::thread::spawn(|| Ok(())),
+            sync_counter: 0,
+            sync_tracker: Vec::new(),
+            sync_all: false,
+            sync_failed: Vec::new(),
+            check_counter: 0,
+            check_report: Vec::new(),
+            sync_cancel: CancelToken::new(),
+            download_tracker: HashSet::new(),
+            download_cancel: CancelToken::new(),
+            image_tracker: HashSet::new(),
+            chapter_tracker: HashSet::new(),
+            playing_ep: None,
+            terminal_busy: Arc::new(AtomicBool::new(false)),
+            waiting_play: None,
+            queue: Vec::new(),
+            scrobbling: false,
+            now_playing: None,
+            now_playing_writer: None,
+            now_playing_text: None,
+            last_status: None,
+            #[cfg(feature = "mpris")]
+            mpris: None,
+            remote_socket: None,
+            adding_feeds: HashSet::new(),
+            changing_urls: HashSet::new(),
+            import_settings: HashMap::new(),
+            tx_to_ui: tx_to_ui,
+            tx_to_main: tx_to_main,
+            rx_to_main: rx_to_main,
+        };
+        return (dir, ctrl, rx_from_main);
+    }
+
+    /// Returns the IDs of the test podcast and of its episode with the
+    /// given title.
+    fn ids(ctrl: &MainController, title: &str) -> (i64, i64) {
+        let pod_id = ctrl.podcasts.borrow_order()[0];
+        let ep_id = ctrl
+            .db
+            .get_episodes(pod_id, true)
+            .unwrap()
+            .iter()
+            .find(|ep| ep.title == title)
+            .unwrap()
+            .id;
+        return (pod_id, ep_id);
+    }
+
+    /// Returns the notifications that have been sent to the UI.
+    fn notifs(rx: &mpsc::Receiver<MainMessage>) -> Vec<String> {
+        return rx
+            .try_iter()
+            .filter_map(|msg| match msg {
+                MainMessage::UiSpawnNotif(message, ..) => Some(message),
+                _ => None,
+            })
+            .collect();
+    }
+
+    #[test]
+    fn maintenance_interval() {
+        let last = Utc.ymd(2022, 4, 1).and_hms(12, 0, 0);
+        let now = Utc.ymd(2022, 5, 1).and_hms(12, 0, 0);
+        assert!(maintenance_due(None, now, 30));
+        assert!(maintenance_due(Some(last), now, 30));
+        assert!(!maintenance_due(Some(last), now, 31));
+        assert!(!maintenance_due(Some(last), now, i64::MAX));
+    }
+
+    #[test]
+    fn new_episode_age_limit() {
+        let now = Utc.ymd(2022, 5, 1).and_hms(12, 0, 0);
+        let old = Utc.ymd(2022, 4, 1).and_hms(12, 0, 0);
+        assert!(recent_enough(Some(old), now, None));
+        assert!(recent_enough(Some(old), now, Some(30)));
+        assert!(!recent_enough(Some(old), now, Some(29)));
+        assert!(recent_enough(None, now, Some(1)));
+        assert!(recent_enough(Some(old), now, Some(i64::MAX)));
+    }
+
+    #[test]
+    fn queue_skips_removed_episodes() {
+        let (dir, mut ctrl, rx) = test_controller("queue-removed", &["First", "Second"]);
+        let first = ids(&ctrl, "First");
+        let second = ids(&ctrl, "Second");
+        ctrl.queue = vec![first, second];
+        ctrl.remove_episode(second.0, second.1, false);
+        ctrl.playing_ep = Some(first.1);
+        ctrl.episode_finished(first.0, first.1);
+        assert!(notifs(&rx).contains(&"Reached the end of the queue.".to_string()));
+
+        // the player may have been waiting to start it
+        ctrl.play_file(second.0, second.1);
+        assert_eq!(notifs(&rx), vec!["Can't play an episode that has been removed."]);
+        assert_eq!(ctrl.playing_ep, Some(first.1));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn removed_episodes_leave_queue() {
+        let (dir, mut ctrl, rx) = test_controller("queue-pruned", &["First", "Second", "Third"]);
+        let first = ids(&ctrl, "First");
+        let second = ids(&ctrl, "Second");
+        let third = ids(&ctrl, "Third");
+        ctrl.queue = vec![first, second, third];
+        ctrl.queue_to_ui();
+
+        ctrl.triage(TriageAction::Hide, vec![second]);
+        assert_eq!(ctrl.queue, vec![first, third]);
+        assert_eq!(ctrl.db.get_queue().unwrap(), vec![first, third]);
+        let shown = [first.1, third.1];
+        assert!(rx
+            .try_iter()
+            .any(|msg| matches!(msg, MainMessage::UiUpdateQueue(eps) if eps == shown)));
+
+        ctrl.remove_all_episodes(first.0, false);
+        assert!(ctrl.queue.is_empty());
+        assert!(ctrl.db.get_queue().unwrap().is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn delete_multi_skips_missing() {
+        let (dir, ctrl, rx) = test_controller("delete-multi", &["First"]);
+        let first = ids(&ctrl, "First");
+        let path = ctrl.config.download_path.join("first.mp3");
+        fs::create_dir_all(&ctrl.config.download_path).unwrap();
+        fs::write(&path, b"audio").unwrap();
+        ctrl.db.insert_file(first.1, &path).unwrap();
+        ctrl.reload_podcasts();
+
+        ctrl.delete_multi(vec![(first.0, first.1 + 100), first, (first.0 + 100, 1)]);
+        assert!(!path.exists());
+        assert_eq!(ctrl.podcasts.clone_episode(first.0, first.1).unwrap().path, None);
+        assert_eq!(notifs(&rx), vec!["Deleted \"First\""]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
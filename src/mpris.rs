@@ -0,0 +1,293 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use serde_json::json;
+use zbus::blocking::{Connection, ConnectionBuilder};
+use zbus::zvariant::{ObjectPath, OwnedValue, Value};
+use zbus::{block_on, dbus_interface, fdo};
+
+use crate::play_file;
+
+/// Bus name used for the MPRIS interface. If another instance of
+/// shellcaster already has it, the process id is added to the end.
+const BUS_NAME: &str = "org.mpris.MediaPlayer2.shellcaster";
+
+/// Object path of the MPRIS interface, as required by the spec.
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+
+/// Information about the episode that is playing, which is shown by
+/// desktop media widgets.
+#[derive(Debug, Clone)]
+pub struct TrackInfo {
+    pub ep_id: i64,
+    pub title: String,
+    pub podcast: String,
+    pub art_url: Option<String>,
+    pub duration: Option<i64>,
+}
+
+/// Connection to the session D-Bus, serving the MPRIS interface for as
+/// long as it is kept around.
+pub struct Mpris {
+    connection: Connection,
+}
+
+impl Mpris {
+    /// Connects to the session D-Bus and starts serving the MPRIS
+    /// interface. `socket` is the mpv IPC socket used to control
+    /// playback; without it, only the current episode is shown.
+    pub fn new(socket: Option<PathBuf>) -> Result<Self> {
+        let build = |name: String| -> zbus::Result<Connection> {
+            return ConnectionBuilder::session()?
+                .name(name)?
+                .serve_at(OBJECT_PATH, Root)?
+                .serve_at(OBJECT_PATH, Player {
+                    socket: socket.clone(),
+                    track: None,
+                })?
+                .build();
+        };
+        let connection = build(BUS_NAME.to_string())
+            .or_else(|_| build(format!("{BUS_NAME}.instance{}", std::process::id())))
+            .with_context(|| "Could not connect to D-Bus")?;
+        return Ok(Self {
+            connection: connection,
+        });
+    }
+
+    /// Changes the episode shown as playing, and lets MPRIS clients
+    /// know about the change.
+    pub fn set_track(&self, track: TrackInfo) -> Result<()> {
+        let iface = self
+            .connection
+            .object_server()
+            .interface::<_, Player>(OBJECT_PATH)?;
+        iface.get_mut().track = Some(track);
+        let player = iface.get();
+        block_on(player.metadata_changed(iface.signal_context()))?;
+        block_on(player.playback_status_changed(iface.signal_context()))?;
+        return Ok(());
+    }
+}
+
+
+/// The org.mpris.MediaPlayer2 interface, which describes the player
+/// itself.
+struct Root;
+
+#[dbus_interface(name = "org.mpris.MediaPlayer2")]
+impl Root {
+    /// Bringing the terminal to the front is not possible.
+    fn raise(&self) {}
+
+    /// Quitting from outside the app is not supported.
+    fn quit(&self) {}
+
+    #[dbus_interface(property)]
+    fn can_quit(&self) -> bool {
+        return false;
+    }
+
+    #[dbus_interface(property)]
+    fn can_raise(&self) -> bool {
+        return false;
+    }
+
+    #[dbus_interface(property)]
+    fn has_track_list(&self) -> bool {
+        return false;
+    }
+
+    #[dbus_interface(property)]
+    fn identity(&self) -> String {
+        return "Shellcaster".to_string();
+    }
+
+    #[dbus_interface(property)]
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        return Vec::new();
+    }
+
+    #[dbus_interface(property)]
+    fn supported_mime_types(&self) -> Vec<String> {
+        return Vec::new();
+    }
+}
+
+
+/// The org.mpris.MediaPlayer2.Player interface, which controls
+/// playback by passing commands on to mpv.
+struct Player {
+    socket: Option<PathBuf>,
+    track: Option<TrackInfo>,
+}
+
+impl Player {
+    /// Sends a command to mpv, converting any error into a D-Bus error.
+    fn mpv(&self, command: serde_json::Value) -> fdo::Result<serde_json::Value> {
+        let socket = self.socket.as_ref().ok_or_else(|| {
+            fdo::Error::NotSupported("Set mpv_socket to control playback".to_string())
+        })?;
+        return play_file::mpv_command(socket, command)
+            .map_err(|err| fdo::Error::Failed(err.to_string()));
+    }
+}
+
+#[dbus_interface(name = "org.mpris.MediaPlayer2.Player")]
+impl Player {
+    fn play_pause(&self) -> fdo::Result<()> {
+        self.mpv(json!(["cycle", "pause"]))?;
+        return Ok(());
+    }
+
+    fn play(&self) -> fdo::Result<()> {
+        self.mpv(json!(["set_property", "pause", false]))?;
+        return Ok(());
+    }
+
+    fn pause(&self) -> fdo::Result<()> {
+        self.mpv(json!(["set_property", "pause", true]))?;
+        return Ok(());
+    }
+
+    fn stop(&self) -> fdo::Result<()> {
+        self.mpv(json!(["stop"]))?;
+        return Ok(());
+    }
+
+    /// Skips to the next chapter.
+    fn next(&self) -> fdo::Result<()> {
+        self.mpv(json!(["add", "chapter", 1]))?;
+        return Ok(());
+    }
+
+    /// Goes back to the previous chapter.
+    fn previous(&self) -> fdo::Result<()> {
+        self.mpv(json!(["add", "chapter", -1]))?;
+        return Ok(());
+    }
+
+    /// Seeks forward or backward by `offset` microseconds.
+    fn seek(&self, offset: i64) -> fdo::Result<()> {
+        self.mpv(json!(["seek", offset as f64 / 1e6, "relative"]))?;
+        return Ok(());
+    }
+
+    /// Seeks to `position` microseconds from the start of the episode.
+    fn set_position(&self, _track_id: ObjectPath<'_>, position: i64) -> fdo::Result<()> {
+        self.mpv(json!(["seek", position as f64 / 1e6, "absolute"]))?;
+        return Ok(());
+    }
+
+    /// Opening other files is not supported.
+    fn open_uri(&self, _uri: String) {}
+
+    /// Asks mpv whether it is paused; if mpv can't be reached, nothing
+    /// is playing.
+    #[dbus_interface(property)]
+    fn playback_status(&self) -> String {
+        if self.track.is_none() {
+            return "Stopped".to_string();
+        }
+        return match self.mpv(json!(["get_property", "pause"])) {
+            Ok(serde_json::Value::Bool(true)) => "Paused".to_string(),
+            Ok(_) => "Playing".to_string(),
+            // without a socket, the player is assumed to be playing
+            Err(fdo::Error::NotSupported(_)) => "Playing".to_string(),
+            Err(_) => "Stopped".to_string(),
+        };
+    }
+
+    #[dbus_interface(property)]
+    fn metadata(&self) -> HashMap<String, OwnedValue> {
+        let mut metadata = HashMap::new();
+        if let Some(track) = &self.track {
+            let path = format!("/org/shellcaster/episode/{}", track.ep_id);
+            if let Ok(path) = ObjectPath::try_from(path) {
+                metadata.insert("mpris:trackid".to_string(), Value::from(path).into());
+            }
+            metadata.insert(
+                "xesam:title".to_string(),
+                Value::from(track.title.clone()).into(),
+            );
+            metadata.insert(
+                "xesam:album".to_string(),
+                Value::from(track.podcast.clone()).into(),
+            );
+            metadata.insert(
+                "xesam:artist".to_string(),
+                Value::from(vec![track.podcast.clone()]).into(),
+            );
+            if let Some(url) = &track.art_url {
+                metadata.insert("mpris:artUrl".to_string(), Value::from(url.clone()).into());
+            }
+            if let Some(duration) = track.duration {
+                metadata.insert(
+                    "mpris:length".to_string(),
+                    Value::from(duration * 1_000_000).into(),
+                );
+            }
+        }
+        return metadata;
+    }
+
+    /// Current position in microseconds, as reported by mpv.
+    #[dbus_interface(property)]
+    fn position(&self) -> i64 {
+        return match self.mpv(json!(["get_property", "time-pos"])) {
+            Ok(serde_json::Value::Number(pos)) => (pos.as_f64().unwrap_or(0.0) * 1e6) as i64,
+            _ => 0,
+        };
+    }
+
+    #[dbus_interface(property)]
+    fn rate(&self) -> f64 {
+        return 1.0;
+    }
+
+    #[dbus_interface(property)]
+    fn minimum_rate(&self) -> f64 {
+        return 1.0;
+    }
+
+    #[dbus_interface(property)]
+    fn maximum_rate(&self) -> f64 {
+        return 1.0;
+    }
+
+    #[dbus_interface(property)]
+    fn volume(&self) -> f64 {
+        return 1.0;
+    }
+
+    #[dbus_interface(property)]
+    fn can_go_next(&self) -> bool {
+        return self.socket.is_some();
+    }
+
+    #[dbus_interface(property)]
+    fn can_go_previous(&self) -> bool {
+        return self.socket.is_some();
+    }
+
+    #[dbus_interface(property)]
+    fn can_play(&self) -> bool {
+        return self.socket.is_some();
+    }
+
+    #[dbus_interface(property)]
+    fn can_pause(&self) -> bool {
+        return self.socket.is_some();
+    }
+
+    #[dbus_interface(property)]
+    fn can_seek(&self) -> bool {
+        return self.socket.is_some();
+    }
+
+    #[dbus_interface(property)]
+    fn can_control(&self) -> bool {
+        return self.socket.is_some();
+    }
+}
@@ -0,0 +1,148 @@
+use serde_json::{json, Value};
+
+use crate::db::{Database, SyncPreview};
+use crate::feeds::{FeedCheck, PodcastFeed};
+use crate::types::*;
+
+// Machine-readable output of the subcommands, for the `--json` flag.
+// Dates are given in RFC 3339 format, and missing values as null.
+
+/// Prints a JSON document on its own line of stdout.
+pub fn print(value: &Value) {
+    println!("{value}");
+}
+
+/// Describes a podcast in the library.
+pub fn podcast(pod: &Podcast) -> Value {
+    return json!({
+        "id": pod.id,
+        "title": pod.display_title(),
+        "url": pod.url,
+        "archived": pod.archived,
+        "episodes": pod.episodes.len(false),
+        "unplayed": pod.num_unplayed(),
+        "last_checked": pod.last_checked.to_rfc3339(),
+    });
+}
+
+/// Describes an episode in the library.
+pub fn episode(ep: &Episode) -> Value {
+    return json!({
+        "id": ep.id,
+        "podcast_id": ep.pod_id,
+        "title": ep.display_title(),
+        "url": ep.url,
+        "pubdate": ep.pubdate.map(|date| date.to_rfc3339()),
+        "duration": ep.duration,
+        "played": ep.played,
+        "starred": ep.starred,
+        "file": ep.path.as_ref().map(|path| path.to_string_lossy()),
+    });
+}
+
+/// Describes an episode found by a sync.
+pub fn new_episode(ep: &NewEpisode) -> Value {
+    return json!({
+        "id": ep.id,
+        "podcast_id": ep.pod_id,
+        "podcast": ep.pod_title,
+        "title": ep.title,
+        "pubdate": ep.pubdate.map(|date| date.to_rfc3339()),
+    });
+}
+
+/// Describes a podcast that could not be synced, imported, etc.
+pub fn podcast_error(pod_id: Option<i64>, title: &str, error: &str) -> Value {
+    return json!({ "id": pod_id, "podcast": title, "error": error });
+}
+
+/// Describes an episode that could not be downloaded.
+pub fn download_error(ep_id: i64, title: &str, error: &str) -> Value {
+    return json!({ "id": ep_id, "title": title, "error": error });
+}
+
+/// Describes what syncing a feed would change, like
+/// `feeds::check_report()`. `status` is "ok" if the feed could be
+/// compared with the database; otherwise it is "not_in_database",
+/// "needs_login", or "error", with the error message in `error`.
+pub fn check(db: &Database, feed: &PodcastFeed, found: &FeedCheck) -> Value {
+    let title = feed.title.clone().unwrap_or_else(|| feed.url.clone());
+    let none = SyncPreview::default;
+    let (status, error, preview) = match (found, feed.id) {
+        (FeedCheck::Fetched(pod), Some(id)) => match db.preview_sync(id, pod) {
+            Ok(preview) => ("ok", None, preview),
+            Err(err) => ("error", Some(format!("{err}")), none()),
+        },
+        (FeedCheck::Fetched(_), None) => ("not_in_database", None, none()),
+        (FeedCheck::NotModified, _) => ("ok", None, none()),
+        (FeedCheck::AuthRequired, _) => ("needs_login", None, none()),
+        (FeedCheck::Error(err), _) => ("error", Some(err.clone()), none()),
+    };
+    let mut value = preview_json(feed.id, &title, &preview);
+    value["status"] = json!(status);
+    value["error"] = json!(error);
+    return value;
+}
+
+/// Lays out the changes in a podcast's sync preview.
+fn preview_json(pod_id: Option<i64>, title: &str, preview: &SyncPreview) -> Value {
+    let renamed: Vec<Value> = preview
+        .retitled
+        .iter()
+        .map(|(old, new)| json!({ "old": old, "new": new }))
+        .collect();
+    return json!({
+        "id": pod_id,
+        "podcast": title,
+        "new_episodes": preview.added,
+        "renamed": renamed,
+        "other_updates": preview.updated,
+    });
+}
+
+
+// TESTS -----------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn new_episode_fields() {
+        let ep = NewEpisode {
+            id: 4,
+            pod_id: 2,
+            title: "Episode".to_string(),
+            pod_title: "Podcast".to_string(),
+            pubdate: Some(Utc.ymd(2024, 3, 1).and_hms(12, 0, 0)),
+            selected: false,
+        };
+        assert_eq!(
+            new_episode(&ep),
+            json!({
+                "id": 4,
+                "podcast_id": 2,
+                "podcast": "Podcast",
+                "title": "Episode",
+                "pubdate": "2024-03-01T12:00:00+00:00",
+            })
+        );
+    }
+
+    #[test]
+    fn preview_fields() {
+        let preview = SyncPreview {
+            added: vec!["New".to_string()],
+            retitled: vec![("Old".to_string(), "Renamed".to_string())],
+            updated: 2,
+        };
+        let value = preview_json(Some(3), "Podcast", &preview);
+        assert_eq!(value["new_episodes"], json!(["New"]));
+        assert_eq!(value["renamed"], json!([{ "old": "Old", "new": "Renamed" }]));
+        assert_eq!(value["other_updates"], 2);
+
+        let value = preview_json(None, "Feed", &SyncPreview::default());
+        assert_eq!(value["id"], Value::Null);
+        assert_eq!(value["new_episodes"], json!([]));
+    }
+}
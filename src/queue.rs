@@ -0,0 +1,145 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+use chrono::{DateTime, Utc};
+
+/// Puts the items in a random order.
+pub fn shuffle<T>(items: &mut [T]) {
+    // the standard library has no random number generator, but every
+    // RandomState starts from a random seed, which is plenty for
+    // shuffling a play queue
+    let mut state = RandomState::new().build_hasher().finish() | 1;
+    for i in (1..items.len()).rev() {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        let j = (state % (i as u64 + 1)) as usize;
+        items.swap(i, j);
+    }
+}
+
+/// Reorders the items so that podcasts take turns: the first episode
+/// of each podcast (in the order the podcasts first appear), then the
+/// second episode of each, and so on. Each podcast's episodes stay in
+/// the same order. `podcast` gives the podcast ID of an item.
+pub fn interleave<T>(items: Vec<T>, podcast: impl Fn(&T) -> i64) -> Vec<T> {
+    let mut shows: Vec<(i64, Vec<T>)> = Vec::new();
+    for item in items.into_iter() {
+        let pod_id = podcast(&item);
+        match shows.iter_mut().find(|(id, _)| *id == pod_id) {
+            Some((_, eps)) => eps.push(item),
+            None => shows.push((pod_id, vec![item])),
+        }
+    }
+
+    let mut shows: Vec<_> = shows.into_iter().map(|(_, eps)| eps.into_iter()).collect();
+    let mut interleaved = Vec::new();
+    loop {
+        let before = interleaved.len();
+        interleaved.extend(shows.iter_mut().filter_map(|eps| eps.next()));
+        if interleaved.len() == before {
+            return interleaved;
+        }
+    }
+}
+
+/// Adds the `count` newest of the `candidates` (podcast ID, episode ID,
+/// and publication date) to the end of the queue, newest first,
+/// skipping any that are in the queue already. Returns how many were
+/// added.
+pub fn fill(
+    queue: &mut Vec<(i64, i64)>,
+    mut candidates: Vec<(i64, i64, Option<DateTime<Utc>>)>,
+    count: usize,
+) -> usize {
+    candidates.retain(|&(pod_id, ep_id, _)| !queue.contains(&(pod_id, ep_id)));
+    // episodes without a date go last
+    candidates.sort_by_key(|c| std::cmp::Reverse(c.2));
+    let added = candidates.len().min(count);
+    queue.extend(candidates.into_iter().take(count).map(|(pod_id, ep_id, _)| (pod_id, ep_id)));
+    return added;
+}
+
+/// Puts the episodes (podcast ID and episode ID) in the queue right
+/// after the episode that is `playing`, or at the top of the queue if
+/// that episode is not in it, keeping their order. Episodes that are in
+/// the queue already are moved there. Returns how many were placed.
+pub fn insert_next(
+    queue: &mut Vec<(i64, i64)>,
+    mut episodes: Vec<(i64, i64)>,
+    playing: Option<i64>,
+) -> usize {
+    // the playing episode itself stays where it is
+    episodes.retain(|&(_, ep_id)| Some(ep_id) != playing);
+    let mut seen = Vec::new();
+    episodes.retain(|ep| {
+        let first = !seen.contains(ep);
+        seen.push(*ep);
+        first
+    });
+    queue.retain(|ep| !episodes.contains(ep));
+    let index = queue
+        .iter()
+        .position(|&(_, ep_id)| Some(ep_id) == playing)
+        .map_or(0, |index| index + 1);
+    let placed = episodes.len();
+    queue.splice(index..index, episodes);
+    return placed;
+}
+
+
+// TESTS -----------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn shuffle_keeps_items() {
+        let mut items: Vec<i64> = (1..=20).collect();
+        shuffle(&mut items);
+        let mut sorted = items.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, (1..=20).collect::<Vec<i64>>());
+
+        let mut empty: Vec<i64> = Vec::new();
+        shuffle(&mut empty);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn interleave_by_podcast() {
+        let queue = vec![(1, 10), (1, 11), (1, 12), (2, 20), (3, 30), (2, 21)];
+        let interleaved = interleave(queue, |ep| ep.0);
+        assert_eq!(interleaved, vec![(1, 10), (2, 20), (3, 30), (1, 11), (2, 21), (1, 12)]);
+    }
+
+    #[test]
+    fn fill_with_newest() {
+        let day = |d| Some(Utc.ymd(2024, 3, d).and_hms(12, 0, 0));
+        let mut queue = vec![(1, 10)];
+        let candidates =
+            vec![(1, 10, day(5)), (1, 11, day(1)), (2, 20, day(4)), (2, 21, None), (3, 30, day(3))];
+        assert_eq!(fill(&mut queue, candidates.clone(), 2), 2);
+        assert_eq!(queue, vec![(1, 10), (2, 20), (3, 30)]);
+
+        // asking for more than there are adds them all, undated last
+        assert_eq!(fill(&mut queue, candidates, 10), 2);
+        assert_eq!(queue, vec![(1, 10), (2, 20), (3, 30), (1, 11), (2, 21)]);
+    }
+
+    #[test]
+    fn insert_after_playing() {
+        let mut queue = vec![(1, 10), (1, 11), (2, 20), (3, 30)];
+        assert_eq!(insert_next(&mut queue, vec![(4, 40), (3, 30), (4, 40)], Some(11)), 2);
+        assert_eq!(queue, vec![(1, 10), (1, 11), (4, 40), (3, 30), (2, 20)]);
+
+        // nothing from the queue is playing, so they go to the top
+        assert_eq!(insert_next(&mut queue, vec![(2, 20), (5, 50)], Some(99)), 2);
+        assert_eq!(queue, vec![(2, 20), (5, 50), (1, 10), (1, 11), (4, 40), (3, 30)]);
+
+        // the playing episode is not moved
+        assert_eq!(insert_next(&mut queue, vec![(1, 11)], Some(11)), 0);
+        assert_eq!(queue.len(), 6);
+    }
+}
@@ -0,0 +1,297 @@
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+#[cfg(unix)]
+use std::os::unix::fs::FileTypeExt;
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::process::Command;
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::types::Message;
+
+/// How long a connection waits for the main controller to reply to a
+/// command before giving up.
+const REPLY_TIMEOUT: u64 = 5000;
+
+/// How long a connection has to send its command, in milliseconds.
+/// Connections are dealt with one at a time, so one that never sends
+/// anything would otherwise hold up all of the others.
+const READ_TIMEOUT: u64 = 1000;
+
+/// Commands that can be sent to a running instance of shellcaster.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RemoteCmd {
+    SyncAll,
+    Sync(i64),
+    Play(i64),
+    PlayNext,
+    QueueNext(i64),
+    Download(i64),
+    MarkPlayed(i64, bool),
+    List(Option<i64>),
+    Quit,
+}
+
+impl RemoteCmd {
+    /// Parses a command from one line of text, e.g., "play 42".
+    pub fn parse(line: &str) -> Result<Self> {
+        let mut words = line.split_whitespace();
+        let name = words.next().ok_or_else(|| anyhow!("No command given"))?;
+        let id = words
+            .next()
+            .map(|id| {
+                id.parse::<i64>()
+                    .map_err(|_| anyhow!("Invalid id for {name}: {id}"))
+            })
+            .transpose()?;
+        if words.next().is_some() {
+            return Err(anyhow!("Too many arguments for {name}"));
+        }
+        let needs_id = || id.ok_or_else(|| anyhow!("{name} needs an id"));
+
+        return match name {
+            "sync-all" => Ok(Self::SyncAll),
+            "sync" => Ok(Self::Sync(needs_id()?)),
+            "play" => Ok(Self::Play(needs_id()?)),
+            "play-next" => Ok(Self::PlayNext),
+            "queue-next" => Ok(Self::QueueNext(needs_id()?)),
+            "download" => Ok(Self::Download(needs_id()?)),
+            "mark-played" => Ok(Self::MarkPlayed(needs_id()?, true)),
+            "mark-unplayed" => Ok(Self::MarkPlayed(needs_id()?, false)),
+            "list" => Ok(Self::List(id)),
+            "quit" => Ok(Self::Quit),
+            _ => Err(anyhow!("Unknown command: {name}")),
+        };
+    }
+}
+
+/// Enum used for communicating back to the main controller when a
+/// command comes in over the socket. The main controller sends its
+/// reply (or an error message) back through the included channel.
+#[derive(Debug)]
+pub enum RemoteMsg {
+    Command(RemoteCmd, Sender<Result<String, String>>),
+}
+
+/// Starts listening for commands on the unix socket at `path`, in a
+/// new thread. Fails if another instance is already listening there,
+/// or if there is a file at `path` that is not a socket.
+#[cfg(unix)]
+pub fn spawn_listener(path: &Path, tx_to_main: Sender<Message>) -> Result<()> {
+    if let Ok(metadata) = fs::symlink_metadata(path) {
+        if !metadata.file_type().is_socket() {
+            return Err(anyhow!(
+                "Could not create socket: {} already exists and is not a socket",
+                path.to_string_lossy()
+            ));
+        }
+        if UnixStream::connect(path).is_ok() {
+            return Err(anyhow!("Remote control socket is already in use"));
+        }
+        // left over from an instance that did not shut down cleanly
+        fs::remove_file(path)
+            .with_context(|| format!("Could not remove file: {}", path.to_string_lossy()))?;
+    }
+    let listener = UnixListener::bind(path)
+        .with_context(|| format!("Could not create socket: {}", path.to_string_lossy()))?;
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if handle_connection(stream, &tx_to_main).is_err() {
+                return;
+            }
+        }
+    });
+    return Ok(());
+}
+
+#[cfg(not(unix))]
+pub fn spawn_listener(_path: &Path, _tx_to_main: Sender<Message>) -> Result<()> {
+    return Err(anyhow!("Remote control is not supported on this platform"));
+}
+
+/// Reads one command from a connection, passes it on to the main
+/// controller, and writes back the reply. Returns an error only if the
+/// main controller is no longer listening.
+#[cfg(unix)]
+fn handle_connection(stream: UnixStream, tx_to_main: &Sender<Message>) -> Result<()> {
+    let mut line = String::new();
+    if stream
+        .set_read_timeout(Some(Duration::from_millis(READ_TIMEOUT)))
+        .is_err()
+    {
+        return Ok(());
+    }
+    let mut writer = match stream.try_clone() {
+        Ok(writer) => writer,
+        Err(_) => return Ok(()),
+    };
+    if BufReader::new(stream).read_line(&mut line).is_err() {
+        return Ok(());
+    }
+
+    let reply = match RemoteCmd::parse(&line) {
+        Ok(cmd) => {
+            let (tx_reply, rx_reply) = mpsc::channel();
+            tx_to_main.send(Message::Remote(RemoteMsg::Command(cmd, tx_reply)))?;
+            rx_reply
+                .recv_timeout(Duration::from_millis(REPLY_TIMEOUT))
+                .unwrap_or_else(|_| Err("No reply from shellcaster".to_string()))
+        }
+        Err(err) => {
+            log::warn!("Invalid remote command: {err}");
+            Err(err.to_string())
+        }
+    };
+    let text = match reply {
+        Ok(text) => format!("ok\n{text}"),
+        Err(err) => format!("error\n{err}"),
+    };
+    let _ = writer.write_all(text.as_bytes());
+    return Ok(());
+}
+
+/// Removes the socket file when shutting down.
+pub fn remove_socket(path: &Path) {
+    let _ = fs::remove_file(path);
+}
+
+/// Sends a command to the running instance listening at `path`, and
+/// returns its reply.
+#[cfg(unix)]
+pub fn send(path: &Path, command: &str) -> Result<String> {
+    let mut stream = UnixStream::connect(path).with_context(|| {
+        format!(
+            "Could not connect to {}. Is shellcaster running?",
+            path.to_string_lossy()
+        )
+    })?;
+    stream.write_all(format!("{}\n", command.trim()).as_bytes())?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    return match response.split_once('\n') {
+        Some(("ok", text)) => Ok(text.to_string()),
+        Some(("error", err)) => Err(anyhow!(err.to_string())),
+        _ => Err(anyhow!("Invalid reply from shellcaster")),
+    };
+}
+
+#[cfg(not(unix))]
+pub fn send(_path: &Path, _command: &str) -> Result<String> {
+    return Err(anyhow!("Remote control is not supported on this platform"));
+}
+
+/// Sends a command to an instance running on another machine, by
+/// running `shellcaster remote` there over `ssh`, and returns its
+/// reply. The command is checked here first, so that only valid
+/// commands are passed to the remote shell.
+pub fn send_ssh(host: &str, command: &str) -> Result<String> {
+    RemoteCmd::parse(command)?;
+    let output = Command::new("ssh")
+        .args(ssh_args(host, command))
+        .output()
+        .with_context(|| "Could not run ssh")?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout).trim_end().to_string();
+    if output.status.success() {
+        return Ok(stdout);
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    log::warn!("Remote command over ssh to {host} failed: {stderr}");
+    return Err(anyhow!(if stderr.is_empty() {
+        format!("Could not run shellcaster on {host}")
+    } else {
+        stderr
+    }));
+}
+
+/// Returns the arguments to `ssh` for sending a command to `host`. The
+/// host comes after "--", so that a host starting with "-" cannot be
+/// taken for an option.
+fn ssh_args<'a>(host: &'a str, command: &'a str) -> Vec<&'a str> {
+    let mut args = vec!["--", host, "shellcaster", "remote"];
+    args.extend(command.split_whitespace());
+    return args;
+}
+
+
+// TESTS -----------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_commands() {
+        assert_eq!(RemoteCmd::parse("sync-all\n").unwrap(), RemoteCmd::SyncAll);
+        assert_eq!(RemoteCmd::parse("play 42").unwrap(), RemoteCmd::Play(42));
+        assert_eq!(
+            RemoteCmd::parse("  mark-unplayed   7 ").unwrap(),
+            RemoteCmd::MarkPlayed(7, false)
+        );
+        assert_eq!(RemoteCmd::parse("list").unwrap(), RemoteCmd::List(None));
+        assert_eq!(RemoteCmd::parse("list 3").unwrap(), RemoteCmd::List(Some(3)));
+        assert_eq!(RemoteCmd::parse("queue-next 5").unwrap(), RemoteCmd::QueueNext(5));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(RemoteCmd::parse("").is_err());
+        assert!(RemoteCmd::parse("play").is_err());
+        assert!(RemoteCmd::parse("play abc").is_err());
+        assert!(RemoteCmd::parse("play 1 2").is_err());
+        assert!(RemoteCmd::parse("rewind").is_err());
+        assert!(RemoteCmd::parse("queue-next").is_err());
+    }
+
+    #[test]
+    fn ssh_host_is_not_an_option() {
+        assert_eq!(
+            ssh_args("me@homeserver", " play  42 "),
+            vec!["--", "me@homeserver", "shellcaster", "remote", "play", "42"]
+        );
+        assert_eq!(ssh_args("-oProxyCommand=x", "list")[..2], ["--", "-oProxyCommand=x"]);
+        assert!(send_ssh("me@homeserver", "play; rm -rf ~").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn listener_keeps_other_files() {
+        let path =
+            std::env::temp_dir().join(format!("shellcaster-remote-file-{}", std::process::id()));
+        fs::write(&path, "not a socket").unwrap();
+        let (tx, _rx) = mpsc::channel();
+        assert!(spawn_listener(&path, tx).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "not a socket");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn idle_connection_times_out() {
+        let path =
+            std::env::temp_dir().join(format!("shellcaster-remote-idle-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let (tx, rx) = mpsc::channel();
+        spawn_listener(&path, tx).unwrap();
+        // answers commands as the main controller would
+        thread::spawn(move || {
+            for msg in rx.iter() {
+                if let Message::Remote(RemoteMsg::Command(cmd, reply)) = msg {
+                    let _ = reply.send(Ok(format!("{cmd:?}")));
+                }
+            }
+        });
+
+        // a connection that never sends anything does not hold up the
+        // next one for good
+        let _idle = UnixStream::connect(&path).unwrap();
+        assert_eq!(send(&path, "queue-next 5").unwrap(), "QueueNext(5)");
+        remove_socket(&path);
+    }
+}
@@ -0,0 +1,194 @@
+use sanitize_filename::{sanitize_with_options, Options};
+
+/// File name used for titles that have nothing left in them once they
+/// have been sanitized.
+const PLACEHOLDER_STEM: &str = "episode";
+
+/// Identifies the set of rules used to turn podcast and episode titles
+/// into file and directory names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SanitizeProfile {
+    /// Strips anything that is not safe on FAT32 drives, including all
+    /// non-ASCII characters. Useful when syncing downloads to portable
+    /// media players, SD cards, etc.
+    Conservative,
+    /// Keeps the full range of Unicode characters and only removes the
+    /// characters that can never appear in a filename on Unix-like
+    /// systems.
+    Unicode,
+    /// Removes characters that are invalid on Windows, and avoids
+    /// Windows-reserved names such as "CON" or "LPT1".
+    Windows,
+}
+
+/// Converts titles into safe file names, according to the selected
+/// profile, and truncates them so they fit within `max_length` bytes.
+#[derive(Debug, Clone, Copy)]
+pub struct Sanitizer {
+    pub profile: SanitizeProfile,
+    pub max_length: usize,
+}
+
+impl Sanitizer {
+    /// Creates a new Sanitizer.
+    pub fn new(profile: SanitizeProfile, max_length: usize) -> Self {
+        return Self {
+            profile: profile,
+            max_length: max_length,
+        };
+    }
+
+    /// Sanitizes a name that has no file extension (e.g., a directory
+    /// name), truncating it to the maximum length if necessary.
+    pub fn sanitize(&self, name: &str) -> String {
+        let clean = self.apply_profile(name);
+        return self.trim_end(&truncate_bytes(&clean, self.max_length));
+    }
+
+    /// Sanitizes a file name and appends the extension. If the name has
+    /// to be truncated, it is the stem that gets shortened, so the
+    /// extension is always preserved.
+    pub fn sanitize_with_ext(&self, stem: &str, ext: &str) -> String {
+        let clean_ext = self.apply_profile(ext);
+        if clean_ext.is_empty() {
+            return self.sanitize_stem(stem, self.max_length);
+        }
+        let stem_length = self.max_length.saturating_sub(clean_ext.len() + 1);
+        let stem = self.sanitize_stem(stem, stem_length);
+        return format!("{stem}.{clean_ext}");
+    }
+
+    /// Sanitizes the part of a file name before the extension. This is
+    /// never left empty, so a title with nothing usable in it (e.g.,
+    /// "CON" on Windows) gets a placeholder name instead.
+    fn sanitize_stem(&self, stem: &str, max_length: usize) -> String {
+        let clean = self.apply_profile(stem);
+        let clean = self.trim_end(&truncate_bytes(&clean, max_length));
+        if clean.is_empty() {
+            return truncate_bytes(PLACEHOLDER_STEM, max_length);
+        }
+        return clean;
+    }
+
+    /// Applies the profile's rules for the end of a name again after it
+    /// has been truncated, as Windows does not allow names ending in a
+    /// dot or a space.
+    fn trim_end(&self, name: &str) -> String {
+        return match self.profile {
+            SanitizeProfile::Unicode => name.to_string(),
+            SanitizeProfile::Windows | SanitizeProfile::Conservative => windows_sanitize(name),
+        };
+    }
+
+    /// Removes any characters not allowed by the selected profile.
+    fn apply_profile(&self, name: &str) -> String {
+        return match self.profile {
+            SanitizeProfile::Unicode => {
+                let clean: String = name
+                    .chars()
+                    .filter(|c| *c != '/' && !c.is_control())
+                    .collect();
+                let clean = clean.trim().to_string();
+                if clean == "." || clean == ".." {
+                    "".to_string()
+                } else {
+                    clean
+                }
+            }
+            SanitizeProfile::Windows => windows_sanitize(name),
+            SanitizeProfile::Conservative => {
+                let ascii: String = name
+                    .chars()
+                    .filter(|c| c.is_ascii() && !c.is_ascii_control())
+                    .collect();
+                windows_sanitize(&ascii)
+            }
+        };
+    }
+}
+
+/// Applies the Windows rules for file names: strips reserved characters
+/// and trailing dots/spaces, and avoids reserved device names.
+fn windows_sanitize(name: &str) -> String {
+    let options = || Options {
+        truncate: false,
+        windows: true,
+        replacement: "",
+    };
+    let clean = sanitize_with_options(name, options());
+    let clean = clean.trim().trim_end_matches(['.', ' ']);
+    // trimming can leave a reserved name behind (e.g., "CON. ")
+    return sanitize_with_options(clean, options());
+}
+
+/// Truncates a string to at most `max_bytes` bytes, making sure not to
+/// split a multi-byte character.
+fn truncate_bytes(text: &str, max_bytes: usize) -> String {
+    if text.len() <= max_bytes {
+        return text.to_string();
+    }
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    return text[..end].trim_end().to_string();
+}
+
+
+// TESTS -----------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows_reserved_chars() {
+        let sanitizer = Sanitizer::new(SanitizeProfile::Windows, 255);
+        assert_eq!(sanitizer.sanitize("What? Why: \"Now\""), "What Why Now");
+    }
+
+    #[test]
+    fn windows_reserved_name() {
+        let sanitizer = Sanitizer::new(SanitizeProfile::Windows, 255);
+        assert_eq!(sanitizer.sanitize("CON"), "");
+        assert_eq!(sanitizer.sanitize_with_ext("CON", "mp3"), "episode.mp3");
+        let sanitizer = Sanitizer::new(SanitizeProfile::Unicode, 255);
+        assert_eq!(sanitizer.sanitize_with_ext("/", "mp3"), "episode.mp3");
+    }
+
+    #[test]
+    fn truncate_trailing_dots() {
+        let sanitizer = Sanitizer::new(SanitizeProfile::Windows, 8);
+        assert_eq!(sanitizer.sanitize_with_ext("Vol. 2 of 3", "mp3"), "Vol.mp3");
+        let sanitizer = Sanitizer::new(SanitizeProfile::Windows, 5);
+        assert_eq!(sanitizer.sanitize("Ab .. cd"), "Ab");
+        let sanitizer = Sanitizer::new(SanitizeProfile::Conservative, 8);
+        assert_eq!(sanitizer.sanitize_with_ext("Ab . cd", "mp3"), "Ab.mp3");
+    }
+
+    #[test]
+    fn unicode_keeps_accents() {
+        let sanitizer = Sanitizer::new(SanitizeProfile::Unicode, 255);
+        assert_eq!(sanitizer.sanitize("Le Café: épisode 1/2"), "Le Café: épisode 12");
+    }
+
+    #[test]
+    fn conservative_strips_unicode() {
+        let sanitizer = Sanitizer::new(SanitizeProfile::Conservative, 255);
+        assert_eq!(sanitizer.sanitize("Le Café: épisode 😉"), "Le Caf pisode");
+    }
+
+    #[test]
+    fn truncate_preserves_extension() {
+        let sanitizer = Sanitizer::new(SanitizeProfile::Windows, 10);
+        assert_eq!(
+            sanitizer.sanitize_with_ext("A very long title", "mp3"),
+            "A very.mp3"
+        );
+    }
+
+    #[test]
+    fn truncate_multibyte() {
+        let sanitizer = Sanitizer::new(SanitizeProfile::Unicode, 4);
+        assert_eq!(sanitizer.sanitize("ééé"), "éé");
+    }
+}
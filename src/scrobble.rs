@@ -0,0 +1,176 @@
+use std::sync::mpsc::Sender;
+use std::thread;
+
+use anyhow::{anyhow, Result};
+use serde_json::json;
+
+use crate::http;
+use crate::types::Message;
+
+/// ListenBrainz server used if `listenbrainz_url` is not set.
+pub const DEFAULT_URL: &str = "https://api.listenbrainz.org";
+
+/// Most listens sent in one request; any more wait for the next one.
+pub const MAX_LISTENS: usize = 100;
+
+/// One episode that was played to the end, waiting in the spool to be
+/// submitted. `id` is its row in the spool, and `listened_at` is when
+/// playback started, as a Unix timestamp.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Listen {
+    pub id: i64,
+    pub listened_at: i64,
+    pub podcast: String,
+    pub episode: String,
+}
+
+/// Enum used for communicating back to the main controller after trying
+/// to submit listens. `Submitted` holds the spool IDs of the listens
+/// that were accepted. `Offline` means the server could not be reached
+/// (or had trouble of its own), and `Unauthorized` that the token is
+/// wrong, so the listens should stay in the spool for later. `Rejected`
+/// means the server refused the listens themselves, so sending them
+/// again would not help; it holds their spool IDs.
+#[derive(Debug)]
+pub enum ScrobbleMsg {
+    Submitted(Vec<i64>),
+    Offline,
+    Unauthorized(String),
+    Rejected(Vec<i64>, String),
+}
+
+/// Spawns a thread that submits listens to ListenBrainz, and lets the
+/// main controller know how it went.
+pub fn spawn_submit(url: String, token: String, listens: Vec<Listen>, tx_to_main: Sender<Message>) {
+    thread::spawn(move || {
+        let msg = match submit(&url, &token, &listens) {
+            Ok(()) => ScrobbleMsg::Submitted(listens.iter().map(|l| l.id).collect()),
+            Err(SubmitError::Offline) => ScrobbleMsg::Offline,
+            Err(SubmitError::Unauthorized(err)) => ScrobbleMsg::Unauthorized(err.to_string()),
+            Err(SubmitError::Rejected(err)) => {
+                ScrobbleMsg::Rejected(listens.iter().map(|l| l.id).collect(), err.to_string())
+            }
+        };
+        let _ = tx_to_main.send(Message::Scrobble(msg));
+    });
+}
+
+#[derive(Debug)]
+enum SubmitError {
+    Offline,
+    Unauthorized(anyhow::Error),
+    Rejected(anyhow::Error),
+}
+
+/// Sends listens to the "submit-listens" endpoint of a ListenBrainz
+/// server.
+fn submit(url: &str, token: &str, listens: &[Listen]) -> Result<(), SubmitError> {
+    let endpoint = format!("{}/1/submit-listens", url.trim_end_matches('/'));
+    let result = http::BLOCKING_CLIENT
+        .post(&endpoint)
+        .header("Authorization", format!("Token {token}"))
+        .header("Content-Type", "application/json")
+        .body(payload(listens).to_string())
+        .send();
+    return match result {
+        Ok(response) if response.status().is_success() => Ok(()),
+        Ok(response) => {
+            let code = response.status().as_u16();
+            let reason = response.text().unwrap_or_default();
+            Err(failure(code, &reason))
+        }
+        Err(_) => Err(SubmitError::Offline),
+    };
+}
+
+/// Works out what an error status from ListenBrainz means for the
+/// listens that were sent.
+fn failure(code: u16, reply: &str) -> SubmitError {
+    let err = anyhow!("ListenBrainz returned status {code}: {}", error_message(reply));
+    return match code {
+        401 | 403 => SubmitError::Unauthorized(err),
+        // rate limits and server errors should pass, so try again later
+        429 | 500.. => SubmitError::Offline,
+        _ => SubmitError::Rejected(err),
+    };
+}
+
+/// Builds the body of a submission: the podcast is given as the
+/// artist, and the episode as the track.
+fn payload(listens: &[Listen]) -> serde_json::Value {
+    let listen_type = if listens.len() == 1 { "single" } else { "import" };
+    let payload: Vec<serde_json::Value> = listens
+        .iter()
+        .map(|listen| {
+            json!({
+                "listened_at": listen.listened_at,
+                "track_metadata": {
+                    "artist_name": listen.podcast,
+                    "track_name": listen.episode,
+                    "additional_info": {
+                        "media_player": "shellcaster",
+                        "submission_client": "shellcaster",
+                        "submission_client_version": env!("CARGO_PKG_VERSION"),
+                    },
+                },
+            })
+        })
+        .collect();
+    return json!({ "listen_type": listen_type, "payload": payload });
+}
+
+/// Picks the error message out of a reply from ListenBrainz, or returns
+/// the reply as it is.
+fn error_message(reply: &str) -> String {
+    return serde_json::from_str::<serde_json::Value>(reply)
+        .ok()
+        .and_then(|v| v.get("error").and_then(|e| e.as_str()).map(str::to_string))
+        .unwrap_or_else(|| reply.trim().to_string());
+}
+
+
+// TESTS -----------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listen(id: i64) -> Listen {
+        return Listen {
+            id: id,
+            listened_at: 1700000000 + id,
+            podcast: "Rerel Cast".to_string(),
+            episode: format!("Episode {id}"),
+        };
+    }
+
+    #[test]
+    fn single_and_import() {
+        let body = payload(&[listen(1)]);
+        assert_eq!(body["listen_type"], "single");
+        assert_eq!(body["payload"][0]["listened_at"], 1700000001);
+        assert_eq!(body["payload"][0]["track_metadata"]["artist_name"], "Rerel Cast");
+        assert_eq!(body["payload"][0]["track_metadata"]["track_name"], "Episode 1");
+
+        let body = payload(&[listen(1), listen(2)]);
+        assert_eq!(body["listen_type"], "import");
+        assert_eq!(body["payload"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn rejection_reason() {
+        assert_eq!(
+            error_message(r#"{"code": 401, "error": "Invalid authorization token."}"#),
+            "Invalid authorization token."
+        );
+        assert_eq!(error_message("Bad Gateway\n"), "Bad Gateway");
+    }
+
+    #[test]
+    fn only_bad_listens_are_dropped() {
+        let reply = r#"{"code": 400, "error": "Invalid listened_at."}"#;
+        assert!(matches!(failure(400, reply), SubmitError::Rejected(_)));
+        assert!(matches!(failure(401, reply), SubmitError::Unauthorized(_)));
+        assert!(matches!(failure(429, reply), SubmitError::Offline));
+        assert!(matches!(failure(503, "Service Unavailable"), SubmitError::Offline));
+    }
+}
@@ -0,0 +1,95 @@
+use anyhow::Result;
+#[cfg(not(feature = "keyring"))]
+use anyhow::anyhow;
+
+/// Service name that shellcaster's secrets are stored under in the
+/// system keyring.
+#[cfg(feature = "keyring")]
+const SERVICE: &str = "shellcaster";
+
+/// Secrets from config.toml that can be kept in the system keyring
+/// instead. A secret in the keyring takes the place of the one in
+/// config.toml.
+pub const CONFIG_SECRETS: [&str; 2] = ["webdav_password", "listenbrainz_token"];
+
+/// Name of the keyring entry holding the password for a private
+/// podcast feed. `db_id` tells apart the podcasts of different
+/// databases (see `Database::keyring_id()`), which can share ids.
+pub fn feed_password(db_id: &str, pod_id: i64) -> String {
+    return format!("podcast-{db_id}-{pod_id}");
+}
+
+/// Name that feed passwords were kept under before they included the
+/// id of their database; these are moved to the new names once.
+pub fn old_feed_password(pod_id: i64) -> String {
+    return format!("podcast-{pod_id}");
+}
+
+/// Returns a new id for a database, to keep its feed passwords apart
+/// from those of other databases. This only needs to be unique, not
+/// secret.
+pub fn new_database_id() -> String {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos(),
+    );
+    hasher.write_u32(std::process::id());
+    return format!("{:016x}", hasher.finish());
+}
+
+/// Looks up a secret in the system keyring. Returns None if there is
+/// no such secret, or if the keyring cannot be reached.
+#[cfg(feature = "keyring")]
+pub fn get(name: &str) -> Option<String> {
+    let entry = keyring::Entry::new(SERVICE, name).ok()?;
+    return entry.get_password().ok();
+}
+
+/// Without keyring support, there are never any secrets in the keyring.
+#[cfg(not(feature = "keyring"))]
+pub fn get(_name: &str) -> Option<String> {
+    return None;
+}
+
+/// Stores a secret in the system keyring, replacing the one that was
+/// there, if any.
+#[cfg(feature = "keyring")]
+pub fn set(name: &str, secret: &str) -> Result<()> {
+    let entry = keyring::Entry::new(SERVICE, name)?;
+    entry.set_password(secret)?;
+    return Ok(());
+}
+
+/// Without keyring support, secrets cannot be stored in the keyring.
+#[cfg(not(feature = "keyring"))]
+pub fn set(_name: &str, _secret: &str) -> Result<()> {
+    return Err(no_keyring());
+}
+
+/// Removes a secret from the system keyring. It is not an error if
+/// there was no such secret.
+#[cfg(feature = "keyring")]
+pub fn delete(name: &str) -> Result<()> {
+    let entry = keyring::Entry::new(SERVICE, name)?;
+    return match entry.delete_password() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(err) => Err(err.into()),
+    };
+}
+
+/// Without keyring support, there is no keyring to remove secrets from.
+#[cfg(not(feature = "keyring"))]
+pub fn delete(_name: &str) -> Result<()> {
+    return Err(no_keyring());
+}
+
+/// Error returned when shellcaster was built without keyring support.
+#[cfg(not(feature = "keyring"))]
+fn no_keyring() -> anyhow::Error {
+    return anyhow!("shellcaster was built without keyring support (the \"keyring\" feature).");
+}
@@ -0,0 +1,414 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+
+use crate::types::{Episode, Menuable};
+
+/// A filter defined by the user in config.toml, e.g.,
+/// "unplayed AND downloaded AND duration < 30min".
+#[derive(Debug, Clone)]
+pub struct SmartFilter {
+    pub name: String,
+    pub expr: FilterExpr,
+}
+
+impl SmartFilter {
+    /// Parses the filter expression, giving an error that names the
+    /// filter if it is invalid.
+    pub fn new(name: String, filter: &str) -> Result<Self> {
+        let expr = FilterExpr::parse(filter)
+            .map_err(|err| anyhow!("Invalid filter \"{name}\": {err}"))?;
+        return Ok(Self {
+            name: name,
+            expr: expr,
+        });
+    }
+}
+
+/// A predicate on episodes, built up from simple conditions combined
+/// with AND, OR, NOT, and parentheses. Durations are in seconds and
+/// ages in days; tags are lowercase.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterExpr {
+    Played,
+    Downloaded,
+    Duration(Comparison, i64),
+    Age(Comparison, i64),
+    Tag(String),
+    Not(Box<FilterExpr>),
+    And(Vec<FilterExpr>),
+    Or(Vec<FilterExpr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+}
+
+impl Comparison {
+    fn compare(self, left: i64, right: i64) -> bool {
+        return match self {
+            Self::Less => left < right,
+            Self::LessEqual => left <= right,
+            Self::Greater => left > right,
+            Self::GreaterEqual => left >= right,
+            Self::Equal => left == right,
+        };
+    }
+}
+
+impl FilterExpr {
+    /// Parses a filter expression. Keywords are case-insensitive. The
+    /// conditions available are:
+    /// * `played`, `unplayed`, `downloaded`, `undownloaded`;
+    /// * `duration` compared to a length like `30min`, `1h`, or `90s`
+    ///   (minutes if no unit is given);
+    /// * `age` (time since the episode was published) compared to a
+    ///   length like `7d` or `2w` (days if no unit is given);
+    /// * `tag = X`, for episodes of podcasts tagged `X` (see
+    ///   `Podcast::tags()`).
+    pub fn parse(text: &str) -> Result<Self> {
+        let tokens = tokenize(text);
+        let mut parser = Parser {
+            tokens: tokens,
+            pos: 0,
+        };
+        let expr = parser.parse_or()?;
+        if let Some(token) = parser.peek() {
+            return Err(anyhow!("Unexpected \"{token}\""));
+        }
+        return Ok(expr);
+    }
+
+    /// Indicates whether an episode passes the filter, given the tags
+    /// of its podcast. Episodes without a known duration or publication
+    /// date fail those conditions.
+    pub fn matches(&self, ep: &Episode, tags: &[String]) -> bool {
+        return match self {
+            Self::Played => ep.is_played(),
+            Self::Downloaded => ep.path.is_some(),
+            Self::Duration(cmp, secs) => ep.duration.is_some_and(|dur| cmp.compare(dur, *secs)),
+            Self::Age(cmp, days) => ep
+                .pubdate
+                .is_some_and(|date| cmp.compare((Utc::now() - date).num_days(), *days)),
+            Self::Tag(tag) => tags.contains(tag),
+            Self::Not(expr) => !expr.matches(ep, tags),
+            Self::And(exprs) => exprs.iter().all(|expr| expr.matches(ep, tags)),
+            Self::Or(exprs) => exprs.iter().any(|expr| expr.matches(ep, tags)),
+        };
+    }
+}
+
+/// Splits a filter expression into words, parentheses, and comparison
+/// operators, so that e.g. "duration<30min" works without spaces.
+fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() || "()<>=".contains(c) {
+            if !word.is_empty() {
+                tokens.push(std::mem::take(&mut word));
+            }
+            match c {
+                '(' | ')' => tokens.push(c.to_string()),
+                '<' | '>' if chars.peek() == Some(&'=') => {
+                    chars.next();
+                    tokens.push(format!("{c}="));
+                }
+                '<' | '>' | '=' => tokens.push(c.to_string()),
+                _ => (),
+            }
+        } else {
+            word.push(c);
+        }
+    }
+    if !word.is_empty() {
+        tokens.push(word);
+    }
+    return tokens;
+}
+
+/// Recursive descent parser over the tokens of a filter expression.
+/// NOT binds tightest, then AND, then OR.
+struct Parser {
+    tokens: Vec<String>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&str> {
+        return self.tokens.get(self.pos).map(|token| token.as_str());
+    }
+
+    fn next(&mut self) -> Result<String> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| anyhow!("Unexpected end of filter"))?;
+        self.pos += 1;
+        return Ok(token);
+    }
+
+    fn next_is(&mut self, keyword: &str) -> bool {
+        if self.peek().is_some_and(|token| token.eq_ignore_ascii_case(keyword)) {
+            self.pos += 1;
+            return true;
+        }
+        return false;
+    }
+
+    fn parse_or(&mut self) -> Result<FilterExpr> {
+        let mut exprs = vec![self.parse_and()?];
+        while self.next_is("or") {
+            exprs.push(self.parse_and()?);
+        }
+        return Ok(if exprs.len() == 1 {
+            exprs.remove(0)
+        } else {
+            FilterExpr::Or(exprs)
+        });
+    }
+
+    fn parse_and(&mut self) -> Result<FilterExpr> {
+        let mut exprs = vec![self.parse_not()?];
+        while self.next_is("and") {
+            exprs.push(self.parse_not()?);
+        }
+        return Ok(if exprs.len() == 1 {
+            exprs.remove(0)
+        } else {
+            FilterExpr::And(exprs)
+        });
+    }
+
+    fn parse_not(&mut self) -> Result<FilterExpr> {
+        if self.next_is("not") {
+            return Ok(FilterExpr::Not(Box::new(self.parse_not()?)));
+        }
+        return self.parse_condition();
+    }
+
+    fn parse_condition(&mut self) -> Result<FilterExpr> {
+        let token = self.next()?;
+        let not = |expr| FilterExpr::Not(Box::new(expr));
+        return match token.to_lowercase().as_str() {
+            "(" => {
+                let expr = self.parse_or()?;
+                match self.next()?.as_str() {
+                    ")" => Ok(expr),
+                    other => Err(anyhow!("Expected \")\" but found \"{other}\"")),
+                }
+            }
+            "played" => Ok(FilterExpr::Played),
+            "unplayed" => Ok(not(FilterExpr::Played)),
+            "downloaded" => Ok(FilterExpr::Downloaded),
+            "undownloaded" => Ok(not(FilterExpr::Downloaded)),
+            "duration" => {
+                let cmp = self.parse_comparison()?;
+                let secs = parse_length(&self.next()?, 60, &[
+                    ("s", 1),
+                    ("sec", 1),
+                    ("m", 60),
+                    ("min", 60),
+                    ("h", 3600),
+                ])?;
+                Ok(FilterExpr::Duration(cmp, secs))
+            }
+            "age" => {
+                let cmp = self.parse_comparison()?;
+                let days = parse_length(&self.next()?, 1, &[("d", 1), ("w", 7)])?;
+                Ok(FilterExpr::Age(cmp, days))
+            }
+            "tag" => match self.parse_comparison()? {
+                Comparison::Equal => Ok(FilterExpr::Tag(self.next()?.to_lowercase())),
+                _ => Err(anyhow!("Expected \"=\" after \"tag\"")),
+            },
+            _ => Err(anyhow!("Unknown condition \"{token}\"")),
+        };
+    }
+
+    fn parse_comparison(&mut self) -> Result<Comparison> {
+        return match self.next()?.as_str() {
+            "<" => Ok(Comparison::Less),
+            "<=" => Ok(Comparison::LessEqual),
+            ">" => Ok(Comparison::Greater),
+            ">=" => Ok(Comparison::GreaterEqual),
+            "=" => Ok(Comparison::Equal),
+            other => Err(anyhow!("Expected a comparison but found \"{other}\"")),
+        };
+    }
+}
+
+/// Parses a point in time given either as a date like "2023-06-01" or
+/// as an age like "90d", "6w", "6m" or "2y" (days if no unit is given),
+/// which is counted back from now.
+pub fn parse_cutoff(text: &str) -> Result<DateTime<Utc>> {
+    let text = text.trim();
+    if let Some(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+    {
+        return Ok(Utc.from_utc_datetime(&date));
+    }
+    let days = parse_length(text, 1, &[("d", 1), ("w", 7), ("m", 30), ("y", 365)])
+        .map_err(|_| anyhow!("Expected a date like 2023-06-01 or an age like 90d, not \"{text}\""))?;
+    // ages too long to count back are refused, rather than panicking
+    return days
+        .checked_mul(86_400)
+        .and_then(|secs| u64::try_from(secs).ok())
+        .and_then(|secs| chrono::Duration::from_std(std::time::Duration::from_secs(secs)).ok())
+        .and_then(|age| Utc::now().checked_sub_signed(age))
+        .ok_or_else(|| anyhow!("\"{text}\" is too long ago"));
+}
+
+/// Parses a number with an optional unit (e.g., "30min"), returning it
+/// multiplied by the size of the unit. `default` is the size used when
+/// there is no unit.
+fn parse_length(token: &str, default: i64, units: &[(&str, i64)]) -> Result<i64> {
+    let split = token
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(token.len());
+    let (number, unit) = token.split_at(split);
+    let number: i64 = number
+        .parse()
+        .map_err(|_| anyhow!("Expected a number but found \"{token}\""))?;
+    let size = match unit {
+        "" => default,
+        _ => units
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(unit))
+            .map(|(_, size)| *size)
+            .ok_or_else(|| anyhow!("Unknown unit in \"{token}\""))?,
+    };
+    return number
+        .checked_mul(size)
+        .ok_or_else(|| anyhow!("\"{token}\" is too large"));
+}
+
+
+// TESTS -----------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn episode(played: bool, downloaded: bool, duration: Option<i64>) -> Episode {
+        return Episode {
+            id: 1,
+            pod_id: 1,
+            title: "Episode".to_string(),
+            url: String::new(),
+            guid: String::new(),
+            description: String::new(),
+            pubdate: Some(Utc::now() - Duration::days(3)),
+            duration: duration,
+            image_url: None,
+            chapters_url: None,
+            chapters: Vec::new(),
+            transcript_url: None,
+            transcript_type: None,
+            enclosures: Vec::new(),
+            enclosure_error: None,
+            path: downloaded.then(|| "/tmp/episode.mp3".into()),
+            played: played,
+            updated: false,
+            starred: false,
+            clean_title: None,
+            position: None,
+            last_played: None,
+            new: false,
+        };
+    }
+
+    #[test]
+    fn parse_precedence() {
+        let expr = FilterExpr::parse("unplayed AND downloaded OR NOT played").unwrap();
+        assert_eq!(
+            expr,
+            FilterExpr::Or(vec![
+                FilterExpr::And(vec![
+                    FilterExpr::Not(Box::new(FilterExpr::Played)),
+                    FilterExpr::Downloaded,
+                ]),
+                FilterExpr::Not(Box::new(FilterExpr::Played)),
+            ])
+        );
+        let expr = FilterExpr::parse("duration<=1h and (age > 2w)").unwrap();
+        assert_eq!(
+            expr,
+            FilterExpr::And(vec![
+                FilterExpr::Duration(Comparison::LessEqual, 3600),
+                FilterExpr::Age(Comparison::Greater, 14),
+            ])
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(FilterExpr::parse("").is_err());
+        assert!(FilterExpr::parse("unplayed AND").is_err());
+        assert!(FilterExpr::parse("(played").is_err());
+        assert!(FilterExpr::parse("duration 30").is_err());
+        assert!(FilterExpr::parse("duration < 30days").is_err());
+        assert!(FilterExpr::parse("duration < 9223372036854775807h").is_err());
+        assert!(FilterExpr::parse("starred").is_err());
+    }
+
+    #[test]
+    fn matches_episodes() {
+        let expr = FilterExpr::parse("unplayed AND downloaded AND duration < 30min").unwrap();
+        assert!(expr.matches(&episode(false, true, Some(600)), &[]));
+        assert!(!expr.matches(&episode(true, true, Some(600)), &[]));
+        assert!(!expr.matches(&episode(false, false, Some(600)), &[]));
+        assert!(!expr.matches(&episode(false, true, Some(3600)), &[]));
+        assert!(!expr.matches(&episode(false, true, None), &[]));
+
+        let expr = FilterExpr::parse("age < 1w").unwrap();
+        assert!(expr.matches(&episode(false, false, None), &[]));
+    }
+
+    #[test]
+    fn matches_tags() {
+        let expr = FilterExpr::parse("tag=Technology AND NOT tag = en").unwrap();
+        assert_eq!(
+            expr,
+            FilterExpr::And(vec![
+                FilterExpr::Tag("technology".to_string()),
+                FilterExpr::Not(Box::new(FilterExpr::Tag("en".to_string()))),
+            ])
+        );
+        let tags = |list: &[&str]| list.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+        let ep = episode(false, false, None);
+        assert!(expr.matches(&ep, &tags(&["technology", "de"])));
+        assert!(!expr.matches(&ep, &tags(&["technology", "en-us", "en"])));
+        assert!(!expr.matches(&ep, &[]));
+
+        assert!(FilterExpr::parse("tag < news").is_err());
+        assert!(FilterExpr::parse("tag =").is_err());
+    }
+
+    #[test]
+    fn cutoffs() {
+        let date = parse_cutoff("2023-06-01").unwrap();
+        assert_eq!(date.to_rfc3339(), "2023-06-01T00:00:00+00:00");
+
+        let days = |text| {
+            let cutoff = parse_cutoff(text).unwrap();
+            return (Utc::now() - cutoff).num_days();
+        };
+        assert_eq!(days("90"), 90);
+        assert_eq!(days(" 2w "), 14);
+        assert_eq!(days("6m"), 180);
+        assert_eq!(days("1y"), 365);
+
+        assert!(parse_cutoff("yesterday").is_err());
+        assert!(parse_cutoff("2023-13-01").is_err());
+        assert!(parse_cutoff("100000000d").is_err());
+        assert!(parse_cutoff("9223372036854775807y").is_err());
+    }
+}
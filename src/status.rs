@@ -0,0 +1,182 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::types::format_hms;
+
+/// Snapshot of the app's state, written to the status file as JSON so
+/// that status bars and shell prompts can display it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Status {
+    pub now_playing: Option<NowPlaying>,
+    pub podcasts: usize,
+    pub unplayed: usize,
+    pub downloads: usize,
+    pub syncing: usize,
+}
+
+/// The episode that is playing in the media player, if it is still
+/// running. `duration` is the length of the episode in seconds, if the
+/// feed gave one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NowPlaying {
+    pub podcast: String,
+    pub episode: String,
+    pub started: i64,
+    pub duration: Option<i64>,
+}
+
+impl NowPlaying {
+    /// Fills in a now-playing template, given the number of seconds
+    /// played so far. The template can contain `{podcast}`, `{episode}`,
+    /// `{elapsed}`, and `{duration}`.
+    pub fn format(&self, template: &str, elapsed: i64) -> String {
+        let duration = match self.duration {
+            Some(duration) => format_hms(duration),
+            None => "--:--:--".to_string(),
+        };
+        return template
+            .replace("{podcast}", &self.podcast)
+            .replace("{episode}", &self.episode)
+            .replace("{elapsed}", &format_hms(elapsed.max(0)))
+            .replace("{duration}", &duration);
+    }
+}
+
+impl Status {
+    /// Converts the status to JSON.
+    pub fn to_json(&self) -> Result<String> {
+        return serde_json::to_string_pretty(self).with_context(|| "Could not serialize status");
+    }
+}
+
+/// Writes the status to `path`. The file is written in full before it
+/// replaces the old one, so readers never see a half-written file.
+pub fn write_status(path: &Path, status: &Status) -> Result<()> {
+    let json = status.to_json()?;
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, json)
+        .with_context(|| format!("Could not write file: {}", tmp_path.to_string_lossy()))?;
+    fs::rename(&tmp_path, path)
+        .with_context(|| format!("Could not write file: {}", path.to_string_lossy()))?;
+    return Ok(());
+}
+
+/// Writes now-playing text to a file, or to a named pipe that a status
+/// bar reads from. Writing happens on a separate thread, as opening a
+/// named pipe waits until something opens it for reading; if several
+/// updates arrive in the meantime, only the latest one is written. The
+/// pipe is kept open between updates, so that readers see one line per
+/// update rather than the end of the stream after each one.
+#[derive(Debug)]
+pub struct NowPlayingWriter {
+    tx: Sender<String>,
+}
+
+impl NowPlayingWriter {
+    /// Starts the thread that writes to `path`.
+    pub fn new(path: PathBuf) -> Self {
+        let (tx, rx) = mpsc::channel::<String>();
+        thread::spawn(move || {
+            let mut pipe: Option<fs::File> = None;
+            while let Ok(mut text) = rx.recv() {
+                while let Ok(newer) = rx.try_recv() {
+                    text = newer;
+                }
+                if !is_fifo(&path) {
+                    let _ = write_now_playing(&path, &text);
+                    continue;
+                }
+                // if the last reader has gone, wait for the next one
+                for _ in 0..2 {
+                    if pipe.is_none() {
+                        pipe = fs::OpenOptions::new().write(true).open(&path).ok();
+                    }
+                    match pipe.as_mut().map(|pipe| writeln!(pipe, "{text}")) {
+                        Some(Ok(())) => break,
+                        _ => pipe = None,
+                    }
+                }
+            }
+        });
+        return Self { tx: tx };
+    }
+
+    /// Sends text to be written; an empty string clears the output.
+    pub fn write(&self, text: String) {
+        let _ = self.tx.send(text);
+    }
+}
+
+/// Replaces the contents of a now-playing file with one line of text,
+/// in the same way as the status file.
+pub fn write_now_playing(path: &Path, text: &str) -> Result<()> {
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, format!("{text}\n"))
+        .with_context(|| format!("Could not write file: {}", tmp_path.to_string_lossy()))?;
+    fs::rename(&tmp_path, path)
+        .with_context(|| format!("Could not write file: {}", path.to_string_lossy()))?;
+    return Ok(());
+}
+
+/// Indicates whether `path` is a named pipe.
+pub fn is_fifo(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        return fs::metadata(path).is_ok_and(|meta| meta.file_type().is_fifo());
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        return false;
+    }
+}
+
+
+// TESTS -----------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_json() {
+        let status = Status {
+            now_playing: Some(NowPlaying {
+                podcast: "A Podcast".to_string(),
+                episode: "Episode \"1\"".to_string(),
+                started: 1650000000,
+                duration: None,
+            }),
+            podcasts: 2,
+            unplayed: 5,
+            downloads: 1,
+            syncing: 0,
+        };
+        let json: serde_json::Value = serde_json::from_str(&status.to_json().unwrap()).unwrap();
+        assert_eq!(json["now_playing"]["episode"], "Episode \"1\"");
+        assert_eq!(json["unplayed"], 5);
+        assert_eq!(json["downloads"], 1);
+    }
+
+    #[test]
+    fn now_playing_template() {
+        let mut now_playing = NowPlaying {
+            podcast: "A Podcast".to_string(),
+            episode: "Episode 1".to_string(),
+            started: 1650000000,
+            duration: Some(3725),
+        };
+        assert_eq!(
+            now_playing.format("{podcast}: {episode} [{elapsed}/{duration}]", 75),
+            "A Podcast: Episode 1 [00:01:15/01:02:05]"
+        );
+        now_playing.duration = None;
+        assert_eq!(now_playing.format("{elapsed} of {duration}", 0), "00:00:00 of --:--:--");
+    }
+}
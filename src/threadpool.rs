@@ -1,6 +1,11 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
 
+use tokio::runtime::Runtime;
+use tokio::sync::{Notify, Semaphore};
+
 // Much of the threadpool implementation here was taken directly from
 // the Rust Book: https://doc.rust-lang.org/book/ch20-02-multithreaded.html
 // and https://doc.rust-lang.org/book/ch20-03-graceful-shutdown-and-cleanup.html
@@ -61,6 +66,96 @@ impl Drop for Threadpool {
     }
 }
 
+/// Runs network jobs (syncing feeds and downloading episodes) as tasks
+/// on an async runtime, so that many of them can wait on the network at
+/// once without needing a thread each. At most `limit` jobs run at the
+/// same time, and the rest wait their turn.
+#[derive(Debug, Clone)]
+pub struct TaskPool {
+    runtime: Arc<Runtime>,
+    permits: Arc<Semaphore>,
+}
+
+impl TaskPool {
+    /// Creates a new TaskPool, with its own runtime, that runs up to
+    /// `limit` jobs at a time.
+    pub fn new(limit: usize) -> TaskPool {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .enable_all()
+            .build()
+            .expect("Could not start async runtime");
+        return TaskPool {
+            runtime: Arc::new(runtime),
+            permits: Arc::new(Semaphore::new(limit)),
+        };
+    }
+
+    /// Creates a TaskPool that shares this one's runtime, but has its
+    /// own limit on the number of jobs running at a time.
+    pub fn with_limit(&self, limit: usize) -> TaskPool {
+        return TaskPool {
+            runtime: self.runtime.clone(),
+            permits: Arc::new(Semaphore::new(limit)),
+        };
+    }
+
+    /// Adds a new job to the pool, which starts as soon as fewer than
+    /// `limit` other jobs are running.
+    pub fn spawn<F>(&self, job: F)
+    where F: Future<Output = ()> + Send + 'static {
+        let permits = self.permits.clone();
+        self.runtime.spawn(async move {
+            let _permit = permits.acquire_owned().await.expect("Task pool error");
+            job.await;
+        });
+    }
+}
+
+/// Flag shared between the main controller and the jobs it starts, so
+/// that jobs can be stopped early. Jobs on a TaskPool can wait on
+/// `cancelled()` to stop right away; jobs on a Threadpool have to check
+/// the flag themselves, since a worker thread cannot be interrupted
+/// from outside.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<CancelState>);
+
+#[derive(Debug, Default)]
+struct CancelState {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /// Tells every job holding a copy of this token to stop.
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::SeqCst);
+        self.0.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        return self.0.cancelled.load(Ordering::SeqCst);
+    }
+
+    /// Waits until the token is cancelled (or returns right away if it
+    /// already has been).
+    pub async fn cancelled(&self) {
+        let notified = self.0.notify.notified();
+        tokio::pin!(notified);
+        // registers for the notification before checking the flag, so
+        // a cancellation in between is not missed
+        notified.as_mut().enable();
+        if self.is_cancelled() {
+            return;
+        }
+        notified.await;
+    }
+}
+
 type Job = Box<dyn FnOnce() + Send + 'static>;
 
 /// Messages used by Threadpool to communicate with Workers.
@@ -97,3 +192,64 @@ impl Worker {
         };
     }
 }
+
+
+// TESTS -----------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use std::time::Duration;
+
+    #[test]
+    fn task_pool_limit() {
+        let pool = TaskPool::new(2);
+        let running = Arc::new(AtomicUsize::new(0));
+        let most = Arc::new(AtomicUsize::new(0));
+        let (tx, rx) = mpsc::channel();
+        for _ in 0..6 {
+            let running = running.clone();
+            let most = most.clone();
+            let tx = tx.clone();
+            pool.spawn(async move {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                most.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+                tx.send(()).unwrap();
+            });
+        }
+        for _ in 0..6 {
+            rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        }
+        assert_eq!(most.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn cancel_wakes_jobs() {
+        let pool = TaskPool::new(4);
+        let cancel = CancelToken::new();
+        let (tx, rx) = mpsc::channel();
+        for _ in 0..2 {
+            let cancel = cancel.clone();
+            let tx = tx.clone();
+            pool.spawn(async move {
+                cancel.cancelled().await;
+                tx.send(()).unwrap();
+            });
+        }
+        assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+        cancel.cancel();
+        assert!(cancel.is_cancelled());
+        for _ in 0..2 {
+            rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        }
+
+        // jobs that start waiting after the cancellation stop right away
+        pool.spawn(async move {
+            cancel.cancelled().await;
+            tx.send(()).unwrap();
+        });
+        rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    }
+}
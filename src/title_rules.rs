@@ -0,0 +1,119 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+
+use crate::types::Podcast;
+
+/// Rules that clean up episode titles before they are shown or used to
+/// name downloaded files, e.g., to strip "Episode 123:" from the start
+/// of every title. Each rule is a pattern and the text that replaces
+/// every match of it; the replacement can refer to groups in the
+/// pattern as `$1`, `$name`, etc. Rules are applied in order.
+#[derive(Debug, Clone, Default)]
+pub struct TitleRules {
+    rules: Vec<(Regex, String)>,
+}
+
+impl TitleRules {
+    /// Compiles a list of patterns and their replacements.
+    pub fn new<'a, I>(rules: I) -> Result<Self>
+    where I: IntoIterator<Item = (&'a str, &'a str)> {
+        let mut compiled = Vec::new();
+        for (pattern, replacement) in rules {
+            if pattern.is_empty() {
+                continue;
+            }
+            let regex = Regex::new(pattern)
+                .with_context(|| format!("Invalid title pattern: {pattern}"))?;
+            compiled.push((regex, replacement.to_string()));
+        }
+        return Ok(Self { rules: compiled });
+    }
+
+    /// Returns a copy of these rules with the rule of a single podcast
+    /// added at the end, if it has one. A podcast's rule that does not
+    /// compile is skipped, as it was checked when it was entered.
+    pub fn for_podcast(&self, podcast: &Podcast) -> Self {
+        let mut rules = self.clone();
+        if let Some(pattern) = podcast.title_pattern.as_deref() {
+            let replacement = podcast.title_replacement.as_deref().unwrap_or("");
+            if let Ok(extra) = Self::new([(pattern, replacement)]) {
+                rules.rules.extend(extra.rules);
+            }
+        }
+        return rules;
+    }
+
+    /// Indicates whether there are no rules to apply.
+    pub fn is_empty(&self) -> bool {
+        return self.rules.is_empty();
+    }
+
+    /// Applies the rules to a title. Returns None if the rules leave the
+    /// title as it was, or would leave nothing of it.
+    pub fn apply(&self, title: &str) -> Option<String> {
+        let mut clean = title.to_string();
+        for (regex, replacement) in self.rules.iter() {
+            clean = regex.replace_all(&clean, replacement.as_str()).to_string();
+        }
+        let clean = clean.trim();
+        if clean.is_empty() || clean == title {
+            return None;
+        }
+        return Some(clean.to_string());
+    }
+
+    /// Cleans up the titles of all episodes of a podcast, using these
+    /// rules along with the podcast's own.
+    pub fn clean_podcast(&self, podcast: &Podcast) {
+        let rules = self.for_podcast(podcast);
+        let mut episodes = podcast.episodes.borrow_map();
+        for ep in episodes.values_mut() {
+            ep.clean_title = if rules.is_empty() {
+                None
+            } else {
+                rules.apply(&ep.title)
+            };
+        }
+    }
+}
+
+/// Checks that a pattern entered by the user is a valid regex.
+pub fn validate(pattern: &str) -> Result<()> {
+    return TitleRules::new([(pattern, "")]).map(|_| ());
+}
+
+
+// TESTS -----------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_prefix_and_suffix() {
+        let rules =
+            TitleRules::new([(r"^Episode \d+:\s*", ""), (r"\s*\| Big Network$", "")]).unwrap();
+        assert_eq!(
+            rules.apply("Episode 123: The Story | Big Network"),
+            Some("The Story".to_string())
+        );
+        assert_eq!(rules.apply("The Story"), None);
+    }
+
+    #[test]
+    fn replace_with_groups() {
+        let rules = TitleRules::new([(r"^Ep\. (\d+) - (.*)$", "$2 (#$1)")]).unwrap();
+        assert_eq!(rules.apply("Ep. 7 - Trains"), Some("Trains (#7)".to_string()));
+    }
+
+    #[test]
+    fn never_empty() {
+        let rules = TitleRules::new([(".*", "")]).unwrap();
+        assert_eq!(rules.apply("Bonus"), None);
+    }
+
+    #[test]
+    fn invalid_pattern() {
+        assert!(validate("Episode (").is_err());
+        assert!(validate(r"^Episode \d+: ").is_ok());
+    }
+}
@@ -0,0 +1,323 @@
+use std::io::Read;
+use std::sync::mpsc::Sender;
+
+use anyhow::{anyhow, Context, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::html;
+use crate::http;
+use crate::threadpool::Threadpool;
+use crate::types::Message;
+
+/// Largest transcript file that will be downloaded, in bytes.
+const MAX_TRANSCRIPT_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Transcripts usually split the text into short segments of a few
+/// words each; consecutive segments from the same speaker are joined
+/// into paragraphs of roughly this many characters.
+const PARAGRAPH_LENGTH: usize = 300;
+
+lazy_static! {
+    /// Regex for a cue timing line in SRT or WebVTT files, e.g.,
+    /// "00:01:02,500 --> 00:01:05,000"; hours are optional in WebVTT
+    static ref RE_CUE_TIMING: Regex = Regex::new(
+        r"^(?:(\d+):)?(\d{1,2}):(\d{2})[,.](\d{1,3})\s+-->\s+"
+    ).expect("Regex error");
+
+    /// Regex for the speaker of a WebVTT cue, e.g., "<v Jane Doe>"
+    static ref RE_VTT_VOICE: Regex = Regex::new(r"<v(?:\.[^ >]*)?\s+([^>]+)>").expect("Regex error");
+}
+
+/// Transcript formats that can be displayed, in order of preference
+/// when a feed lists more than one transcript for an episode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TranscriptFormat {
+    Json,
+    WebVtt,
+    Srt,
+    Html,
+    Text,
+}
+
+impl TranscriptFormat {
+    /// Identifies the format from the MIME type given in the
+    /// `podcast:transcript` tag.
+    pub fn from_mime(mime: &str) -> Option<Self> {
+        return match mime.trim().to_lowercase().as_str() {
+            "application/json" => Some(TranscriptFormat::Json),
+            "text/vtt" => Some(TranscriptFormat::WebVtt),
+            "application/x-subrip" | "application/srt" | "text/srt" => {
+                Some(TranscriptFormat::Srt)
+            }
+            "text/html" => Some(TranscriptFormat::Html),
+            "text/plain" => Some(TranscriptFormat::Text),
+            _ => None,
+        };
+    }
+}
+
+/// One paragraph of a transcript. `start` is the number of seconds from
+/// the start of the episode, if the transcript includes timings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranscriptLine {
+    pub start: Option<f64>,
+    pub speaker: Option<String>,
+    pub text: String,
+}
+
+/// Enum used for communicating back to the main controller once a
+/// transcript has been fetched. Holds the podcast and episode ids.
+#[derive(Debug)]
+pub enum TranscriptMsg {
+    Fetched(i64, i64, Vec<TranscriptLine>),
+    Error(i64, i64),
+}
+
+/// Transcript file format defined by the Podcasting 2.0 namespace; see
+/// https://github.com/Podcastindex-org/podcast-namespace/blob/main/transcripts/transcripts.md
+#[derive(Debug, Deserialize)]
+struct JsonTranscript {
+    segments: Vec<JsonSegment>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonSegment {
+    speaker: Option<String>,
+    start_time: Option<f64>,
+    body: String,
+}
+
+/// Downloads and parses a transcript on a separate thread, and lets
+/// the main controller know when it is finished.
+pub fn fetch_transcript(
+    pod_id: i64,
+    ep_id: i64,
+    url: String,
+    mime: Option<String>,
+    max_retries: usize,
+    threadpool: &Threadpool,
+    tx_to_main: Sender<Message>,
+) {
+    threadpool.execute(move || {
+        let format = mime.as_deref().and_then(TranscriptFormat::from_mime);
+        let result = download_transcript(&url, max_retries)
+            .and_then(|text| parse_transcript(&text, format));
+        let msg = match result {
+            Ok(lines) => TranscriptMsg::Fetched(pod_id, ep_id, lines),
+            Err(_) => TranscriptMsg::Error(pod_id, ep_id),
+        };
+        tx_to_main
+            .send(Message::Transcript(msg))
+            .expect("Thread messaging error");
+    });
+}
+
+/// Downloads a transcript file and returns its contents.
+fn download_transcript(url: &str, max_retries: usize) -> Result<String> {
+    let response = http::get(url, max_retries)
+        .map_err(|err| anyhow!("Could not fetch transcript: {err:#}"))?;
+
+    let mut bytes = Vec::new();
+    response
+        .take(MAX_TRANSCRIPT_SIZE)
+        .read_to_end(&mut bytes)?;
+    return Ok(String::from_utf8_lossy(&bytes).to_string());
+}
+
+/// Parses a transcript into paragraphs. If the format is not known,
+/// it is guessed from the contents of the file.
+pub fn parse_transcript(text: &str, format: Option<TranscriptFormat>) -> Result<Vec<TranscriptLine>> {
+    let text = text.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+    let format = format.unwrap_or_else(|| {
+        let start = text.trim_start();
+        if start.starts_with("WEBVTT") {
+            TranscriptFormat::WebVtt
+        } else if start.starts_with('{') {
+            TranscriptFormat::Json
+        } else if text.contains("-->") {
+            TranscriptFormat::Srt
+        } else if start.starts_with('<') {
+            TranscriptFormat::Html
+        } else {
+            TranscriptFormat::Text
+        }
+    });
+
+    let lines = match format {
+        TranscriptFormat::Json => join_segments(parse_json(&text)?),
+        TranscriptFormat::WebVtt | TranscriptFormat::Srt => join_segments(parse_cues(&text)),
+        TranscriptFormat::Html => {
+            let rendered = html::render(&text);
+            let lines: Vec<String> =
+                rendered.lines.iter().map(|line| html::line_text(line)).collect();
+            parse_paragraphs(&lines.join("\n\n"))
+        }
+        TranscriptFormat::Text => parse_paragraphs(&text),
+    };
+    if lines.is_empty() {
+        return Err(anyhow!("Transcript is empty"));
+    }
+    return Ok(lines);
+}
+
+/// Parses a JSON transcript into its segments.
+fn parse_json(text: &str) -> Result<Vec<TranscriptLine>> {
+    let json: JsonTranscript =
+        serde_json::from_str(text).with_context(|| "Could not parse transcript file")?;
+    return Ok(json
+        .segments
+        .into_iter()
+        .map(|seg| TranscriptLine {
+            start: seg.start_time,
+            speaker: seg.speaker.filter(|s| !s.trim().is_empty()),
+            text: seg.body.trim().to_string(),
+        })
+        .collect());
+}
+
+/// Parses the cues of an SRT or WebVTT file. Both formats consist of
+/// blocks separated by blank lines, where each cue has a timing line
+/// followed by its text; any other blocks (the WebVTT header, notes,
+/// styles) have no timing line and are skipped.
+fn parse_cues(text: &str) -> Vec<TranscriptLine> {
+    let mut lines = Vec::new();
+    for block in text.split("\n\n") {
+        let mut block_lines = block.lines().skip_while(|l| !RE_CUE_TIMING.is_match(l.trim()));
+        let timing = match block_lines.next() {
+            Some(timing) => timing.trim(),
+            None => continue,
+        };
+        let start = RE_CUE_TIMING.captures(timing).map(|cap| {
+            let num = |i: usize| {
+                cap.get(i)
+                    .map_or(0.0, |m| m.as_str().parse::<f64>().unwrap_or(0.0))
+            };
+            let millis = cap.get(4).map_or(0.0, |m| {
+                // fractions are written with up to three digits
+                let digits = m.as_str();
+                digits.parse::<f64>().unwrap_or(0.0) / 10f64.powi(digits.len() as i32)
+            });
+            num(1) * 3600.0 + num(2) * 60.0 + num(3) + millis
+        });
+
+        let cue_text = block_lines.collect::<Vec<&str>>().join(" ");
+        let speaker = RE_VTT_VOICE
+            .captures(&cue_text)
+            .map(|cap| cap[1].trim().to_string());
+        let cue_text = html::to_text(&cue_text);
+        if !cue_text.trim().is_empty() {
+            lines.push(TranscriptLine {
+                start: start,
+                speaker: speaker,
+                text: cue_text.trim().to_string(),
+            });
+        }
+    }
+    return lines;
+}
+
+/// Splits plain text into paragraphs at blank lines.
+fn parse_paragraphs(text: &str) -> Vec<TranscriptLine> {
+    return text
+        .split("\n\n")
+        .map(|para| para.split_whitespace().collect::<Vec<&str>>().join(" "))
+        .filter(|para| !para.is_empty())
+        .map(|para| TranscriptLine {
+            start: None,
+            speaker: None,
+            text: para,
+        })
+        .collect();
+}
+
+/// Joins consecutive segments from the same speaker into paragraphs. A
+/// new paragraph starts when the speaker changes, or at the end of a
+/// sentence once the paragraph is long enough.
+fn join_segments(segments: Vec<TranscriptLine>) -> Vec<TranscriptLine> {
+    let mut lines: Vec<TranscriptLine> = Vec::new();
+    for seg in segments.into_iter() {
+        if let Some(last) = lines.last_mut() {
+            let sentence_end = last.text.ends_with(['.', '?', '!']);
+            let speaker_change = seg.speaker.is_some() && seg.speaker != last.speaker;
+            let long_enough = sentence_end && last.text.len() >= PARAGRAPH_LENGTH;
+            if !speaker_change && !long_enough {
+                last.text = format!("{} {}", last.text, seg.text);
+                continue;
+            }
+        }
+        lines.push(seg);
+    }
+    return lines;
+}
+
+
+// TESTS -----------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn srt_transcript() {
+        let text = "1\r\n00:00:01,500 --> 00:00:03,000\r\nHello and\r\nwelcome.\r\n\r\n2\r\n01:02:03,250 --> 01:02:05,000\r\nSecond cue.\r\n";
+        let lines = parse_transcript(text, None).unwrap();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].start, Some(1.5));
+        assert_eq!(lines[0].text, "Hello and welcome. Second cue.");
+    }
+
+    #[test]
+    fn vtt_transcript() {
+        let text = "WEBVTT\n\nNOTE a comment\n\ncue-1\n00:05.000 --> 00:07.000 align:start\n<v Jane Doe>Hi, I'm Jane.</v>\n\n00:08.000 --> 00:09.000\n<v Bob>And I'm &amp; Bob.\n";
+        let lines = parse_transcript(text, Some(TranscriptFormat::WebVtt)).unwrap();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].start, Some(5.0));
+        assert_eq!(lines[0].speaker, Some("Jane Doe".to_string()));
+        assert_eq!(lines[0].text, "Hi, I'm Jane.");
+        assert_eq!(lines[1].speaker, Some("Bob".to_string()));
+        assert_eq!(lines[1].text, "And I'm & Bob.");
+    }
+
+    #[test]
+    fn json_transcript() {
+        let text = r#"{
+            "version": "1.0.0",
+            "segments": [
+                { "speaker": "Jane", "startTime": 0.5, "endTime": 1.0, "body": "Hello" },
+                { "speaker": "Jane", "startTime": 1.0, "endTime": 1.5, "body": "there." },
+                { "speaker": "Bob", "startTime": 2.0, "endTime": 2.5, "body": "Hi!" }
+            ]
+        }"#;
+        let lines = parse_transcript(text, None).unwrap();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].text, "Hello there.");
+        assert_eq!(lines[1].start, Some(2.0));
+    }
+
+    #[test]
+    fn html_transcript() {
+        let text = "<p>First <b>paragraph</b>.</p><p>Second&nbsp;one.</p>";
+        let lines = parse_transcript(text, Some(TranscriptFormat::Html)).unwrap();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].text, "First paragraph.");
+    }
+
+    #[test]
+    fn empty_transcript() {
+        assert!(parse_transcript("WEBVTT\n\n", None).is_err());
+    }
+
+    #[test]
+    fn preferred_format() {
+        let mut formats = [
+            TranscriptFormat::from_mime("text/html").unwrap(),
+            TranscriptFormat::from_mime("application/x-subrip").unwrap(),
+            TranscriptFormat::from_mime("application/json").unwrap(),
+        ];
+        formats.sort();
+        assert_eq!(formats[0], TranscriptFormat::Json);
+        assert_eq!(TranscriptFormat::from_mime("audio/mpeg"), None);
+    }
+}
@@ -4,14 +4,29 @@ use std::path::PathBuf;
 use std::sync::{Arc, Mutex, MutexGuard};
 use unicode_segmentation::UnicodeSegmentation;
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Timelike, Utc};
 use lazy_static::lazy_static;
 use nohash_hasher::BuildNoHashHasher;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 
+use crate::chapters::ChapterMsg;
+use crate::config::{Glyphs, NewPodcastEpisodes};
+use crate::cover_art::ImageMsg;
+use crate::download_rules::DownloadRules;
 use crate::downloads::DownloadMsg;
 use crate::feeds::FeedMsg;
+use crate::hooks::HookMsg;
+use crate::play_file::PlayerMsg;
+use crate::remote::RemoteMsg;
+use crate::scrobble::ScrobbleMsg;
+use crate::secrets;
+use crate::smart_filters::SmartFilter;
+use crate::transcript::TranscriptMsg;
 use crate::ui::UiMsg;
+use crate::updates::UpdateMsg;
+use crate::watch::WatchMsg;
+use crate::webhooks::WebhookMsg;
 
 lazy_static! {
     /// Regex for removing "A", "An", and "The" from the beginning of
@@ -25,6 +40,43 @@ pub trait Menuable {
     fn get_id(&self) -> i64;
     fn get_title(&self, length: usize) -> String;
     fn is_played(&self) -> bool;
+
+    /// Returns the markers for the state of the item (e.g., downloaded
+    /// or played), from those in `glyphs`; `queued` indicates whether
+    /// the item is in the play queue. Only episodes are marked.
+    fn get_markers(&self, _glyphs: &Glyphs, _queued: bool) -> String {
+        return String::new();
+    }
+
+    /// Returns the title as with `get_title()`, but starting with
+    /// `markers` instead of the usual ones.
+    fn get_marked_title(&self, length: usize, _markers: &str) -> String {
+        return self.get_title(length);
+    }
+
+    /// Returns the name of the group the item is shown under in its
+    /// menu, given the groups made up of tags in `tag_groups`. Only
+    /// podcasts are grouped.
+    fn get_group(&self, _tag_groups: &[TagGroup]) -> Option<String> {
+        return None;
+    }
+
+    /// Returns the IDs of the items nested under the item in the tree
+    /// layout, in the order they are shown. Only podcasts have nested
+    /// items: their (filtered) episodes.
+    fn get_children(&self) -> Vec<i64> {
+        return Vec::new();
+    }
+
+    /// Calls `f` with the nested item with the given ID, if there is
+    /// one.
+    fn map_child(&self, _id: i64, _f: &mut dyn FnMut(&dyn Menuable)) {}
+
+    /// Returns the text that finding in a menu looks through for what
+    /// the user types. Only podcasts and episodes can be found.
+    fn get_find_text(&self) -> String {
+        return String::new();
+    }
 }
 
 /// Struct holding data about an individual podcast feed. This includes a
@@ -33,24 +85,332 @@ pub trait Menuable {
 pub struct Podcast {
     pub id: i64,
     pub title: String,
+    pub custom_title: Option<String>,
     pub sort_title: String,
     pub url: String,
     pub description: Option<String>,
     pub author: Option<String>,
     pub explicit: Option<bool>,
     pub last_checked: DateTime<Utc>,
+    pub image_url: Option<String>,
+    pub categories: Vec<String>,
+    pub language: Option<String>,
+    pub website: Option<String>,
+    pub funding_url: Option<String>,
+    pub funding_text: Option<String>,
+    pub notify: bool,
+    pub archived: bool,
+    pub include_pattern: Option<String>,
+    pub exclude_pattern: Option<String>,
+    pub title_pattern: Option<String>,
+    pub title_replacement: Option<String>,
+    pub show_pattern: Option<String>,
+    pub hide_pattern: Option<String>,
+    pub group: Option<String>,
+    pub overrides: PodcastOverrides,
+    pub auth: FeedAuth,
+    pub validators: FeedValidators,
+    pub schedule: FeedSchedule,
     pub episodes: LockVec<Episode>,
 }
 
+/// Settings of one podcast that take the place of the global settings
+/// in config.toml. `None` means the global setting applies.
+/// `auto_download` limits how many new episodes are downloaded
+/// automatically after a sync, and `keep_downloads` limits how many
+/// downloaded episodes are kept before the oldest files are deleted
+/// (0 meaning there is no limit).
+/// `preferred_enclosure` picks which version of an episode to download
+/// when the feed offers several (see `choose_enclosure()`).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PodcastOverrides {
+    pub download_dir: Option<String>,
+    pub play_command: Option<String>,
+    pub auto_download: Option<i64>,
+    pub keep_downloads: Option<i64>,
+    pub preferred_enclosure: Option<String>,
+}
+
+/// Credentials and extra HTTP headers for a private feed, sent when
+/// fetching the feed and when downloading its episodes. Each header is
+/// stored as a "Name: value" line. `accept_invalid_certs` turns off
+/// TLS certificate checks for the feed and its episodes, for
+/// self-hosted servers with certificates that cannot be checked. A
+/// password kept in the system keyring is not read until it is needed;
+/// until then, `password` is None and `keyring_entry` names it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeedAuth {
+    pub user: Option<String>,
+    pub password: Option<String>,
+    #[serde(default)]
+    pub headers: Vec<String>,
+    #[serde(default)]
+    pub accept_invalid_certs: bool,
+    #[serde(skip)]
+    pub keyring_entry: Option<String>,
+}
+
+impl FeedAuth {
+    /// Fills in the password from the keyring, if it is kept there.
+    /// This can block for a while, so on the task pool it is run with
+    /// `feeds::load_password()`.
+    pub fn with_password(mut self) -> Self {
+        if self.password.is_none() {
+            self.password = self.keyring_entry.as_deref().and_then(secrets::get);
+        }
+        return self;
+    }
+
+    /// Parses headers entered as "Name: value" separated by "|". Returns
+    /// None if any of them is not in that form.
+    pub fn parse_headers(input: &str) -> Option<Vec<String>> {
+        let mut headers = Vec::new();
+        for header in input.split('|').map(|h| h.trim()).filter(|h| !h.is_empty()) {
+            match header.split_once(':') {
+                Some((name, _)) if !name.trim().is_empty() => headers.push(header.to_string()),
+                _ => return None,
+            }
+        }
+        return Some(headers);
+    }
+
+    /// Returns the headers split into names and values, skipping any
+    /// that are not in the form "Name: value".
+    pub fn header_pairs(&self) -> Vec<(&str, &str)> {
+        return self
+            .headers
+            .iter()
+            .filter_map(|header| header.split_once(':'))
+            .map(|(name, value)| (name.trim(), value.trim()))
+            .filter(|(name, _)| !name.is_empty())
+            .collect();
+    }
+}
+
+/// The ETag and Last-Modified headers from the last time a feed was
+/// fetched. These are sent back on the next sync, so that a feed that
+/// has not changed can reply with "304 Not Modified" instead of the
+/// whole feed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FeedValidators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+/// Longest ttl that feeds are trusted with, in minutes (one week).
+const MAX_TTL: i64 = 7 * 24 * 60;
+
+/// Hints from a feed about how often it is worth checking: `ttl` is
+/// the number of minutes the feed may be cached for, and the feed asks
+/// not to be checked during `skip_hours` (0 to 23, in UTC) or on
+/// `skip_days` (e.g., "Saturday"). As the ttl comes from the feed, it
+/// is capped at `MAX_TTL` minutes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FeedSchedule {
+    pub ttl: Option<i64>,
+    pub skip_hours: Vec<u32>,
+    pub skip_days: Vec<String>,
+}
+
+impl FeedSchedule {
+    /// Returns whether the feed may be checked at `now`, given that it
+    /// was last checked at `last_checked`.
+    pub fn allows_check(&self, last_checked: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        if let Some(ttl) = self.ttl {
+            let next_check = last_checked.checked_add_signed(Duration::minutes(ttl.min(MAX_TTL)));
+            if next_check.is_some_and(|next_check| now < next_check) {
+                return false;
+            }
+        }
+        if self.skip_hours.contains(&now.hour()) {
+            return false;
+        }
+        let day = now.format("%A").to_string();
+        return !self.skip_days.contains(&day);
+    }
+}
+
+/// All of the settings of one podcast that are specific to shellcaster,
+/// as written to and read from a library export file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PodcastSettings {
+    #[serde(default = "default_notify")]
+    pub notify: bool,
+    pub custom_title: Option<String>,
+    pub include_pattern: Option<String>,
+    pub exclude_pattern: Option<String>,
+    pub title_pattern: Option<String>,
+    pub title_replacement: Option<String>,
+    pub show_pattern: Option<String>,
+    pub hide_pattern: Option<String>,
+    pub group: Option<String>,
+    #[serde(flatten)]
+    pub overrides: PodcastOverrides,
+    #[serde(flatten)]
+    pub auth: FeedAuth,
+}
+
+impl Default for PodcastSettings {
+    fn default() -> Self {
+        return Self {
+            notify: default_notify(),
+            custom_title: None,
+            include_pattern: None,
+            exclude_pattern: None,
+            title_pattern: None,
+            title_replacement: None,
+            show_pattern: None,
+            hide_pattern: None,
+            group: None,
+            overrides: PodcastOverrides::default(),
+            auth: FeedAuth::default(),
+        };
+    }
+}
+
+fn default_notify() -> bool {
+    return true;
+}
+
+/// A group of podcasts shown under a header in the podcast menu, made
+/// up of the podcasts with any of the `tags` (see `Podcast::tags()`),
+/// as set in the [groups] section of config.toml.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagGroup {
+    pub name: String,
+    pub tags: Vec<String>,
+}
+
+/// Compares the groups of two podcasts, to sort the podcast menu:
+/// podcasts that are not in a group come first, followed by the groups
+/// in alphabetical order. Names that only differ in case are still
+/// different groups, so they are kept apart rather than mixed together.
+pub fn compare_groups(a: Option<&str>, b: Option<&str>) -> Ordering {
+    return a
+        .map(str::to_lowercase)
+        .cmp(&b.map(str::to_lowercase))
+        .then_with(|| a.cmp(&b));
+}
+
+/// Cleans up the name of a group: white space around it is removed, and
+/// a name that only differs in case from one of the `known` groups is
+/// given the same spelling, so that both end up in one group. Returns
+/// None if the name is empty.
+pub fn normalize_group<'a, I>(name: &str, known: I) -> Option<String>
+where I: IntoIterator<Item = &'a str> {
+    let name = name.trim();
+    if name.is_empty() {
+        return None;
+    }
+    let lower = name.to_lowercase();
+    let same = known.into_iter().find(|group| group.trim().to_lowercase() == lower);
+    return Some(same.map(str::trim).unwrap_or(name).to_string());
+}
+
+/// Converts a category, language or author to the form used for tags.
+pub fn to_tag(value: &str) -> String {
+    return value
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join("-")
+        .to_lowercase();
+}
+
 impl Podcast {
+    /// Returns the title the podcast is shown with: the one the user
+    /// gave it, if any, or else the title from its feed.
+    pub fn display_title(&self) -> &str {
+        return self.custom_title.as_deref().unwrap_or(&self.title);
+    }
+
+    /// Returns the shellcaster-specific settings of the podcast.
+    pub fn settings(&self) -> PodcastSettings {
+        return PodcastSettings {
+            notify: self.notify,
+            custom_title: self.custom_title.clone(),
+            include_pattern: self.include_pattern.clone(),
+            exclude_pattern: self.exclude_pattern.clone(),
+            title_pattern: self.title_pattern.clone(),
+            title_replacement: self.title_replacement.clone(),
+            show_pattern: self.show_pattern.clone(),
+            hide_pattern: self.hide_pattern.clone(),
+            group: self.group.clone(),
+            overrides: self.overrides.clone(),
+            auth: self.auth.clone(),
+        };
+    }
+
+    /// Returns the tags the podcast gets automatically from its feed:
+    /// its categories, its language (both, e.g., "en-us" and "en") and
+    /// its author. Tags are lowercase, with spaces replaced by "-".
+    pub fn tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = self.categories.iter().map(|c| to_tag(c)).collect();
+        if let Some(lang) = &self.language {
+            let lang = to_tag(lang);
+            if let Some((base, _)) = lang.split_once('-') {
+                tags.push(base.to_string());
+            }
+            tags.push(lang);
+        }
+        if let Some(author) = &self.author {
+            tags.push(to_tag(author));
+        }
+        let mut unique = Vec::new();
+        for tag in tags {
+            if !tag.is_empty() && !unique.contains(&tag) {
+                unique.push(tag);
+            }
+        }
+        return unique;
+    }
+
+    /// Returns the group the podcast is shown under in the podcast
+    /// menu: the one the user put it in, if any, or else the first of
+    /// the `tag_groups` that has one of its tags.
+    pub fn group_name(&self, tag_groups: &[TagGroup]) -> Option<String> {
+        let known = tag_groups.iter().map(|group| group.name.as_str());
+        if let Some(group) = self.group.as_deref().and_then(|name| normalize_group(name, known)) {
+            return Some(group);
+        }
+        let tags = self.tags();
+        return tag_groups
+            .iter()
+            .find(|group| group.tags.iter().any(|tag| tags.contains(tag)))
+            .map(|group| group.name.clone());
+    }
+
     /// Counts and returns the number of unplayed episodes in the podcast.
-    fn num_unplayed(&self) -> usize {
+    pub fn num_unplayed(&self) -> usize {
         return self
             .episodes
             .map(|ep| !ep.is_played() as usize, false)
             .iter()
             .sum();
     }
+
+    /// Returns the rules deciding which new episodes of the podcast
+    /// are downloaded automatically; episodes that are hidden by the
+    /// skip rules are never downloaded automatically. Patterns are
+    /// checked when they are set, so an invalid one is simply ignored
+    /// here.
+    pub fn download_rules(&self) -> DownloadRules {
+        let rules = DownloadRules::new(
+            self.include_pattern.as_deref(),
+            self.exclude_pattern.as_deref(),
+        )
+        .unwrap_or_default();
+        return rules
+            .clone()
+            .with(self.show_pattern.as_deref(), self.hide_pattern.as_deref())
+            .unwrap_or(rules);
+    }
+
+    /// Returns the rules deciding which episodes of the podcast are
+    /// shown at all, e.g., to hide trailers or rebroadcasts.
+    pub fn skip_rules(&self) -> DownloadRules {
+        return DownloadRules::new(self.show_pattern.as_deref(), self.hide_pattern.as_deref())
+            .unwrap_or_default();
+    }
 }
 
 impl Menuable for Podcast {
@@ -60,8 +420,14 @@ impl Menuable for Podcast {
     }
 
     /// Returns the title for the podcast, up to length characters.
+    /// Archived podcasts are marked with "[A]".
     fn get_title(&self, length: usize) -> String {
         let mut title_length = length;
+        let title = if self.archived {
+            format!("[A] {}", self.display_title())
+        } else {
+            self.display_title().to_string()
+        };
 
         // if the size available is big enough, we add the unplayed data
         // to the end
@@ -69,20 +435,38 @@ impl Menuable for Podcast {
             let meta_str = format!("({}/{})", self.num_unplayed(), self.episodes.len(false));
             title_length = length - meta_str.chars().count() - 3;
 
-            let out = self.title.substr(0, title_length);
+            let out = title.substr(0, title_length);
 
             return format!(
                 " {out} {meta_str:>width$} ",
                 width = length - out.grapheme_len() - 3
             ); // this pads spaces between title and totals
         } else {
-            return format!(" {} ", self.title.substr(0, title_length - 2));
+            return format!(" {} ", title.substr(0, title_length.saturating_sub(2)));
         }
     }
 
     fn is_played(&self) -> bool {
         return self.num_unplayed() == 0;
     }
+
+    fn get_group(&self, tag_groups: &[TagGroup]) -> Option<String> {
+        return self.group_name(tag_groups);
+    }
+
+    fn get_children(&self) -> Vec<i64> {
+        return self.episodes.borrow_filtered_order().clone();
+    }
+
+    fn get_find_text(&self) -> String {
+        return self.display_title().to_string();
+    }
+
+    fn map_child(&self, id: i64, f: &mut dyn FnMut(&dyn Menuable)) {
+        if let Some(ep) = self.episodes.borrow_map().get(&id) {
+            f(ep);
+        }
+    }
 }
 
 impl PartialEq for Podcast {
@@ -108,7 +492,9 @@ impl Ord for Podcast {
 /// Struct holding data about an individual podcast episode. Most of this
 /// is metadata, but if the episode has been downloaded to the local
 /// machine, the filepath will be included here as well. `played`
-/// indicates whether the podcast has been marked as played or unplayed.
+/// indicates whether the podcast has been marked as played or unplayed,
+/// and `updated` whether a downloaded episode has since been re-released
+/// with a different file.
 #[derive(Debug, Clone)]
 pub struct Episode {
     pub id: i64,
@@ -119,25 +505,89 @@ pub struct Episode {
     pub description: String,
     pub pubdate: Option<DateTime<Utc>>,
     pub duration: Option<i64>,
+    pub image_url: Option<String>,
+    pub chapters_url: Option<String>,
+    pub chapters: Vec<Chapter>,
+    pub transcript_url: Option<String>,
+    pub transcript_type: Option<String>,
+    pub enclosures: Vec<Enclosure>,
+    pub enclosure_error: Option<String>,
     pub path: Option<PathBuf>,
     pub played: bool,
+    pub updated: bool,
+    pub starred: bool,
+    pub clean_title: Option<String>,
+    pub position: Option<i64>,
+    pub last_played: Option<i64>,
+    pub new: bool,
 }
 
 impl Episode {
+    /// Returns the title the episode is shown with: its title after the
+    /// title cleanup rules have been applied, if they changed it, or
+    /// else the title from its feed.
+    pub fn display_title(&self) -> &str {
+        return self.clean_title.as_deref().unwrap_or(&self.title);
+    }
+
     /// Formats the duration in seconds into an HH:MM:SS format.
     pub fn format_duration(&self) -> String {
         return match self.duration {
-            Some(dur) => {
-                let mut seconds = dur;
-                let hours = seconds / 3600;
-                seconds -= hours * 3600;
-                let minutes = seconds / 60;
-                seconds -= minutes * 60;
-                format!("{hours:02}:{minutes:02}:{seconds:02}")
-            }
+            Some(dur) => format_hms(dur),
             None => "--:--:--".to_string(),
         };
     }
+
+    /// Indicates whether the episode was left partway through the last
+    /// time it was played.
+    pub fn is_in_progress(&self) -> bool {
+        return self.position.is_some_and(|pos| pos > 0);
+    }
+
+    /// Returns the URL to download the episode from: that of the version
+    /// that best fits `preference` (see `choose_enclosure()`), or else
+    /// the main enclosure from the feed.
+    pub fn download_url(&self, preference: Option<&str>) -> String {
+        return preference
+            .and_then(|pref| choose_enclosure(&self.enclosures, pref))
+            .map(|enc| enc.url.clone())
+            .unwrap_or_else(|| self.url.clone());
+    }
+
+    /// Formats how far into the episode playback has got, e.g.,
+    /// "00:12:34 of 00:45:00", or just "00:12:34" if the length of the
+    /// episode is not known.
+    pub fn format_position(&self) -> String {
+        let position = format_hms(self.position.unwrap_or(0));
+        return match self.duration {
+            Some(_) => format!("{position} of {}", self.format_duration()),
+            None => position,
+        };
+    }
+}
+
+/// Formats a number of seconds into an HH:MM:SS format.
+pub fn format_hms(mut seconds: i64) -> String {
+    let hours = seconds / 3600;
+    seconds -= hours * 3600;
+    let minutes = seconds / 60;
+    seconds -= minutes * 60;
+    return format!("{hours:02}:{minutes:02}:{seconds:02}");
+}
+
+/// Formats a number of bytes, e.g., "1.5 GB".
+pub fn format_size(bytes: u64) -> String {
+    let units = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    return match unit {
+        0 => format!("{bytes} B"),
+        _ => format!("{size:.1} {}", units[unit]),
+    };
 }
 
 impl Menuable for Episode {
@@ -147,13 +597,48 @@ impl Menuable for Episode {
     }
 
     /// Returns the title for the episode, up to length characters.
+    /// Starred episodes are marked with "*", and downloaded episodes
+    /// with "[D]" (or "[U]" if their download is out of date).
     fn get_title(&self, length: usize) -> String {
-        let out = match self.path {
-            Some(_) => {
-                let title = self.title.substr(0, length - 4);
-                format!("[D] {title}")
-            }
-            None => self.title.substr(0, length),
+        let markers = match self.path {
+            Some(_) if self.updated => "[U]",
+            Some(_) => "[D]",
+            None => "",
+        };
+        return self.get_marked_title(length, markers);
+    }
+
+    /// Returns the markers for the state of the episode, in the order
+    /// new, queued, downloaded (or updated), and played.
+    fn get_markers(&self, glyphs: &Glyphs, queued: bool) -> String {
+        let states = [
+            (self.new, &glyphs.new),
+            (queued, &glyphs.queued),
+            (self.path.is_some() && !self.updated, &glyphs.downloaded),
+            (self.path.is_some() && self.updated, &glyphs.updated),
+            (self.played, &glyphs.played),
+        ];
+        return states
+            .into_iter()
+            .filter(|(state, _)| *state)
+            .map(|(_, glyph)| glyph.as_str())
+            .collect();
+    }
+
+    /// Returns the title for the episode, up to length characters,
+    /// starting with `markers` if there are any.
+    fn get_marked_title(&self, length: usize, markers: &str) -> String {
+        let title = if self.starred {
+            format!("* {}", self.display_title())
+        } else {
+            self.display_title().to_string()
+        };
+        let out = if markers.is_empty() {
+            title.substr(0, length)
+        } else {
+            let markers_len = markers.graphemes(true).count();
+            let title = title.substr(0, length.saturating_sub(markers_len + 1));
+            format!("{markers} {title}")
         };
         if length > crate::config::EPISODE_PUBDATE_LENGTH {
             let dur = self.format_duration();
@@ -187,13 +672,97 @@ impl Menuable for Episode {
                 width = length - out_added.grapheme_len() - 3
             );
         } else {
-            return format!(" {} ", out.substr(0, length - 2));
+            return format!(" {} ", out.substr(0, length.saturating_sub(2)));
         }
     }
 
     fn is_played(&self) -> bool {
         return self.played;
     }
+
+    fn get_find_text(&self) -> String {
+        return self.display_title().to_string();
+    }
+}
+
+/// One of the files an episode is available as, e.g., a smaller or
+/// Opus version listed with the Podcasting 2.0 `alternateEnclosure`
+/// tag. `bitrate` is in bits per second, and `length` in bytes. The
+/// `enclosures` of an episode list all of its versions, starting with
+/// the main one, or none if the feed does not list any others.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Enclosure {
+    pub url: String,
+    pub mime: Option<String>,
+    pub bitrate: Option<i64>,
+    pub length: Option<i64>,
+    pub title: Option<String>,
+}
+
+impl Enclosure {
+    /// Indicates whether the enclosure is in `format`, which may be a
+    /// full MIME type (e.g., "audio/ogg"), the second part of one
+    /// ("ogg"), a codec ("opus"), or a file extension ("mp3").
+    pub fn has_format(&self, format: &str) -> bool {
+        let format = format.to_lowercase();
+        let mime = self.mime.as_deref().unwrap_or("").to_lowercase();
+        let (mime_type, params) = mime.split_once(';').unwrap_or((&mime, ""));
+        let mime_type = mime_type.trim();
+        let subtype = mime_type.split('/').nth(1).unwrap_or("");
+        let path = self.url.split(['?', '#']).next().unwrap_or("");
+        let extension = path
+            .rsplit_once('.')
+            .map(|(_, ext)| ext.to_lowercase())
+            .filter(|ext| !ext.contains('/'));
+        return mime_type == format
+            || subtype == format
+            || params.contains(&format)
+            || self.title.as_deref().is_some_and(|t| t.eq_ignore_ascii_case(&format))
+            || extension.as_deref() == Some(format.as_str());
+    }
+
+    /// Describes the enclosure for the list of versions of an episode,
+    /// e.g., "audio/opus, 32 kbps, 12.0 MB".
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(title) = &self.title {
+            parts.push(title.clone());
+        }
+        parts.push(self.mime.clone().unwrap_or_else(|| "unknown type".to_string()));
+        if let Some(bitrate) = self.bitrate {
+            parts.push(format!("{} kbps", bitrate / 1000));
+        }
+        if let Some(length) = self.length.filter(|len| *len > 0) {
+            parts.push(format_size(length as u64));
+        }
+        return parts.join(", ");
+    }
+}
+
+/// Picks the version of an episode that best fits `preference`: a list
+/// of choices separated by commas, tried in order, each of which is
+/// "smallest", "largest", or a format (see `Enclosure::has_format()`).
+/// The size of a version is judged by its bitrate, or its length if
+/// the bitrate is not known. Returns None if nothing fits.
+pub fn choose_enclosure<'a>(
+    enclosures: &'a [Enclosure],
+    preference: &str,
+) -> Option<&'a Enclosure> {
+    let size = |enc: &&Enclosure| (enc.bitrate.is_some(), enc.bitrate.or(enc.length));
+    for choice in preference.split(',').map(|c| c.trim()).filter(|c| !c.is_empty()) {
+        let found = match choice.to_lowercase().as_str() {
+            "smallest" => enclosures
+                .iter()
+                .filter(|enc| size(enc).1.is_some())
+                .min_by_key(|enc| (!size(enc).0, size(enc).1)),
+            "largest" => enclosures.iter().max_by_key(size),
+            format => enclosures.iter().find(|enc| enc.has_format(format)),
+        };
+        if found.is_some() {
+            return found;
+        }
+    }
+    return None;
 }
 
 
@@ -208,6 +777,14 @@ pub struct PodcastNoId {
     pub author: Option<String>,
     pub explicit: Option<bool>,
     pub last_checked: DateTime<Utc>,
+    pub image_url: Option<String>,
+    pub categories: Vec<String>,
+    pub language: Option<String>,
+    pub website: Option<String>,
+    pub funding_url: Option<String>,
+    pub funding_text: Option<String>,
+    pub validators: FeedValidators,
+    pub schedule: FeedSchedule,
     pub episodes: Vec<EpisodeNoId>,
 }
 
@@ -221,6 +798,12 @@ pub struct EpisodeNoId {
     pub description: String,
     pub pubdate: Option<DateTime<Utc>>,
     pub duration: Option<i64>,
+    pub image_url: Option<String>,
+    pub chapters_url: Option<String>,
+    pub transcript_url: Option<String>,
+    pub transcript_type: Option<String>,
+    pub enclosures: Vec<Enclosure>,
+    pub enclosure_error: Option<String>,
 }
 
 /// Struct holding data about an individual podcast episode, specifically
@@ -232,6 +815,7 @@ pub struct NewEpisode {
     pub pod_id: i64,
     pub title: String,
     pub pod_title: String,
+    pub pubdate: Option<DateTime<Utc>>,
     pub selected: bool,
 }
 
@@ -266,6 +850,268 @@ impl Menuable for NewEpisode {
     }
 }
 
+/// Struct holding one episode in the inbox or the play queue, which
+/// list episodes from any podcast.
+#[derive(Debug, Clone)]
+pub struct ListedEpisode {
+    pub id: i64,
+    pub pod_id: i64,
+    pub title: String,
+    pub pod_title: String,
+    pub pubdate: Option<DateTime<Utc>>,
+}
+
+impl ListedEpisode {
+    /// Gathers the details of an episode for the inbox or the queue.
+    pub fn new(podcast: &Podcast, episode: &Episode) -> Self {
+        return Self {
+            id: episode.id,
+            pod_id: podcast.id,
+            title: episode.display_title().to_string(),
+            pod_title: podcast.display_title().to_string(),
+            pubdate: episode.pubdate,
+        };
+    }
+}
+
+impl Menuable for ListedEpisode {
+    /// Returns the database ID for the episode.
+    fn get_id(&self) -> i64 {
+        return self.id;
+    }
+
+    /// Returns the title for the episode and its podcast, up to length
+    /// characters, with the publication date on the right if there is
+    /// room.
+    fn get_title(&self, length: usize) -> String {
+        let title = format!(" {} ({}) ", self.title, self.pod_title);
+        if let Some(pubdate) = self.pubdate {
+            let date = format!("{} ", pubdate.format("%F"));
+            let date_len = date.grapheme_len();
+            if length > date_len + 10 {
+                let title = title.substr(0, length - date_len);
+                return format!("{title}{date:>width$}", width = length - title.grapheme_len());
+            }
+        }
+        return title.substr(0, length);
+    }
+
+    fn is_played(&self) -> bool {
+        return true;
+    }
+
+    /// Episodes can be found by their own title or that of their
+    /// podcast.
+    fn get_find_text(&self) -> String {
+        return format!("{} ({})", self.title, self.pod_title);
+    }
+}
+
+/// Ways of dealing with an episode in the inbox or in triage mode. Each
+/// one takes the episode out of the inbox.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriageAction {
+    Queue,
+    QueueNext,
+    Download,
+    Played,
+    Seen,
+    Hide,
+}
+
+/// Struct holding one line of the digest shown after syncing all
+/// podcasts. A line is either a podcast heading (with `ep_id` set to
+/// None), which can be expanded to show its new episodes, or one of
+/// those new episodes, or a podcast that could not be synced (with
+/// `failed` set). The `id` is only the position of the line in the
+/// digest, as podcast and episode IDs could overlap.
+#[derive(Debug, Clone)]
+pub struct DigestItem {
+    pub id: i64,
+    pub pod_id: i64,
+    pub ep_id: Option<i64>,
+    pub title: String,
+    pub num_new: usize,
+    pub expanded: bool,
+    pub failed: bool,
+}
+
+impl Menuable for DigestItem {
+    /// Returns the position of the line in the digest.
+    fn get_id(&self) -> i64 {
+        return self.id;
+    }
+
+    /// Returns the title for the podcast or episode, up to length
+    /// characters.
+    fn get_title(&self, length: usize) -> String {
+        let full_string = match self.ep_id {
+            Some(_) => format!("     {} ", self.title),
+            None if self.failed => format!(" ! {} (could not sync) ", self.title),
+            None => {
+                let arrow = if self.expanded { "▾" } else { "▸" };
+                format!(" {arrow} {} ({} new) ", self.title, self.num_new)
+            }
+        };
+        return full_string.substr(0, length);
+    }
+
+    /// Podcast headings are shown in bold, episodes are not.
+    fn is_played(&self) -> bool {
+        return self.ep_id.is_some();
+    }
+}
+
+/// One line in the fuzzy finder: a podcast (with `ep_id` set to None)
+/// or one of its episodes. The `id` is only the position of the item
+/// among everything that can be found, as podcast and episode IDs could
+/// overlap.
+#[derive(Debug, Clone)]
+pub struct FinderItem {
+    pub id: i64,
+    pub pod_id: i64,
+    pub ep_id: Option<i64>,
+    pub title: String,
+    pub pod_title: String,
+}
+
+impl Menuable for FinderItem {
+    /// Returns the position of the item in the finder.
+    fn get_id(&self) -> i64 {
+        return self.id;
+    }
+
+    /// Returns the title of the podcast, or of the episode followed by
+    /// that of its podcast, up to length characters.
+    fn get_title(&self, length: usize) -> String {
+        let full_string = match self.ep_id {
+            Some(_) => format!(" {} ({}) ", self.title, self.pod_title),
+            None => format!(" {} ", self.title),
+        };
+        return full_string.substr(0, length);
+    }
+
+    /// Podcasts are shown in bold, episodes are not.
+    fn is_played(&self) -> bool {
+        return self.ep_id.is_some();
+    }
+}
+
+/// Identifies a setting that can be changed in the podcast settings
+/// window, or a choice in one of the other lists shown in that window.
+/// `Resume` holds the podcast and episode IDs of an episode in progress,
+/// and `Enclosure` the ID of an episode and the position of one of its
+/// versions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingKind {
+    IncludePattern,
+    ExcludePattern,
+    TestRules,
+    DownloadDir,
+    PlayCommand,
+    AutoDownload,
+    KeepDownloads,
+    Login,
+    Headers,
+    Archived,
+    CustomTitle,
+    Group,
+    TitleRule,
+    ShowPattern,
+    HidePattern,
+    PreferredEnclosure,
+    InvalidCerts,
+    NewPodcast(NewPodcastEpisodes),
+    Filter(FilterType),
+    Resume(i64, i64),
+    Enclosure(i64, usize),
+    Setup(SetupStep),
+}
+
+/// Identifies a line of the setup window shown the first time
+/// shellcaster runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetupStep {
+    DownloadDir,
+    Player,
+    Import,
+    Theme,
+    Finish,
+}
+
+/// Struct holding one line of the podcast settings window: the name of
+/// a setting and its current value. The `id` is the position of the
+/// line in the window.
+#[derive(Debug, Clone)]
+pub struct PodcastSetting {
+    pub id: i64,
+    pub kind: SettingKind,
+    pub label: String,
+    pub value: String,
+}
+
+impl Menuable for PodcastSetting {
+    /// Returns the position of the line in the settings window.
+    fn get_id(&self) -> i64 {
+        return self.id;
+    }
+
+    /// Returns the name and value of the setting, up to length
+    /// characters.
+    fn get_title(&self, length: usize) -> String {
+        let full_string = if self.value.is_empty() {
+            format!(" {} ", self.label)
+        } else {
+            format!(" {}: {} ", self.label, self.value)
+        };
+        return full_string.substr(0, length);
+    }
+
+    fn is_played(&self) -> bool {
+        return true;
+    }
+}
+
+/// Struct holding one chapter of an episode. `start` is the number of
+/// seconds from the start of the episode, and `id` is the chapter's
+/// position in the list of chapters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chapter {
+    pub id: i64,
+    pub start: f64,
+    pub title: String,
+}
+
+impl Chapter {
+    /// Formats the start time of the chapter into an HH:MM:SS format.
+    pub fn format_start(&self) -> String {
+        let mut seconds = self.start as i64;
+        let hours = seconds / 3600;
+        seconds -= hours * 3600;
+        let minutes = seconds / 60;
+        seconds -= minutes * 60;
+        return format!("{hours:02}:{minutes:02}:{seconds:02}");
+    }
+}
+
+impl Menuable for Chapter {
+    /// Returns the position of the chapter in the list of chapters.
+    fn get_id(&self) -> i64 {
+        return self.id;
+    }
+
+    /// Returns the start time and title of the chapter, up to length
+    /// characters.
+    fn get_title(&self, length: usize) -> String {
+        let full_string = format!(" {}  {} ", self.format_start(), self.title);
+        return full_string.substr(0, length);
+    }
+
+    fn is_played(&self) -> bool {
+        return true;
+    }
+}
+
 /// Struct used to hold a vector of data inside a reference-counted
 /// mutex, to allow for multiple owners of mutable data.
 /// Primarily, the LockVec is used to provide methods that abstract
@@ -424,6 +1270,22 @@ impl<T: Clone + Menuable> LockVec<T> {
             .collect();
     }
 
+    /// Sorts the filtered order, i.e., the order the items are shown
+    /// in, using the comparator function `compare`. The order itself is
+    /// kept as the items were loaded, so code that goes through all of
+    /// the items does not depend on how the user sorted the lists.
+    pub fn sort_filtered_by<F>(&self, mut compare: F)
+    where F: FnMut(&T, &T) -> Ordering {
+        let (map, _unused, mut filtered_order) = self.borrow();
+        drop(_unused);
+        filtered_order.sort_by(|a, b| {
+            compare(
+                map.get(a).expect("Index error in LockVec"),
+                map.get(b).expect("Index error in LockVec"),
+            )
+        });
+    }
+
     /// Returns the number of items in the LockVec.
     pub fn len(&self, filtered: bool) -> usize {
         if filtered {
@@ -477,6 +1339,15 @@ impl LockVec<Episode> {
         let ep_map = self.borrow_map();
         return ep_map.get(&ep_id).cloned();
     }
+
+    /// Returns the ids of all of the episodes, newest first, whichever
+    /// way the list is sorted. Episodes without a publication date come
+    /// last, in the order they were loaded.
+    pub fn ids_newest_first(&self) -> Vec<i64> {
+        let mut episodes = self.map(|ep| (ep.id, ep.pubdate), false);
+        episodes.sort_by_key(|(_, pubdate)| std::cmp::Reverse(*pubdate));
+        return episodes.into_iter().map(|(id, _)| id).collect();
+    }
 }
 
 
@@ -487,30 +1358,166 @@ pub enum Message {
     Ui(UiMsg),
     Feed(FeedMsg),
     Dl(DownloadMsg),
+    Watch(WatchMsg),
+    Image(ImageMsg),
+    Chapters(ChapterMsg),
+    Transcript(TranscriptMsg),
+    Hook(HookMsg),
+    Remote(RemoteMsg),
+    Update(UpdateMsg),
+    Player(PlayerMsg),
+    Scrobble(ScrobbleMsg),
+    Webhook(WebhookMsg),
 }
 
 
 /// Simple enum to designate the status of a filter. "Positive" and
 /// "Negative" cases represent, e.g., "played" vs. "unplayed".
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum FilterStatus {
     PositiveCases,
     NegativeCases,
     All,
 }
 
-/// Enum to identify which filters has been changed
-#[derive(Debug, Clone, Copy)]
+/// Enum to identify which filters has been changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FilterType {
     Played,
     Downloaded,
+    Starred,
+    Age,
+    Duration,
+    Smart,
+    Clear,
 }
 
-/// Struct holding information about all active filters.
-#[derive(Debug, Clone, Copy)]
+/// Filter on the length of episodes, in minutes. Episodes without a
+/// known duration are hidden while either limit is set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DurationFilter {
+    All,
+    Under(i64),
+    Over(i64),
+}
+
+impl DurationFilter {
+    /// The settings of the filter, in the order they are cycled through.
+    const CHOICES: [DurationFilter; 6] = [
+        DurationFilter::All,
+        DurationFilter::Under(15),
+        DurationFilter::Under(30),
+        DurationFilter::Under(60),
+        DurationFilter::Over(60),
+        DurationFilter::Over(120),
+    ];
+
+    /// Returns the next setting of the filter.
+    pub fn cycle(&self) -> Self {
+        return next_choice(&Self::CHOICES, self);
+    }
+
+    /// Indicates whether an episode with the given duration (in
+    /// seconds) passes the filter.
+    pub fn matches(&self, duration: Option<i64>) -> bool {
+        return match self {
+            Self::All => true,
+            Self::Under(mins) => duration.is_some_and(|secs| secs < mins * 60),
+            Self::Over(mins) => duration.is_some_and(|secs| secs > mins * 60),
+        };
+    }
+
+    /// Describes the filter, e.g., "Under 30 minutes".
+    pub fn label(&self) -> String {
+        let length = |mins: i64| match mins {
+            60 => "1 hour".to_string(),
+            mins if mins % 60 == 0 => format!("{} hours", mins / 60),
+            mins => format!("{mins} minutes"),
+        };
+        return match self {
+            Self::All => "Any duration".to_string(),
+            Self::Under(mins) => format!("Under {}", length(*mins)),
+            Self::Over(mins) => format!("Over {}", length(*mins)),
+        };
+    }
+}
+
+/// Filter on when episodes were published, as a number of days before
+/// now. Episodes without a known publication date are hidden while the
+/// filter is set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AgeFilter {
+    All,
+    LastDays(i64),
+}
+
+impl AgeFilter {
+    /// The settings of the filter, in the order they are cycled through.
+    const CHOICES: [AgeFilter; 5] = [
+        AgeFilter::All,
+        AgeFilter::LastDays(1),
+        AgeFilter::LastDays(7),
+        AgeFilter::LastDays(30),
+        AgeFilter::LastDays(365),
+    ];
+
+    /// Returns the next setting of the filter.
+    pub fn cycle(&self) -> Self {
+        return next_choice(&Self::CHOICES, self);
+    }
+
+    /// Indicates whether an episode with the given publication date
+    /// passes the filter.
+    pub fn matches(&self, pubdate: Option<DateTime<Utc>>) -> bool {
+        return match self {
+            Self::All => true,
+            Self::LastDays(days) => {
+                pubdate.is_some_and(|date| (Utc::now() - date).num_days() < *days)
+            }
+        };
+    }
+
+    /// Describes the filter, e.g., "Last 7 days".
+    pub fn label(&self) -> String {
+        return match self {
+            Self::All => "Any date".to_string(),
+            Self::LastDays(1) => "Last day".to_string(),
+            Self::LastDays(365) => "Last year".to_string(),
+            Self::LastDays(days) => format!("Last {days} days"),
+        };
+    }
+}
+
+/// Helper function that returns the choice after `current` in a list,
+/// going back to the first one after the last.
+fn next_choice<T: Copy + PartialEq>(choices: &[T], current: &T) -> T {
+    let idx = choices.iter().position(|c| c == current).map_or(0, |i| i + 1);
+    return choices[idx % choices.len()];
+}
+
+/// Struct holding information about all active filters, which all apply
+/// at once. `smart` is the index of the active saved filter from
+/// config.toml, if any. The filters are saved in the database, so they
+/// are kept across restarts (see `Filters::save()`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Filters {
     pub played: FilterStatus,
     pub downloaded: FilterStatus,
+    pub starred: FilterStatus,
+    pub age: AgeFilter,
+    pub duration: DurationFilter,
+    #[serde(skip)]
+    pub smart: Option<usize>,
+}
+
+/// The filters as they are saved in the database, with the saved filter
+/// given by name rather than by its place in config.toml.
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedFilters {
+    #[serde(flatten)]
+    filters: Filters,
+    smart_filter: Option<String>,
 }
 
 impl Default for Filters {
@@ -518,8 +1525,297 @@ impl Default for Filters {
         return Self {
             played: FilterStatus::All,
             downloaded: FilterStatus::All,
+            starred: FilterStatus::All,
+            age: AgeFilter::All,
+            duration: DurationFilter::All,
+            smart: None,
+        };
+    }
+}
+
+impl Filters {
+    /// Turns the filters into JSON to be saved in the database. The saved
+    /// filter is recorded by name, so that the same one is restored even
+    /// if others are added to or taken out of config.toml in between.
+    pub fn save(&self, smart_filters: &[SmartFilter]) -> String {
+        let saved = SavedFilters {
+            filters: *self,
+            smart_filter: self.smart_name(smart_filters).map(str::to_string),
+        };
+        return serde_json::to_string(&saved).unwrap_or_default();
+    }
+
+    /// Restores filters saved with `save()`. A saved filter that is no
+    /// longer in config.toml is left out; if the filters cannot be read
+    /// at all, none are used.
+    pub fn restore(saved: &str, smart_filters: &[SmartFilter]) -> Self {
+        return match serde_json::from_str::<SavedFilters>(saved) {
+            Ok(saved) => {
+                let mut filters = saved.filters;
+                filters.smart = saved
+                    .smart_filter
+                    .and_then(|name| smart_filters.iter().position(|f| f.name == name));
+                filters
+            }
+            Err(_) => Self::default(),
+        };
+    }
+
+    /// Returns the name of the active saved filter, if any.
+    pub fn smart_name<'a>(&self, smart_filters: &'a [SmartFilter]) -> Option<&'a str> {
+        return self
+            .smart
+            .and_then(|idx| smart_filters.get(idx))
+            .map(|filter| filter.name.as_str());
+    }
+
+    /// Changes one of the filters to its next setting, or clears all of
+    /// them. `num_smart` is the number of saved filters in config.toml.
+    pub fn change(&mut self, filter_type: FilterType, num_smart: usize) {
+        use FilterStatus::*;
+        match filter_type {
+            // the order that makes the most sense is different for
+            // each: played goes from all -> neg -> pos; downloaded
+            // and starred go from all -> pos -> neg; this is purely
+            // based on the idea that people are most likely to want to
+            // specifically find unplayed episodes, or downloaded
+            // episodes
+            FilterType::Played => {
+                self.played = match self.played {
+                    All => NegativeCases,
+                    NegativeCases => PositiveCases,
+                    PositiveCases => All,
+                };
+            }
+            FilterType::Downloaded | FilterType::Starred => {
+                let status = if filter_type == FilterType::Downloaded {
+                    &mut self.downloaded
+                } else {
+                    &mut self.starred
+                };
+                *status = match status {
+                    All => PositiveCases,
+                    PositiveCases => NegativeCases,
+                    NegativeCases => All,
+                };
+            }
+            FilterType::Age => self.age = self.age.cycle(),
+            FilterType::Duration => self.duration = self.duration.cycle(),
+            FilterType::Smart => {
+                // cycles through the saved filters, then back to none
+                // of them
+                let next = match self.smart {
+                    Some(idx) => idx + 1,
+                    None => 0,
+                };
+                self.smart = (next < num_smart).then_some(next);
+            }
+            FilterType::Clear => *self = Self::default(),
+        }
+    }
+
+    /// Describes the current setting of one of the filters, e.g.,
+    /// "Unplayed only".
+    pub fn describe(&self, filter_type: FilterType, smart_filters: &[SmartFilter]) -> String {
+        let status = |status: FilterStatus, all: &str, pos: &str, neg: &str| match status {
+            FilterStatus::All => all.to_string(),
+            FilterStatus::PositiveCases => pos.to_string(),
+            FilterStatus::NegativeCases => neg.to_string(),
+        };
+        return match filter_type {
+            FilterType::Played => {
+                status(self.played, "Played and unplayed", "Played only", "Unplayed only")
+            }
+            FilterType::Downloaded => status(
+                self.downloaded,
+                "Downloaded and undownloaded",
+                "Downloaded only",
+                "Undownloaded only",
+            ),
+            FilterType::Starred => {
+                status(self.starred, "Starred and unstarred", "Starred only", "Unstarred only")
+            }
+            FilterType::Age => self.age.label(),
+            FilterType::Duration => self.duration.label(),
+            FilterType::Smart => match self.smart.and_then(|idx| smart_filters.get(idx)) {
+                Some(filter) => filter.name.clone(),
+                None if smart_filters.is_empty() => "No saved filters in config".to_string(),
+                None => "No saved filter".to_string(),
+            },
+            FilterType::Clear => "None".to_string(),
+        };
+    }
+
+    /// Lists the filters that are active, e.g., "unplayed, under 30
+    /// minutes", or returns None if none of them are.
+    pub fn summary(&self, smart_filters: &[SmartFilter]) -> Option<String> {
+        let mut active = Vec::new();
+        let mut status = |status: FilterStatus, pos: &str, neg: &str| match status {
+            FilterStatus::All => (),
+            FilterStatus::PositiveCases => active.push(pos.to_string()),
+            FilterStatus::NegativeCases => active.push(neg.to_string()),
+        };
+        status(self.played, "played", "unplayed");
+        status(self.downloaded, "downloaded", "undownloaded");
+        status(self.starred, "starred", "unstarred");
+        if self.age != AgeFilter::All {
+            active.push(self.age.label().to_lowercase());
+        }
+        if self.duration != DurationFilter::All {
+            active.push(self.duration.label().to_lowercase());
+        }
+        if let Some(filter) = self.smart.and_then(|idx| smart_filters.get(idx)) {
+            active.push(filter.name.clone());
+        }
+        return (!active.is_empty()).then(|| active.join(", "));
+    }
+
+    /// Indicates whether an episode passes the filters, apart from the
+    /// saved filter, which is checked separately.
+    pub fn matches(&self, ep: &Episode) -> bool {
+        let status = |status: FilterStatus, value: bool| match status {
+            FilterStatus::All => true,
+            FilterStatus::PositiveCases => value,
+            FilterStatus::NegativeCases => !value,
+        };
+        return status(self.played, ep.is_played())
+            && status(self.downloaded, ep.path.is_some())
+            && status(self.starred, ep.starred)
+            && self.age.matches(ep.pubdate)
+            && self.duration.matches(ep.duration);
+    }
+}
+
+/// Enum to identify which list is being sorted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortTarget {
+    Podcasts,
+    Episodes,
+}
+
+/// Ways the podcast and episode lists can be sorted. Not every mode
+/// applies to both lists; see `SortMode::cycle()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortMode {
+    Title,
+    Newest,
+    Oldest,
+    Duration,
+    Unplayed,
+    Updated,
+}
+
+impl SortMode {
+    /// Returns the sort modes available for a list, in the order they
+    /// are cycled through.
+    pub fn modes(target: SortTarget) -> &'static [SortMode] {
+        return match target {
+            SortTarget::Podcasts => &[SortMode::Title, SortMode::Updated, SortMode::Unplayed],
+            SortTarget::Episodes => &[
+                SortMode::Newest,
+                SortMode::Oldest,
+                SortMode::Title,
+                SortMode::Duration,
+                SortMode::Unplayed,
+            ],
+        };
+    }
+
+    /// Returns the next sort mode for a list, wrapping around at the
+    /// end.
+    pub fn cycle(self, target: SortTarget) -> SortMode {
+        let modes = Self::modes(target);
+        let idx = modes.iter().position(|m| *m == self).map_or(0, |i| i + 1);
+        return modes[idx % modes.len()];
+    }
+
+    /// Parses a sort mode from the config file, falling back to the
+    /// default for the list if it is not valid there.
+    pub fn from_config(value: Option<&str>, target: SortTarget) -> SortMode {
+        let mode = match value {
+            Some("title") => SortMode::Title,
+            Some("newest") => SortMode::Newest,
+            Some("oldest") => SortMode::Oldest,
+            Some("duration") => SortMode::Duration,
+            Some("unplayed") => SortMode::Unplayed,
+            Some("updated") => SortMode::Updated,
+            _ => return Self::modes(target)[0],
+        };
+        if Self::modes(target).contains(&mode) {
+            return mode;
+        }
+        return Self::modes(target)[0];
+    }
+
+    /// Name of the sort mode, as used in the config file.
+    pub fn config_name(self) -> &'static str {
+        return match self {
+            SortMode::Title => "title",
+            SortMode::Newest => "newest",
+            SortMode::Oldest => "oldest",
+            SortMode::Duration => "duration",
+            SortMode::Unplayed => "unplayed",
+            SortMode::Updated => "updated",
+        };
+    }
+
+    /// Describes the sort mode for notifications.
+    pub fn description(self) -> &'static str {
+        return match self {
+            SortMode::Title => "title",
+            SortMode::Newest => "newest first",
+            SortMode::Oldest => "oldest first",
+            SortMode::Duration => "longest first",
+            SortMode::Unplayed => "unplayed first",
+            SortMode::Updated => "recently updated",
+        };
+    }
+
+    /// Compares two podcasts. Ties are broken by title.
+    pub fn compare_podcasts(self, a: &Podcast, b: &Podcast) -> Ordering {
+        let latest = |pod: &Podcast| pod.episodes.map(|ep| ep.pubdate, false).into_iter().max();
+        let order = match self {
+            SortMode::Updated => latest(b).cmp(&latest(a)),
+            SortMode::Unplayed => b.num_unplayed().cmp(&a.num_unplayed()),
+            _ => Ordering::Equal,
         };
+        return order.then_with(|| a.cmp(b));
     }
+
+    /// Compares two episodes. Ties are broken by publication date,
+    /// newest first.
+    pub fn compare_episodes(self, a: &Episode, b: &Episode) -> Ordering {
+        let order = match self {
+            SortMode::Title => a.title.to_lowercase().cmp(&b.title.to_lowercase()),
+            SortMode::Oldest => a.pubdate.cmp(&b.pubdate),
+            SortMode::Duration => b.duration.cmp(&a.duration),
+            SortMode::Unplayed => a.played.cmp(&b.played),
+            _ => Ordering::Equal,
+        };
+        return order.then_with(|| b.pubdate.cmp(&a.pubdate));
+    }
+}
+
+/// Totals across the whole library, shown in the statistics window.
+/// Hidden episodes are not counted. `listened` is the total duration of
+/// played episodes, in seconds, and `disk_usage` holds the size of the
+/// downloaded files of each podcast, by title, largest first.
+#[derive(Debug, Clone, Default)]
+pub struct LibraryStats {
+    pub podcasts: i64,
+    pub episodes: i64,
+    pub unplayed: i64,
+    pub downloaded: i64,
+    pub listened: i64,
+    pub added_last_week: i64,
+    pub disk_usage: Vec<(String, u64)>,
+}
+
+/// Struct holding the sort modes of the podcast and episode lists.
+#[derive(Debug, Clone, Copy)]
+pub struct Sorts {
+    pub podcasts: SortMode,
+    pub episodes: SortMode,
 }
 
 
@@ -546,3 +1842,136 @@ impl StringUtils for String {
     }
 }
 
+
+
+// TESTS -----------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn group_names_normalized() {
+        let known = ["News", "Tech"];
+        assert_eq!(normalize_group(" news ", known), Some("News".to_string()));
+        assert_eq!(normalize_group("Comedy", known), Some("Comedy".to_string()));
+        assert_eq!(normalize_group("  ", known), None);
+
+        // groups differing only in case are sorted apart, not mixed
+        let mut groups = vec![Some("news"), Some("Tech"), Some("News"), None, Some("news")];
+        groups.sort_by(|a, b| compare_groups(*a, *b));
+        assert_eq!(groups, vec![None, Some("News"), Some("news"), Some("news"), Some("Tech")]);
+    }
+    use chrono::TimeZone;
+
+    fn episode(id: i64, title: &str, day: Option<u32>) -> Episode {
+        return Episode {
+            id: id,
+            pod_id: 1,
+            title: title.to_string(),
+            url: String::new(),
+            guid: String::new(),
+            description: String::new(),
+            pubdate: day.map(|day| Utc.ymd(2022, 1, day).and_hms(12, 0, 0)),
+            duration: None,
+            image_url: None,
+            chapters_url: None,
+            chapters: Vec::new(),
+            transcript_url: None,
+            transcript_type: None,
+            enclosures: Vec::new(),
+            enclosure_error: None,
+            path: None,
+            played: false,
+            updated: false,
+            starred: false,
+            clean_title: None,
+            position: None,
+            last_played: None,
+            new: false,
+        };
+    }
+
+    #[test]
+    fn sort_keeps_order() {
+        let episodes = LockVec::new(vec![
+            episode(1, "Charlie", Some(3)),
+            episode(2, "Alpha", Some(2)),
+            episode(3, "Bravo", Some(1)),
+        ]);
+        episodes.sort_filtered_by(|a, b| a.title.cmp(&b.title));
+        assert_eq!(episodes.map(|ep| ep.id, true), vec![2, 3, 1]);
+        assert_eq!(episodes.map(|ep| ep.id, false), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn newest_first_ignores_sorting() {
+        let episodes = LockVec::new(vec![
+            episode(1, "Undated", None),
+            episode(2, "Oldest", Some(1)),
+            episode(3, "Newest", Some(9)),
+            episode(4, "Middle", Some(5)),
+        ]);
+        episodes.sort_filtered_by(|a, b| a.pubdate.cmp(&b.pubdate));
+        assert_eq!(episodes.ids_newest_first(), vec![3, 4, 2, 1]);
+    }
+
+    #[test]
+    fn schedule_caps_ttl() {
+        let last_checked = Utc.ymd(2022, 4, 15).and_hms(10, 0, 0);
+        let schedule = FeedSchedule {
+            ttl: Some(i64::MAX),
+            ..FeedSchedule::default()
+        };
+        assert!(!schedule.allows_check(last_checked, last_checked + Duration::days(6)));
+        assert!(schedule.allows_check(last_checked, last_checked + Duration::days(8)));
+    }
+
+    #[test]
+    fn narrow_titles() {
+        let ep = episode(1, "Episode", None);
+        for width in 0..4 {
+            assert!(ep.get_title(width).chars().count() <= width.max(2));
+        }
+    }
+
+    #[test]
+    fn saved_filter_kept_by_name() {
+        let smart = |names: &[&str]| -> Vec<SmartFilter> {
+            names
+                .iter()
+                .map(|name| SmartFilter::new(name.to_string(), "unplayed").unwrap())
+                .collect()
+        };
+        let mut filters = Filters::default();
+        filters.change(FilterType::Played, 0);
+        filters.smart = Some(1);
+        let saved = filters.save(&smart(&["Short", "Commute"]));
+
+        // another filter was added before it in config.toml
+        let restored = Filters::restore(&saved, &smart(&["News", "Short", "Commute"]));
+        assert_eq!(restored.smart, Some(2));
+        assert_eq!(restored.played, FilterStatus::NegativeCases);
+
+        // or it was taken out
+        assert_eq!(Filters::restore(&saved, &smart(&["Short"])).smart, None);
+        assert_eq!(Filters::restore("not json", &smart(&["Short"])), Filters::default());
+    }
+
+    #[test]
+    fn duration_filters() {
+        let under = DurationFilter::Under(30);
+        assert!(under.matches(Some(29 * 60)));
+        assert!(!under.matches(Some(30 * 60)));
+        assert!(!under.matches(None));
+        let over = DurationFilter::Over(120);
+        assert!(over.matches(Some(2 * 3600 + 1)));
+        assert!(!over.matches(Some(2 * 3600)));
+        assert!(DurationFilter::All.matches(None));
+
+        assert_eq!(under.label(), "Under 30 minutes");
+        assert_eq!(DurationFilter::Under(60).label(), "Under 1 hour");
+        assert_eq!(over.label(), "Over 2 hours");
+        assert_eq!(over.cycle(), DurationFilter::All);
+        assert_eq!(DurationFilter::All.cycle(), DurationFilter::Under(15));
+    }
+}
@@ -1,4 +1,6 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
 
 use crossterm::style::Color;
 use lazy_static::lazy_static;
@@ -17,6 +19,12 @@ lazy_static! {
 
 /// Holds information about the colors to use in the application. Tuple
 /// values represent (foreground, background), respectively.
+///
+/// The colors held as Options follow another color unless they are
+/// set: borders, titles, notifications and played items follow `normal`
+/// (played items are dimmed as well), the downloaded marker (e.g.,
+/// "[D]") keeps the colors of the rest of its line, and the borders and
+/// titles of popup windows follow `border` and `title`.
 #[derive(Debug, Clone)]
 pub struct AppColors {
     pub normal: (Color, Color),
@@ -24,6 +32,13 @@ pub struct AppColors {
     pub highlighted_active: (Color, Color),
     pub highlighted: (Color, Color),
     pub error: (Color, Color),
+    pub border: (Option<Color>, Option<Color>),
+    pub title: (Option<Color>, Option<Color>),
+    pub notification: (Option<Color>, Option<Color>),
+    pub played: (Option<Color>, Option<Color>),
+    pub downloaded: (Option<Color>, Option<Color>),
+    pub popup_border: (Option<Color>, Option<Color>),
+    pub popup_title: (Option<Color>, Option<Color>),
 }
 
 impl AppColors {
@@ -35,63 +50,140 @@ impl AppColors {
             highlighted_active: (Color::Black, Color::DarkYellow),
             highlighted: (Color::Black, Color::Grey),
             error: (Color::Red, Color::Black),
+            border: (None, None),
+            title: (None, None),
+            notification: (None, None),
+            played: (None, None),
+            downloaded: (None, None),
+            popup_border: (None, None),
+            popup_title: (None, None),
         };
     }
 
+    /// Returns the colors for the borders around panels.
+    pub fn border(&self) -> (Color, Color) {
+        return Self::or_normal(self.border, self.normal);
+    }
+
+    /// Returns the colors for the titles at the top of panels.
+    pub fn title(&self) -> (Color, Color) {
+        return Self::or_normal(self.title, self.normal);
+    }
+
+    /// Returns the colors for regular (non-error) notifications.
+    pub fn notification(&self) -> (Color, Color) {
+        return Self::or_normal(self.notification, self.normal);
+    }
+
+    /// Returns the colors for played podcasts and episodes.
+    pub fn played(&self) -> (Color, Color) {
+        return Self::or_normal(self.played, self.normal);
+    }
+
+    /// Helper function that fills in the colors of `colors` that are not
+    /// set from `other`.
+    fn or_normal(colors: (Option<Color>, Option<Color>), other: (Color, Color)) -> (Color, Color) {
+        return (colors.0.unwrap_or(other.0), colors.1.unwrap_or(other.1));
+    }
+
+    /// Indicates whether played items should be dimmed, which they are
+    /// unless their colors have been set.
+    pub fn dim_played(&self) -> bool {
+        return self.played == (None, None);
+    }
+
+    /// Returns the colors for the downloaded marker, on a line that is
+    /// otherwise shown in the colors `line`.
+    pub fn downloaded(&self, line: (Color, Color)) -> (Color, Color) {
+        return (
+            self.downloaded.0.unwrap_or(line.0),
+            self.downloaded.1.unwrap_or(line.1),
+        );
+    }
+
+    /// Returns a copy of these colors to use for popup windows, whose
+    /// borders and titles are drawn with the popup colors.
+    pub fn for_popups(&self) -> Self {
+        let mut colors = self.clone();
+        let border = Self::or_normal(self.popup_border, self.border());
+        let title = Self::or_normal(self.popup_title, self.title());
+        colors.border = (Some(border.0), Some(border.1));
+        colors.title = (Some(title.0), Some(title.1));
+        return colors;
+    }
+
+    /// Creates an AppColors struct from the default colors, then the
+    /// colors from the named theme in `themes_dir` (if any), and finally
+    /// any colors set in the "colors" section of config.toml.
+    pub fn from_theme(
+        themes_dir: &Path,
+        theme: Option<&str>,
+        overrides: Option<AppColorsFromToml>,
+    ) -> Result<Self> {
+        let mut colors = Self::default();
+        if let Some(theme) = theme {
+            colors.add_from_config(load_theme(themes_dir, theme)?);
+        }
+        if let Some(overrides) = overrides {
+            colors.add_from_config(overrides);
+        }
+        return Ok(colors);
+    }
+
     /// Reading in values that were set in the config file, this changes
     /// the associated colors. Note that this only modifies colors that
     /// were set in the config, so this is most useful in conjunction
     /// with `default()` to set default colors and then change
     /// the ones that the user has set.
     pub fn add_from_config(&mut self, config: AppColorsFromToml) {
-        if let Some(val) = config.normal_foreground {
-            if let Ok(v) = Self::color_from_str(&val) {
-                self.normal.0 = v;
-            }
-        }
-        if let Some(val) = config.normal_background {
-            if let Ok(v) = Self::color_from_str(&val) {
-                self.normal.1 = v;
-            }
-        }
-        if let Some(val) = config.bold_foreground {
-            if let Ok(v) = Self::color_from_str(&val) {
-                self.bold.0 = v;
-            }
-        }
-        if let Some(val) = config.bold_background {
-            if let Ok(v) = Self::color_from_str(&val) {
-                self.bold.1 = v;
-            }
-        }
-        if let Some(val) = config.highlighted_active_foreground {
-            if let Ok(v) = Self::color_from_str(&val) {
-                self.highlighted_active.0 = v;
-            }
-        }
-        if let Some(val) = config.highlighted_active_background {
-            if let Ok(v) = Self::color_from_str(&val) {
-                self.highlighted_active.1 = v;
-            }
-        }
-        if let Some(val) = config.highlighted_foreground {
-            if let Ok(v) = Self::color_from_str(&val) {
-                self.highlighted.0 = v;
-            }
-        }
-        if let Some(val) = config.highlighted_background {
-            if let Ok(v) = Self::color_from_str(&val) {
-                self.highlighted.1 = v;
-            }
-        }
-        if let Some(val) = config.error_foreground {
+        Self::set_color(&mut self.normal.0, config.normal_foreground);
+        Self::set_color(&mut self.normal.1, config.normal_background);
+        Self::set_color(&mut self.bold.0, config.bold_foreground);
+        Self::set_color(&mut self.bold.1, config.bold_background);
+        Self::set_color(
+            &mut self.highlighted_active.0,
+            config.highlighted_active_foreground,
+        );
+        Self::set_color(
+            &mut self.highlighted_active.1,
+            config.highlighted_active_background,
+        );
+        Self::set_color(&mut self.highlighted.0, config.highlighted_foreground);
+        Self::set_color(&mut self.highlighted.1, config.highlighted_background);
+        Self::set_color(&mut self.error.0, config.error_foreground);
+        Self::set_color(&mut self.error.1, config.error_background);
+        Self::set_optional_color(&mut self.border.0, config.border_foreground);
+        Self::set_optional_color(&mut self.border.1, config.border_background);
+        Self::set_optional_color(&mut self.title.0, config.title_foreground);
+        Self::set_optional_color(&mut self.title.1, config.title_background);
+        Self::set_optional_color(&mut self.notification.0, config.notification_foreground);
+        Self::set_optional_color(&mut self.notification.1, config.notification_background);
+        Self::set_optional_color(&mut self.played.0, config.played_foreground);
+        Self::set_optional_color(&mut self.played.1, config.played_background);
+        Self::set_optional_color(&mut self.downloaded.0, config.downloaded_foreground);
+        Self::set_optional_color(&mut self.downloaded.1, config.downloaded_background);
+        Self::set_optional_color(&mut self.popup_border.0, config.popup_border_foreground);
+        Self::set_optional_color(&mut self.popup_border.1, config.popup_border_background);
+        Self::set_optional_color(&mut self.popup_title.0, config.popup_title_foreground);
+        Self::set_optional_color(&mut self.popup_title.1, config.popup_title_background);
+    }
+
+    /// Helper function that changes `color` to the value given in
+    /// `val`, if the value was set and is a valid color.
+    fn set_color(color: &mut Color, val: Option<String>) {
+        if let Some(val) = val {
             if let Ok(v) = Self::color_from_str(&val) {
-                self.error.0 = v;
+                *color = v;
             }
         }
-        if let Some(val) = config.error_background {
+    }
+
+    /// As with `set_color()`, for colors that follow another color
+    /// until they are set.
+    fn set_optional_color(color: &mut Option<Color>, val: Option<String>) {
+        if let Some(val) = val {
             if let Ok(v) = Self::color_from_str(&val) {
-                self.error.1 = v;
+                *color = Some(v);
             }
         }
     }
@@ -143,6 +235,99 @@ impl AppColors {
             };
         }
     }
+
+    /// Returns each color as it is named in the "colors" section of
+    /// config.toml, along with its value, or None for colors that
+    /// follow another color.
+    pub fn settings(&self) -> Vec<(String, Option<Color>)> {
+        let set = |(fg, bg): (Color, Color)| (Some(fg), Some(bg));
+        let colors = [
+            ("normal", set(self.normal)),
+            ("bold", set(self.bold)),
+            ("highlighted_active", set(self.highlighted_active)),
+            ("highlighted", set(self.highlighted)),
+            ("error", set(self.error)),
+            ("border", self.border),
+            ("title", self.title),
+            ("notification", self.notification),
+            ("played", self.played),
+            ("downloaded", self.downloaded),
+            ("popup_border", self.popup_border),
+            ("popup_title", self.popup_title),
+        ];
+        return colors
+            .into_iter()
+            .flat_map(|(name, (fg, bg))| {
+                [(format!("{name}_foreground"), fg), (format!("{name}_background"), bg)]
+            })
+            .collect();
+    }
+
+    /// Writes a color the way it would be set in config.toml, i.e., the
+    /// reverse of `color_from_str()`.
+    pub fn color_to_str(color: Color) -> String {
+        return match color {
+            Color::Rgb { r, g, b } => format!("#{r:02x}{g:02x}{b:02x}"),
+            Color::Reset => "terminal".to_string(),
+            Color::AnsiValue(val) => format!("ansi({val})"),
+            color => format!("{color:?}").to_lowercase(),
+        };
+    }
+}
+
+
+/// Themes that come with shellcaster, which the setup window offers
+/// along with the installed ones.
+pub const BUNDLED_THEMES: [(&str, &str); 1] =
+    [("gruvbox", include_str!("../../themes/gruvbox.toml"))];
+
+/// Copies a theme that comes with shellcaster to `themes_dir`, unless a
+/// theme of that name is there already.
+pub fn install_theme(themes_dir: &Path, name: &str) -> Result<()> {
+    let path = theme_path(themes_dir, name);
+    let text = match BUNDLED_THEMES.iter().find(|(bundled, _)| *bundled == name) {
+        Some((_, text)) if !path.exists() => text,
+        _ => return Ok(()),
+    };
+    fs::create_dir_all(themes_dir).with_context(|| {
+        format!("Could not create themes folder: {}", themes_dir.to_string_lossy())
+    })?;
+    return fs::write(&path, text)
+        .with_context(|| format!("Could not write theme file: {}", path.to_string_lossy()));
+}
+
+/// Returns the path to the theme file with the given name, e.g.,
+/// `<themes_dir>/gruvbox.toml` for the theme "gruvbox".
+pub fn theme_path(themes_dir: &Path, name: &str) -> PathBuf {
+    let mut path = themes_dir.to_path_buf();
+    path.push(format!("{name}.toml"));
+    return path;
+}
+
+/// Returns the names of all themes in `themes_dir`, in alphabetical
+/// order. If the folder does not exist, the list is empty.
+pub fn list_themes(themes_dir: &Path) -> Vec<String> {
+    let mut themes: Vec<String> = match fs::read_dir(themes_dir) {
+        Ok(entries) => entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+            .filter_map(|path| path.file_stem().map(|s| s.to_string_lossy().to_string()))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    themes.sort();
+    return themes;
+}
+
+/// Reads the colors from a named theme file in `themes_dir`. Theme files
+/// use the same keys as the "colors" section of config.toml.
+pub fn load_theme(themes_dir: &Path, name: &str) -> Result<AppColorsFromToml> {
+    let path = theme_path(themes_dir, name);
+    let theme_string = fs::read_to_string(&path)
+        .with_context(|| format!("Could not read theme file: {}", path.to_string_lossy()))?;
+    return toml::from_str(&theme_string)
+        .with_context(|| format!("Could not parse theme file: {}", path.to_string_lossy()));
 }
 
 
@@ -163,6 +348,61 @@ mod tests {
         });
     }
 
+    #[test]
+    fn config_overrides_defaults() {
+        let config: AppColorsFromToml = toml::from_str(
+            "border_foreground = \"#fabd2f\"\nnormal_background = \"not a color\"",
+        )
+        .unwrap();
+        let mut colors = AppColors::default();
+        colors.add_from_config(config);
+        assert_eq!(colors.border().0, Color::Rgb {
+            r: 250,
+            g: 189,
+            b: 47
+        });
+        assert_eq!(colors.normal.1, Color::Black);
+    }
+
+    #[test]
+    fn optional_colors_follow_others() {
+        let mut colors = AppColors::default();
+        colors.add_from_config(
+            toml::from_str("normal_foreground = \"white\"\ntitle_foreground = \"blue\"").unwrap(),
+        );
+        assert_eq!(colors.played(), (Color::White, Color::Black));
+        assert!(colors.dim_played());
+        assert_eq!(colors.border(), (Color::White, Color::Black));
+        assert_eq!(colors.notification(), (Color::White, Color::Black));
+        assert_eq!(colors.for_popups().title(), (Color::Blue, Color::Black));
+        assert_eq!(colors.for_popups().border(), (Color::White, Color::Black));
+
+        colors.add_from_config(
+            toml::from_str("played_foreground = \"darkgrey\"\npopup_title_background = \"red\"")
+                .unwrap(),
+        );
+        assert_eq!(colors.played(), (Color::DarkGrey, Color::Black));
+        assert!(!colors.dim_played());
+        assert_eq!(colors.for_popups().title(), (Color::Blue, Color::Red));
+        assert_eq!(colors.title(), (Color::Blue, Color::Black));
+        assert_eq!(colors.downloaded(colors.bold), colors.bold);
+    }
+
+    #[test]
+    fn list_example_themes() {
+        let themes = list_themes(Path::new("themes"));
+        assert!(themes.contains(&"gruvbox".to_string()));
+        assert!(list_themes(Path::new("no/such/dir")).is_empty());
+    }
+
+    #[test]
+    fn color_to_config_value() {
+        for text in ["#fabd2f", "darkyellow", "terminal"] {
+            let color = AppColors::color_from_str(text).unwrap();
+            assert_eq!(AppColors::color_to_str(color), text);
+        }
+    }
+
     #[test]
     fn color_invalid_hex() {
         let color = String::from("#gg0000");
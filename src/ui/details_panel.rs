@@ -1,9 +1,18 @@
+use std::cmp::min;
+use std::ops::Range;
+use std::path::PathBuf;
 use std::rc::Rc;
 
 use chrono::{DateTime, Utc};
 use crossterm::style::{self, Stylize};
+use regex::Regex;
+use textwrap::core::display_width;
+use unicode_segmentation::UnicodeSegmentation;
 
 use super::panel::Panel;
+use crate::cover_art::{self, ImageProtocol};
+use crate::html::{self, Rendered, TextStyle};
+use crate::types::Chapter;
 use super::AppColors;
 use super::Scroll;
 
@@ -11,7 +20,9 @@ use super::Scroll;
 #[derive(Debug)]
 pub enum DetailsLine {
     Blank,
+    Image(u16, u16),
     Line(String, Option<style::ContentStyle>),
+    Spans(Vec<(String, Option<style::ContentStyle>)>),
     KeyValueLine(
         (String, Option<style::ContentStyle>),
         (String, Option<style::ContentStyle>),
@@ -26,8 +37,16 @@ pub struct Details {
     pub ep_title: Option<String>,
     pub pubdate: Option<DateTime<Utc>>,
     pub duration: Option<String>,
+    pub problem: Option<String>,
     pub explicit: Option<bool>,
-    pub description: Option<String>,
+    pub author: Option<String>,
+    pub categories: Vec<String>,
+    pub language: Option<String>,
+    pub website: Option<String>,
+    pub funding: Option<String>,
+    pub description: Option<Rendered>,
+    pub image: Option<PathBuf>,
+    pub chapters: Vec<Chapter>,
 }
 
 #[derive(Debug)]
@@ -37,6 +56,16 @@ pub struct DetailsPanel {
     pub content: Vec<DetailsLine>,
     pub top_row: u16,    // top row of text shown in window
     pub total_rows: u16, // the total number of rows the details take up
+    pub cover_art: Option<ImageProtocol>,
+    pub wrap: bool,
+    pub wrap_width: Option<usize>,
+    pub art_request: Option<(PathBuf, ImageProtocol, u16, u16)>,
+    art_pending: Option<(PathBuf, u16, u16)>,
+    art_cache: Option<(PathBuf, u16, u16, Option<String>)>,
+    copy_cursor: Option<usize>, // line under the cursor in copy mode
+    copy_anchor: Option<usize>, // start of the selection in copy mode
+    search: Option<Regex>,
+    found: Option<(usize, usize)>, // line and position of current match
 }
 
 impl DetailsPanel {
@@ -57,6 +86,16 @@ impl DetailsPanel {
             content: Vec::new(),
             top_row: 0,
             total_rows: 0,
+            cover_art: None,
+            wrap: true,
+            wrap_width: None,
+            art_request: None,
+            art_pending: None,
+            art_cache: None,
+            copy_cursor: None,
+            copy_anchor: None,
+            search: None,
+            found: None,
         };
     }
 
@@ -68,6 +107,10 @@ impl DetailsPanel {
     /// Insert new details into the details pane.
     pub fn change_details(&mut self, details: Details) {
         self.top_row = 0;
+        self.copy_cursor = None;
+        self.copy_anchor = None;
+        self.search = None;
+        self.found = None;
         self.details = Some(details);
         self.stringify_content();
         self.redraw();
@@ -77,6 +120,10 @@ impl DetailsPanel {
     /// Updates window size.
     pub fn resize(&mut self, n_row: u16, n_col: u16, start_x: u16) {
         self.panel.resize(n_row, n_col, start_x);
+        // the text is wrapped differently, so line numbers change
+        self.copy_cursor = None;
+        self.copy_anchor = None;
+        self.found = None;
         self.stringify_content();
         self.redraw();
         self.write_details();
@@ -139,17 +186,27 @@ impl DetailsPanel {
                 .on(self.panel.colors.normal.1)
                 .attribute(style::Attribute::Underlined);
 
-            self.content.clear();
+            let mut content = Vec::new();
+
+            // cover art, if the terminal can display it; images are
+            // assumed to be square, and terminal cells twice as tall
+            // as they are wide
+            if let (Some(_), Some(_)) = (self.cover_art, &details.image) {
+                let cols = min(num_cols as u16, crate::config::COVER_ART_MAX_COLS);
+                let rows = min(cols / 2, self.panel.get_rows() / 2);
+                if rows > 0 {
+                    content.push(DetailsLine::Image(cols, rows));
+                    content.push(DetailsLine::Blank);
+                }
+            }
 
             // podcast title
             let text = match &details.pod_title {
                 Some(t) => t,
                 None => "No title",
             };
-            let wrapper = textwrap::wrap(text, num_cols);
-            for line in wrapper {
-                self.content
-                    .push(DetailsLine::Line(line.to_string(), Some(bold)));
+            for line in self.fit_text(text, 0) {
+                content.push(DetailsLine::Line(line, Some(bold)));
             }
 
             // episode title
@@ -157,91 +214,827 @@ impl DetailsPanel {
                 Some(t) => t,
                 None => "No title",
             };
-            let wrapper = textwrap::wrap(text, num_cols);
-            for line in wrapper {
-                self.content
-                    .push(DetailsLine::Line(line.to_string(), Some(bold)));
+            for line in self.fit_text(text, 0) {
+                content.push(DetailsLine::Line(line, Some(bold)));
             }
 
-            self.content.push(DetailsLine::Blank); // blank line
+            content.push(DetailsLine::Blank); // blank line
 
             // published date
             if let Some(date) = details.pubdate {
-                self.content.push(DetailsLine::KeyValueLine(
-                    ("Published".to_string(), Some(underlined)),
-                    (format!("{}", date.format("%B %-d, %Y")), None),
-                ));
+                let date = format!("{}", date.format("%B %-d, %Y"));
+                content.extend(self.key_value_lines("Published", &date, underlined));
             }
 
             // duration
             if let Some(dur) = &details.duration {
-                self.content.push(DetailsLine::KeyValueLine(
-                    ("Duration".to_string(), Some(underlined)),
-                    (dur.clone(), None),
-                ));
+                content.extend(self.key_value_lines("Duration", dur, underlined));
+            }
+
+            // why the episode cannot be downloaded or played
+            if let Some(problem) = &details.problem {
+                content.extend(self.key_value_lines("Problem", problem, underlined));
             }
 
             // explicit
             if let Some(exp) = details.explicit {
-                let exp_string = if exp {
-                    "Yes".to_string()
-                } else {
-                    "No".to_string()
-                };
-                self.content.push(DetailsLine::KeyValueLine(
-                    ("Explicit".to_string(), Some(underlined)),
-                    (exp_string, None),
-                ));
+                let exp_string = if exp { "Yes" } else { "No" };
+                content.extend(self.key_value_lines("Explicit", exp_string, underlined));
+            }
+
+            // podcast metadata from the feed
+            if let Some(author) = &details.author {
+                content.extend(self.key_value_lines("Author", author, underlined));
+            }
+            if !details.categories.is_empty() {
+                let categories = details.categories.join(", ");
+                content.extend(self.key_value_lines("Categories", &categories, underlined));
+            }
+            if let Some(lang) = &details.language {
+                content.extend(self.key_value_lines("Language", lang, underlined));
+            }
+            if let Some(website) = &details.website {
+                content.extend(self.key_value_lines("Website", website, underlined));
+            }
+            if let Some(funding) = &details.funding {
+                content.extend(self.key_value_lines("Support this show", funding, underlined));
             }
 
-            self.content.push(DetailsLine::Blank); // blank line
+            content.push(DetailsLine::Blank); // blank line
+
+            // chapters, with long titles lined up after the start times
+            if !details.chapters.is_empty() {
+                for line in self.fit_text("Chapters:", 0) {
+                    content.push(DetailsLine::Line(line, Some(bold)));
+                }
+                for chapter in details.chapters.iter() {
+                    let start = chapter.format_start();
+                    let text = format!("{start}  {}", chapter.title);
+                    for line in self.fit_text(&text, start.chars().count() + 2) {
+                        content.push(DetailsLine::Line(line, None));
+                    }
+                }
+                content.push(DetailsLine::Blank); // blank line
+            }
 
             // description
             match &details.description {
                 Some(desc) => {
-                    let wrapper = textwrap::wrap("Description:", num_cols);
-                    for line in wrapper {
-                        self.content
-                            .push(DetailsLine::Line(line.to_string(), Some(bold)));
+                    for line in self.fit_text("Description:", 0) {
+                        content.push(DetailsLine::Line(line, Some(bold)));
                     }
-                    let wrapper = textwrap::wrap(desc, num_cols);
-                    for line in wrapper {
-                        self.content.push(DetailsLine::Line(line.to_string(), None));
+                    let normal = self.panel.colors.normal;
+                    let span_style = |style: TextStyle| {
+                        if style == TextStyle::default() {
+                            return None;
+                        }
+                        let mut content_style = style::ContentStyle::new().with(normal.0).on(normal.1);
+                        if style.bold {
+                            content_style = content_style.attribute(style::Attribute::Bold);
+                        }
+                        if style.italic {
+                            content_style = content_style.attribute(style::Attribute::Italic);
+                        }
+                        return Some(content_style);
+                    };
+                    for line in desc.lines.iter() {
+                        let text = html::line_text(line);
+                        if text.is_empty() {
+                            content.push(DetailsLine::Blank);
+                            continue;
+                        }
+                        // the fitted lines are found in the text again,
+                        // to carry the styles over to them; lines after
+                        // the first start with the hanging indent, and
+                        // truncated lines end with "…", neither of which
+                        // is part of the text
+                        let mut pos = 0;
+                        let fitted = self.fit_text(&text, hanging_indent(&text));
+                        for (i, fitted) in fitted.into_iter().enumerate() {
+                            let body = if i > 0 {
+                                fitted.trim_start_matches(' ')
+                            } else {
+                                fitted.as_str()
+                            };
+                            let indent = &fitted[..fitted.len() - body.len()];
+                            let (body, ellipsis) = match body.strip_suffix('…') {
+                                Some(cut) if !self.wrap && fitted != text => (cut, "…"),
+                                _ => (body, ""),
+                            };
+                            match text[pos..].find(body) {
+                                Some(idx) => {
+                                    let start = pos + idx;
+                                    pos = start + body.len();
+                                    let mut spans = Vec::new();
+                                    if !indent.is_empty() {
+                                        spans.push((indent.to_string(), None));
+                                    }
+                                    spans.extend(
+                                        html::slice_spans(line, start, pos)
+                                            .into_iter()
+                                            .map(|span| (span.text, span_style(span.style))),
+                                    );
+                                    if !ellipsis.is_empty() {
+                                        spans.push((ellipsis.to_string(), None));
+                                    }
+                                    content.push(DetailsLine::Spans(spans));
+                                }
+                                None => content.push(DetailsLine::Line(fitted, None)),
+                            }
+                        }
+                    }
+                    // link URLs are lined up after their numbers
+                    if !desc.links.is_empty() {
+                        content.push(DetailsLine::Blank);
+                        content.push(DetailsLine::Line("Links:".to_string(), Some(bold)));
+                        for (i, link) in desc.links.iter().enumerate() {
+                            let number = format!("[{}] ", i + 1);
+                            let text = format!("{number}{link}");
+                            for line in self.fit_text(&text, number.len()) {
+                                content.push(DetailsLine::Line(line, None));
+                            }
+                        }
                     }
                 }
                 None => {
-                    let wrapper = textwrap::wrap("No description.", num_cols);
-                    for line in wrapper {
-                        self.content.push(DetailsLine::Line(line.to_string(), None));
+                    for line in self.fit_text("No description.", 0) {
+                        content.push(DetailsLine::Line(line, None));
                     }
                 }
             }
+            self.content = content;
         }
     }
 
-    /// Write the details content to the screen.
+    /// Fits a line of text to the panel. If wrapping is turned on, the
+    /// text is wrapped to the panel (or to `details_wrap_width`, if that
+    /// is narrower), with the lines after the first indented by
+    /// `indent` columns; otherwise, it is cut off with "…" if it does not
+    /// fit on one line.
+    fn fit_text(&self, text: &str, indent: usize) -> Vec<String> {
+        let num_cols = self.panel.get_cols() as usize;
+        if !self.wrap {
+            return vec![truncate(text, num_cols)];
+        }
+        let width = match self.wrap_width {
+            Some(width) if width > 0 => min(width, num_cols),
+            _ => num_cols,
+        };
+        // very deep indents would leave little room for the text
+        let indent = " ".repeat(min(indent, width / 2));
+        let options = textwrap::Options::new(width).subsequent_indent(&indent);
+        return textwrap::wrap(text, options)
+            .into_iter()
+            .map(|line| line.into_owned())
+            .collect();
+    }
+
+    /// Returns the lines for a piece of metadata, such as "Duration:
+    /// 01:02:03". If the value does not fit on one line, it is wrapped
+    /// with the lines after the first lined up after the label.
+    fn key_value_lines(
+        &self,
+        key: &str,
+        value: &str,
+        key_style: style::ContentStyle,
+    ) -> Vec<DetailsLine> {
+        let label = format!("{key}:");
+        let text = format!("{label} {value}");
+        let mut lines = Vec::new();
+        for line in self.fit_text(&text, label.chars().count() + 1) {
+            match line.strip_prefix(&label) {
+                Some(rest) if lines.is_empty() => lines.push(DetailsLine::KeyValueLine(
+                    (key.to_string(), Some(key_style)),
+                    (rest.trim_start().to_string(), None),
+                )),
+                _ => lines.push(DetailsLine::Line(line, None)),
+            }
+        }
+        return lines;
+    }
+
+    /// Switches between wrapping long lines of text and cutting them
+    /// off, and redraws the panel. Returns true if lines are now
+    /// wrapped.
+    pub fn toggle_wrap(&mut self) -> bool {
+        self.wrap = !self.wrap;
+        self.top_row = 0;
+        self.copy_cursor = None;
+        self.copy_anchor = None;
+        self.found = None;
+        self.stringify_content();
+        self.redraw_details();
+        return self.wrap;
+    }
+
+    /// Write the details content to the screen. In copy mode, the
+    /// selected lines are highlighted.
     pub fn write_details(&mut self) {
+        if let Some(protocol) = self.cover_art {
+            self.panel.write_raw(0, cover_art::clear_sequence(protocol));
+        }
         if !self.content.is_empty() {
+            let highlight = style::ContentStyle::new()
+                .with(self.panel.colors.highlighted_active.0)
+                .on(self.panel.colors.highlighted_active.1);
+            let selection = self.copy_selection();
             let mut row = 0;
-            for line in self.content.iter().skip(self.top_row as usize) {
+            let mut image = None;
+            for (i, line) in self.content.iter().enumerate().skip(self.top_row as usize) {
+                if row >= self.panel.get_rows() {
+                    break;
+                }
+                let selected = selection.as_ref().is_some_and(|s| s.contains(&i));
+                if !selected {
+                    // lines with search matches are written in pieces,
+                    // with the matches highlighted
+                    if let Some(spans) = self.highlight_matches(i) {
+                        self.panel.write_spans(row, &spans);
+                        row += 1;
+                        continue;
+                    }
+                }
                 match line {
                     DetailsLine::Blank => row += 1,
+                    DetailsLine::Image(cols, rows) => {
+                        image = Some((row, *cols, *rows));
+                        row += rows;
+                    }
                     DetailsLine::Line(text, style) => {
-                        row = self.panel.write_wrap_line(row, text, *style);
+                        let style = if selected { Some(highlight) } else { *style };
+                        self.panel.write_line(row, text.clone(), style);
+                        row += 1;
+                    }
+                    DetailsLine::Spans(spans) => {
+                        if selected {
+                            let text = spans.iter().map(|(text, _)| text.as_str()).collect();
+                            self.panel.write_line(row, text, Some(highlight));
+                        } else {
+                            self.panel.write_spans(row, spans);
+                        }
                         row += 1;
                     }
                     DetailsLine::KeyValueLine((key, key_style), (val, val_style)) => {
+                        let (key_style, val_style) = if selected {
+                            (Some(highlight), Some(highlight))
+                        } else {
+                            (*key_style, *val_style)
+                        };
                         self.panel.write_key_value_line(
                             row,
                             key.clone(),
                             val.clone(),
-                            *key_style,
-                            *val_style,
+                            key_style,
+                            val_style,
                         );
                         row += 1;
                     }
                 }
             }
+
+            if let Some((row, cols, rows)) = image {
+                if let Some(art) = self.render_art(cols, rows) {
+                    self.panel.write_raw(row, &art);
+                }
+            }
+        }
+    }
+
+    /// Indicates whether the panel is in copy mode.
+    pub fn is_copy_mode(&self) -> bool {
+        return self.copy_cursor.is_some();
+    }
+
+    /// Starts copy mode, with the cursor on the first line of text that
+    /// is visible. Returns false if there is no text to copy.
+    pub fn start_copy_mode(&mut self) -> bool {
+        let first = (self.top_row as usize..self.content.len())
+            .find(|i| self.is_copyable(*i))
+            .or_else(|| (0..self.content.len()).find(|i| self.is_copyable(*i)));
+        self.copy_cursor = first;
+        self.copy_anchor = None;
+        self.redraw_details();
+        return first.is_some();
+    }
+
+    /// Leaves copy mode.
+    pub fn stop_copy_mode(&mut self) {
+        self.copy_cursor = None;
+        self.copy_anchor = None;
+        self.redraw_details();
+    }
+
+    /// Moves the copy mode cursor up or down by `lines` lines of text,
+    /// skipping blank lines, and scrolls to keep it in view.
+    pub fn move_copy_cursor(&mut self, lines: Scroll) {
+        let cursor = match self.copy_cursor {
+            Some(cursor) => cursor,
+            None => return,
+        };
+        let copyable: Vec<usize> = (0..self.content.len())
+            .filter(|i| self.is_copyable(*i))
+            .collect();
+        let pos = copyable.iter().position(|i| *i == cursor).unwrap_or(0);
+        let new_pos = match lines {
+            Scroll::Up(v) => pos.saturating_sub(v as usize),
+            Scroll::Down(v) => min(pos.saturating_add(v as usize), copyable.len() - 1),
+        };
+        self.copy_cursor = Some(copyable[new_pos]);
+        self.scroll_to_line(copyable[new_pos], 0);
+        self.redraw_details();
+    }
+
+    /// Starts a selection at the copy mode cursor, or clears the
+    /// selection if there is one already.
+    pub fn toggle_copy_anchor(&mut self) {
+        if self.copy_anchor.is_some() {
+            self.copy_anchor = None;
+        } else {
+            self.copy_anchor = self.copy_cursor;
+        }
+        self.redraw_details();
+    }
+
+    /// Returns the text of the selected lines in copy mode (or the line
+    /// under the cursor if nothing is selected), one per line.
+    pub fn selected_text(&self) -> Option<String> {
+        let selection = self.copy_selection()?;
+        let lines: Vec<String> = selection.filter_map(|i| self.line_text(i)).collect();
+        return Some(lines.join("\n"));
+    }
+
+    /// Returns the full episode description, as it was before being
+    /// wrapped to fit the panel, followed by its links.
+    pub fn description(&self) -> Option<String> {
+        return Some(self.details.as_ref()?.description.as_ref()?.plain_text());
+    }
+
+    /// Indicates whether there is a search whose matches are shown.
+    pub fn is_searching(&self) -> bool {
+        return self.search.is_some();
+    }
+
+    /// Highlights every match of `term` (ignoring case), and moves to
+    /// the first one from the top of the panel. An empty `term` repeats
+    /// the previous search from the current match. Returns false if the
+    /// term was not found.
+    pub fn search(&mut self, term: &str) -> bool {
+        if !term.is_empty() {
+            let pattern = format!("(?i){}", regex::escape(term));
+            self.search = Regex::new(&pattern).ok();
+            self.found = None;
+        }
+        return self.next_match(true);
+    }
+
+    /// Moves to the next match of the search (or the previous one, if
+    /// `forward` is false), wrapping around at the end of the details,
+    /// and scrolls to show it. Returns false if there are no matches.
+    pub fn next_match(&mut self, forward: bool) -> bool {
+        let matches = self.matches();
+        let next = match (self.found, forward) {
+            // the first search starts from the top of the panel
+            (None, _) => matches
+                .iter()
+                .find(|(line, _)| *line >= self.top_row as usize)
+                .or_else(|| matches.first()),
+            (Some(found), true) => matches.iter().find(|m| **m > found).or_else(|| matches.first()),
+            (Some(found), false) => {
+                matches.iter().rev().find(|m| **m < found).or_else(|| matches.last())
+            }
+        };
+        self.found = next.copied();
+        if let Some((line, _)) = self.found {
+            self.scroll_to_line(line, 2);
+        }
+        self.redraw_details();
+        return self.found.is_some();
+    }
+
+    /// Stops searching, and removes the highlights from the matches.
+    pub fn clear_search(&mut self) {
+        self.search = None;
+        self.found = None;
+        self.redraw_details();
+    }
+
+    /// Returns the line and position of every match of the search.
+    fn matches(&self) -> Vec<(usize, usize)> {
+        let re = match &self.search {
+            Some(re) => re,
+            None => return Vec::new(),
+        };
+        return (0..self.content.len())
+            .filter_map(|i| Some((i, self.line_text(i)?)))
+            .flat_map(|(i, text)| {
+                re.find_iter(&text).map(|m| (i, m.start())).collect::<Vec<_>>()
+            })
+            .collect();
+    }
+
+    /// Returns the pieces of a line with the matches of the search
+    /// picked out in the highlight colors, and the current match in the
+    /// active highlight colors, or None if the line has no matches.
+    fn highlight_matches(
+        &self,
+        index: usize,
+    ) -> Option<Vec<(String, Option<style::ContentStyle>)>> {
+        let re = self.search.as_ref()?;
+        let spans = match &self.content[index] {
+            DetailsLine::Line(text, style) => vec![(text.clone(), *style)],
+            DetailsLine::Spans(spans) => spans.clone(),
+            DetailsLine::KeyValueLine((key, key_style), (val, val_style)) => {
+                vec![(format!("{key}:"), *key_style), (format!(" {val}"), *val_style)]
+            }
+            _ => return None,
+        };
+        let text: String = spans.iter().map(|(text, _)| text.as_str()).collect();
+        let colors = &self.panel.colors;
+        let ranges: Vec<_> = re
+            .find_iter(&text)
+            .map(|m| {
+                let (fg, bg) = if self.found == Some((index, m.start())) {
+                    colors.highlighted_active
+                } else {
+                    colors.highlighted
+                };
+                (m.range(), style::ContentStyle::new().with(fg).on(bg))
+            })
+            .collect();
+        if ranges.is_empty() {
+            return None;
+        }
+        return Some(restyle(&spans, &ranges));
+    }
+
+    /// Returns the text of a line, or None if it is not text.
+    fn line_text(&self, index: usize) -> Option<String> {
+        return match self.content.get(index)? {
+            DetailsLine::Line(text, _) => Some(text.clone()),
+            DetailsLine::Spans(spans) => {
+                Some(spans.iter().map(|(text, _)| text.as_str()).collect())
+            }
+            DetailsLine::KeyValueLine((key, _), (val, _)) => Some(format!("{key}: {val}")),
+            _ => None,
+        };
+    }
+
+    /// Scrolls so that the line at `index` is on screen, with at least
+    /// `margin` lines above it if the panel has to scroll up to it.
+    fn scroll_to_line(&mut self, index: usize, margin: usize) {
+        if index < self.top_row as usize {
+            self.top_row = index.saturating_sub(margin) as u16;
+        }
+        while self.rows_between(self.top_row as usize, index) > self.panel.get_rows() as usize {
+            self.top_row += 1;
+        }
+    }
+
+    /// Returns the range of lines that are selected in copy mode.
+    fn copy_selection(&self) -> Option<std::ops::RangeInclusive<usize>> {
+        let cursor = self.copy_cursor?;
+        let anchor = self.copy_anchor.unwrap_or(cursor);
+        return Some(min(cursor, anchor)..=cursor.max(anchor));
+    }
+
+    /// Indicates whether a line holds text that can be copied.
+    fn is_copyable(&self, index: usize) -> bool {
+        return matches!(
+            self.content.get(index),
+            Some(DetailsLine::Line(_, _))
+                | Some(DetailsLine::Spans(_))
+                | Some(DetailsLine::KeyValueLine(_, _))
+        );
+    }
+
+    /// Returns the number of screen rows taken up by the lines from
+    /// `start` to `end`, inclusive.
+    fn rows_between(&self, start: usize, end: usize) -> usize {
+        return self.content[start..=end]
+            .iter()
+            .map(|line| match line {
+                DetailsLine::Image(_, rows) => *rows as usize,
+                _ => 1,
+            })
+            .sum();
+    }
+
+    /// Clears the panel and writes the details again.
+    pub fn redraw_details(&mut self) {
+        self.panel.clear_inner();
+        self.write_details();
+    }
+
+    /// Returns the escape sequence that draws the cover art for the
+    /// current details, if it has been rendered already. Otherwise the
+    /// image is left out for now, and `art_request` is set so that it
+    /// is rendered off the UI thread.
+    fn render_art(&mut self, cols: u16, rows: u16) -> Option<String> {
+        let protocol = self.cover_art?;
+        let path = self.details.as_ref()?.image.clone()?;
+        if let Some((c_path, c_cols, c_rows, art)) = &self.art_cache {
+            if *c_path == path && *c_cols == cols && *c_rows == rows {
+                // images that can't be decoded are simply left out
+                return art.clone();
+            }
+        }
+        let wanted = (path.clone(), cols, rows);
+        if self.art_pending.as_ref() != Some(&wanted) {
+            self.art_pending = Some(wanted);
+            self.art_request = Some((path, protocol, cols, rows));
+        }
+        return None;
+    }
+
+    /// Stores cover art that has been rendered at the given size.
+    /// Returns true if it belongs with the details currently shown, in
+    /// which case the panel should be redrawn.
+    pub fn set_art(&mut self, path: PathBuf, cols: u16, rows: u16, art: Option<String>) -> bool {
+        if self.art_pending.as_ref() == Some(&(path.clone(), cols, rows)) {
+            self.art_pending = None;
+        }
+        let shown = self.details.as_ref().is_some_and(|d| d.image.as_ref() == Some(&path));
+        if shown {
+            self.art_cache = Some((path, cols, rows, art));
+        }
+        return shown;
+    }
+}
+
+
+/// Splits up the styled pieces of a line so that each of `ranges`, the
+/// byte ranges of parts of the whole line, has its own style.
+fn restyle(
+    spans: &[(String, Option<style::ContentStyle>)],
+    ranges: &[(Range<usize>, style::ContentStyle)],
+) -> Vec<(String, Option<style::ContentStyle>)> {
+    let mut out = Vec::new();
+    let mut offset = 0;
+    for (text, style) in spans.iter() {
+        let end = offset + text.len();
+        let mut pos = offset;
+        for (range, range_style) in ranges.iter() {
+            if range.end <= pos || range.start >= end {
+                continue;
+            }
+            let start = range.start.max(pos);
+            let stop = range.end.min(end);
+            if start > pos {
+                out.push((text[pos - offset..start - offset].to_string(), *style));
+            }
+            out.push((text[start - offset..stop - offset].to_string(), Some(*range_style)));
+            pos = stop;
+        }
+        if pos < end {
+            out.push((text[pos - offset..].to_string(), *style));
+        }
+        offset = end;
+    }
+    return out;
+}
+
+/// Returns the number of columns that the lines of a description
+/// after the first should be indented by, to line up with the text of
+/// the first line: its own indent, plus the bullet or number of a list
+/// item.
+fn hanging_indent(text: &str) -> usize {
+    let body = text.trim_start_matches(' ');
+    let indent = text.len() - body.len();
+    if body.starts_with("• ") {
+        return indent + 2;
+    }
+    return match body.split_once(". ") {
+        Some((number, _)) if !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()) => {
+            indent + number.len() + 2
+        }
+        _ => indent,
+    };
+}
+
+/// Cuts off text that is wider than `width` columns, ending it with
+/// "…".
+fn truncate(text: &str, width: usize) -> String {
+    if display_width(text) <= width {
+        return text.to_string();
+    }
+    let mut out = String::new();
+    let mut used = 0;
+    for grapheme in text.graphemes(true) {
+        let grapheme_width = display_width(grapheme);
+        if used + grapheme_width + 1 > width {
+            break;
         }
+        out.push_str(grapheme);
+        used += grapheme_width;
+    }
+    return format!("{}…", out.trim_end());
+}
+
+
+// TESTS -----------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_panel() -> DetailsPanel {
+        let colors = Rc::new(AppColors::default());
+        let mut panel = DetailsPanel::new("Details".to_string(), 2, colors, 8, 40, 0, (0, 0, 0, 0));
+        panel.change_details(Details {
+            pod_title: Some("Podcast".to_string()),
+            ep_title: Some("Episode".to_string()),
+            pubdate: None,
+            duration: Some("00:10:00".to_string()),
+            problem: None,
+            explicit: None,
+            author: None,
+            categories: Vec::new(),
+            language: None,
+            website: None,
+            funding: None,
+            description: Some(html::render("First line.\nSecond line.\nThird line.\nFourth line.")),
+            image: None,
+            chapters: Vec::new(),
+        });
+        return panel;
+    }
+
+    #[test]
+    fn art_rendered_off_thread() {
+        let mut panel = create_panel();
+        panel.cover_art = Some(ImageProtocol::Kitty);
+        let mut details = panel.details.take().unwrap();
+        details.image = Some(PathBuf::from("cover"));
+        panel.change_details(details);
+
+        // the image is only requested once while it is being rendered
+        let (path, protocol, cols, rows) = panel.art_request.take().unwrap();
+        assert_eq!(protocol, ImageProtocol::Kitty);
+        panel.redraw_details();
+        assert!(panel.art_request.is_none());
+
+        assert!(panel.set_art(path.clone(), cols, rows, Some("art".to_string())));
+        assert_eq!(panel.render_art(cols, rows), Some("art".to_string()));
+        assert!(!panel.set_art(PathBuf::from("other"), cols, rows, None));
+        assert_eq!(panel.render_art(cols, rows), Some("art".to_string()));
+    }
+
+    #[test]
+    fn copy_line() {
+        let mut panel = create_panel();
+        assert!(panel.start_copy_mode());
+        assert_eq!(panel.selected_text().unwrap(), "Podcast");
+
+        // blank lines are skipped
+        panel.move_copy_cursor(Scroll::Down(2));
+        assert_eq!(panel.selected_text().unwrap(), "Duration: 00:10:00");
+
+        panel.stop_copy_mode();
+        assert!(panel.selected_text().is_none());
+    }
+
+    #[test]
+    fn copy_selection() {
+        let mut panel = create_panel();
+        panel.start_copy_mode();
+        panel.move_copy_cursor(Scroll::Down(4));
+        panel.toggle_copy_anchor();
+        panel.move_copy_cursor(Scroll::Down(1));
+        assert_eq!(panel.selected_text().unwrap(), "First line.\nSecond line.");
+
+        // the cursor stays on screen at the end of the text
+        panel.move_copy_cursor(Scroll::Down(u16::MAX));
+        assert_eq!(panel.top_row, 4);
+        assert!(panel.selected_text().unwrap().ends_with("Fourth line."));
+        assert_eq!(panel.description().unwrap().lines().count(), 4);
+    }
+
+    #[test]
+    fn html_description() {
+        let mut panel = create_panel();
+        let description = "<p>Show notes with <b>a bold phrase that wraps</b> onto the next line.</p><ul><li><a href=\"https://example.com\">Link</a></li></ul>";
+        panel.change_details(Details {
+            pod_title: Some("Podcast".to_string()),
+            ep_title: Some("Episode".to_string()),
+            pubdate: None,
+            duration: None,
+            problem: None,
+            explicit: None,
+            author: None,
+            categories: Vec::new(),
+            language: None,
+            website: None,
+            funding: None,
+            description: Some(html::render(description)),
+            image: None,
+            chapters: Vec::new(),
+        });
+        panel.start_copy_mode();
+        panel.move_copy_cursor(Scroll::Down(u16::MAX));
+        panel.toggle_copy_anchor();
+        panel.move_copy_cursor(Scroll::Up(u16::MAX));
+        let text = panel.selected_text().unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(&lines[3..], [
+            "Show notes with a bold phrase that",
+            "wraps onto the next line.",
+            "• Link [1]",
+            "Links:",
+            "[1] https://example.com",
+        ]);
+        match &panel.content[5] {
+            DetailsLine::Spans(spans) => {
+                assert_eq!(spans[0].0, "Show notes with ");
+                assert!(spans[0].1.is_none() && spans[1].1.is_some());
+            }
+            line => panic!("Expected styled text, found {line:?}"),
+        }
+    }
+
+    #[test]
+    fn wrap_and_truncate() {
+        let mut panel = create_panel();
+        let description = "<ul><li>A list item that is long enough to wrap</li></ul><a href=\"https://example.com/episodes/2023/06/01/show-notes\">Notes</a>";
+        panel.change_details(Details {
+            pod_title: Some("Podcast".to_string()),
+            ep_title: Some("Episode".to_string()),
+            pubdate: None,
+            duration: Some("two hours, give or take a few minutes".to_string()),
+            problem: None,
+            explicit: None,
+            author: None,
+            categories: Vec::new(),
+            language: None,
+            website: None,
+            funding: None,
+            description: Some(html::render(description)),
+            image: None,
+            chapters: Vec::new(),
+        });
+        let lines = |panel: &DetailsPanel| -> Vec<String> {
+            return panel
+                .content
+                .iter()
+                .filter_map(|line| match line {
+                    DetailsLine::Line(text, _) => Some(text.clone()),
+                    DetailsLine::Spans(spans) => {
+                        Some(spans.iter().map(|(text, _)| text.as_str()).collect())
+                    }
+                    DetailsLine::KeyValueLine((key, _), (val, _)) => Some(format!("{key}: {val}")),
+                    _ => None,
+                })
+                .collect();
+        };
+
+        // lines after the first are lined up after the label, bullet,
+        // or link number
+        assert_eq!(lines(&panel)[2..], [
+            "Duration: two hours, give or take a",
+            "          few minutes",
+            "Description:",
+            "• A list item that is long enough",
+            "  to wrap",
+            "Notes [1]",
+            "Links:",
+            "[1] https://example.com/",
+            "    episodes/2023/06/01/show-notes",
+        ]);
+
+        // the real and mock panels differ in width by a column
+        assert!(!panel.toggle_wrap());
+        let truncated = lines(&panel);
+        assert_eq!(truncated.len(), 8);
+        for (i, line) in truncated.iter().enumerate() {
+            assert!(display_width(line) <= panel.panel.get_cols() as usize);
+            assert_eq!(line.ends_with('…'), [2, 4, 7].contains(&i));
+        }
+        assert!(truncated[2].starts_with("Duration: two hours"));
+        assert!(truncated[7].starts_with("[1] https://example.com/episodes/"));
+    }
+
+    #[test]
+    fn search() {
+        let mut panel = create_panel();
+        assert!(panel.search("LINE"));
+        assert_eq!(panel.found, Some((6, 6)));
+
+        // the current match is picked out from the others
+        let colors = AppColors::default();
+        let spans = panel.highlight_matches(7).unwrap();
+        assert_eq!(spans[0], ("Second ".to_string(), None));
+        assert_eq!(spans[1].0, "line");
+        assert_eq!(spans[1].1.unwrap().background_color, Some(colors.highlighted.1));
+        let spans = panel.highlight_matches(6).unwrap();
+        assert_eq!(spans[1].1.unwrap().background_color, Some(colors.highlighted_active.1));
+
+        // moving past the last match wraps around to the first
+        assert!(panel.next_match(false));
+        assert_eq!(panel.found, Some((9, 7)));
+        assert!(panel.next_match(true));
+        assert_eq!(panel.found, Some((6, 6)));
+
+        assert!(!panel.search("missing"));
+        assert!(panel.highlight_matches(6).is_none());
+        panel.clear_search();
+        assert!(!panel.is_searching());
     }
 }
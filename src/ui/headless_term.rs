@@ -0,0 +1,1096 @@
+//! Stand-in for `term.rs` used when shellcaster is built with the
+//! "headless" feature. Instead of the real terminal, the UI draws to a
+//! virtual screen, and reads its input from a queue of scripted events.
+//! The state is kept per thread, so tests running in parallel each get
+//! their own screen.
+
+// the driver functions are only called from tests
+#![cfg_attr(not(test), allow(dead_code))]
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io;
+use std::time::Duration;
+
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+
+use crate::keymap;
+
+thread_local! {
+    static SCREEN: RefCell<Screen> = RefCell::new(Screen::new(80, 24));
+    static EVENTS: RefCell<VecDeque<Event>> = const { RefCell::new(VecDeque::new()) };
+}
+
+/// The virtual screen, along with the state needed to interpret the
+/// escape sequences written to it.
+#[derive(Debug)]
+struct Screen {
+    cells: Vec<Vec<char>>,
+    n_col: u16,
+    n_row: u16,
+    x: u16,
+    y: u16,
+    // bytes not interpreted yet, i.e., an unfinished escape sequence
+    // or UTF-8 character
+    pending: Vec<u8>,
+}
+
+impl Screen {
+    fn new(n_col: u16, n_row: u16) -> Self {
+        return Screen {
+            cells: vec![vec![' '; n_col as usize]; n_row as usize],
+            n_col: n_col,
+            n_row: n_row,
+            x: 0,
+            y: 0,
+            pending: Vec::new(),
+        };
+    }
+
+    /// Changes the size of the screen, keeping what fits of its
+    /// contents.
+    fn resize(&mut self, n_col: u16, n_row: u16) {
+        self.cells.resize(n_row as usize, Vec::new());
+        for row in self.cells.iter_mut() {
+            row.resize(n_col as usize, ' ');
+        }
+        self.n_col = n_col;
+        self.n_row = n_row;
+        self.x = self.x.min(n_col.saturating_sub(1));
+        self.y = self.y.min(n_row.saturating_sub(1));
+    }
+
+    /// Interprets as much of the written bytes as possible, keeping the
+    /// rest until more is written.
+    fn write(&mut self, buf: &[u8]) {
+        self.pending.extend_from_slice(buf);
+        let text = match std::str::from_utf8(&self.pending) {
+            Ok(text) => text.to_string(),
+            Err(err) => String::from_utf8_lossy(&self.pending[..err.valid_up_to()]).into_owned(),
+        };
+
+        let mut chars = text.char_indices().peekable();
+        let mut done = 0;
+        while let Some((start, c)) = chars.next() {
+            if c == '\x1b' {
+                match parse_escape(&text[start..]) {
+                    Some((len, seq)) => {
+                        self.apply(seq);
+                        while chars.peek().is_some_and(|(i, _)| *i < start + len) {
+                            chars.next();
+                        }
+                        done = start + len;
+                    }
+                    None => break,
+                }
+            } else {
+                self.print(c);
+                done = start + c.len_utf8();
+            }
+        }
+        self.pending.drain(..done);
+    }
+
+    /// Puts a single character at the cursor and moves the cursor on.
+    fn print(&mut self, c: char) {
+        match c {
+            '\r' => self.x = 0,
+            '\n' => self.y = (self.y + 1).min(self.n_row.saturating_sub(1)),
+            _ => {
+                if self.x < self.n_col && self.y < self.n_row {
+                    self.cells[self.y as usize][self.x as usize] = c;
+                }
+                self.x = self.x.saturating_add(1);
+            }
+        }
+    }
+
+    fn apply(&mut self, seq: Escape) {
+        match seq {
+            Escape::MoveTo(x, y) => {
+                self.x = x.min(self.n_col.saturating_sub(1));
+                self.y = y.min(self.n_row.saturating_sub(1));
+            }
+            Escape::MoveRight(n) => self.x = self.x.saturating_add(n),
+            Escape::MoveLeft(n) => self.x = self.x.saturating_sub(n),
+            Escape::ClearAll => {
+                for row in self.cells.iter_mut() {
+                    row.iter_mut().for_each(|c| *c = ' ');
+                }
+            }
+            Escape::ClearLine => {
+                if let Some(row) = self.cells.get_mut(self.y as usize) {
+                    row.iter_mut().for_each(|c| *c = ' ');
+                }
+            }
+            Escape::ClearToEnd => {
+                if let Some(row) = self.cells.get_mut(self.y as usize) {
+                    row.iter_mut().skip(self.x as usize).for_each(|c| *c = ' ');
+                }
+            }
+            Escape::Ignored => (),
+        }
+    }
+
+    fn lines(&self) -> Vec<String> {
+        return self
+            .cells
+            .iter()
+            .map(|row| row.iter().collect::<String>().trim_end().to_string())
+            .collect();
+    }
+}
+
+/// The escape sequences that change what is on the virtual screen.
+/// Anything else (colors, cursor visibility, cover art, etc.) is
+/// skipped over.
+#[derive(Debug, PartialEq)]
+enum Escape {
+    MoveTo(u16, u16),
+    MoveRight(u16),
+    MoveLeft(u16),
+    ClearAll,
+    ClearLine,
+    ClearToEnd,
+    Ignored,
+}
+
+/// Parses the escape sequence at the start of `text`, returning its
+/// length in bytes, or None if the sequence is not complete yet.
+fn parse_escape(text: &str) -> Option<(usize, Escape)> {
+    let bytes = text.as_bytes();
+    match bytes.get(1)? {
+        b'[' => {
+            // CSI: parameters, then a single final byte
+            let end = bytes[2..].iter().position(|b| (0x40..=0x7e).contains(b))? + 2;
+            let params = &text[2..end];
+            let nums: Vec<u16> = params.split(';').map(|p| p.parse().unwrap_or(0)).collect();
+            let first = nums.first().copied().unwrap_or(0);
+            let seq = if params.starts_with('?') {
+                Escape::Ignored
+            } else {
+                match bytes[end] {
+                    b'H' => {
+                        let y = first.max(1) - 1;
+                        let x = nums.get(1).copied().unwrap_or(1).max(1) - 1;
+                        Escape::MoveTo(x, y)
+                    }
+                    b'C' => Escape::MoveRight(first.max(1)),
+                    b'D' => Escape::MoveLeft(first.max(1)),
+                    b'J' if first == 2 => Escape::ClearAll,
+                    b'K' if first == 2 => Escape::ClearLine,
+                    b'K' => Escape::ClearToEnd,
+                    _ => Escape::Ignored,
+                }
+            };
+            return Some((end + 1, seq));
+        }
+        b']' | b'_' | b'P' => {
+            // OSC, APC, and DCS: ended by BEL or by ESC \
+            let mut i = 2;
+            while i < bytes.len() {
+                if bytes[i] == 0x07 {
+                    return Some((i + 1, Escape::Ignored));
+                }
+                if bytes[i] == 0x1b && bytes.get(i + 1) == Some(&b'\\') {
+                    return Some((i + 2, Escape::Ignored));
+                }
+                i += 1;
+            }
+            return None;
+        }
+        _ => return Some((2, Escape::Ignored)),
+    }
+}
+
+/// Writer that draws to the virtual screen.
+pub struct Output;
+
+impl io::Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        SCREEN.with(|s| s.borrow_mut().write(buf));
+        return Ok(buf.len());
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        return Ok(());
+    }
+}
+
+/// Returns the writer that the UI draws to.
+pub fn stdout() -> Output {
+    return Output;
+}
+
+/// Returns the size of the virtual screen, as (columns, rows).
+pub fn size() -> (u16, u16) {
+    return SCREEN.with(|s| {
+        let s = s.borrow();
+        (s.n_col, s.n_row)
+    });
+}
+
+pub fn enable_raw_mode() {}
+
+pub fn disable_raw_mode() {}
+
+pub fn pause_input() {}
+
+pub fn resume_input() {}
+
+pub fn catch_interrupts(_catch: bool) {}
+
+/// Returns straight away, as though Enter had been pressed.
+pub fn wait_for_enter() {}
+
+/// Returns straight away, as though the program had been suspended and
+/// then continued.
+pub fn suspend() {}
+
+/// Indicates whether there is a scripted event waiting to be read.
+pub fn poll() -> bool {
+    return EVENTS.with(|e| !e.borrow().is_empty());
+}
+
+/// Returns the next scripted event. Once the script runs out, this
+/// returns Esc, so that anything waiting on input (e.g., a text prompt)
+/// is cancelled rather than blocking forever.
+pub fn read() -> Event {
+    return EVENTS
+        .with(|e| e.borrow_mut().pop_front())
+        .unwrap_or_else(|| Event::Key(key(KeyCode::Esc, KeyModifiers::NONE)));
+}
+
+/// Returns straight away: the script is only read when the test asks.
+pub fn wait(_timeout: Option<Duration>) {}
+
+/// Returns a function that does nothing, as there is never anything to
+/// wake up from.
+pub fn waker() -> impl Fn() + Send + 'static {
+    return || ();
+}
+
+// DRIVER ----------------------------------------------------------------
+
+/// Sets the size of the virtual screen. This should be called before
+/// creating the UI; afterwards, use `Ui::resize_screen()` so that the
+/// UI redraws itself to match.
+pub fn set_size(n_col: u16, n_row: u16) {
+    SCREEN.with(|s| s.borrow_mut().resize(n_col, n_row));
+}
+
+/// Queues key presses, written the same way as keys in config.toml,
+/// e.g., "j", "Enter", "Space", "Up", or "C-r".
+pub fn push_keys(keys: &[&str]) {
+    for k in keys.iter() {
+        push_event(Event::Key(key_from_str(k)));
+    }
+}
+
+/// Queues a key press for each character of `text`, as when typing
+/// into a text prompt.
+pub fn type_text(text: &str) {
+    for c in text.chars() {
+        push_event(Event::Key(key(KeyCode::Char(c), KeyModifiers::NONE)));
+    }
+}
+
+/// Queues any other input event.
+pub fn push_event(event: Event) {
+    EVENTS.with(|e| e.borrow_mut().push_back(event));
+}
+
+/// Returns the contents of the virtual screen, one String per row,
+/// without trailing spaces.
+pub fn screen() -> Vec<String> {
+    return SCREEN.with(|s| s.borrow().lines());
+}
+
+fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+    return KeyEvent {
+        code: code,
+        modifiers: modifiers,
+    };
+}
+
+/// Turns a key as written in config.toml into the KeyEvent that
+/// produces it.
+fn key_from_str(name: &str) -> KeyEvent {
+    let normalized = keymap::normalize_key(name);
+    let mut rest = normalized.as_str();
+    let mut modifiers = KeyModifiers::NONE;
+    for (prefix, modifier) in [
+        ("Ctrl+", KeyModifiers::CONTROL),
+        ("Alt+", KeyModifiers::ALT),
+        ("Shift+", KeyModifiers::SHIFT),
+    ] {
+        if rest.len() > prefix.len() && rest.starts_with(prefix) {
+            modifiers |= modifier;
+            rest = &rest[prefix.len()..];
+        }
+    }
+    let code = match rest {
+        "Backspace" => KeyCode::Backspace,
+        "Enter" => KeyCode::Enter,
+        "Left" => KeyCode::Left,
+        "Right" => KeyCode::Right,
+        "Up" => KeyCode::Up,
+        "Down" => KeyCode::Down,
+        "Home" => KeyCode::Home,
+        "End" => KeyCode::End,
+        "PgUp" => KeyCode::PageUp,
+        "PgDn" => KeyCode::PageDown,
+        "Tab" => KeyCode::Tab,
+        "Del" => KeyCode::Delete,
+        "Ins" => KeyCode::Insert,
+        "Esc" => KeyCode::Esc,
+        "Space" => KeyCode::Char(' '),
+        _ => match rest.strip_prefix('F').and_then(|n| n.parse().ok()) {
+            Some(num) => KeyCode::F(num),
+            None => KeyCode::Char(rest.chars().next().unwrap_or(' ')),
+        },
+    };
+    return key(code, modifiers);
+}
+
+// TESTS -----------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, NewPodcastEpisodes};
+    use crate::types::*;
+    use crate::ui::{EpisodeList, Ui, UiMsg};
+    use chrono::Utc;
+    use crossterm::{cursor, execute, style, terminal};
+    use std::path::{Path, PathBuf};
+
+    fn headless_ui(n_col: u16, n_row: u16) -> Ui {
+        let config = Config::new(Path::new("/nonexistent/config.toml")).unwrap();
+        return Ui::headless(config, LockVec::new(Vec::new()), n_col, n_row);
+    }
+
+    fn episode(id: i64, title: &str, new: bool) -> Episode {
+        return Episode {
+            id: id,
+            pod_id: 1,
+            title: title.to_string(),
+            url: String::new(),
+            guid: String::new(),
+            description: String::new(),
+            pubdate: Some(Utc::now()),
+            duration: None,
+            image_url: None,
+            chapters_url: None,
+            chapters: Vec::new(),
+            transcript_url: None,
+            transcript_type: None,
+            enclosures: Vec::new(),
+            enclosure_error: None,
+            path: None,
+            played: false,
+            updated: false,
+            starred: false,
+            clean_title: None,
+            position: None,
+            last_played: None,
+            new: new,
+        };
+    }
+
+    fn podcast(episodes: Vec<Episode>) -> Podcast {
+        return Podcast {
+            id: 1,
+            title: "Test Cast".to_string(),
+            custom_title: None,
+            sort_title: "test cast".to_string(),
+            url: String::new(),
+            description: None,
+            author: None,
+            explicit: None,
+            last_checked: Utc::now(),
+            image_url: None,
+            categories: Vec::new(),
+            language: None,
+            website: None,
+            funding_url: None,
+            funding_text: None,
+            notify: true,
+            archived: false,
+            include_pattern: None,
+            exclude_pattern: None,
+            title_pattern: None,
+            title_replacement: None,
+            show_pattern: None,
+            hide_pattern: None,
+            group: None,
+            overrides: PodcastOverrides::default(),
+            auth: FeedAuth::default(),
+            validators: FeedValidators::default(),
+            schedule: FeedSchedule::default(),
+            episodes: LockVec::new(episodes),
+        };
+    }
+
+    fn screen_text() -> String {
+        return screen().join("\n");
+    }
+
+    #[test]
+    fn virtual_screen() {
+        set_size(20, 3);
+        execute!(
+            stdout(),
+            terminal::Clear(terminal::ClearType::All),
+            cursor::MoveTo(2, 1),
+            style::Print("héllo"),
+            style::SetForegroundColor(style::Color::Red),
+            cursor::MoveLeft(3),
+            style::Print("LL"),
+            cursor::MoveTo(0, 2),
+            style::Print("gone"),
+            cursor::MoveTo(0, 2),
+            terminal::Clear(terminal::ClearType::CurrentLine),
+        )
+        .unwrap();
+        assert_eq!(screen(), vec!["", "  héLLo", ""]);
+    }
+
+    #[test]
+    fn split_writes() {
+        set_size(10, 1);
+        let mut out = stdout();
+        io::Write::write_all(&mut out, b"\x1b[1;").unwrap();
+        io::Write::write_all(&mut out, b"3Hab\xc3").unwrap();
+        io::Write::write_all(&mut out, b"\xa9").unwrap();
+        assert_eq!(screen(), vec!["  abé"]);
+    }
+
+    #[test]
+    fn key_names() {
+        assert_eq!(
+            key_from_str("j"),
+            key(KeyCode::Char('j'), KeyModifiers::NONE)
+        );
+        assert_eq!(
+            key_from_str("Enter"),
+            key(KeyCode::Enter, KeyModifiers::NONE)
+        );
+        assert_eq!(
+            key_from_str("Space"),
+            key(KeyCode::Char(' '), KeyModifiers::NONE)
+        );
+        assert_eq!(
+            key_from_str("C-r"),
+            key(KeyCode::Char('r'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(key_from_str("F5"), key(KeyCode::F(5), KeyModifiers::NONE));
+    }
+
+    #[test]
+    fn welcome_and_help() {
+        let mut ui = headless_ui(80, 24);
+        assert!(screen_text().contains("Welcome to shellcaster!"));
+
+        ui.press(&["?"]);
+        assert!(screen_text().contains("Available keybindings"));
+
+        ui.press(&["q"]);
+        assert!(!screen_text().contains("Available keybindings"));
+        assert!(screen_text().contains("Welcome to shellcaster!"));
+    }
+
+    #[test]
+    fn theme_preview() {
+        let mut ui = headless_ui(100, 64);
+        ui.press(&["P"]);
+        assert!(screen_text().contains("Colors (theme: default):"));
+        assert!(screen_text().contains("highlighted_active (black on darkyellow):  Selected item"));
+        assert!(screen_text().contains("theme_preview = P"));
+        assert!(screen_text().contains("played (grey on black):  Played episode"));
+
+        // switching themes keeps the preview open
+        ui.press(&["t"]);
+        assert!(screen_text().contains("Colors (theme: default):"));
+
+        ui.press(&["q"]);
+        assert!(!screen_text().contains("Theme preview"));
+    }
+
+    #[test]
+    fn first_run_setup() {
+        let mut ui = headless_ui(100, 30);
+        ui.spawn_setup_win(vec!["mpv %s".to_string(), "vlc %s".to_string()], true);
+        assert!(screen_text().contains("Player: mpv %s"));
+        assert!(screen_text().contains("Theme: default"));
+
+        // the players that were found come first, then any command
+        ui.press(&["j", "Enter"]);
+        assert!(screen_text().contains("Player: vlc %s"));
+        push_keys(&["Enter"]);
+        type_text("cvlc %s");
+        ui.press(&["Enter"]);
+        assert!(screen_text().contains("Player: cvlc %s"));
+
+        push_keys(&["k", "Enter"]);
+        type_text("~/Podcasts");
+        ui.press(&["Enter"]);
+        assert!(screen_text().contains("Download folder: ~/Podcasts"));
+
+        let msgs = ui.press(&["G", "Enter"]);
+        assert!(matches!(
+            msgs.as_slice(),
+            [UiMsg::FinishSetup(settings)] if settings == &vec![
+                ("download_path".to_string(), Some("\"~/Podcasts\"".to_string())),
+                ("play_command".to_string(), Some("\"cvlc %s\"".to_string())),
+            ]
+        ));
+        assert!(!screen_text().contains("Player:"));
+        assert!(screen_text().contains("Welcome to shellcaster!"));
+    }
+
+    #[test]
+    fn reopen_setup() {
+        let mut ui = headless_ui(100, 30);
+        ui.press(&["A-o"]);
+        assert!(screen_text().contains("Choose the settings to change."));
+
+        // nothing is saved unless it was changed
+        let msgs = ui.press(&["G", "Enter"]);
+        assert!(matches!(
+            msgs.as_slice(),
+            [UiMsg::FinishSetup(settings)] if settings.is_empty()
+        ));
+
+        // a blank download folder goes back to the default
+        push_keys(&["A-o", "Enter"]);
+        ui.press(&["Enter"]);
+        assert!(screen_text().contains("Download folder: (default)"));
+        let msgs = ui.press(&["G", "Enter"]);
+        assert!(matches!(
+            msgs.as_slice(),
+            [UiMsg::FinishSetup(settings)] if settings == &vec![
+                ("download_path".to_string(), None),
+            ]
+        ));
+    }
+
+    #[test]
+    fn import_file() {
+        let mut ui = headless_ui(80, 24);
+        push_keys(&["O"]);
+        type_text("/no/such/file.opml");
+        assert!(ui.press(&["Enter"]).is_empty());
+
+        push_keys(&["O"]);
+        type_text("Cargo.toml");
+        let msgs = ui.press(&["Enter"]);
+        assert!(matches!(
+            msgs.as_slice(),
+            [UiMsg::ImportFile(path)] if path.ends_with("Cargo.toml")
+        ));
+    }
+
+    #[test]
+    fn podcast_groups() {
+        let mut config = Config::new(Path::new("/nonexistent/config.toml")).unwrap();
+        config.groups = vec![TagGroup {
+            name: "News".to_string(),
+            tags: vec!["news".to_string()],
+        }];
+        // Beta is put in the group by hand (spelled differently), and
+        // Gamma by its tags
+        let pods = [
+            ("Alpha", None, None),
+            ("Beta", Some(" news"), None),
+            ("Gamma", None, Some("News")),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(i, (title, group, category))| {
+            let mut pod = podcast(vec![episode(i as i64 + 10, "Episode", false)]);
+            pod.id = i as i64 + 1;
+            pod.title = title.to_string();
+            pod.group = group.map(str::to_string);
+            pod.categories = category.into_iter().map(str::to_string).collect();
+            pod
+        })
+        .collect();
+        let mut ui = Ui::headless(config, LockVec::new(pods), 100, 30);
+        assert!(screen_text().contains("- News (2)"));
+        assert!(screen_text().contains("Gamma"));
+
+        // the header has no episodes, and Enter on it collapses the group
+        ui.press(&["j"]);
+        assert_eq!(ui.get_current_ids(), (None, None));
+        ui.press(&["Enter"]);
+        assert!(screen_text().contains("+ News (2)"));
+        assert!(!screen_text().contains("Gamma"));
+
+        ui.press(&["z", "j"]);
+        assert_eq!(ui.get_current_ids(), (Some(2), Some(11)));
+
+        // collapsing the group of the podcast moves the cursor to its
+        // header
+        ui.press(&["Z"]);
+        assert!(!screen_text().contains("Beta"));
+        assert_eq!(ui.get_current_ids(), (None, None));
+        ui.press(&["Z", "G"]);
+        assert_eq!(ui.get_current_ids(), (Some(3), Some(12)));
+    }
+
+    #[test]
+    fn tree_layout() {
+        let mut config = Config::new(Path::new("/nonexistent/config.toml")).unwrap();
+        config.tree_layout = true;
+        let pods = (1..=2)
+            .map(|id| {
+                let mut pod = podcast(vec![
+                    episode(id * 10, "First Episode", false),
+                    episode(id * 10 + 1, "Second Episode", false),
+                ]);
+                pod.id = id;
+                pod.title = format!("Cast {id}");
+                pod
+            })
+            .collect();
+        let mut ui = Ui::headless(config, LockVec::new(pods), 100, 30);
+        assert!(screen_text().contains("+ Cast 1"));
+        assert!(!screen_text().contains("Episodes"));
+        assert!(!screen_text().contains("First Episode"));
+
+        // the episodes are listed beneath the podcast once it is expanded
+        ui.press(&["l"]);
+        let lines = ui.screen();
+        let row = |text: &str| lines.iter().position(|l| l.contains(text));
+        assert!(lines.iter().any(|l| l.contains("- Cast 1")));
+        assert!(row("Cast 1") < row("Second Episode"));
+        assert!(row("Second Episode") < row("Cast 2"));
+
+        ui.press(&["l"]);
+        assert_eq!(ui.get_current_ids(), (Some(1), Some(10)));
+        ui.press(&["j"]);
+        assert_eq!(ui.get_current_ids(), (Some(1), Some(11)));
+        ui.press(&["j"]);
+        assert_eq!(ui.get_current_ids(), (Some(2), Some(20)));
+
+        // collapsing the podcast from one of its episodes moves the
+        // cursor up to it
+        ui.press(&["k", "h"]);
+        assert_eq!(ui.get_current_ids(), (Some(1), Some(10)));
+        assert!(!screen_text().contains("First Episode"));
+
+        // switching layouts keeps the cursor on the same episode
+        ui.press(&["l", "l", "j", "A-l"]);
+        assert!(screen_text().contains("Episodes"));
+        assert_eq!(ui.get_current_ids(), (Some(1), Some(11)));
+        ui.press(&["A-l"]);
+        assert!(!screen_text().contains("Episodes"));
+        assert_eq!(ui.get_current_ids(), (Some(1), Some(11)));
+    }
+
+    #[test]
+    fn description_cache() {
+        let config = Config::new(Path::new("/nonexistent/config.toml")).unwrap();
+        let mut ep = episode(10, "Episode", false);
+        ep.description = "<p>First version</p>".to_string();
+        let pods = LockVec::new(vec![podcast(vec![ep])]);
+        let mut ui = Ui::headless(config, pods.clone(), 160, 30);
+        ui.press(&["l"]);
+        assert!(screen_text().contains("First version"));
+
+        // the rendered description is kept until the menus are updated,
+        // e.g., after a sync
+        let mut ep = pods.clone_episode(1, 10).unwrap();
+        ep.description = "<p>Second version</p>".to_string();
+        pods.map_single(1, |pod| pod.episodes.replace(10, ep));
+        ui.update_details_panel();
+        assert!(screen_text().contains("First version"));
+        ui.update_menus();
+        ui.update_details_panel();
+        assert!(screen_text().contains("Second version"));
+    }
+
+    #[test]
+    fn tabs() {
+        let config = Config::new(Path::new("/nonexistent/config.toml")).unwrap();
+        let mut downloaded = episode(3, "Downloaded Episode", false);
+        downloaded.path = Some(PathBuf::from("/tmp/3.mp3"));
+        let pod = podcast(vec![
+            episode(2, "Fresh Episode", true),
+            downloaded,
+            episode(4, "Old Episode", false),
+        ]);
+        let mut ui = Ui::headless(config, LockVec::new(vec![pod]), 100, 30);
+        assert!(screen_text().contains("1 Library 2 3 4 5"));
+        ui.press(&["l", "j"]);
+        assert_eq!(ui.get_current_ids(), (Some(1), Some(3)));
+
+        ui.press(&["A-2"]);
+        assert!(screen_text().contains("New (1)"));
+        assert!(screen_text().contains("2 New"));
+        assert_eq!(ui.get_current_ids(), (Some(1), Some(2)));
+        // the keys for episodes work on the ones listed in the tab
+        assert!(matches!(ui.press(&["p"]).as_slice(), [UiMsg::Play(1, 2)]));
+
+        ui.press(&["A-4"]);
+        assert!(screen_text().contains("Downloads (1)"));
+        assert_eq!(ui.get_current_ids(), (Some(1), Some(3)));
+
+        // the queue is listed in its own order
+        ui.update_queue(vec![4, 2]);
+        ui.press(&["A-3", "j"]);
+        assert!(screen_text().contains("Queue (2)"));
+        assert_eq!(ui.get_current_ids(), (Some(1), Some(2)));
+
+        push_keys(&["A-5"]);
+        type_text("old");
+        ui.press(&["Enter"]);
+        assert!(screen_text().contains("Search: old (1)"));
+        assert_eq!(ui.get_current_ids(), (Some(1), Some(4)));
+
+        // each tab keeps its own cursor
+        ui.press(&["Tab"]);
+        assert_eq!(ui.get_current_ids(), (Some(1), Some(3)));
+        ui.press(&["A-3"]);
+        assert_eq!(ui.get_current_ids(), (Some(1), Some(2)));
+    }
+
+    #[test]
+    fn fuzzy_finder() {
+        let config = Config::new(Path::new("/nonexistent/config.toml")).unwrap();
+        let mut holes = episode(5, "Black Holes Explained", false);
+        holes.pod_id = 2;
+        let mut other = podcast(vec![holes]);
+        other.id = 2;
+        other.title = "Science Hour".to_string();
+        other.sort_title = "science hour".to_string();
+        let pod = podcast(vec![episode(2, "Fresh Episode", false)]);
+        let mut ui = Ui::headless(config, LockVec::new(vec![pod, other]), 100, 30);
+
+        // only the podcasts are listed until something is typed
+        ui.press(&["f"]);
+        assert!(screen_text().contains("Find (2)"));
+        ui.press(&["b", "h", "x", "Backspace"]);
+        assert!(screen_text().contains("> bh"));
+        assert!(screen_text().contains("Find (1)"));
+        assert!(screen_text().contains("Black Holes Explained (Science Hour)"));
+        ui.press(&["Enter"]);
+        assert_eq!(ui.get_current_ids(), (Some(2), Some(5)));
+
+        // keys that are bound to actions are typed into the query
+        let msgs = ui.press(&["f", "q", "Esc"]);
+        assert!(msgs.is_empty());
+        assert!(!screen_text().contains("Find ("));
+        assert_eq!(ui.get_current_ids(), (Some(2), Some(5)));
+    }
+
+    #[test]
+    fn find_in_menu() {
+        let config = Config::new(Path::new("/nonexistent/config.toml")).unwrap();
+        let mut holes = episode(5, "Black Holes Explained", false);
+        holes.pod_id = 2;
+        let mut other = podcast(vec![holes]);
+        other.id = 2;
+        other.title = "Science Hour".to_string();
+        let pod = podcast(vec![
+            episode(2, "Fresh Episode", false),
+            episode(3, "Old Episode", false),
+        ]);
+        let mut ui = Ui::headless(config, LockVec::new(vec![pod, other]), 100, 30);
+
+        push_keys(&["F"]);
+        type_text("sci");
+        ui.press(&["Enter"]);
+        assert!(screen_text().contains("Podcasts [find: sci]"));
+        assert!(!screen_text().contains("Test Cast"));
+        assert_eq!(ui.get_current_ids(), (Some(2), Some(5)));
+
+        // Escape lists everything again, with the cursor where it was
+        ui.press(&["Esc"]);
+        assert!(screen_text().contains("Test Cast"));
+        assert!(!screen_text().contains("[find:"));
+        assert_eq!(ui.get_current_ids(), (Some(2), Some(5)));
+
+        ui.press(&["k", "l"]);
+        push_keys(&["F"]);
+        type_text("old");
+        ui.press(&["Enter"]);
+        assert!(screen_text().contains("Episodes [find: old]"));
+        assert!(!screen_text().contains("Fresh Episode"));
+        assert_eq!(ui.get_current_ids(), (Some(1), Some(3)));
+
+        // cancelling while typing lists everything again too
+        push_keys(&["F"]);
+        type_text("xyz");
+        ui.press(&["Esc"]);
+        assert!(screen_text().contains("Fresh Episode"));
+        assert_eq!(ui.get_current_ids(), (Some(1), Some(3)));
+    }
+
+    #[test]
+    fn triage_mode() {
+        let config = Config::new(Path::new("/nonexistent/config.toml")).unwrap();
+        let pod = podcast(vec![
+            episode(2, "First Episode", true),
+            episode(3, "Second Episode", true),
+            episode(4, "Third Episode", true),
+        ]);
+        let mut ui = Ui::headless(config, LockVec::new(vec![pod]), 100, 30);
+        ui.press(&["b"]);
+        assert!(screen_text().contains("Episodes [triage]"));
+        assert_eq!(ui.get_current_ids(), (Some(1), Some(2)));
+
+        // each key deals with the episode and moves on to the next one
+        let msgs = ui.press(&["d"]);
+        assert!(matches!(
+            msgs.as_slice(),
+            [UiMsg::Triage(TriageAction::Download, eps)] if eps == &[(1, 2)]
+        ));
+        assert_eq!(ui.get_current_ids(), (Some(1), Some(3)));
+        ui.press(&["j", "k"]);
+        let msgs = ui.press(&["p", "x", "q"]);
+        assert!(matches!(
+            msgs.as_slice(),
+            [
+                UiMsg::Triage(TriageAction::Played, played),
+                UiMsg::Triage(TriageAction::Hide, hidden),
+                UiMsg::Triage(TriageAction::Queue, queued),
+            ] if played == &[(1, 3)] && hidden == &[(1, 4)] && queued == &[(1, 4)]
+        ));
+
+        // other keys are ignored until triage mode is stopped
+        assert!(ui.press(&["r", "a"]).is_empty());
+        ui.press(&["Esc"]);
+        assert!(!screen_text().contains("[triage]"));
+        assert!(matches!(ui.press(&["q"]).as_slice(), [UiMsg::Quit]));
+    }
+
+    #[test]
+    fn add_feed() {
+        let mut ui = headless_ui(80, 24);
+        push_keys(&["a"]);
+        type_text("https://example.com/feed.xml");
+        let msgs = ui.press(&["Enter"]);
+        assert!(matches!(
+            msgs.as_slice(),
+            [UiMsg::AddFeed(url)] if url == "https://example.com/feed.xml"
+        ));
+
+        // a prompt with nothing left to read is cancelled
+        assert!(ui.press(&["a"]).is_empty());
+    }
+
+    #[test]
+    fn change_feed_url() {
+        let config = Config::new(Path::new("/nonexistent/config.toml")).unwrap();
+        let mut pod = podcast(vec![episode(10, "Episode", false)]);
+        pod.url = "https://example.com/old.xml".to_string();
+        let mut ui = Ui::headless(config, LockVec::new(vec![pod]), 80, 24);
+        push_keys(&["e"]);
+        type_text(" https://example.com/new.xml ");
+        let msgs = ui.press(&["Enter"]);
+        assert!(matches!(
+            msgs.as_slice(),
+            [UiMsg::ChangeFeedUrl(1, url)] if url == "https://example.com/new.xml"
+        ));
+
+        // the URL the podcast already has is left alone
+        push_keys(&["e"]);
+        type_text("https://example.com/old.xml");
+        assert!(ui.press(&["Enter"]).is_empty());
+    }
+
+    #[test]
+    fn resize() {
+        let mut ui = headless_ui(80, 24);
+        ui.resize_screen(60, 15);
+        let lines = ui.screen();
+        assert_eq!(lines.len(), 15);
+        assert!(lines.iter().all(|l| l.chars().count() <= 60));
+        assert!(screen_text().contains("Welcome to shellcaster!"));
+
+        ui.resize_screen(100, 30);
+        let lines = ui.screen();
+        assert_eq!(lines.len(), 30);
+        assert!(lines.iter().any(|l| l.chars().count() > 60));
+        assert!(screen_text().contains("Welcome to shellcaster!"));
+    }
+
+    #[test]
+    fn inbox_triage() {
+        let config = Config::new(Path::new("/nonexistent/config.toml")).unwrap();
+        let pod = podcast(vec![
+            episode(2, "Fresh Episode", true),
+            episode(3, "Newer Episode", true),
+            episode(4, "Old Episode", false),
+            episode(5, "Next Episode", true),
+        ]);
+        let mut ui = Ui::headless(config, LockVec::new(vec![pod]), 100, 30);
+
+        ui.press(&["i"]);
+        let text = screen_text();
+        assert!(text.contains("3 new episodes."));
+        assert!(text.contains("Fresh Episode (Test Cast)"));
+        assert!(!text.contains("Old Episode (Test Cast)"));
+
+        let msgs = ui.press(&["d"]);
+        assert!(matches!(
+            msgs.as_slice(),
+            [UiMsg::Triage(TriageAction::Download, eps)] if eps.len() == 1
+        ));
+        assert!(screen_text().contains("2 new episodes."));
+
+        let msgs = ui.press(&["="]);
+        assert!(matches!(msgs.as_slice(), [UiMsg::Triage(TriageAction::QueueNext, _)]));
+        assert!(screen_text().contains("1 new episode."));
+
+        // the last episode closes the inbox
+        let msgs = ui.press(&["r"]);
+        assert!(matches!(msgs.as_slice(), [UiMsg::Triage(TriageAction::Hide, _)]));
+        assert!(!screen_text().contains("new episode"));
+    }
+
+    #[test]
+    fn notification_history() {
+        let mut ui = headless_ui(100, 30);
+        ui.press(&["N"]);
+        assert!(!screen_text().contains("Most recent notifications"));
+
+        ui.timed_notif("Error downloading episode: http://example.com/1.mp3".to_string(), 0, true);
+        ui.timed_notif("Sync complete.".to_string(), 0, false);
+        ui.press(&["N"]);
+        let text = screen_text();
+        assert!(text.contains("Most recent notifications, newest first:"));
+        let error = text.find("[error]  Error downloading episode: http://example.com/1.mp3");
+        let sync = text.find("Sync complete.");
+        assert!(sync.is_some() && error.is_some() && sync < error);
+    }
+
+    #[test]
+    fn download_library() {
+        let mut ui = headless_ui(100, 30);
+        let msgs = ui.press(&["A-d"]);
+        assert!(matches!(msgs.as_slice(), [UiMsg::DownloadLibrary]));
+
+        push_keys(&["y", "Enter"]);
+        let msg =
+            ui.ask_download_library(vec![(1, 2), (1, 3)], "2 episodes (about 50 MB)".to_string());
+        assert!(matches!(msg, UiMsg::DownloadMulti(eps) if eps.len() == 2));
+
+        push_keys(&["n", "Enter"]);
+        let msg = ui.ask_download_library(vec![(1, 2)], "1 episode (about 25 MB)".to_string());
+        assert!(matches!(msg, UiMsg::Noop));
+    }
+
+    #[test]
+    fn queue_reordering() {
+        let eps = vec![
+            episode(2, "First", false),
+            episode(3, "Second", false),
+            episode(4, "Third", false),
+        ];
+        let pod = podcast(eps.clone());
+        let listed = eps.iter().map(|ep| ListedEpisode::new(&pod, ep)).collect();
+        let mut ui = headless_ui(100, 30);
+        ui.popup_win.spawn_episode_list_win(EpisodeList::Queue, listed);
+        let order = |msgs: &[UiMsg]| match msgs {
+            [UiMsg::SetQueue(eps)] => eps.iter().map(|ep| ep.1).collect::<Vec<i64>>(),
+            _ => panic!("expected the queue to be set"),
+        };
+
+        // the cursor follows the episode that was moved
+        assert_eq!(order(&ui.press(&["A-j"])), vec![3, 2, 4]);
+        assert_eq!(order(&ui.press(&["A-j"])), vec![3, 4, 2]);
+        assert_eq!(order(&ui.press(&["A-g"])), vec![2, 3, 4]);
+
+        // marked episodes move together
+        ui.press(&["Space", "Space"]);
+        assert_eq!(order(&ui.press(&["A-G"])), vec![4, 2, 3]);
+        assert_eq!(order(&ui.press(&["r"])), vec![4]);
+        assert_eq!(order(&ui.press(&["R"])), Vec::<i64>::new());
+        assert!(!screen_text().contains("in the queue"));
+    }
+
+    #[test]
+    fn queue_interleave() {
+        let eps = vec![
+            episode(2, "First", false),
+            episode(3, "Second", false),
+            episode(4, "Third", false),
+        ];
+        let pod = podcast(eps.clone());
+        let mut listed: Vec<ListedEpisode> =
+            eps.iter().map(|ep| ListedEpisode::new(&pod, ep)).collect();
+        listed[2].pod_id = 2;
+        let mut ui = headless_ui(100, 30);
+        ui.popup_win.spawn_episode_list_win(EpisodeList::Queue, listed);
+
+        let msgs = ui.press(&["A-i"]);
+        let interleaved = [(1, 2), (2, 4), (1, 3)];
+        assert!(matches!(msgs.as_slice(), [UiMsg::SetQueue(eps)] if *eps == interleaved));
+        let msgs = ui.press(&["A-r"]);
+        assert!(matches!(msgs.as_slice(), [UiMsg::SetQueue(eps)] if eps.len() == 3));
+    }
+
+    #[test]
+    fn new_podcast_unplayed() {
+        let config = Config::new(Path::new("/nonexistent/config.toml")).unwrap();
+        let pod = podcast(vec![episode(2, "First", false), episode(3, "Second", false)]);
+        let mut ui = Ui::headless(config, LockVec::new(vec![pod]), 100, 30);
+        ui.spawn_new_podcast_win(1);
+        let text = screen_text();
+        assert!(text.contains("Added Test Cast with 2 episodes."));
+        assert!(text.contains("None (mark all as played)"));
+
+        let msgs = ui.press(&["Down", "Down", "Enter"]);
+        assert!(matches!(
+            msgs.as_slice(),
+            [UiMsg::MarkNewPodcast(1, NewPodcastEpisodes::AllPlayed)]
+        ));
+    }
+
+    #[test]
+    fn sync_digest_failures() {
+        let config = Config::new(Path::new("/nonexistent/config.toml")).unwrap();
+        let fresh = podcast(vec![episode(2, "Fresh Episode", true)]);
+        let mut old = episode(3, "Old Episode", false);
+        old.pod_id = 2;
+        let mut broken = podcast(vec![old]);
+        broken.id = 2;
+        broken.title = "Broken Cast".to_string();
+        broken.sort_title = "broken cast".to_string();
+        let mut ui = Ui::headless(config, LockVec::new(vec![fresh, broken]), 100, 30);
+
+        let new_ep = NewEpisode {
+            id: 2,
+            pod_id: 1,
+            title: "Fresh Episode".to_string(),
+            pod_title: "Test Cast".to_string(),
+            pubdate: None,
+            selected: true,
+        };
+        ui.popup_win.spawn_digest_win(vec![new_ep], vec![(2, "Broken Cast".to_string())]);
+        let text = screen_text();
+        assert!(text.contains("1 new episode across 1 podcast. 1 feed could not be synced."));
+        assert!(text.contains("Test Cast (1 new)"));
+        assert!(text.contains("! Broken Cast (could not sync)"));
+        assert!(!text.contains("Old Episode"));
+
+        // the feed that failed can be jumped to
+        ui.press(&["Down", "Enter"]);
+        let text = screen_text();
+        assert!(!text.contains("Sync digest"));
+        assert!(text.contains("Old Episode"));
+    }
+
+    #[test]
+    fn suspend_redraws() {
+        let mut ui = headless_ui(100, 30);
+        let before = screen();
+        ui.press(&["C-z"]);
+        // the screen is cleared on leaving, so everything on it now was
+        // drawn again on return
+        assert_eq!(screen(), before);
+        assert!(screen_text().contains("Welcome to shellcaster!"));
+    }
+}
@@ -1,11 +1,37 @@
 use std::cmp::min;
 use std::collections::hash_map::Entry;
+use std::collections::HashSet;
 
 use crossterm::style::{self, Stylize};
 
 use super::{Panel, Scroll};
+use crate::config::Glyphs;
 use crate::types::*;
 
+/// One row of a menu: an item, by its ID, the header of a group of
+/// items, with the name of the group and the number of items in it, or
+/// (in the tree layout) an item nested under another one, by the IDs of
+/// the parent and of the nested item.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MenuRow {
+    Item(i64),
+    Group(String, usize),
+    Child(i64, i64),
+}
+
+impl MenuRow {
+    /// Returns whether both rows are for the same item or group, even
+    /// if the number of items in the group has changed.
+    fn is_same(&self, other: &MenuRow) -> bool {
+        return match (self, other) {
+            (MenuRow::Item(a), MenuRow::Item(b)) => a == b,
+            (MenuRow::Group(a, _), MenuRow::Group(b, _)) => a == b,
+            (MenuRow::Child(a, x), MenuRow::Child(b, y)) => a == b && x == y,
+            _ => false,
+        };
+    }
+}
+
 /// Generic struct holding details about a list menu. These menus are
 /// contained by the UI, and hold the list of podcasts or podcast
 /// episodes. They also hold the Panel used to draw all elements to the
@@ -27,6 +53,22 @@ use crate::types::*;
 /// * `active` indicates whether the menu is currently interactive, e.g.,
 ///   if the user scrolls up or down, this is the menu that will receive
 ///   those events.
+/// * `marked` holds the IDs of items the user has tagged for a batch
+///   operation (e.g., downloading several episodes at once).
+/// * `drawn` holds the rows of the (filtered) list as it was last
+///   printed, so the cursor can stay on the same item when the list
+///   changes underneath it.
+/// * `groups` are the groups made up of tags from config.toml, and
+///   `collapsed` holds the names of the groups whose items are hidden
+///   under their headers. Items in a group are shown together under a
+///   header with its name; only podcasts are grouped.
+/// * `tree` indicates whether the menu is shown in the tree layout, where
+///   the items in `expanded` have their children (i.e., the episodes of
+///   a podcast) listed beneath them. Children that are marked for a
+///   batch operation are held in `marked_children`.
+/// * `find_term` narrows the menu to the items that contain it (see
+///   `Menuable::get_find_text()`), while the user is finding something
+///   in the menu.
 #[derive(Debug)]
 pub struct Menu<T>
 where T: Clone + Menuable
@@ -38,6 +80,16 @@ where T: Clone + Menuable
     pub top_row: u16,   // top row of text shown in window
     pub selected: u16,  // which line of text is highlighted
     pub active: bool,
+    pub marked: HashSet<i64>,
+    pub drawn: Vec<MenuRow>,
+    pub queued: HashSet<i64>,
+    pub glyphs: Glyphs,
+    pub groups: Vec<TagGroup>,
+    pub collapsed: HashSet<String>,
+    pub tree: bool,
+    pub expanded: HashSet<i64>,
+    pub marked_children: HashSet<i64>,
+    pub find_term: Option<String>,
 }
 
 impl<T: Clone + Menuable> Menu<T> {
@@ -51,6 +103,16 @@ impl<T: Clone + Menuable> Menu<T> {
             top_row: 0,
             selected: 0,
             active: false,
+            marked: HashSet::new(),
+            drawn: Vec::new(),
+            queued: HashSet::new(),
+            glyphs: Glyphs::ascii(),
+            groups: Vec::new(),
+            collapsed: HashSet::new(),
+            tree: false,
+            expanded: HashSet::new(),
+            marked_children: HashSet::new(),
+            find_term: None,
         };
     }
 
@@ -68,49 +130,309 @@ impl<T: Clone + Menuable> Menu<T> {
             self.selected = self.start_row;
         }
 
-        let (map, _unused, order) = self.items.borrow();
-        drop(_unused);
-        if !order.is_empty() {
+        let rows = self.rows();
+        if !rows.is_empty() {
             // update selected item if list has gotten shorter
             let current_selected = self.get_menu_idx(self.selected);
-            let list_len = order.len();
+            let list_len = rows.len();
             if current_selected >= list_len {
                 self.selected = (self.selected as usize - (current_selected - list_len) - 1) as u16;
             }
 
             // for visible rows, print strings from list
             for i in self.start_row..self.panel.get_rows() {
-                if let Some(elem_id) = order.get(self.get_menu_idx(i)) {
-                    let elem = map.get(elem_id).expect("Could not retrieve menu item.");
-
-                    if i == self.selected || !elem.is_played() {
-                        let style = if !elem.is_played() {
-                            style::ContentStyle::new()
-                                .with(self.panel.colors.bold.0)
-                                .on(self.panel.colors.bold.1)
-                                .attribute(style::Attribute::Bold)
-                        } else {
-                            style::ContentStyle::new()
-                                .with(self.panel.colors.normal.0)
-                                .on(self.panel.colors.normal.1)
-                        };
-                        self.panel.write_line(
-                            i,
-                            elem.get_title(self.panel.get_cols() as usize),
-                            Some(style),
-                        );
-                    } else {
-                        self.panel.write_line(
-                            i,
-                            elem.get_title(self.panel.get_cols() as usize),
-                            None,
-                        );
+                if let Some(row) = rows.get(self.get_menu_idx(i)) {
+                    if let Some((title, markers, is_played)) = self.format_row(row) {
+                        Self::write_title(&mut self.panel, i, title, markers, is_played);
                     }
                 } else {
                     break;
                 }
             }
         }
+        self.drawn = rows;
+    }
+
+    /// Returns the rows of the (filtered) list. Items in a group follow
+    /// a header for the group, unless it is collapsed; the list is
+    /// expected to be sorted so that the items of each group are next to
+    /// each other, after the items that are not in a group. In the tree
+    /// layout, the children of expanded items follow them. Items that do
+    /// not contain the find term (if any) are left out, as are the
+    /// groups left empty by that.
+    pub fn rows(&self) -> Vec<MenuRow> {
+        let (map, _unused, order) = self.items.borrow();
+        drop(_unused);
+        let find_term = self.find_term.as_ref().map(|term| term.to_lowercase());
+        let mut rows = Vec::with_capacity(order.len());
+        let mut header = None;
+        let mut current: Option<String> = None;
+        for id in order.iter() {
+            if let Some(term) = &find_term {
+                if !map.get(id).is_some_and(|elem| Self::is_found(elem, term)) {
+                    continue;
+                }
+            }
+            let group = map.get(id).and_then(|elem| elem.get_group(&self.groups));
+            if group != current {
+                header = group.as_ref().map(|name| {
+                    rows.push(MenuRow::Group(name.clone(), 0));
+                    rows.len() - 1
+                });
+                current = group;
+            }
+            if let Some(MenuRow::Group(_, count)) = header.and_then(|idx| rows.get_mut(idx)) {
+                *count += 1;
+            }
+            if !current.as_ref().is_some_and(|name| self.collapsed.contains(name)) {
+                rows.push(MenuRow::Item(*id));
+                if self.tree && self.expanded.contains(id) {
+                    if let Some(elem) = map.get(id) {
+                        let children = elem.get_children().into_iter();
+                        rows.extend(children.map(|child| MenuRow::Child(*id, child)));
+                    }
+                }
+            }
+        }
+        return rows;
+    }
+
+    /// Returns the title of a row, sized to fit the panel, along with
+    /// the position and length in bytes of its state markers (see
+    /// `format_title()`) and whether it is shown as played. The header of
+    /// a group is shown as played only if all of the items in it are.
+    /// Returns None if the item is no longer in the list.
+    fn format_row(&self, row: &MenuRow) -> Option<(String, (usize, usize), bool)> {
+        let width = self.panel.get_cols() as usize;
+        return match row {
+            MenuRow::Item(id) if self.tree => self.items.map_single(*id, |elem| {
+                let glyph = if self.expanded.contains(id) {
+                    &self.glyphs.expanded
+                } else {
+                    &self.glyphs.collapsed
+                };
+                let marked = self.marked.contains(id);
+                let (title, _) = self.format_title(elem, marked, width.saturating_sub(2));
+                let (start, rest) = title.split_at(1);
+                (format!("{start}{glyph} {rest}"), (0, 0), elem.is_played())
+            }),
+            MenuRow::Item(id) => self.items.map_single(*id, |elem| {
+                let marked = self.marked.contains(id);
+                let (title, marker_len) = self.format_title(elem, marked, width);
+                (title, (1, marker_len), elem.is_played())
+            }),
+            MenuRow::Child(id, child_id) => self
+                .items
+                .map_single(*id, |elem| {
+                    let mut details = None;
+                    elem.map_child(*child_id, &mut |child| {
+                        let marked = self.marked_children.contains(child_id);
+                        let (title, marker_len) =
+                            self.format_title(child, marked, width.saturating_sub(2));
+                        details = Some((format!("  {title}"), (3, marker_len), child.is_played()));
+                    });
+                    details
+                })
+                .flatten(),
+            MenuRow::Group(name, count) => {
+                let glyph = if self.collapsed.contains(name) {
+                    &self.glyphs.collapsed
+                } else {
+                    &self.glyphs.expanded
+                };
+                let played = self
+                    .items
+                    .filter_map(|elem| {
+                        (elem.get_group(&self.groups).as_ref() == Some(name))
+                            .then(|| elem.is_played())
+                    })
+                    .into_iter()
+                    .all(|played| played);
+                let title = format!(" {glyph} {name} ({count})");
+                Some((format!("{:width$}", title.substr(0, width)), (0, 0), played))
+            }
+        };
+    }
+
+    /// Returns the row the cursor is on, if any.
+    pub fn selected_row(&self) -> Option<MenuRow> {
+        return self.rows().get(self.get_menu_idx(self.selected)).cloned();
+    }
+
+    /// Returns the ID of the item the cursor is on (or that of its
+    /// parent, if it is on a nested item), or None if the list is empty
+    /// or the cursor is on the header of a group.
+    pub fn selected_id(&self) -> Option<i64> {
+        return match self.selected_row() {
+            Some(MenuRow::Item(id)) | Some(MenuRow::Child(id, _)) => Some(id),
+            _ => None,
+        };
+    }
+
+    /// Moves the cursor to the item with the given ID, expanding its
+    /// group if it is collapsed. Returns false if the item is not in the
+    /// (filtered) list.
+    pub fn select_id(&mut self, id: i64) -> bool {
+        if let Some(Some(group)) = self.items.map_single(id, |elem| elem.get_group(&self.groups)) {
+            self.collapsed.remove(&group);
+        }
+        return match self.rows().iter().position(|row| *row == MenuRow::Item(id)) {
+            Some(idx) => {
+                self.select_index(idx);
+                true
+            }
+            None => false,
+        };
+    }
+
+    /// Moves the cursor to the nested item `child_id` of the item `id`,
+    /// expanding both the item and its group. Returns false if the
+    /// nested item is not in the (filtered) list.
+    pub fn select_child(&mut self, id: i64, child_id: i64) -> bool {
+        self.expanded.insert(id);
+        if !self.select_id(id) {
+            return false;
+        }
+        let row = MenuRow::Child(id, child_id);
+        return match self.rows().iter().position(|r| *r == row) {
+            Some(idx) => {
+                self.select_index(idx);
+                true
+            }
+            None => false,
+        };
+    }
+
+    /// In the tree layout, shows the children of the item the cursor is
+    /// on if `expand` is true, or hides them otherwise; when they are
+    /// hidden while the cursor is on one of them, the cursor moves up to
+    /// their parent. Returns false if nothing changed.
+    pub fn set_expanded(&mut self, expand: bool) -> bool {
+        let id = match self.selected_row() {
+            Some(MenuRow::Item(id)) => id,
+            Some(MenuRow::Child(id, _)) if !expand => id,
+            _ => return false,
+        };
+        let changed = if expand {
+            self.expanded.insert(id)
+        } else {
+            self.expanded.remove(&id)
+        };
+        if changed {
+            self.reselect(MenuRow::Item(id));
+        }
+        return changed;
+    }
+
+    /// Collapses the group the cursor is in (or on the header of), or
+    /// expands it if it was collapsed. Returns false if the cursor is
+    /// not in a group.
+    pub fn toggle_group(&mut self) -> bool {
+        let group = match self.selected_row() {
+            Some(MenuRow::Group(name, _)) => Some(name),
+            Some(MenuRow::Item(id)) | Some(MenuRow::Child(id, _)) => {
+                self.items.map_single(id, |elem| elem.get_group(&self.groups)).flatten()
+            }
+            None => None,
+        };
+        let group = match group {
+            Some(group) => group,
+            None => return false,
+        };
+        if !self.collapsed.remove(&group) {
+            self.collapsed.insert(group.clone());
+        }
+        self.reselect(MenuRow::Group(group, 0));
+        return true;
+    }
+
+    /// Collapses all of the groups, or expands them all if they were
+    /// all collapsed already. Returns false if there are no groups.
+    pub fn toggle_all_groups(&mut self) -> bool {
+        let rows = self.rows();
+        let groups: Vec<String> = rows
+            .iter()
+            .filter_map(|row| match row {
+                MenuRow::Group(name, _) => Some(name.clone()),
+                _ => None,
+            })
+            .collect();
+        if groups.is_empty() {
+            return false;
+        }
+        let selected = rows.get(self.get_menu_idx(self.selected)).cloned();
+        if groups.iter().all(|name| self.collapsed.contains(name)) {
+            self.collapsed.clear();
+        } else {
+            self.collapsed.extend(groups);
+        }
+
+        // the cursor stays on its item, or moves to the header of the
+        // group the item was hidden in
+        let group = match &selected {
+            Some(MenuRow::Item(id)) | Some(MenuRow::Child(id, _)) => {
+                self.items.map_single(*id, |elem| elem.get_group(&self.groups)).flatten()
+            }
+            _ => None,
+        };
+        match (selected, group) {
+            (Some(row), None) => self.reselect(row),
+            (Some(row), Some(group)) => {
+                if self.collapsed.contains(&group) {
+                    self.reselect(MenuRow::Group(group, 0));
+                } else {
+                    self.reselect(row);
+                }
+            }
+            (None, _) => self.select_index(0),
+        }
+        return true;
+    }
+
+    /// Narrows the menu to the items that contain `term`, ignoring case,
+    /// or lists all of them again if it is None. The cursor stays on
+    /// the same item if it is still listed, and otherwise goes to the
+    /// top.
+    pub fn set_find_term(&mut self, term: Option<String>) {
+        let row = self.selected_row();
+        self.find_term = term;
+        self.top_row = 0;
+        self.selected = self.start_row;
+        let rows = self.rows();
+        if rows.is_empty() {
+            self.panel.clear_inner();
+            self.update_items();
+            return;
+        }
+        let idx = row.and_then(|row| rows.iter().position(|r| r.is_same(&row)));
+        self.select_index(idx.unwrap_or(0));
+    }
+
+    /// Lists all of the items again if the find term leaves out the item
+    /// `id`, e.g., to move the cursor to it. The menu still needs to be
+    /// redrawn. Returns whether the find term was cleared.
+    pub fn unhide(&mut self, id: i64) -> bool {
+        let term = match &self.find_term {
+            Some(term) => term.to_lowercase(),
+            None => return false,
+        };
+        if self.items.map_single(id, |elem| Self::is_found(elem, &term)) == Some(false) {
+            self.find_term = None;
+            return true;
+        }
+        return false;
+    }
+
+    /// Indicates whether the item contains the (lowercase) find term.
+    fn is_found(elem: &T, term: &str) -> bool {
+        return elem.get_find_text().to_lowercase().contains(term);
+    }
+
+    /// Moves the cursor to the row for the same item or group as `row`,
+    /// or to the top of the list if it is gone.
+    pub fn reselect(&mut self, row: MenuRow) {
+        let idx = self.rows().iter().position(|r| r.is_same(&row)).unwrap_or(0);
+        self.select_index(idx);
     }
 
     /// If a header exists, prints lines of text to the panel to appear
@@ -129,7 +451,7 @@ impl<T: Clone + Menuable> Menu<T> {
     /// not fall out of bounds, and then updates the panel to
     /// represent the new visible list.
     pub fn scroll(&mut self, lines: Scroll) {
-        let list_len = self.items.len(true) as u16;
+        let list_len = self.rows().len() as u16;
         if list_len == 0 {
             return;
         }
@@ -190,12 +512,11 @@ impl<T: Clone + Menuable> Menu<T> {
     pub fn highlight_item(&mut self, item_y: u16, active: bool) {
         // if list is empty, will return None
         let el_details = self
-            .items
-            .map_single_by_index(self.get_menu_idx(item_y), |el| {
-                (el.get_title(self.panel.get_cols() as usize), el.is_played())
-            });
+            .rows()
+            .get(self.get_menu_idx(item_y))
+            .and_then(|row| self.format_row(row));
 
-        if let Some((title, is_played)) = el_details {
+        if let Some((title, _, is_played)) = el_details {
             let mut style = style::ContentStyle::new();
             if active {
                 style = style.with(self.panel.colors.highlighted_active.0).on(self
@@ -222,23 +543,106 @@ impl<T: Clone + Menuable> Menu<T> {
     pub fn unhighlight_item(&mut self, item_y: u16) {
         // if list is empty, will return None
         let el_details = self
-            .items
-            .map_single_by_index(self.get_menu_idx(item_y), |el| {
-                (el.get_title(self.panel.get_cols() as usize), el.is_played())
-            });
+            .rows()
+            .get(self.get_menu_idx(item_y))
+            .and_then(|row| self.format_row(row));
 
-        if let Some((title, is_played)) = el_details {
-            let style = if is_played {
-                style::ContentStyle::new()
-                    .with(self.panel.colors.normal.0)
-                    .on(self.panel.colors.normal.1)
-            } else {
-                style::ContentStyle::new()
-                    .with(self.panel.colors.bold.0)
-                    .on(self.panel.colors.bold.1)
-                    .attribute(style::Attribute::Bold)
-            };
-            self.panel.write_line(item_y, title, Some(style));
+        if let Some((title, markers, is_played)) = el_details {
+            Self::write_title(&mut self.panel, item_y, title, markers, is_played);
+        }
+    }
+
+    /// Writes the title of an item that is not highlighted, in the
+    /// colors for played or unplayed items. The state markers, given by
+    /// their position and length in bytes in the title, are shown in the
+    /// colors for the downloaded marker.
+    fn write_title(
+        panel: &mut Panel,
+        item_y: u16,
+        title: String,
+        markers: (usize, usize),
+        is_played: bool,
+    ) {
+        let (marker_start, marker_len) = markers;
+        let colors = if is_played {
+            panel.colors.played()
+        } else {
+            panel.colors.bold
+        };
+        let mut style = style::ContentStyle::new().with(colors.0).on(colors.1);
+        if !is_played {
+            style = style.attribute(style::Attribute::Bold);
+        } else if panel.colors.dim_played() {
+            style = style.attribute(style::Attribute::Dim);
+        }
+        if marker_len > 0 {
+            let marker_colors = panel.colors.downloaded(colors);
+            let (start, rest) = title.split_at(marker_start);
+            let (marker, rest) = rest.split_at(marker_len);
+            let spans = [
+                (start.to_string(), Some(style)),
+                (marker.to_string(), Some(style.with(marker_colors.0).on(marker_colors.1))),
+                (rest.to_string(), Some(style)),
+            ];
+            panel.write_spans(item_y, &spans);
+        } else {
+            panel.write_line(item_y, title, Some(style));
+        }
+    }
+
+    /// Returns the title of an item, sized to `width` columns, along with
+    /// the length in bytes of the state markers at its start (0 if they
+    /// did not fit). Items that are `marked` for a batch operation have
+    /// their leading space replaced with a "*".
+    fn format_title(&self, elem: &dyn Menuable, marked: bool, width: usize) -> (String, usize) {
+        let markers = elem.get_markers(&self.glyphs, self.queued.contains(&elem.get_id()));
+        let title = elem.get_marked_title(width, &markers);
+        let marker_len = match title.get(1..) {
+            Some(rest) if !markers.is_empty() && rest.starts_with(&markers) => markers.len(),
+            _ => 0,
+        };
+        if marked {
+            if let Some(rest) = title.strip_prefix(' ') {
+                return (format!("*{rest}"), marker_len);
+            }
+        }
+        return (title, marker_len);
+    }
+
+    /// Marks the currently selected item for a batch operation, or
+    /// unmarks it if it was already marked.
+    pub fn toggle_mark(&mut self) {
+        let (marked, id) = match self.selected_row() {
+            Some(MenuRow::Item(id)) => (&mut self.marked, id),
+            Some(MenuRow::Child(_, id)) => (&mut self.marked_children, id),
+            _ => return,
+        };
+        if !marked.remove(&id) {
+            marked.insert(id);
+        }
+        self.highlight_selected();
+    }
+
+    /// Returns the IDs of all marked items, in the order they appear in
+    /// the menu. Items that are not shown (e.g., because they were
+    /// filtered out, or do not contain the find term) are skipped.
+    pub fn get_marked(&self) -> Vec<i64> {
+        return self
+            .rows()
+            .into_iter()
+            .filter_map(|row| match row {
+                MenuRow::Item(id) if self.marked.contains(&id) => Some(id),
+                _ => None,
+            })
+            .collect();
+    }
+
+    /// Removes all marks, and redraws the menu if there were any.
+    pub fn clear_marks(&mut self) {
+        if !self.marked.is_empty() {
+            self.marked.clear();
+            self.update_items();
+            self.highlight_selected();
         }
     }
 
@@ -269,6 +673,67 @@ impl<T: Clone + Menuable> Menu<T> {
         self.redraw();
     }
 
+    /// Moves the cursor to the row at position `index` in the
+    /// (filtered) list, scrolling the list if the item is not currently
+    /// visible.
+    pub fn select_index(&mut self, index: usize) {
+        let list_len = self.rows().len();
+        if list_len == 0 {
+            return;
+        }
+        let index = min(index, list_len - 1) as u16;
+        let visible_rows = self.panel.get_rows() - self.start_row;
+        if index < self.top_row {
+            self.top_row = index;
+        } else if index >= self.top_row + visible_rows {
+            self.top_row = index - visible_rows + 1;
+        }
+        self.selected = self.start_row + index - self.top_row;
+        self.panel.clear_inner();
+        self.update_items();
+        self.highlight_selected();
+    }
+
+    /// Returns the ID of the item the cursor was on (or that of its
+    /// parent) when the menu was last drawn.
+    pub fn drawn_selection(&self) -> Option<i64> {
+        return match self.drawn.get(self.get_menu_idx(self.selected)) {
+            Some(MenuRow::Item(id)) | Some(MenuRow::Child(id, _)) => Some(*id),
+            _ => None,
+        };
+    }
+
+    /// After the list has changed (e.g., new episodes were added by a
+    /// sync), moves the cursor back to the item it was on when the menu
+    /// was last drawn, keeping it on the same row of the screen where
+    /// possible. If that item is gone, the cursor goes to its nearest
+    /// neighbor that is still in the list, looking below it first. This
+    /// only adjusts the position; the menu still needs to be redrawn.
+    pub fn follow_selection(&mut self) {
+        let old_idx = self.get_menu_idx(self.selected);
+        let order = self.rows();
+        if order.is_empty() || self.drawn.is_empty() || order == self.drawn {
+            return;
+        }
+        let position = |idx: usize| {
+            let row = self.drawn.get(idx)?;
+            return order.iter().position(|x| x.is_same(row));
+        };
+        let new_idx = (0..self.drawn.len()).find_map(|dist| {
+            position(old_idx + dist).or_else(|| position(old_idx.checked_sub(dist)?))
+        });
+        let new_idx = match new_idx {
+            Some(idx) => idx as u16,
+            None => return,
+        };
+
+        let visible_rows = self.panel.get_rows() - self.start_row;
+        let max_top = (order.len() as u16).saturating_sub(visible_rows);
+        let row = self.selected - self.start_row;
+        self.top_row = min(new_idx.saturating_sub(row), max_top);
+        self.selected = self.start_row + new_idx - self.top_row;
+    }
+
     /// Given a row on the panel, this translates it into the
     /// corresponding menu item it represents. Note that this does not
     /// do any checks to ensure `screen_y` is between 0 and `n_rows`,
@@ -284,17 +749,14 @@ impl Menu<Podcast> {
     /// Returns a cloned reference to the list of episodes from the
     /// currently selected podcast.
     pub fn get_episodes(&self) -> LockVec<Episode> {
-        let index = self.get_menu_idx(self.selected);
-        let (borrowed_map, _unused, borrowed_order) = self.items.borrow();
-        drop(_unused);
-        let pod_id = borrowed_order
-            .get(index)
-            .expect("Could not retrieve podcast.");
-        return borrowed_map
-            .get(pod_id)
-            .expect("Could not retrieve podcast info.")
-            .episodes
-            .clone();
+        // the header of a group has no episodes of its own
+        return match self.selected_id() {
+            Some(pod_id) => self
+                .items
+                .map_single(pod_id, |pod| pod.episodes.clone())
+                .expect("Could not retrieve podcast info."),
+            None => LockVec::new(Vec::new()),
+        };
     }
 
     /// Controls how the window changes when it is inactive (i.e., not
@@ -305,6 +767,15 @@ impl Menu<Podcast> {
     }
 }
 
+impl Menu<ListedEpisode> {
+    /// Controls how the window changes when it is inactive (i.e., not
+    /// available for user input to modify state).
+    pub fn deactivate(&mut self) {
+        self.active = false;
+        self.highlight_item(self.selected, false);
+    }
+}
+
 impl Menu<Episode> {
     /// Controls how the window changes when it is inactive (i.e., not
     /// available for user input to modify state). If true,
@@ -376,7 +847,9 @@ impl Menu<NewEpisode> {
 
 
 // TESTS ----------------------------------------------------------------
-#[cfg(test)]
+// these read back what was drawn to the mock panel, which is replaced
+// by the real one with the "headless" feature
+#[cfg(all(test, not(feature = "headless")))]
 mod tests {
     use super::*;
     use chrono::Utc;
@@ -405,8 +878,21 @@ mod tests {
                 description: String::new(),
                 pubdate: Some(Utc::now()),
                 duration: Some(12345),
+                image_url: None,
+                chapters_url: None,
+                chapters: Vec::new(),
+                transcript_url: None,
+                transcript_type: None,
+                enclosures: Vec::new(),
+                enclosure_error: None,
                 path: None,
                 played: played,
+                updated: false,
+                starred: false,
+                clean_title: None,
+                position: None,
+                last_played: None,
+                new: false,
             });
         }
 
@@ -427,6 +913,16 @@ mod tests {
             top_row: top_row,
             selected: selected,
             active: true,
+            marked: HashSet::new(),
+            drawn: Vec::new(),
+            queued: HashSet::new(),
+            glyphs: Glyphs::unicode(),
+            groups: Vec::new(),
+            collapsed: HashSet::new(),
+            tree: false,
+            expanded: HashSet::new(),
+            marked_children: HashSet::new(),
+            find_term: None,
         };
     }
 
@@ -541,4 +1037,134 @@ mod tests {
 
         assert_eq!(menu.panel.get_row(3), expected);
     }
+
+    #[test]
+    fn toggle_mark() {
+        let real_rows = 5;
+        let real_cols = 65;
+        let mut menu = create_menu(real_rows + 2, real_cols + 3, 0, 1);
+        menu.update_items();
+
+        menu.toggle_mark();
+        assert_eq!(menu.get_marked(), vec![1]);
+        assert!(menu.panel.get_row(1).starts_with('*'));
+
+        menu.toggle_mark();
+        assert!(menu.get_marked().is_empty());
+        assert!(menu.panel.get_row(1).starts_with(' '));
+    }
+
+    #[test]
+    fn marks_hidden_by_find() {
+        let mut menu = create_menu(7, 68, 0, 0);
+        menu.update_items();
+        menu.toggle_mark();
+        menu.select_index(1);
+        menu.toggle_mark();
+        assert_eq!(menu.get_marked().len(), 2);
+
+        // only the marked items that are still listed are acted on
+        menu.set_find_term(Some("cool".to_string()));
+        assert_eq!(menu.get_marked(), vec![0]);
+        menu.set_find_term(None);
+        assert_eq!(menu.get_marked().len(), 2);
+    }
+
+    #[test]
+    fn select_index_scrolls() {
+        let real_rows = 5;
+        let real_cols = 65;
+        let mut menu = create_menu(real_rows + 2, real_cols + 3, 0, 0);
+        menu.update_items();
+
+        menu.select_index(6);
+        assert_eq!(menu.top_row, 2);
+        assert_eq!(menu.selected, 4);
+
+        menu.select_index(0);
+        assert_eq!(menu.top_row, 0);
+        assert_eq!(menu.selected, 0);
+    }
+
+    #[test]
+    fn follow_selection() {
+        let real_rows = 5;
+        let real_cols = 65;
+        let mut menu = create_menu(real_rows + 2, real_cols + 3, 0, 3);
+        menu.update_items();
+        let episodes = menu.items.map(|ep| ep.clone(), false);
+
+        // two new episodes at the top push the selected one down
+        let mut synced = episodes.clone();
+        for id in [10, 11] {
+            let mut ep = episodes[0].clone();
+            ep.id = id;
+            synced.insert(0, ep);
+        }
+        menu.items.replace_all(synced);
+        menu.follow_selection();
+        menu.update_items();
+        assert_eq!(menu.drawn_selection(), Some(3));
+        assert_eq!((menu.top_row, menu.selected), (2, 3));
+
+        // a removed episode hands the cursor to the one after it
+        let remaining = episodes.iter().filter(|ep| ep.id != 3).cloned().collect();
+        menu.items.replace_all(remaining);
+        menu.follow_selection();
+        menu.update_items();
+        assert_eq!(menu.drawn_selection(), Some(4));
+        assert_eq!((menu.top_row, menu.selected), (0, 3));
+    }
+
+    #[test]
+    fn status_markers() {
+        let real_rows = 5;
+        let real_cols = 65;
+        let mut menu = create_menu(real_rows + 2, real_cols + 3, 0, 4);
+        let mut episodes = menu.items.map(|ep| ep.clone(), false);
+        episodes[0].path = Some(std::path::PathBuf::from("episode.mp3"));
+        episodes[1].title = "[D] is not a marker here".to_string();
+        episodes[4].new = true;
+        menu.items.replace_all(episodes);
+        menu.queued.insert(3);
+        menu.glyphs = Glyphs {
+            played: "[P]".to_string(),
+            ..Glyphs::ascii()
+        };
+        menu.update_items();
+
+        // episodes with an even ID are played
+        assert!(menu.panel.get_row(0).starts_with(" [D][P] A Very Cool Episode "));
+        let width = menu.panel.get_cols() as usize;
+        let marker_lens = menu.items.map(|ep| menu.format_title(ep, false, width).1, false);
+        assert_eq!(marker_lens[..5], [6, 0, 3, 3, 6]);
+
+        menu.glyphs = Glyphs::unicode();
+        menu.update_items();
+        assert!(menu.panel.get_row(0).starts_with(" ↓ A Very Cool Episode "));
+    }
+
+    #[test]
+    fn find_term() {
+        let mut menu = create_menu(7, 68, 0, 4);
+        menu.update_items();
+        assert_eq!(menu.drawn_selection(), Some(4));
+
+        // the cursor stays on its episode if it is still listed
+        menu.set_find_term(Some("ANOTHER".to_string()));
+        assert_eq!(menu.rows(), vec![MenuRow::Item(4)]);
+        assert_eq!(menu.selected_id(), Some(4));
+        assert!(menu.panel.get_row(0).contains("Here's another title"));
+
+        menu.set_find_term(Some("episode with".to_string()));
+        assert_eq!(menu.rows(), vec![MenuRow::Item(2), MenuRow::Item(3)]);
+        assert_eq!(menu.selected_id(), Some(2));
+
+        menu.set_find_term(Some("nothing like this".to_string()));
+        assert!(menu.rows().is_empty());
+        assert_eq!(menu.panel.get_row(0).trim(), "");
+
+        menu.set_find_term(None);
+        assert_eq!(menu.rows().len(), 7);
+    }
 }
@@ -15,6 +15,8 @@ pub struct Panel {
     pub n_row: u16,
     pub n_col: u16,
     pub margins: (u16, u16, u16, u16),
+    pub hidden: bool,
+    pub tab_bar: Vec<(String, bool)>,
 }
 
 impl Panel {
@@ -40,11 +42,15 @@ impl Panel {
             n_row: n_row,
             n_col: n_col,
             margins: margins,
+            hidden: false,
+            tab_bar: Vec::new(),
         };
     }
 
     pub fn redraw(&self) {}
 
+    pub fn redraw_border(&self) {}
+
     // pub fn clear(&mut self) {
     //     self.clear_inner();
     // }
@@ -57,6 +63,12 @@ impl Panel {
         self.buffer[y as usize] = string;
     }
 
+    pub fn write_spans(&mut self, y: u16, spans: &[(String, Option<style::ContentStyle>)]) {
+        self.buffer[y as usize] = spans.iter().map(|(text, _)| text.as_str()).collect();
+    }
+
+    pub fn write_raw(&self, _y: u16, _raw: &str) {}
+
     pub fn write_key_value_line(
         &mut self,
         y: u16,
@@ -88,6 +100,18 @@ impl Panel {
         return row - 1;
     }
 
+    pub fn set_title(&mut self, title: String) {
+        self.title = title;
+    }
+
+    pub fn get_title(&self) -> &str {
+        return &self.title;
+    }
+
+    pub fn set_tab_bar(&mut self, tabs: Vec<(String, bool)>) {
+        self.tab_bar = tabs;
+    }
+
     pub fn resize(&mut self, n_row: u16, n_col: u16, start_x: u16) {
         self.n_row = n_row;
         self.n_col = n_col;
@@ -1,54 +1,60 @@
-use std::io::{self, Write};
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process;
 use std::rc::Rc;
 use std::sync::mpsc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
+use anyhow::{anyhow, Context, Result};
 use crossterm::{
     self, cursor,
-    event::{self, Event},
-    execute, terminal,
+    event::{self, Event, KeyModifiers},
+    execute, queue, style, terminal,
 };
-use lazy_static::lazy_static;
-use regex::Regex;
+use chrono::{DateTime, TimeZone, Utc};
 
-#[cfg_attr(not(test), path = "panel.rs")]
-#[cfg_attr(test, path = "mock_panel.rs")]
+#[cfg_attr(any(not(test), feature = "headless"), path = "panel.rs")]
+#[cfg_attr(all(test, not(feature = "headless")), path = "mock_panel.rs")]
 mod panel;
 
+// with the "headless" feature, the UI draws to a virtual screen instead
+// of the terminal, and reads scripted key presses
+#[cfg_attr(not(feature = "headless"), path = "term.rs")]
+#[cfg_attr(feature = "headless", path = "headless_term.rs")]
+pub mod term;
+
 pub mod colors;
 mod details_panel;
 mod menu;
 mod notification;
 mod popup;
+mod setup;
+mod tabs;
+mod transcript_win;
 
 use self::colors::AppColors;
 use self::details_panel::{Details, DetailsPanel};
-use self::menu::Menu;
+use self::menu::{Menu, MenuRow};
 use self::notification::NotifWin;
 use self::panel::Panel;
-use self::popup::PopupWin;
+use self::popup::{EpisodeList, PopupWin};
+use self::setup::Setup;
+use self::tabs::{Tab, TabView};
 
 use super::MainMessage;
-use crate::config::Config;
+use crate::clipboard;
+use crate::config::{Config, EnterAction, NewPodcastEpisodes};
+use crate::cover_art::{self, ImageProtocol};
+use crate::download_rules;
+use crate::html::{self, Rendered};
+use crate::smart_filters;
+use crate::title_rules;
+use crate::feeds::PodcastFeed;
 use crate::keymap::{Keybindings, UserAction};
 use crate::types::*;
 
-/// Amount of time between ticks in the event loop
-const TICK_RATE: u64 = 20;
-
-lazy_static! {
-    /// Regex for finding <br/> tags -- also captures any surrounding
-    /// line breaks
-    static ref RE_BR_TAGS: Regex = Regex::new(r"((\r\n)|\r|\n)*<br */?>((\r\n)|\r|\n)*").expect("Regex error");
-
-    /// Regex for finding HTML tags
-    static ref RE_HTML_TAGS: Regex = Regex::new(r"<[^<>]*>").expect("Regex error");
-
-    /// Regex for finding more than two line breaks
-    static ref RE_MULT_LINE_BREAKS: Regex = Regex::new(r"((\r\n)|\r|\n){3,}").expect("Regex error");
-}
-
 
 /// Enum used for communicating back to the main controller after user
 /// input has been captured by the UI. usize values always represent the
@@ -59,19 +65,58 @@ pub enum UiMsg {
     AddFeed(String),
     Play(i64, i64),
     MarkPlayed(i64, i64, bool),
+    MarkPlayedMulti(Vec<(i64, i64)>, bool),
+    ToggleStar(i64, i64),
     MarkAllPlayed(i64, bool),
+    MarkOlderPlayed(Option<i64>, DateTime<Utc>),
+    MarkNewPodcast(i64, NewPodcastEpisodes),
     Sync(i64),
     SyncAll,
+    CheckAll,
     Download(i64, i64),
     DownloadMulti(Vec<(i64, i64)>),
     DownloadAll(i64),
+    DownloadEnclosure(i64, i64, usize),
+    DownloadLibrary,
+    Cancel,
     UnmarkDownloaded(i64, i64),
     Delete(i64, i64),
+    DeleteMulti(Vec<(i64, i64)>),
     DeleteAll(i64),
     RemovePodcast(i64, bool),
     RemoveEpisode(i64, i64, bool),
     RemoveAllEpisodes(i64, bool),
+    ToggleNotify(i64),
+    SetDownloadRules(i64, Option<String>, Option<String>),
+    SetOverrides(i64, PodcastOverrides),
+    SetAuth(i64, FeedAuth),
+    SetArchived(i64, bool),
+    SetCustomTitle(i64, Option<String>),
+    SetGroup(i64, Option<String>),
+    SetTitleRule(i64, Option<String>, Option<String>),
+    SetSkipRules(i64, Option<String>, Option<String>),
+    ChangeFeedUrl(i64, String),
+    RetryFeed(Box<PodcastFeed>, Option<FeedAuth>),
+    ReloadConfig,
+    SaveSettings(Vec<(String, Option<String>)>),
+    FinishSetup(Vec<(String, Option<String>)>),
+    ImportFile(PathBuf),
+    FetchImage(String),
+    RenderImage(PathBuf, ImageProtocol, u16, u16),
+    FetchChapters(i64, i64),
+    JumpToChapter(i64, f64),
+    FetchTranscript(i64, i64),
     FilterChange(FilterType),
+    SortChange(SortTarget),
+    ShowStats,
+    ShowLog,
+    Triage(TriageAction, Vec<(i64, i64)>),
+    ShowQueue,
+    Dequeue(i64, i64),
+    SetQueue(Vec<(i64, i64)>),
+    ShuffleQueue,
+    InterleaveQueue,
+    FillQueue(usize),
     Quit,
     Noop,
 }
@@ -95,109 +140,258 @@ enum ActivePanel {
 /// it encapsulates the terminal menus and panels, and holds data about
 /// the size of the screen.
 #[derive(Debug)]
-pub struct Ui<'a> {
+pub struct Ui {
     n_row: u16,
     n_col: u16,
-    keymap: &'a Keybindings,
+    config: Rc<Config>,
+    keymap: Rc<Keybindings>,
     colors: Rc<AppColors>,
+    theme: Option<String>,
+    cover_art: Option<ImageProtocol>,
+    wrap_details: bool,
+    requested_images: HashSet<String>,
+    image_requests: Vec<String>,
+    chapter_requests: Vec<(i64, i64)>,
+    descriptions: HashMap<i64, Option<Rendered>>,
+    pending_msgs: Vec<UiMsg>,
     podcast_menu: Menu<Podcast>,
     episode_menu: Menu<Episode>,
     details_panel: Option<DetailsPanel>,
     active_panel: ActivePanel,
     notif_win: NotifWin,
-    popup_win: PopupWin<'a>,
+    popup_win: PopupWin,
+    filters: Filters,
+    setup: Option<Setup>,
+    tab: Tab,
+    tabs: Vec<TabView>,
+    queue: Vec<i64>,
+    search_term: Option<String>,
+    triage: bool,
 }
 
-impl<'a> Ui<'a> {
+impl Ui {
     /// Spawns a UI object in a new thread, with message channels to send
-    /// and receive messages
+    /// and receive messages. If the UI can't go on (e.g., because the
+    /// terminal can't be drawn to), the terminal is restored, the main
+    /// controller is told to quit, and the error is returned when the
+    /// thread is joined.
     pub fn spawn(
         config: Config,
         items: LockVec<Podcast>,
         rx_from_main: mpsc::Receiver<MainMessage>,
         tx_to_main: mpsc::Sender<Message>,
-    ) -> thread::JoinHandle<()> {
+    ) -> thread::JoinHandle<Result<()>> {
         return thread::spawn(move || {
-            let mut ui = Ui::new(&config, items);
-            ui.init();
-            let mut message_iter = rx_from_main.try_iter();
-            // this is the main event loop: on each loop, we update
-            // any messages at the bottom, check for user input, and
-            // then process any messages from the main thread
-            loop {
-                ui.notif_win.check_notifs();
+            // messages from the main thread are passed along by a
+            // thread of their own, which wakes up the UI for each one.
+            // It keeps draining them even after the UI has stopped, so
+            // that the main controller can still send messages while it
+            // finishes up
+            let (tx_messages, rx_messages) = mpsc::channel();
+            let wake = term::waker();
+            thread::spawn(move || {
+                for message in rx_from_main.iter() {
+                    if tx_messages.send(message).is_ok() {
+                        wake();
+                    }
+                }
+            });
 
+            let result = Self::run(config, items, rx_messages, &tx_to_main);
+            if result.is_err() {
+                Self::restore_terminal();
+                let _ = tx_to_main.send(Message::Ui(UiMsg::Quit));
+            }
+            return result;
+        });
+    }
+
+    /// Creates the UI and runs its event loop until the main controller
+    /// asks it to tear down.
+    fn run(
+        config: Config,
+        items: LockVec<Podcast>,
+        rx_messages: mpsc::Receiver<MainMessage>,
+        tx_to_main: &mpsc::Sender<Message>,
+    ) -> Result<()> {
+        let mut ui = Ui::new(config, items)?;
+        ui.init()?;
+
+        // this is the main event loop: on each loop, we update
+        // any messages at the bottom, check for user input, and
+        // then process any messages from the main thread, before
+        // sleeping until there is something else to do
+        'events: loop {
+            ui.notif_win.check_notifs();
+
+            while term::poll() {
                 match ui.getch() {
                     UiMsg::Noop => (),
-                    input => tx_to_main
-                        .send(Message::Ui(input))
-                        .expect("Thread messaging error"),
+                    input => send_to_main(tx_to_main, Message::Ui(input))?,
+                }
+                // messages from macros, in the order they were run
+                for msg in ui.pending_msgs.drain(..) {
+                    send_to_main(tx_to_main, Message::Ui(msg))?;
                 }
+            }
 
-                if let Some(message) = message_iter.next() {
-                    match message {
-                        MainMessage::UiUpdateMenus => ui.update_menus(),
-                        MainMessage::UiSpawnNotif(msg, duration, error) => {
-                            ui.timed_notif(msg, error, duration)
-                        }
-                        MainMessage::UiSpawnPersistentNotif(msg, error) => {
-                            ui.persistent_notif(msg, error)
+            // cover art that is not cached yet is fetched by the
+            // main controller, off the UI thread
+            for url in ui.image_requests.drain(..) {
+                send_to_main(tx_to_main, Message::Ui(UiMsg::FetchImage(url)))?;
+            }
+            if let Some(det) = ui.details_panel.as_mut() {
+                if let Some((path, protocol, cols, rows)) = det.art_request.take() {
+                    let msg = UiMsg::RenderImage(path, protocol, cols, rows);
+                    send_to_main(tx_to_main, Message::Ui(msg))?;
+                }
+            }
+            for (pod_id, ep_id) in ui.chapter_requests.drain(..) {
+                send_to_main(tx_to_main, Message::Ui(UiMsg::FetchChapters(pod_id, ep_id)))?;
+            }
+
+            for message in rx_messages.try_iter() {
+                match message {
+                    MainMessage::UiUpdateMenus => ui.update_menus(),
+                    MainMessage::UiSpawnNotif(msg, duration, error) => {
+                        ui.timed_notif(msg, error, duration)
+                    }
+                    MainMessage::UiSpawnPersistentNotif(msg, error) => {
+                        ui.persistent_notif(msg, error)
+                    }
+                    MainMessage::UiClearPersistentNotif => ui.clear_persistent_notif(),
+                    MainMessage::UiTearDown => {
+                        ui.tear_down();
+                        break 'events;
+                    }
+                    MainMessage::UiSpawnDownloadPopup(episodes, selected) => {
+                        ui.clear_cover_art();
+                        ui.popup_win.spawn_download_win(episodes, selected);
+                    }
+                    MainMessage::UiSpawnDigestPopup(episodes, failed) => {
+                        ui.clear_cover_art();
+                        ui.popup_win.spawn_digest_win(episodes, failed);
+                    }
+                    MainMessage::UiReloadConfig(config) => ui.reload_config(*config),
+                    MainMessage::UiUpdateFilters(filters) => ui.update_filters(filters),
+                    MainMessage::UiAskNewPodcast(pod_id) => {
+                        ui.spawn_new_podcast_win(pod_id);
+                    }
+                    MainMessage::UiAskFeedAuth(feed) => {
+                        let msg = ui.ask_feed_auth(*feed);
+                        send_to_main(tx_to_main, Message::Ui(msg))?;
+                    }
+                    MainMessage::UiAskDeadFeed(feed, failures) => {
+                        let msg = ui.ask_dead_feed(*feed, failures);
+                        send_to_main(tx_to_main, Message::Ui(msg))?;
+                    }
+                    MainMessage::UiAskDownloadLibrary(episodes, description) => {
+                        let msg = ui.ask_download_library(episodes, description);
+                        send_to_main(tx_to_main, Message::Ui(msg))?;
+                    }
+                    MainMessage::UiSpawnStats(stats) => {
+                        ui.clear_cover_art();
+                        ui.popup_win.spawn_stats_win(stats);
+                    }
+                    MainMessage::UiSpawnQueue(episodes) => {
+                        if episodes.is_empty() {
+                            ui.timed_notif("The queue is empty.".to_string(), 5000, false);
+                        } else {
+                            ui.clear_cover_art();
+                            ui.popup_win.spawn_episode_list_win(EpisodeList::Queue, episodes);
                         }
-                        MainMessage::UiClearPersistentNotif => ui.clear_persistent_notif(),
-                        MainMessage::UiTearDown => {
-                            ui.tear_down();
-                            break;
+                    }
+                    MainMessage::UiUpdateQueue(episodes) => ui.update_queue(episodes),
+                    MainMessage::UiSpawnTranscript(title, lines) => {
+                        ui.clear_cover_art();
+                        ui.popup_win.spawn_transcript_win(title, lines);
+                    }
+                    MainMessage::UiSpawnWhatsNew(version, entries) => {
+                        ui.clear_cover_art();
+                        ui.popup_win.spawn_whats_new_win(version, entries);
+                    }
+                    MainMessage::UiSpawnLog(path, lines) => {
+                        ui.clear_cover_art();
+                        ui.popup_win.spawn_log_win(path, lines);
+                    }
+                    MainMessage::UiSpawnSetup(players) => {
+                        ui.spawn_setup_win(players, true)
+                    }
+                    MainMessage::UiSpawnConfigProblems(lines) => {
+                        ui.clear_cover_art();
+                        ui.popup_win.spawn_config_win(lines);
+                    }
+                    MainMessage::UiSpawnCheckReport(lines) => {
+                        ui.clear_cover_art();
+                        ui.popup_win.spawn_check_win(lines);
+                    }
+                    MainMessage::UiHandOverTerminal(ready, done) => {
+                        ui.hand_over_terminal(ready, done)
+                    }
+                    MainMessage::UiUpdateDetails => {
+                        if !ui.popup_win.is_non_welcome_popup_active() {
+                            ui.update_details_panel();
                         }
-                        MainMessage::UiSpawnDownloadPopup(episodes, selected) => {
-                            ui.popup_win.spawn_download_win(episodes, selected);
+                    }
+                    MainMessage::UiUpdateArt(path, cols, rows, art) => {
+                        if let Some(det) = ui.details_panel.as_mut() {
+                            if det.set_art(path, cols, rows, art)
+                                && !ui.popup_win.is_non_welcome_popup_active()
+                            {
+                                det.redraw_details();
+                            }
                         }
                     }
                 }
+            }
 
-                io::stdout().flush().unwrap();
+            ui.notif_win.check_notifs();
+            term::stdout().flush().context("Can't draw to screen")?;
 
-                // slight delay to avoid excessive CPU usage
-                thread::sleep(Duration::from_millis(TICK_RATE));
-            }
-        });
+            // wake up again in time to clear the next notification
+            let timeout = ui
+                .notif_win
+                .next_expiry()
+                .map(|expiry| expiry.saturating_duration_since(Instant::now()));
+            term::wait(timeout);
+        }
+        return Ok(());
     }
 
     /// Initializes the UI with a list of podcasts and podcast episodes,
     /// creates the menus and panels, and returns a UI object for future
     /// manipulation.
-    pub fn new(config: &'a Config, items: LockVec<Podcast>) -> Ui<'a> {
-        terminal::enable_raw_mode().expect("Terminal can't run in raw mode.");
+    pub fn new(config: Config, items: LockVec<Podcast>) -> Result<Ui> {
+        term::enable_raw_mode();
         execute!(
-            io::stdout(),
+            term::stdout(),
             terminal::EnterAlternateScreen,
             terminal::Clear(terminal::ClearType::All),
             cursor::Hide
         )
-        .expect("Can't draw to screen.");
+        .context("Can't draw to screen")?;
 
         let colors = Rc::new(config.colors.clone());
 
-        let (n_col, n_row) = terminal::size().expect("Can't get terminal size");
+        let (n_col, n_row) = term::size();
         let (pod_col, ep_col, det_col) = Self::calculate_sizes(n_col);
 
-        let first_pod = match items.borrow_filtered_order().get(0) {
-            Some(first_id) => match items.borrow_map().get(first_id) {
-                Some(pod) => pod.episodes.clone(),
-                None => LockVec::new(Vec::new()),
-            },
-            None => LockVec::new(Vec::new()),
-        };
-
+        // in the tree layout, the podcast panel also takes up the space
+        // of the episode panel
         let podcast_panel = Panel::new(
             "Podcasts".to_string(),
             0,
             colors.clone(),
             n_row - 1,
-            pod_col,
+            if config.tree_layout { pod_col + ep_col - 1 } else { pod_col },
             0,
             (0, 0, 0, 0),
         );
-        let podcast_menu = Menu::new(podcast_panel, None, items);
+        let mut podcast_menu = Menu::new(podcast_panel, None, items);
+        podcast_menu.glyphs = config.glyphs.clone();
+        podcast_menu.groups = config.groups.clone();
+        podcast_menu.tree = config.tree_layout;
+        let first_pod = podcast_menu.get_episodes();
 
         let episode_panel = Panel::new(
             "Episodes".to_string(),
@@ -209,10 +403,16 @@ impl<'a> Ui<'a> {
             (0, 0, 0, 0),
         );
 
-        let episode_menu = Menu::new(episode_panel, None, first_pod);
+        let mut episode_menu = Menu::new(episode_panel, None, first_pod);
+        episode_menu.glyphs = config.glyphs.clone();
+        episode_menu.panel.hidden = config.tree_layout;
 
+        let cover_art = match config.image_cache {
+            Some(_) => config.cover_art.protocol(),
+            None => None,
+        };
         let details_panel = if n_col > crate::config::DETAILS_PANEL_LENGTH {
-            Some(DetailsPanel::new(
+            let mut det = DetailsPanel::new(
                 "Details".to_string(),
                 2,
                 colors.clone(),
@@ -220,35 +420,87 @@ impl<'a> Ui<'a> {
                 det_col,
                 pod_col + ep_col - 2,
                 (0, 1, 0, 1),
-            ))
+            );
+            det.cover_art = cover_art;
+            det.wrap = config.wrap_details;
+            det.wrap_width = config.details_wrap_width;
+            Some(det)
         } else {
             None
         };
 
+        // the tabs other than the library list their episodes in one
+        // panel, in place of the podcast and episode panels
+        let tabs = Tab::ALL
+            .iter()
+            .map(|tab| {
+                let list = (*tab != Tab::Library).then(|| {
+                    let mut panel = Panel::new(
+                        tab.name().to_string(),
+                        0,
+                        colors.clone(),
+                        n_row - 1,
+                        pod_col + ep_col - 1,
+                        0,
+                        (0, 0, 0, 0),
+                    );
+                    panel.hidden = true;
+                    Menu::new(panel, None, LockVec::new(Vec::new()))
+                });
+                let active_panel = match tab {
+                    Tab::Library => ActivePanel::PodcastMenu,
+                    _ => ActivePanel::EpisodeMenu,
+                };
+                TabView {
+                    tab: *tab,
+                    active_panel: active_panel,
+                    list: list,
+                }
+            })
+            .collect();
+
         let notif_win = NotifWin::new(colors.clone(), n_row - 1, n_row, n_col);
-        let popup_win = PopupWin::new(&config.keybindings, colors.clone(), n_row, n_col);
+        let keymap = Rc::new(config.keybindings.clone());
+        let popup_win = PopupWin::new(keymap.clone(), colors.clone(), n_row, n_col);
 
-        return Ui {
+        return Ok(Ui {
             n_row: n_row,
             n_col: n_col,
-            keymap: &config.keybindings,
+            theme: config.theme.clone(),
+            wrap_details: config.wrap_details,
+            config: Rc::new(config),
+            keymap: keymap,
             colors: colors,
+            cover_art: cover_art,
+            requested_images: HashSet::new(),
+            image_requests: Vec::new(),
+            chapter_requests: Vec::new(),
+            descriptions: HashMap::new(),
+            pending_msgs: Vec::new(),
             podcast_menu: podcast_menu,
             episode_menu: episode_menu,
             details_panel: details_panel,
             active_panel: ActivePanel::PodcastMenu,
             notif_win: notif_win,
             popup_win: popup_win,
-        };
+            filters: Filters::default(),
+            setup: None,
+            tab: Tab::Library,
+            tabs: tabs,
+            queue: Vec::new(),
+            search_term: None,
+            triage: false,
+        });
     }
 
     /// This should be called immediately after creating the UI, in order
     /// to draw everything to the screen.
-    pub fn init(&mut self) {
+    pub fn init(&mut self) -> Result<()> {
         self.podcast_menu.redraw();
         self.episode_menu.redraw();
         self.podcast_menu.activate();
         self.update_details_panel();
+        self.place_tab_bar();
 
         self.notif_win.redraw();
 
@@ -256,7 +508,20 @@ impl<'a> Ui<'a> {
         if self.podcast_menu.items.is_empty() {
             self.popup_win.spawn_welcome_win();
         }
-        io::stdout().flush().unwrap();
+        term::stdout().flush().context("Can't draw to screen")?;
+        return Ok(());
+    }
+
+    /// Builds the UI and draws it once, then tears it down again,
+    /// without waiting for any input. Returns how long it took to build
+    /// the menus and to draw the first screen, for `--bench-startup`.
+    pub fn bench_startup(config: Config, items: LockVec<Podcast>) -> Result<(Duration, Duration)> {
+        let start = Instant::now();
+        let mut ui = Ui::new(config, items)?;
+        let built = start.elapsed();
+        let drawn = ui.init().map(|_| start.elapsed() - built);
+        ui.tear_down();
+        return Ok((built, drawn?));
     }
 
     /// Waits for user input and, where necessary, provides UiMsgs
@@ -268,12 +533,10 @@ impl<'a> Ui<'a> {
     /// new podcast feed spawns a UI window to capture the feed URL, and
     /// only then passes this data back to the main controller.
     pub fn getch(&mut self) -> UiMsg {
-        if event::poll(Duration::from_secs(0)).expect("Can't poll for inputs") {
-            match event::read().expect("Can't read inputs") {
+        if term::poll() {
+            match term::read() {
                 Event::Resize(n_col, n_row) => self.resize(n_col, n_row),
                 Event::Key(input) => {
-                    let (curr_pod_id, curr_ep_id) = self.get_current_ids();
-
                     // get rid of the "welcome" window once the podcast
                     // list is no longer empty
                     if self.popup_win.welcome_win && !self.podcast_menu.items.is_empty() {
@@ -284,157 +547,1765 @@ impl<'a> Ui<'a> {
                     // welcome window which takes no input), then
                     // redirect user input there
                     if self.popup_win.is_non_welcome_popup_active() {
+                        // searching needs the notification bar, so it is
+                        // handled here rather than by the popup window
+                        if self.popup_win.is_transcript_active()
+                            && self.keymap.get_from_input(input) == Some(&UserAction::Search)
+                        {
+                            let term = self.spawn_input_notif("Search: ");
+                            if !self.popup_win.search_transcript(&term) {
+                                self.timed_notif("No matches found.".to_string(), 2000, false);
+                            }
+                            return UiMsg::Noop;
+                        }
+                        if self.popup_win.is_settings_active()
+                            && self.keymap.get_from_input(input) == Some(&UserAction::Select)
+                        {
+                            return self.change_podcast_setting();
+                        }
+                        // the theme preview stays open while switching
+                        // themes or reloading the config, and is
+                        // redrawn with the new colors
+                        if self.popup_win.is_theme_active() {
+                            if let Some(action @ (UserAction::CycleTheme | UserAction::ReloadConfig)) =
+                                self.keymap.get_from_input(input).copied()
+                            {
+                                return self.perform_action(action);
+                            }
+                        }
                         let popup_msg = self.popup_win.handle_input(input);
 
                         // need to check if popup window is still active,
                         // as handling character input above may involve
                         // closing the popup window
                         if !self.popup_win.is_popup_active() {
+                            if let Some((pod_id, ep_id)) = self.popup_win.jump_to.take() {
+                                self.jump_to(pod_id, ep_id);
+                            }
                             self.update_menus();
+                            // the screen is flushed by the UI loop, where
+                            // errors drawing it are handled
                             if self.details_panel.is_some() {
                                 self.update_details_panel();
                             }
-                            io::stdout().flush().unwrap();
                         }
                         return popup_msg;
                     } else {
-                        match self.keymap.get_from_input(input) {
-                            Some(a @ UserAction::Down)
-                            | Some(a @ UserAction::Up)
-                            | Some(a @ UserAction::Left)
-                            | Some(a @ UserAction::Right)
-                            | Some(a @ UserAction::PageUp)
-                            | Some(a @ UserAction::PageDown)
-                            | Some(a @ UserAction::BigUp)
-                            | Some(a @ UserAction::BigDown)
-                            | Some(a @ UserAction::GoTop)
-                            | Some(a @ UserAction::GoBot) => {
-                                self.move_cursor(a, curr_pod_id, curr_ep_id)
-                            }
-
-                            Some(UserAction::AddFeed) => {
-                                let url = &self.spawn_input_notif("Feed URL: ");
-                                if !url.is_empty() {
-                                    return UiMsg::AddFeed(url.to_string());
-                                }
-                            }
+                        // in triage mode, single keys deal with the
+                        // selected episode at once
+                        if self.triage {
+                            return self.triage_input(input);
+                        }
 
-                            Some(UserAction::Sync) => {
-                                if let Some(pod_id) = curr_pod_id {
-                                    return UiMsg::Sync(pod_id);
-                                }
-                            }
-                            Some(UserAction::SyncAll) => {
-                                if curr_pod_id.is_some() {
-                                    return UiMsg::SyncAll;
-                                }
-                            }
+                        // Escape lists all of the items of a menu that was
+                        // narrowed by finding in it
+                        if input.code == event::KeyCode::Esc && self.find_in_menu(true) {
+                            return UiMsg::Noop;
+                        }
 
-                            Some(UserAction::Play) => {
-                                if let Some(pod_id) = curr_pod_id {
-                                    if let Some(ep_id) = curr_ep_id {
-                                        return UiMsg::Play(pod_id, ep_id);
+                        // while searching the details panel, n and N move
+                        // between the matches, and Escape stops searching
+                        if let (ActivePanel::DetailsPanel, Some(det)) =
+                            (&self.active_panel, self.details_panel.as_mut())
+                        {
+                            if det.is_searching() {
+                                match input.code {
+                                    event::KeyCode::Char('n') => {
+                                        det.next_match(true);
+                                        return UiMsg::Noop;
                                     }
-                                }
-                            }
-                            Some(UserAction::MarkPlayed) => {
-                                if let ActivePanel::EpisodeMenu = self.active_panel {
-                                    if let Some(ui_msg) = self.mark_played(curr_pod_id, curr_ep_id)
-                                    {
-                                        return ui_msg;
+                                    event::KeyCode::Char('N') => {
+                                        det.next_match(false);
+                                        return UiMsg::Noop;
                                     }
-                                }
-                            }
-                            Some(UserAction::MarkAllPlayed) => {
-                                if let Some(ui_msg) = self.mark_all_played(curr_pod_id) {
-                                    return ui_msg;
-                                }
-                            }
-
-                            Some(UserAction::Download) => {
-                                if let Some(pod_id) = curr_pod_id {
-                                    if let Some(ep_id) = curr_ep_id {
-                                        return UiMsg::Download(pod_id, ep_id);
+                                    event::KeyCode::Esc => {
+                                        det.clear_search();
+                                        return UiMsg::Noop;
                                     }
+                                    _ => (),
                                 }
                             }
-                            Some(UserAction::DownloadAll) => {
-                                if let Some(pod_id) = curr_pod_id {
-                                    return UiMsg::DownloadAll(pod_id);
-                                }
-                            }
+                        }
 
-                            Some(UserAction::Delete) => {
-                                if let ActivePanel::EpisodeMenu = self.active_panel {
-                                    if let Some(pod_id) = curr_pod_id {
-                                        if let Some(ep_id) = curr_ep_id {
-                                            return UiMsg::Delete(pod_id, ep_id);
-                                        }
-                                    }
-                                }
-                            }
-                            Some(UserAction::DeleteAll) => {
-                                if let Some(pod_id) = curr_pod_id {
-                                    return UiMsg::DeleteAll(pod_id);
-                                }
-                            }
-                            Some(UserAction::UnmarkDownloaded) => {
-                                if let ActivePanel::EpisodeMenu = self.active_panel {
-                                    if let Some(pod_id) = curr_pod_id {
-                                        if let Some(ep_id) = curr_ep_id {
-                                            return UiMsg::UnmarkDownloaded(pod_id, ep_id);
-                                        }
-                                    }
-                                }
-                            }
+                        // in copy mode, the details panel takes all input
+                        if self.details_panel.as_ref().is_some_and(|d| d.is_copy_mode()) {
+                            self.copy_mode_input(input);
+                            return UiMsg::Noop;
+                        }
 
-                            Some(UserAction::Remove) => match self.active_panel {
-                                ActivePanel::PodcastMenu => {
-                                    if let Some(ui_msg) = self.remove_podcast(curr_pod_id) {
-                                        return ui_msg;
-                                    }
+                        // a macro runs each of its actions in turn, and
+                        // stops early if one of them opens a popup window
+                        if let Some(actions) = self.keymap.get_macro_from_input(input).cloned() {
+                            for action in actions.iter() {
+                                if self.popup_win.is_non_welcome_popup_active() {
+                                    break;
                                 }
-                                ActivePanel::EpisodeMenu => {
-                                    if let Some(ui_msg) =
-                                        self.remove_episode(curr_pod_id, curr_ep_id)
-                                    {
-                                        return ui_msg;
-                                    }
-                                }
-                                _ => (),
-                            },
-                            Some(UserAction::RemoveAll) => {
-                                let ui_msg = match self.active_panel {
-                                    ActivePanel::PodcastMenu => self.remove_podcast(curr_pod_id),
-                                    ActivePanel::EpisodeMenu => {
-                                        self.remove_all_episodes(curr_pod_id)
-                                    }
-                                    _ => None,
-                                };
-                                if let Some(ui_msg) = ui_msg {
-                                    return ui_msg;
+                                match self.perform_action(*action) {
+                                    UiMsg::Noop => (),
+                                    msg => self.pending_msgs.push(msg),
                                 }
                             }
+                        } else if let Some(action) = self.keymap.get_from_input(input).copied() {
+                            return self.perform_action(action);
+                        }
+                    }
+                }
+                _ => (),
+            }
+        } // end of poll()
+        return UiMsg::Noop;
+    }
+
+    /// Carries out a single user action on the main panels, returning
+    /// a message for the main controller where one is needed.
+    fn perform_action(&mut self, action: UserAction) -> UiMsg {
+        let (curr_pod_id, curr_ep_id) = self.get_current_ids();
+        let action = match action {
+            UserAction::Select => self.select_action(),
+            action => Some(action),
+        };
+        match action {
+            Some(a @ UserAction::Down)
+            | Some(a @ UserAction::Up)
+            | Some(a @ UserAction::Left)
+            | Some(a @ UserAction::Right)
+            | Some(a @ UserAction::PageUp)
+            | Some(a @ UserAction::PageDown)
+            | Some(a @ UserAction::BigUp)
+            | Some(a @ UserAction::BigDown)
+            | Some(a @ UserAction::GoTop)
+            | Some(a @ UserAction::GoBot) => {
+                self.move_cursor(&a, curr_pod_id, curr_ep_id)
+            }
+
+            Some(UserAction::ToggleGroup) => {
+                if self.tab == Tab::Library && self.podcast_menu.toggle_group() {
+                    self.follow_podcast_cursor(curr_pod_id);
+                }
+            }
+            Some(UserAction::ToggleAllGroups) => {
+                if self.tab == Tab::Library && self.podcast_menu.toggle_all_groups() {
+                    self.follow_podcast_cursor(curr_pod_id);
+                }
+            }
+            Some(UserAction::ToggleLayout) => self.toggle_layout(curr_pod_id, curr_ep_id),
+
+            Some(UserAction::TabLibrary) => self.switch_tab(Tab::Library),
+            Some(UserAction::TabNew) => self.switch_tab(Tab::New),
+            Some(UserAction::TabQueue) => self.switch_tab(Tab::Queue),
+            Some(UserAction::TabDownloads) => self.switch_tab(Tab::Downloads),
+            Some(UserAction::TabSearch) => self.switch_tab(Tab::Search),
+            Some(UserAction::NextTab) => self.switch_tab(self.tab.next()),
+
+            Some(UserAction::AddFeed) => {
+                let url = &self.spawn_input_notif("Feed URL: ");
+                if !url.is_empty() {
+                    return UiMsg::AddFeed(url.to_string());
+                }
+            }
+            Some(UserAction::Import) => {
+                if let Some((_, path)) = self.ask_import_file() {
+                    return UiMsg::ImportFile(path);
+                }
+            }
+
+            Some(UserAction::Sync) => {
+                if let Some(pod_id) = curr_pod_id {
+                    return UiMsg::Sync(pod_id);
+                }
+            }
+            Some(UserAction::SyncAll) => {
+                if curr_pod_id.is_some() {
+                    return UiMsg::SyncAll;
+                }
+            }
+            Some(UserAction::CheckAll) => {
+                if curr_pod_id.is_some() {
+                    return UiMsg::CheckAll;
+                }
+            }
+
+            Some(UserAction::Play) => {
+                if let Some(pod_id) = curr_pod_id {
+                    if let Some(ep_id) = curr_ep_id {
+                        return UiMsg::Play(pod_id, ep_id);
+                    }
+                }
+            }
+            Some(UserAction::MarkPlayed) => {
+                if let ActivePanel::EpisodeMenu = self.active_panel {
+                    if let Some(ui_msg) = self.mark_played_marked(curr_pod_id) {
+                        return ui_msg;
+                    }
+                    if let Some(ui_msg) = self.mark_played(curr_pod_id, curr_ep_id)
+                    {
+                        return ui_msg;
+                    }
+                }
+            }
+            Some(UserAction::ToggleStar) => {
+                if let (ActivePanel::EpisodeMenu, Some(pod_id), Some(ep_id)) =
+                    (&self.active_panel, curr_pod_id, curr_ep_id)
+                {
+                    return UiMsg::ToggleStar(pod_id, ep_id);
+                }
+            }
+            Some(UserAction::Enqueue) => {
+                if let (ActivePanel::EpisodeMenu, Some(pod_id)) = (&self.active_panel, curr_pod_id) {
+                    if let Some(eps) = self.take_marked(pod_id) {
+                        return UiMsg::Triage(TriageAction::Queue, eps);
+                    }
+                    if let Some(ep_id) = curr_ep_id {
+                        return UiMsg::Triage(TriageAction::Queue, vec![(pod_id, ep_id)]);
+                    }
+                }
+            }
+            Some(UserAction::EnqueueNext) => {
+                if let (ActivePanel::EpisodeMenu, Some(pod_id)) = (&self.active_panel, curr_pod_id) {
+                    if let Some(eps) = self.take_marked(pod_id) {
+                        return UiMsg::Triage(TriageAction::QueueNext, eps);
+                    }
+                    if let Some(ep_id) = curr_ep_id {
+                        return UiMsg::Triage(TriageAction::QueueNext, vec![(pod_id, ep_id)]);
+                    }
+                }
+            }
+            Some(UserAction::MarkAllPlayed) => {
+                if let Some(ui_msg) = self.mark_all_played(curr_pod_id) {
+                    return ui_msg;
+                }
+            }
+            Some(UserAction::MarkOlderPlayed) => {
+                if let Some(pod_id) = curr_pod_id {
+                    if let Some(ui_msg) = self.mark_older_played(Some(pod_id)) {
+                        return ui_msg;
+                    }
+                }
+            }
+            Some(UserAction::MarkLibraryOlderPlayed) => {
+                if curr_pod_id.is_some() {
+                    if let Some(ui_msg) = self.mark_older_played(None) {
+                        return ui_msg;
+                    }
+                }
+            }
+
+            Some(UserAction::Download) => {
+                if let Some(pod_id) = curr_pod_id {
+                    if let Some(eps) = self.take_marked(pod_id) {
+                        return UiMsg::DownloadMulti(eps);
+                    }
+                    if let Some(ep_id) = curr_ep_id {
+                        return UiMsg::Download(pod_id, ep_id);
+                    }
+                }
+            }
+            Some(UserAction::DownloadAll) => {
+                if let Some(pod_id) = curr_pod_id {
+                    return UiMsg::DownloadAll(pod_id);
+                }
+            }
+            Some(UserAction::ChooseEnclosure) => {
+                if let (Some(pod_id), Some(ep_id)) = (curr_pod_id, curr_ep_id) {
+                    self.spawn_enclosure_win(pod_id, ep_id);
+                }
+            }
+            Some(UserAction::DownloadLibrary) => return UiMsg::DownloadLibrary,
+            Some(UserAction::Cancel) => return UiMsg::Cancel,
+
+            Some(UserAction::Delete) => {
+                if let ActivePanel::EpisodeMenu = self.active_panel {
+                    if let Some(pod_id) = curr_pod_id {
+                        if let Some(eps) = self.take_marked(pod_id) {
+                            return UiMsg::DeleteMulti(eps);
+                        }
+                        if let Some(ep_id) = curr_ep_id {
+                            return UiMsg::Delete(pod_id, ep_id);
+                        }
+                    }
+                }
+            }
+            Some(UserAction::DeleteAll) => {
+                if let Some(pod_id) = curr_pod_id {
+                    return UiMsg::DeleteAll(pod_id);
+                }
+            }
+            Some(UserAction::UnmarkDownloaded) => {
+                if let ActivePanel::EpisodeMenu = self.active_panel {
+                    if let Some(pod_id) = curr_pod_id {
+                        if let Some(ep_id) = curr_ep_id {
+                            return UiMsg::UnmarkDownloaded(pod_id, ep_id);
+                        }
+                    }
+                }
+            }
+
+            Some(UserAction::Remove) => match self.active_panel {
+                ActivePanel::PodcastMenu => {
+                    if let Some(ui_msg) = self.remove_podcast(curr_pod_id) {
+                        return ui_msg;
+                    }
+                }
+                ActivePanel::EpisodeMenu => {
+                    if let Some(ui_msg) =
+                        self.remove_episode(curr_pod_id, curr_ep_id)
+                    {
+                        return ui_msg;
+                    }
+                }
+                _ => (),
+            },
+            Some(UserAction::RemoveAll) => {
+                let ui_msg = match self.active_panel {
+                    ActivePanel::PodcastMenu => self.remove_podcast(curr_pod_id),
+                    ActivePanel::EpisodeMenu => {
+                        self.remove_all_episodes(curr_pod_id)
+                    }
+                    _ => None,
+                };
+                if let Some(ui_msg) = ui_msg {
+                    return ui_msg;
+                }
+            }
+
+            Some(UserAction::ToggleSelect) => {
+                if let ActivePanel::EpisodeMenu = self.active_panel {
+                    if let Some(list) = self.tabs[self.tab.index()].list.as_mut() {
+                        list.toggle_mark();
+                    } else if self.podcast_menu.tree {
+                        self.podcast_menu.toggle_mark();
+                        self.episode_menu.marked = self.podcast_menu.marked_children.clone();
+                    } else {
+                        self.episode_menu.toggle_mark();
+                    }
+                    self.scroll_current_window(curr_pod_id, Scroll::Down(1));
+                }
+            }
+
+            Some(UserAction::ToggleNotify) => {
+                if let Some(pod_id) = curr_pod_id {
+                    return UiMsg::ToggleNotify(pod_id);
+                }
+            }
+
+            Some(UserAction::PodcastSettings) => {
+                if let Some(pod_id) = curr_pod_id {
+                    self.spawn_settings_win(pod_id);
+                }
+            }
+
+            Some(UserAction::ChangeFeedUrl) => {
+                if let Some(pod_id) = curr_pod_id {
+                    return self.change_feed_url(pod_id);
+                }
+            }
+
+            Some(UserAction::Support) => {
+                if let Some(pod_id) = curr_pod_id {
+                    self.open_funding(pod_id);
+                }
+            }
+
+            Some(UserAction::CycleTheme) => return self.cycle_theme(),
+
+            Some(UserAction::ThemePreview) => {
+                self.clear_cover_art();
+                let theme_name = self.theme.as_deref().unwrap_or("default");
+                self.popup_win.spawn_theme_win(theme_name);
+            }
+
+            Some(UserAction::ReloadConfig) => return UiMsg::ReloadConfig,
+            Some(UserAction::Setup) => {
+                self.spawn_setup_win(crate::play_file::find_players(), false);
+            }
+
+            Some(UserAction::Chapters) => {
+                if let (Some(pod_id), Some(ep_id)) = (curr_pod_id, curr_ep_id) {
+                    self.spawn_chapter_win(pod_id, ep_id);
+                }
+            }
+
+            Some(UserAction::CopyMode) => self.start_copy_mode(),
+            Some(UserAction::Search) => match self.active_panel {
+                ActivePanel::DetailsPanel => self.search_details(),
+                // in the search tab, this starts a new search
+                _ if self.tab == Tab::Search => self.switch_tab(Tab::Search),
+                _ => (),
+            },
+            Some(UserAction::YankAll) => {
+                let text = self.details_panel.as_ref().and_then(|d| d.description());
+                self.copy_text(text);
+            }
+            Some(UserAction::ToggleWrap) => {
+                if let Some(det) = self.details_panel.as_mut() {
+                    self.wrap_details = det.toggle_wrap();
+                    let message = if self.wrap_details {
+                        "Wrapping long lines in the details panel."
+                    } else {
+                        "Cutting off long lines in the details panel."
+                    };
+                    self.timed_notif(message.to_string(), 3000, false);
+                }
+            }
+
+            Some(UserAction::Transcript) => {
+                if let (Some(pod_id), Some(ep_id)) = (curr_pod_id, curr_ep_id) {
+                    return UiMsg::FetchTranscript(pod_id, ep_id);
+                }
+            }
+
+            Some(UserAction::FilterPlayed) => {
+                return UiMsg::FilterChange(FilterType::Played);
+            }
+            Some(UserAction::FilterDownloaded) => {
+                return UiMsg::FilterChange(FilterType::Downloaded);
+            }
+            Some(UserAction::CycleFilter) => {
+                return UiMsg::FilterChange(FilterType::Smart);
+            }
+            Some(UserAction::FilterMenu) => self.spawn_filters_win(),
+            Some(UserAction::Find) => self.spawn_finder_win(),
+            Some(UserAction::FindInMenu) => {
+                self.find_in_menu(false);
+            }
+            Some(UserAction::InProgress) => self.spawn_in_progress_win(),
+            Some(UserAction::Inbox) => self.spawn_inbox_win(),
+            Some(UserAction::TriageMode) => self.set_triage(true),
+            Some(UserAction::Queue) => return UiMsg::ShowQueue,
+            Some(UserAction::Stats) => return UiMsg::ShowStats,
+            Some(UserAction::ShowLog) => return UiMsg::ShowLog,
+            Some(UserAction::Notifications) => {
+                let history = self.notif_win.history();
+                if history.is_empty() {
+                    self.timed_notif("No notifications yet.".to_string(), 5000, false);
+                } else {
+                    self.clear_cover_art();
+                    self.popup_win.spawn_notif_history_win(history);
+                }
+            }
+            Some(UserAction::Suspend) => self.suspend(),
+            Some(UserAction::Shell) => self.run_shell(),
+            Some(UserAction::CycleSort) => {
+                return match self.active_panel {
+                    ActivePanel::PodcastMenu => UiMsg::SortChange(SortTarget::Podcasts),
+                    _ => UiMsg::SortChange(SortTarget::Episodes),
+                };
+            }
+
+            Some(UserAction::Help) => {
+                self.clear_cover_art();
+                self.popup_win.spawn_help_win();
+            }
+
+            Some(UserAction::Quit) => {
+                return UiMsg::Quit;
+            }
+            // already converted by select_action()
+            Some(UserAction::Select)
+            | Some(UserAction::Yank)
+            | None => (),
+            Some(UserAction::ShuffleQueue) => return UiMsg::ShuffleQueue,
+            Some(UserAction::InterleaveQueue) => return UiMsg::InterleaveQueue,
+            Some(UserAction::FillQueue) => {
+                if let Some(ui_msg) = self.fill_queue() {
+                    return ui_msg;
+                }
+            }
+            // only used in the queue window
+            Some(UserAction::MoveUp)
+            | Some(UserAction::MoveDown)
+            | Some(UserAction::MoveToTop)
+            | Some(UserAction::MoveToBottom) => (),
+        }
+        return UiMsg::Noop;
+    }
+
+    /// Starts copy mode in the details panel, where a cursor can be moved
+    /// through the text to select lines to copy to the clipboard.
+    pub fn start_copy_mode(&mut self) {
+        if let Some(det) = self.details_panel.as_mut() {
+            if det.start_copy_mode() {
+                let message = format!(
+                    "Copy mode: {} to select, {} to copy, {} to copy description, {} to leave",
+                    self.first_key(UserAction::ToggleSelect),
+                    self.first_key(UserAction::Yank),
+                    self.first_key(UserAction::YankAll),
+                    self.first_key(UserAction::Quit),
+                );
+                self.timed_notif(message, 5000, false);
+            }
+        }
+    }
+
+    /// Asks for a term to search for in the details panel, and
+    /// highlights its matches. An empty term repeats the last search.
+    fn search_details(&mut self) {
+        if self.details_panel.is_none() {
+            return;
+        }
+        let term = self.spawn_input_notif("Search: ");
+        if self.details_panel.as_mut().is_some_and(|det| det.search(&term)) {
+            self.timed_notif(
+                "n: next match, N: previous match, Esc: stop searching".to_string(),
+                5000,
+                false,
+            );
+        } else {
+            self.timed_notif("No matches found.".to_string(), 2000, false);
+        }
+    }
+
+    /// Handles user input while the details panel is in copy mode.
+    fn copy_mode_input(&mut self, input: event::KeyEvent) {
+        let det = match self.details_panel.as_mut() {
+            Some(det) => det,
+            None => return,
+        };
+        let mut text = None;
+        match self.keymap.get_from_input(input) {
+            Some(UserAction::Down) => det.move_copy_cursor(Scroll::Down(1)),
+            Some(UserAction::Up) => det.move_copy_cursor(Scroll::Up(1)),
+            Some(UserAction::PageDown) => det.move_copy_cursor(Scroll::Down(self.n_row - 3)),
+            Some(UserAction::PageUp) => det.move_copy_cursor(Scroll::Up(self.n_row - 3)),
+            Some(UserAction::BigDown) => {
+                det.move_copy_cursor(Scroll::Down(self.n_row / crate::config::BIG_SCROLL_AMOUNT))
+            }
+            Some(UserAction::BigUp) => {
+                det.move_copy_cursor(Scroll::Up(self.n_row / crate::config::BIG_SCROLL_AMOUNT))
+            }
+            Some(UserAction::GoTop) => det.move_copy_cursor(Scroll::Up(u16::MAX)),
+            Some(UserAction::GoBot) => det.move_copy_cursor(Scroll::Down(u16::MAX)),
+
+            Some(UserAction::ToggleSelect) => det.toggle_copy_anchor(),
+            Some(UserAction::Yank) => {
+                text = det.selected_text();
+                det.stop_copy_mode();
+            }
+            Some(UserAction::YankAll) => {
+                text = det.description();
+                det.stop_copy_mode();
+            }
+            Some(UserAction::Quit) | Some(UserAction::CopyMode) => det.stop_copy_mode(),
+            _ => {
+                if input.code == event::KeyCode::Esc {
+                    det.stop_copy_mode();
+                }
+            }
+        }
+        self.copy_text(text);
+    }
+
+    /// Copies text to the clipboard, and lets the user know whether it
+    /// worked.
+    fn copy_text(&mut self, text: Option<String>) {
+        if let Some(text) = text {
+            match clipboard::copy(&text, self.config.clipboard_command.as_deref()) {
+                Ok(_) => self.timed_notif("Copied to clipboard.".to_string(), 2000, false),
+                Err(err) => self.timed_notif(format!("Could not copy: {err}"), 5000, true),
+            }
+        }
+    }
+
+    /// Opens the link the podcast gives for supporting the show with
+    /// `open_command`, or copies it to the clipboard if that is not set.
+    fn open_funding(&mut self, pod_id: i64) {
+        let url = self
+            .podcast_menu
+            .items
+            .map_single(pod_id, |pod| pod.funding_url.clone())
+            .flatten();
+        let url = match url {
+            Some(url) => url,
+            None => {
+                let message = "This podcast does not list a way to support it.";
+                self.timed_notif(message.to_string(), 3000, false);
+                return;
+            }
+        };
+        match self.config.open_command.as_deref() {
+            Some(command) => match clipboard::open(&url, command) {
+                Ok(_) => self.timed_notif(format!("Opened {url}"), 3000, false),
+                Err(err) => self.timed_notif(format!("Could not open link: {err}"), 5000, true),
+            },
+            None => self.copy_text(Some(url)),
+        }
+    }
+
+    /// Returns the first key bound to an action, for showing in
+    /// notifications.
+    fn first_key(&self, action: UserAction) -> String {
+        return match self.keymap.keys_for_action(action).first() {
+            Some(key) => format!("\"{key}\""),
+            None => "<missing>".to_string(),
+        };
+    }
+
+    /// Switches to the next theme in the themes folder, in alphabetical
+    /// order, after which the default colors are used again. Any colors
+    /// set in config.toml still apply on top of the theme. The new theme
+    /// is saved to config.toml, so it is still used after restarting.
+    pub fn cycle_theme(&mut self) -> UiMsg {
+        let themes = colors::list_themes(&self.config.themes_dir);
+        let next = match &self.theme {
+            Some(current) => themes.iter().position(|t| t == current).map_or_else(
+                || themes.first(),
+                |idx| themes.get(idx + 1),
+            ),
+            None => themes.first(),
+        };
+        if !self.apply_theme(next.cloned()) {
+            return UiMsg::Noop;
+        }
+        let value = self
+            .theme
+            .as_ref()
+            .map(|theme| toml::Value::String(theme.clone()).to_string());
+        return UiMsg::SaveSettings(vec![("theme".to_string(), value)]);
+    }
+
+    /// Switches to the colors of a theme (or the default colors, for
+    /// None), with any colors set in config.toml on top. Returns whether
+    /// the theme could be loaded.
+    fn apply_theme(&mut self, theme: Option<String>) -> bool {
+        match AppColors::from_theme(
+            &self.config.themes_dir,
+            theme.as_deref(),
+            self.config.color_overrides.clone(),
+        ) {
+            Ok(colors) => {
+                self.theme = theme;
+                self.set_colors(Rc::new(colors));
+                let name = self.theme.as_deref().unwrap_or("default");
+                self.timed_notif(format!("Theme: {name}"), 3000, false);
+                return true;
+            }
+            Err(err) => self.timed_notif(format!("{err}"), 5000, true),
+        }
+        return false;
+    }
+
+    /// Swaps in a newly loaded configuration, applying the keybindings,
+    /// colors, and other display settings without restarting.
+    pub fn reload_config(&mut self, config: Config) {
+        self.keymap = Rc::new(config.keybindings.clone());
+        self.popup_win.set_keymap(self.keymap.clone());
+        self.cover_art = match config.image_cache {
+            Some(_) => config.cover_art.protocol(),
+            None => None,
+        };
+        self.theme = config.theme.clone();
+        self.wrap_details = config.wrap_details;
+        self.episode_menu.glyphs = config.glyphs.clone();
+        self.podcast_menu.glyphs = config.glyphs.clone();
+        self.podcast_menu.groups = config.groups.clone();
+        if config.tree_layout != self.config.tree_layout {
+            let (curr_pod_id, curr_ep_id) = self.get_current_ids();
+            self.toggle_layout(curr_pod_id, curr_ep_id);
+        }
+        let colors = Rc::new(config.colors.clone());
+        self.config = Rc::new(config);
+
+        // redraws the screen, including the details panel
+        self.set_colors(colors);
+        self.timed_notif("Configuration reloaded.".to_string(), 3000, false);
+    }
+
+    /// Replaces the colors used by all panels, and redraws the whole
+    /// screen with the new colors.
+    pub fn set_colors(&mut self, colors: Rc<AppColors>) {
+        self.colors = colors;
+        self.podcast_menu.panel.colors = self.colors.clone();
+        self.episode_menu.panel.colors = self.colors.clone();
+        self.notif_win.set_colors(self.colors.clone());
+        let theme_name = self.theme.as_deref().unwrap_or("default");
+        self.popup_win.set_colors(self.colors.clone(), theme_name);
+
+        // the details panel is recreated with the new colors when
+        // resizing
+        self.details_panel = None;
+        self.resize(self.n_col, self.n_row);
+    }
+
+    /// Converts the "select" key into the action the user has chosen for
+    /// the currently active menu. On the header of a group of podcasts,
+    /// it collapses or expands the group.
+    fn select_action(&self) -> Option<UserAction> {
+        if matches!(self.active_panel, ActivePanel::PodcastMenu)
+            && matches!(self.podcast_menu.selected_row(), Some(MenuRow::Group(..)))
+        {
+            return Some(UserAction::ToggleGroup);
+        }
+        let (enter, mark_played) = match self.active_panel {
+            ActivePanel::PodcastMenu => (self.config.enter_podcast, UserAction::MarkAllPlayed),
+            ActivePanel::EpisodeMenu => (self.config.enter_episode, UserAction::MarkPlayed),
+            ActivePanel::DetailsPanel => return None,
+        };
+        return Some(match enter {
+            EnterAction::Play => UserAction::Play,
+            EnterAction::Open => UserAction::Right,
+            EnterAction::MarkPlayed => mark_played,
+        });
+    }
+
+    /// Opens a popup window listing the chapters of an episode, or
+    /// explains why there are none.
+    fn spawn_chapter_win(&mut self, pod_id: i64, ep_id: i64) {
+        let episode = self.podcast_menu.items.clone_episode(pod_id, ep_id);
+        if let Some(ep) = episode {
+            if !ep.chapters.is_empty() {
+                self.clear_cover_art();
+                self.popup_win.spawn_chapter_win(ep_id, ep.chapters);
+            } else if ep.chapters_url.is_some() || ep.path.is_some() {
+                self.chapter_requests.push((pod_id, ep_id));
+                self.timed_notif("Looking for chapters...".to_string(), 3000, false);
+            } else {
+                self.timed_notif("This episode has no chapters.".to_string(), 3000, false);
+            }
+        }
+    }
+
+    /// Opens a popup window listing the settings of a podcast.
+    fn spawn_settings_win(&mut self, pod_id: i64) {
+        if let Some(podcast) = self.podcast_menu.items.clone_podcast(pod_id) {
+            let (settings, header) = Self::podcast_settings(&podcast);
+            self.clear_cover_art();
+            self.popup_win
+                .spawn_settings_win("Podcast settings", pod_id, settings, header);
+        }
+    }
+
+    /// Opens the setup window, where the user picks a few settings to
+    /// start with on the `first_run`, or changes them later on.
+    /// `players` are the play commands of the players that were found.
+    pub fn spawn_setup_win(&mut self, players: Vec<String>, first_run: bool) {
+        let setup = Setup::new(&self.config, players, first_run);
+        let (settings, header) = setup.settings();
+        self.setup = Some(setup);
+        self.clear_cover_art();
+        self.popup_win.spawn_settings_win("Setup", 0, settings, header);
+    }
+
+    /// Asks the user for an OPML or library file to import. Returns the
+    /// path as typed in, along with the expanded one, if the file exists.
+    fn ask_import_file(&mut self) -> Option<(String, PathBuf)> {
+        let input = self
+            .notif_win
+            .input_notif_or_cancel("OPML or library file to import: ")?;
+        let input = input.trim();
+        if input.is_empty() {
+            return None;
+        }
+        return match crate::config::expand_path(Some(input)) {
+            Ok(Some(path)) if path.is_file() => Some((input.to_string(), path)),
+            Ok(_) => {
+                self.timed_notif(format!("Could not find the file {input}."), 5000, true);
+                None
+            }
+            Err(err) => {
+                self.timed_notif(format!("{err:#}"), 5000, true);
+                None
+            }
+        };
+    }
+
+    /// Changes the choice on one line of the setup window. Finishing
+    /// closes the window and has the choices saved to config.toml.
+    fn change_setup(&mut self, step: SetupStep) -> UiMsg {
+        let mut setup = match self.setup.take() {
+            Some(setup) => setup,
+            None => return UiMsg::Noop,
+        };
+        let mut msg = UiMsg::Noop;
+        match step {
+            SetupStep::DownloadDir => {
+                if let Some(input) =
+                    self.notif_win.input_notif_or_cancel("Download folder (blank for default): ")
+                {
+                    setup.set_download_path(&input);
+                }
+            }
+            SetupStep::Player => {
+                // the players that were found are picked in turn, and
+                // after the last one, any command can be typed in
+                if !setup.next_player() {
+                    let prompt = "Play command, with %s for the file (e.g., mpv %s): ";
+                    let input = self.notif_win.input_notif_or_cancel(prompt);
+                    setup.set_play_command(input.as_deref().unwrap_or(""));
+                }
+            }
+            SetupStep::Import => {
+                if let Some((input, path)) = self.ask_import_file() {
+                    setup.imported = Some(input);
+                    msg = UiMsg::ImportFile(path);
+                }
+            }
+            SetupStep::Theme => {
+                // themes that come with shellcaster are copied to the
+                // themes folder, so they can be loaded like any other
+                let theme = setup.next_theme();
+                let installed = match &theme {
+                    Some(name) => colors::install_theme(&self.config.themes_dir, name),
+                    None => Ok(()),
+                };
+                match installed {
+                    Ok(()) => {
+                        self.apply_theme(theme);
+                    }
+                    Err(err) => self.timed_notif(format!("{err:#}"), 5000, true),
+                }
+            }
+            SetupStep::Finish => {
+                self.popup_win.turn_off_settings_win();
+                return UiMsg::FinishSetup(setup.config_settings());
+            }
+        }
+        let (settings, header) = setup.settings();
+        self.setup = Some(setup);
+        self.popup_win.update_settings_win(settings, header);
+        return msg;
+    }
+
+    /// Opens a popup window asking which episodes of a newly added
+    /// podcast should start off unplayed.
+    fn spawn_new_podcast_win(&mut self, pod_id: i64) {
+        let (title, num_eps) = match self
+            .podcast_menu
+            .items
+            .map_single(pod_id, |pod| (pod.display_title().to_string(), pod.episodes.len(false)))
+        {
+            Some(info) => info,
+            None => return,
+        };
+        let options = [
+            (NewPodcastEpisodes::LatestUnplayed, "Only the latest episode"),
+            (NewPodcastEpisodes::AllUnplayed, "All episodes"),
+            (NewPodcastEpisodes::AllPlayed, "None (mark all as played)"),
+        ];
+        let settings = options
+            .iter()
+            .enumerate()
+            .map(|(i, (choice, label))| PodcastSetting {
+                id: i as i64,
+                kind: SettingKind::NewPodcast(*choice),
+                label: label.to_string(),
+                value: String::new(),
+            })
+            .collect();
+        let header = format!(
+            "Added {title} with {num_eps} episodes. Which episodes should be marked as unplayed?"
+        );
+        self.clear_cover_art();
+        self.popup_win
+            .spawn_settings_win("New podcast", pod_id, settings, header);
+    }
+
+    /// Opens a popup window listing the versions of an episode that its
+    /// feed offers, e.g., in different formats or sizes. Choosing one
+    /// downloads it.
+    fn spawn_enclosure_win(&mut self, pod_id: i64, ep_id: i64) {
+        let info = self.podcast_menu.items.map_single(pod_id, |pod| {
+            let preference = pod
+                .overrides
+                .preferred_enclosure
+                .clone()
+                .or_else(|| self.config.preferred_enclosure.clone());
+            pod.episodes.map_single(ep_id, |ep| {
+                let chosen = ep.download_url(preference.as_deref());
+                (ep.display_title().to_string(), ep.enclosures.clone(), chosen)
+            })
+        });
+        let (title, enclosures, chosen) = match info.flatten() {
+            Some(info) => info,
+            None => return,
+        };
+        if enclosures.is_empty() {
+            let message = "The feed only offers one version of this episode.";
+            self.timed_notif(message.to_string(), 3000, false);
+            return;
+        }
+        let settings = enclosures
+            .iter()
+            .enumerate()
+            .map(|(i, enc)| PodcastSetting {
+                id: i as i64,
+                kind: SettingKind::Enclosure(ep_id, i),
+                label: match i {
+                    0 => "Main version".to_string(),
+                    _ => format!("Version {}", i + 1),
+                },
+                value: if enc.url == chosen {
+                    format!("{} (preferred)", enc.describe())
+                } else {
+                    enc.describe()
+                },
+            })
+            .collect();
+        let header = format!("Choose which version of {title} to download.");
+        self.clear_cover_art();
+        self.popup_win.spawn_settings_win("Versions", pod_id, settings, header);
+    }
+
+    /// Opens a popup window listing the filters, where each can be
+    /// changed in turn.
+    fn spawn_filters_win(&mut self) {
+        let (settings, header) = self.filter_settings();
+        self.clear_cover_art();
+        // the filters are not tied to any one podcast
+        self.popup_win.spawn_settings_win("Filters", 0, settings, header);
+    }
+
+    /// Opens the fuzzy finder, for jumping to any of the podcasts or
+    /// episodes currently shown in the menus.
+    fn spawn_finder_win(&mut self) {
+        let mut items = Vec::new();
+        {
+            let podcasts = self.podcast_menu.items.borrow_map();
+            for pod_id in self.podcast_menu.items.borrow_filtered_order().iter() {
+                let pod = match podcasts.get(pod_id) {
+                    Some(pod) => pod,
+                    None => continue,
+                };
+                let pod_title = pod.display_title().to_string();
+                items.push(FinderItem {
+                    id: items.len() as i64,
+                    pod_id: pod.id,
+                    ep_id: None,
+                    title: pod_title.clone(),
+                    pod_title: pod_title.clone(),
+                });
+                let episodes = pod.episodes.borrow_map();
+                for ep_id in pod.episodes.borrow_filtered_order().iter() {
+                    if let Some(ep) = episodes.get(ep_id) {
+                        items.push(FinderItem {
+                            id: items.len() as i64,
+                            pod_id: pod.id,
+                            ep_id: Some(ep.id),
+                            title: ep.display_title().to_string(),
+                            pod_title: pod_title.clone(),
+                        });
+                    }
+                }
+            }
+        }
+        if items.is_empty() {
+            self.timed_notif("There is nothing to find yet.".to_string(), 5000, false);
+            return;
+        }
+        self.clear_cover_art();
+        self.popup_win.spawn_finder_win(items);
+    }
+
+    /// Opens a popup window listing the new episodes found by syncing,
+    /// across all podcasts, with the most recently published first.
+    fn spawn_inbox_win(&mut self) {
+        let episodes = tabs::list_episodes(Tab::New, &self.podcast_menu.items, &[], None);
+        if episodes.is_empty() {
+            self.timed_notif("The inbox is empty.".to_string(), 5000, false);
+            return;
+        }
+        self.clear_cover_art();
+        self.popup_win.spawn_episode_list_win(EpisodeList::Inbox, episodes);
+    }
+
+    /// Opens a popup window listing the episodes that were left partway
+    /// through, across all podcasts, with the most recently played
+    /// first. Choosing one plays it from where it was left.
+    fn spawn_in_progress_win(&mut self) {
+        let mut episodes = Vec::new();
+        for pod in self.podcast_menu.items.borrow_map().values() {
+            for ep in pod.episodes.borrow_map().values() {
+                if ep.is_in_progress() {
+                    episodes.push((
+                        ep.last_played.unwrap_or(0),
+                        pod.id,
+                        ep.id,
+                        format!("{} ({})", ep.display_title(), pod.display_title()),
+                        ep.format_position(),
+                    ));
+                }
+            }
+        }
+        if episodes.is_empty() {
+            let message = if self.config.mpv_socket.is_some() {
+                "No episodes are in progress."
+            } else {
+                "Set mpv_socket in the config file to keep track of episodes in progress."
+            };
+            self.timed_notif(message.to_string(), 5000, false);
+            return;
+        }
+        episodes.sort_by_key(|ep| std::cmp::Reverse(ep.0));
+
+        let settings = episodes
+            .into_iter()
+            .enumerate()
+            .map(|(i, (last_played, pod_id, ep_id, label, position))| {
+                let last_played = Utc
+                    .timestamp_opt(last_played, 0)
+                    .single()
+                    .map(|date| format!(", last played {}", date.format("%F")))
+                    .unwrap_or_default();
+                PodcastSetting {
+                    id: i as i64,
+                    kind: SettingKind::Resume(pod_id, ep_id),
+                    label: label,
+                    value: format!("{position}{last_played}"),
+                }
+            })
+            .collect::<Vec<_>>();
+        let header = format!(
+            "{} episode{} in progress, most recently played first.",
+            settings.len(),
+            if settings.len() == 1 { "" } else { "s" }
+        );
+        self.clear_cover_art();
+        // the list is not tied to any one podcast
+        self.popup_win.spawn_settings_win("In progress", 0, settings, header);
+    }
+
+    /// Lists the filters and their current settings for the filters
+    /// window, along with a summary of the active ones.
+    fn filter_settings(&self) -> (Vec<PodcastSetting>, String) {
+        let smart_filters = &self.config.smart_filters;
+        let lines = [
+            (FilterType::Played, "Played"),
+            (FilterType::Downloaded, "Downloaded"),
+            (FilterType::Starred, "Starred"),
+            (FilterType::Age, "Published"),
+            (FilterType::Duration, "Duration"),
+            (FilterType::Smart, "Saved filter"),
+            (FilterType::Clear, "Clear all filters"),
+        ];
+        let settings = lines
+            .iter()
+            .enumerate()
+            .map(|(i, (filter_type, label))| PodcastSetting {
+                id: i as i64,
+                kind: SettingKind::Filter(*filter_type),
+                label: label.to_string(),
+                value: if *filter_type == FilterType::Clear {
+                    String::new()
+                } else {
+                    self.filters.describe(*filter_type, smart_filters)
+                },
+            })
+            .collect();
+        let header = match self.filters.summary(smart_filters) {
+            Some(summary) => format!("Active filters: {summary}."),
+            None => "No filters are active.".to_string(),
+        };
+        return (settings, header);
+    }
+
+    /// Keeps track of the filters that are active, showing them in the
+    /// title of the episode panel and in the filters window, if it is
+    /// open.
+    pub fn update_filters(&mut self, filters: Filters) {
+        self.filters = filters;
+        self.set_panel_titles();
+        self.update_menus();
+        if let Some((_, SettingKind::Filter(_))) = self.popup_win.selected_setting() {
+            let (settings, header) = self.filter_settings();
+            self.popup_win.update_settings_win(settings, header);
+        }
+    }
+
+    /// Shows the active filters in the title of the episode panel, or
+    /// in that of the podcast panel in the tree layout, where the
+    /// episodes are listed in it.
+    fn set_panel_titles(&mut self) {
+        let summary = self.filters.summary(&self.config.smart_filters);
+        let title = |name: &str| match &summary {
+            Some(summary) => format!("{name} ({summary})"),
+            None => name.to_string(),
+        };
+        let podcasts = if self.podcast_menu.tree {
+            title("Podcasts")
+        } else {
+            "Podcasts".to_string()
+        };
+        // in the tree layout, episodes are triaged in the podcast menu
+        let podcasts = Self::menu_title(
+            podcasts,
+            self.podcast_menu.find_term.as_deref(),
+            self.triage && self.podcast_menu.tree,
+        );
+        self.podcast_menu.panel.set_title(podcasts);
+        let episodes = Self::menu_title(
+            title("Episodes"),
+            self.episode_menu.find_term.as_deref(),
+            self.triage,
+        );
+        self.episode_menu.panel.set_title(episodes);
+    }
+
+    /// Narrows the active menu to the items that contain what the user
+    /// types, as they type it; Escape lists all of the items again. If
+    /// `clear` is set, the menu is listed in full again right away
+    /// instead. Returns false if there is no menu to find things in, or
+    /// (with `clear`) if it was not narrowed.
+    fn find_in_menu(&mut self, clear: bool) -> bool {
+        let old_pod_id = self.podcast_menu.selected_id();
+        let notif_win = &self.notif_win;
+        let found = match (&mut self.tabs[self.tab.index()].list, &self.active_panel) {
+            (_, ActivePanel::DetailsPanel) => false,
+            (Some(list), _) => Self::narrow_menu(notif_win, list, clear),
+            (None, ActivePanel::EpisodeMenu) if !self.podcast_menu.tree => {
+                Self::narrow_menu(notif_win, &mut self.episode_menu, clear)
+            }
+            (None, _) => Self::narrow_menu(notif_win, &mut self.podcast_menu, clear),
+        };
+        if !found {
+            return false;
+        }
+
+        if self.tab == Tab::Library {
+            self.set_panel_titles();
+            self.follow_podcast_cursor(old_pod_id);
+        } else {
+            self.refresh_list();
+        }
+        self.place_tab_bar();
+        self.update_details_panel();
+        return true;
+    }
+
+    /// Narrows `menu` as the user types what to find in it (see
+    /// `find_in_menu()`).
+    fn narrow_menu<T: Clone + Menuable>(
+        notif_win: &NotifWin,
+        menu: &mut Menu<T>,
+        clear: bool,
+    ) -> bool {
+        if clear {
+            if menu.find_term.is_none() {
+                return false;
+            }
+            menu.set_find_term(None);
+            return true;
+        }
+        // if the user cancels, the cursor goes back to where it was, as
+        // it is lost when nothing is found
+        let row = menu.selected_row();
+        let input = notif_win.live_input_notif("Find: ", &mut |term| {
+            menu.set_find_term(Some(term.to_string()).filter(|term| !term.is_empty()));
+        });
+        if input.is_none() {
+            menu.set_find_term(None);
+            if let Some(row) = row {
+                menu.reselect(row);
+            }
+        }
+        return true;
+    }
+
+    /// Adds the term that a menu has been narrowed to by finding in it,
+    /// if any, to the title of the menu, and whether the menu is in
+    /// triage mode.
+    fn menu_title(title: String, find_term: Option<&str>, triage: bool) -> String {
+        let title = match find_term {
+            Some(term) => format!("{title} [find: {term}]"),
+            None => title,
+        };
+        return match triage {
+            true => format!("{title} [triage]"),
+            false => title,
+        };
+    }
+
+    /// Starts or stops triage mode, in which single keys deal with the
+    /// selected episode at once (see `triage_input()`). The cursor moves
+    /// to the episodes if it is not on them already.
+    fn set_triage(&mut self, on: bool) {
+        if on {
+            match self.active_panel {
+                ActivePanel::PodcastMenu
+                    if self.tab == Tab::Library && !self.podcast_menu.tree =>
+                {
+                    self.perform_action(UserAction::Right);
+                }
+                ActivePanel::DetailsPanel => {
+                    self.perform_action(UserAction::Left);
+                }
+                _ => (),
+            }
+            let message = "Triage mode: j/k to move, d to download, p to mark as played, \
+                           x to hide, q to queue, Esc to stop.";
+            self.timed_notif(message.to_string(), 5000, false);
+        }
+        self.triage = on;
+        if self.tab == Tab::Library {
+            self.set_panel_titles();
+        } else {
+            self.refresh_list();
+        }
+        self.place_tab_bar();
+    }
+
+    /// Handles user input in triage mode: j and k (or the usual keys)
+    /// move the cursor, and d, p, x, and q download, mark as played,
+    /// hide, or queue the selected episode without asking, moving on to
+    /// the next one. Escape stops triage mode; other keys are ignored,
+    /// so a slip of the finger does nothing unexpected.
+    fn triage_input(&mut self, input: event::KeyEvent) -> UiMsg {
+        let plain = !input.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+        let triage = match input.code {
+            event::KeyCode::Char('j') if plain => return self.perform_action(UserAction::Down),
+            event::KeyCode::Char('k') if plain => return self.perform_action(UserAction::Up),
+            event::KeyCode::Char('d') if plain => TriageAction::Download,
+            event::KeyCode::Char('p') if plain => TriageAction::Played,
+            event::KeyCode::Char('x') if plain => TriageAction::Hide,
+            event::KeyCode::Char('q') if plain => TriageAction::Queue,
+            event::KeyCode::Esc => {
+                self.set_triage(false);
+                return UiMsg::Noop;
+            }
+            _ => {
+                match self.keymap.get_from_input(input).copied() {
+                    Some(
+                        action @ (UserAction::Down
+                        | UserAction::Up
+                        | UserAction::BigDown
+                        | UserAction::BigUp
+                        | UserAction::PageDown
+                        | UserAction::PageUp
+                        | UserAction::GoTop
+                        | UserAction::GoBot),
+                    ) => return self.perform_action(action),
+                    Some(UserAction::TriageMode) => self.set_triage(false),
+                    _ => (),
+                }
+                return UiMsg::Noop;
+            }
+        };
+
+        let (curr_pod_id, curr_ep_id) = self.get_current_ids();
+        if let (ActivePanel::EpisodeMenu, Some(pod_id), Some(ep_id)) =
+            (&self.active_panel, curr_pod_id, curr_ep_id)
+        {
+            // the cursor moves on to the next episode right away, and
+            // stays on it if this one drops out of the list
+            self.perform_action(UserAction::Down);
+            return UiMsg::Triage(triage, vec![(pod_id, ep_id)]);
+        }
+        return UiMsg::Noop;
+    }
+
+    /// Keeps track of the episodes in the play queue, which are marked
+    /// in the episode menu (and, in the tree layout, the podcast menu).
+    /// The menus are redrawn anyway when a popup window closes.
+    pub fn update_queue(&mut self, episodes: Vec<i64>) {
+        self.episode_menu.queued = episodes.iter().copied().collect();
+        self.podcast_menu.queued = self.episode_menu.queued.clone();
+        self.queue = episodes;
+        if !self.popup_win.is_non_welcome_popup_active() {
+            self.update_menus();
+        }
+    }
+
+    /// Lists the settings of a podcast for the settings window, along
+    /// with a summary of how many of its episodes pass the download
+    /// rules.
+    fn podcast_settings(podcast: &Podcast) -> (Vec<PodcastSetting>, String) {
+        let pattern = |p: &Option<String>| match p {
+            Some(p) => p.clone(),
+            None => "(not set)".to_string(),
+        };
+        let settings = vec![
+            PodcastSetting {
+                id: 0,
+                kind: SettingKind::IncludePattern,
+                label: "Auto-download only titles matching".to_string(),
+                value: pattern(&podcast.include_pattern),
+            },
+            PodcastSetting {
+                id: 1,
+                kind: SettingKind::ExcludePattern,
+                label: "Never auto-download titles matching".to_string(),
+                value: pattern(&podcast.exclude_pattern),
+            },
+            PodcastSetting {
+                id: 2,
+                kind: SettingKind::TestRules,
+                label: "Test the download rules on a title".to_string(),
+                value: String::new(),
+            },
+            PodcastSetting {
+                id: 3,
+                kind: SettingKind::AutoDownload,
+                label: "Auto-download at most".to_string(),
+                value: match podcast.overrides.auto_download {
+                    Some(num) => format!("{num} new episodes per sync"),
+                    None => "(no limit)".to_string(),
+                },
+            },
+            PodcastSetting {
+                id: 4,
+                kind: SettingKind::KeepDownloads,
+                label: "Keep downloads of".to_string(),
+                value: match podcast.overrides.keep_downloads {
+                    Some(num) => format!("the latest {num} episodes"),
+                    None => "(all episodes)".to_string(),
+                },
+            },
+            PodcastSetting {
+                id: 5,
+                kind: SettingKind::DownloadDir,
+                label: "Download directory".to_string(),
+                value: podcast
+                    .overrides
+                    .download_dir
+                    .clone()
+                    .unwrap_or_else(|| "(default)".to_string()),
+            },
+            PodcastSetting {
+                id: 6,
+                kind: SettingKind::PlayCommand,
+                label: "Play command".to_string(),
+                value: podcast
+                    .overrides
+                    .play_command
+                    .clone()
+                    .unwrap_or_else(|| "(default)".to_string()),
+            },
+            PodcastSetting {
+                id: 7,
+                kind: SettingKind::Login,
+                label: "Login".to_string(),
+                value: podcast
+                    .auth
+                    .user
+                    .clone()
+                    .unwrap_or_else(|| "(not set)".to_string()),
+            },
+            PodcastSetting {
+                id: 8,
+                kind: SettingKind::Headers,
+                label: "Extra HTTP headers".to_string(),
+                value: match podcast.auth.headers.len() {
+                    0 => "(not set)".to_string(),
+                    1 => "1 header".to_string(),
+                    num => format!("{num} headers"),
+                },
+            },
+            PodcastSetting {
+                id: 9,
+                kind: SettingKind::Archived,
+                label: "Archived (not synced with the others)".to_string(),
+                value: if podcast.archived { "yes" } else { "no" }.to_string(),
+            },
+            PodcastSetting {
+                id: 10,
+                kind: SettingKind::CustomTitle,
+                label: "Title".to_string(),
+                value: podcast
+                    .custom_title
+                    .clone()
+                    .unwrap_or_else(|| "(from the feed)".to_string()),
+            },
+            PodcastSetting {
+                id: 11,
+                kind: SettingKind::Group,
+                label: "Group".to_string(),
+                value: podcast.group.clone().unwrap_or_else(|| "(none)".to_string()),
+            },
+            PodcastSetting {
+                id: 12,
+                kind: SettingKind::TitleRule,
+                label: "Clean up episode titles".to_string(),
+                value: match &podcast.title_pattern {
+                    Some(pattern) => format!(
+                        "{pattern} -> \"{}\"",
+                        podcast.title_replacement.as_deref().unwrap_or("")
+                    ),
+                    None => "(not set)".to_string(),
+                },
+            },
+            PodcastSetting {
+                id: 13,
+                kind: SettingKind::ShowPattern,
+                label: "Show only episodes with titles matching".to_string(),
+                value: pattern(&podcast.show_pattern),
+            },
+            PodcastSetting {
+                id: 14,
+                kind: SettingKind::HidePattern,
+                label: "Hide episodes with titles matching".to_string(),
+                value: pattern(&podcast.hide_pattern),
+            },
+            PodcastSetting {
+                id: 15,
+                kind: SettingKind::PreferredEnclosure,
+                label: "Preferred version to download".to_string(),
+                value: podcast
+                    .overrides
+                    .preferred_enclosure
+                    .clone()
+                    .unwrap_or_else(|| "(default)".to_string()),
+            },
+            PodcastSetting {
+                id: 16,
+                kind: SettingKind::InvalidCerts,
+                label: "Accept invalid TLS certificates".to_string(),
+                value: if podcast.auth.accept_invalid_certs {
+                    "YES (insecure)"
+                } else {
+                    "no"
+                }
+                .to_string(),
+            },
+        ];
+
+        let rules = podcast.download_rules();
+        let allowed = podcast
+            .episodes
+            .map(|ep| rules.allows(&ep.title) as usize, false)
+            .iter()
+            .sum::<usize>();
+        let header = format!(
+            "{}: {allowed} of {} episodes pass the download rules.",
+            podcast.display_title(),
+            podcast.episodes.len(false)
+        );
+        return (settings, header);
+    }
+
+    /// Changes the setting selected in the podcast settings window,
+    /// asking the user for the new value.
+    fn change_podcast_setting(&mut self) -> UiMsg {
+        let (pod_id, kind) = match self.popup_win.selected_setting() {
+            Some(selected) => selected,
+            None => return UiMsg::Noop,
+        };
+        if let SettingKind::NewPodcast(choice) = kind {
+            self.popup_win.turn_off_settings_win();
+            return UiMsg::MarkNewPodcast(pod_id, choice);
+        }
+        if let SettingKind::Filter(filter_type) = kind {
+            return UiMsg::FilterChange(filter_type);
+        }
+        if let SettingKind::Resume(pod_id, ep_id) = kind {
+            self.popup_win.turn_off_settings_win();
+            return UiMsg::Play(pod_id, ep_id);
+        }
+        if let SettingKind::Enclosure(ep_id, index) = kind {
+            self.popup_win.turn_off_settings_win();
+            return UiMsg::DownloadEnclosure(pod_id, ep_id, index);
+        }
+        if let SettingKind::Setup(step) = kind {
+            return self.change_setup(step);
+        }
+        let mut podcast = match self.podcast_menu.items.clone_podcast(pod_id) {
+            Some(podcast) => podcast,
+            None => return UiMsg::Noop,
+        };
+
+        // archiving is switched on and off without asking for anything
+        if kind == SettingKind::Archived {
+            podcast.archived = !podcast.archived;
+            let (settings, header) = Self::podcast_settings(&podcast);
+            self.popup_win.update_settings_win(settings, header);
+            return UiMsg::SetArchived(pod_id, podcast.archived);
+        }
+
+        // certificate checks are easy to turn back on, but turning them
+        // off has to be confirmed
+        if kind == SettingKind::InvalidCerts {
+            if !podcast.auth.accept_invalid_certs
+                && !self.ask_for_confirmation(
+                    "Stop checking TLS certificates for this podcast? Anyone on the network could pose as its server.",
+                )
+            {
+                return UiMsg::Noop;
+            }
+            podcast.auth.accept_invalid_certs = !podcast.auth.accept_invalid_certs;
+            let (settings, header) = Self::podcast_settings(&podcast);
+            self.popup_win.update_settings_win(settings, header);
+            return UiMsg::SetAuth(pod_id, podcast.auth.clone());
+        }
+
+        let prompt = match kind {
+            SettingKind::IncludePattern => "Only titles matching (blank for all): ",
+            SettingKind::ExcludePattern => "Skip titles matching (blank for none): ",
+            SettingKind::TestRules => "Episode title: ",
+            SettingKind::AutoDownload => "Auto-download at most (blank for no limit): ",
+            SettingKind::KeepDownloads => "Keep downloads of latest (blank for all): ",
+            SettingKind::DownloadDir => "Download directory (blank for default): ",
+            SettingKind::PlayCommand => "Play command (blank for default): ",
+            SettingKind::Login => "User name (blank for none): ",
+            SettingKind::Headers => "Headers, as Name: value separated by | (blank for none): ",
+            SettingKind::CustomTitle => "Title (blank for the feed's title): ",
+            SettingKind::Group => "Group (blank for none): ",
+            SettingKind::TitleRule => "Remove or replace in episode titles (blank for none): ",
+            SettingKind::ShowPattern => "Show only titles matching (blank for all): ",
+            SettingKind::HidePattern => "Hide titles matching (blank for none): ",
+            SettingKind::PreferredEnclosure => {
+                "Preferred version, e.g., opus or smallest (blank for default): "
+            }
+            SettingKind::Archived
+            | SettingKind::InvalidCerts
+            | SettingKind::NewPodcast(_)
+            | SettingKind::Filter(_)
+            | SettingKind::Resume(..)
+            | SettingKind::Enclosure(..)
+            | SettingKind::Setup(_) => return UiMsg::Noop,
+        };
+        let input = match self.notif_win.input_notif_or_cancel(prompt) {
+            Some(input) => input,
+            None => return UiMsg::Noop,
+        };
+
+        if kind == SettingKind::TestRules {
+            if !input.is_empty() {
+                let message = if podcast.download_rules().allows(&input) {
+                    format!("\"{input}\" would be downloaded.")
+                } else {
+                    format!("\"{input}\" would not be downloaded.")
+                };
+                self.timed_notif(message, 5000, false);
+            }
+            return UiMsg::Noop;
+        }
+
+        let value = if input.trim().is_empty() {
+            None
+        } else {
+            Some(input.trim().to_string())
+        };
+        let msg = match kind {
+            SettingKind::IncludePattern | SettingKind::ExcludePattern => {
+                if let Some(Err(err)) = value.as_deref().map(download_rules::validate) {
+                    self.timed_notif(format!("{err:#}"), 5000, true);
+                    return UiMsg::Noop;
+                }
+                if kind == SettingKind::IncludePattern {
+                    podcast.include_pattern = value;
+                } else {
+                    podcast.exclude_pattern = value;
+                }
+                UiMsg::SetDownloadRules(
+                    pod_id,
+                    podcast.include_pattern.clone(),
+                    podcast.exclude_pattern.clone(),
+                )
+            }
+            SettingKind::ShowPattern | SettingKind::HidePattern => {
+                if let Some(Err(err)) = value.as_deref().map(download_rules::validate) {
+                    self.timed_notif(format!("{err:#}"), 5000, true);
+                    return UiMsg::Noop;
+                }
+                if kind == SettingKind::ShowPattern {
+                    podcast.show_pattern = value;
+                } else {
+                    podcast.hide_pattern = value;
+                }
+                UiMsg::SetSkipRules(
+                    pod_id,
+                    podcast.show_pattern.clone(),
+                    podcast.hide_pattern.clone(),
+                )
+            }
+            SettingKind::AutoDownload | SettingKind::KeepDownloads => {
+                let num = match value.as_deref().map(str::parse::<i64>) {
+                    Some(Ok(num)) if num >= 0 => Some(num),
+                    Some(_) => {
+                        self.timed_notif("Please enter a number.".to_string(), 5000, true);
+                        return UiMsg::Noop;
+                    }
+                    None => None,
+                };
+                if kind == SettingKind::AutoDownload {
+                    podcast.overrides.auto_download = num;
+                } else {
+                    // keeping 0 downloads would delete all of them, so
+                    // it is taken to mean there is no limit
+                    podcast.overrides.keep_downloads = num.filter(|num| *num > 0);
+                }
+                UiMsg::SetOverrides(pod_id, podcast.overrides.clone())
+            }
+            SettingKind::DownloadDir => {
+                podcast.overrides.download_dir = value;
+                UiMsg::SetOverrides(pod_id, podcast.overrides.clone())
+            }
+            SettingKind::PlayCommand => {
+                podcast.overrides.play_command = value;
+                UiMsg::SetOverrides(pod_id, podcast.overrides.clone())
+            }
+            SettingKind::PreferredEnclosure => {
+                podcast.overrides.preferred_enclosure = value;
+                UiMsg::SetOverrides(pod_id, podcast.overrides.clone())
+            }
+            SettingKind::Login => {
+                podcast.auth.password = match value {
+                    Some(_) => match self.notif_win.secret_input_notif("Password: ") {
+                        Some(password) => Some(password),
+                        None => return UiMsg::Noop,
+                    },
+                    None => None,
+                };
+                // a new password replaces the one in the keyring, and
+                // removing the login deletes it
+                podcast.auth.keyring_entry = None;
+                podcast.auth.user = value;
+                UiMsg::SetAuth(pod_id, podcast.auth.clone())
+            }
+            SettingKind::Headers => {
+                podcast.auth.headers = match value.as_deref().map(FeedAuth::parse_headers) {
+                    Some(Some(headers)) => headers,
+                    Some(None) => {
+                        self.timed_notif("Headers must look like Name: value.".to_string(), 5000, true);
+                        return UiMsg::Noop;
+                    }
+                    None => Vec::new(),
+                };
+                UiMsg::SetAuth(pod_id, podcast.auth.clone())
+            }
+            SettingKind::CustomTitle => {
+                podcast.custom_title = value;
+                UiMsg::SetCustomTitle(pod_id, podcast.custom_title.clone())
+            }
+            SettingKind::Group => {
+                podcast.group = value;
+                UiMsg::SetGroup(pod_id, podcast.group.clone())
+            }
+            SettingKind::TitleRule => {
+                if let Some(Err(err)) = value.as_deref().map(title_rules::validate) {
+                    self.timed_notif(format!("{err:#}"), 5000, true);
+                    return UiMsg::Noop;
+                }
+                // the replacement is taken as typed, since spaces at
+                // either end of it may matter
+                podcast.title_replacement = match value {
+                    Some(_) => match self
+                        .notif_win
+                        .input_notif_or_cancel("Replace with (blank to remove): ")
+                    {
+                        Some(replacement) if replacement.is_empty() => None,
+                        Some(replacement) => Some(replacement),
+                        None => return UiMsg::Noop,
+                    },
+                    None => None,
+                };
+                podcast.title_pattern = value;
+                UiMsg::SetTitleRule(
+                    pod_id,
+                    podcast.title_pattern.clone(),
+                    podcast.title_replacement.clone(),
+                )
+            }
+            SettingKind::TestRules
+            | SettingKind::Archived
+            | SettingKind::InvalidCerts
+            | SettingKind::NewPodcast(_)
+            | SettingKind::Filter(_)
+            | SettingKind::Resume(..)
+            | SettingKind::Enclosure(..)
+            | SettingKind::Setup(_) => return UiMsg::Noop,
+        };
 
-                            Some(UserAction::FilterPlayed) => {
-                                return UiMsg::FilterChange(FilterType::Played);
-                            }
-                            Some(UserAction::FilterDownloaded) => {
-                                return UiMsg::FilterChange(FilterType::Downloaded);
-                            }
+        let (settings, header) = Self::podcast_settings(&podcast);
+        self.popup_win.update_settings_win(settings, header);
+        return msg;
+    }
 
-                            Some(UserAction::Help) => self.popup_win.spawn_help_win(),
+    /// Asks the user how to log in to a feed that requires it: either
+    /// with a user name and password, or by sending a header (e.g., with
+    /// a token). Returns the message for trying the feed again.
+    fn ask_feed_auth(&mut self, mut feed: PodcastFeed) -> UiMsg {
+        let name: String = feed
+            .title
+            .as_ref()
+            .unwrap_or(&feed.url)
+            .chars()
+            .take(30)
+            .collect();
+        let prompt = format!("Login for {name}. User name (blank to use a header): ");
+        let user = match self.notif_win.input_notif_or_cancel(&prompt) {
+            Some(user) => user.trim().to_string(),
+            None => return UiMsg::RetryFeed(Box::new(feed), None),
+        };
 
-                            Some(UserAction::Quit) => {
-                                return UiMsg::Quit;
-                            }
-                            None => (),
-                        } // end of input match
-                    }
+        let mut auth = std::mem::take(&mut feed.auth);
+        if user.is_empty() {
+            let input = self
+                .notif_win
+                .input_notif_or_cancel("Headers, as Name: value separated by |: ");
+            match input.as_deref().map(FeedAuth::parse_headers) {
+                Some(Some(headers)) if !headers.is_empty() => auth.headers = headers,
+                Some(Some(_)) | None => return UiMsg::RetryFeed(Box::new(feed), None),
+                Some(None) => {
+                    self.timed_notif("Headers must look like Name: value.".to_string(), 5000, true);
+                    return UiMsg::RetryFeed(Box::new(feed), None);
                 }
-                _ => (),
             }
-        } // end of poll()
+        } else {
+            match self.notif_win.secret_input_notif("Password: ") {
+                Some(password) => {
+                    auth.user = Some(user);
+                    auth.password = Some(password);
+                }
+                None => return UiMsg::RetryFeed(Box::new(feed), None),
+            }
+        }
+        return UiMsg::RetryFeed(Box::new(feed), Some(auth));
+    }
+
+    /// Asks for a new feed URL for a podcast (e.g., after the show has
+    /// moved). The main controller only saves it once the new feed has
+    /// been fetched.
+    fn change_feed_url(&mut self, pod_id: i64) -> UiMsg {
+        let (title, old_url) = match self
+            .podcast_menu
+            .items
+            .map_single(pod_id, |pod| (pod.display_title().to_string(), pod.url.clone()))
+        {
+            Some(info) => info,
+            None => return UiMsg::Noop,
+        };
+        let name: String = title.chars().take(30).collect();
+        let prompt = format!("New feed URL for {name}: ");
+        return match self.notif_win.input_notif_or_cancel(&prompt) {
+            Some(url) if !url.trim().is_empty() && url.trim() != old_url => {
+                UiMsg::ChangeFeedUrl(pod_id, url.trim().to_string())
+            }
+            _ => UiMsg::Noop,
+        };
+    }
+
+    /// Asks the user what to do about a podcast whose feed has failed
+    /// `failures` syncs in a row: try it again, change its feed URL, or
+    /// archive it. Returns the message for the main controller.
+    fn ask_dead_feed(&mut self, feed: PodcastFeed, failures: i64) -> UiMsg {
+        let pod_id = match feed.id {
+            Some(id) => id,
+            None => return UiMsg::Noop,
+        };
+        let name: String = feed
+            .title
+            .as_ref()
+            .unwrap_or(&feed.url)
+            .chars()
+            .take(30)
+            .collect();
+        let prompt = format!(
+            "{name} failed its last {failures} syncs. (r)etry, change feed (u)rl, (a)rchive, or leave it? "
+        );
+        let input = self.notif_win.input_notif(&prompt);
+        return match input.trim().chars().next().map(|c| c.to_ascii_lowercase()) {
+            Some('r') => UiMsg::Sync(pod_id),
+            Some('u') => self.change_feed_url(pod_id),
+            Some('a') => UiMsg::SetArchived(pod_id, true),
+            _ => UiMsg::Noop,
+        };
+    }
+
+    /// Asks the user whether to download a batch of episodes from across
+    /// the library, described (with how much space they should take up)
+    /// by `description`. Returns the message for the main controller.
+    fn ask_download_library(&mut self, episodes: Vec<(i64, i64)>, description: String) -> UiMsg {
+        if self.ask_for_confirmation(&format!("Download {description}?")) {
+            return UiMsg::DownloadMulti(episodes);
+        }
         return UiMsg::Noop;
     }
 
@@ -445,8 +2316,15 @@ impl<'a> Ui<'a> {
 
         let (pod_col, ep_col, det_col) = Self::calculate_sizes(n_col);
 
-        self.podcast_menu.resize(n_row - 1, pod_col, 0);
+        if self.podcast_menu.tree {
+            self.podcast_menu.resize(n_row - 1, pod_col + ep_col - 1, 0);
+        } else {
+            self.podcast_menu.resize(n_row - 1, pod_col, 0);
+        }
         self.episode_menu.resize(n_row - 1, ep_col, pod_col - 1);
+        for list in self.tabs.iter_mut().filter_map(|view| view.list.as_mut()) {
+            list.resize(n_row - 1, pod_col + ep_col - 1, 0);
+        }
         self.highlight_items();
 
         if self.details_panel.is_some() {
@@ -463,10 +2341,15 @@ impl<'a> Ui<'a> {
                 if let ActivePanel::DetailsPanel = self.active_panel {
                     self.active_panel = ActivePanel::EpisodeMenu;
                     self.episode_menu.activate();
+                    if let Some(list) = self.tabs[self.tab.index()].list.as_mut() {
+                        list.activate();
+                    } else if self.podcast_menu.tree {
+                        self.podcast_menu.activate();
+                    }
                 }
             }
         } else if det_col > 0 {
-            self.details_panel = Some(DetailsPanel::new(
+            let mut det = DetailsPanel::new(
                 "Details".to_string(),
                 2,
                 self.colors.clone(),
@@ -474,10 +2357,15 @@ impl<'a> Ui<'a> {
                 det_col,
                 pod_col + ep_col - 2,
                 (0, 1, 0, 1),
-            ));
+            );
+            det.cover_art = self.cover_art;
+            det.wrap = self.wrap_details;
+            det.wrap_width = self.config.details_wrap_width;
+            self.details_panel = Some(det);
             self.update_details_panel();
         }
 
+        self.place_tab_bar();
         self.popup_win.resize(n_row, n_col);
         self.notif_win.resize(n_row, n_col);
     }
@@ -498,6 +2386,14 @@ impl<'a> Ui<'a> {
                 self.scroll_current_window(curr_pod_id, Scroll::Up(1));
             }
 
+            UserAction::Left | UserAction::Right if self.tab != Tab::Library => {
+                self.move_in_list(action);
+            }
+
+            UserAction::Left | UserAction::Right if self.podcast_menu.tree => {
+                self.move_in_tree(action, curr_pod_id, curr_ep_id);
+            }
+
             UserAction::Left => {
                 if curr_pod_id.is_some() {
                     match self.active_panel {
@@ -571,16 +2467,361 @@ impl<'a> Ui<'a> {
         }
     }
 
+    /// Moves the cursor to the given podcast and, if provided, to one of
+    /// its episodes. Does nothing if the podcast is currently filtered
+    /// out of the list.
+    pub fn jump_to(&mut self, pod_id: i64, ep_id: Option<i64>) {
+        if self.tab != Tab::Library {
+            self.switch_tab(Tab::Library);
+        }
+        self.episode_menu.clear_marks();
+        // finding in a menu may have left out the podcast or episode
+        if self.podcast_menu.unhide(pod_id) {
+            self.set_panel_titles();
+            self.place_tab_bar();
+        }
+        if self.podcast_menu.tree {
+            let old_pod_id = self.podcast_menu.selected_id();
+            let found = match ep_id {
+                Some(ep_id) => self.podcast_menu.select_child(pod_id, ep_id),
+                None => false,
+            };
+            if found || self.podcast_menu.select_id(pod_id) {
+                self.podcast_menu.marked_children.clear();
+                self.sync_tree(old_pod_id);
+                self.podcast_menu.activate();
+            }
+            return;
+        }
+        if !self.podcast_menu.select_id(pod_id) {
+            return;
+        }
+        self.episode_menu.items = self.podcast_menu.get_episodes();
+        self.episode_menu.top_row = 0;
+        self.episode_menu.selected = 0;
+        if ep_id.is_some_and(|ep_id| self.episode_menu.unhide(ep_id)) {
+            self.set_panel_titles();
+            self.place_tab_bar();
+        }
+
+        let ep_idx = ep_id.and_then(|ep_id| {
+            self.episode_menu.rows().iter().position(|row| *row == MenuRow::Item(ep_id))
+        });
+        match ep_idx {
+            Some(idx) => {
+                self.episode_menu.redraw();
+                self.episode_menu.select_index(idx);
+                self.active_panel = ActivePanel::EpisodeMenu;
+                self.podcast_menu.deactivate();
+                self.episode_menu.activate();
+            }
+            None => {
+                self.active_panel = ActivePanel::PodcastMenu;
+                self.podcast_menu.activate();
+                self.episode_menu.deactivate(false);
+            }
+        }
+    }
+
+    /// Updates the episode menu after the cursor in the podcast menu has
+    /// been moved from the podcast `old_pod_id`, e.g., to the header of a
+    /// group that was collapsed. The podcast menu becomes the active one
+    /// if the cursor is now on another podcast.
+    fn follow_podcast_cursor(&mut self, old_pod_id: Option<i64>) {
+        self.podcast_menu.redraw();
+        if self.podcast_menu.tree {
+            self.sync_tree(old_pod_id);
+            self.update_details_panel();
+        } else if self.podcast_menu.selected_id() != old_pod_id {
+            self.episode_menu.items = self.podcast_menu.get_episodes();
+            self.episode_menu.top_row = 0;
+            self.episode_menu.selected = 0;
+            self.episode_menu.marked.clear();
+            self.episode_menu.redraw();
+            if !matches!(self.active_panel, ActivePanel::PodcastMenu) {
+                self.active_panel = ActivePanel::PodcastMenu;
+                self.podcast_menu.activate();
+                self.episode_menu.deactivate(false);
+            }
+            self.update_details_panel();
+        }
+        self.highlight_items();
+    }
+
+    /// In the tree layout, points the (hidden) episode menu at the
+    /// episode the cursor is on, or at the first episode of the podcast
+    /// it is on, so that actions on the current episode work as they do
+    /// with separate panels. The episode menu is the active one while
+    /// the cursor is on an episode. Marks are cleared when the cursor
+    /// moves away from the podcast `old_pod_id`.
+    fn sync_tree(&mut self, old_pod_id: Option<i64>) {
+        if self.podcast_menu.selected_id() != old_pod_id
+            && !self.podcast_menu.marked_children.is_empty()
+        {
+            self.podcast_menu.marked_children.clear();
+            self.podcast_menu.update_items();
+            self.podcast_menu.highlight_selected();
+        }
+        self.episode_menu.items = self.podcast_menu.get_episodes();
+        self.episode_menu.marked = self.podcast_menu.marked_children.clone();
+        self.episode_menu.selected = 0;
+        self.episode_menu.top_row = 0;
+
+        let on_episode = match self.podcast_menu.selected_row() {
+            Some(MenuRow::Child(_, ep_id)) => {
+                let ep_idx = self
+                    .episode_menu
+                    .items
+                    .borrow_filtered_order()
+                    .iter()
+                    .position(|id| *id == ep_id);
+                self.episode_menu.top_row = ep_idx.unwrap_or(0) as u16;
+                true
+            }
+            _ => false,
+        };
+        match self.active_panel {
+            _ if self.tab != Tab::Library => (),
+            ActivePanel::PodcastMenu if on_episode => {
+                self.active_panel = ActivePanel::EpisodeMenu;
+            }
+            ActivePanel::EpisodeMenu if !on_episode => {
+                self.active_panel = ActivePanel::PodcastMenu;
+            }
+            _ => (),
+        }
+    }
+
+    /// Moves the cursor left or right in the tree layout: right expands
+    /// a podcast (or a collapsed group), then moves to its first episode,
+    /// and from an episode on to the details panel; left goes back the
+    /// same way, collapsing the podcast.
+    fn move_in_tree(
+        &mut self,
+        action: &UserAction,
+        curr_pod_id: Option<i64>,
+        curr_ep_id: Option<i64>,
+    ) {
+        let right = matches!(action, UserAction::Right);
+        match (&self.active_panel, self.podcast_menu.selected_row()) {
+            (ActivePanel::DetailsPanel, _) => {
+                if !right {
+                    self.active_panel = ActivePanel::EpisodeMenu;
+                    self.podcast_menu.activate();
+                }
+                return;
+            }
+            (_, Some(MenuRow::Group(name, _))) => {
+                if right == self.podcast_menu.collapsed.contains(&name) {
+                    self.podcast_menu.toggle_group();
+                }
+            }
+            (_, Some(MenuRow::Item(pod_id))) if right => {
+                if !self.podcast_menu.set_expanded(true) {
+                    if let Some(ep_id) = curr_ep_id {
+                        self.podcast_menu.select_child(pod_id, ep_id);
+                    }
+                }
+            }
+            (_, Some(MenuRow::Child(..))) if right => {
+                if self.details_panel.is_some() {
+                    self.active_panel = ActivePanel::DetailsPanel;
+                    self.podcast_menu.deactivate();
+                }
+                return;
+            }
+            (_, Some(_)) => {
+                self.podcast_menu.set_expanded(false);
+            }
+            (_, None) => return,
+        }
+        self.sync_tree(curr_pod_id);
+        self.update_details_panel();
+    }
+
+    /// Switches between the layout with separate podcast and episode
+    /// panels and the tree layout, keeping the cursor on the same
+    /// podcast and episode.
+    fn toggle_layout(&mut self, curr_pod_id: Option<i64>, curr_ep_id: Option<i64>) {
+        let on_episode = !matches!(self.active_panel, ActivePanel::PodcastMenu);
+        let row = self.podcast_menu.selected_row();
+        self.podcast_menu.tree = !self.podcast_menu.tree;
+        self.podcast_menu.marked_children.clear();
+        self.episode_menu.marked.clear();
+        // the episode menu is hidden in the tree layout
+        self.episode_menu.find_term = None;
+        self.set_visibility();
+        self.set_panel_titles();
+        self.resize(self.n_col, self.n_row);
+
+        // the other tabs are laid out the same either way
+        if self.tab != Tab::Library {
+            return;
+        }
+        match (curr_pod_id, row) {
+            (Some(pod_id), _) => self.jump_to(pod_id, curr_ep_id.filter(|_| on_episode)),
+            (None, Some(row)) => self.podcast_menu.reselect(row),
+            (None, None) => (),
+        }
+        self.update_details_panel();
+    }
+
+    /// Switches to another tab, which comes back with the same panel
+    /// active as when it was left. The episodes listed in the tab are
+    /// gathered again, as they may have changed in the meantime. The
+    /// search tab asks for a term to search for when it is first opened,
+    /// or when switched to again while it is open.
+    fn switch_tab(&mut self, tab: Tab) {
+        if tab == Tab::Search && (self.tab == Tab::Search || self.search_term.is_none()) {
+            let term = self.spawn_input_notif("Search episodes: ");
+            if term.trim().is_empty() {
+                return;
+            }
+            self.search_term = Some(term.trim().to_string());
+            // the results of a new search start at the top
+            if let Some(list) = self.tabs[tab.index()].list.as_mut() {
+                list.top_row = 0;
+                list.selected = 0;
+                list.marked.clear();
+                list.drawn.clear();
+            }
+        } else if tab == self.tab {
+            return;
+        }
+
+        // each tab keeps its own active panel
+        std::mem::swap(&mut self.active_panel, &mut self.tabs[self.tab.index()].active_panel);
+        std::mem::swap(&mut self.active_panel, &mut self.tabs[tab.index()].active_panel);
+        self.tab = tab;
+        let active = matches!(self.active_panel, ActivePanel::EpisodeMenu);
+        if let Some(list) = self.tabs[tab.index()].list.as_mut() {
+            list.active = active;
+        }
+
+        self.refresh_list();
+        self.set_visibility();
+        self.resize(self.n_col, self.n_row);
+        self.update_details_panel();
+    }
+
+    /// Gathers the episodes listed in the current tab again, unless it is
+    /// the library, keeping the cursor on the same episode if it is
+    /// still listed. The list still needs to be redrawn.
+    fn refresh_list(&mut self) {
+        let tab = self.tab;
+        let episodes = tabs::list_episodes(
+            tab,
+            &self.podcast_menu.items,
+            &self.queue,
+            self.search_term.as_deref(),
+        );
+        let title = match (tab, &self.search_term) {
+            (Tab::Search, Some(term)) => format!("Search: {term} ({})", episodes.len()),
+            _ => format!("{} ({})", tab.name(), episodes.len()),
+        };
+        if let Some(list) = self.tabs[tab.index()].list.as_mut() {
+            list.items = LockVec::new(episodes);
+            list.panel
+                .set_title(Self::menu_title(title, list.find_term.as_deref(), self.triage));
+            list.follow_selection();
+        }
+    }
+
+    /// Shows the panels of the current tab, and hides the others. In the
+    /// tree layout, the library has no episode panel.
+    fn set_visibility(&mut self) {
+        let tab = self.tab;
+        self.podcast_menu.panel.hidden = tab != Tab::Library;
+        self.episode_menu.panel.hidden = tab != Tab::Library || self.podcast_menu.tree;
+        for view in self.tabs.iter_mut() {
+            if let Some(list) = view.list.as_mut() {
+                list.panel.hidden = view.tab != tab;
+            }
+        }
+    }
+
+    /// Shows the tab bar at the top of the rightmost panel on screen.
+    fn place_tab_bar(&mut self) {
+        let tab = self.tab;
+        self.podcast_menu.panel.set_tab_bar(Vec::new());
+        self.episode_menu.panel.set_tab_bar(Vec::new());
+        for list in self.tabs.iter_mut().filter_map(|view| view.list.as_mut()) {
+            list.panel.set_tab_bar(Vec::new());
+        }
+
+        let panel = if let Some(det) = self.details_panel.as_mut() {
+            &mut det.panel
+        } else if let Some(list) = self.tabs[tab.index()].list.as_mut() {
+            &mut list.panel
+        } else if self.podcast_menu.tree {
+            &mut self.podcast_menu.panel
+        } else {
+            &mut self.episode_menu.panel
+        };
+        let width = (panel.get_cols() as usize).saturating_sub(panel.get_title().len() + 2);
+        panel.set_tab_bar(tabs::tab_bar(tab, width));
+
+        // the tab bar may have been on another panel before, e.g., if
+        // the details panel was just added
+        self.podcast_menu.panel.redraw_border();
+        self.episode_menu.panel.redraw_border();
+        for list in self.tabs.iter().filter_map(|view| view.list.as_ref()) {
+            list.panel.redraw_border();
+        }
+        if let Some(det) = self.details_panel.as_ref() {
+            det.panel.redraw_border();
+        }
+    }
+
+    /// Moves between the list of episodes in a tab other than the
+    /// library and the details panel.
+    fn move_in_list(&mut self, action: &UserAction) {
+        let list = match self.tabs[self.tab.index()].list.as_mut() {
+            Some(list) => list,
+            None => return,
+        };
+        match (action, &self.active_panel) {
+            (UserAction::Right, ActivePanel::EpisodeMenu)
+                if self.details_panel.is_some() && !list.items.is_empty() =>
+            {
+                self.active_panel = ActivePanel::DetailsPanel;
+                list.deactivate();
+            }
+            (UserAction::Left, ActivePanel::DetailsPanel) => {
+                self.active_panel = ActivePanel::EpisodeMenu;
+                list.activate();
+            }
+            _ => (),
+        }
+    }
+
     /// Scrolls the current active menu by the specified amount and
     /// refreshes the window.
     pub fn scroll_current_window(&mut self, pod_id: Option<i64>, scroll: Scroll) {
         match self.active_panel {
+            ActivePanel::EpisodeMenu if self.tab != Tab::Library => {
+                if let Some(list) = self.tabs[self.tab.index()].list.as_mut() {
+                    list.scroll(scroll);
+                    self.update_details_panel();
+                }
+            }
+            // in the tree layout, the episodes are rows of the podcast
+            // menu too
+            ActivePanel::PodcastMenu | ActivePanel::EpisodeMenu if self.podcast_menu.tree => {
+                if !self.podcast_menu.items.is_empty() {
+                    self.podcast_menu.scroll(scroll);
+                    self.sync_tree(pod_id);
+                    self.update_details_panel();
+                }
+            }
             ActivePanel::PodcastMenu => {
-                if pod_id.is_some() {
+                // the cursor may be on the header of a group
+                if !self.podcast_menu.items.is_empty() {
                     self.podcast_menu.scroll(scroll);
 
                     self.episode_menu.top_row = 0;
                     self.episode_menu.selected = 0;
+                    self.episode_menu.marked.clear();
 
                     // update episodes menu with new list
                     self.episode_menu.items = self.podcast_menu.get_episodes();
@@ -611,11 +2852,7 @@ impl<'a> Ui<'a> {
     ) -> Option<UiMsg> {
         if let Some(pod_id) = curr_pod_id {
             if let Some(ep_id) = curr_ep_id {
-                if let Some(played) = self
-                    .episode_menu
-                    .items
-                    .map_single(ep_id, |ep| ep.is_played())
-                {
+                if let Some(played) = self.map_episode(pod_id, ep_id, |ep| ep.is_played()) {
                     return Some(UiMsg::MarkPlayed(pod_id, ep_id, !played));
                 }
             }
@@ -623,6 +2860,58 @@ impl<'a> Ui<'a> {
         return None;
     }
 
+    /// If any episodes have been marked in the episode menu, this
+    /// returns the (podcast ID, episode ID) pairs for all of them and
+    /// clears the marks. Returns None if nothing is marked.
+    fn take_marked(&mut self, pod_id: i64) -> Option<Vec<(i64, i64)>> {
+        // the episodes listed in the other tabs are from any podcast
+        if let Some(list) = self.tabs[self.tab.index()].list.as_mut() {
+            let marked: Vec<(i64, i64)> = list
+                .get_marked()
+                .into_iter()
+                .filter_map(|ep_id| list.items.map_single(ep_id, |ep| (ep.pod_id, ep.id)))
+                .collect();
+            if marked.is_empty() {
+                return None;
+            }
+            list.clear_marks();
+            return Some(marked);
+        }
+        let marked = self.episode_menu.get_marked();
+        if marked.is_empty() {
+            return None;
+        }
+        self.episode_menu.clear_marks();
+        if self.podcast_menu.tree {
+            self.podcast_menu.marked_children.clear();
+            self.podcast_menu.update_items();
+            self.highlight_items();
+        }
+        return Some(marked.into_iter().map(|ep_id| (pod_id, ep_id)).collect());
+    }
+
+    /// Mark all episodes that have been marked in the episode menu as
+    /// played or unplayed. If any of them are unplayed, this will
+    /// convert all of them to played; if all are played already, only
+    /// then will it convert them to unplayed.
+    fn mark_played_marked(&mut self, curr_pod_id: Option<i64>) -> Option<UiMsg> {
+        let eps = self.take_marked(curr_pod_id?)?;
+        let all_played = eps.iter().all(|(pod_id, ep_id)| {
+            self.map_episode(*pod_id, *ep_id, |ep| ep.is_played()).unwrap_or(true)
+        });
+        return Some(UiMsg::MarkPlayedMulti(eps, !all_played));
+    }
+
+    /// Calls `f` with the episode `ep_id` of the podcast `pod_id`, if
+    /// there is one, whichever tab it is listed in.
+    fn map_episode<B>(&self, pod_id: i64, ep_id: i64, f: impl FnOnce(&Episode) -> B) -> Option<B> {
+        return self
+            .podcast_menu
+            .items
+            .map_single(pod_id, |pod| pod.episodes.map_single(ep_id, f))
+            .flatten();
+    }
+
     /// Mark all episodes for a given podcast as played or unplayed. If
     /// there are any unplayed episodes, this will convert all episodes
     /// to played; if all are played already, only then will it convert
@@ -640,6 +2929,41 @@ impl<'a> Ui<'a> {
         return None;
     }
 
+    /// Asks how many of the newest unplayed episodes to add to the play
+    /// queue.
+    pub fn fill_queue(&mut self) -> Option<UiMsg> {
+        let input =
+            self.spawn_input_notif("Add how many of the newest unplayed episodes to the queue? ");
+        if input.trim().is_empty() {
+            return None;
+        }
+        match input.trim().parse::<usize>() {
+            Ok(count) if count > 0 => return Some(UiMsg::FillQueue(count)),
+            _ => {
+                self.timed_notif("Enter a number of episodes.".to_string(), 5000, true);
+                return None;
+            }
+        }
+    }
+
+    /// Asks for a date or an age, and marks the episodes published
+    /// before it as played, in the given podcast or, if there is none,
+    /// in all podcasts.
+    pub fn mark_older_played(&mut self, pod_id: Option<i64>) -> Option<UiMsg> {
+        let input =
+            self.spawn_input_notif("Mark as played if older than (e.g., 2023-06-01 or 90d): ");
+        if input.trim().is_empty() {
+            return None;
+        }
+        match smart_filters::parse_cutoff(&input) {
+            Ok(cutoff) => return Some(UiMsg::MarkOlderPlayed(pod_id, cutoff)),
+            Err(err) => {
+                self.timed_notif(format!("{err}"), 5000, true);
+                return None;
+            }
+        }
+    }
+
     /// Remove a podcast from the list.
     pub fn remove_podcast(&mut self, curr_pod_id: Option<i64>) -> Option<UiMsg> {
         let confirm = self.ask_for_confirmation("Are you sure you want to remove the podcast?");
@@ -677,11 +3001,8 @@ impl<'a> Ui<'a> {
         if let Some(pod_id) = curr_pod_id {
             if let Some(ep_id) = curr_ep_id {
                 // check if we have local files first
-                let is_downloaded = self
-                    .episode_menu
-                    .items
-                    .map_single(ep_id, |ep| ep.path.is_some())
-                    .unwrap_or(false);
+                let is_downloaded =
+                    self.map_episode(pod_id, ep_id, |ep| ep.path.is_some()).unwrap_or(false);
                 if is_downloaded {
                     let ask_delete = self.spawn_yes_no_notif("Delete local file too?");
                     delete = ask_delete.unwrap_or(false); // default not to delete
@@ -714,21 +3035,14 @@ impl<'a> Ui<'a> {
     /// menus, returns the IDs of the current podcast and episode (if
     /// they exist).
     pub fn get_current_ids(&self) -> (Option<i64>, Option<i64>) {
-        let current_pod_index = (self.podcast_menu.selected + self.podcast_menu.top_row) as usize;
-        let current_ep_index = (self.episode_menu.selected + self.episode_menu.top_row) as usize;
-
-        let current_pod_id = self
-            .podcast_menu
-            .items
-            .borrow_filtered_order()
-            .get(current_pod_index)
-            .copied();
-        let current_ep_id = self
-            .episode_menu
-            .items
-            .borrow_filtered_order()
-            .get(current_ep_index)
-            .copied();
+        if let Some(list) = &self.tabs[self.tab.index()].list {
+            return match list.selected_id() {
+                Some(ep_id) => (list.items.map_single(ep_id, |ep| ep.pod_id), Some(ep_id)),
+                None => (None, None),
+            };
+        }
+        let current_pod_id = self.podcast_menu.selected_id();
+        let current_ep_id = self.episode_menu.selected_id();
         return (current_pod_id, current_ep_id);
     }
 
@@ -831,19 +3145,54 @@ impl<'a> Ui<'a> {
     /// Forces the menus to check the list of podcasts/episodes again and
     /// update.
     pub fn update_menus(&mut self) {
+        // a sync may have changed the descriptions, so they are
+        // rendered again when next shown
+        self.descriptions.clear();
+
+        if self.tab != Tab::Library {
+            self.refresh_list();
+            if let Some(list) = self.tabs[self.tab.index()].list.as_mut() {
+                list.redraw();
+            }
+        }
+
+        // the lists may have changed since they were last drawn, so the
+        // cursors are moved to follow the items they were on
+        let old_pod_id = self.podcast_menu.drawn_selection();
+        self.podcast_menu.follow_selection();
         self.podcast_menu.redraw();
+        if self.podcast_menu.tree {
+            self.sync_tree(old_pod_id);
+            self.highlight_items();
+            return;
+        }
 
-        self.episode_menu.items = if !self.podcast_menu.items.is_empty() {
+        // the filters may leave no podcasts to show, even if there are
+        // some in the list
+        self.episode_menu.items = if self.podcast_menu.items.len(true) > 0 {
             self.podcast_menu.get_episodes()
         } else {
             LockVec::new(Vec::new())
         };
+        if self.podcast_menu.drawn_selection() == old_pod_id {
+            self.episode_menu.follow_selection();
+        } else {
+            self.episode_menu.top_row = 0;
+            self.episode_menu.selected = 0;
+            self.episode_menu.marked.clear();
+        }
         self.episode_menu.redraw();
         self.highlight_items();
     }
 
     /// Forces the menus to redraw the highlighted item.
     pub fn highlight_items(&mut self) {
+        if let Some(list) = self.tabs[self.tab.index()].list.as_mut() {
+            if let ActivePanel::EpisodeMenu = self.active_panel {
+                list.highlight_selected();
+            }
+            return;
+        }
         match self.active_panel {
             ActivePanel::PodcastMenu => {
                 self.podcast_menu.highlight_selected();
@@ -856,17 +3205,112 @@ impl<'a> Ui<'a> {
         }
     }
 
+    /// Removes any cover art from the screen, e.g., before showing a
+    /// popup window over top of the details panel.
+    pub fn clear_cover_art(&self) {
+        if let Some(protocol) = self.cover_art {
+            // any error writing to the terminal shows up when the
+            // screen is next flushed
+            let sequence = cover_art::clear_sequence(protocol);
+            let _ = queue!(term::stdout(), style::Print(sequence));
+        }
+    }
+
     /// When the program is ending, this performs tear-down functions so
     /// that the terminal is properly restored to its prior settings.
     pub fn tear_down(&self) {
-        terminal::disable_raw_mode().unwrap();
-        execute!(
-            io::stdout(),
+        Self::restore_terminal();
+    }
+
+    /// Gives the terminal to another program: cover art is removed, input
+    /// is no longer read, and the terminal is put back to its prior
+    /// settings until `return_to_terminal()` is called.
+    fn leave_terminal(&self) {
+        self.clear_cover_art();
+        term::pause_input();
+        term::catch_interrupts(true);
+        Self::restore_terminal();
+    }
+
+    /// Takes the terminal back after `leave_terminal()`, and redraws the
+    /// whole screen, as the terminal may have been drawn over or resized
+    /// in the meantime.
+    fn return_to_terminal(&mut self) {
+        term::enable_raw_mode();
+        let _ = execute!(
+            term::stdout(),
+            terminal::EnterAlternateScreen,
+            terminal::Clear(terminal::ClearType::All),
+            cursor::Hide
+        );
+        term::catch_interrupts(false);
+        term::resume_input();
+        let (n_col, n_row) = term::size();
+        self.resize(n_col, n_row);
+    }
+
+    /// Suspends shellcaster as Ctrl-Z would in other programs, redrawing
+    /// the screen once it is brought back to the foreground.
+    pub fn suspend(&mut self) {
+        if !cfg!(unix) {
+            self.timed_notif(
+                "Suspending is not supported on this system.".to_string(),
+                5000,
+                true,
+            );
+            return;
+        }
+        self.leave_terminal();
+        term::suspend();
+        self.return_to_terminal();
+    }
+
+    /// Opens the user's shell in place of the UI, returning to it once
+    /// the shell exits.
+    pub fn run_shell(&mut self) {
+        let shell = user_shell();
+        self.leave_terminal();
+        println!("Exit the shell to return to shellcaster.");
+        let result = process::Command::new(&shell).status();
+        self.return_to_terminal();
+        if let Err(err) = result {
+            self.timed_notif(format!("Could not start shell {shell}: {err}"), 5000, true);
+        }
+    }
+
+    /// Gives the terminal to a player that runs in it, letting the main
+    /// controller know through `ready` once it can be started. The
+    /// terminal is taken back when the main controller sends word (or
+    /// stops sending) on `done`; if the player failed, its output is
+    /// left on screen until Enter is pressed.
+    pub fn hand_over_terminal(
+        &mut self,
+        ready: mpsc::Sender<()>,
+        done: mpsc::Receiver<Option<String>>,
+    ) {
+        self.leave_terminal();
+        let _ = ready.send(());
+        let error = done.recv().ok().flatten();
+        if let Some(err) = &error {
+            println!("\n{err}\nPress Enter to return to shellcaster.");
+            term::wait_for_enter();
+        }
+        self.return_to_terminal();
+        if let Some(err) = error {
+            self.timed_notif(format!("Error: {err}"), 5000, true);
+        }
+    }
+
+    /// Puts the terminal back to its prior settings. Errors are ignored,
+    /// as this is also used to clean up after the UI has already failed.
+    fn restore_terminal() {
+        term::disable_raw_mode();
+        let _ = execute!(
+            term::stdout(),
             terminal::Clear(terminal::ClearType::All),
             terminal::LeaveAlternateScreen,
             cursor::Show
-        )
-        .unwrap();
+        );
     }
 
     /// Updates the details panel with information about the current
@@ -880,51 +3324,89 @@ impl<'a> Ui<'a> {
                     // get a couple details from the current podcast
                     let mut pod_title = None;
                     let mut pod_explicit = None;
+                    let mut image_url = None;
+                    let mut pod_author = None;
+                    let mut pod_categories = Vec::new();
+                    let mut pod_language = None;
+                    let mut pod_website = None;
+                    let mut pod_funding = None;
+                    let mut episodes = LockVec::new(Vec::new());
                     if let Some(pod) = self.podcast_menu.items.borrow_map().get(&pod_id) {
-                        pod_title = if pod.title.is_empty() {
+                        episodes = pod.episodes.clone();
+                        image_url = pod.image_url.clone();
+                        pod_author = pod.author.clone().filter(|a| !a.trim().is_empty());
+                        pod_categories = pod.categories.clone();
+                        pod_language = pod.language.clone();
+                        pod_website = pod.website.clone();
+                        pod_funding = pod.funding_url.as_ref().map(|url| {
+                            match &pod.funding_text {
+                                Some(text) => format!("{text} ({url})"),
+                                None => url.clone(),
+                            }
+                        });
+                        pod_title = if pod.display_title().is_empty() {
                             None
                         } else {
-                            Some(pod.title.clone())
+                            Some(pod.display_title().to_string())
                         };
                         pod_explicit = pod.explicit;
                     };
 
                     // the rest of the details come from the current episode
-                    if let Some(ep) = self.episode_menu.items.borrow_map().get(&ep_id) {
+                    if let Some(ep) = episodes.borrow_map().get(&ep_id) {
                         let ep_title = if ep.title.is_empty() {
                             None
                         } else {
                             Some(ep.title.clone())
                         };
 
-                        let desc = if ep.description.is_empty() {
-                            None
-                        } else {
-                            // convert <br/> tags to a single line break
-                            let br_to_lb = RE_BR_TAGS.replace_all(&ep.description, "\n");
-
-                            // strip all HTML tags
-                            let stripped_tags = RE_HTML_TAGS.replace_all(&br_to_lb, "");
-
-                            // convert HTML entities (e.g., &amp;)
-                            let decoded = match escaper::decode_html(&stripped_tags) {
-                                Err(_) => stripped_tags.to_string(),
-                                Ok(s) => s,
-                            };
-
-                            // remove anything more than two line breaks (i.e., one blank line)
-                            let no_line_breaks = RE_MULT_LINE_BREAKS.replace_all(&decoded, "\n\n");
+                        let desc = self
+                            .descriptions
+                            .entry(ep_id)
+                            .or_insert_with(|| {
+                                let rendered = html::render(&ep.description);
+                                (!rendered.lines.is_empty()).then_some(rendered)
+                            })
+                            .clone();
+
+                        // episode artwork takes precedence over the
+                        // podcast's artwork
+                        let mut image = None;
+                        if let (Some(_), Some(cache_dir)) = (self.cover_art, &self.config.image_cache)
+                        {
+                            if let Some(url) = ep.image_url.clone().or(image_url) {
+                                let path = cover_art::cache_path(cache_dir, &url);
+                                if path.exists() {
+                                    image = Some(path);
+                                } else if self.requested_images.insert(url.clone()) {
+                                    self.image_requests.push(url);
+                                }
+                            }
+                        }
 
-                            Some(no_line_breaks.to_string())
-                        };
+                        // chapters are looked up the first time the
+                        // episode is shown
+                        if ep.chapters.is_empty()
+                            && (ep.chapters_url.is_some() || ep.path.is_some())
+                        {
+                            self.chapter_requests.push((pod_id, ep_id));
+                        }
 
                         let details = Details {
                             pod_title: pod_title,
                             ep_title: ep_title,
                             pubdate: ep.pubdate,
                             duration: Some(ep.format_duration()),
+                            problem: ep.enclosure_error.clone(),
                             explicit: pod_explicit,
+                            author: pod_author,
+                            categories: pod_categories,
+                            language: pod_language,
+                            website: pod_website,
+                            funding: pod_funding,
                             description: desc,
+                            image: image,
+                            chapters: ep.chapters.clone(),
                         };
                         det.change_details(details);
                     };
@@ -933,3 +3415,87 @@ impl<'a> Ui<'a> {
         }
     }
 }
+
+/// Passes a message on to the main controller. This only fails if the
+/// main controller has already stopped.
+fn send_to_main(tx_to_main: &mpsc::Sender<Message>, msg: Message) -> Result<()> {
+    return tx_to_main
+        .send(msg)
+        .map_err(|_| anyhow!("Lost contact with the main thread"));
+}
+
+/// Returns the shell to open with the "shell" action: the one set in
+/// $SHELL (or %COMSPEC% on Windows), or else the system default.
+fn user_shell() -> String {
+    let (var, default) = if cfg!(windows) {
+        ("COMSPEC", "cmd.exe")
+    } else {
+        ("SHELL", "/bin/sh")
+    };
+    return std::env::var(var)
+        .ok()
+        .filter(|shell| !shell.is_empty())
+        .unwrap_or_else(|| default.to_string());
+}
+
+/// Driver for end-to-end tests, available with the "headless" feature:
+/// scripted key presses go in, and the contents of the virtual screen
+/// can be read back out. See `headless_term.rs`.
+#[cfg(feature = "headless")]
+#[cfg_attr(not(test), allow(dead_code))]
+impl Ui {
+    /// Creates a UI drawing to a virtual screen of the given size, and
+    /// draws it once.
+    pub fn headless(config: Config, items: LockVec<Podcast>, n_col: u16, n_row: u16) -> Ui {
+        term::set_size(n_col, n_row);
+        let mut ui = Ui::new(config, items).expect("Can't draw to screen.");
+        ui.init().expect("Can't draw to screen.");
+        return ui;
+    }
+
+    /// Queues the given keys (written as in config.toml), then handles
+    /// every queued event, returning the messages that would have been
+    /// sent to the main controller.
+    pub fn press(&mut self, keys: &[&str]) -> Vec<UiMsg> {
+        term::push_keys(keys);
+        let mut msgs = Vec::new();
+        while term::poll() {
+            match self.getch() {
+                UiMsg::Noop => (),
+                msg => msgs.push(msg),
+            }
+            msgs.append(&mut self.pending_msgs);
+        }
+        return msgs;
+    }
+
+    /// Changes the size of the virtual screen, and lets the UI redraw
+    /// itself as it would when the terminal is resized.
+    pub fn resize_screen(&mut self, n_col: u16, n_row: u16) {
+        term::set_size(n_col, n_row);
+        term::push_event(Event::Resize(n_col, n_row));
+        self.press(&[]);
+    }
+
+    /// Returns the contents of the virtual screen, one String per row.
+    pub fn screen(&self) -> Vec<String> {
+        return term::screen();
+    }
+}
+
+
+// TESTS -----------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lost_main_thread() {
+        let (tx, rx) = mpsc::channel();
+        assert!(send_to_main(&tx, Message::Ui(UiMsg::Quit)).is_ok());
+        assert!(matches!(rx.recv(), Ok(Message::Ui(UiMsg::Quit))));
+        drop(rx);
+        let err = send_to_main(&tx, Message::Ui(UiMsg::Quit)).unwrap_err();
+        assert_eq!(err.to_string(), "Lost contact with the main thread");
+    }
+}
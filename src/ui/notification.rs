@@ -1,7 +1,9 @@
-use std::io;
+use std::collections::VecDeque;
 use std::rc::Rc;
 use std::time::{Duration, Instant};
 
+use chrono::{DateTime, Local};
+
 use crossterm::{
     cursor,
     event::{self, KeyCode},
@@ -9,7 +11,9 @@ use crossterm::{
     style::Stylize,
 };
 
+use super::term;
 use super::AppColors;
+use crate::config::NOTIF_HISTORY_LENGTH;
 
 /// Holds details of a notification message. The `expiry` is optional,
 /// and is used to create timed notifications -- `Instant` should refer
@@ -44,6 +48,10 @@ impl Notification {
 /// stack based on their expiry times. As such, it will generally be a
 /// FIFO approach (older notifications will generally expire first), but
 /// not necessarily.
+///
+/// The `history` holds the most recent timed and persistent
+/// notifications, along with when they were shown, so they can be read
+/// again after they disappear.
 #[derive(Debug)]
 pub struct NotifWin {
     colors: Rc<AppColors>,
@@ -53,6 +61,7 @@ pub struct NotifWin {
     msg_stack: Vec<Notification>,
     persistent_msg: Option<Notification>,
     current_msg: Option<Notification>,
+    history: VecDeque<(DateTime<Local>, Notification)>,
 }
 
 impl NotifWin {
@@ -66,9 +75,16 @@ impl NotifWin {
             msg_stack: Vec::new(),
             persistent_msg: None,
             current_msg: None,
+            history: VecDeque::new(),
         };
     }
 
+    /// Changes the colors used for notifications. The window must be
+    /// redrawn for the change to take effect.
+    pub fn set_colors(&mut self, colors: Rc<AppColors>) {
+        self.colors = colors;
+    }
+
     /// Initiates the window -- primarily, sets the background on the
     /// window.
     pub fn redraw(&self) {
@@ -76,7 +92,7 @@ impl NotifWin {
         let empty = vec![" "; self.total_cols as usize];
         let empty_string = empty.join("");
         queue!(
-            io::stdout(),
+            term::stdout(),
             cursor::MoveTo(0, self.start_y),
             style::PrintStyledContent(
                 style::style(&empty_string)
@@ -87,6 +103,12 @@ impl NotifWin {
         .unwrap();
     }
 
+    /// Returns the time at which the next timed notification expires,
+    /// if there are any.
+    pub fn next_expiry(&self) -> Option<Instant> {
+        return self.msg_stack.iter().filter_map(|x| x.expiry).min();
+    }
+
     /// Checks if the current notification needs to be changed, and
     /// updates the message window accordingly.
     pub fn check_notifs(&mut self) {
@@ -137,8 +159,45 @@ impl NotifWin {
     /// input line. This returns the user's input; if the user cancels
     /// their input, the String will be empty.
     pub fn input_notif(&self, prefix: &str) -> String {
+        return self.input_notif_or_cancel(prefix).unwrap_or_default();
+    }
+
+    /// Same as `input_notif()`, but returns None if the user cancels
+    /// their input, so it can be told apart from an empty input.
+    pub fn input_notif_or_cancel(&self, prefix: &str) -> Option<String> {
+        return self.read_input(prefix, false, None);
+    }
+
+    /// Same as `input_notif_or_cancel()`, but calls `on_change` with
+    /// the input so far every time the user changes it, e.g., to find
+    /// things as the user types.
+    pub fn live_input_notif(
+        &self,
+        prefix: &str,
+        on_change: &mut dyn FnMut(&str),
+    ) -> Option<String> {
+        return self.read_input(prefix, false, Some(on_change));
+    }
+
+    /// Same as `input_notif_or_cancel()`, but hides what the user types
+    /// (e.g., for passwords).
+    pub fn secret_input_notif(&self, prefix: &str) -> Option<String> {
+        return self.read_input(prefix, true, None);
+    }
+
+    /// Reads a line of input from the user after the prompt `prefix`,
+    /// returning None if they cancel. If `secret` is set, each character
+    /// typed is shown as "*". `on_change`, if given, is called with the
+    /// input every time it changes.
+    fn read_input(
+        &self,
+        prefix: &str,
+        secret: bool,
+        mut on_change: Option<&mut dyn FnMut(&str)>,
+    ) -> Option<String> {
+        let shown = |c: char| if secret { '*' } else { c };
         execute!(
-            io::stdout(),
+            term::stdout(),
             cursor::MoveTo(0, self.start_y),
             style::Print(&prefix),
             cursor::Show
@@ -152,7 +211,8 @@ impl NotifWin {
         let mut current_max_x = prefix.len() as u16;
         let mut cursor_x = prefix.len() as u16;
         loop {
-            if let event::Event::Key(input) = event::read().expect("") {
+            if let event::Event::Key(input) = term::read() {
+                let old_len = current_max_x;
                 let cursor_idx = (cursor_x - min_x) as usize;
                 match input.code {
                     // Cancel input
@@ -169,12 +229,12 @@ impl NotifWin {
                             current_max_x -= 1;
                             cursor_x -= 1;
                             let _ = inputs.remove(cursor_idx - 1);
-                            execute!(io::stdout(), cursor::MoveLeft(1)).unwrap();
+                            execute!(term::stdout(), cursor::MoveLeft(1)).unwrap();
                             for i in inputs.chars().skip(cursor_idx - 1) {
-                                execute!(io::stdout(), style::Print(i)).unwrap();
+                                execute!(term::stdout(), style::Print(shown(i))).unwrap();
                             }
                             execute!(
-                                io::stdout(),
+                                term::stdout(),
                                 style::Print(" "),
                                 cursor::MoveTo(cursor_x, self.start_y)
                             )
@@ -186,10 +246,10 @@ impl NotifWin {
                             current_max_x -= 1;
                             let _ = inputs.remove(cursor_idx);
                             for i in inputs.chars().skip(cursor_idx) {
-                                execute!(io::stdout(), style::Print(i)).unwrap();
+                                execute!(term::stdout(), style::Print(shown(i))).unwrap();
                             }
                             execute!(
-                                io::stdout(),
+                                term::stdout(),
                                 style::Print(" "),
                                 cursor::MoveTo(cursor_x, self.start_y)
                             )
@@ -199,13 +259,13 @@ impl NotifWin {
                     KeyCode::Left => {
                         if cursor_x > min_x {
                             cursor_x -= 1;
-                            execute!(io::stdout(), cursor::MoveLeft(1)).unwrap();
+                            execute!(term::stdout(), cursor::MoveLeft(1)).unwrap();
                         }
                     }
                     KeyCode::Right => {
                         if cursor_x < current_max_x {
                             cursor_x += 1;
-                            execute!(io::stdout(), cursor::MoveRight(1)).unwrap();
+                            execute!(term::stdout(), cursor::MoveRight(1)).unwrap();
                         }
                     }
                     KeyCode::Char(c) => {
@@ -214,26 +274,34 @@ impl NotifWin {
                         if cursor_x < current_max_x {
                             inputs.insert(cursor_idx, c);
                             for i in inputs.chars().skip(cursor_idx) {
-                                execute!(io::stdout(), style::Print(i)).unwrap();
+                                execute!(term::stdout(), style::Print(shown(i))).unwrap();
                             }
-                            execute!(io::stdout(), cursor::MoveTo(cursor_x, self.start_y)).unwrap();
+                            execute!(term::stdout(), cursor::MoveTo(cursor_x, self.start_y)).unwrap();
                         } else {
                             inputs.push(c);
-                            execute!(io::stdout(), style::Print(c)).unwrap();
+                            execute!(term::stdout(), style::Print(shown(c))).unwrap();
                         }
                     }
                     _ => (),
                 }
+
+                // every change to the input adds or removes a character
+                if current_max_x != old_len {
+                    if let Some(on_change) = on_change.as_mut() {
+                        on_change(&inputs);
+                        execute!(term::stdout(), cursor::MoveTo(cursor_x, self.start_y)).unwrap();
+                    }
+                }
             }
         }
 
-        execute!(io::stdout(), cursor::Hide).unwrap();
+        execute!(term::stdout(), cursor::Hide).unwrap();
         self.redraw();
 
         if cancelled {
-            return String::from("");
+            return None;
         }
-        return inputs;
+        return Some(inputs);
     }
 
     /// Prints a notification to the window.
@@ -246,11 +314,11 @@ impl NotifWin {
                 .attribute(style::Attribute::Bold)
         } else {
             style::style(&notif.message)
-                .with(self.colors.normal.0)
-                .on(self.colors.normal.1)
+                .with(self.colors.notification().0)
+                .on(self.colors.notification().1)
         };
         queue!(
-            io::stdout(),
+            term::stdout(),
             cursor::MoveTo(0, self.start_y),
             style::PrintStyledContent(styled)
         )
@@ -262,8 +330,9 @@ impl NotifWin {
     /// presenting error messages, among other things.
     pub fn timed_notif(&mut self, message: String, duration: u64, error: bool) {
         let expiry = Instant::now() + Duration::from_millis(duration);
-        self.msg_stack
-            .push(Notification::new(message, error, Some(expiry)));
+        let notif = Notification::new(message, error, Some(expiry));
+        self.remember(&notif);
+        self.msg_stack.push(notif);
     }
 
     /// Adds a notification that will stay on screen indefinitely. Must
@@ -272,6 +341,9 @@ impl NotifWin {
     /// overwrite that message.
     pub fn persistent_notif(&mut self, message: String, error: bool) {
         let notif = Notification::new(message, error, None);
+        if self.persistent_msg.as_ref() != Some(&notif) {
+            self.remember(&notif);
+        }
         self.persistent_msg = Some(notif.clone());
         if self.msg_stack.is_empty() {
             self.display_notif(&notif);
@@ -279,6 +351,29 @@ impl NotifWin {
         }
     }
 
+    /// Adds a notification to the history, dropping the oldest one if
+    /// the history is full.
+    fn remember(&mut self, notif: &Notification) {
+        if self.history.len() >= NOTIF_HISTORY_LENGTH {
+            self.history.pop_front();
+        }
+        self.history.push_back((Local::now(), notif.clone()));
+    }
+
+    /// Returns the notifications in the history, newest first, each
+    /// with the time it was shown.
+    pub fn history(&self) -> Vec<String> {
+        return self
+            .history
+            .iter()
+            .rev()
+            .map(|(time, notif)| {
+                let marker = if notif.error { " [error]" } else { "" };
+                format!("{}{marker}  {}", time.format("%H:%M:%S"), notif.message)
+            })
+            .collect();
+    }
+
     /// Clears any persistent notification that is being displayed. Does
     /// not affect timed notifications, user input notifications, etc.
     pub fn clear_persistent_notif(&mut self) {
@@ -1,10 +1,11 @@
-use std::io;
 use std::rc::Rc;
 
 use crossterm::style::{self, Stylize};
 use crossterm::{cursor, queue};
 
+use super::term;
 use super::AppColors;
+use crate::types::StringUtils;
 
 pub const VERTICAL: &str = "│";
 pub const HORIZONTAL: &str = "─";
@@ -24,7 +25,10 @@ pub const BOTTOM_TEE: &str = "┴";
 /// the x and y coordinates to account for the border and margins, so
 /// users of the methods can calculate rows and columns relative to the
 /// Panel (i.e., x = 0 and y = 0 represent the top-left printable
-/// cell in the window).
+/// cell in the window). A `hidden` panel is not drawn at all, e.g., the
+/// episode panel in the tree layout, where the podcast panel takes its
+/// place. The `tab_bar`, if any, is shown at the right end of the top
+/// border, with the current tab in the colors of the title.
 #[derive(Debug)]
 pub struct Panel {
     screen_pos: usize,
@@ -34,6 +38,8 @@ pub struct Panel {
     n_row: u16,
     n_col: u16,
     margins: (u16, u16, u16, u16),
+    pub hidden: bool,
+    tab_bar: Vec<(String, bool)>,
 }
 
 impl Panel {
@@ -55,22 +61,38 @@ impl Panel {
             n_row: n_row,
             n_col: n_col,
             margins: margins,
+            hidden: false,
+            tab_bar: Vec::new(),
         };
     }
 
     /// Redraws borders and refreshes the window to display on terminal.
     pub fn redraw(&self) {
+        if self.hidden {
+            return;
+        }
         self.clear();
         self.draw_border();
     }
 
+    /// Redraws the borders, title and tab bar of the panel, without
+    /// clearing what is inside it.
+    pub fn redraw_border(&self) {
+        if !self.hidden {
+            self.draw_border();
+        }
+    }
+
     /// Clears the whole Panel.
     pub fn clear(&self) {
+        if self.hidden {
+            return;
+        }
         let empty = vec![" "; self.n_col as usize];
         let empty_string = empty.join("");
         for r in 0..(self.n_row - 1) {
             queue!(
-                io::stdout(),
+                term::stdout(),
                 cursor::MoveTo(self.start_x, r),
                 style::PrintStyledContent(
                     style::style(&empty_string)
@@ -85,11 +107,14 @@ impl Panel {
     /// Clears the inner section of the Panel, leaving the borders
     /// intact.
     pub fn clear_inner(&self) {
+        if self.hidden {
+            return;
+        }
         let empty = vec![" "; self.n_col as usize - 2];
         let empty_string = empty.join("");
         for r in 1..(self.n_row - 1) {
             queue!(
-                io::stdout(),
+                term::stdout(),
                 cursor::MoveTo(self.start_x + 1, r),
                 style::PrintStyledContent(
                     style::style(&empty_string)
@@ -125,10 +150,10 @@ impl Panel {
         border_bottom.push(BOTTOM_RIGHT);
 
         queue!(
-            io::stdout(),
+            term::stdout(),
             style::SetColors(style::Colors::new(
-                self.colors.normal.0,
-                self.colors.normal.1
+                self.colors.border().0,
+                self.colors.border().1
             )),
             cursor::MoveTo(self.start_x, 0),
             style::Print(border_top.join("")),
@@ -139,7 +164,7 @@ impl Panel {
 
         for r in 1..(self.n_row - 1) {
             queue!(
-                io::stdout(),
+                term::stdout(),
                 cursor::MoveTo(self.start_x, r),
                 style::Print(VERTICAL.to_string()),
                 cursor::MoveTo(self.start_x + self.n_col - 1, r),
@@ -149,12 +174,38 @@ impl Panel {
         }
 
         queue!(
-            io::stdout(),
+            term::stdout(),
             cursor::MoveTo(self.start_x + 2, 0),
-            style::Print(&self.title),
+            style::PrintStyledContent(
+                style::style(&self.title)
+                    .with(self.colors.title().0)
+                    .on(self.colors.title().1)
+            ),
             style::ResetColor,
         )
         .unwrap();
+
+        // the tab bar is left out if it would run into the title
+        let bar_len: usize = self.tab_bar.iter().map(|(name, _)| name.grapheme_len() + 1).sum();
+        let bar_start = (self.n_col as usize).checked_sub(bar_len + 2);
+        if let Some(start) = bar_start.filter(|start| *start > self.title.grapheme_len() + 3) {
+            queue!(term::stdout(), cursor::MoveTo(self.start_x + start as u16, 0)).unwrap();
+            for (name, current) in self.tab_bar.iter() {
+                let colors = if *current {
+                    self.colors.title()
+                } else {
+                    self.colors.border()
+                };
+                queue!(
+                    term::stdout(),
+                    style::PrintStyledContent(
+                        style::style(format!(" {name}")).with(colors.0).on(colors.1)
+                    ),
+                )
+                .unwrap();
+            }
+            queue!(term::stdout(), style::ResetColor).unwrap();
+        }
     }
 
     /// Writes a line of text to the window. Note that this does not do
@@ -162,6 +213,9 @@ impl Panel {
     /// up wrapping and may mess up the format. Use `write_wrap_line()`
     /// if you need line wrapping.
     pub fn write_line(&self, y: u16, string: String, style: Option<style::ContentStyle>) {
+        if self.hidden {
+            return;
+        }
         let styled = match style {
             Some(style) => style.apply(string),
             None => style::style(string)
@@ -169,13 +223,46 @@ impl Panel {
                 .on(self.colors.normal.1),
         };
         queue!(
-            io::stdout(),
+            term::stdout(),
             cursor::MoveTo(self.abs_x(0), self.abs_y(y)),
             style::PrintStyledContent(styled)
         )
         .unwrap();
     }
 
+    /// Writes a line made up of several pieces of text, each with its
+    /// own style. As with `write_line()`, this does not check the line
+    /// length.
+    pub fn write_spans(&self, y: u16, spans: &[(String, Option<style::ContentStyle>)]) {
+        if self.hidden {
+            return;
+        }
+        queue!(term::stdout(), cursor::MoveTo(self.abs_x(0), self.abs_y(y))).unwrap();
+        for (text, style) in spans.iter() {
+            let styled = match style {
+                Some(style) => style.apply(text.clone()),
+                None => style::style(text.clone())
+                    .with(self.colors.normal.0)
+                    .on(self.colors.normal.1),
+            };
+            queue!(term::stdout(), style::PrintStyledContent(styled)).unwrap();
+        }
+    }
+
+    /// Writes raw output (e.g., an escape sequence that draws an image)
+    /// to the terminal, starting at the given row of the window.
+    pub fn write_raw(&self, y: u16, raw: &str) {
+        if self.hidden {
+            return;
+        }
+        queue!(
+            term::stdout(),
+            cursor::MoveTo(self.abs_x(0), self.abs_y(y)),
+            style::Print(raw)
+        )
+        .unwrap();
+    }
+
     /// Writes a line of styled text to the window, representing a key
     /// and value. The text will be shown as "key: value", and styled
     /// with the provided styles. Note that this does not do checking
@@ -190,10 +277,13 @@ impl Panel {
         key_style: Option<style::ContentStyle>,
         value_style: Option<style::ContentStyle>,
     ) {
+        if self.hidden {
+            return;
+        }
         key.push(':');
         value.insert(0, ' ');
 
-        queue!(io::stdout(), cursor::MoveTo(self.abs_x(0), self.abs_y(y))).unwrap();
+        queue!(term::stdout(), cursor::MoveTo(self.abs_x(0), self.abs_y(y))).unwrap();
 
         let key_styled = match key_style {
             Some(kstyle) => kstyle.apply(key),
@@ -201,14 +291,14 @@ impl Panel {
                 .with(self.colors.normal.0)
                 .on(self.colors.normal.1),
         };
-        queue!(io::stdout(), style::PrintStyledContent(key_styled)).unwrap();
+        queue!(term::stdout(), style::PrintStyledContent(key_styled)).unwrap();
         let value_styled = match value_style {
             Some(vstyle) => vstyle.apply(value),
             None => style::style(value)
                 .with(self.colors.normal.0)
                 .on(self.colors.normal.1),
         };
-        queue!(io::stdout(), style::PrintStyledContent(value_styled)).unwrap();
+        queue!(term::stdout(), style::PrintStyledContent(value_styled)).unwrap();
     }
 
     /// Writes one or more lines of text from a String, word wrapping
@@ -223,7 +313,7 @@ impl Panel {
     ) -> u16 {
         let mut row = start_y;
         let max_row = self.get_rows();
-        if row >= max_row {
+        if row >= max_row || self.hidden {
             return row;
         }
         let content_style = match style {
@@ -235,7 +325,7 @@ impl Panel {
         let wrapper = textwrap::wrap(string, self.get_cols() as usize);
         for line in wrapper {
             queue!(
-                io::stdout(),
+                term::stdout(),
                 cursor::MoveTo(self.abs_x(0), self.abs_y(row)),
                 style::PrintStyledContent(content_style.apply(line))
             )
@@ -249,6 +339,24 @@ impl Panel {
         return row - 1;
     }
 
+    /// Changes the title shown at the top of the panel. The panel needs
+    /// to be redrawn to show it.
+    pub fn set_title(&mut self, title: String) {
+        self.title = title;
+    }
+
+    /// Returns the title shown at the top of the panel.
+    pub fn get_title(&self) -> &str {
+        return &self.title;
+    }
+
+    /// Changes the names of the tabs shown at the top of the panel, each
+    /// with whether it is the current one. The panel needs to be redrawn
+    /// to show them.
+    pub fn set_tab_bar(&mut self, tabs: Vec<(String, bool)>) {
+        self.tab_bar = tabs;
+    }
+
     /// Updates window size.
     pub fn resize(&mut self, n_row: u16, n_col: u16, start_x: u16) {
         self.n_row = n_row;
@@ -1,23 +1,40 @@
-use std::cmp::min;
+use std::cmp::{min, Reverse};
+use std::collections::HashSet;
 use std::rc::Rc;
 
 use crossterm::{
-    event::{KeyCode, KeyEvent},
+    event::{KeyCode, KeyEvent, KeyModifiers},
     style,
     style::Stylize,
 };
 
+use super::transcript_win::TranscriptWin;
 use super::{AppColors, Menu, Panel, Scroll, UiMsg};
 use crate::config::BIG_SCROLL_AMOUNT;
+use crate::fuzzy;
 use crate::keymap::{Keybindings, UserAction};
+use crate::queue;
+use crate::transcript::TranscriptLine;
 use crate::types::*;
 
+/// The most matches that are listed in the fuzzy finder; with a large
+/// library, a short query can match thousands of episodes.
+const MAX_FINDER_RESULTS: usize = 500;
+
 /// Enum indicating the type of the currently active popup window.
 #[derive(Debug)]
 pub enum ActivePopup {
     WelcomeWin(Panel),
     HelpWin(Panel),
     DownloadWin(Menu<NewEpisode>),
+    DigestWin(Menu<DigestItem>),
+    EpisodeListWin(Menu<ListedEpisode>),
+    ChapterWin(Menu<Chapter>),
+    FinderWin(Menu<FinderItem>),
+    SettingsWin(Menu<PodcastSetting>),
+    TranscriptWin(TranscriptWin),
+    StatsWin(Panel),
+    ThemeWin(Panel),
     None,
 }
 
@@ -34,11 +51,51 @@ impl ActivePopup {
         return matches!(self, ActivePopup::DownloadWin(_));
     }
 
+    pub fn is_digest_win(&self) -> bool {
+        return matches!(self, ActivePopup::DigestWin(_));
+    }
+
+    pub fn is_episode_list_win(&self) -> bool {
+        return matches!(self, ActivePopup::EpisodeListWin(_));
+    }
+
+    pub fn is_chapter_win(&self) -> bool {
+        return matches!(self, ActivePopup::ChapterWin(_));
+    }
+
+    pub fn is_finder_win(&self) -> bool {
+        return matches!(self, ActivePopup::FinderWin(_));
+    }
+
+    pub fn is_settings_win(&self) -> bool {
+        return matches!(self, ActivePopup::SettingsWin(_));
+    }
+
+    pub fn is_transcript_win(&self) -> bool {
+        return matches!(self, ActivePopup::TranscriptWin(_));
+    }
+
+    pub fn is_stats_win(&self) -> bool {
+        return matches!(self, ActivePopup::StatsWin(_));
+    }
+
+    pub fn is_theme_win(&self) -> bool {
+        return matches!(self, ActivePopup::ThemeWin(_));
+    }
+
     pub fn is_none(&self) -> bool {
         return matches!(self, ActivePopup::None);
     }
 }
 
+/// The lists of episodes from any podcast that can be shown in the
+/// episode list window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EpisodeList {
+    Inbox,
+    Queue,
+}
+
 /// Holds all state relevant for handling popup windows. Holds an
 /// ActivePopup enum that itself contains the Panel/Menu displayed with
 /// the current popup window (if any). The `bool` values provide an
@@ -46,22 +103,51 @@ impl ActivePopup {
 /// for multiple popup windows to exist (though only one is "active" at
 /// any given time).
 #[derive(Debug)]
-pub struct PopupWin<'a> {
+pub struct PopupWin {
     popup: ActivePopup,
     new_episodes: Vec<NewEpisode>,
-    keymap: &'a Keybindings,
+    digest_episodes: Vec<NewEpisode>,
+    digest_failed: Vec<(i64, String)>,
+    digest_expanded: HashSet<i64>,
+    list_kind: EpisodeList,
+    list_episodes: Vec<ListedEpisode>,
+    chapters: Vec<Chapter>,
+    chapters_ep_id: i64,
+    finder_items: Vec<FinderItem>,
+    finder_query: String,
+    finder_results: Vec<FinderItem>,
+    settings: Vec<PodcastSetting>,
+    settings_panel: String,
+    settings_pod_id: i64,
+    settings_header: String,
+    transcript_panel: String,
+    transcript_title: String,
+    transcript: Vec<TranscriptLine>,
+    stats: LibraryStats,
+    theme_name: String,
+    keymap: Rc<Keybindings>,
     colors: Rc<AppColors>,
+    popup_colors: Rc<AppColors>,
     total_rows: u16,
     total_cols: u16,
     pub welcome_win: bool,
     pub help_win: bool,
     pub download_win: bool,
+    pub digest_win: bool,
+    pub list_win: bool,
+    pub chapter_win: bool,
+    pub finder_win: bool,
+    pub settings_win: bool,
+    pub transcript_win: bool,
+    pub stats_win: bool,
+    pub theme_win: bool,
+    pub jump_to: Option<(i64, Option<i64>)>,
 }
 
-impl<'a> PopupWin<'a> {
+impl PopupWin {
     /// Set up struct for handling popup windows.
     pub fn new(
-        keymap: &'a Keybindings,
+        keymap: Rc<Keybindings>,
         colors: Rc<AppColors>,
         total_rows: u16,
         total_cols: u16,
@@ -69,32 +155,86 @@ impl<'a> PopupWin<'a> {
         return Self {
             popup: ActivePopup::None,
             new_episodes: Vec::new(),
+            digest_episodes: Vec::new(),
+            digest_failed: Vec::new(),
+            digest_expanded: HashSet::new(),
+            list_kind: EpisodeList::Inbox,
+            list_episodes: Vec::new(),
+            chapters: Vec::new(),
+            chapters_ep_id: 0,
+            finder_items: Vec::new(),
+            finder_query: String::new(),
+            finder_results: Vec::new(),
+            settings: Vec::new(),
+            settings_panel: String::new(),
+            settings_pod_id: 0,
+            settings_header: String::new(),
+            transcript_panel: String::new(),
+            transcript_title: String::new(),
+            transcript: Vec::new(),
+            stats: LibraryStats::default(),
+            theme_name: String::new(),
             keymap: keymap,
+            popup_colors: Rc::new(colors.for_popups()),
             colors: colors,
             total_rows: total_rows,
             total_cols: total_cols,
             welcome_win: false,
             help_win: false,
             download_win: false,
+            digest_win: false,
+            list_win: false,
+            chapter_win: false,
+            finder_win: false,
+            settings_win: false,
+            transcript_win: false,
+            stats_win: false,
+            theme_win: false,
+            jump_to: None,
         };
     }
 
     /// Indicates whether any sort of popup window is currently on the
     /// screen.
     pub fn is_popup_active(&self) -> bool {
-        return self.welcome_win || self.help_win || self.download_win;
+        return self.welcome_win
+            || self.help_win
+            || self.download_win
+            || self.digest_win
+            || self.list_win
+            || self.chapter_win
+            || self.finder_win
+            || self.settings_win
+            || self.transcript_win
+            || self.stats_win
+            || self.theme_win;
     }
 
     /// Indicates whether a popup window *other than the welcome window*
     /// is currently on the screen.
     pub fn is_non_welcome_popup_active(&self) -> bool {
-        return self.help_win || self.download_win;
+        return self.help_win
+            || self.download_win
+            || self.digest_win
+            || self.list_win
+            || self.chapter_win
+            || self.finder_win
+            || self.settings_win
+            || self.transcript_win
+            || self.stats_win
+            || self.theme_win;
     }
 
     /// Resize the currently active popup window if one exists.
     pub fn resize(&mut self, total_rows: u16, total_cols: u16) {
         self.total_rows = total_rows;
         self.total_cols = total_cols;
+        // the transcript window is resized in place, so it keeps its
+        // scroll position and search result
+        if let ActivePopup::TranscriptWin(win) = &mut self.popup {
+            win.resize(total_rows - 1, total_cols);
+            return;
+        }
         match &self.popup {
             ActivePopup::WelcomeWin(_win) => {
                 let welcome_win = self.make_welcome_win();
@@ -109,10 +249,58 @@ impl<'a> PopupWin<'a> {
                 download_win.activate();
                 self.popup = ActivePopup::DownloadWin(download_win);
             }
+            ActivePopup::DigestWin(win) => {
+                let index = win.get_menu_idx(win.selected);
+                self.refresh_digest_win(index);
+            }
+            ActivePopup::EpisodeListWin(win) => {
+                let index = win.get_menu_idx(win.selected);
+                self.refresh_episode_list_win(index);
+            }
+            ActivePopup::ChapterWin(win) => {
+                let index = win.get_menu_idx(win.selected);
+                let mut chapter_win = self.make_chapter_win();
+                chapter_win.activate();
+                chapter_win.select_index(index);
+                self.popup = ActivePopup::ChapterWin(chapter_win);
+            }
+            ActivePopup::FinderWin(win) => {
+                let index = win.get_menu_idx(win.selected);
+                self.refresh_finder_win(index);
+            }
+            ActivePopup::SettingsWin(win) => {
+                let index = win.get_menu_idx(win.selected);
+                self.refresh_settings_win(index);
+            }
+            ActivePopup::StatsWin(_win) => {
+                let stats_win = self.make_stats_win();
+                self.popup = ActivePopup::StatsWin(stats_win);
+            }
+            ActivePopup::ThemeWin(_win) => {
+                let theme_win = self.make_theme_win();
+                self.popup = ActivePopup::ThemeWin(theme_win);
+            }
+            ActivePopup::TranscriptWin(_win) => (),
             ActivePopup::None => (),
         }
     }
 
+    /// Changes the colors used for popup windows, and redraws the
+    /// currently active popup window (if any). `theme_name` is shown in
+    /// the theme preview window.
+    pub fn set_colors(&mut self, colors: Rc<AppColors>, theme_name: &str) {
+        self.popup_colors = Rc::new(colors.for_popups());
+        self.colors = colors;
+        self.theme_name = theme_name.to_string();
+        self.resize(self.total_rows, self.total_cols);
+    }
+
+    /// Replaces the keybindings, e.g., after the config file has been
+    /// reloaded.
+    pub fn set_keymap(&mut self, keymap: Rc<Keybindings>) {
+        self.keymap = keymap;
+    }
+
     /// Create a welcome window and draw it to the screen.
     pub fn spawn_welcome_win(&mut self) {
         self.welcome_win = true;
@@ -135,7 +323,7 @@ impl<'a> PopupWin<'a> {
         let mut welcome_win = Panel::new(
             "Shellcaster".to_string(),
             0,
-            self.colors.clone(),
+            self.popup_colors.clone(),
             self.total_rows - 1,
             self.total_cols,
             0,
@@ -184,22 +372,79 @@ impl<'a> PopupWin<'a> {
             (Some(UserAction::PageDown), "Page down:"),
             (Some(UserAction::GoTop), "Go to top:"),
             (Some(UserAction::GoBot), "Go to bottom:"),
+            (Some(UserAction::ToggleGroup), "Collapse group:"),
+            (Some(UserAction::ToggleAllGroups), "Collapse all groups:"),
+            (Some(UserAction::ToggleLayout), "Switch layout:"),
+            (Some(UserAction::TabLibrary), "Library tab:"),
+            (Some(UserAction::TabNew), "New episodes tab:"),
+            (Some(UserAction::TabQueue), "Queue tab:"),
+            (Some(UserAction::TabDownloads), "Downloads tab:"),
+            (Some(UserAction::TabSearch), "Search tab:"),
+            (Some(UserAction::NextTab), "Next tab:"),
             // (None, ""),
             (Some(UserAction::AddFeed), "Add feed:"),
+            (Some(UserAction::Import), "Import podcasts:"),
             (Some(UserAction::Sync), "Sync:"),
             (Some(UserAction::SyncAll), "Sync all:"),
+            (Some(UserAction::CheckAll), "Check for changes:"),
             // (None, ""),
+            (Some(UserAction::Select), "Select:"),
             (Some(UserAction::Play), "Play:"),
             (Some(UserAction::MarkPlayed), "Mark as played:"),
             (Some(UserAction::MarkAllPlayed), "Mark all as played:"),
+            (Some(UserAction::MarkOlderPlayed), "Mark older as played:"),
+            (Some(UserAction::MarkLibraryOlderPlayed), "Mark older as played (all):"),
+            (Some(UserAction::ToggleStar), "Star/unstar episode:"),
+            (Some(UserAction::Enqueue), "Add to end of queue:"),
+            (Some(UserAction::EnqueueNext), "Play next in queue:"),
+            (Some(UserAction::MoveUp), "Move up in queue:"),
+            (Some(UserAction::MoveDown), "Move down in queue:"),
+            (Some(UserAction::MoveToTop), "Move to top of queue:"),
+            (Some(UserAction::MoveToBottom), "Move to bottom of queue:"),
+            (Some(UserAction::ShuffleQueue), "Shuffle queue:"),
+            (Some(UserAction::InterleaveQueue), "Interleave podcasts in queue:"),
+            (Some(UserAction::FillQueue), "Fill queue with newest:"),
             // (None, ""),
             (Some(UserAction::Download), "Download:"),
             (Some(UserAction::DownloadAll), "Download all:"),
+            (Some(UserAction::ChooseEnclosure), "Choose version to download:"),
+            (Some(UserAction::DownloadLibrary), "Download library:"),
+            (Some(UserAction::Cancel), "Cancel syncing/downloads:"),
             (Some(UserAction::Delete), "Delete file:"),
             (Some(UserAction::DeleteAll), "Delete all files:"),
             (Some(UserAction::UnmarkDownloaded), "Unmark as downloaded:"),
             (Some(UserAction::Remove), "Remove from list:"),
             (Some(UserAction::RemoveAll), "Remove all from list:"),
+            (Some(UserAction::ToggleSelect), "Mark/unmark episode:"),
+            (Some(UserAction::ToggleNotify), "Toggle notifications:"),
+            (Some(UserAction::PodcastSettings), "Podcast settings:"),
+            (Some(UserAction::ChangeFeedUrl), "Change feed URL:"),
+            (Some(UserAction::Support), "Support this show:"),
+            (Some(UserAction::CycleSort), "Change sort order:"),
+            (Some(UserAction::CycleFilter), "Cycle saved filters:"),
+            (Some(UserAction::FilterMenu), "Filter episodes:"),
+            (Some(UserAction::Find), "Find podcast or episode:"),
+            (Some(UserAction::FindInMenu), "Find in menu:"),
+            (Some(UserAction::InProgress), "In progress:"),
+            (Some(UserAction::Inbox), "Inbox:"),
+            (Some(UserAction::TriageMode), "Triage mode:"),
+            (Some(UserAction::Queue), "Play queue:"),
+            (Some(UserAction::Stats), "Statistics:"),
+            (Some(UserAction::ShowLog), "Show log:"),
+            (Some(UserAction::Notifications), "Notification history:"),
+            (Some(UserAction::Suspend), "Suspend:"),
+            (Some(UserAction::Shell), "Open a shell:"),
+            (Some(UserAction::CycleTheme), "Next color theme:"),
+            (Some(UserAction::ThemePreview), "Theme preview:"),
+            (Some(UserAction::ReloadConfig), "Reload config:"),
+            (Some(UserAction::Setup), "Setup:"),
+            (Some(UserAction::Chapters), "Chapters:"),
+            (Some(UserAction::Transcript), "Transcript:"),
+            (Some(UserAction::Search), "Search transcript:"),
+            (Some(UserAction::CopyMode), "Copy mode:"),
+            (Some(UserAction::Yank), "Copy selected lines:"),
+            (Some(UserAction::YankAll), "Copy description:"),
+            (Some(UserAction::ToggleWrap), "Wrap/truncate details:"),
             // (None, ""),
             (Some(UserAction::Help), "Help:"),
             (Some(UserAction::Quit), "Quit:"),
@@ -227,7 +472,7 @@ impl<'a> PopupWin<'a> {
         let mut help_win = Panel::new(
             "Help".to_string(),
             0,
-            self.colors.clone(),
+            self.popup_colors.clone(),
             self.total_rows - 1,
             self.total_cols,
             0,
@@ -262,7 +507,7 @@ impl<'a> PopupWin<'a> {
         } else {
             1
         };
-        let keys_per_row = key_strs.len() as u16 / n_cols;
+        let keys_per_row = (key_strs.len() as u16).div_ceil(n_cols);
 
         // write each line of keys -- the list will be presented "down"
         // rather than "across", but we print to the screen a line at a
@@ -287,6 +532,20 @@ impl<'a> PopupWin<'a> {
             row += 1;
         }
 
+        // macros are listed after the regular keybindings
+        let macros = self.keymap.macros();
+        if !macros.is_empty() {
+            row += 1;
+            for (key, actions) in macros {
+                let names: Vec<&str> = actions.iter().map(|a| a.config_name()).collect();
+                row = help_win.write_wrap_line(
+                    row + 1,
+                    &format!("Macro \"{key}\": {}", names.join(", ")),
+                    None,
+                );
+            }
+        }
+
         let _ = help_win.write_wrap_line(row + 2, "Press \"q\" to close this window.", None);
         return help_win;
     }
@@ -309,7 +568,7 @@ impl<'a> PopupWin<'a> {
         let mut download_panel = Panel::new(
             "New episodes".to_string(),
             0,
-            self.colors.clone(),
+            self.popup_colors.clone(),
             self.total_rows - 1,
             self.total_cols,
             0,
@@ -331,6 +590,796 @@ impl<'a> PopupWin<'a> {
         return download_win;
     }
 
+    /// Create a new digest window summarizing the results of syncing
+    /// all podcasts, and draw it to the screen. A digest that is still
+    /// open is replaced.
+    pub fn spawn_digest_win(&mut self, episodes: Vec<NewEpisode>, failed: Vec<(i64, String)>) {
+        self.digest_episodes = episodes;
+        self.digest_failed = failed;
+        self.digest_expanded = HashSet::new();
+        if self.popup.is_digest_win() {
+            self.refresh_digest_win(0);
+        } else {
+            self.digest_win = true;
+            self.change_win();
+        }
+    }
+
+    /// Create a new Menu holding the digest window.
+    pub fn make_digest_win(&self) -> Menu<DigestItem> {
+        // the warning on the unused mut is a function of Rust getting
+        // confused between panel.rs and mock_panel.rs
+        #[allow(unused_mut)]
+        let mut digest_panel = Panel::new(
+            "Sync digest".to_string(),
+            0,
+            self.popup_colors.clone(),
+            self.total_rows - 1,
+            self.total_cols,
+            0,
+            (1, 0, 0, 0),
+        );
+
+        let items = self.digest_items();
+        let n_pods = items.iter().filter(|item| item.ep_id.is_none() && !item.failed).count();
+        let n_eps = self.digest_episodes.len();
+        let mut header = format!(
+            "{n_eps} new episode{} across {n_pods} podcast{}.",
+            if n_eps == 1 { "" } else { "s" },
+            if n_pods == 1 { "" } else { "s" }
+        );
+        match self.digest_failed.len() {
+            0 => (),
+            1 => header = format!("{header} 1 feed could not be synced."),
+            n => header = format!("{header} {n} feeds could not be synced."),
+        }
+        header = format!(
+            "{header} Expand or collapse a podcast with {}/{}. Press {} to jump to an item, {} to download it, or {} to close this window.",
+            self.list_keys(UserAction::Right, Some(1)),
+            self.list_keys(UserAction::Left, Some(1)),
+            self.list_keys(UserAction::Select, Some(1)),
+            self.list_keys(UserAction::Download, Some(1)),
+            self.list_keys(UserAction::Quit, Some(1)));
+
+        let mut digest_win = Menu::new(digest_panel, Some(header), LockVec::new(items));
+        digest_win.redraw();
+        return digest_win;
+    }
+
+    /// Builds the lines of the digest: one heading per podcast, in the
+    /// order they appear in the list of new episodes, followed by its
+    /// episodes if the podcast has been expanded.
+    fn digest_items(&self) -> Vec<DigestItem> {
+        let mut pods: Vec<(i64, String)> = Vec::new();
+        for ep in self.digest_episodes.iter() {
+            if !pods.iter().any(|(id, _)| *id == ep.pod_id) {
+                pods.push((ep.pod_id, ep.pod_title.clone()));
+            }
+        }
+
+        let mut items = Vec::new();
+        for (pod_id, pod_title) in pods.into_iter() {
+            let eps: Vec<&NewEpisode> = self
+                .digest_episodes
+                .iter()
+                .filter(|ep| ep.pod_id == pod_id)
+                .collect();
+            let expanded = self.digest_expanded.contains(&pod_id);
+            items.push(DigestItem {
+                id: items.len() as i64,
+                pod_id: pod_id,
+                ep_id: None,
+                title: pod_title,
+                num_new: eps.len(),
+                expanded: expanded,
+                failed: false,
+            });
+            if expanded {
+                for ep in eps.into_iter() {
+                    items.push(DigestItem {
+                        id: items.len() as i64,
+                        pod_id: pod_id,
+                        ep_id: Some(ep.id),
+                        title: ep.title.clone(),
+                        num_new: 1,
+                        expanded: false,
+                        failed: false,
+                    });
+                }
+            }
+        }
+        for (pod_id, pod_title) in self.digest_failed.iter() {
+            items.push(DigestItem {
+                id: items.len() as i64,
+                pod_id: *pod_id,
+                ep_id: None,
+                title: pod_title.clone(),
+                num_new: 0,
+                expanded: false,
+                failed: true,
+            });
+        }
+        return items;
+    }
+
+    /// Rebuilds the digest window (e.g., after expanding or collapsing
+    /// a podcast) and moves the cursor to the line at `index`.
+    fn refresh_digest_win(&mut self, index: usize) {
+        let mut win = self.make_digest_win();
+        win.activate();
+        win.select_index(index);
+        self.popup = ActivePopup::DigestWin(win);
+    }
+
+    /// Expands or collapses the podcast at the current digest line. If
+    /// the line is an episode, collapsing moves the cursor back to its
+    /// podcast.
+    fn toggle_digest_pod(&mut self, expand: bool) {
+        let item = match &self.popup {
+            ActivePopup::DigestWin(menu) => menu
+                .items
+                .map_single_by_index(menu.get_menu_idx(menu.selected), |item| item.clone()),
+            _ => None,
+        };
+        if let Some(item) = item {
+            if expand && item.ep_id.is_none() && !item.expanded && !item.failed {
+                self.digest_expanded.insert(item.pod_id);
+                self.refresh_digest_win(item.id as usize);
+            } else if !expand && (item.expanded || item.ep_id.is_some()) {
+                self.digest_expanded.remove(&item.pod_id);
+                let index = self
+                    .digest_items()
+                    .iter()
+                    .position(|i| i.pod_id == item.pod_id)
+                    .unwrap_or(0);
+                self.refresh_digest_win(index);
+            }
+        }
+    }
+
+    /// Create a new window listing episodes from any podcast, i.e., the
+    /// inbox or the play queue, and draw it to the screen. A list that
+    /// is still open is replaced.
+    pub fn spawn_episode_list_win(&mut self, kind: EpisodeList, episodes: Vec<ListedEpisode>) {
+        self.list_kind = kind;
+        self.list_episodes = episodes;
+        if self.popup.is_episode_list_win() {
+            self.refresh_episode_list_win(0);
+        } else {
+            self.list_win = true;
+            self.change_win();
+        }
+    }
+
+    /// Create a new Menu holding the episode list window.
+    pub fn make_episode_list_win(&self) -> Menu<ListedEpisode> {
+        let n_eps = self.list_episodes.len();
+        let (title, header) = match self.list_kind {
+            EpisodeList::Inbox => (
+                "Inbox",
+                format!(
+                    "{n_eps} new episode{}. Press {} to add an episode to the queue, {} to play it next, {} to download it, {} to mark it as seen, {} to hide it, or {} to play it. Press {} to mark all as seen, or {} to close this window.",
+                    if n_eps == 1 { "" } else { "s" },
+                    self.list_keys(UserAction::Enqueue, Some(1)),
+                    self.list_keys(UserAction::EnqueueNext, Some(1)),
+                    self.list_keys(UserAction::Download, Some(1)),
+                    self.list_keys(UserAction::MarkPlayed, Some(1)),
+                    self.list_keys(UserAction::Remove, Some(1)),
+                    self.list_keys(UserAction::Play, Some(1)),
+                    self.list_keys(UserAction::MarkAllPlayed, Some(1)),
+                    self.list_keys(UserAction::Quit, Some(1))
+                ),
+            ),
+            EpisodeList::Queue => (
+                "Queue",
+                format!(
+                    "{n_eps} episode{} in the queue. Press {} to play an episode, {} to take it out of the queue, {}/{} to move it up/down, or {}/{} to move it to the top/bottom. Press {} to mark several episodes to move or take out together, {} to clear the queue, or {} to close this window.",
+                    if n_eps == 1 { "" } else { "s" },
+                    self.list_keys(UserAction::Play, Some(1)),
+                    self.list_keys(UserAction::Remove, Some(1)),
+                    self.list_keys(UserAction::MoveUp, Some(1)),
+                    self.list_keys(UserAction::MoveDown, Some(1)),
+                    self.list_keys(UserAction::MoveToTop, Some(1)),
+                    self.list_keys(UserAction::MoveToBottom, Some(1)),
+                    self.list_keys(UserAction::ToggleSelect, Some(1)),
+                    self.list_keys(UserAction::RemoveAll, Some(1)),
+                    self.list_keys(UserAction::Quit, Some(1))
+                ),
+            ),
+        };
+
+        // the warning on the unused mut is a function of Rust getting
+        // confused between panel.rs and mock_panel.rs
+        #[allow(unused_mut)]
+        let mut list_panel = Panel::new(
+            title.to_string(),
+            0,
+            self.popup_colors.clone(),
+            self.total_rows - 1,
+            self.total_cols,
+            0,
+            (1, 0, 0, 0),
+        );
+        let mut list_win = Menu::new(
+            list_panel,
+            Some(header),
+            LockVec::new(self.list_episodes.clone()),
+        );
+        list_win.redraw();
+        return list_win;
+    }
+
+    /// Rebuilds the episode list window and moves the cursor to the line
+    /// at `index`.
+    fn refresh_episode_list_win(&mut self, index: usize) {
+        let mut win = self.make_episode_list_win();
+        win.activate();
+        win.select_index(index);
+        self.popup = ActivePopup::EpisodeListWin(win);
+    }
+
+    /// Returns the podcast and episode IDs of the episodes in the
+    /// episode list window, in order.
+    fn listed_pairs(&self) -> Vec<(i64, i64)> {
+        return self.list_episodes.iter().map(|ep| (ep.pod_id, ep.id)).collect();
+    }
+
+    /// Takes the episode at `index` out of the episode list window,
+    /// closing the window if it was the last one.
+    fn remove_listed_episode(&mut self, index: usize) {
+        if index < self.list_episodes.len() {
+            self.list_episodes.remove(index);
+        }
+        if self.list_episodes.is_empty() {
+            self.turn_off_episode_list_win();
+        } else {
+            self.refresh_episode_list_win(min(index, self.list_episodes.len() - 1));
+        }
+    }
+
+    /// Create a new window listing the chapters of an episode, and draw
+    /// it to the screen.
+    pub fn spawn_chapter_win(&mut self, ep_id: i64, chapters: Vec<Chapter>) {
+        self.chapters = chapters;
+        self.chapters_ep_id = ep_id;
+        self.chapter_win = true;
+        self.change_win();
+    }
+
+    /// Create a new Menu holding the chapter window.
+    pub fn make_chapter_win(&self) -> Menu<Chapter> {
+        // the warning on the unused mut is a function of Rust getting
+        // confused between panel.rs and mock_panel.rs
+        #[allow(unused_mut)]
+        let mut chapter_panel = Panel::new(
+            "Chapters".to_string(),
+            0,
+            self.popup_colors.clone(),
+            self.total_rows - 1,
+            self.total_cols,
+            0,
+            (1, 0, 0, 0),
+        );
+
+        let header = format!(
+            "Press {} to jump to a chapter in mpv, or {} to close this window.",
+            self.list_keys(UserAction::Select, Some(1)),
+            self.list_keys(UserAction::Quit, Some(1))
+        );
+        let mut chapter_win = Menu::new(
+            chapter_panel,
+            Some(header),
+            LockVec::new(self.chapters.clone()),
+        );
+        chapter_win.redraw();
+        return chapter_win;
+    }
+
+    /// Create a new window for finding a podcast or episode by typing
+    /// part of its title, out of `items`, and draw it to the screen.
+    pub fn spawn_finder_win(&mut self, items: Vec<FinderItem>) {
+        self.finder_items = items;
+        self.finder_query = String::new();
+        self.find();
+        self.finder_win = true;
+        self.change_win();
+    }
+
+    /// Create a new Menu holding the fuzzy finder.
+    pub fn make_finder_win(&self) -> Menu<FinderItem> {
+        // the warning on the unused mut is a function of Rust getting
+        // confused between panel.rs and mock_panel.rs
+        #[allow(unused_mut)]
+        let mut finder_panel = Panel::new(
+            format!("Find ({})", self.finder_results.len()),
+            0,
+            self.popup_colors.clone(),
+            self.total_rows - 1,
+            self.total_cols,
+            0,
+            (1, 0, 0, 0),
+        );
+
+        let header = format!(
+            "Type part of the title of a podcast or episode. Press Enter to jump to the selected one, or Esc to close this window.\n\n> {}",
+            self.finder_query
+        );
+        let mut finder_win = Menu::new(
+            finder_panel,
+            Some(header),
+            LockVec::new(self.finder_results.clone()),
+        );
+        finder_win.redraw();
+        return finder_win;
+    }
+
+    /// Rebuilds the fuzzy finder and moves the cursor to the line at
+    /// `index`.
+    fn refresh_finder_win(&mut self, index: usize) {
+        let mut win = self.make_finder_win();
+        win.activate();
+        win.select_index(index);
+        self.popup = ActivePopup::FinderWin(win);
+    }
+
+    /// Finds the items matching the query typed into the fuzzy finder,
+    /// best matches first. Until something is typed, only the podcasts
+    /// are listed.
+    fn find(&mut self) {
+        let mut matches: Vec<(i64, &FinderItem)> = self
+            .finder_items
+            .iter()
+            .filter(|item| !self.finder_query.is_empty() || item.ep_id.is_none())
+            .filter_map(|item| {
+                fuzzy::score(&self.finder_query, &item.title).map(|score| (score, item))
+            })
+            .collect();
+        // podcasts come before episodes that match as well
+        matches.sort_by_key(|(score, item)| (Reverse(*score), item.ep_id.is_some(), item.id));
+        self.finder_results = matches
+            .into_iter()
+            .take(MAX_FINDER_RESULTS)
+            .map(|(_, item)| item.clone())
+            .collect();
+    }
+
+    /// Create a new window listing the settings of a podcast, and draw
+    /// it to the screen. `header` describes the current settings, e.g.,
+    /// how many episodes pass the download rules. The same window is
+    /// used for other lists of choices, under a different `title`.
+    pub fn spawn_settings_win(
+        &mut self,
+        title: &str,
+        pod_id: i64,
+        settings: Vec<PodcastSetting>,
+        header: String,
+    ) {
+        self.settings_panel = title.to_string();
+        self.settings_pod_id = pod_id;
+        self.settings = settings;
+        self.settings_header = header;
+        self.settings_win = true;
+        self.change_win();
+    }
+
+    /// Create a new Menu holding the podcast settings window.
+    pub fn make_settings_win(&self) -> Menu<PodcastSetting> {
+        // the warning on the unused mut is a function of Rust getting
+        // confused between panel.rs and mock_panel.rs
+        #[allow(unused_mut)]
+        let mut settings_panel = Panel::new(
+            self.settings_panel.clone(),
+            0,
+            self.popup_colors.clone(),
+            self.total_rows - 1,
+            self.total_cols,
+            0,
+            (1, 0, 0, 0),
+        );
+
+        let header = format!(
+            "{} Press {} to choose an option, or {} to close this window.",
+            self.settings_header,
+            self.list_keys(UserAction::Select, Some(1)),
+            self.list_keys(UserAction::Quit, Some(1))
+        );
+        let mut settings_win = Menu::new(
+            settings_panel,
+            Some(header),
+            LockVec::new(self.settings.clone()),
+        );
+        settings_win.redraw();
+        return settings_win;
+    }
+
+    /// Replaces the settings shown in the settings window (e.g., after
+    /// one has been changed), keeping the cursor where it was.
+    pub fn update_settings_win(&mut self, settings: Vec<PodcastSetting>, header: String) {
+        self.settings = settings;
+        self.settings_header = header;
+        if let ActivePopup::SettingsWin(win) = &self.popup {
+            let index = win.get_menu_idx(win.selected);
+            self.refresh_settings_win(index);
+        }
+    }
+
+    /// Rebuilds the settings window and moves the cursor to the line at
+    /// `index`.
+    fn refresh_settings_win(&mut self, index: usize) {
+        let mut win = self.make_settings_win();
+        win.activate();
+        win.select_index(index);
+        self.popup = ActivePopup::SettingsWin(win);
+    }
+
+    /// Returns the podcast ID and the kind of setting currently selected
+    /// in the settings window, if it is open.
+    pub fn selected_setting(&self) -> Option<(i64, SettingKind)> {
+        return match &self.popup {
+            ActivePopup::SettingsWin(menu) => menu
+                .items
+                .map_single_by_index(menu.get_menu_idx(menu.selected), |s| s.kind)
+                .map(|kind| (self.settings_pod_id, kind)),
+            _ => None,
+        };
+    }
+
+    /// Indicates whether the settings window is the one currently
+    /// receiving input.
+    pub fn is_settings_active(&self) -> bool {
+        return self.popup.is_settings_win();
+    }
+
+    /// Create a new window showing the transcript of an episode, and
+    /// draw it to the screen.
+    pub fn spawn_transcript_win(&mut self, title: String, lines: Vec<TranscriptLine>) {
+        self.transcript_panel = "Transcript".to_string();
+        self.transcript_title = title;
+        self.transcript = lines;
+        self.transcript_win = true;
+        self.change_win();
+    }
+
+    /// Create a window listing what has changed since the version of
+    /// shellcaster that was last run, and draw it to the screen. This
+    /// uses the transcript window, so it can be scrolled and searched.
+    pub fn spawn_whats_new_win(&mut self, version: String, entries: Vec<String>) {
+        self.transcript_panel = "What's new".to_string();
+        self.transcript_title = format!(
+            "Shellcaster has been updated to version {version}. Here is what has changed since you last used it:"
+        );
+        self.transcript = entries
+            .into_iter()
+            .map(|text| TranscriptLine {
+                start: None,
+                speaker: None,
+                text: text,
+            })
+            .collect();
+        self.transcript_win = true;
+        self.change_win();
+    }
+
+    /// Create a window showing the most recent lines of the log file,
+    /// newest first. This uses the transcript window, so it can be
+    /// scrolled and searched.
+    pub fn spawn_log_win(&mut self, path: String, lines: Vec<String>) {
+        self.transcript_panel = "Log".to_string();
+        self.transcript_title = format!("Most recent messages in {path}, newest first:");
+        self.transcript = lines
+            .into_iter()
+            .map(|text| TranscriptLine {
+                start: None,
+                speaker: None,
+                text: text,
+            })
+            .collect();
+        self.transcript_win = true;
+        self.change_win();
+    }
+
+    /// Create a window listing the problems found in the config file.
+    /// This uses the transcript window, so it can be scrolled and
+    /// searched.
+    pub fn spawn_config_win(&mut self, lines: Vec<String>) {
+        self.transcript_panel = "Config".to_string();
+        self.transcript_title =
+            "Problems found in config.toml (run `shellcaster check-config` to check it again):"
+                .to_string();
+        self.transcript = lines
+            .into_iter()
+            .map(|text| TranscriptLine {
+                start: None,
+                speaker: None,
+                text: text,
+            })
+            .collect();
+        self.transcript_win = true;
+        self.change_win();
+    }
+
+    /// Create a window showing what syncing all podcasts would change,
+    /// found by checking their feeds without saving anything. This uses
+    /// the transcript window, so it can be scrolled and searched.
+    pub fn spawn_check_win(&mut self, lines: Vec<String>) {
+        self.transcript_panel = "Check".to_string();
+        self.transcript_title = "Changes that syncing would make (nothing has been saved):"
+            .to_string();
+        self.transcript = lines
+            .into_iter()
+            .map(|text| TranscriptLine {
+                start: None,
+                speaker: None,
+                text: text,
+            })
+            .collect();
+        self.transcript_win = true;
+        self.change_win();
+    }
+
+    /// Create a window listing the most recent notifications, newest
+    /// first. This uses the transcript window, so it can be scrolled and
+    /// searched.
+    pub fn spawn_notif_history_win(&mut self, lines: Vec<String>) {
+        self.transcript_panel = "Notifications".to_string();
+        self.transcript_title = "Most recent notifications, newest first:".to_string();
+        self.transcript = lines
+            .into_iter()
+            .map(|text| TranscriptLine {
+                start: None,
+                speaker: None,
+                text: text,
+            })
+            .collect();
+        self.transcript_win = true;
+        self.change_win();
+    }
+
+    /// Create a new window holding the transcript.
+    pub fn make_transcript_win(&self) -> TranscriptWin {
+        let header = format!(
+            "{}\n\nPress {} to search, or {} to close this window.",
+            self.transcript_title,
+            self.list_keys(UserAction::Search, Some(1)),
+            self.list_keys(UserAction::Quit, Some(1))
+        );
+        return TranscriptWin::new(
+            self.transcript_panel.clone(),
+            header,
+            self.transcript.clone(),
+            self.popup_colors.clone(),
+            self.total_rows - 1,
+            self.total_cols,
+        );
+    }
+
+    /// Searches the transcript window for `term`, if it is open;
+    /// returns false if nothing was found.
+    pub fn search_transcript(&mut self, term: &str) -> bool {
+        return match self.popup {
+            ActivePopup::TranscriptWin(ref mut win) => win.search(term),
+            _ => false,
+        };
+    }
+
+    /// Indicates whether the transcript window is the one currently
+    /// receiving input.
+    pub fn is_transcript_active(&self) -> bool {
+        return self.popup.is_transcript_win();
+    }
+
+    /// Create a new statistics window and draw it to the screen.
+    pub fn spawn_stats_win(&mut self, stats: LibraryStats) {
+        self.stats = stats;
+        self.stats_win = true;
+        self.change_win();
+    }
+
+    /// Create a new Panel holding the library statistics. Totals come
+    /// first, then the disk usage of each podcast, largest first, for
+    /// as many podcasts as fit in the window.
+    pub fn make_stats_win(&self) -> Panel {
+        #[allow(unused_mut)]
+        let mut stats_win = Panel::new(
+            "Statistics".to_string(),
+            0,
+            self.popup_colors.clone(),
+            self.total_rows - 1,
+            self.total_cols,
+            0,
+            (1, 1, 1, 1),
+        );
+        stats_win.redraw();
+        let heading = style::ContentStyle::new()
+            .with(self.colors.normal.0)
+            .on(self.colors.normal.1)
+            .attribute(style::Attribute::Underlined);
+
+        let stats = &self.stats;
+        let total_size: u64 = stats.disk_usage.iter().map(|(_, size)| size).sum();
+        let totals = [
+            ("Podcasts:", stats.podcasts.to_string()),
+            ("Episodes:", stats.episodes.to_string()),
+            ("Unplayed:", stats.unplayed.to_string()),
+            (
+                "Downloaded:",
+                format!("{} ({})", stats.downloaded, format_size(total_size)),
+            ),
+            ("Listening time:", format_listened(stats.listened)),
+            ("Added in the last week:", stats.added_last_week.to_string()),
+        ];
+        let label_width = totals.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+
+        let mut row = 0;
+        row = stats_win.write_wrap_line(row, "Library statistics:", Some(heading));
+        row += 1;
+        for (label, value) in totals.iter() {
+            stats_win.write_line(row + 1, format!("{label:<label_width$}  {value}"), None);
+            row += 1;
+        }
+
+        if !stats.disk_usage.is_empty() {
+            row += 1;
+            row = stats_win.write_wrap_line(row + 1, "Disk usage by podcast:", Some(heading));
+            row += 1;
+
+            // leave room for the closing message at the bottom
+            let rows_left = stats_win.get_rows().saturating_sub(row + 4) as usize;
+            let shown = if stats.disk_usage.len() > rows_left {
+                rows_left.saturating_sub(1)
+            } else {
+                stats.disk_usage.len()
+            };
+            for (title, size) in stats.disk_usage.iter().take(shown) {
+                stats_win.write_line(row + 1, format!("{:>10}  {title}", format_size(*size)), None);
+                row += 1;
+            }
+            if shown < stats.disk_usage.len() {
+                let more = stats.disk_usage.len() - shown;
+                stats_win.write_line(row + 1, format!("...and {more} more"), None);
+                row += 1;
+            }
+        }
+
+        let _ = stats_win.write_wrap_line(row + 2, "Press \"q\" to close this window.", None);
+        return stats_win;
+    }
+
+    /// Create a new theme preview window and draw it to the screen.
+    pub fn spawn_theme_win(&mut self, theme_name: &str) {
+        self.theme_name = theme_name.to_string();
+        self.theme_win = true;
+        self.change_win();
+    }
+
+    /// Create a new Panel previewing the current colors, with a sample
+    /// of text in each one, followed by every keybinding as it would be
+    /// written in config.toml. The window is redrawn whenever the
+    /// colors change, so themes can be tried out with it open.
+    pub fn make_theme_win(&self) -> Panel {
+        #[allow(unused_mut)]
+        let mut theme_win = Panel::new(
+            "Theme preview".to_string(),
+            0,
+            self.popup_colors.clone(),
+            self.total_rows - 1,
+            self.total_cols,
+            0,
+            (1, 1, 1, 1),
+        );
+        theme_win.redraw();
+        let heading = style::ContentStyle::new()
+            .with(self.colors.normal.0)
+            .on(self.colors.normal.1)
+            .attribute(style::Attribute::Underlined);
+
+        let colors = &self.colors;
+        let samples = [
+            ("normal", "Episode title", colors.normal),
+            ("bold", "Unplayed episode", colors.bold),
+            ("highlighted_active", "Selected item", colors.highlighted_active),
+            ("highlighted", "Selected, other panel", colors.highlighted),
+            ("error", "Error message", colors.error),
+            ("notification", "Notification", colors.notification()),
+            ("border", "Window border", colors.border()),
+            ("title", "Window title", colors.title()),
+            ("played", "Played episode", colors.played()),
+            ("downloaded", "[D]", colors.downloaded(colors.normal)),
+            ("popup_border", "Popup border", self.popup_colors.border()),
+            ("popup_title", "Popup title", self.popup_colors.title()),
+        ];
+        let labels: Vec<String> = samples
+            .iter()
+            .map(|(name, _, (fg, bg))| {
+                format!(
+                    "{name} ({} on {})",
+                    AppColors::color_to_str(*fg),
+                    AppColors::color_to_str(*bg)
+                )
+            })
+            .collect();
+        let label_width = labels.iter().map(|l| l.len()).max().unwrap_or(0);
+
+        let mut row = 0;
+        row = theme_win.write_wrap_line(
+            row,
+            &format!("Colors (theme: {}):", self.theme_name),
+            Some(heading),
+        );
+        row += 1;
+        for ((name, text, (fg, bg)), label) in samples.iter().zip(labels) {
+            let mut sample_style = style::ContentStyle::new().with(*fg).on(*bg);
+            if *name == "bold" {
+                sample_style = sample_style.attribute(style::Attribute::Bold);
+            } else if *name == "played" && colors.dim_played() {
+                sample_style = sample_style.attribute(style::Attribute::Dim);
+            }
+            theme_win.write_key_value_line(
+                row + 1,
+                format!("{label:>label_width$}"),
+                format!(" {text} "),
+                None,
+                Some(sample_style),
+            );
+            row += 1;
+        }
+
+        // keybindings in the same form as the "keybindings" section of
+        // config.toml, in as many columns as fit
+        row += 1;
+        row = theme_win.write_wrap_line(row + 1, "Keybindings:", Some(heading));
+        row += 1;
+        let mut bindings: Vec<String> = UserAction::all()
+            .into_iter()
+            .map(|action| {
+                let keys = self.keymap.keys_for_action(action);
+                format!("{} = {}", action.config_name(), keys.join(" "))
+            })
+            .collect();
+        for (key, actions) in self.keymap.macros() {
+            let names: Vec<&str> = actions.iter().map(|a| a.config_name()).collect();
+            bindings.push(format!("{key} = {}", names.join(", ")));
+        }
+
+        let col_width = bindings.iter().map(|b| b.chars().count()).max().unwrap_or(0) + 3;
+        let n_cols = (theme_win.get_cols() as usize / col_width).max(1);
+        // leave room for the closing message at the bottom
+        let rows_left = theme_win.get_rows().saturating_sub(row + 4) as usize;
+        let shown = if bindings.len().div_ceil(n_cols) > rows_left {
+            rows_left.saturating_sub(1)
+        } else {
+            rows_left
+        };
+        for line in bindings.chunks(n_cols).take(shown) {
+            let line: String = line.iter().map(|b| format!("{b:<col_width$}")).collect();
+            theme_win.write_line(row + 1, line.trim_end().to_string(), None);
+            row += 1;
+        }
+        let more = bindings.len().saturating_sub(shown * n_cols);
+        if more > 0 {
+            theme_win.write_line(row + 1, format!("...and {more} more"), None);
+            row += 1;
+        }
+
+        let _ = theme_win.write_wrap_line(
+            row + 2,
+            &format!(
+                "Press {} for the next theme, {} to reload config.toml, or {} to close this window.",
+                self.list_keys(UserAction::CycleTheme, Some(1)),
+                self.list_keys(UserAction::ReloadConfig, Some(1)),
+                self.list_keys(UserAction::Quit, Some(1)),
+            ),
+            None,
+        );
+        return theme_win;
+    }
+
+    /// Indicates whether the theme preview window is the one currently
+    /// on the screen.
+    pub fn is_theme_active(&self) -> bool {
+        return self.popup.is_theme_win();
+    }
+
     /// Appends a new episode to the list of new episodes.
     pub fn _add_episodes(&mut self, mut episodes: Vec<NewEpisode>) {
         self.new_episodes.append(&mut episodes);
@@ -354,6 +1403,63 @@ impl<'a> PopupWin<'a> {
         self.change_win();
     }
 
+    /// Gets rid of the digest window.
+    pub fn turn_off_digest_win(&mut self) {
+        self.digest_win = false;
+        self.digest_episodes = Vec::new();
+        self.digest_failed = Vec::new();
+        self.change_win();
+    }
+
+    /// Gets rid of the episode list window.
+    pub fn turn_off_episode_list_win(&mut self) {
+        self.list_win = false;
+        self.list_episodes = Vec::new();
+        self.change_win();
+    }
+
+    /// Gets rid of the chapter window.
+    pub fn turn_off_chapter_win(&mut self) {
+        self.chapter_win = false;
+        self.chapters = Vec::new();
+        self.change_win();
+    }
+
+    /// Gets rid of the fuzzy finder.
+    pub fn turn_off_finder_win(&mut self) {
+        self.finder_win = false;
+        self.finder_items = Vec::new();
+        self.finder_results = Vec::new();
+        self.change_win();
+    }
+
+    /// Gets rid of the settings window.
+    pub fn turn_off_settings_win(&mut self) {
+        self.settings_win = false;
+        self.settings = Vec::new();
+        self.change_win();
+    }
+
+    /// Gets rid of the statistics window.
+    pub fn turn_off_stats_win(&mut self) {
+        self.stats_win = false;
+        self.stats = LibraryStats::default();
+        self.change_win();
+    }
+
+    /// Gets rid of the theme preview window.
+    pub fn turn_off_theme_win(&mut self) {
+        self.theme_win = false;
+        self.change_win();
+    }
+
+    /// Gets rid of the transcript window.
+    pub fn turn_off_transcript_win(&mut self) {
+        self.transcript_win = false;
+        self.transcript = Vec::new();
+        self.change_win();
+    }
+
     /// When there is a change to the active popup window, this should
     /// be called to check for other popup windows that are "in the
     /// queue" -- this lets one popup window appear over top of another
@@ -367,14 +1473,84 @@ impl<'a> PopupWin<'a> {
         if self.help_win && !self.popup.is_help_win() {
             let win = self.make_help_win();
             self.popup = ActivePopup::HelpWin(win);
-        } else if self.download_win && !self.popup.is_download_win() {
+        } else if self.chapter_win && !self.popup.is_chapter_win() {
+            let mut win = self.make_chapter_win();
+            win.activate();
+            self.popup = ActivePopup::ChapterWin(win);
+        } else if self.finder_win && !self.popup.is_finder_win() {
+            let mut win = self.make_finder_win();
+            win.activate();
+            self.popup = ActivePopup::FinderWin(win);
+        } else if self.settings_win
+            && !self.chapter_win
+            && !self.finder_win
+            && !self.popup.is_settings_win()
+        {
+            let mut win = self.make_settings_win();
+            win.activate();
+            self.popup = ActivePopup::SettingsWin(win);
+        } else if self.stats_win && !self.popup.is_stats_win() {
+            let win = self.make_stats_win();
+            self.popup = ActivePopup::StatsWin(win);
+        } else if self.theme_win && !self.stats_win && !self.popup.is_theme_win() {
+            let win = self.make_theme_win();
+            self.popup = ActivePopup::ThemeWin(win);
+        } else if self.transcript_win
+            && !self.chapter_win
+            && !self.finder_win
+            && !self.settings_win
+            && !self.popup.is_transcript_win()
+        {
+            let win = self.make_transcript_win();
+            self.popup = ActivePopup::TranscriptWin(win);
+        } else if self.download_win
+            && !self.chapter_win
+            && !self.finder_win
+            && !self.settings_win
+            && !self.transcript_win
+            && !self.popup.is_download_win()
+        {
             let mut win = self.make_download_win();
             win.activate();
             self.popup = ActivePopup::DownloadWin(win);
+        } else if self.digest_win
+            && !self.download_win
+            && !self.chapter_win
+            && !self.finder_win
+            && !self.settings_win
+            && !self.transcript_win
+            && !self.popup.is_digest_win()
+        {
+            let mut win = self.make_digest_win();
+            win.activate();
+            self.popup = ActivePopup::DigestWin(win);
+        } else if self.list_win
+            && !self.download_win
+            && !self.digest_win
+            && !self.chapter_win
+            && !self.finder_win
+            && !self.settings_win
+            && !self.transcript_win
+            && !self.popup.is_episode_list_win()
+        {
+            let mut win = self.make_episode_list_win();
+            win.activate();
+            self.popup = ActivePopup::EpisodeListWin(win);
         } else if self.welcome_win && !self.popup.is_welcome_win() {
             let win = self.make_welcome_win();
             self.popup = ActivePopup::WelcomeWin(win);
-        } else if !self.help_win && !self.download_win && !self.welcome_win && !self.popup.is_none()
+        } else if !self.help_win
+            && !self.download_win
+            && !self.digest_win
+            && !self.list_win
+            && !self.chapter_win
+            && !self.finder_win
+            && !self.settings_win
+            && !self.transcript_win
+            && !self.stats_win
+            && !self.theme_win
+            && !self.welcome_win
+            && !self.popup.is_none()
         {
             self.popup = ActivePopup::None;
         }
@@ -396,6 +1572,18 @@ impl<'a> PopupWin<'a> {
                     _ => (),
                 }
             }
+            ActivePopup::StatsWin(ref mut _win) => {
+                if let Some(UserAction::Quit) = self.keymap.get_from_input(input) {
+                    self.turn_off_stats_win();
+                }
+            }
+            // switching themes and reloading the config are handled by
+            // the UI
+            ActivePopup::ThemeWin(ref mut _win) => {
+                if let Some(UserAction::Quit) = self.keymap.get_from_input(input) {
+                    self.turn_off_theme_win();
+                }
+            }
             ActivePopup::DownloadWin(ref mut menu) => match self.keymap.get_from_input(input) {
                 Some(UserAction::Down) => menu.scroll(Scroll::Down(1)),
                 Some(UserAction::Up) => menu.scroll(Scroll::Up(1)),
@@ -426,6 +1614,245 @@ impl<'a> PopupWin<'a> {
 
                 Some(_) | None => (),
             },
+            ActivePopup::DigestWin(ref mut menu) => {
+                let item = menu
+                    .items
+                    .map_single_by_index(menu.get_menu_idx(menu.selected), |item| item.clone());
+                match self.keymap.get_from_input(input) {
+                    Some(UserAction::Down) => menu.scroll(Scroll::Down(1)),
+                    Some(UserAction::Up) => menu.scroll(Scroll::Up(1)),
+                    Some(UserAction::PageDown) => menu.scroll(Scroll::Down(self.total_rows - 3)),
+                    Some(UserAction::PageUp) => menu.scroll(Scroll::Up(self.total_rows - 3)),
+                    Some(UserAction::GoTop) => menu.scroll(Scroll::Up(u16::MAX)),
+                    Some(UserAction::GoBot) => menu.scroll(Scroll::Down(u16::MAX)),
+
+                    Some(UserAction::Right) => self.toggle_digest_pod(true),
+                    Some(UserAction::Left) => self.toggle_digest_pod(false),
+
+                    Some(UserAction::Select) | Some(UserAction::Play) => {
+                        if let Some(item) = item {
+                            self.jump_to = Some((item.pod_id, item.ep_id));
+                            self.turn_off_digest_win();
+                        }
+                    }
+
+                    Some(UserAction::Download) => {
+                        if let Some(item) = item {
+                            let eps: Vec<(i64, i64)> = self
+                                .digest_episodes
+                                .iter()
+                                .filter(|ep| {
+                                    ep.pod_id == item.pod_id
+                                        && item.ep_id.is_none_or(|id| id == ep.id)
+                                })
+                                .map(|ep| (ep.pod_id, ep.id))
+                                .collect();
+                            msg = UiMsg::DownloadMulti(eps);
+                        }
+                    }
+
+                    Some(UserAction::Quit) => self.turn_off_digest_win(),
+
+                    Some(_) | None => (),
+                }
+            }
+            ActivePopup::EpisodeListWin(ref mut menu) => {
+                let index = menu.get_menu_idx(menu.selected);
+                let ep = menu
+                    .items
+                    .map_single_by_index(index, |ep| (ep.pod_id, ep.id));
+                let action = self.keymap.get_from_input(input).copied();
+                let marked = menu.marked.clone();
+                match action {
+                    Some(UserAction::Down) => menu.scroll(Scroll::Down(1)),
+                    Some(UserAction::Up) => menu.scroll(Scroll::Up(1)),
+                    Some(UserAction::PageDown) => menu.scroll(Scroll::Down(self.total_rows - 3)),
+                    Some(UserAction::PageUp) => menu.scroll(Scroll::Up(self.total_rows - 3)),
+                    Some(UserAction::GoTop) => menu.scroll(Scroll::Up(u16::MAX)),
+                    Some(UserAction::GoBot) => menu.scroll(Scroll::Down(u16::MAX)),
+                    Some(UserAction::Quit) => self.turn_off_episode_list_win(),
+                    // several episodes in the queue can be marked, to
+                    // move or remove them together
+                    Some(UserAction::ToggleSelect) if self.list_kind == EpisodeList::Queue => {
+                        menu.toggle_mark();
+                        menu.scroll(Scroll::Down(1));
+                        return msg;
+                    }
+                    _ => (),
+                }
+
+                if self.list_kind == EpisodeList::Queue {
+                    match action {
+                        Some(UserAction::MoveUp)
+                        | Some(UserAction::MoveDown)
+                        | Some(UserAction::MoveToTop)
+                        | Some(UserAction::MoveToBottom) => {
+                            let action = action.unwrap();
+                            let cursor =
+                                move_listed(&mut self.list_episodes, &marked, index, action);
+                            let mut win = self.make_episode_list_win();
+                            win.marked = marked;
+                            win.activate();
+                            win.select_index(cursor);
+                            self.popup = ActivePopup::EpisodeListWin(win);
+                            return UiMsg::SetQueue(self.listed_pairs());
+                        }
+                        Some(UserAction::Remove) if !marked.is_empty() => {
+                            self.list_episodes.retain(|ep| !marked.contains(&ep.id));
+                            msg = UiMsg::SetQueue(self.listed_pairs());
+                            if self.list_episodes.is_empty() {
+                                self.turn_off_episode_list_win();
+                            } else {
+                                let index = min(index, self.list_episodes.len() - 1);
+                                self.refresh_episode_list_win(index);
+                            }
+                            return msg;
+                        }
+                        Some(UserAction::RemoveAll) => {
+                            self.turn_off_episode_list_win();
+                            return UiMsg::SetQueue(Vec::new());
+                        }
+                        Some(UserAction::ShuffleQueue) | Some(UserAction::InterleaveQueue) => {
+                            if action == Some(UserAction::ShuffleQueue) {
+                                queue::shuffle(&mut self.list_episodes);
+                            } else {
+                                let episodes = std::mem::take(&mut self.list_episodes);
+                                self.list_episodes = queue::interleave(episodes, |ep| ep.pod_id);
+                            }
+                            self.refresh_episode_list_win(0);
+                            return UiMsg::SetQueue(self.listed_pairs());
+                        }
+                        _ => (),
+                    }
+                }
+
+                // every other action takes the episode out of the list
+                let ep = match ep {
+                    Some(ep) => ep,
+                    None => return msg,
+                };
+                msg = match (self.list_kind, action) {
+                    (_, Some(UserAction::Select)) | (_, Some(UserAction::Play)) => {
+                        UiMsg::Play(ep.0, ep.1)
+                    }
+                    (EpisodeList::Inbox, Some(UserAction::Enqueue)) => {
+                        UiMsg::Triage(TriageAction::Queue, vec![ep])
+                    }
+                    (EpisodeList::Inbox, Some(UserAction::EnqueueNext)) => {
+                        UiMsg::Triage(TriageAction::QueueNext, vec![ep])
+                    }
+                    (EpisodeList::Inbox, Some(UserAction::Download)) => {
+                        UiMsg::Triage(TriageAction::Download, vec![ep])
+                    }
+                    (EpisodeList::Inbox, Some(UserAction::MarkPlayed)) => {
+                        UiMsg::Triage(TriageAction::Seen, vec![ep])
+                    }
+                    (EpisodeList::Inbox, Some(UserAction::Remove)) => {
+                        UiMsg::Triage(TriageAction::Hide, vec![ep])
+                    }
+                    (EpisodeList::Inbox, Some(UserAction::MarkAllPlayed)) => {
+                        let eps = self.list_episodes.iter().map(|ep| (ep.pod_id, ep.id)).collect();
+                        self.turn_off_episode_list_win();
+                        return UiMsg::Triage(TriageAction::Seen, eps);
+                    }
+                    (EpisodeList::Queue, Some(UserAction::Remove)) => UiMsg::Dequeue(ep.0, ep.1),
+                    _ => return msg,
+                };
+                // episodes played from the queue stay in it
+                if !(self.list_kind == EpisodeList::Queue && matches!(msg, UiMsg::Play(..))) {
+                    self.remove_listed_episode(index);
+                }
+            }
+            ActivePopup::ChapterWin(ref mut menu) => match self.keymap.get_from_input(input) {
+                Some(UserAction::Down) => menu.scroll(Scroll::Down(1)),
+                Some(UserAction::Up) => menu.scroll(Scroll::Up(1)),
+                Some(UserAction::PageDown) => menu.scroll(Scroll::Down(self.total_rows - 3)),
+                Some(UserAction::PageUp) => menu.scroll(Scroll::Up(self.total_rows - 3)),
+                Some(UserAction::GoTop) => menu.scroll(Scroll::Up(u16::MAX)),
+                Some(UserAction::GoBot) => menu.scroll(Scroll::Down(u16::MAX)),
+
+                Some(UserAction::Select) | Some(UserAction::Play) => {
+                    if let Some(start) = menu
+                        .items
+                        .map_single_by_index(menu.get_menu_idx(menu.selected), |ch| ch.start)
+                    {
+                        msg = UiMsg::JumpToChapter(self.chapters_ep_id, start);
+                        self.turn_off_chapter_win();
+                    }
+                }
+
+                Some(UserAction::Quit) => self.turn_off_chapter_win(),
+
+                Some(_) | None => (),
+            },
+            // the finder takes typed characters as its query, so it
+            // only uses keys that cannot be typed
+            ActivePopup::FinderWin(ref mut menu) => match input.code {
+                KeyCode::Down => menu.scroll(Scroll::Down(1)),
+                KeyCode::Up => menu.scroll(Scroll::Up(1)),
+                KeyCode::PageDown => menu.scroll(Scroll::Down(self.total_rows - 3)),
+                KeyCode::PageUp => menu.scroll(Scroll::Up(self.total_rows - 3)),
+
+                KeyCode::Enter | KeyCode::Char('\n') => {
+                    if let Some(item) = menu
+                        .items
+                        .map_single_by_index(menu.get_menu_idx(menu.selected), |item| {
+                            (item.pod_id, item.ep_id)
+                        })
+                    {
+                        self.jump_to = Some(item);
+                        self.turn_off_finder_win();
+                    }
+                }
+
+                KeyCode::Esc | KeyCode::Char('\u{1b}') => self.turn_off_finder_win(),
+
+                KeyCode::Backspace | KeyCode::Char('\u{7f}') if !self.finder_query.is_empty() => {
+                    self.finder_query.pop();
+                    self.find();
+                    self.refresh_finder_win(0);
+                }
+
+                KeyCode::Char(c)
+                    if !input.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+                {
+                    self.finder_query.push(c);
+                    self.find();
+                    self.refresh_finder_win(0);
+                }
+
+                _ => (),
+            },
+            ActivePopup::SettingsWin(ref mut menu) => match self.keymap.get_from_input(input) {
+                Some(UserAction::Down) => menu.scroll(Scroll::Down(1)),
+                Some(UserAction::Up) => menu.scroll(Scroll::Up(1)),
+                Some(UserAction::GoTop) => menu.scroll(Scroll::Up(u16::MAX)),
+                Some(UserAction::GoBot) => menu.scroll(Scroll::Down(u16::MAX)),
+
+                // changing a setting needs the notification bar, so it
+                // is handled by the UI
+                Some(UserAction::Quit) => self.turn_off_settings_win(),
+
+                Some(_) | None => (),
+            },
+            ActivePopup::TranscriptWin(ref mut win) => match self.keymap.get_from_input(input) {
+                Some(UserAction::Down) => win.scroll(Scroll::Down(1)),
+                Some(UserAction::Up) => win.scroll(Scroll::Up(1)),
+                Some(UserAction::PageDown) => win.scroll(Scroll::Down(self.total_rows - 3)),
+                Some(UserAction::PageUp) => win.scroll(Scroll::Up(self.total_rows - 3)),
+                Some(UserAction::BigDown) => {
+                    win.scroll(Scroll::Down(self.total_rows / BIG_SCROLL_AMOUNT))
+                },
+                Some(UserAction::BigUp) => {
+                    win.scroll(Scroll::Up(self.total_rows / BIG_SCROLL_AMOUNT))
+                },
+                Some(UserAction::GoTop) => win.scroll(Scroll::Up(u16::MAX)),
+                Some(UserAction::GoBot) => win.scroll(Scroll::Down(u16::MAX)),
+
+                Some(UserAction::Quit) => self.turn_off_transcript_win(),
+
+                Some(_) | None => (),
+            },
             _ => (),
         }
         return msg;
@@ -460,3 +1887,63 @@ impl<'a> PopupWin<'a> {
         };
     }
 }
+
+/// Moves the episodes in `marked` (or, if none are marked, the one at
+/// `index`) up or down by one place, or to the top or bottom of the
+/// list, keeping them in the same order. Returns the new position of the
+/// episode that was at `index`, so the cursor can follow it.
+fn move_listed(
+    episodes: &mut Vec<ListedEpisode>,
+    marked: &HashSet<i64>,
+    index: usize,
+    action: UserAction,
+) -> usize {
+    let current = match episodes.get(index) {
+        Some(ep) => ep.id,
+        None => return index,
+    };
+    let moving = |ep: &ListedEpisode| {
+        if marked.is_empty() {
+            ep.id == current
+        } else {
+            marked.contains(&ep.id)
+        }
+    };
+    match action {
+        UserAction::MoveToTop | UserAction::MoveToBottom => {
+            let (mut chosen, rest): (Vec<_>, Vec<_>) =
+                episodes.drain(..).partition(|ep| moving(ep));
+            if action == UserAction::MoveToTop {
+                chosen.extend(rest);
+                *episodes = chosen;
+            } else {
+                *episodes = rest;
+                episodes.extend(chosen);
+            }
+        }
+        // each moving episode swaps places with the one before (or
+        // after) it, unless that one is moving too
+        UserAction::MoveUp => {
+            for i in 1..episodes.len() {
+                if moving(&episodes[i]) && !moving(&episodes[i - 1]) {
+                    episodes.swap(i - 1, i);
+                }
+            }
+        }
+        UserAction::MoveDown => {
+            for i in (1..episodes.len()).rev() {
+                if moving(&episodes[i - 1]) && !moving(&episodes[i]) {
+                    episodes.swap(i - 1, i);
+                }
+            }
+        }
+        _ => (),
+    }
+    return episodes.iter().position(|ep| ep.id == current).unwrap_or(index);
+}
+
+/// Formats a number of seconds as hours and minutes, e.g., "12h 05m".
+fn format_listened(seconds: i64) -> String {
+    let minutes = seconds / 60;
+    return format!("{}h {:02}m", minutes / 60, minutes % 60);
+}
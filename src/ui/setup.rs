@@ -0,0 +1,164 @@
+use super::colors;
+use crate::config::Config;
+use crate::types::*;
+
+/// Holds the choices made in the setup window, which is shown the first
+/// time shellcaster runs, or whenever the user opens it. The changes
+/// are written to config.toml once the user is done.
+#[derive(Debug, Clone)]
+pub struct Setup {
+    first_run: bool,
+    current_download_path: String,
+    download_path: Option<Option<String>>,
+    players: Vec<String>,
+    current_play_command: String,
+    play_command: String,
+    themes: Vec<String>,
+    current_theme: Option<String>,
+    theme: Option<String>,
+    pub imported: Option<String>,
+}
+
+impl Setup {
+    /// Starts off with the current settings. On the `first_run`, the
+    /// first of the `players` that were found (see
+    /// `play_file::find_players()`) is picked to play episodes with
+    /// instead. The themes to choose from are the installed ones, along
+    /// with the ones that come with shellcaster.
+    pub fn new(config: &Config, players: Vec<String>, first_run: bool) -> Self {
+        let mut themes = colors::list_themes(&config.themes_dir);
+        for (name, _) in colors::BUNDLED_THEMES.iter() {
+            if !themes.iter().any(|theme| theme == name) {
+                themes.push(name.to_string());
+            }
+        }
+        themes.sort();
+        let play_command = match players.first() {
+            Some(player) if first_run => player.clone(),
+            _ => config.play_command.clone(),
+        };
+        return Self {
+            first_run: first_run,
+            current_download_path: config.download_path.to_string_lossy().to_string(),
+            download_path: None,
+            players: players,
+            current_play_command: config.play_command.clone(),
+            play_command: play_command,
+            themes: themes,
+            current_theme: config.theme.clone(),
+            theme: config.theme.clone(),
+            imported: None,
+        };
+    }
+
+    /// Lists the lines of the setup window, along with the text that
+    /// explains it.
+    pub fn settings(&self) -> (Vec<PodcastSetting>, String) {
+        let download_path = match &self.download_path {
+            Some(Some(path)) => path.clone(),
+            Some(None) => "(default)".to_string(),
+            None if self.first_run => format!("{} (default)", self.current_download_path),
+            None => self.current_download_path.clone(),
+        };
+        let player = match self.players.len() {
+            0 => format!("{} (no players found)", self.play_command),
+            _ => self.play_command.clone(),
+        };
+        let lines = [
+            (SetupStep::DownloadDir, "Download folder", download_path),
+            (SetupStep::Player, "Player", player),
+            (
+                SetupStep::Import,
+                "Import podcasts from an OPML file",
+                self.imported.clone().unwrap_or_default(),
+            ),
+            (
+                SetupStep::Theme,
+                "Theme",
+                self.theme.clone().unwrap_or_else(|| "default".to_string()),
+            ),
+            (SetupStep::Finish, "Save these settings", String::new()),
+        ];
+        let settings = lines
+            .into_iter()
+            .enumerate()
+            .map(|(i, (step, label, value))| PodcastSetting {
+                id: i as i64,
+                kind: SettingKind::Setup(step),
+                label: label.to_string(),
+                value: value,
+            })
+            .collect();
+        let header = if self.first_run {
+            "Welcome to shellcaster! Choose a few settings to start with. They are saved to config.toml, where these and all other settings can be changed later; nothing is saved if this window is closed first."
+        } else {
+            "Choose the settings to change. They are saved to config.toml; nothing is saved if this window is closed first."
+        };
+        return (settings, header.to_string());
+    }
+
+    /// Sets the download folder typed in by the user. A blank one goes
+    /// back to the default folder.
+    pub fn set_download_path(&mut self, path: &str) {
+        let path = path.trim();
+        self.download_path = Some((!path.is_empty()).then(|| path.to_string()));
+    }
+
+    /// Switches to the next of the players that were found. Returns
+    /// false after the last one, or if none were found, when the user
+    /// should be asked for a play command instead.
+    pub fn next_player(&mut self) -> bool {
+        let next = match self.players.iter().position(|p| *p == self.play_command) {
+            Some(idx) => self.players.get(idx + 1),
+            None => None,
+        };
+        return match next {
+            Some(player) => {
+                self.play_command = player.clone();
+                true
+            }
+            None => false,
+        };
+    }
+
+    /// Sets the play command typed in by the user. A blank one goes
+    /// back to the first player that was found, if any.
+    pub fn set_play_command(&mut self, command: &str) {
+        if !command.trim().is_empty() {
+            self.play_command = command.trim().to_string();
+        } else if let Some(player) = self.players.first() {
+            self.play_command = player.clone();
+        }
+    }
+
+    /// Switches to the next theme, in alphabetical order, after which
+    /// the default colors are used again. Returns the new theme.
+    pub fn next_theme(&mut self) -> Option<String> {
+        let next = match &self.theme {
+            Some(current) => match self.themes.iter().position(|t| t == current) {
+                Some(idx) => self.themes.get(idx + 1),
+                None => self.themes.first(),
+            },
+            None => self.themes.first(),
+        };
+        self.theme = next.cloned();
+        return self.theme.clone();
+    }
+
+    /// Returns the settings that were changed, to write to config.toml
+    /// with `config_writer::save_settings()`.
+    pub fn config_settings(&self) -> Vec<(String, Option<String>)> {
+        let quote = |value: &str| toml::Value::String(value.to_string()).to_string();
+        let mut settings = Vec::new();
+        if let Some(path) = &self.download_path {
+            settings.push(("download_path".to_string(), path.as_deref().map(quote)));
+        }
+        if self.play_command != self.current_play_command {
+            settings.push(("play_command".to_string(), Some(quote(&self.play_command))));
+        }
+        if self.theme != self.current_theme {
+            settings.push(("theme".to_string(), self.theme.as_deref().map(quote)));
+        }
+        return settings;
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+
+use super::{ActivePanel, Menu};
+use crate::types::*;
+
+/// The views that the main screen can be switched between. The library
+/// shows the podcast and episode menus; each of the other tabs lists
+/// episodes from across all podcasts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tab {
+    Library,
+    New,
+    Queue,
+    Downloads,
+    Search,
+}
+
+impl Tab {
+    /// All of the tabs, in the order they are shown in the tab bar.
+    pub const ALL: [Tab; 5] = [Tab::Library, Tab::New, Tab::Queue, Tab::Downloads, Tab::Search];
+
+    /// Returns the position of the tab in the tab bar.
+    pub fn index(self) -> usize {
+        return self as usize;
+    }
+
+    /// Returns the name of the tab, as shown in the tab bar.
+    pub fn name(self) -> &'static str {
+        return match self {
+            Tab::Library => "Library",
+            Tab::New => "New",
+            Tab::Queue => "Queue",
+            Tab::Downloads => "Downloads",
+            Tab::Search => "Search",
+        };
+    }
+
+    /// Returns the tab after this one in the tab bar, going back to the
+    /// first one after the last.
+    pub fn next(self) -> Tab {
+        return Tab::ALL[(self.index() + 1) % Tab::ALL.len()];
+    }
+}
+
+/// One of the tabs, with the panel that was active when the user last
+/// left it. The tabs other than the library have a menu listing their
+/// episodes, which keeps its own cursor and scroll position.
+#[derive(Debug)]
+pub struct TabView {
+    pub tab: Tab,
+    pub active_panel: ActivePanel,
+    pub list: Option<Menu<ListedEpisode>>,
+}
+
+/// Returns the labels for the tab bar, each with whether it is the
+/// `current` tab. The tabs are numbered, and only the current one is
+/// named if the names of all of them do not fit in `width` columns.
+pub fn tab_bar(current: Tab, width: usize) -> Vec<(String, bool)> {
+    let labels: Vec<(String, bool)> = Tab::ALL
+        .iter()
+        .map(|tab| (format!("{} {}", tab.index() + 1, tab.name()), *tab == current))
+        .collect();
+    let len: usize = labels.iter().map(|(label, _)| label.len() + 1).sum();
+    if len <= width {
+        return labels;
+    }
+    return Tab::ALL
+        .iter()
+        .zip(labels)
+        .map(|(tab, (label, is_current))| match is_current {
+            true => (label, true),
+            false => ((tab.index() + 1).to_string(), false),
+        })
+        .collect();
+}
+
+/// Gathers the episodes listed in one of the tabs other than the
+/// library, from the podcasts in `podcasts`: the new episodes, the ones
+/// in the play queue (whose IDs are in `queue`, in order), the
+/// downloaded ones, or the ones with the `search` term in their title or
+/// that of their podcast. Apart from the queue, the most recently
+/// published are first.
+pub fn list_episodes(
+    tab: Tab,
+    podcasts: &LockVec<Podcast>,
+    queue: &[i64],
+    search: Option<&str>,
+) -> Vec<ListedEpisode> {
+    let search = search.map(|term| term.to_lowercase());
+    let mut episodes = Vec::new();
+    for pod in podcasts.borrow_map().values() {
+        let pod_matches = search
+            .as_ref()
+            .is_some_and(|term| pod.display_title().to_lowercase().contains(term));
+        for ep in pod.episodes.borrow_map().values() {
+            let listed = match tab {
+                Tab::Library => false,
+                Tab::New => ep.new,
+                Tab::Queue => queue.contains(&ep.id),
+                Tab::Downloads => ep.path.is_some(),
+                Tab::Search => {
+                    pod_matches
+                        || search
+                            .as_ref()
+                            .is_some_and(|term| ep.display_title().to_lowercase().contains(term))
+                }
+            };
+            if listed {
+                episodes.push(ListedEpisode::new(pod, ep));
+            }
+        }
+    }
+
+    if tab == Tab::Queue {
+        let positions: HashMap<i64, usize> =
+            queue.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+        episodes.sort_by_key(|ep| positions.get(&ep.id).copied());
+    } else {
+        episodes.sort_by_key(|ep| std::cmp::Reverse(ep.pubdate));
+    }
+    return episodes;
+}
@@ -0,0 +1,217 @@
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use crossterm::event::{self, Event};
+use crossterm::terminal;
+use lazy_static::lazy_static;
+
+lazy_static! {
+    /// Input events are read on a thread of their own and passed along
+    /// a channel, so that the UI can block until there is either input
+    /// to handle or a message from the main controller.
+    static ref INPUT: Input = Input::start();
+}
+
+/// How often the input thread checks whether it has been paused.
+const PAUSE_CHECK: Duration = Duration::from_millis(50);
+
+/// Set while another program (e.g., a shell) is using the terminal, so
+/// that the input thread does not take its input.
+static PAUSED: AtomicBool = AtomicBool::new(false);
+
+/// Set by the input thread once it has seen that it is paused, and has
+/// stopped reading input.
+static PARKED: AtomicBool = AtomicBool::new(false);
+
+/// Set once the input thread has exited (e.g., after an error reading
+/// input), so that nothing waits for it to park.
+static STOPPED: AtomicBool = AtomicBool::new(false);
+
+/// The longest `pause_input()` waits for the input thread to park. The
+/// thread checks every `PAUSE_CHECK`, so this only runs out if it is
+/// stuck.
+const PAUSE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The receiving end of the input thread's channel. A `None` sent
+/// along the channel carries no event, and only wakes up the UI.
+struct Input {
+    queue: Mutex<InputQueue>,
+    tx: Mutex<Sender<Option<Event>>>,
+}
+
+struct InputQueue {
+    rx: Receiver<Option<Event>>,
+    next: Option<Event>,
+}
+
+impl Input {
+    /// Spawns the thread that reads input events. This should only
+    /// happen once the terminal is in raw mode.
+    fn start() -> Self {
+        let (tx, rx) = mpsc::channel();
+        let tx_events = tx.clone();
+        thread::spawn(move || {
+            loop {
+                if PAUSED.load(Ordering::SeqCst) {
+                    PARKED.store(true, Ordering::SeqCst);
+                    thread::sleep(PAUSE_CHECK);
+                    continue;
+                }
+                match event::poll(PAUSE_CHECK) {
+                    Ok(false) => (),
+                    Ok(true) => match event::read() {
+                        Ok(event) => {
+                            if tx_events.send(Some(event)).is_err() {
+                                break;
+                            }
+                        }
+                        Err(_) => break,
+                    },
+                    Err(_) => break,
+                }
+            }
+            STOPPED.store(true, Ordering::SeqCst);
+        });
+        return Self {
+            queue: Mutex::new(InputQueue { rx: rx, next: None }),
+            tx: Mutex::new(tx),
+        };
+    }
+}
+
+/// Returns the writer that the UI draws to.
+pub fn stdout() -> io::Stdout {
+    return io::stdout();
+}
+
+/// Returns the size of the terminal, as (columns, rows).
+pub fn size() -> (u16, u16) {
+    return terminal::size().expect("Can't get terminal size");
+}
+
+pub fn enable_raw_mode() {
+    terminal::enable_raw_mode().expect("Terminal can't run in raw mode.");
+}
+
+pub fn disable_raw_mode() {
+    let _ = terminal::disable_raw_mode();
+}
+
+/// Stops reading input, waiting until the input thread has stopped, so
+/// that another program can be given the terminal. This gives up after
+/// `PAUSE_TIMEOUT`, or straight away if the input thread has exited.
+pub fn pause_input() {
+    PARKED.store(false, Ordering::SeqCst);
+    PAUSED.store(true, Ordering::SeqCst);
+    lazy_static::initialize(&INPUT);
+    let start = std::time::Instant::now();
+    while !PARKED.load(Ordering::SeqCst)
+        && !STOPPED.load(Ordering::SeqCst)
+        && start.elapsed() < PAUSE_TIMEOUT
+    {
+        thread::sleep(Duration::from_millis(5));
+    }
+}
+
+/// Starts reading input again after `pause_input()`.
+pub fn resume_input() {
+    PAUSED.store(false, Ordering::SeqCst);
+}
+
+/// While another program has the terminal, Ctrl-C should stop only that
+/// program. Rather than ignoring the signal, a handler that does nothing
+/// is used, as programs started from here get back the default handler
+/// (where an ignored signal would stay ignored).
+pub fn catch_interrupts(catch: bool) {
+    #[cfg(unix)]
+    {
+        extern "C" fn do_nothing(_: libc::c_int) {}
+        let handler = if catch {
+            do_nothing as extern "C" fn(libc::c_int) as libc::sighandler_t
+        } else {
+            libc::SIG_DFL
+        };
+        // SAFETY: the handler does nothing, so it is safe to run at any
+        // point
+        unsafe {
+            libc::signal(libc::SIGINT, handler);
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = catch;
+}
+
+/// Waits for Enter to be pressed, once the terminal has been put back to
+/// its prior settings.
+pub fn wait_for_enter() {
+    let _ = io::stdin().read_line(&mut String::new());
+}
+
+/// Stops the program as if Ctrl-Z had been pressed in a shell, returning
+/// once it is continued (e.g., with `fg`). The terminal should be
+/// restored first. This does nothing on systems without Unix job
+/// control.
+pub fn suspend() {
+    // SAFETY: raise() is always safe to call; the default action for
+    // SIGTSTP stops the process until it receives SIGCONT
+    #[cfg(unix)]
+    unsafe {
+        libc::raise(libc::SIGTSTP);
+    }
+}
+
+/// Indicates whether there is an input event waiting to be read.
+pub fn poll() -> bool {
+    let mut queue = INPUT.queue.lock().unwrap();
+    while queue.next.is_none() {
+        match queue.rx.try_recv() {
+            Ok(Some(event)) => queue.next = Some(event),
+            Ok(None) => (),
+            Err(_) => break,
+        }
+    }
+    return queue.next.is_some();
+}
+
+/// Waits for the next input event.
+pub fn read() -> Event {
+    let mut queue = INPUT.queue.lock().unwrap();
+    if let Some(event) = queue.next.take() {
+        return event;
+    }
+    loop {
+        if let Some(event) = queue.rx.recv().expect("Can't read inputs") {
+            return event;
+        }
+    }
+}
+
+/// Blocks until there is an input event waiting, the UI is woken up
+/// (see `waker()`), or the timeout runs out. With no timeout, this
+/// waits indefinitely.
+pub fn wait(timeout: Option<Duration>) {
+    let mut queue = INPUT.queue.lock().unwrap();
+    if queue.next.is_some() {
+        return;
+    }
+    let received = match timeout {
+        Some(timeout) => queue.rx.recv_timeout(timeout).ok(),
+        None => queue.rx.recv().ok(),
+    };
+    if let Some(Some(event)) = received {
+        queue.next = Some(event);
+    }
+}
+
+/// Returns a function that can be called from any thread to wake the
+/// UI up from `wait()`.
+pub fn waker() -> impl Fn() + Send + 'static {
+    let tx = INPUT.tx.lock().unwrap().clone();
+    return move || {
+        let _ = tx.send(None);
+    };
+}
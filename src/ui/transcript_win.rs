@@ -0,0 +1,234 @@
+use std::rc::Rc;
+
+use crossterm::style::{self, Stylize};
+
+use super::panel::Panel;
+use super::AppColors;
+use super::Scroll;
+use crate::transcript::TranscriptLine;
+
+/// Full-screen popup window showing an episode's transcript, which can
+/// be scrolled and searched.
+#[derive(Debug)]
+pub struct TranscriptWin {
+    pub panel: Panel,
+    pub header: String,
+    pub lines: Vec<TranscriptLine>,
+    pub content: Vec<String>, // transcript wrapped to fit the window
+    pub top_row: u16,         // top row of text shown in window
+    pub search: Option<String>,
+    pub found_row: Option<usize>,
+}
+
+impl TranscriptWin {
+    /// Creates a new transcript window and draws it to the screen.
+    pub fn new(
+        title: String,
+        header: String,
+        lines: Vec<TranscriptLine>,
+        colors: Rc<AppColors>,
+        n_row: u16,
+        n_col: u16,
+    ) -> Self {
+        let panel = Panel::new(title, 0, colors, n_row, n_col, 0, (1, 1, 0, 1));
+        let mut win = Self {
+            panel: panel,
+            header: header,
+            lines: lines,
+            content: Vec::new(),
+            top_row: 0,
+            search: None,
+            found_row: None,
+        };
+        win.stringify_content();
+        win.redraw();
+        return win;
+    }
+
+    /// Redraws the window, with the text starting at the current top
+    /// row.
+    pub fn redraw(&mut self) {
+        self.panel.redraw();
+        self.write_content();
+    }
+
+    /// Format the transcript to fit the window as currently sized. The
+    /// header takes up the first lines, and each paragraph is followed
+    /// by a blank line.
+    fn stringify_content(&mut self) {
+        let num_cols = self.panel.get_cols() as usize;
+        self.content.clear();
+        for line in textwrap::wrap(&self.header, num_cols) {
+            self.content.push(line.to_string());
+        }
+        self.content.push(String::new());
+
+        for line in self.lines.iter() {
+            let mut prefix = String::new();
+            if let Some(start) = line.start {
+                let mut seconds = start as i64;
+                let hours = seconds / 3600;
+                seconds -= hours * 3600;
+                let minutes = seconds / 60;
+                seconds -= minutes * 60;
+                prefix = format!("[{hours:02}:{minutes:02}:{seconds:02}] ");
+            }
+            if let Some(speaker) = &line.speaker {
+                prefix = format!("{prefix}{speaker}: ");
+            }
+            let text = format!("{prefix}{}", line.text);
+            for wrapped in textwrap::wrap(&text, num_cols) {
+                self.content.push(wrapped.to_string());
+            }
+            self.content.push(String::new());
+        }
+    }
+
+    /// Writes the visible part of the transcript to the screen, with
+    /// the line holding the current search result highlighted.
+    fn write_content(&mut self) {
+        self.panel.clear_inner();
+        let n_row = self.panel.get_rows() as usize;
+        let highlight = style::ContentStyle::new()
+            .with(self.panel.colors.highlighted_active.0)
+            .on(self.panel.colors.highlighted_active.1);
+        for row in 0..n_row {
+            let index = self.top_row as usize + row;
+            match self.content.get(index) {
+                Some(line) => {
+                    let style = if self.found_row == Some(index) {
+                        Some(highlight)
+                    } else {
+                        None
+                    };
+                    self.panel.write_line(row as u16, line.clone(), style);
+                }
+                None => break,
+            }
+        }
+    }
+
+    /// Updates window size.
+    pub fn resize(&mut self, n_row: u16, n_col: u16) {
+        self.panel.resize(n_row, n_col, 0);
+        self.stringify_content();
+        self.found_row = None;
+        self.top_row = self.top_row.min(self.max_top_row());
+        self.redraw();
+    }
+
+    /// Returns the highest value that `top_row` can take, where the end
+    /// of the transcript is at the bottom of the window.
+    fn max_top_row(&self) -> u16 {
+        let n_row = self.panel.get_rows() as usize;
+        return self.content.len().saturating_sub(n_row) as u16;
+    }
+
+    /// Scrolls the transcript up or down by the given number of lines.
+    pub fn scroll(&mut self, lines: Scroll) {
+        let old_top_row = self.top_row;
+        self.top_row = match lines {
+            Scroll::Up(v) => self.top_row.saturating_sub(v),
+            Scroll::Down(v) => self.top_row.saturating_add(v).min(self.max_top_row()),
+        };
+        if self.top_row != old_top_row {
+            self.write_content();
+        }
+    }
+
+    /// Moves to the next line containing `term` (ignoring case), after
+    /// the previous search result, wrapping around to the start of the
+    /// transcript if necessary. An empty `term` repeats the previous
+    /// search. Returns false if the term was not found.
+    pub fn search(&mut self, term: &str) -> bool {
+        if !term.is_empty() {
+            self.search = Some(term.to_lowercase());
+            self.found_row = None;
+        }
+        let term = match &self.search {
+            Some(term) => term.clone(),
+            None => return false,
+        };
+
+        let start = match self.found_row {
+            Some(row) => row + 1,
+            None => self.top_row as usize,
+        };
+        let total = self.content.len();
+        let found = (0..total)
+            .map(|i| (start + i) % total)
+            .find(|i| self.content[*i].to_lowercase().contains(&term));
+
+        self.found_row = found;
+        if let Some(row) = found {
+            // keep the result in view, a few lines from the top
+            let n_row = self.panel.get_rows() as usize;
+            if row < self.top_row as usize || row >= self.top_row as usize + n_row {
+                self.top_row = (row.saturating_sub(2) as u16).min(self.max_top_row());
+            }
+        }
+        self.write_content();
+        return found.is_some();
+    }
+}
+
+
+// TESTS -----------------------------------------------------------------
+// these read back what was drawn to the mock panel, which is replaced
+// by the real one with the "headless" feature
+#[cfg(all(test, not(feature = "headless")))]
+mod tests {
+    use super::*;
+
+    fn create_win(n_row: u16) -> TranscriptWin {
+        let colors = Rc::new(AppColors::default());
+        let lines = (0..10)
+            .map(|i| TranscriptLine {
+                start: Some(i as f64 * 65.0),
+                speaker: None,
+                text: format!("Paragraph number {i}"),
+            })
+            .collect();
+        return TranscriptWin::new(
+            "Transcript".to_string(),
+            "Header".to_string(),
+            lines,
+            colors,
+            n_row,
+            50,
+        );
+    }
+
+    #[test]
+    fn transcript_lines() {
+        let win = create_win(10);
+        assert_eq!(win.panel.get_row(0), "Header");
+        assert_eq!(win.panel.get_row(2), "[00:00:00] Paragraph number 0");
+        assert_eq!(win.panel.get_row(4), "[00:01:05] Paragraph number 1");
+    }
+
+    #[test]
+    fn transcript_scroll() {
+        let mut win = create_win(10);
+        win.scroll(Scroll::Down(u16::MAX));
+        let n_row = win.panel.get_rows() as usize;
+        assert_eq!(win.top_row as usize, win.content.len() - n_row);
+        win.scroll(Scroll::Up(u16::MAX));
+        assert_eq!(win.top_row, 0);
+    }
+
+    #[test]
+    fn transcript_search() {
+        let mut win = create_win(10);
+        assert!(win.search("NUMBER 8"));
+        assert_eq!(win.content[win.found_row.unwrap()], "[00:08:40] Paragraph number 8");
+        assert!(win.top_row > 0);
+
+        // searching again wraps around to the same line
+        let found = win.found_row;
+        assert!(win.search(""));
+        assert_eq!(win.found_row, found);
+
+        assert!(!win.search("missing"));
+    }
+}
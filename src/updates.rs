@@ -0,0 +1,80 @@
+use std::sync::mpsc::Sender;
+use std::thread;
+
+use anyhow::{anyhow, Result};
+use semver::Version;
+use serde::Deserialize;
+
+use crate::http;
+use crate::types::Message;
+
+/// GitHub API endpoint for the latest release. Nothing is sent apart
+/// from the request itself; the reply is only compared with the
+/// current version.
+const RELEASES_URL: &str = "https://api.github.com/repos/luvchurchill/shellcaster/releases/latest";
+
+/// Enum used for communicating back to the main controller when a
+/// newer version has been released.
+#[derive(Debug)]
+pub enum UpdateMsg {
+    Available(Version),
+}
+
+#[derive(Debug, Deserialize)]
+struct Release {
+    tag_name: String,
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    prerelease: bool,
+}
+
+/// Spawns a thread that checks whether a newer version has been
+/// released, and lets the main controller know if so. Failures (e.g.,
+/// being offline) are silently ignored.
+pub fn spawn_check(current: Version, tx_to_main: Sender<Message>) {
+    thread::spawn(move || {
+        if let Ok(Some(version)) = check(&current) {
+            let _ = tx_to_main.send(Message::Update(UpdateMsg::Available(version)));
+        }
+    });
+}
+
+/// Asks the GitHub API for the latest release, returning its version if
+/// it is newer than `current`.
+fn check(current: &Version) -> Result<Option<Version>> {
+    let text = http::BLOCKING_CLIENT
+        .get(RELEASES_URL)
+        .header("Accept", "application/vnd.github+json")
+        .send()?
+        .error_for_status()?
+        .text()?;
+    let release: Release = serde_json::from_str(&text)?;
+    if release.draft || release.prerelease {
+        return Ok(None);
+    }
+    let latest = parse_tag(&release.tag_name)
+        .ok_or_else(|| anyhow!("Invalid release tag: {}", release.tag_name))?;
+    return Ok(if latest > *current { Some(latest) } else { None });
+}
+
+/// Parses a release tag like "v2.1.0" into a version.
+fn parse_tag(tag: &str) -> Option<Version> {
+    let tag = tag.trim();
+    return Version::parse(tag.strip_prefix('v').unwrap_or(tag)).ok();
+}
+
+
+// TESTS -----------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn release_tags() {
+        assert_eq!(parse_tag("v2.1.0"), Some(Version::new(2, 1, 0)));
+        assert_eq!(parse_tag("2.0.10"), Some(Version::new(2, 0, 10)));
+        assert_eq!(parse_tag("latest"), None);
+        assert!(parse_tag("v2.0.10").unwrap() > parse_tag("v2.0.9").unwrap());
+    }
+}
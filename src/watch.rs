@@ -0,0 +1,137 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::feeds::PodcastFeed;
+use crate::library;
+use crate::types::Message;
+
+/// Name of the subdirectory where processed files are moved to.
+const ARCHIVE_DIR: &str = "archive";
+
+/// Enum used for communicating back to the main controller when files
+/// are found in the watch folder.
+#[derive(Debug)]
+pub enum WatchMsg {
+    Import(String, Vec<PodcastFeed>),
+    Error(String),
+}
+
+/// Spawns a thread that periodically checks `dir` for `.opml`, `.json`
+/// (shellcaster library) or `.txt` files. Any feeds found in those files are sent back to the main
+/// controller, and the files are moved into an "archive" subdirectory
+/// so they are not imported twice. The thread stops once the main
+/// controller is no longer listening.
+pub fn spawn_watcher(dir: PathBuf, tx_to_main: Sender<Message>) {
+    thread::spawn(move || loop {
+        for msg in check_dir(&dir) {
+            if tx_to_main.send(Message::Watch(msg)).is_err() {
+                return;
+            }
+        }
+        thread::sleep(Duration::from_millis(crate::config::WATCH_DIR_INTERVAL));
+    });
+}
+
+/// Checks the watch directory once, returning a message for each file
+/// that was processed.
+fn check_dir(dir: &Path) -> Vec<WatchMsg> {
+    let mut messages = Vec::new();
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return messages,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+        let ext = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase());
+        let feeds = match ext.as_deref() {
+            Some("opml") | Some("json") => read_feed_file(&path),
+            Some("txt") => read_url_list(&path),
+            _ => continue,
+        };
+
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        match feeds {
+            Ok(feeds) => messages.push(WatchMsg::Import(file_name.clone(), feeds)),
+            Err(_) => messages.push(WatchMsg::Error(format!(
+                "Could not read podcasts from {file_name}"
+            ))),
+        }
+        if archive_file(dir, &path).is_err() {
+            messages.push(WatchMsg::Error(format!("Could not archive {file_name}")));
+        }
+    }
+    return messages;
+}
+
+/// Reads a list of podcast feeds from an OPML or library file.
+fn read_feed_file(path: &Path) -> Result<Vec<PodcastFeed>> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("Could not read file: {}", path.to_string_lossy()))?;
+    return library::import_any(text);
+}
+
+/// Reads a list of podcast feeds from a text file with one URL per
+/// line. Blank lines and lines starting with "#" are ignored.
+fn read_url_list(path: &Path) -> Result<Vec<PodcastFeed>> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("Could not read file: {}", path.to_string_lossy()))?;
+    return Ok(parse_url_list(&text));
+}
+
+/// Parses the contents of a text file with one URL per line.
+fn parse_url_list(text: &str) -> Vec<PodcastFeed> {
+    return text
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|url| PodcastFeed::new(None, url.to_string(), None))
+        .collect();
+}
+
+/// Moves a processed file into the archive subdirectory.
+fn archive_file(dir: &Path, path: &Path) -> Result<()> {
+    let mut archive = dir.to_path_buf();
+    archive.push(ARCHIVE_DIR);
+    fs::create_dir_all(&archive)?;
+
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("Invalid file name"))?;
+    archive.push(file_name);
+
+    // avoid overwriting a file with the same name that was archived
+    // previously
+    if archive.exists() {
+        let stamp = chrono::Utc::now().format("%Y%m%d_%H%M%S");
+        archive.set_file_name(format!("{}.{stamp}", file_name.to_string_lossy()));
+    }
+    fs::rename(path, &archive)?;
+    return Ok(());
+}
+
+
+// TESTS -----------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn url_list() {
+        let text = "https://example.com/feed.xml\n\n# a comment\n  https://example.org/rss  \n";
+        let feeds = parse_url_list(text);
+        assert_eq!(feeds.len(), 2);
+        assert_eq!(feeds[0].url, "https://example.com/feed.xml");
+        assert_eq!(feeds[1].url, "https://example.org/rss");
+    }
+}
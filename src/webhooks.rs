@@ -0,0 +1,336 @@
+use std::sync::mpsc::Sender;
+use std::thread;
+
+use anyhow::{anyhow, Result};
+
+use crate::encoding::percent_encode;
+use crate::http;
+use crate::types::{Message, NewEpisode};
+
+/// Most episodes listed in a single "new episodes" message; any others
+/// are only counted.
+const MAX_LISTED: usize = 10;
+
+/// Events that can be sent to a webhook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookEvent {
+    NewEpisodes,
+    Download,
+    SyncFailed,
+}
+
+impl WebhookEvent {
+    /// Returns the name used for this event in the config file and in
+    /// the {event} placeholder.
+    pub fn config_name(&self) -> &'static str {
+        return match self {
+            WebhookEvent::NewEpisodes => "new_episodes",
+            WebhookEvent::Download => "download",
+            WebhookEvent::SyncFailed => "sync_failed",
+        };
+    }
+
+    /// Finds the event with the given config name.
+    pub fn from_config(name: &str) -> Option<Self> {
+        return [
+            WebhookEvent::NewEpisodes,
+            WebhookEvent::Download,
+            WebhookEvent::SyncFailed,
+        ]
+        .into_iter()
+        .find(|event| event.config_name() == name);
+    }
+}
+
+/// A URL that is sent a request when certain events happen. The URL
+/// and the body can contain placeholders that are filled in with
+/// details about the event (see `Notice`). Without a body, the message
+/// is sent as plain text, which suits services like ntfy.
+#[derive(Debug, Clone)]
+pub struct Webhook {
+    pub url: String,
+    pub body: Option<String>,
+    pub events: Vec<WebhookEvent>,
+}
+
+impl Webhook {
+    /// Creates a webhook from the config file. If no events are listed,
+    /// it is sent all of them.
+    pub fn new(url: String, body: Option<String>, events: Option<Vec<String>>) -> Result<Self> {
+        if url.trim().is_empty() {
+            return Err(anyhow!("Webhook URL can't be empty"));
+        }
+        let events = match events {
+            Some(names) => names
+                .iter()
+                .map(|name| {
+                    WebhookEvent::from_config(name)
+                        .ok_or_else(|| anyhow!("Unknown webhook event: {name}"))
+                })
+                .collect::<Result<Vec<WebhookEvent>>>()?,
+            None => vec![
+                WebhookEvent::NewEpisodes,
+                WebhookEvent::Download,
+                WebhookEvent::SyncFailed,
+            ],
+        };
+        return Ok(Self {
+            url: url,
+            body: body.filter(|body| !body.trim().is_empty()),
+            events: events,
+        });
+    }
+
+    /// Fills in the placeholders of the URL and body for an event,
+    /// returning the URL, the body, and its content type. A body that
+    /// looks like JSON has its values escaped to fit inside JSON
+    /// strings.
+    fn request(&self, notice: &Notice) -> (String, String, &'static str) {
+        let url = notice.fill(&self.url, percent_encode);
+        return match &self.body {
+            Some(body) if body.trim_start().starts_with(['{', '[']) => {
+                (url, notice.fill(body, json_escape), "application/json")
+            }
+            Some(body) => (url, notice.fill(body, str::to_string), "text/plain; charset=utf-8"),
+            None => (url, notice.message.clone(), "text/plain; charset=utf-8"),
+        };
+    }
+}
+
+/// Details about an event, which fill in the {event}, {title},
+/// {message}, {podcast}, and {episode} placeholders of a webhook.
+/// `podcast` and `episode` are left empty when the event is about more
+/// than one of them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notice {
+    pub event: WebhookEvent,
+    pub title: String,
+    pub message: String,
+    pub podcast: String,
+    pub episode: String,
+}
+
+impl Notice {
+    /// Describes the episodes found by a sync, listing each one on a
+    /// line of the message. Returns None if there are none.
+    pub fn new_episodes(episodes: &[NewEpisode]) -> Option<Self> {
+        let first = episodes.first()?;
+        let title = match episodes.len() {
+            1 => "1 new episode".to_string(),
+            n => format!("{n} new episodes"),
+        };
+        let mut lines: Vec<String> = episodes
+            .iter()
+            .take(MAX_LISTED)
+            .map(|ep| format!("{}: {}", ep.pod_title, ep.title))
+            .collect();
+        if episodes.len() > MAX_LISTED {
+            lines.push(format!("...and {} more", episodes.len() - MAX_LISTED));
+        }
+        let one_podcast = episodes.iter().all(|ep| ep.pod_id == first.pod_id);
+        return Some(Self {
+            event: WebhookEvent::NewEpisodes,
+            title: title,
+            message: lines.join("\n"),
+            podcast: if one_podcast { first.pod_title.clone() } else { String::new() },
+            episode: if episodes.len() == 1 { first.title.clone() } else { String::new() },
+        });
+    }
+
+    /// Describes an episode that finished downloading.
+    pub fn download(pod_title: &str, ep_title: &str) -> Self {
+        return Self {
+            event: WebhookEvent::Download,
+            title: "Episode downloaded".to_string(),
+            message: format!("{pod_title}: {ep_title}"),
+            podcast: pod_title.to_string(),
+            episode: ep_title.to_string(),
+        };
+    }
+
+    /// Describes the podcasts that could not be synced, one on each line
+    /// of the message. Returns None if there are none.
+    pub fn sync_failed(pod_titles: &[String]) -> Option<Self> {
+        let first = pod_titles.first()?;
+        let title = match pod_titles.len() {
+            1 => "1 podcast could not be synced".to_string(),
+            n => format!("{n} podcasts could not be synced"),
+        };
+        return Some(Self {
+            event: WebhookEvent::SyncFailed,
+            title: title,
+            message: pod_titles.join("\n"),
+            podcast: if pod_titles.len() == 1 { first.clone() } else { String::new() },
+            episode: String::new(),
+        });
+    }
+
+    /// Replaces the placeholders in a template, escaping each value
+    /// with `escape`. This is done in one pass over the template, so
+    /// that a value which happens to contain a placeholder (e.g., an
+    /// episode titled "{podcast}") is left as it is. Anything else in
+    /// braces is kept too.
+    fn fill(&self, template: &str, escape: fn(&str) -> String) -> String {
+        let mut out = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            out.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let value = rest
+                .find('}')
+                .and_then(|end| self.placeholder(&rest[1..end]).map(|value| (end, value)));
+            match value {
+                Some((end, value)) => {
+                    out.push_str(&escape(value));
+                    rest = &rest[end + 1..];
+                }
+                None => {
+                    out.push('{');
+                    rest = &rest[1..];
+                }
+            }
+        }
+        out.push_str(rest);
+        return out;
+    }
+
+    /// Returns the value of a placeholder, given its name without the
+    /// braces.
+    fn placeholder(&self, name: &str) -> Option<&str> {
+        return match name {
+            "event" => Some(self.event.config_name()),
+            "title" => Some(&self.title),
+            "message" => Some(&self.message),
+            "podcast" => Some(&self.podcast),
+            "episode" => Some(&self.episode),
+            _ => None,
+        };
+    }
+}
+
+/// Enum used for communicating back to the main controller when a
+/// webhook request fails.
+#[derive(Debug)]
+pub enum WebhookMsg {
+    Failed(String),
+}
+
+/// Sends an event to every webhook that wants it, each on a separate
+/// thread, and lets the main controller know of any that fail.
+pub fn fire(webhooks: &[Webhook], notice: Notice, tx_to_main: Sender<Message>) {
+    for webhook in webhooks.iter().filter(|hook| hook.events.contains(&notice.event)) {
+        let webhook = webhook.clone();
+        let notice = notice.clone();
+        let tx_to_main = tx_to_main.clone();
+        thread::spawn(move || match send(&webhook, &notice) {
+            Ok(()) => log::info!("Sent {} event to a webhook", notice.event.config_name()),
+            Err(err) => {
+                let _ = tx_to_main.send(Message::Webhook(WebhookMsg::Failed(err.to_string())));
+            }
+        });
+    }
+}
+
+/// Sends an event to a single webhook as a POST request, and waits for
+/// the reply.
+pub fn send(webhook: &Webhook, notice: &Notice) -> Result<()> {
+    let (url, body, content_type) = webhook.request(notice);
+    let result = http::BLOCKING_CLIENT
+        .post(&url)
+        .header("Content-Type", content_type)
+        .body(body)
+        .send();
+    let host = url.split('/').nth(2).unwrap_or(&url).to_string();
+    return match result {
+        Ok(response) if response.status().is_success() => Ok(()),
+        Ok(response) => Err(anyhow!(
+            "Webhook {host} returned status {}",
+            response.status().as_u16()
+        )),
+        Err(_) => Err(anyhow!("Could not reach webhook {host}")),
+    };
+}
+
+/// Escapes text to be put inside a JSON string.
+fn json_escape(text: &str) -> String {
+    let quoted = serde_json::Value::String(text.to_string()).to_string();
+    return quoted[1..quoted.len() - 1].to_string();
+}
+
+
+// TESTS -----------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_episode(id: i64, pod_id: i64, pod_title: &str) -> NewEpisode {
+        return NewEpisode {
+            id: id,
+            pod_id: pod_id,
+            title: format!("Episode \"{id}\""),
+            pod_title: pod_title.to_string(),
+            pubdate: None,
+            selected: false,
+        };
+    }
+
+    #[test]
+    fn url_and_json_templates() {
+        let notice = Notice::new_episodes(&[
+            new_episode(1, 1, "Rerel Cast"),
+            new_episode(2, 1, "Rerel Cast"),
+        ])
+        .unwrap();
+        let webhook = Webhook::new(
+            "https://ntfy.sh/pods?title={title}&p={podcast}".to_string(),
+            Some(r#"{"text": "{message}", "episode": "{episode}"}"#.to_string()),
+            None,
+        )
+        .unwrap();
+        let (url, body, content_type) = webhook.request(&notice);
+        assert_eq!(url, "https://ntfy.sh/pods?title=2%20new%20episodes&p=Rerel%20Cast");
+        assert_eq!(content_type, "application/json");
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["text"], "Rerel Cast: Episode \"1\"\nRerel Cast: Episode \"2\"");
+        assert_eq!(json["episode"], "");
+    }
+
+    #[test]
+    fn plain_message() {
+        let notice = Notice::sync_failed(&["Rerel Cast".to_string()]).unwrap();
+        let webhook = Webhook::new(
+            "https://example.com/hook".to_string(),
+            None,
+            Some(vec!["sync_failed".to_string()]),
+        )
+        .unwrap();
+        let (_, body, content_type) = webhook.request(&notice);
+        assert_eq!(body, "Rerel Cast");
+        assert_eq!(content_type, "text/plain; charset=utf-8");
+        assert!(Notice::sync_failed(&[]).is_none());
+    }
+
+    #[test]
+    fn placeholders_in_values() {
+        let notice = Notice::download("{episode} Cast", "Ep {podcast}");
+        let webhook = Webhook::new(
+            "https://example.com/hook".to_string(),
+            Some("{ {podcast}: {episode} {unknown} {".to_string()),
+            None,
+        )
+        .unwrap();
+        let (_, body, _) = webhook.request(&notice);
+        assert_eq!(body, "{ {episode} Cast: Ep {podcast} {unknown} {");
+    }
+
+    #[test]
+    fn unknown_event() {
+        let err = Webhook::new(
+            "https://example.com/hook".to_string(),
+            None,
+            Some(vec!["played".to_string()]),
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "Unknown webhook event: played");
+    }
+}